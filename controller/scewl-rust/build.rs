@@ -1,52 +1,702 @@
 use std::env;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use std::error::Error;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
 
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+#[path = "config.rs"]
+mod config;
+
+use config::{Config, Keys, Memory, Radio, Watchdog, MAX_PEERS};
+
+/// The size of the flash page reserved for the provisioning record, as in `src/provision.rs`
+const PROVISION_PAGE: u32 = 1024;
+
+/// The size of the pair of flash pages reserved for the key-value store, as in `src/kv.rs`
+const STORE_PAGES: u32 = 2 * 1024;
+
+/// The size of a flash page, to which the staging region of an update is aligned
+const FLASH_PAGE: u32 = 1024;
+
+/// Every cipher suite feature, with the identifier which the firmware advertises to the SSS
+const SUITES: &[(&str, u8)] = &[
+    ("suite-trivial", 0),
+    ("suite-aes-cbc-hmac", 1),
+    ("suite-test", 0),
+];
+
+/// Locates the registration secret for the given id: `SCEWL_SECRET_PATH` names the file itself,
+/// otherwise `SCEWL_SECRET_DIR` names the directory holding `{id}_secret`, otherwise the secret is
+/// expected where the competition image places it, in `/sed`
+///
+/// Relative paths are taken relative to this crate, as the secret is included from `OUT_DIR`.
+fn secret_path(id: u16) -> PathBuf {
+    let path = env::var_os("SCEWL_SECRET_PATH").map_or_else(
+        || {
+            env::var_os("SCEWL_SECRET_DIR")
+                .map_or_else(|| PathBuf::from("/sed"), PathBuf::from)
+                .join(format!("{}_secret", id))
+        },
+        PathBuf::from,
+    );
+    Path::new(&env::var_os("CARGO_MANIFEST_DIR").unwrap()).join(path)
+}
+
+/// Reads the registration secret at the given path, checking that it is usable, so that a missing
+/// or truncated secret fails the build with a message saying why
+fn read_secret(path: &Path) -> Result<Vec<u8>, String> {
+    let secret = fs::read(path).map_err(|e| {
+        format!(
+            "cannot read the registration secret {}: {} (set SCEWL_SECRET_PATH or SCEWL_SECRET_DIR)",
+            path.display(),
+            e
+        )
+    })?;
+
+    if secret.len() != 64 {
+        return Err(format!(
+            "the registration secret {} is {} bytes, but must be exactly 64",
+            path.display(),
+            secret.len()
+        ));
+    }
+    if secret.iter().all(|&b| b == 0) {
+        return Err(format!(
+            "the registration secret {} is all zeros",
+            path.display()
+        ));
+    }
+
+    Ok(secret)
+}
+
+/// Splits the registration secret into two shares whose XOR is the secret, the mask and the
+/// masked secret, each as a `Secret` expression, so that the secret itself is never compiled in
+/// (see `src/masked.rs`)
+///
+/// The mask is derived with HKDF-SHA256 from the secret, salted with `SOURCE_DATE_EPOCH` should
+/// it be set, so that the same sources and secret always build the same image, as reproducible
+/// builds require; a new release stamp draws a new mask.
+fn split_secret(secret: &[u8]) -> (String, String) {
+    let salt = env::var("SOURCE_DATE_EPOCH").unwrap_or_default();
+    let mut mask = vec![0_u8; secret.len()];
+    Hkdf::<Sha256>::new(Some(salt.as_bytes()), secret)
+        .expand(b"scewl secret mask", &mut mask)
+        .expect("secrets are far shorter than HKDF can expand to");
+    let masked: Vec<u8> = secret.iter().zip(&mask).map(|(b, m)| b ^ m).collect();
+
+    (
+        format!("Secret({:?})", mask),
+        format!("Secret({:?})", masked),
+    )
+}
+
+/// Generates the identities embedded in a multi-identity image, one for each id of `SCEWL_IDS`
+/// (comma-separated), with each secret located as for `SCEWL_ID`
+///
+/// As for the secret, problems are reported through the compiler.
+fn identities() -> String {
+    let mut identities = Vec::new();
+    let mut masks = Vec::new();
+    let mut errors = Vec::new();
+
+    match env::var("SCEWL_IDS") {
+        Ok(ids) => {
+            for id in ids.split(',') {
+                let id = match id.trim().parse::<u16>() {
+                    Ok(id) => id,
+                    Err(_) => {
+                        errors.push(format!("SCEWL_IDS contains the invalid id {:?}", id));
+                        continue;
+                    }
+                };
+                let secret = secret_path(id);
+                println!("cargo:rerun-if-changed={}", secret.display());
+                match read_secret(&secret) {
+                    Ok(secret) => {
+                        let (mask, masked) = split_secret(&secret);
+                        identities.push(format!("({}, {})", id, masked));
+                        masks.push(mask);
+                    }
+                    Err(e) => errors.push(e),
+                }
+            }
+        }
+        Err(_) => {
+            errors.push("the multi-identity feature requires SCEWL_IDS, e.g. 10,11,12".into())
+        }
+    }
+
+    // the identity is selected by a single byte
+    if identities.len() > 256 {
+        errors.push(format!(
+            "SCEWL_IDS names {} ids, but at most 256 may be selected",
+            identities.len()
+        ));
+    }
+    if feature_enabled("provisioned") {
+        errors.push("the multi-identity and provisioned features are mutually exclusive".into());
+    }
+
+    format!(
+        r#"
+#[doc(hidden)]
+#[link_section = ".rodata.secret"]
+static IDENTITIES: [(u16, Secret); {}] = [{}];
+
+#[doc(hidden)]
+#[link_section = ".rodata.mask"]
+static IDENTITY_MASKS: [Secret; {}] = [{}];
+{}
+        "#,
+        identities.len(),
+        identities.join(", "),
+        masks.len(),
+        masks.join(", "),
+        errors
+            .iter()
+            .map(|e| format!("compile_error!({:?});", e))
+            .collect::<Vec<_>>()
+            .join("\n")
+    )
+}
+
+/// Checks the deployment configuration against the firmware being built, returning every problem
+fn check_config(config: &Config) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    for feature in &config.deployment.features {
+        if !feature_enabled(feature) {
+            errors.push(format!(
+                "the deployment requires the `{}` feature, which is not enabled",
+                feature
+            ));
+        }
+    }
+
+    let (keys, supported) = (&config.keys, &Keys::SUPPORTED);
+    if (keys.aes, keys.hmac, keys.seed) != (supported.aes, supported.hmac, supported.seed) {
+        errors.push(format!(
+            "the deployment uses {}/{}/{} byte AES/HMAC/seed keys, but only {}/{}/{} are supported",
+            keys.aes, keys.hmac, keys.seed, supported.aes, supported.hmac, supported.seed
+        ));
+    }
+
+    if config.deployment.peers == 0 {
+        errors.push("the deployment must have at least one peer".into());
+    }
+    if config.deployment.peers > MAX_PEERS {
+        errors.push(format!(
+            "the deployment has {} peers, but the controller tracks at most {}",
+            config.deployment.peers, MAX_PEERS
+        ));
+    }
+
+    let buckets = &config.deployment.buckets;
+    if buckets
+        .iter()
+        .any(|&bucket| bucket == 0 || bucket % 16 != 0 || bucket > usize::from(Radio::MAX_MTU))
+    {
+        errors.push(format!(
+            "the padding buckets must be multiples of 16 bytes, of at most {}",
+            Radio::MAX_MTU
+        ));
+    }
+    if buckets.windows(2).any(|pair| pair[0] >= pair[1]) {
+        errors.push("the padding buckets must be in ascending order".into());
+    }
+
+    if !(Radio::MIN_MTU..=Radio::MAX_MTU).contains(&config.radio.mtu) {
+        errors.push(format!(
+            "the radio MTU of {} bytes must be between {} and {}",
+            config.radio.mtu,
+            Radio::MIN_MTU,
+            Radio::MAX_MTU
+        ));
+    }
+
+    if config.radio.frame_timeout == 0 {
+        errors.push("the frame timeout must be at least one millisecond".into());
+    }
+
+    if config.sss.timeout == 0 {
+        errors.push("the SSS timeout must be at least one millisecond".into());
+    }
+
+    if !(1..=Watchdog::MAX_TIMEOUT).contains(&config.watchdog.timeout) {
+        errors.push(format!(
+            "the watchdog timeout must be between 1 and {} milliseconds",
+            Watchdog::MAX_TIMEOUT
+        ));
+    }
+
+    if config.sss.keepalive == Some(0) {
+        errors.push("the SSS keepalive period must be at least one second".into());
+    }
+
+    if config.session.sends == Some(0) || config.session.lifetime == Some(0) {
+        errors.push("a session limit must be at least one frame or one second".into());
+    }
+
+    let (memory, available) = (&config.memory, &Memory::AVAILABLE);
+    if memory.flash > available.flash || memory.ram > available.ram {
+        errors.push(format!(
+            "the memory layout has {} bytes of flash and {} of RAM, but the lm3s6965 has only {} and {}",
+            memory.flash, memory.ram, available.flash, available.ram
+        ));
+    }
+    if [memory.flash, memory.ram, memory.persist, memory.stack]
+        .iter()
+        .any(|size| size % 4 != 0)
+    {
+        errors.push("the sizes in the memory layout must be multiples of 4 bytes".into());
+    }
+    if feature_enabled("update") {
+        let update = &config.update;
+        if update.key().is_none() {
+            errors.push(
+                "the update feature requires update.key, an Ed25519 public key in hex".into(),
+            );
+        }
+        if update.source < 3 {
+            errors.push(format!(
+                "updates may only come from another SED, not from id {}; see update.source",
+                update.source
+            ));
+        }
+        let staging = staging(config);
+        if staging <= FLASH_PAGE || !staging.is_multiple_of(FLASH_PAGE) {
+            errors.push(format!(
+                "the staging region of {} bytes must be a multiple of the {} byte flash page, and \
+                 larger than one",
+                staging, FLASH_PAGE
+            ));
+        }
+        // an image as large as the staging region allows must fit below it once swapped in
+        let firmware = config.memory.flash.saturating_sub(reserved_flash(config));
+        if firmware + FLASH_PAGE < staging || !config.memory.flash.is_multiple_of(FLASH_PAGE) {
+            errors.push(format!(
+                "the staging region of {} bytes must leave at least as much of the flash for the \
+                 firmware, which must be a whole number of pages",
+                staging
+            ));
+        }
+    }
+    if memory.flash <= reserved_flash(config) {
+        errors.push("the reserved flash pages leave no flash for the firmware".into());
+    }
+    if memory.budget.is_some_and(|budget| budget > memory.flash) {
+        errors.push("the flash budget exceeds the flash itself".into());
+    }
+    if memory.persist >= memory.ram {
+        errors.push(format!(
+            "the persistent region of {} bytes leaves no RAM out of {} bytes",
+            memory.persist, memory.ram
+        ));
+    }
+    if memory.persist.saturating_add(memory.stack) >= memory.ram {
+        errors.push(format!(
+            "the persistent region and stack of {} and {} bytes leave no RAM out of {} bytes",
+            memory.persist, memory.stack, memory.ram
+        ));
+    }
+
+    errors
+}
+
+/// Generates the linker script describing the memory layout, which `cortex-m-rt` includes
+///
+/// The persistent region is carved from the top of RAM, above the stack, and holds the `.persist`
+/// section; the linker fails the build should its contents not fit. The large buffers (e.g. that
+/// of the controller) are gathered into the `.buffers` section, which like `.bss` is in RAM but is
+/// neither loaded nor zeroed; should the statics leave less RAM than `memory.stack` for the stack,
+/// the linker fails the build. Both sections are inserted after `.bss`, so that the heap still
+/// begins where `cortex-m-rt` expects. Should the SED be
+/// provisioned at runtime, the provisioning page is likewise carved from the top of flash, and
+/// with the `flash-store` feature, the pages of the key-value store below it, and with the `update`
+/// feature, the staging region below them; the linker then also fails the build should the
+/// firmware not fit the staging region, as it could not be updated to itself. Should
+/// the flash be budgeted, the linker also fails the build should the firmware exceed its budget.
+fn memory_x(config: &Config) -> String {
+    let memory = &config.memory;
+    let provisioned = feature_enabled("provisioned");
+    let flash_store = feature_enabled("flash-store");
+    let budget = memory.budget.map_or_else(String::new, |budget| {
+        format!(
+            r#"
+/* the flash occupied by the firmware, i.e. up to the end of the initial values of .data */
+ASSERT(__sidata + (__edata - __sdata) - ORIGIN(FLASH) <= {}, "
+ERROR(scewl): the firmware exceeds its flash budget of {} bytes; see memory.budget");
+"#,
+            budget, budget
+        )
+    });
+    let provision = if provisioned {
+        format!(
+            r#"
+/* the page holding the provisioning record, which the firmware reads at boot */
+__sprovision = {:#010X};
+"#,
+            Memory::FLASH_ORIGIN + memory.flash - PROVISION_PAGE
+        )
+    } else {
+        String::new()
+    };
+    let store = if flash_store {
+        format!(
+            r#"
+/* the pages holding the key-value store, which the firmware programs at runtime */
+__sstore = {:#010X};
+"#,
+            Memory::FLASH_ORIGIN + memory.flash
+                - PROVISION_PAGE * u32::from(provisioned)
+                - STORE_PAGES
+        )
+    } else {
+        String::new()
+    };
+    let staging = if feature_enabled("update") {
+        format!(
+            r#"
+/* the region in which an update is staged, of which the first page describes the update */
+__sstaging = {:#010X};
+ASSERT(__sidata + (__edata - __sdata) - ORIGIN(FLASH) <= {}, "
+ERROR(scewl): the firmware would not fit the staging region of an update; see update.staging");
+"#,
+            Memory::FLASH_ORIGIN + memory.flash - reserved_flash(config),
+            staging(config) - FLASH_PAGE
+        )
+    } else {
+        String::new()
+    };
+
+    format!(
+        r#"/* generated by build.rs from the deployment configuration */
+MEMORY
+{{
+  FLASH   : ORIGIN = {:#010X}, LENGTH = {}
+  RAM     : ORIGIN = {:#010X}, LENGTH = {}
+  PERSIST : ORIGIN = {:#010X}, LENGTH = {}
+}}
+{}{}{}
+SECTIONS
+{{
+  .buffers (NOLOAD) : ALIGN(4)
+  {{
+    __sbuffers = .;
+    *(.buffers .buffers.*);
+    . = ALIGN(4);
+    __ebuffers = .;
+  }} > RAM
+
+  .persist (NOLOAD) : ALIGN(4)
+  {{
+    __spersist = .;
+    KEEP(*(.persist .persist.*));
+    . = ALIGN(4);
+    __epersist = .;
+  }} > PERSIST
+}} INSERT AFTER .bss;
+
+ASSERT(__epersist - __spersist <= LENGTH(PERSIST), "
+ERROR(scewl): the persisted state does not fit in the persistent region; increase memory.persist");
+
+/* the stack grows down from the persistent region towards the statics, i.e. to the heap */
+ASSERT(ORIGIN(RAM) + LENGTH(RAM) - __sheap >= {}, "
+ERROR(scewl): the statics leave less RAM for the stack than memory.stack; shrink the buffers");
+{}"#,
+        Memory::FLASH_ORIGIN,
+        memory.flash - reserved_flash(config),
+        Memory::RAM_ORIGIN,
+        memory.ram - memory.persist,
+        Memory::RAM_ORIGIN + memory.ram - memory.persist,
+        memory.persist,
+        provision,
+        store,
+        staging,
+        memory.stack,
+        budget
+    )
+}
+
+/// The flash reserved at the top of flash for the pages which the firmware reads (and writes) at
+/// runtime, in bytes, as selected by the features of this build
+fn reserved_flash(config: &Config) -> u32 {
+    let mut reserved = 0;
+    if feature_enabled("provisioned") {
+        reserved += PROVISION_PAGE;
+    }
+    if feature_enabled("flash-store") {
+        reserved += STORE_PAGES;
+    }
+    if feature_enabled("update") {
+        reserved += staging(config);
+    }
+    reserved
+}
+
+/// The size of the region in which an update is staged, in bytes: `update.staging`, or by default
+/// the larger half of the pages left below the provisioning page and the key-value store, so that
+/// an image as large as the firmware's region fits the rest of the staging region
+fn staging(config: &Config) -> u32 {
+    config.update.staging.unwrap_or_else(|| {
+        let mut pages = config.memory.flash / FLASH_PAGE;
+        if feature_enabled("provisioned") {
+            pages = pages.saturating_sub(PROVISION_PAGE / FLASH_PAGE);
+        }
+        if feature_enabled("flash-store") {
+            pages = pages.saturating_sub(STORE_PAGES / FLASH_PAGE);
+        }
+        pages.div_ceil(2) * FLASH_PAGE
+    })
+}
+
+/// Determines whether the given feature of this crate is enabled for this build
+fn feature_enabled(feature: &str) -> bool {
+    env::var_os(format!(
+        "CARGO_FEATURE_{}",
+        feature.to_uppercase().replace('-', "_")
+    ))
+    .is_some()
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=config.rs");
     println!("cargo:rerun-if-env-changed=SCEWL_ID");
+    println!("cargo:rerun-if-env-changed=SCEWL_IDS");
+    println!("cargo:rerun-if-env-changed=SCEWL_SECRET_PATH");
+    println!("cargo:rerun-if-env-changed=SCEWL_SECRET_DIR");
+    println!("cargo:rerun-if-env-changed=SCEWL_CONFIG");
+
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
 
     let out_dir = env::var_os("OUT_DIR").unwrap();
+
     let values_path = Path::new(&out_dir).join("values.rs");
     let mut values = File::create(values_path)?;
 
+    values.write_all(
+        r#"
+/// A share of a registration secret (see `scewl::masked`), aligned to its size so that the memory
+/// protection unit may lock exactly the share as a region of its own
+#[doc(hidden)]
+#[repr(C, align(64))]
+struct Secret([u8; 64]);
+        "#
+        .as_ref(),
+    )?;
+
     let id = env::var("SCEWL_ID");
     match id {
         Ok(id) => {
             let id = id.parse::<u16>()?;
+            let secret = secret_path(id);
+            println!("cargo:rerun-if-changed={}", secret.display());
+            // reported through the compiler, so that the failure reads as any other build error
+            let (mask, masked) = match read_secret(&secret) {
+                Ok(secret) => split_secret(&secret),
+                Err(e) => (
+                    "Secret([0_u8; 64])".into(),
+                    format!("Secret([0_u8; 64]);\ncompile_error!({:?})", e),
+                ),
+            };
 
             values.write_all(
                 format!(
                     r#"
 #[doc(hidden)]
+#[allow(dead_code)] // provisioned SEDs read their id at boot
 const SCEWL_ID: u16 = {};
 
 #[doc(hidden)]
-const SECRET: [u8; 64] = *include_bytes!("/sed/{}_secret");
+#[allow(dead_code)] // only used by the secure handlers
+// the XOR of the secret and its mask, in a section of flash of its own, so that it is only read
+// in place, by reference
+#[link_section = ".rodata.secret"]
+static SECRET: Secret = {};
+
+#[doc(hidden)]
+#[allow(dead_code)] // only used by the secure handlers
+// the mask of the secret, in a section of flash apart from it
+#[link_section = ".rodata.mask"]
+static SECRET_MASK: Secret = {};
                     "#,
-                    id, id
+                    id, masked, mask
                 )
                 .as_ref(),
             )?;
         }
         Err(_) => {
-            println!("cargo:warning=Default values used for SCEWL_ID and SECRET");
+            // a provisioned or multi-identity SED takes both from elsewhere instead
+            if !feature_enabled("provisioned") && !feature_enabled("multi-identity") {
+                println!("cargo:warning=Default values used for SCEWL_ID and SECRET");
+            }
 
             values.write_all(
                 r#"
 #[doc(hidden)]
+#[allow(dead_code)] // provisioned SEDs read their id at boot
 const SCEWL_ID: u16 = 0;
 
 #[doc(hidden)]
-const SECRET: [u8; 64] = [0_u8; 64];
+#[allow(dead_code)] // only used by the secure handlers
+// the XOR of the secret and its mask, in a section of flash of its own, so that it is only read
+// in place, by reference
+#[link_section = ".rodata.secret"]
+static SECRET: Secret = Secret([0_u8; 64]);
+
+#[doc(hidden)]
+#[allow(dead_code)] // only used by the secure handlers
+// the mask of the secret, in a section of flash apart from it
+#[link_section = ".rodata.mask"]
+static SECRET_MASK: Secret = Secret([0_u8; 64]);
                     "#
                 .as_ref(),
             )?;
         }
     }
 
+    if feature_enabled("multi-identity") {
+        values.write_all(identities().as_ref())?;
+    }
+
+    // as with the secret, problems with the configuration are reported through the compiler
+    let (config, mut errors) =
+        match config::read(Path::new(&env::var_os("CARGO_MANIFEST_DIR").unwrap())) {
+            Ok(config) => {
+                let errors = check_config(&config);
+                (config, errors)
+            }
+            Err(e) => (Config::default(), vec![e]),
+        };
+
+    // an image with no suite, or several, could not say which to advertise to the SSS
+    let suites: Vec<_> = SUITES
+        .iter()
+        .filter(|(feature, _)| feature_enabled(feature))
+        .collect();
+    let suite = match suites.as_slice() {
+        // AES-CMAC in place of HMAC-SHA256 is a suite of its own, as the SSS must agree on it
+        [("suite-aes-cbc-hmac", _)] if feature_enabled("cmac") => 2,
+        [(_, id)] => *id,
+        _ => {
+            if feature_enabled("firmware") {
+                errors.push(format!(
+                    "exactly one cipher suite feature must be enabled, out of {}",
+                    SUITES
+                        .iter()
+                        .map(|(feature, _)| format!("`{}`", feature))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+            0
+        }
+    };
+    // the trivial handlers are given no keys, and the test handlers fixed ones, so neither has an
+    // epoch to record
+    let keyless = feature_enabled("suite-trivial") || feature_enabled("suite-test");
+    if feature_enabled("anti-rollback") && keyless {
+        errors.push("the anti-rollback feature requires the `suite-aes-cbc-hmac` suite".into());
+    }
+    // the trivial handlers have no verification segment
+    if feature_enabled("cmac") && feature_enabled("suite-trivial") {
+        errors.push("the cmac feature requires the `suite-aes-cbc-hmac` suite".into());
+    }
+    // the trivial handlers keep no counters, and the test handlers do not reserve them
+    if feature_enabled("persist-counters") && keyless {
+        errors.push("the persist-counters feature requires the `suite-aes-cbc-hmac` suite".into());
+    }
+    // takes precedence over the device crate's memory.x, as our search path is given first
+    if errors.is_empty() {
+        fs::write(Path::new(&out_dir).join("memory.x"), memory_x(&config))?;
+        println!("cargo:rustc-link-search={}", Path::new(&out_dir).display());
+    }
+
+    for e in errors {
+        values.write_all(format!("\ncompile_error!({:?});\n", e).as_ref())?;
+    }
+
+    values.write_all(
+        format!(
+            r#"
+#[doc(hidden)]
+const MAX_MESSAGE: usize = {};
+
+#[doc(hidden)]
+#[allow(dead_code)] // only advertised by the secure handlers
+const SUITE: u8 = {};
+
+#[doc(hidden)]
+#[allow(dead_code)] // not used by the test runner
+const MTU: u16 = {};
+
+#[doc(hidden)]
+#[allow(dead_code)] // not used by the test runner
+const FRAME_TIMEOUT: u64 = {};
+
+#[doc(hidden)]
+#[allow(dead_code)] // not used by the test runner
+const SSS_TIMEOUT: u64 = {};
+
+#[doc(hidden)]
+#[allow(dead_code)] // not used by the test runner
+const SSS_KEEPALIVE: Option<u64> = {:?};
+
+#[doc(hidden)]
+#[allow(dead_code)] // not used by the test runner
+const SESSION_SENDS: Option<u32> = {:?};
+
+#[doc(hidden)]
+#[allow(dead_code)] // not used by the test runner
+const SESSION_LIFETIME: Option<u64> = {:?};
+
+#[doc(hidden)]
+#[allow(dead_code)] // only used with the heartbeat feature
+const HEARTBEAT_TARGET: u16 = {};
+
+#[doc(hidden)]
+#[allow(dead_code)] // only used with the heartbeat feature
+const HEARTBEAT_PERIOD: u64 = {};
+
+#[doc(hidden)]
+#[allow(dead_code)] // only used with the watchdog feature
+const WATCHDOG_TIMEOUT: u64 = {};
+
+#[doc(hidden)]
+#[allow(dead_code)] // only used with the update feature
+const UPDATE_SOURCE: u16 = {};
+
+#[doc(hidden)]
+#[allow(dead_code)] // only used with the update feature
+const UPDATE_KEY: [u8; 32] = {:?};
+
+#[doc(hidden)]
+#[allow(dead_code, clippy::unreadable_literal)] // only used with the update feature
+const UPDATE_STAGING: u32 = {};
+            "#,
+            config.deployment.max_message,
+            suite,
+            config.radio.mtu,
+            config.radio.frame_timeout,
+            config.sss.timeout,
+            config.sss.keepalive,
+            config.session.sends,
+            config.session.lifetime,
+            config.heartbeat.target,
+            config.heartbeat.period,
+            config.watchdog.timeout,
+            config.update.source,
+            config.update.key().unwrap_or_default(),
+            staging(&config)
+        )
+        .as_ref(),
+    )?;
+
     Ok(())
 }
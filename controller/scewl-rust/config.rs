@@ -0,0 +1,293 @@
+//! The deployment configuration, shared by the build scripts of the firmware and of `scewl-core`,
+//! each of which includes this file as a module of its own
+
+// each build script reads only the settings which it generates code from
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+/// The deployment configuration, read from `SCEWL_CONFIG` or `deployment.toml` should either exist;
+/// see `deployment.example.toml` for every setting and its default
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Settings shared by every SED in the deployment
+    pub deployment: Deployment,
+    /// The sizes of the keys distributed by the SSS
+    pub keys: Keys,
+    /// Where and how often heartbeats are sent
+    pub heartbeat: Heartbeat,
+    /// Where firmware updates come from and how they are checked
+    pub update: Update,
+    /// How this SED uses the radio
+    pub radio: Radio,
+    /// How this SED talks to the SSS
+    pub sss: Sss,
+    /// How long the keys of each registration may be used
+    pub session: Session,
+    /// How long the run loop may go without kicking the watchdog
+    pub watchdog: Watchdog,
+    /// The memory layout of the SED, from which `memory.x` is generated
+    pub memory: Memory,
+}
+
+/// Settings shared by every SED in the deployment
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Deployment {
+    /// The number of SEDs in the deployment
+    pub peers: u16,
+    /// The largest message body which a CPU may send, in bytes
+    pub max_message: usize,
+    /// The sizes to which the ciphertext of each frame is padded, in bytes, ascending
+    pub buckets: Vec<usize>,
+    /// The features which the firmware must be built with
+    pub features: Vec<String>,
+}
+
+impl Default for Deployment {
+    fn default() -> Self {
+        Self {
+            peers: 16,
+            max_message: 0x4000,
+            buckets: Vec::new(),
+            features: Vec::new(),
+        }
+    }
+}
+
+/// The sizes of the keys distributed by the SSS, in bytes
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keys {
+    /// The AES key
+    pub aes: usize,
+    /// The HMAC key
+    pub hmac: usize,
+    /// The seed for each SED's random number generator
+    pub seed: usize,
+}
+
+impl Keys {
+    /// The sizes used by the secure handlers and `sss.py`, which are the only ones supported
+    pub const SUPPORTED: Keys = Keys {
+        aes: 16,
+        hmac: 64,
+        seed: 32,
+    };
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Keys::SUPPORTED
+    }
+}
+
+/// Where and how often heartbeats are sent, should the `heartbeat` feature be enabled
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Heartbeat {
+    /// The id of the FAA or SED which heartbeats are sent to
+    pub target: u16,
+    /// The number of seconds between heartbeats
+    pub period: u64,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        // heartbeats go to the FAA every minute unless configured otherwise
+        Self {
+            target: 2,
+            period: 60,
+        }
+    }
+}
+
+/// How long the run loop may go without kicking the watchdog, should the `watchdog` feature be
+/// enabled
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Watchdog {
+    /// The number of milliseconds after which the watchdog resets the controller
+    pub timeout: u64,
+}
+
+impl Watchdog {
+    /// The longest timeout, which the watchdog's counter still holds at the 12 MHz core clock
+    pub const MAX_TIMEOUT: u64 = 600_000;
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        // a registration reads from the SSS a few times, each of which may take its timeout
+        Self { timeout: 30_000 }
+    }
+}
+
+/// Where firmware updates come from and how they are checked, should the `update` feature be
+/// enabled
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Update {
+    /// The id of the SED which updates are accepted from
+    pub source: u16,
+    /// The Ed25519 public key which every update must be signed with, as 64 hex digits
+    pub key: Option<String>,
+    /// The size of the flash region in which an update is staged, in bytes, including the page
+    /// which describes the update; by default, the larger half of the pages left once the other
+    /// reservations of the build are made
+    pub staging: Option<u32>,
+}
+
+impl Update {
+    /// The public key, should it be 64 hex digits
+    pub fn key(&self) -> Option<[u8; 32]> {
+        let key = self.key.as_ref()?;
+        if key.len() != 64 {
+            return None;
+        }
+        let mut bytes = [0; 32];
+        for (byte, digits) in bytes.iter_mut().zip(key.as_bytes().chunks(2)) {
+            *byte = u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()?;
+        }
+        Some(bytes)
+    }
+}
+
+/// How this SED uses the radio
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Radio {
+    /// The largest frame which this SED accepts over the radio, in bytes, which it announces to
+    /// its peers
+    pub mtu: u16,
+    /// The number of milliseconds after which a frame from the CPU or the radio which has begun to
+    /// arrive, but stalled, is abandoned
+    pub frame_timeout: u64,
+}
+
+impl Radio {
+    /// The least MTU, which leaves room for a short message alongside the crypto overhead
+    pub const MIN_MTU: u16 = 0x100;
+    /// The greatest MTU, which is the size of the controller's data buffer
+    pub const MAX_MTU: u16 = 0x4100;
+}
+
+impl Default for Radio {
+    fn default() -> Self {
+        Self {
+            mtu: Radio::MAX_MTU,
+            // the largest frame takes under 1.5s at 115200 baud
+            frame_timeout: 3000,
+        }
+    }
+}
+
+/// How this SED talks to the SSS
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sss {
+    /// The number of milliseconds after which a read from the SSS gives up
+    pub timeout: u64,
+    /// The number of seconds between keepalives to the SSS while registered, if they are sent
+    pub keepalive: Option<u64>,
+}
+
+impl Default for Sss {
+    fn default() -> Self {
+        // the SSS answers at once, so anything slower than this has surely stalled
+        Self {
+            timeout: 5000,
+            keepalive: None,
+        }
+    }
+}
+
+/// How long the keys of each registration may be used, beyond which the SED rekeys; unbounded by
+/// default
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Session {
+    /// The most frames which may be sent under the keys of a registration, if there is a limit
+    pub sends: Option<u32>,
+    /// The longest time for which the keys of a registration may be used, in seconds, if there is
+    /// a limit
+    pub lifetime: Option<u64>,
+}
+
+/// The memory layout of the SED, from which `memory.x` is generated
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Memory {
+    /// The size of the flash, in bytes
+    pub flash: u32,
+    /// The size of the RAM, in bytes, including the persistent region
+    pub ram: u32,
+    /// The size of the persistent region at the top of RAM, in bytes, which is neither zeroed nor
+    /// initialised at boot and so survives resets
+    pub persist: u32,
+    /// The least RAM which must be left for the stack, in bytes, beyond which linking fails
+    pub stack: u32,
+    /// The most flash which the firmware may occupy, in bytes, beyond which linking fails
+    pub budget: Option<u32>,
+}
+
+impl Memory {
+    /// The origin of the flash on the lm3s6965
+    pub const FLASH_ORIGIN: u32 = 0x0000_0000;
+    /// The origin of the RAM on the lm3s6965
+    pub const RAM_ORIGIN: u32 = 0x2000_0000;
+    /// The memory of the lm3s6965, which no layout may exceed
+    pub const AVAILABLE: Memory = Memory {
+        flash: 256 * 1024,
+        ram: 64 * 1024,
+        persist: 0,
+        stack: 0,
+        budget: None,
+    };
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        // the whole part, with 1K persisted for the crash log and any future persisted state, and
+        // 16K left for the stack, which holds the handlers and their per-peer tables
+        Self {
+            persist: 1024,
+            stack: 16 * 1024,
+            ..Memory::AVAILABLE
+        }
+    }
+}
+
+/// The most peers a deployment may have; each is tracked by the per-peer tables of the handlers,
+/// which are sized for the deployment
+pub const MAX_PEERS: u16 = 256;
+
+/// Reads the deployment configuration, or the defaults should there be no configuration file
+///
+/// Relative paths are taken relative to the given directory, that of the firmware crate, as for
+/// the secret.
+pub fn read(base: &Path) -> Result<Config, String> {
+    let named = env::var_os("SCEWL_CONFIG");
+    let path = base.join(
+        named
+            .as_ref()
+            .map_or_else(|| PathBuf::from("deployment.toml"), PathBuf::from),
+    );
+    println!("cargo:rerun-if-changed={}", path.display());
+
+    match fs::read_to_string(&path) {
+        Ok(config) => toml::from_str(&config)
+            .map_err(|e| format!("invalid deployment configuration {}: {}", path.display(), e)),
+        Err(_) if named.is_none() => Ok(Config::default()),
+        Err(e) => Err(format!(
+            "cannot read the deployment configuration {}: {}",
+            path.display(),
+            e
+        )),
+    }
+}
@@ -0,0 +1,57 @@
+//! The host simulation of the throughput benchmark; see the `bench` module of the library for
+//! details and for the format of the report
+//!
+//! Run with `cargo bench -p scewl-core --target x86_64-unknown-linux-gnu --features bench,std`.
+
+use std::convert::TryInto;
+use std::time::Instant;
+
+use scewl::bench::{self, Clock, SIZES};
+use scewl::codec::SCEWL_MAX_DATA_SZ;
+use scewl::scratch::Pool;
+use scewl::{secure, trivial};
+
+/// The approximate number of bytes sent for each measurement
+const BUDGET: usize = 16 * 1024 * 1024;
+
+fn main() {
+    let mut data: Box<[u8; SCEWL_MAX_DATA_SZ]> = vec![0_u8; SCEWL_MAX_DATA_SZ]
+        .into_boxed_slice()
+        .try_into()
+        .unwrap();
+    let scratch = Pool::new();
+    let epoch = Instant::now();
+    let mut clock = Clock {
+        ticks_per_sec: 1_000_000_000,
+        #[allow(clippy::cast_possible_truncation)] // a benchmark will not run for 584 years
+        now: || epoch.elapsed().as_nanos() as u64,
+    };
+
+    for &size in &SIZES {
+        let iterations = bench::iterations(size, BUDGET);
+
+        let report = bench::measure(
+            "trivial",
+            &mut trivial::CryptoHandler,
+            &mut trivial::CryptoHandler,
+            &mut data,
+            &scratch,
+            size,
+            iterations,
+            &mut clock,
+        );
+        println!("{}", report);
+
+        let report = bench::measure(
+            "secure",
+            &mut secure::CryptoHandler::new([1; 32], [2; 16], [3; 64]),
+            &mut secure::CryptoHandler::new([4; 32], [2; 16], [3; 64]),
+            &mut data,
+            &scratch,
+            size,
+            iterations,
+            &mut clock,
+        );
+        println!("{}", report);
+    }
+}
@@ -0,0 +1,150 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[path = "../config.rs"]
+mod config;
+
+use config::{Config, MAX_PEERS};
+
+/// Runs git with the given arguments, returning its trimmed output should it succeed
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Determines whether the given feature of this crate is enabled for this build
+fn feature_enabled(feature: &str) -> bool {
+    env::var_os(format!(
+        "CARGO_FEATURE_{}",
+        feature.to_uppercase().replace('-', "_")
+    ))
+    .is_some()
+}
+
+/// The features of this crate which are enabled for this build, in alphabetical order
+///
+/// Features which cargo implies for optional dependencies are not reported.
+fn enabled_features() -> Result<Vec<String>, Box<dyn Error>> {
+    let manifest = fs::read_to_string(
+        Path::new(&env::var_os("CARGO_MANIFEST_DIR").unwrap()).join("Cargo.toml"),
+    )?;
+    let manifest: toml::Value = toml::from_str(&manifest)?;
+
+    Ok(manifest
+        .get("features")
+        .and_then(toml::Value::as_table)
+        .map(|features| {
+            features
+                .keys()
+                .filter(|feature| feature_enabled(feature))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=../config.rs");
+    println!("cargo:rerun-if-env-changed=SCEWL_CONFIG");
+
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    // the commit the image is built from, announced in the boot banner; rebuilt as HEAD moves
+    let build_hash = git(&["rev-parse", "--short=12", "HEAD"]).unwrap_or_else(|| "unknown".into());
+    for path in [
+        git(&["rev-parse", "--git-path", "HEAD"]),
+        git(&["rev-parse", "--symbolic-full-name", "HEAD"])
+            .and_then(|head| git(&["rev-parse", "--git-path", &head])),
+    ]
+    .iter()
+    .flatten()
+    .filter(|path| Path::new(path).exists())
+    {
+        println!("cargo:rerun-if-changed={}", path);
+    }
+
+    let out_dir = env::var_os("OUT_DIR").unwrap();
+
+    // honours SOURCE_DATE_EPOCH, so that reproducible builds remain reproducible
+    let timestamp = match env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch.parse::<u64>()?,
+        Err(_) => SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+    };
+    let features = enabled_features()?;
+
+    fs::write(
+        Path::new(&out_dir).join("build_info.rs"),
+        format!(
+            r#"
+/// The git commit which the firmware was built from, or `unknown` outside of a git checkout
+pub const COMMIT: &str = {:?};
+
+/// The time of the build, in seconds since the Unix epoch
+#[allow(clippy::unreadable_literal)] // generated
+pub const TIMESTAMP: u64 = {};
+
+/// The features of this crate which the firmware was built with
+pub const FEATURES: &[&str] = &{:?};
+            "#,
+            build_hash, timestamp, features
+        ),
+    )?;
+
+    // the configuration is that of the firmware, whose build reports any problem with it, so this
+    // one falls back on the defaults rather than failing twice
+    let firmware = Path::new(&env::var_os("CARGO_MANIFEST_DIR").unwrap()).join("..");
+    let config = config::read(&firmware).unwrap_or_else(|_| Config::default());
+
+    // the counter tables are hash-indexed, so their capacity must be a power of two
+    let capacity = config
+        .deployment
+        .peers
+        .clamp(1, MAX_PEERS)
+        .next_power_of_two();
+    fs::write(
+        Path::new(&out_dir).join("deployment.rs"),
+        format!(
+            r#"
+/// The number of SEDs in the deployment
+pub const PEERS: u16 = {};
+
+/// The number of peers for which the per-peer tables have room: [`PEERS`] rounded up to a power
+/// of two
+pub const PEER_CAPACITY: u16 = {};
+
+/// [`PEER_CAPACITY`] as a type, with which the per-peer tables are sized
+#[cfg(feature = "crypto")]
+pub type PeerCapacity = heapless::consts::U{};
+
+/// The size of the flash, in bytes
+#[allow(dead_code)] // only used by the firmware, to configure the memory protection unit
+#[allow(clippy::unreadable_literal)] // generated from the configuration
+pub const FLASH: u32 = {};
+
+/// The size of the RAM, in bytes, including the persistent region
+#[allow(dead_code, clippy::unreadable_literal)] // as above
+pub const RAM: u32 = {};
+
+/// The sizes to which the secure handlers pad the ciphertext of each frame, in bytes, ascending
+#[allow(dead_code)] // only used by the secure handlers
+pub const BUCKETS: &[usize] = &{:?};
+            "#,
+            config.deployment.peers,
+            capacity,
+            capacity,
+            config.memory.flash,
+            config.memory.ram,
+            config.deployment.buckets
+        ),
+    )?;
+
+    Ok(())
+}
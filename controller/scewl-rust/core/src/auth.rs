@@ -0,0 +1,121 @@
+//! Defines the trait for authentication via SSS. This is segmented out in its own module mostly for
+//! visual clarity.
+//!
+//! See [Handler](Handler) for details on how authentication handlers should be defined.
+
+use core::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::controller::{self, Controller};
+use crate::crypto::Handler as CryptoHandler;
+use crate::transport::Transport;
+
+/// Error type for (de)registration with the SSS
+///
+/// Callers conventionally import this as `AuthError`, alongside `AuthHandler`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// A message could not be exchanged with the SSS
+    Controller(controller::Error),
+    /// The response from the SSS could not be decoded
+    Malformed,
+    /// The SSS refused the request
+    Refused,
+    /// The SSS distributed keys of an epoch older than the SED has already accepted
+    Rollback,
+    /// The response was not tagged by the SSS of this deployment
+    Forged,
+}
+
+impl From<controller::Error> for Error {
+    fn from(err: controller::Error) -> Error {
+        Error::Controller(err)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Error::Controller(err) => write!(f, "exchange with the SSS failed: {err}"),
+            Error::Malformed => write!(f, "malformed response from the SSS"),
+            Error::Refused => write!(f, "refused by the SSS"),
+            Error::Rollback => write!(f, "keys of a superseded epoch from the SSS"),
+            Error::Forged => write!(f, "response not tagged by the SSS"),
+        }
+    }
+}
+
+/// Defines basic methods required for SSS registration and deregistration.
+///
+/// Your implementation should conform to the data and serialisations defined by your `sss.py`
+/// and instantiate an appropriate [crypto handler](crate::crypto::Handler) to handle
+/// cryptographic operations during runtime.
+///
+/// It is expected that your SSS will define values necessary for communications between SEDs.
+/// Ensure that your `sss.py` sufficiently provides any secrets necessary for communications to
+/// the controller so that they may be used by your crypto handler.
+///
+/// The controller owns its handler, and lends it mutably to each operation alongside itself, so a
+/// handler may keep state between operations, such as nonces, retry counters, or a session with
+/// the SSS. While an operation runs, the controller holds no handler; the handler is returned to
+/// it once the operation completes, whether or not it succeeded.
+pub trait Handler<C: CryptoHandler>: Sized {
+    /// Register with the SSS. If the registration is successful, it should return the associated
+    /// [crypto handler](crate::crypto::Handler). If it is not successful, it should return the
+    /// [error](Error) which caused it to fail.
+    fn sss_register<T: Transport>(
+        &mut self,
+        controller: &mut Controller<Self, C, T>,
+    ) -> Result<C, Error>;
+
+    /// Deregister with the SSS, returning the [error](Error) which caused it to fail, if any.
+    ///
+    /// The controller also deregisters ahead of registering afresh, should the CPU request to
+    /// register while registered, in which case it withholds the handler's notification of the
+    /// response from the CPU.
+    fn sss_deregister<T: Transport>(
+        &mut self,
+        controller: &mut Controller<Self, C, T>,
+    ) -> Result<(), Error>;
+
+    /// Obtain fresh keys from the SSS while registered, without deregistering. If the SSS
+    /// distributes them, it should return a new [crypto handler](crate::crypto::Handler), which
+    /// the controller swaps for its own in one step; otherwise, the controller keeps its own, and
+    /// the [error](Error) which caused it to fail should be returned. Unlike registration, the
+    /// CPU is not notified of the response by the handler. Handlers which cannot rekey (the
+    /// default) refuse to.
+    fn sss_rekey<T: Transport>(
+        &mut self,
+        controller: &mut Controller<Self, C, T>,
+    ) -> Result<C, Error> {
+        let _ = controller;
+        Err(Error::Refused)
+    }
+
+    /// Handle the keys of a new epoch or the list of revoked devices, pushed by the SSS while
+    /// registered, whose message of `len` bytes is at the start of the controller's [data
+    /// buffer](Controller::data), handing them to the controller's [crypto
+    /// handler](Controller::crypto) or to [`Controller::revoke`] and acknowledging them to the
+    /// SSS. Handlers which do not take pushes (the default) refuse them.
+    fn sss_push<T: Transport>(
+        &mut self,
+        controller: &mut Controller<Self, C, T>,
+        len: usize,
+    ) -> Result<(), Error> {
+        let _ = (controller, len);
+        Err(Error::Refused)
+    }
+
+    /// Tell the SSS that this SED is still alive, as the controller does periodically while
+    /// registered, returning whether the SSS still holds it registered. Should the SSS not, the
+    /// controller forgets its crypto handler and notifies the CPU of the deregistration; should
+    /// the SSS not answer, the [error](Error) which caused it to fail should be returned, and the
+    /// controller stays registered. Handlers which do not keep alive (the default) take the
+    /// registration to stand.
+    fn sss_keepalive<T: Transport>(
+        &mut self,
+        controller: &mut Controller<Self, C, T>,
+    ) -> Result<bool, Error> {
+        let _ = controller;
+        Ok(true)
+    }
+}
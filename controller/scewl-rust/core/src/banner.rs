@@ -0,0 +1,98 @@
+//! The banner announced once at boot, which identifies the image a controller is running
+//!
+//! The banner is logged over the debug channel and sent to the CPU as a status frame, from the
+//! controller's own id, whose body is [`MAGIC`] followed by the banner's text, e.g.:
+//!
+//! ```text
+//! BOOT controller 0.1.0 (3f2a9c1d07e4) secure id 10
+//! ```
+//!
+//! The build hash is the git commit which the image was built from, as determined by `build.rs`,
+//! or `unknown` should the image have been built outside of a git checkout. Should the previous
+//! boot have been ended by the watchdog, the text ends with [`WATCHDOG`], so
+//! that the CPU learns the controller wedged and was reset.
+
+use core::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::codec::Id;
+use crate::cursor::WriteCursor;
+
+/// The magic which prefixes the body of the banner's status frame
+pub const MAGIC: [u8; 5] = *b"BOOT ";
+
+/// The suffix of the banner's text after a reset by the watchdog
+pub const WATCHDOG: &str = " after watchdog reset";
+
+/// The identification of a controller image
+#[derive(Debug, Copy, Clone)]
+pub struct Banner<'a> {
+    /// The version of the firmware
+    pub version: &'a str,
+    /// The git commit which the firmware was built from
+    pub build: &'a str,
+    /// The handler family selected by the firmware, e.g. `secure`
+    pub handlers: &'a str,
+    /// The id of the controller
+    pub id: Id,
+    /// Whether the previous boot was ended by the watchdog
+    pub watchdog: bool,
+}
+
+impl Display for Banner<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "controller {} ({}) {} id {}{}",
+            self.version,
+            self.build,
+            self.handlers,
+            u16::from(self.id),
+            if self.watchdog { WATCHDOG } else { "" }
+        )
+    }
+}
+
+impl Banner<'_> {
+    /// Serialises the status frame body announcing this banner to the buffer, returning its length
+    ///
+    /// This writes the same text as the banner's [`Display`] implementation, but by hand, so that
+    /// images which do not log need not link `core::fmt` for the banner alone.
+    pub fn to_bytes(&self, buf: &mut [u8]) -> usize {
+        let mut digits = [0_u8; 5];
+        let parts: [&[u8]; 10] = [
+            &MAGIC,
+            b"controller ",
+            self.version.as_bytes(),
+            b" (",
+            self.build.as_bytes(),
+            b") ",
+            self.handlers.as_bytes(),
+            b" id ",
+            decimal(self.id.into(), &mut digits),
+            if self.watchdog {
+                WATCHDOG.as_bytes()
+            } else {
+                b""
+            },
+        ];
+
+        parts
+            .iter()
+            .fold(WriteCursor::new(buf), |cur, part| cur.write(part));
+        parts.iter().map(|part| part.len()).sum()
+    }
+}
+
+/// Writes the number in decimal to the end of the buffer, returning the digits written
+#[allow(clippy::cast_possible_truncation)] // a single decimal digit
+fn decimal(mut n: u16, buf: &mut [u8; 5]) -> &[u8] {
+    let mut start = buf.len();
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            return &buf[start..];
+        }
+    }
+}
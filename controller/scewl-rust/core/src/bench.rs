@@ -0,0 +1,134 @@
+//! The throughput benchmark shared by the host simulation and the on-target runner
+//!
+//! Each measurement pushes messages of one size from a sending crypto handler to a receiving one,
+//! exactly as two controllers would: the sender encrypts, then the receiver verifies and decrypts.
+//! The time for this whole exchange is the end-to-end latency of a message, excluding only the
+//! time spent on the UARTs.
+//!
+//! Results are emitted as [reports](Report), one JSON object per line, so that regressions can be
+//! tracked by simply collecting the output of each run:
+//!
+//!  - on the host: `cargo bench -p scewl-core --target x86_64-unknown-linux-gnu --features bench,std`
+//!  - on the target (under QEMU): `cargo run --release --features selftest,bench`
+//!
+//! As there is no common notion of time between the two, the caller supplies a monotonic clock
+//! along with its resolution; the host uses nanoseconds and the target uses core clock cycles.
+
+use core::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::codec::{Id, Message, SCEWL_MAX_DATA_SZ};
+use crate::crypto::Handler as CryptoHandler;
+use crate::scratch::Pool;
+
+/// The message sizes measured by the benchmark
+pub const SIZES: [usize; 3] = [64, 1024, 16 * 1024];
+
+/// The result of measuring one crypto handler at one message size
+#[derive(Debug, Copy, Clone)]
+pub struct Report {
+    /// The name of the handler family measured
+    pub handler: &'static str,
+    /// The size of the messages sent
+    pub size: usize,
+    /// The number of messages sent
+    pub iterations: u32,
+    /// The total time taken to send and receive every message, in clock ticks
+    pub ticks: u64,
+    /// The resolution of the clock which was used for this measurement
+    pub ticks_per_sec: u64,
+}
+
+impl Report {
+    /// The mean end-to-end latency of a single message, in clock ticks
+    pub fn latency(&self) -> u64 {
+        self.ticks / u64::from(self.iterations.max(1))
+    }
+
+    /// The sustained throughput, in bytes of cleartext per second
+    #[allow(clippy::cast_possible_truncation)] // bounded by the bytes sent times the clock rate
+    pub fn throughput(&self) -> u64 {
+        let bytes = self.size as u128 * u128::from(self.iterations);
+        (bytes * u128::from(self.ticks_per_sec) / u128::from(self.ticks.max(1))) as u64
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            r#"{{"handler":"{}","size":{},"iterations":{},"ticks":{},"ticks_per_sec":{},"latency_ticks":{},"throughput_bps":{}}}"#,
+            self.handler,
+            self.size,
+            self.iterations,
+            self.ticks,
+            self.ticks_per_sec,
+            self.latency(),
+            self.throughput()
+        )
+    }
+}
+
+/// A monotonic clock supplied by the caller, along with its resolution
+pub struct Clock<F: FnMut() -> u64> {
+    /// The number of ticks of this clock in one second
+    pub ticks_per_sec: u64,
+    /// Returns the current time in ticks
+    pub now: F,
+}
+
+/// The number of messages to send of the given size so that roughly `budget` bytes are sent
+#[allow(clippy::cast_possible_truncation)] // saturated to the range of a u32
+pub fn iterations(size: usize, budget: usize) -> u32 {
+    (budget / size).clamp(1, u32::MAX as usize) as u32
+}
+
+/// Measures the end-to-end time taken to send `iterations` messages of `size` bytes from `sender`
+/// to `receiver` as a direct message, using the supplied clock
+///
+/// This panics should the receiver fail to recover any message, as the measurement would be
+/// meaningless.
+#[allow(clippy::too_many_arguments)] // the buffers are lent separately, as by the controller
+pub fn measure<C: CryptoHandler>(
+    handler: &'static str,
+    sender: &mut C,
+    receiver: &mut C,
+    data: &mut [u8; SCEWL_MAX_DATA_SZ],
+    scratch: &Pool,
+    size: usize,
+    iterations: u32,
+    clock: &mut Clock<impl FnMut() -> u64>,
+) -> Report {
+    let start = (clock.now)();
+
+    for i in 0..iterations {
+        // vary the content so no two messages are alike
+        #[allow(clippy::cast_possible_truncation)] // only the low byte is wanted
+        data[sender.content_offset()..][..size].fill(i as u8);
+
+        let mut msg = Message {
+            tgt_id: Id::Other(11),
+            src_id: Id::Other(10),
+            len: size,
+        };
+        msg.len = sender.encrypt(data, msg, scratch);
+
+        assert_eq!(
+            receiver.verify(data, msg, scratch),
+            Ok(()),
+            "message failed verification"
+        );
+        assert_eq!(
+            receiver.decrypt(data, msg, scratch),
+            Ok(size),
+            "message failed decryption"
+        );
+    }
+
+    Report {
+        handler,
+        size,
+        iterations,
+        ticks: (clock.now)() - start,
+        ticks_per_sec: clock.ticks_per_sec,
+    }
+}
@@ -0,0 +1,72 @@
+//! The hardware on which the controller runs, as far as the controller itself reaches it
+//!
+//! The controller is hardware-free, so that the firmware, the host tools, and the tests may all
+//! share it; whatever else it needs of the lm3s6965 beyond its [transports](crate::transport) and
+//! [clocks](crate::time::Clock) is reached through the [`Board`] given to its
+//! [constructor](crate::controller::Controller::new). The firmware implements it over the
+//! hardware, and tests may use the [`Bare`] board, which has none.
+
+use crate::crashlog::Crash;
+
+/// The services of the hardware which the controller uses, as described in the [module
+/// documentation](self)
+pub trait Board {
+    /// Busy-waits for at least the given number of cycles, e.g. to jitter the timing of a
+    /// response
+    fn delay(&self, cycles: u32);
+
+    /// Stirs a sample into the runtime entropy pool, e.g. the header of each frame as it arrives
+    fn stir(&self, sample: u32);
+
+    /// Makes the registration secret readable, for the controller to (de)register or rekey
+    fn unlock_secret(&self);
+
+    /// Locks the registration secret away, as it is whenever the controller is registered
+    fn lock_secret(&self);
+
+    /// Kicks the watchdog, if any, on every pass of the [run loop](crate::controller::Controller::run)
+    fn kick(&self);
+
+    /// Waits for the next interrupt on each pass of the run loop, unless `ready` reports that there
+    /// is work waiting already; returning at once is always correct, if less frugal
+    fn idle(&self, ready: &dyn Fn() -> bool);
+
+    /// The code which the [integrity check](crate::integrity) rehashes
+    fn code(&self) -> &'static [u8];
+
+    /// The panic which ended the previous boot, if any (see the [crash log](crate::crashlog))
+    fn crash(&self) -> Option<&'static Crash>;
+
+    /// Resets the controller, e.g. to apply a committed [update](crate::update)
+    fn reset(&self) -> !;
+}
+
+/// A board without any hardware, for tests: delays and idling return at once, the secret is never
+/// locked away, there is no code to check and no crash to report, and a reset panics
+pub struct Bare;
+
+impl Board for Bare {
+    fn delay(&self, _cycles: u32) {}
+
+    fn stir(&self, _sample: u32) {}
+
+    fn unlock_secret(&self) {}
+
+    fn lock_secret(&self) {}
+
+    fn kick(&self) {}
+
+    fn idle(&self, _ready: &dyn Fn() -> bool) {}
+
+    fn code(&self) -> &'static [u8] {
+        &[]
+    }
+
+    fn crash(&self) -> Option<&'static Crash> {
+        None
+    }
+
+    fn reset(&self) -> ! {
+        panic!("the bare board cannot reset")
+    }
+}
@@ -0,0 +1,12 @@
+//! Metadata about the build, generated by `build.rs`, so that a deployed image can be traced back
+//! to the source and configuration it was built from
+//!
+//! The metadata is reported by the boot [banner](crate::banner) and by the version
+//! [diagnostic command](crate::diag). Note that the timestamp is that of the last time `build.rs`
+//! ran, which is whenever the commit, the configuration, or `SOURCE_DATE_EPOCH` changes; set
+//! `SOURCE_DATE_EPOCH` for reproducible builds.
+
+/// The version of the firmware
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+include!(concat!(env!("OUT_DIR"), "/build_info.rs"));
@@ -0,0 +1,338 @@
+//! The frame codec shared by the controller and any host-side tooling which needs to speak the
+//! controller's wire format
+//!
+//! This module contains only the (de)serialisation of the frames exchanged between the controller,
+//! the CPU, the SSS, and other SEDs; it is entirely free of hardware and cryptographic
+//! dependencies. As such, it may be compiled for the host with:
+//!
+//! ```text
+//! cargo build -p scewl-core --features codec --target x86_64-unknown-linux-gnu
+//! ```
+//!
+//! which allows test scripts and the SSS to construct and validate controller-compatible frames
+//! without re-implementing the format by hand. The firmware itself uses this exact module, so the
+//! two can never drift apart.
+//!
+//! Every multi-byte field of every frame (ids, lengths, counters, and so on) is little-endian and
+//! of a fixed width, as written by the [cursors](crate::cursor), so the format is the same on any
+//! host; the original specification left it to be the native order of its little-endian peers.
+//!
+//! The base frame types, as defined by the original specification, are available at this level.
+//! The additional frame segments used by the [secure handlers](secure) are available in their own
+//! module.
+
+use core::mem::size_of;
+
+use crate::ct;
+use crate::cursor::{ReadCursor, WriteCursor};
+
+pub mod secure;
+
+/// The max data size for the messages after processing by the `CryptoHandler`.
+///
+/// This value has been chosen as a reasonable max on the in- and out-bound messages over the radio.
+/// In an environment where we are guaranteed to have messages of even larger length sent reasonably
+/// quickly with no data corruption worries, this value should be updated or removed.
+pub const SCEWL_MAX_DATA_SZ: usize = 0x4000 + 0x100;
+
+/// The max size of the messages which the controller and its handlers compose themselves (e.g. a
+/// request to the SSS, or a notification to the CPU), rather than receive.
+///
+/// The largest is a registration proof bearing an ML-KEM-512 encapsulation key.
+pub const SCEWL_MAX_TX_SZ: usize = 0x400;
+
+/// A simple type renaming for SCEWL IDs.
+///
+/// This ensures that ids require explicit coercion to be up/downcasted to u16s. Explicit coercions
+/// are checked by clippy and are manually reviewed.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Default)]
+pub enum Id {
+    /// Id which denotes a broadcast; (de)serialised to/from `0_u16`
+    #[default]
+    Broadcast,
+    /// Id which denotes the SSS; (de)serialised to/from `1_u16`
+    SSS,
+    /// Id which denotes the FAA; (de)serialised to/from `2_u16`
+    FAA,
+    /// Id which denotes any other SED; (de)serialised to/from the u16 contained by this member
+    Other(u16),
+}
+
+impl From<u16> for Id {
+    fn from(num: u16) -> Id {
+        match num {
+            0 => Id::Broadcast,
+            1 => Id::SSS,
+            2 => Id::FAA,
+            id => Id::Other(id),
+        }
+    }
+}
+
+impl Id {
+    /// Whether this id is the given one, compared in [constant time](crate::ct) as serialised
+    pub fn ct_eq(self, other: Id) -> bool {
+        ct::eq_u16(self.into(), other.into())
+    }
+}
+
+/// Hashes an id as its serialised form, so that per-peer tables may be indexed by id
+#[cfg(feature = "crypto")]
+impl hash32::Hash for Id {
+    fn hash<H: hash32::Hasher>(&self, state: &mut H) {
+        u16::from(*self).hash(state);
+    }
+}
+
+impl From<Id> for u16 {
+    fn from(id: Id) -> u16 {
+        match id {
+            Id::Broadcast => 0,
+            Id::SSS => 1,
+            Id::FAA => 2,
+            Id::Other(id) => id,
+        }
+    }
+}
+
+/// The message header required by the SCEWL specification
+///
+/// Note that magicS and magicC are omitted from the struct declaration itself. This type is only
+/// serialised with [`to_bytes`](MessageHeader::to_bytes), where it is prefixed with 'S' and 'C' as
+/// denoted by the specification.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct MessageHeader {
+    /// ID of the SED to receive this message
+    pub tgt_id: Id,
+    /// ID of the SED sending this message
+    pub src_id: Id,
+    /// The length of this message
+    pub len: u16,
+}
+
+impl MessageHeader {
+    /// The header magic which prefixes every message
+    pub const MAGIC: [u8; 2] = *b"SC";
+
+    /// The version of the [extension](MessageHeader::extension) which this firmware writes, and
+    /// the only one which it reads
+    pub const VERSION: u8 = 1;
+
+    /// The size of the [extension](MessageHeader::extension) which follows the header where it is
+    /// checked
+    pub const EXTENSION_SIZE: usize = size_of::<u8>() + size_of::<u16>();
+
+    /// Converts the `MessageHeader` to a correct header according to the specification.
+    ///
+    /// While the struct itself does not have the magicS and magicC fields from the original
+    /// controller code, this method introduces these values explicitly to ensure that this header
+    /// possesses the header magic required.
+    pub fn to_bytes(self) -> [u8; MessageHeader::size()] {
+        let mut bytes = [0_u8; MessageHeader::size()];
+        WriteCursor::new(&mut bytes)
+            .write(&MessageHeader::MAGIC)
+            .write_u16(self.tgt_id.into())
+            .write_u16(self.src_id.into())
+            .write_u16(self.len);
+        bytes
+    }
+
+    /// Instantiates a `MessageHeader` based on the contents of a buffer according to the
+    /// specification.
+    ///
+    /// While this type does not have the magicS and magicC fields, they are assumed to be present
+    /// by this method; use [`verify_magic`](MessageHeader::verify_magic) if they have not already
+    /// been consumed by the reader.
+    pub fn from_bytes(buf: [u8; MessageHeader::size()]) -> Self {
+        let mut cur = ReadCursor::new(&buf);
+        // ignore the first two bytes
+        cur.advance(MessageHeader::MAGIC.len());
+
+        Self {
+            tgt_id: cur.read_u16().into(),
+            src_id: cur.read_u16().into(),
+            len: cur.read_u16(),
+        }
+    }
+
+    /// Determines whether the buffer begins with the header magic
+    pub fn verify_magic(buf: &[u8]) -> bool {
+        buf.starts_with(&MessageHeader::MAGIC)
+    }
+
+    /// The constant size of the header, including the magic
+    pub const fn size() -> usize {
+        size_of::<[u8; 2]>() + 3 * size_of::<u16>()
+    }
+
+    /// The CRC-16 of the serialised header, including the magic, followed by the given version of
+    /// the [extension](MessageHeader::extension)
+    pub fn crc(self, version: u8) -> u16 {
+        let mut bytes = [0_u8; MessageHeader::size() + size_of::<u8>()];
+        bytes[..MessageHeader::size()].copy_from_slice(&self.to_bytes());
+        bytes[MessageHeader::size()] = version;
+        crc16(&bytes)
+    }
+
+    /// The extension of the header, laid out as `version: u8 | crc: u16` with the
+    /// [current version](MessageHeader::VERSION) and the header's [CRC](MessageHeader::crc)
+    ///
+    /// Once the SSS enables the [extended header](secure::CAP_HEADER_CRC), it follows the header
+    /// of every frame between SEDs on the radio, so that a corrupted length is detected before the
+    /// receiver reads (or discards) that many bytes, and a frame of a later format is recognised
+    /// as such rather than misparsed.
+    pub fn extension(self) -> [u8; MessageHeader::EXTENSION_SIZE] {
+        let [lo, hi] = self.crc(MessageHeader::VERSION).to_le_bytes();
+        [MessageHeader::VERSION, lo, hi]
+    }
+}
+
+/// Computes the CRC-16 of the data, with the CCITT polynomial and initialised to all ones (i.e.
+/// CRC-16/CCITT-FALSE)
+///
+/// Only headers are checked, so a bitwise implementation suffices.
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF_u16, |crc, &byte| {
+        (0..8).fold(crc ^ u16::from(byte) << 8, |crc, _| {
+            (crc << 1) ^ (0x1021 & (crc >> 15).wrapping_neg())
+        })
+    })
+}
+
+/// Container for SSS messages, according to the specification for SSS messages between the CPU and
+/// the controller.
+///
+/// This type (and its members) are public as messages received from the CPU are in this format. The
+/// `AuthHandler` is permitted (and expected) to implement a different message format to communicate
+/// with the SSS and update the SSS accordingly.
+#[derive(Copy, Clone, Debug)]
+pub struct SSSMessage {
+    /// The ID of the device attempting to register
+    pub dev_id: Id,
+    /// The operation attempted; see [`SSSOp`] for details
+    pub op: SSSOp,
+}
+
+impl SSSMessage {
+    /// Serialise the SSS message to a byte array
+    pub fn to_bytes(self) -> [u8; SSSMessage::size()] {
+        let mut bytes = [0_u8; SSSMessage::size()];
+
+        WriteCursor::new(&mut bytes)
+            .write_u16(self.dev_id.into())
+            .write_i16(self.op.into());
+
+        bytes
+    }
+
+    /// Deserialise the SSS message from a byte array
+    pub fn from_bytes(data: &[u8]) -> SSSMessage {
+        let mut cur = ReadCursor::new(data);
+
+        SSSMessage {
+            dev_id: cur.read_u16().into(),
+            op: cur.read_i16().into(),
+        }
+    }
+
+    /// Acquires the (constant) size of the `SSSMessage` type, for convenience when this type is
+    /// (de)serialised over a stream.
+    pub const fn size() -> usize {
+        size_of::<u16>() + size_of::<i16>()
+    }
+}
+
+/// SSS operation as listed by the specification for SSS messages to/from the CPU
+#[allow(dead_code)]
+#[derive(Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Debug)]
+pub enum SSSOp {
+    /// Indicates that registration has already occurred for this device
+    Already = -1,
+    /// Indicates that registration was successful for this device, or that this device is attempting to register
+    Register,
+    /// Indicates that deregistration was successful for this device, or that this device is attempting to deregister
+    Deregister,
+    /// Indicates that the SSS is pushing the keys of a new epoch to this device, unsolicited
+    Rotate,
+    /// Indicates that this device has taken up the keys of the new epoch pushed by the SSS
+    Rotated,
+    /// Indicates that the SSS is pushing the list of devices since deregistered to this device,
+    /// unsolicited
+    Revoke,
+    /// Indicates that this device has taken up the list of revoked devices pushed by the SSS
+    Revoked,
+    /// Indicates that the SSS is challenging this device to prove its secret before it answers the
+    /// requested operation
+    Challenge,
+    /// Indicates that this device, while registered, is requesting fresh keys without
+    /// deregistering, or that they were distributed; the SSS may also prompt a device to do so
+    Rekey,
+    /// Indicates that this device, while registered, is checking that the SSS still holds it
+    /// registered, or that the SSS does; the SSS answers with [`SSSOp::Deregister`] should it not
+    Keepalive,
+    /// Indicates that this device, once its deregistration was answered, attests that it is
+    /// erasing its keys, or that the SSS acknowledged it and so deregistered the device
+    Erased,
+    /// An unknown SSS operation, used in the case of a corrupt SSS message (unhandled by the original implementation)
+    Unknown,
+}
+
+impl From<i16> for SSSOp {
+    fn from(op: i16) -> SSSOp {
+        match op {
+            -1 => SSSOp::Already,
+            0 => SSSOp::Register,
+            1 => SSSOp::Deregister,
+            2 => SSSOp::Rotate,
+            3 => SSSOp::Rotated,
+            4 => SSSOp::Revoke,
+            5 => SSSOp::Revoked,
+            6 => SSSOp::Challenge,
+            7 => SSSOp::Rekey,
+            8 => SSSOp::Keepalive,
+            9 => SSSOp::Erased,
+            _ => SSSOp::Unknown,
+        }
+    }
+}
+
+impl From<SSSOp> for i16 {
+    fn from(op: SSSOp) -> i16 {
+        op as i16
+    }
+}
+
+/// Message wrapper, for implementations _not_ consistent with the original specification -- for
+/// messages which are in- or out-bound on the SSS or radio
+#[derive(Debug, Copy, Clone)]
+pub struct Message {
+    /// ID of the SED to receive this message
+    pub tgt_id: Id,
+    /// ID of the SED that sent this message
+    pub src_id: Id,
+    /// The length of this message, which is explicitly sized as usize and NOT u16 to support larger
+    /// messages than those which go to and from the CPU
+    pub len: usize,
+}
+
+impl Message {
+    /// Converts the message to its canonical (and specification-compliant) form
+    #[allow(clippy::cast_possible_truncation)] // length is truncated appropriately
+    pub fn to_canonical(self) -> MessageHeader {
+        MessageHeader {
+            tgt_id: self.tgt_id,
+            src_id: self.src_id,
+            len: self.len as u16,
+        }
+    }
+}
+
+impl From<MessageHeader> for Message {
+    fn from(hdr: MessageHeader) -> Self {
+        Message {
+            tgt_id: hdr.tgt_id,
+            src_id: hdr.src_id,
+            len: hdr.len.into(),
+        }
+    }
+}
@@ -0,0 +1,859 @@
+//! Frame segments used by the secure handler family of the controller firmware
+//!
+//! These types describe the verification and content segments prepended by the secure crypto
+//! handler as well as the (de)registration messages exchanged with the secure SSS. Only the layout
+//! of these segments is defined here; the cryptographic operations which produce and consume them
+//! remain in the firmware's secure module.
+
+use core::convert::{TryFrom, TryInto};
+use core::fmt::{Debug, Formatter, Result as FmtResult};
+use core::mem::size_of;
+
+use crate::codec::{Id, SSSOp};
+use crate::cursor::{ReadCursor, WriteCursor};
+
+/// The identifier of the cipher suite of AES-128-CBC content with an HMAC-SHA256 verification
+/// segment
+pub const SUITE_CBC_HMAC: u8 = 1;
+
+/// The identifier of the cipher suite of AES-128-CBC content with an AES-CMAC verification segment,
+/// as implemented with the `cmac` feature
+pub const SUITE_CBC_CMAC: u8 = 2;
+
+/// The identifier of the cipher suite implemented by the secure handlers, which is advertised to
+/// the SSS on (de)registration
+pub const SUITE: u8 = if cfg!(feature = "cmac") {
+    SUITE_CBC_CMAC
+} else {
+    SUITE_CBC_HMAC
+};
+
+/// The capability of checking a versioned [extension](crate::codec::MessageHeader::extension),
+/// which carries a CRC, after the header of each frame between SEDs on the radio
+pub const CAP_HEADER_CRC: u8 = 1 << 0;
+
+/// The capability of protecting frames between SEDs with AES-128-GCM, whose tag replaces the HMAC
+/// and the hash of the content, rather than with AES-128-CBC and HMAC-SHA256
+pub const CAP_AES_GCM: u8 = 1 << 1;
+
+/// The capability of signing broadcasts with an Ed25519 key unique to each SED, whose seed and the
+/// public keys of the other SEDs of the deployment follow the secrets of the registration response
+/// (and the allowlist, should there be one) as [`SecureSSSSigningKeys`]
+pub const CAP_BROADCAST_SIGS: u8 = 1 << 2;
+
+/// The capability of agreeing a secret with each peer in an ephemeral X25519
+/// [handshake](crate::secure::Handshakes), with which the direct messages between the two are
+/// keyed rather than with the deployment's keys alone
+pub const CAP_EPHEMERAL_KEYS: u8 = 1 << 3;
+
+/// The capability of protecting frames between SEDs with AES-128-GCM-SIV, under which a repeated
+/// nonce does not break confidentiality; it takes precedence over [`CAP_AES_GCM`]
+pub const CAP_AES_GCM_SIV: u8 = 1 << 4;
+
+/// The capability of establishing the secrets of the registration response by ML-KEM-512, whose
+/// encapsulation key follows the registration request; experimental, and only advertised by
+/// firmware built with the `pq` feature (see [`pq`](crate::secure::pq))
+pub const CAP_PQ_KEM: u8 = 1 << 5;
+
+/// The capability of restricting the SEDs with which this SED may exchange frames to those of an
+/// allowlist, which follows the secrets of the registration response as [`SecureSSSAllowlist`]
+pub const CAP_ALLOWLIST: u8 = 1 << 6;
+
+/// The capabilities implemented by the firmware, which are advertised to the SSS on
+/// (de)registration alongside the suite
+///
+/// The SSS responds to a registration with those which the deployment enables, which are always
+/// among those advertised, as the SSS refuses SEDs lacking any capability the deployment enables.
+pub const CAPS: u8 = CAP_HEADER_CRC
+    | CAP_AES_GCM
+    | CAP_BROADCAST_SIGS
+    | CAP_EPHEMERAL_KEYS
+    | CAP_AES_GCM_SIV
+    | CAP_ALLOWLIST
+    | if cfg!(feature = "pq") { CAP_PQ_KEM } else { 0 };
+
+/// The verification segment of the message
+#[derive(Copy, Clone, Debug)]
+pub struct VerificationSegment {
+    /// The IV used for decryption of the message
+    pub iv: [u8; 16],
+    /// The counter value of the message
+    pub ctr: u64,
+    /// The epoch of the deployment's keys under which the message was sent
+    pub epoch: u32,
+    /// The HMAC to be verified upon receiving the message
+    pub hmac: [u8; 32],
+}
+
+impl VerificationSegment {
+    /// Serialises this segment to bytes
+    pub fn to_bytes(self) -> [u8; VerificationSegment::size()] {
+        let mut resp = [0_u8; VerificationSegment::size()];
+        WriteCursor::new(&mut resp)
+            .write(&self.iv)
+            .write_u64(self.ctr)
+            .write_u32(self.epoch)
+            .write(&self.hmac);
+        resp
+    }
+
+    /// Deserialises a segment from bytes
+    pub fn from_bytes(data: &[u8]) -> Self {
+        let mut cur = ReadCursor::new(data);
+
+        VerificationSegment {
+            iv: cur.read_literal(),
+            ctr: cur.read_u64(),
+            epoch: cur.read_u32(),
+            hmac: cur.read_literal(),
+        }
+    }
+
+    /// The constant size of the verification segment in its serialised form
+    pub const fn size() -> usize {
+        size_of::<[u8; 16]>() + size_of::<u64>() + size_of::<u32>() + size_of::<[u8; 32]>()
+    }
+}
+
+/// The verification segment of a message protected with AES-128-GCM, which precedes the
+/// encrypted content (with neither a content header nor padding)
+#[derive(Copy, Clone, Debug)]
+pub struct GcmSegment {
+    /// The nonce of the message, which is random
+    pub nonce: [u8; 12],
+    /// The counter value of the message
+    pub ctr: u64,
+    /// The epoch of the deployment's keys under which the message was sent
+    pub epoch: u32,
+    /// The tag, authenticating the transport header, the nonce, the counter, the epoch, and the
+    /// content
+    pub tag: [u8; 16],
+}
+
+impl GcmSegment {
+    /// Serialises this segment to bytes
+    pub fn to_bytes(self) -> [u8; GcmSegment::size()] {
+        let mut resp = [0_u8; GcmSegment::size()];
+        WriteCursor::new(&mut resp)
+            .write(&self.nonce)
+            .write_u64(self.ctr)
+            .write_u32(self.epoch)
+            .write(&self.tag);
+        resp
+    }
+
+    /// Deserialises a segment from bytes
+    pub fn from_bytes(data: &[u8]) -> Self {
+        let mut cur = ReadCursor::new(data);
+
+        GcmSegment {
+            nonce: cur.read_literal(),
+            ctr: cur.read_u64(),
+            epoch: cur.read_u32(),
+            tag: cur.read_literal(),
+        }
+    }
+
+    /// The constant size of the segment in its serialised form
+    pub const fn size() -> usize {
+        size_of::<[u8; 12]>() + size_of::<u64>() + size_of::<u32>() + size_of::<[u8; 16]>()
+    }
+}
+
+/// The header of the encrypted content section
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ContentHeader {
+    /// The length of the cleartext message
+    ///
+    /// This is serialised as a u32 (the width of a usize on the controller) so that hosts with a
+    /// wider usize produce identical headers.
+    pub len: usize,
+}
+
+impl ContentHeader {
+    /// Serialises this header to bytes (in cleartext)
+    #[allow(clippy::cast_possible_truncation)] // lengths never exceed SCEWL_MAX_DATA_SZ
+    pub fn to_bytes(self) -> [u8; ContentHeader::size()] {
+        let mut buf = [0_u8; ContentHeader::size()];
+        WriteCursor::new(&mut buf).write_u32(self.len as u32);
+        buf
+    }
+
+    /// Deserialises a header from bytes (in cleartext)
+    pub fn from_bytes(data: &[u8]) -> Self {
+        let mut cur = ReadCursor::new(data);
+        Self {
+            len: cur.read_u32() as usize,
+        }
+    }
+
+    /// The constant size of the content header
+    pub const fn size() -> usize {
+        size_of::<u32>()
+    }
+}
+
+/// A secure SSS message, to be sent at (de)registration to the SSS, which answers it with a
+/// [`SecureSSSChallenge`] (see [`challenge`](crate::secure::challenge))
+#[derive(Debug, Copy, Clone)]
+pub struct SecureSSSMessage {
+    /// The id of the device registering
+    pub dev_id: Id,
+    /// The operation being requested
+    pub op: SSSOp,
+    /// The identifier of the cipher suite which the SED was built with, e.g. [`SUITE`]
+    pub suite: u8,
+    /// The capabilities which the SED implements, e.g. [`CAPS`]
+    pub caps: u8,
+}
+
+impl SecureSSSMessage {
+    /// Serialises this message into the buffer, returning the length of the message
+    pub fn to_bytes(&self, buf: &mut [u8]) -> usize {
+        WriteCursor::new(buf)
+            .write_u16(self.dev_id.into())
+            .write_i16(self.op.into())
+            .write(&[self.suite, self.caps]);
+
+        SecureSSSMessage::size()
+    }
+
+    /// Deserialises a message from a buffer of bytes, which must hold it exactly
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        (buf.len() == SecureSSSMessage::size()).then(|| {
+            let mut cur = ReadCursor::new(buf);
+            let (dev_id, op) = (cur.read_u16().into(), cur.read_i16().into());
+            let [suite, caps] = cur.read_literal();
+
+            SecureSSSMessage {
+                dev_id,
+                op,
+                suite,
+                caps,
+            }
+        })
+    }
+
+    /// The constant size of a secure SSS message
+    pub const fn size() -> usize {
+        size_of::<u16>() + size_of::<i16>() + 2 * size_of::<u8>()
+    }
+}
+
+/// The challenge with which the SSS answers a [`SecureSSSMessage`]: a fresh nonce, which the SED
+/// proves its secret over with a [`SecureSSSProof`]
+#[derive(Debug, Copy, Clone)]
+pub struct SecureSSSChallenge {
+    /// The id of the device challenged
+    pub dev_id: Id,
+    /// The operation, which is always [`SSSOp::Challenge`]
+    pub op: SSSOp,
+    /// The nonce, drawn afresh for each request
+    pub nonce: [u8; 32],
+}
+
+impl SecureSSSChallenge {
+    /// Serialises this challenge to bytes
+    pub fn to_bytes(&self) -> [u8; SecureSSSChallenge::size()] {
+        let mut buf = [0_u8; SecureSSSChallenge::size()];
+        WriteCursor::new(&mut buf)
+            .write_u16(self.dev_id.into())
+            .write_i16(self.op.into())
+            .write(&self.nonce);
+        buf
+    }
+
+    /// Deserialises a challenge from a buffer of bytes, which must hold it exactly
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        (buf.len() == SecureSSSChallenge::size()).then(|| {
+            let mut cur = ReadCursor::new(buf);
+            SecureSSSChallenge {
+                dev_id: cur.read_u16().into(),
+                op: cur.read_i16().into(),
+                nonce: cur.read_literal(),
+            }
+        })
+    }
+
+    /// The constant size of a challenge
+    pub const fn size() -> usize {
+        size_of::<u16>() + size_of::<i16>() + size_of::<[u8; 32]>()
+    }
+}
+
+/// The keepalive which a registered SED sends to the SSS periodically, with a fresh nonce over
+/// which the SSS tags its [answer](SecureSSSAlive) (see [`keepalive`](crate::secure::keepalive))
+#[derive(Debug, Copy, Clone)]
+pub struct SecureSSSKeepalive {
+    /// The id of the device keeping alive
+    pub dev_id: Id,
+    /// The operation, which is always [`SSSOp::Keepalive`]
+    pub op: SSSOp,
+    /// The nonce, drawn afresh for each keepalive
+    pub nonce: [u8; 16],
+}
+
+impl SecureSSSKeepalive {
+    /// Serialises this keepalive to bytes
+    pub fn to_bytes(&self) -> [u8; SecureSSSKeepalive::size()] {
+        let mut buf = [0_u8; SecureSSSKeepalive::size()];
+        WriteCursor::new(&mut buf)
+            .write_u16(self.dev_id.into())
+            .write_i16(self.op.into())
+            .write(&self.nonce);
+        buf
+    }
+
+    /// Deserialises a keepalive from a buffer of bytes, which must hold it exactly
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        (buf.len() == SecureSSSKeepalive::size()).then(|| {
+            let mut cur = ReadCursor::new(buf);
+            SecureSSSKeepalive {
+                dev_id: cur.read_u16().into(),
+                op: cur.read_i16().into(),
+                nonce: cur.read_literal(),
+            }
+        })
+    }
+
+    /// The constant size of a keepalive
+    pub const fn size() -> usize {
+        size_of::<u16>() + size_of::<i16>() + size_of::<[u8; 16]>()
+    }
+}
+
+/// The answer of the SSS to a [`SecureSSSKeepalive`], telling whether it still holds the SED
+/// registered, tagged over the nonce of the keepalive
+#[derive(Debug, Copy, Clone)]
+pub struct SecureSSSAlive {
+    /// The id of the device which kept alive
+    pub dev_id: Id,
+    /// [`SSSOp::Keepalive`] should the SSS hold the device registered, or [`SSSOp::Deregister`]
+    pub op: SSSOp,
+    /// The HMAC authenticating the id and the operation over the nonce of the keepalive
+    pub tag: [u8; 32],
+}
+
+impl SecureSSSAlive {
+    /// Serialises this answer to bytes
+    pub fn to_bytes(&self) -> [u8; SecureSSSAlive::size()] {
+        let mut buf = [0_u8; SecureSSSAlive::size()];
+        WriteCursor::new(&mut buf)
+            .write_u16(self.dev_id.into())
+            .write_i16(self.op.into())
+            .write(&self.tag);
+        buf
+    }
+
+    /// Deserialises an answer from a buffer of bytes, which must hold it exactly
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        (buf.len() == SecureSSSAlive::size()).then(|| {
+            let mut cur = ReadCursor::new(buf);
+            SecureSSSAlive {
+                dev_id: cur.read_u16().into(),
+                op: cur.read_i16().into(),
+                tag: cur.read_literal(),
+            }
+        })
+    }
+
+    /// The constant size of an answer
+    pub const fn size() -> usize {
+        size_of::<u16>() + size_of::<i16>() + size_of::<[u8; 32]>()
+    }
+}
+
+/// The attestation of the SED, once the SSS answered its deregistration, that it held the keys of
+/// its registration and is erasing them, tagged under those keys over the nonce of the challenge
+/// of the deregistration (see [`erasure`](crate::secure::erasure))
+#[derive(Debug, Copy, Clone)]
+pub struct SecureSSSErasure {
+    /// The id of the device deregistering
+    pub dev_id: Id,
+    /// The operation, which is always [`SSSOp::Erased`]
+    pub op: SSSOp,
+    /// The epoch of the keys erased
+    pub epoch: u32,
+    /// The HMAC, under the keys erased, authenticating the id over the nonce of the challenge
+    pub tag: [u8; 32],
+}
+
+impl SecureSSSErasure {
+    /// Serialises this attestation to bytes
+    pub fn to_bytes(&self) -> [u8; SecureSSSErasure::size()] {
+        let mut buf = [0_u8; SecureSSSErasure::size()];
+        WriteCursor::new(&mut buf)
+            .write_u16(self.dev_id.into())
+            .write_i16(self.op.into())
+            .write_u32(self.epoch)
+            .write(&self.tag);
+        buf
+    }
+
+    /// Deserialises an attestation from a buffer of bytes, which must hold it exactly
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        (buf.len() == SecureSSSErasure::size()).then(|| {
+            let mut cur = ReadCursor::new(buf);
+            SecureSSSErasure {
+                dev_id: cur.read_u16().into(),
+                op: cur.read_i16().into(),
+                epoch: cur.read_u32(),
+                tag: cur.read_literal(),
+            }
+        })
+    }
+
+    /// The constant size of an attestation
+    pub const fn size() -> usize {
+        size_of::<u16>() + size_of::<i16>() + size_of::<u32>() + size_of::<[u8; 32]>()
+    }
+}
+
+/// The answer of the SED to a [`SecureSSSChallenge`], proving its secret over the nonce, after
+/// which the SSS answers the requested operation with a [`SecureSSSResponse`]
+#[derive(Debug, Copy, Clone)]
+pub struct SecureSSSProof {
+    /// The id of the device proving its secret
+    pub dev_id: Id,
+    /// The operation being requested, as in the [`SecureSSSMessage`] which was challenged
+    pub op: SSSOp,
+    /// The HMAC of the nonce and the id under the secret
+    pub proof: [u8; 32],
+}
+
+impl SecureSSSProof {
+    /// Serialises this proof into the buffer, returning the length of the proof
+    pub fn to_bytes(&self, buf: &mut [u8]) -> usize {
+        WriteCursor::new(buf)
+            .write_u16(self.dev_id.into())
+            .write_i16(self.op.into())
+            .write(&self.proof);
+
+        SecureSSSProof::size()
+    }
+
+    /// Deserialises a proof from a buffer of bytes, which must hold it exactly
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        (buf.len() == SecureSSSProof::size()).then(|| {
+            let mut cur = ReadCursor::new(buf);
+            SecureSSSProof {
+                dev_id: cur.read_u16().into(),
+                op: cur.read_i16().into(),
+                proof: cur.read_literal(),
+            }
+        })
+    }
+
+    /// The constant size of a proof
+    pub const fn size() -> usize {
+        size_of::<u16>() + size_of::<i16>() + size_of::<[u8; 32]>()
+    }
+}
+
+/// A secure SSS response, which is expected as the result of an SSS registration attempt
+#[derive(Debug, Copy, Clone)]
+pub struct SecureSSSResponse {
+    /// The id of the device registering
+    pub dev_id: Id,
+    /// The operation which took place
+    pub op: SSSOp,
+    /// The secrets passed as part of the response, if present
+    pub secrets: Option<SecureSSSSecrets>,
+}
+
+/// The secrets which are passed as a result of a successful registration
+///
+/// None of the secrets are included in the `Debug` output, so that they never reach the log.
+#[derive(Copy, Clone)]
+pub struct SecureSSSSecrets {
+    /// The global AES key
+    pub aes_key: [u8; 16],
+    /// The seed to be used for random data generation
+    pub seed: [u8; 32],
+    /// The global HMAC key
+    pub hmac_key: [u8; 64],
+    /// The epoch of the global keys, which the deployment raises whenever it replaces them
+    pub epoch: u32,
+    /// The capabilities which the deployment enables, e.g. [`CAP_HEADER_CRC`]
+    pub caps: u8,
+    /// The integrity check value of the AES key wrap, under which the SSS sends the keys and the
+    /// seed (see [`keywrap`](crate::secure::keywrap))
+    pub integrity: [u8; 8],
+}
+
+impl Debug for SecureSSSSecrets {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("SecureSSSSecrets").finish_non_exhaustive()
+    }
+}
+
+impl SecureSSSResponse {
+    /// Serialises this response to a buffer of bytes, returning the number of bytes written
+    ///
+    /// Responses without secrets are serialised in their short form, exactly as the SSS would.
+    pub fn to_bytes(&self, buf: &mut [u8]) -> usize {
+        let cur = WriteCursor::new(buf)
+            .write_u16(self.dev_id.into())
+            .write_i16(self.op.into());
+
+        match self.secrets {
+            Some(secrets) => {
+                cur.write(&secrets.aes_key)
+                    .write(&secrets.seed)
+                    .write(&secrets.hmac_key)
+                    .write_u32(secrets.epoch)
+                    .write(&[secrets.caps])
+                    .write(&secrets.integrity);
+                SecureSSSResponse::size()
+            }
+            None => size_of::<u16>() + size_of::<i16>(),
+        }
+    }
+
+    /// Deserialise a response from a buffer of bytes
+    ///
+    /// Any bytes after the secrets, such as the [signing keys](SecureSSSSigningKeys), are left
+    /// for the caller to deserialise.
+    pub fn from_bytes(buf: &[u8]) -> Option<SecureSSSResponse> {
+        (buf.len() >= size_of::<u16>() + size_of::<i16>()).then(|| {
+            let mut cur = ReadCursor::new(buf);
+
+            SecureSSSResponse {
+                dev_id: cur.read_u16().into(),
+                op: cur.read_i16().into(),
+                secrets: (buf.len() >= SecureSSSResponse::size()).then(|| SecureSSSSecrets {
+                    aes_key: cur.read_literal(),
+                    seed: cur.read_literal(),
+                    hmac_key: cur.read_literal(),
+                    epoch: cur.read_u32(),
+                    caps: cur.read_literal::<1>()[0],
+                    integrity: cur.read_literal(),
+                }),
+            }
+        })
+    }
+
+    /// The constant size of a secure SSS response
+    pub const fn size() -> usize {
+        size_of::<u16>()
+            + size_of::<i16>()
+            + size_of::<[u8; 16]>()
+            + size_of::<[u8; 32]>()
+            + size_of::<[u8; 64]>()
+            + size_of::<u32>()
+            + size_of::<u8>()
+            + size_of::<[u8; 8]>()
+    }
+}
+
+/// The keys of a new epoch, which the SSS pushes to each registered SED unsolicited should the
+/// deployment replace its keys (see [`rotation`](crate::secure::rotation))
+///
+/// The keys are wrapped under a key derived from the SED's registration secret, and are omitted
+/// from the `Debug` output regardless.
+#[derive(Copy, Clone)]
+pub struct SecureSSSRotation {
+    /// The id of the device to which the keys are pushed
+    pub dev_id: Id,
+    /// The operation, which is always [`SSSOp::Rotate`]
+    pub op: SSSOp,
+    /// The epoch of the keys, which is higher than that of any keys pushed before
+    pub epoch: u32,
+    /// The AES key then the HMAC key of the epoch, wrapped
+    pub wrapped: [u8; 16 + 64],
+    /// The HMAC authenticating the id, the epoch, and the wrapped keys
+    pub tag: [u8; 32],
+}
+
+impl Debug for SecureSSSRotation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("SecureSSSRotation")
+            .field("dev_id", &self.dev_id)
+            .field("op", &self.op)
+            .field("epoch", &self.epoch)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SecureSSSRotation {
+    /// Serialises this push to bytes
+    pub fn to_bytes(&self) -> [u8; SecureSSSRotation::size()] {
+        let mut buf = [0_u8; SecureSSSRotation::size()];
+        WriteCursor::new(&mut buf)
+            .write_u16(self.dev_id.into())
+            .write_i16(self.op.into())
+            .write_u32(self.epoch)
+            .write(&self.wrapped)
+            .write(&self.tag);
+        buf
+    }
+
+    /// Deserialises a push from a buffer of bytes, which must hold it exactly
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        (buf.len() == SecureSSSRotation::size()).then(|| {
+            let mut cur = ReadCursor::new(buf);
+            SecureSSSRotation {
+                dev_id: cur.read_u16().into(),
+                op: cur.read_i16().into(),
+                epoch: cur.read_u32(),
+                wrapped: cur.read_literal(),
+                tag: cur.read_literal(),
+            }
+        })
+    }
+
+    /// The constant size of a push
+    pub const fn size() -> usize {
+        size_of::<u16>()
+            + size_of::<i16>()
+            + size_of::<u32>()
+            + size_of::<[u8; 16 + 64]>()
+            + size_of::<[u8; 32]>()
+    }
+}
+
+/// The devices since deregistered, which the SSS pushes to each registered SED unsolicited
+/// whenever the list changes (see [`revocation`](crate::secure::revocation)): a serial, then the
+/// number of devices and the id of each, then the tag authenticating them
+#[derive(Copy, Clone)]
+pub struct SecureSSSRevocation<'a> {
+    /// The id of the device to which the list is pushed
+    pub dev_id: Id,
+    /// The operation, which is always [`SSSOp::Revoke`]
+    pub op: SSSOp,
+    /// The serial of the list, which is higher than that of any list pushed before
+    pub serial: u32,
+    /// The serialised ids of the revoked devices, of [`Self::ID_SIZE`] bytes each
+    pub ids: &'a [u8],
+    /// The HMAC authenticating the id, the serial, and the revoked ids
+    pub tag: [u8; 32],
+}
+
+impl Debug for SecureSSSRevocation<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("SecureSSSRevocation")
+            .field("dev_id", &self.dev_id)
+            .field("op", &self.op)
+            .field("serial", &self.serial)
+            .field("revoked", &self.count())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> SecureSSSRevocation<'a> {
+    /// The size of the id of each revoked device
+    pub const ID_SIZE: usize = size_of::<u16>();
+
+    /// The number of revoked devices
+    pub fn count(&self) -> usize {
+        self.ids.len() / Self::ID_SIZE
+    }
+
+    /// The ids of the revoked devices
+    pub fn revoked(&self) -> impl ExactSizeIterator<Item = Id> + 'a {
+        self.ids
+            .chunks_exact(Self::ID_SIZE)
+            .map(|id| ReadCursor::new(id).read_u16().into())
+    }
+
+    /// Serialises this push to the buffer, returning the number of bytes written
+    ///
+    /// # Panics
+    ///
+    /// Panics should there be more than 255 revoked devices, or the buffer be too short.
+    pub fn to_bytes(&self, buf: &mut [u8]) -> usize {
+        let count = u8::try_from(self.count()).expect("at most 255 revoked devices");
+        WriteCursor::new(buf)
+            .write_u16(self.dev_id.into())
+            .write_i16(self.op.into())
+            .write_u32(self.serial)
+            .write(&[count])
+            .write(self.ids)
+            .write(&self.tag);
+
+        Self::size(self.count())
+    }
+
+    /// Deserialises a push from a buffer of bytes, which must hold it exactly, borrowing the ids
+    /// from that buffer
+    pub fn from_bytes(buf: &'a [u8]) -> Option<Self> {
+        let fixed = Self::size(0);
+        if buf.len() < fixed {
+            return None;
+        }
+        let (head, rest) = buf.split_at(fixed - size_of::<[u8; 32]>());
+        let mut cur = ReadCursor::new(head);
+        let (dev_id, op, serial) = (cur.read_u16().into(), cur.read_i16().into(), cur.read_u32());
+        let count = usize::from(cur.read_literal::<1>()[0]);
+
+        (rest.len() == count * Self::ID_SIZE + size_of::<[u8; 32]>()).then(|| {
+            let (ids, tag) = rest.split_at(count * Self::ID_SIZE);
+            SecureSSSRevocation {
+                dev_id,
+                op,
+                serial,
+                ids,
+                tag: tag.try_into().unwrap(),
+            }
+        })
+    }
+
+    /// The size of a push revoking the given number of devices
+    pub const fn size(count: usize) -> usize {
+        size_of::<u16>()
+            + size_of::<i16>()
+            + size_of::<u32>()
+            + size_of::<u8>()
+            + count * Self::ID_SIZE
+            + size_of::<[u8; 32]>()
+    }
+
+    /// The greatest size of a push, i.e. revoking 255 devices
+    pub const fn max_size() -> usize {
+        Self::size(255)
+    }
+}
+
+/// The SEDs with which this SED may exchange frames, which follow the secrets of the registration
+/// response should the deployment enable [`CAP_ALLOWLIST`]: the number of SEDs, then the id of each
+#[derive(Copy, Clone)]
+pub struct SecureSSSAllowlist<'a> {
+    /// The serialised ids of the allowed SEDs, of [`Self::ID_SIZE`] bytes each
+    ids: &'a [u8],
+}
+
+impl Debug for SecureSSSAllowlist<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_list().entries(self.allowed()).finish()
+    }
+}
+
+impl<'a> SecureSSSAllowlist<'a> {
+    /// The size of the id of each allowed SED
+    pub const ID_SIZE: usize = size_of::<u16>();
+
+    /// Serialises the ids of the allowed SEDs to the buffer, returning the number of bytes written
+    ///
+    /// # Panics
+    ///
+    /// Panics should there be more than 255 allowed SEDs, or the buffer be too short.
+    pub fn to_bytes(allowed: impl ExactSizeIterator<Item = Id>, buf: &mut [u8]) -> usize {
+        let count = u8::try_from(allowed.len()).expect("at most 255 allowed SEDs");
+        let mut cur = WriteCursor::new(buf).write(&[count]);
+        for id in allowed {
+            cur = cur.write_u16(id.into());
+        }
+
+        Self::size(usize::from(count))
+    }
+
+    /// Deserialises the allowlist from the start of a buffer of bytes, borrowing the ids from that
+    /// buffer
+    ///
+    /// Any bytes after the allowlist, such as the [signing keys](SecureSSSSigningKeys), are left
+    /// for the caller to deserialise from [`Self::serialised_len`] on.
+    pub fn from_bytes(buf: &'a [u8]) -> Option<Self> {
+        let (count, rest) = buf.split_first()?;
+        let ids = rest.get(..usize::from(*count) * Self::ID_SIZE)?;
+
+        Some(SecureSSSAllowlist { ids })
+    }
+
+    /// The number of allowed SEDs
+    pub fn count(&self) -> usize {
+        self.ids.len() / Self::ID_SIZE
+    }
+
+    /// The number of bytes which the allowlist takes up serialised
+    pub fn serialised_len(&self) -> usize {
+        Self::size(self.count())
+    }
+
+    /// The ids of the allowed SEDs
+    pub fn allowed(&self) -> impl ExactSizeIterator<Item = Id> + 'a {
+        self.ids
+            .chunks_exact(Self::ID_SIZE)
+            .map(|id| ReadCursor::new(id).read_u16().into())
+    }
+
+    /// The size of an allowlist of the given number of SEDs
+    pub const fn size(count: usize) -> usize {
+        size_of::<u8>() + count * Self::ID_SIZE
+    }
+
+    /// The greatest size of an allowlist, i.e. of 255 SEDs
+    pub const fn max_size() -> usize {
+        Self::size(255)
+    }
+}
+
+/// The signing keys which follow the secrets of the registration response (and the allowlist,
+/// should there be one) should the deployment enable [`CAP_BROADCAST_SIGS`]: the seed of this SED's Ed25519 key, then the number of other SEDs
+/// and the id and public key of each
+///
+/// The seed is omitted from the `Debug` output, so that it never reaches the log.
+#[derive(Copy, Clone)]
+pub struct SecureSSSSigningKeys<'a> {
+    /// The seed of this SED's signing key
+    pub seed: [u8; 32],
+    /// The serialised ids and public keys of the other SEDs, of [`Self::PEER_SIZE`] bytes each
+    peers: &'a [u8],
+}
+
+impl Debug for SecureSSSSigningKeys<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("SecureSSSSigningKeys")
+            .field("peers", &(self.peers.len() / Self::PEER_SIZE))
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> SecureSSSSigningKeys<'a> {
+    /// The size of the id and public key of each other SED
+    pub const PEER_SIZE: usize = size_of::<u16>() + size_of::<[u8; 32]>();
+
+    /// Serialises the seed and the ids and public keys of the other SEDs to the buffer, returning
+    /// the number of bytes written
+    ///
+    /// # Panics
+    ///
+    /// Panics should there be more than 255 other SEDs, or the buffer be too short.
+    pub fn to_bytes(
+        seed: &[u8; 32],
+        peers: impl ExactSizeIterator<Item = (Id, [u8; 32])>,
+        buf: &mut [u8],
+    ) -> usize {
+        let count = u8::try_from(peers.len()).expect("at most 255 other SEDs");
+        let mut cur = WriteCursor::new(buf).write(seed).write(&[count]);
+        for (id, public) in peers {
+            cur = cur.write_u16(id.into()).write(&public);
+        }
+
+        size_of::<[u8; 32]>() + size_of::<u8>() + usize::from(count) * Self::PEER_SIZE
+    }
+
+    /// Deserialises the signing keys from a buffer of bytes, which must hold them exactly,
+    /// borrowing the public keys from that buffer
+    pub fn from_bytes(buf: &'a [u8]) -> Option<Self> {
+        let (seed, rest) = (buf.len() > 32).then(|| buf.split_at(32))?;
+        let (count, peers) = rest.split_first()?;
+
+        (peers.len() == usize::from(*count) * Self::PEER_SIZE).then(|| SecureSSSSigningKeys {
+            seed: seed.try_into().unwrap(),
+            peers,
+        })
+    }
+
+    /// The ids and public keys of the other SEDs
+    pub fn peers(&self) -> impl Iterator<Item = (Id, [u8; 32])> + 'a {
+        self.peers.chunks_exact(Self::PEER_SIZE).map(|peer| {
+            let mut cur = ReadCursor::new(peer);
+            (cur.read_u16().into(), cur.read_literal())
+        })
+    }
+
+    /// The greatest size of the signing keys, i.e. with 255 other SEDs
+    pub const fn max_size() -> usize {
+        size_of::<[u8; 32]>() + size_of::<u8>() + 255 * Self::PEER_SIZE
+    }
+}
@@ -0,0 +1,133 @@
+//! The kinds of content which SEDs send each other over the radio
+//!
+//! The content of every message between SEDs begins with its [`Kind`], laid out as
+//! `kind: u8 | body`. The sending controller writes the kind just ahead of the body, and the crypto
+//! handler then encrypts and authenticates it with the rest of the content, so that it may be
+//! neither read nor altered on the radio. The receiving controller decides what to do with a
+//! message by its kind alone: [data](Kind::Data), which is everything the CPU of the sender sends,
+//! is forwarded to the CPU verbatim whatever its body begins with, while the other kinds are
+//! messages between the controllers themselves, which are consumed rather than forwarded. A
+//! message of a kind which this firmware does not know is dropped as malformed.
+//!
+//! Some of those kinds are sent on behalf of the CPU, such as the frames of a firmware
+//! [update](Kind::Update). The CPU cannot mark the kind of what it sends in its body, as then any
+//! data could claim to be of any kind, so it asks its own controller to send them in an
+//! [`Envelope`]: a message addressed to the controller itself, laid out as
+//! `MAGIC | kind: u8 | peer: u16 | body`, which is never sent over the radio. The controller sends
+//! the body to the peer as content of the given kind, and hands such content which it receives
+//! from a peer but does not consume to its CPU in an envelope of its own, as from itself, so that
+//! the CPU tells it from data by its source.
+
+use core::mem::size_of;
+
+use crate::codec::Id;
+use crate::cursor::{ReadCursor, WriteCursor};
+
+/// The magic which prefixes every envelope
+pub const MAGIC: [u8; 4] = *b"\0KND";
+
+/// The kind of the content of a message between SEDs
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Kind {
+    /// Data from the CPU of the sender, to be forwarded to the CPU of the receiver
+    Data = 0,
+    /// The announcement of the sender's MTU (see the [MTU module](crate::mtu))
+    Hello = 1,
+    /// A step of the sender's ephemeral key agreement with the receiver (see
+    /// `secure::handshake`), which only the crypto tier sends
+    Handshake = 2,
+    /// A frame of a firmware update, or the acknowledgement of one (see the [update
+    /// module](crate::update)), which the CPU sends in an envelope
+    Update = 3,
+    /// A fragment of data too large for the data buffer (see the [fragment
+    /// module](crate::fragment))
+    Fragment = 4,
+    /// A message to one of the receiver's ports (see the [port module](crate::port)), which the
+    /// CPU sends in an envelope
+    Port = 5,
+}
+
+impl Kind {
+    /// The size of the kind in its serialised form
+    pub const SIZE: usize = 1;
+
+    /// Deserialises a kind from its byte, if it is known
+    pub fn from_byte(b: u8) -> Option<Self> {
+        match b {
+            0 => Some(Kind::Data),
+            1 => Some(Kind::Hello),
+            2 => Some(Kind::Handshake),
+            3 => Some(Kind::Update),
+            4 => Some(Kind::Fragment),
+            5 => Some(Kind::Port),
+            _ => None,
+        }
+    }
+
+    /// Splits the content of a message into its kind and its body, should it begin with a known
+    /// kind
+    pub fn split(content: &[u8]) -> Option<(Self, &[u8])> {
+        let (&b, body) = content.split_first()?;
+        Some((Kind::from_byte(b)?, body))
+    }
+
+    /// Whether the CPU may have its controller send content of this kind in an envelope; the
+    /// controllers' own kinds are only ever sent by the controllers
+    pub fn enveloped(self) -> bool {
+        matches!(self, Kind::Update | Kind::Port)
+    }
+
+    /// Whether content of this kind may be broadcast, rather than only sent to a single peer
+    pub fn broadcasts(self) -> bool {
+        !matches!(self, Kind::Handshake | Kind::Update)
+    }
+}
+
+impl From<Kind> for u8 {
+    fn from(kind: Kind) -> u8 {
+        kind as u8
+    }
+}
+
+/// A message between the CPU and its own controller which carries content of some kind other than
+/// data, to or from a peer
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Envelope<'a> {
+    /// The kind of the content
+    pub kind: Kind,
+    /// The peer to which the content is sent, or from which it was received
+    pub peer: Id,
+    /// The body of the content
+    pub body: &'a [u8],
+}
+
+impl<'a> Envelope<'a> {
+    /// The size of the header which precedes the body of an envelope
+    pub const HEADER_SIZE: usize = MAGIC.len() + Kind::SIZE + size_of::<u16>();
+
+    /// Deserialises an envelope from the body of a message, should it be one of a known kind
+    pub fn from_bytes(buf: &'a [u8]) -> Option<Self> {
+        if buf.len() < Self::HEADER_SIZE || !buf.starts_with(&MAGIC) {
+            return None;
+        }
+
+        let mut cur = ReadCursor::new(&buf[MAGIC.len()..]);
+        let kind = Kind::from_byte(cur.read_literal::<1>()[0])?;
+        let peer = cur.read_u16().into();
+        Some(Envelope {
+            kind,
+            peer,
+            body: &buf[Self::HEADER_SIZE..],
+        })
+    }
+
+    /// Writes the header of an envelope of the given kind to or from the given peer to the buffer,
+    /// returning its length; the body is to follow it
+    pub fn write_header(buf: &mut [u8], kind: Kind, peer: Id) -> usize {
+        WriteCursor::new(buf)
+            .write(&MAGIC)
+            .write(&[kind.into()])
+            .write_u16(peer.into());
+        Self::HEADER_SIZE
+    }
+}
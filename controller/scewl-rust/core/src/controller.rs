@@ -0,0 +1,2426 @@
+//! A near-direct port of the original C implementation, available in the [insecure implementation](https://github.com/mitre-cyber-academy/2021-ectf-insecure-example/blob/master/controller/controller.c).
+//!
+//! As described in the [crate documentation](..), this implementation is modularised to support
+//! changes necessary to secure communications between controller and other SEDs while allowing
+//! debugging of the underlying (and very simple) communications channel implemented in the firmware's interface module.
+//! To support this, the [controller struct](Controller) utilises a type generic to support
+//! arbitrary [authentication handlers](crate::auth::Handler) and [crypto handlers](crate::crypto::Handler),
+//! as well as arbitrary [transports](crate::transport::Transport) for its serial lines, which are
+//! the UARTs in the firmware.
+//!
+//! To allow for custom authentication handlers, the [constructor for Controller](Controller::new)
+//! requires an [`AuthHandler`](crate::auth::Handler), as does its [builder](Controller::builder). The controller calls on this `AuthHandler` during
+//! the [`handle_registration`](Controller::handle_registration) to register and deregister. See [`handle_registration`](Controller::handle_registration)
+//! for details. The registration secret is [locked](crate::board::Board::lock_secret) away by the
+//! board whenever the controller is registered (as the firmware does with its `mpu` feature), and
+//! only unlocked to (de)register.
+//!
+//! As the result of a successful registration, the `AuthHandler` instantiates a [`CryptoHandler`](crate::crypto::Handler).
+//! This `CryptoHandler` will be used during [`handle_scewl_recv`](Controller::handle_scewl_recv),
+//! [`handle_scewl_send`](Controller::handle_scewl_send), [`handle_brdcst_recv`](Controller::handle_brdcst_recv),
+//! and [`handle_brdcst_send`](Controller::handle_brdcst_send) to encrypt and decrypt messages to
+//! and from the radio, not including FAA messages. This enforces that FAA and non-radio messages
+//! are not encrypted, regardless of the `CryptoHandler` used. The content which is encrypted
+//! begins with its [kind](crate::content), which the controller writes ahead of the body of each
+//! message it sends with [`compose`](Controller::compose), and by which alone it decides what to do
+//! with each message it receives. The CPU sends and receives kinds other than data in
+//! [envelopes](crate::content::Envelope) to and from its own controller.
+//!
+//! Each of these handlers [wipes](Controller::wipe) the message from the data buffer once it has
+//! been forwarded (or dropped), so that no plaintext outlives its message.
+//!
+//! Messages are received into the data buffer, and forwarded from it. The messages which the
+//! controller and its handlers compose themselves, such as requests to the SSS and notifications
+//! to the CPU, are instead written to a separate [transmit buffer](Controller::tx) and sent with
+//! [`send_tx`](Controller::send_tx), so that composing one never clobbers a message received
+//! (e.g. the SSS's response to a registration, which is still being read as the CPU is notified).
+//!
+//! When the CPU requests to deregister, and the SSS has acknowledged the controller's attestation
+//! that it is erasing its keys, the `CryptoHandler` is erased and dropped, and both in- and
+//! out-bound SCEWL messages are refused (as they can no longer be sent or verified). We use this
+//! mechanism of type-assured security throughout.
+
+use core::cmp::min;
+use core::convert::TryFrom;
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use core::result::Result as CoreResult;
+use core::time::Duration;
+
+pub use crate::codec::{
+    Id, Message, MessageHeader, SSSMessage, SSSOp, SCEWL_MAX_DATA_SZ, SCEWL_MAX_TX_SZ,
+};
+
+use crate::auth::{Error as AuthError, Handler as AuthHandler};
+use crate::banner::Banner;
+use crate::board::Board;
+use crate::content::{Envelope, Kind};
+use crate::crypto::{Error as CryptoError, Handler as CryptoHandler};
+#[cfg(feature = "drop-notices")]
+use crate::diag::Notice;
+use crate::diag::{self, Command, Drops, Reason, Traffic};
+#[cfg(feature = "fragmentation")]
+use crate::fragment::{self, Fragment, Reassembly};
+#[cfg(feature = "heartbeat")]
+use crate::heartbeat::{Beat, Heartbeat};
+#[cfg(feature = "hexdump")]
+use crate::hexdump::{self, Stage};
+#[cfg(feature = "integrity")]
+use crate::integrity::Monitor;
+use crate::mtu::{self, Hello, Peers};
+#[cfg(feature = "prioritized")]
+use crate::outbound::{self, Class, Outbound};
+use crate::policy::Policy;
+use crate::port;
+#[cfg(feature = "ports")]
+use crate::port::{Control, Ports};
+use crate::scratch::Pool;
+#[cfg(feature = "scripted")]
+use crate::script::{self, Directive, Fault, Faults, Report, State};
+use crate::secure::{Handshake, Handshakes};
+use crate::session::{Lifetime, Session};
+use crate::time::{Clock, Deadline};
+use crate::transport::Error::SomeData;
+use crate::transport::{self, Links, Timed, Transport, INTF};
+#[cfg(feature = "update")]
+use crate::update::{Frame, Status, Updater};
+use crate::{debug, info, invariant, trace, warn};
+use crate::{legacy, level};
+
+/// Error type for the controller's message handling, which preserves the cause of the failure
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// An unexpected error occurred, such as a violated invariant or an injected fault
+    Unknown,
+    /// The operation requires a crypto handler, but the controller is not registered
+    Unregistered,
+    /// The underlying interface failed
+    Interface(transport::Error),
+    /// The message was dropped for the given reason, including failed crypto operations
+    Dropped(Reason),
+    /// A message did not arrive in full before its timeout passed, be it the [SSS's
+    /// answer](Controller::with_sss_timeout) or a [stalled frame](Controller::with_frame_timeout)
+    TimedOut,
+}
+
+impl From<transport::Error> for Error {
+    fn from(err: transport::Error) -> Error {
+        Error::Interface(err)
+    }
+}
+
+impl From<CryptoError> for Error {
+    fn from(reason: CryptoError) -> Error {
+        Error::Dropped(reason)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Error::Unknown => write!(f, "unknown error"),
+            Error::Unregistered => write!(f, "not registered"),
+            Error::Interface(err) => write!(f, "interface: {err}"),
+            Error::Dropped(reason) => write!(f, "dropped: {reason}"),
+            Error::TimedOut => write!(f, "timed out"),
+        }
+    }
+}
+
+/// Simple result type for methods in [`Controller`](Controller), either returning the expected type
+/// or an error
+pub type Result<T> = CoreResult<T, Error>;
+
+/// The buffer from which the content of a message is sent
+#[derive(Copy, Clone)]
+enum Body {
+    /// The data buffer, from the given offset
+    Data(usize),
+    /// The transmit buffer, from its start
+    Tx,
+}
+
+/// Main type for the controller, which is a near-direct port of the original C implementation
+///
+/// The implementation of this type differs in that it can use arbitrary implementations of the
+/// security-enhanceable parts of the SCEWL specification rather easily simply by using different
+/// type which implements the respective handler types. For further information on how to
+/// appropriately develop these handlers, see the documentation for [authentication handlers](crate::auth::Handler)
+/// and [crypto handlers](crate::crypto::Handler).
+pub struct Controller<'a, A, C, T>
+where
+    A: AuthHandler<C>,
+    C: CryptoHandler,
+    T: Transport,
+{
+    /// The ID of this controller; in the original C implementation, this was a macro called
+    /// `SCEWL_ID`
+    id: Id,
+    /// The transports to the CPU, the SSS, and the radio, which more idiomatically manage reading
+    /// and writing to the serial UART peripherals
+    links: Links<T>,
+    /// The data buffer into which messages are received, and from which they are forwarded
+    data: &'a mut [u8; SCEWL_MAX_DATA_SZ],
+    /// The buffer in which the controller and its handlers compose the messages they send
+    tx: &'a mut [u8; SCEWL_MAX_TX_SZ],
+    /// The pool of scratch buffers lent to the handlers (see the [scratch module](crate::scratch))
+    scratch: &'a Pool,
+    /// The hardware on which the controller runs (see the [board module](crate::board))
+    board: &'a dyn Board,
+    /// The authentication handler, which will be used to instantiate the crypto handler for the
+    /// controller post-authentication; absent only while it is [lent](Controller::with_auth) to an
+    /// operation
+    auth: Option<A>,
+    /// The crypto handler, which, when present, will encrypt and decrypt messages over the radio
+    crypto: Option<C>,
+    /// The number of messages dropped for each reason, reported by the [diagnostic command](diag)
+    drops: Drops,
+    /// The frames and bytes received and sent over each link, reported by the
+    /// [diagnostic command](diag)
+    traffic: Traffic,
+    /// The limits which every frame must meet to be read (see the [policy module](crate::policy))
+    policy: Policy<'a>,
+    /// The clock by which reads from the SSS are timed, and how long they may take, if they are
+    sss_timeout: Option<(&'a dyn Clock, Duration)>,
+    /// The clock by which frames read in the run loop are timed, and how long they may take, if
+    /// they are
+    frame_timeout: Option<(&'a dyn Clock, Duration)>,
+    /// The use made of the keys of the current registration, should their lifetime be bounded
+    session: Option<Session<'a>>,
+    /// The clock by which keepalives are sent to the SSS while registered, and their period, if
+    /// they are
+    keepalive: Option<(&'a dyn Clock, Duration)>,
+    /// When the next keepalive is due, while registered
+    next_keepalive: Option<Deadline<'a>>,
+    /// Whether answers from the SSS are withheld from the CPU, as they are while an existing
+    /// registration is torn down for the CPU to register afresh
+    withhold_sss: bool,
+    /// Whether an extension, carrying a version and a CRC, follows the header of each frame between
+    /// SEDs on the radio, as enabled by the SSS at registration
+    header_crc: bool,
+    /// The handshakes with the peers, should the SSS have enabled ephemeral keys at registration
+    handshakes: Option<Handshakes>,
+    /// The largest frame which this controller accepts over the radio, announced to its peers
+    mtu: u16,
+    /// The MTUs announced by the peers (see the [MTU module](crate::mtu))
+    peers: Peers,
+    /// Whether the frame last read from the radio was accepted as a [legacy frame](crate::legacy),
+    /// which is only ever so with the `mixed-mode` feature
+    legacy: bool,
+    /// The message whose fragments are being reassembled, should the controller have been given a
+    /// buffer to reassemble them in (see the [fragment module](crate::fragment))
+    #[cfg(feature = "fragmentation")]
+    reassembly: Option<Reassembly<'a>>,
+    /// The number of the next message which this controller sends in fragments
+    #[cfg(feature = "fragmentation")]
+    next_fragmented: u16,
+    /// The frames awaiting transmission over the radio, should the controller have been given a
+    /// buffer to queue them in (see the [outbound module](crate::outbound))
+    #[cfg(feature = "prioritized")]
+    outbound: Option<Outbound<'a>>,
+    /// The ports which the CPU has opened (see the [port module](crate::port))
+    #[cfg(feature = "ports")]
+    ports: Ports,
+    /// The schedule on which heartbeats are sent, if they are
+    #[cfg(feature = "heartbeat")]
+    heartbeat: Option<Heartbeat<'a>>,
+    /// The check of the code against the digest sealed at registration
+    #[cfg(feature = "integrity")]
+    integrity: Monitor,
+    /// The receiver of firmware updates, if they are accepted
+    #[cfg(feature = "update")]
+    update: Option<&'a mut dyn Updater>,
+    /// The faults armed by the test script
+    #[cfg(feature = "scripted")]
+    faults: Faults,
+}
+
+impl<'a, A: AuthHandler<C>, C: CryptoHandler, T: Transport> Controller<'a, A, C, T> {
+    /// Instantiates a new instance of the controller
+    ///
+    /// As explained in the [module documentation](crate::controller), controllers require an
+    /// authentication handler to manage the registration and crypto handler availability
+    /// during runtime. The controller talks to the CPU, the SSS, and the radio over the given
+    /// links. The transmit buffer is kept apart from the data buffer, and the scratch pool is lent
+    /// to the handlers for their temporaries; like the data buffer, both should be statics rather
+    /// than on the stack. Whatever else the controller needs of the hardware, it reaches through
+    /// the board.
+    pub fn new(
+        id: Id,
+        links: Links<T>,
+        buf: &'a mut [u8; SCEWL_MAX_DATA_SZ],
+        tx: &'a mut [u8; SCEWL_MAX_TX_SZ],
+        scratch: &'a Pool,
+        auth: A,
+        board: &'a dyn Board,
+    ) -> Self {
+        Controller {
+            id,
+            links,
+            data: buf,
+            tx,
+            scratch,
+            board,
+            auth: Some(auth),
+            crypto: None,
+            drops: Drops::default(),
+            traffic: Traffic::default(),
+            policy: Policy::new(id),
+            sss_timeout: None,
+            frame_timeout: None,
+            session: None,
+            keepalive: None,
+            next_keepalive: None,
+            withhold_sss: false,
+            header_crc: false,
+            handshakes: None,
+            mtu: mtu::DEFAULT,
+            peers: Peers::default(),
+            legacy: false,
+            #[cfg(feature = "fragmentation")]
+            reassembly: None,
+            #[cfg(feature = "fragmentation")]
+            next_fragmented: 0,
+            #[cfg(feature = "prioritized")]
+            outbound: None,
+            #[cfg(feature = "ports")]
+            ports: Ports::default(),
+            #[cfg(feature = "heartbeat")]
+            heartbeat: None,
+            #[cfg(feature = "integrity")]
+            integrity: Monitor::new(board.code()),
+            #[cfg(feature = "update")]
+            update: None,
+            #[cfg(feature = "scripted")]
+            faults: Faults::default(),
+        }
+    }
+
+    /// Limits each source on the radio to the first number of frames per second, and every source
+    /// together to the second, as timed by the clock (see the [policy module](crate::policy))
+    pub fn with_rate_limit(mut self, clock: &'a dyn Clock, per_source: u16, global: u16) -> Self {
+        self.policy = self.policy.with_rate_limit(clock, per_source, global);
+        self
+    }
+
+    /// Gives up on each read from the SSS which takes longer than the given timeout, as timed by
+    /// the clock, so that an SSS which never answers fails the (de)registration rather than
+    /// wedging the controller (see [`read_sss`](Controller::read_sss))
+    pub fn with_sss_timeout(mut self, clock: &'a dyn Clock, timeout: Duration) -> Self {
+        self.sss_timeout = Some((clock, timeout));
+        self
+    }
+
+    /// Abandons each frame read with [`read_msg`](Controller::read_msg) which takes longer than
+    /// the given timeout, as timed by the clock, so that a peer which stalls mid-frame does not
+    /// wedge the controller; the run loop then looks for the magic of the next frame
+    pub fn with_frame_timeout(mut self, clock: &'a dyn Clock, timeout: Duration) -> Self {
+        self.frame_timeout = Some((clock, timeout));
+        self
+    }
+
+    /// Bounds the use of the keys of each registration by the given lifetime, as timed by the
+    /// clock, beyond which the controller rekeys with the SSS before it sends anything more (see
+    /// the [session module](crate::session))
+    pub fn with_session_lifetime(mut self, clock: &'a dyn Clock, lifetime: Lifetime) -> Self {
+        self.session = Some(Session::new(clock, lifetime));
+        self
+    }
+
+    /// Keeps alive with the SSS every period while registered, as timed by the clock, so that the
+    /// SSS can tell which SEDs are live and the controller can tell whether the SSS still holds it
+    /// registered (see the [keepalive module](crate::secure::keepalive))
+    pub fn with_sss_keepalive(mut self, clock: &'a dyn Clock, period: Duration) -> Self {
+        self.keepalive = Some((clock, period));
+        self
+    }
+
+    /// Accepts frames of at most the given size over the radio, as announced to the peers (see the
+    /// [MTU module](crate::mtu)), rather than as many as the data buffer holds
+    pub fn with_mtu(mut self, mtu: u16) -> Self {
+        self.mtu = mtu;
+        self
+    }
+
+    /// Reassembles the messages which the peers send in fragments in the given buffer, which, like
+    /// the data buffer, should be a static rather than on the stack (see the [fragment
+    /// module](crate::fragment)); without one, fragments received are dropped
+    #[cfg(feature = "fragmentation")]
+    pub fn with_reassembly(mut self, buf: &'a mut [u8; fragment::CAPACITY]) -> Self {
+        self.reassembly = Some(Reassembly::new(buf));
+        self
+    }
+
+    /// Queues frames for the radio in the given buffer, which, like the data buffer, should be a
+    /// static rather than on the stack, and sends them in order of priority (see the [outbound
+    /// module](crate::outbound)); without one, frames are written out as soon as they are ready
+    #[cfg(feature = "prioritized")]
+    pub fn with_outbound(mut self, buf: &'a mut [u8; outbound::CAPACITY]) -> Self {
+        self.outbound = Some(Outbound::new(buf));
+        self
+    }
+
+    /// Sends heartbeats on the given schedule while the controller runs (see the [heartbeat
+    /// module](crate::heartbeat))
+    #[cfg(feature = "heartbeat")]
+    pub fn with_heartbeat(mut self, heartbeat: Heartbeat<'a>) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
+    /// Accepts firmware updates from the updater's source while the controller runs (see the
+    /// [update module](crate::update))
+    #[cfg(feature = "update")]
+    pub fn with_update(mut self, update: &'a mut dyn Updater) -> Self {
+        self.update = Some(update);
+        self
+    }
+
+    /// Gets the pool of scratch buffers lent to handlers (see the [scratch module](crate::scratch))
+    ///
+    /// As the pool outlives this borrow of the controller, an authentication handler may hold a
+    /// scratch buffer while it also accesses the [data buffer](Controller::data).
+    pub fn scratch(&self) -> &'a Pool {
+        self.scratch
+    }
+
+    /// Begins to build a controller from the same parts as [`new`](Controller::new), to which the
+    /// optional features are added one by one (see [`ControllerBuilder`])
+    pub fn builder(
+        id: Id,
+        links: Links<T>,
+        buf: &'a mut [u8; SCEWL_MAX_DATA_SZ],
+        tx: &'a mut [u8; SCEWL_MAX_TX_SZ],
+        scratch: &'a Pool,
+        auth: A,
+        board: &'a dyn Board,
+    ) -> ControllerBuilder<'a, A, C, T> {
+        ControllerBuilder {
+            controller: Controller::new(id, links, buf, tx, scratch, auth, board),
+        }
+    }
+}
+
+/// Builder for a [`Controller`], begun with [`Controller::builder`]
+///
+/// Each of the optional features is added by the method of the same name, in whichever order suits
+/// the caller, until the controller is [built](ControllerBuilder::build). Every method defers to
+/// the `with_` method of the controller which sets that feature, so the two never disagree on
+/// what a feature does. Features which are not given are left off, as with [`Controller::new`].
+/// The traffic [statistics](Controller::traffic) and [drops](Controller::drops) are always kept.
+///
+/// The builder is generic over the [transport](Transport) of the controller's links, like the
+/// controller itself: the firmware builds it over the UARTs, and tests over whatever transport
+/// suits them.
+pub struct ControllerBuilder<'a, A, C, T>
+where
+    A: AuthHandler<C>,
+    C: CryptoHandler,
+    T: Transport,
+{
+    /// The controller being built, to which each feature is added as it is given
+    controller: Controller<'a, A, C, T>,
+}
+
+impl<'a, A: AuthHandler<C>, C: CryptoHandler, T: Transport> ControllerBuilder<'a, A, C, T> {
+    /// Limits each source on the radio to the first number of frames per second, and every source
+    /// together to the second, as timed by the clock (see
+    /// [`Controller::with_rate_limit`])
+    pub fn rate_limit(self, clock: &'a dyn Clock, per_source: u16, global: u16) -> Self {
+        Self {
+            controller: self.controller.with_rate_limit(clock, per_source, global),
+        }
+    }
+
+    /// Gives up on each read from the SSS which takes longer than the given timeout, as timed by
+    /// the clock (see [`Controller::with_sss_timeout`])
+    pub fn sss_timeout(self, clock: &'a dyn Clock, timeout: Duration) -> Self {
+        Self {
+            controller: self.controller.with_sss_timeout(clock, timeout),
+        }
+    }
+
+    /// Abandons each frame which takes longer than the given timeout to read, as timed by the
+    /// clock (see [`Controller::with_frame_timeout`])
+    pub fn frame_timeout(self, clock: &'a dyn Clock, timeout: Duration) -> Self {
+        Self {
+            controller: self.controller.with_frame_timeout(clock, timeout),
+        }
+    }
+
+    /// Bounds the use of the keys of each registration by the given lifetime, as timed by the
+    /// clock (see [`Controller::with_session_lifetime`])
+    pub fn session_lifetime(self, clock: &'a dyn Clock, lifetime: Lifetime) -> Self {
+        Self {
+            controller: self.controller.with_session_lifetime(clock, lifetime),
+        }
+    }
+
+    /// Keeps alive with the SSS every period while registered, as timed by the clock (see
+    /// [`Controller::with_sss_keepalive`])
+    pub fn sss_keepalive(self, clock: &'a dyn Clock, period: Duration) -> Self {
+        Self {
+            controller: self.controller.with_sss_keepalive(clock, period),
+        }
+    }
+
+    /// Accepts frames of at most the given size over the radio (see [`Controller::with_mtu`])
+    pub fn mtu(self, mtu: u16) -> Self {
+        Self {
+            controller: self.controller.with_mtu(mtu),
+        }
+    }
+
+    /// Reassembles fragmented messages in the given buffer (see
+    /// [`Controller::with_reassembly`])
+    #[cfg(feature = "fragmentation")]
+    pub fn reassembly(self, buf: &'a mut [u8; fragment::CAPACITY]) -> Self {
+        Self {
+            controller: self.controller.with_reassembly(buf),
+        }
+    }
+
+    /// Queues frames for the radio in the given buffer (see [`Controller::with_outbound`])
+    #[cfg(feature = "prioritized")]
+    pub fn outbound(self, buf: &'a mut [u8; outbound::CAPACITY]) -> Self {
+        Self {
+            controller: self.controller.with_outbound(buf),
+        }
+    }
+
+    /// Sends heartbeats on the given schedule (see [`Controller::with_heartbeat`])
+    #[cfg(feature = "heartbeat")]
+    pub fn heartbeat(self, heartbeat: Heartbeat<'a>) -> Self {
+        Self {
+            controller: self.controller.with_heartbeat(heartbeat),
+        }
+    }
+
+    /// Accepts firmware updates from the updater's source (see [`Controller::with_update`])
+    #[cfg(feature = "update")]
+    pub fn update(self, update: &'a mut dyn Updater) -> Self {
+        Self {
+            controller: self.controller.with_update(update),
+        }
+    }
+
+    /// Builds the controller, with each of the features given
+    pub fn build(self) -> Controller<'a, A, C, T> {
+        self.controller
+    }
+}
+
+impl<A: AuthHandler<C>, C: CryptoHandler, T: Transport> Controller<'_, A, C, T> {
+    /// Acquires a copy of the transport for a specific interface, as yet without a deadline. This
+    /// method is used internally as a shorthand for acquiring interfaces to read/write on.
+    fn get_intf(&self, intf: INTF) -> Timed<'static, T> {
+        Timed::new(self.links.get(intf).clone())
+    }
+
+    /// Gets the ID of this controller, which is necessary for some authentication and cryptographic
+    /// operations
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
+    /// Gets the current registration status of this controller
+    pub fn registered(&self) -> bool {
+        self.crypto.is_some()
+    }
+
+    /// Gets the number of messages dropped for each reason since boot
+    pub fn drops(&self) -> &Drops {
+        &self.drops
+    }
+
+    /// Gets the number of frames and bytes received and sent over each link since boot
+    pub fn traffic(&self) -> &Traffic {
+        &self.traffic
+    }
+
+    /// Sets whether an [extension](MessageHeader::extension), carrying a version and a CRC, follows
+    /// the header of each frame between SEDs on the radio, as the SSS may enable at registration;
+    /// it is disabled again on deregistration
+    pub fn set_header_crc(&mut self, enabled: bool) {
+        self.header_crc = enabled;
+    }
+
+    /// Sets the handshakes with which a secret is agreed with each peer (see the [handshake
+    /// module](crate::secure::Handshakes)), as the SSS may enable at registration; they are
+    /// forgotten again on deregistration
+    pub fn set_handshakes(&mut self, handshakes: Option<Handshakes>) {
+        self.handshakes = handshakes;
+    }
+
+    /// Whether an [extension](MessageHeader::extension) follows the given header on the given
+    /// interface, which is only so on the radio between SEDs (the FAA speaks the original format)
+    /// once the SSS has enabled it
+    fn extension_follows(&self, intf: INTF, hdr: &MessageHeader) -> bool {
+        self.header_crc && intf == INTF::RAD && hdr.src_id != Id::FAA && hdr.tgt_id != Id::FAA
+    }
+
+    /// Gets a mutable reference to the internal data of this controller
+    ///
+    /// This method is intended to be used by handlers as a means of accessing the response data of
+    /// a particular [`read_msg`](Controller::read_msg) operation
+    pub fn data(&mut self) -> &mut [u8] {
+        self.data
+    }
+
+    /// Gets a mutable reference to the transmit buffer of this controller
+    ///
+    /// This method is intended to be used by handlers as a means of composing the messages they
+    /// [send](Controller::send_tx), without clobbering the data buffer.
+    pub fn tx(&mut self) -> &mut [u8] {
+        self.tx
+    }
+
+    /// Reads a message of the given length from the interface specified, optionally blocking
+    ///
+    /// The content of the message will be written directly to the data buffer allocated for
+    /// sending and receiving messages, available to other types via [`data`](Controller::data).
+    ///
+    /// Messages which are expected to be encrypted will be verified with the crypto handler's
+    /// [`verify`](crypto::Handler::verify) method.
+    ///
+    /// This method _does not_ complete any encrypt/decrypt operations. Instead, if a message is
+    /// expected to contain encrypted content (e.g. in the case that a message is received from
+    /// another SED via direct message or broadcast), it will be post-processed by the [run loop](Controller::run)
+    /// as handled by [`handle_scewl_recv`](Controller::handle_scewl_recv) and [`handle_brdcst_recv`](Controller::handle_brdcst_recv).
+    /// See the respective method for details on this post-processing operation. A crypto handler
+    /// which [streams](crate::crypto::Handler::stream_block) decryption does, however, begin to
+    /// decrypt a verified message while the rest of it is read.
+    ///
+    /// Should the controller have been given a [frame timeout](Controller::with_frame_timeout), a
+    /// message which has not arrived in full before it passes fails with [`Error::TimedOut`].
+    pub fn read_msg(&mut self, intf: INTF, len: u16) -> Result<Message> {
+        self.read_timed(intf, len, self.frame_timeout)
+    }
+
+    /// Reads a message of the given length from the SSS, as [`read_msg`](Controller::read_msg)
+    /// does, but fails with [`Error::TimedOut`] should the message not have arrived in full
+    /// before the [timeout](Controller::with_sss_timeout), if any, passed
+    ///
+    /// Authentication handlers should read from the SSS with this method, so that an SSS which
+    /// never answers fails the (de)registration, after which the run loop carries on servicing the
+    /// CPU. A late answer is read by the run loop as an unexpected message, and ignored.
+    pub fn read_sss(&mut self, len: u16) -> Result<Message> {
+        self.read_timed(INTF::SSS, len, self.sss_timeout)
+    }
+
+    /// Reads a message of the given length from the interface, failing with [`Error::TimedOut`]
+    /// should it not have arrived in full before the given timeout, if any, passed
+    fn read_timed(
+        &mut self,
+        intf: INTF,
+        len: u16,
+        timeout: Option<(&dyn Clock, Duration)>,
+    ) -> Result<Message> {
+        let res = match timeout {
+            None => self.read_from(self.get_intf(intf), len),
+            Some((clock, timeout)) => {
+                let deadline = Deadline::after(clock, timeout);
+                let uart = self.get_intf(intf).until(deadline);
+                match self.read_from(uart, len) {
+                    Err(Error::Interface(_)) if deadline.passed() => {
+                        warn!("Timed out reading from {:?}", intf);
+                        Err(Error::TimedOut)
+                    }
+                    res => res,
+                }
+            }
+        };
+        if let Ok(msg) = &res {
+            self.traffic.received(intf.into(), msg.len);
+        }
+        res
+    }
+
+    /// Reads a message of the given length from the interface, as [`read_msg`](Controller::read_msg)
+    /// describes
+    fn read_from(&mut self, mut intf: Timed<'_, T>, len: u16) -> Result<Message> {
+        // only the smallest fixed-size message need be cleared, so that a short one parses as
+        // zeros; the rest of the buffer is only ever read up to the length of its message
+        self.data[..SSSMessage::size()].fill(0);
+
+        loop {
+            intf.discard_while(|b| b != b'S')?;
+            let c = intf.discard_while(|b| b == b'S')?;
+
+            if c == b'C' {
+                break;
+            }
+
+            self.drops.record(Reason::BadMagic);
+        }
+
+        let mut buf: [u8; 8] = [0_u8; 8];
+        intf.read(&mut buf[2..])?;
+        let hdr = MessageHeader::from_bytes(buf);
+        // the arrival of each frame is timed by SysTick, which the CPU, radio, and SSS do not share,
+        // and its addressing varies with the traffic
+        self.board
+            .stir(u32::from_le_bytes([buf[2], buf[3], buf[4], buf[5]]));
+
+        trace!("Read header: {:?} {:?}", intf, hdr);
+
+        if self.extension_follows(intf.named(), &hdr) {
+            let mut ext = [0_u8; MessageHeader::EXTENSION_SIZE];
+            intf.read(&mut ext)?;
+            let [version, crc @ ..] = ext;
+            if u16::from_le_bytes(crc) != hdr.crc(version) {
+                warn!("Dropping header: {:?} {:?}: {}", intf, hdr, Reason::BadCrc);
+                self.drops.record(Reason::BadCrc);
+                // the length cannot be trusted, so the body is not discarded; the next frame is
+                // found by its magic instead
+                return Err(Reason::BadCrc.into());
+            }
+            if version != MessageHeader::VERSION {
+                warn!(
+                    "Dropping header: {:?} {:?} v{}: {}",
+                    intf,
+                    hdr,
+                    version,
+                    Reason::BadVersion
+                );
+                self.drops.record(Reason::BadVersion);
+                // the CRC vouches for the length, so the body is skipped whole
+                intf.discard(hdr.len as usize);
+                return Err(Reason::BadVersion.into());
+            }
+        }
+
+        // content from the CPU which will be encrypted is read to where the crypto handler expects
+        // it, rather than shifted there afterwards, as data
+        let offset = if intf.named() == INTF::CPU {
+            self.compose(hdr.tgt_id, Kind::Data)
+        } else {
+            0
+        };
+
+        let room = min(usize::from(len), self.data.len().saturating_sub(offset));
+        // a message from the CPU too large for the data buffer may yet be sent in fragments
+        #[cfg(feature = "fragmentation")]
+        let fragmented =
+            intf.named() == INTF::CPU && usize::from(hdr.len) > room && self.fragments(hdr.tgt_id);
+        #[cfg(feature = "fragmentation")]
+        let room = if fragmented { fragment::CAPACITY } else { room };
+        if let Err(reason) = self.policy.admit(intf.named().into(), &hdr, room) {
+            warn!("Dropping header: {:?} {:?}: {}", intf, hdr, reason);
+            self.drops.record(reason);
+            intf.discard(hdr.len as usize);
+            return Err(reason.into()); // absolutely deny -- this is certainly a bad message
+        }
+        #[cfg(feature = "fragmentation")]
+        if fragmented {
+            return self.read_fragmented(&mut intf, &hdr);
+        }
+        let len = hdr.len as usize;
+
+        let msg = Message {
+            src_id: hdr.src_id,
+            tgt_id: hdr.tgt_id,
+            len,
+        };
+
+        self.legacy = false;
+        let already = if intf.named() == INTF::RAD && hdr.src_id != Id::FAA {
+            self.read_verification(&mut intf, msg)?
+        } else {
+            0
+        };
+        let remaining = len - already;
+
+        if !invariant!(already + remaining == len && offset + len <= self.data.len()) {
+            return Err(Error::Unknown);
+        }
+
+        let res = self.read_rest(&mut intf, offset, already, len);
+
+        #[cfg(feature = "hexdump")]
+        hexdump::dump(
+            if already == 0 {
+                Stage::RawRx
+            } else {
+                Stage::PostVerify
+            },
+            &self.data[offset..][..msg.len],
+        );
+
+        trace!(
+            "Read complete message: {:?} {:?}: {:?}",
+            intf,
+            msg,
+            crate::redact::Payload(&self.data[offset..][..msg.len])
+        );
+
+        #[allow(unused_variables)] // suppress warning for captured when not in semihosting mode
+        if let Err(err @ SomeData(captured)) = res {
+            warn!(
+                "Received buffer was less than the expected length: {:?} {:?}",
+                captured, remaining
+            );
+
+            Err(err.into())
+        } else {
+            Ok(msg)
+        }
+    }
+
+    /// Whether a message from the CPU to the given target would be sent in fragments should it be
+    /// too large for the data buffer, i.e. whether it would be encrypted and sent to other SEDs
+    /// (as decided by [`dispatch_cpu`](Controller::dispatch_cpu))
+    #[cfg(feature = "fragmentation")]
+    fn fragments(&self, tgt_id: Id) -> bool {
+        let sendable = match tgt_id {
+            Id::Broadcast => true,
+            Id::Other(_) => !tgt_id.ct_eq(self.id),
+            _ => false,
+        };
+        sendable && self.registered() && !self.session.as_ref().is_some_and(Session::expired)
+    }
+
+    /// Reads the body of a message from the CPU which is too large for the data buffer, sending
+    /// each fragment of it but the last as it is read (see the [fragment module](crate::fragment))
+    ///
+    /// Each fragment is composed as content of the fragment kind. The last fragment is returned as
+    /// the message read, to be sent as any other; should an earlier fragment fail to be sent, the
+    /// rest of the message is discarded.
+    #[cfg(feature = "fragmentation")]
+    fn read_fragmented(&mut self, intf: &mut Timed<'_, T>, hdr: &MessageHeader) -> Result<Message> {
+        let tgt_id = hdr.tgt_id;
+        let total_len = usize::from(hdr.len);
+        let mut fragment = Fragment {
+            id: self.next_fragmented,
+            index: 0,
+            total: Fragment::count(total_len),
+        };
+        self.next_fragmented = self.next_fragmented.wrapping_add(1);
+        debug!(
+            "Sending {} bytes to {:?} in {} fragments",
+            total_len, tgt_id, fragment.total
+        );
+
+        let mut read = 0;
+        loop {
+            // the kind is written anew each time, as sending a fragment encrypts it in place
+            let offset = self.compose(tgt_id, Kind::Fragment);
+            let n = min(fragment::CHUNK, total_len - read);
+            let head = fragment.to_bytes(&mut self.data[offset..]);
+            if let Err(err) = intf.read(&mut self.data[offset + head..][..n]) {
+                self.wipe(offset + head + n);
+                return Err(err.into());
+            }
+            read += n;
+
+            let len = head + n;
+            if read == total_len {
+                return Ok(Message {
+                    src_id: hdr.src_id,
+                    tgt_id,
+                    len,
+                });
+            }
+
+            let sent = if tgt_id == Id::Broadcast {
+                self.handle_brdcst_send(len)
+            } else {
+                self.handle_scewl_send(tgt_id, len)
+            };
+            if let Err(err) = sent {
+                warn!(
+                    "Discarding the rest of the message to {:?}: {}",
+                    tgt_id, err
+                );
+                intf.discard(total_len - read);
+                return Err(err);
+            }
+            fragment.index += 1;
+        }
+    }
+
+    /// Reads and verifies the verification segment of a frame from another SED on the radio,
+    /// returning the number of bytes of the frame read
+    ///
+    /// A frame which fails verification is discarded, unless it is instead accepted as a [legacy
+    /// frame](crate::legacy), of which the bytes read are then the start of its plaintext.
+    fn read_verification(&mut self, intf: &mut Timed<'_, T>, msg: Message) -> Result<usize> {
+        let crypto = self.crypto.as_mut().ok_or(Error::Unregistered)?;
+        let already = crypto.verification_len();
+        if already > msg.len {
+            let jitter = crypto.jitter();
+            if self.accept_legacy(Reason::BadLength) {
+                return Ok(0);
+            }
+            warn!("Frame is shorter than its verification: {:?}", msg);
+            self.board.delay(jitter);
+            intf.discard(msg.len);
+            self.drop_from_peer(msg.src_id, Reason::BadLength);
+            return Err(Reason::BadLength.into());
+        }
+        if already == 0 {
+            return Ok(0);
+        }
+
+        intf.read(&mut self.data[..already])?;
+        #[cfg(feature = "hexdump")]
+        hexdump::dump(Stage::RawRx, &self.data[..already]);
+        if let Err(reason) = crypto.verify(self.data, msg, self.scratch) {
+            let jitter = crypto.jitter();
+            if !self.accept_legacy(reason) {
+                self.board.delay(jitter);
+                intf.discard(msg.len - already);
+                self.drop_from_peer(msg.src_id, reason);
+                return Err(reason.into());
+            }
+        }
+        Ok(already)
+    }
+
+    /// Whether a frame from another SED which failed verification or decryption for the given
+    /// reason is instead accepted as a [legacy frame](crate::legacy), which it is only with the
+    /// `mixed-mode` feature, and only should the frame not be authentic (rather than, say,
+    /// replayed)
+    fn accept_legacy(&mut self, reason: Reason) -> bool {
+        self.legacy = cfg!(feature = "mixed-mode")
+            && matches!(
+                reason,
+                Reason::Malformed | Reason::BadLength | Reason::BadMac | Reason::NoKey
+            );
+        self.legacy
+    }
+
+    /// Reads the rest of a message of `len` bytes, of which `already` have been read, to `offset`
+    /// in the data buffer
+    ///
+    /// Should the message have been verified by a crypto handler which
+    /// [streams](crate::crypto::Handler::stream_block) decryption, each block is handed to the
+    /// handler to [decrypt](crate::crypto::Handler::decrypt_received) as soon as it has arrived.
+    /// Hexdumps show the message as received, so it is then decrypted afterwards instead, as it is
+    /// in mixed mode, where a frame whose HMAC fails is forwarded as received.
+    fn read_rest(
+        &mut self,
+        intf: &mut Timed<'_, T>,
+        offset: usize,
+        already: usize,
+        len: usize,
+    ) -> transport::Result<()> {
+        // legacy frames may yet be told apart only once the frame has arrived, so in mixed mode the
+        // frame must reach the handler as it was received
+        let streamed = already != 0
+            && !self.legacy
+            && !cfg!(feature = "hexdump")
+            && !cfg!(feature = "mixed-mode");
+        let crypto = match self.crypto.as_mut() {
+            Some(crypto) if streamed && crypto.stream_block() != 0 => crypto,
+            _ => return intf.read(&mut self.data[offset + already..offset + len]),
+        };
+
+        let block = crypto.stream_block();
+        let mut received = already;
+
+        while received < len {
+            let n = min(block, len - received);
+            intf.read(&mut self.data[received..][..n])
+                .map_err(|err| match err {
+                    SomeData(captured) => SomeData(received - already + captured),
+                    err => err,
+                })?;
+            received += n;
+            crypto.decrypt_received(self.data, received);
+        }
+
+        Ok(())
+    }
+
+    /// Sends the current content of the data buffer to the specified interface with the provided
+    /// message header
+    ///
+    /// Note that, in particular, the message header specifies the length of the message. It is not
+    /// necessary to write the message header to the data buffer in advance, as this method will
+    /// send the message header first before sending the content of the data buffer, limited to the
+    /// length specified in the provided message header.
+    pub fn send_msg(&mut self, intf: INTF, msg: &Message) -> Result<()> {
+        self.send_content(intf, msg, Body::Data(0))
+    }
+
+    /// Sends the current content of the [transmit buffer](Controller::tx) to the specified
+    /// interface with the provided message header, as [`send_msg`](Controller::send_msg) does from
+    /// the data buffer, then zeroes it
+    pub fn send_tx(&mut self, intf: INTF, msg: &Message) -> Result<()> {
+        let res = self.send_content(intf, msg, Body::Tx);
+        self.tx[..msg.len.min(SCEWL_MAX_TX_SZ)].fill(0);
+        res
+    }
+
+    /// Sends the content of the given buffer to the specified interface with the provided message
+    /// header, as [`send_msg`](Controller::send_msg) does from the start of the data buffer
+    ///
+    /// This is used to send decrypted content from where the crypto handler left it.
+    #[allow(clippy::unnecessary_wraps)] // writes cannot currently fail, but callers should not assume so
+    fn send_content(&mut self, intf: INTF, msg: &Message, body: Body) -> Result<()> {
+        if self.withhold_sss && intf == INTF::CPU && msg.src_id == Id::SSS {
+            debug!("Withholding answer of the SSS from the CPU");
+            return Ok(());
+        }
+
+        let mut intf = self.get_intf(intf);
+
+        let (buf, offset) = match body {
+            Body::Data(offset) => (&self.data[..], offset),
+            Body::Tx => (&self.tx[..], 0),
+        };
+        if !invariant!(offset + msg.len <= buf.len() && u16::try_from(msg.len).is_ok()) {
+            return Err(Error::Unknown);
+        }
+        let content = &buf[offset..][..msg.len];
+
+        let hdr = msg.to_canonical();
+        let bytes = hdr.to_bytes();
+        let ext = self
+            .extension_follows(intf.named(), &hdr)
+            .then(|| hdr.extension());
+        let parts = [
+            &bytes[..],
+            ext.as_ref().map_or(&[][..], |ext| &ext[..]),
+            content,
+        ];
+
+        #[cfg(feature = "hexdump")]
+        hexdump::dump(Stage::PreTx, content);
+
+        #[cfg(feature = "prioritized")]
+        let queued = intf.named() == INTF::RAD
+            && Self::enqueue(
+                self.outbound.as_mut(),
+                &mut intf,
+                Class::of(hdr.tgt_id),
+                &parts,
+            );
+        #[cfg(not(feature = "prioritized"))]
+        let queued = false;
+
+        if !queued {
+            for part in parts {
+                intf.write(part);
+            }
+        }
+        self.traffic.sent(intf.named().into(), msg.len);
+
+        trace!(
+            "Send: {:?} {:?}: {:?}",
+            intf,
+            msg,
+            crate::redact::Payload(content)
+        );
+
+        Ok(())
+    }
+
+    /// Zeroes the first `len` bytes of the data buffer, which held a message that has now been
+    /// forwarded (or dropped), so that its content lingers for neither a later, shorter message
+    /// nor a bug to expose
+    fn wipe(&mut self, len: usize) {
+        let len = min(len, self.data.len());
+        self.data[..len].fill(0);
+    }
+
+    /// The offset in the data buffer at which the content of a message to the given target
+    /// begins, which is the crypto handler's [content offset](CryptoHandler::content_offset),
+    /// should the message be encrypted (as decided by [`dispatch_cpu`](Controller::dispatch_cpu))
+    fn content_offset(&self, tgt_id: Id) -> Option<usize> {
+        match (&self.crypto, tgt_id) {
+            (Some(crypto), Id::Broadcast) => Some(crypto.content_offset()),
+            (Some(crypto), Id::Other(_)) if !tgt_id.ct_eq(self.id) => Some(crypto.content_offset()),
+            _ => None,
+        }
+    }
+
+    /// The offset in the data buffer at which the body of a message from the CPU to the given
+    /// target lies, which is just after the [kind](Kind) which begins its content should the
+    /// message be encrypted, or the start of the buffer otherwise
+    fn send_offset(&self, tgt_id: Id) -> usize {
+        self.content_offset(tgt_id)
+            .map_or(0, |offset| offset + Kind::SIZE)
+    }
+
+    /// Writes the given kind ahead of the body of a message to the given target, should the
+    /// message be encrypted, returning the [offset of the body](Controller::send_offset)
+    fn compose(&mut self, tgt_id: Id, kind: Kind) -> usize {
+        if let Some(offset) = self.content_offset(tgt_id) {
+            self.data[offset] = kind.into();
+        }
+        self.send_offset(tgt_id)
+    }
+
+    /// Announces the given banner to the CPU as a status frame from this controller (see the
+    /// [banner module](crate::banner)); to be called once at boot
+    pub fn announce(&mut self, banner: &Banner<'_>) -> Result<()> {
+        let len = banner.to_bytes(self.tx);
+
+        self.send_tx(
+            INTF::CPU,
+            &Message {
+                tgt_id: self.id,
+                src_id: self.id,
+                len,
+            },
+        )
+    }
+
+    /// Method which is used internally to handle messages received on the radio interface from
+    /// other SEDs, excluding broadcasts (see [`handle_brdcst_recv`](Controller::handle_brdcst_recv))
+    ///
+    /// This method will be invoked by the [run loop](Controller::run) in the case that a received
+    /// message is from another SED and not a broadcast. The crypto handler's [decryption operation](crate::crypto::Handler::decrypt)
+    /// will be invoked before this message is passed on to the CPU.
+    fn handle_scewl_recv(&mut self, src_id: Id, len: usize) -> Result<()> {
+        debug!(
+            "Handling SCEWL receive from {:?} with size {:?}",
+            src_id, len
+        );
+        self.handle_peer_recv(src_id, len, false)
+    }
+
+    /// Method which is used internally to handle messages received on the CPU interface to be sent
+    /// to other SEDs, excluding broadcasts (see [`handle_brdcst_send`](Controller::handle_brdcst_send))
+    ///
+    /// This method will be invoked by the [run loop](Controller::run) in the case that a message to
+    /// be sent is a direct message to another SED. The crypto handler's [encryption operation](crate::crypto::Handler::encrypt)
+    /// will be invoked before this message is passed on to the radio.
+    ///
+    /// The body of the message lies at the [send offset](Controller::send_offset), just after its
+    /// kind.
+    fn handle_scewl_send(&mut self, tgt_id: Id, len: usize) -> Result<()> {
+        let mut msg = Message {
+            tgt_id,
+            src_id: self.id,
+            len: Kind::SIZE + len,
+        };
+
+        debug!("Handling SCEWL send to {:?} with size {:?}", tgt_id, len);
+
+        if !self.policy.is_allowed(tgt_id) {
+            warn!(
+                "Dropping message to {:?}, which is not on the allowlist",
+                tgt_id
+            );
+            self.drops.record(Reason::NotAllowed);
+            self.wipe(self.send_offset(tgt_id) + len);
+            return Err(Reason::NotAllowed.into());
+        }
+
+        let crypto = self.crypto.as_mut().ok_or(Error::Unregistered)?;
+
+        // checked before encrypting, so that a message which is never sent uses up no counter
+        let sealed = crypto.sealed_len(msg, self.data.len());
+        let mtu = self.peers.mtu(tgt_id);
+        if sealed > usize::from(mtu) {
+            warn!(
+                "Dropping message to {:?} of {} bytes, beyond its MTU of {}",
+                tgt_id, sealed, mtu
+            );
+            self.drops.record(Reason::PeerMtu);
+            self.wipe(self.send_offset(tgt_id) + len);
+            return Err(Reason::PeerMtu.into());
+        }
+
+        msg.len = crypto.encrypt(self.data, msg, self.scratch);
+        if let Some(session) = self.session.as_mut() {
+            session.record_send();
+        }
+
+        let res = self.send_msg(INTF::RAD, &msg);
+        self.wipe(msg.len);
+        res
+    }
+
+    /// Method which is used internally to handle broadcasts received on the radio interface from
+    /// other SEDs (see [`handle_scewl_recv`](Controller::handle_scewl_recv) for information on how
+    /// direct messages are handled)
+    ///
+    /// This method will be invoked by the [run loop](Controller::run) in the case that a received
+    /// message is from another SED and is a broadcast. The crypto handler's [decryption operation](crate::crypto::Handler::decrypt)
+    /// will be invoked before this message is passed on to the CPU.
+    fn handle_brdcst_recv(&mut self, src_id: Id, len: usize) -> Result<()> {
+        debug!(
+            "Handling broadcast received from {:?} with size {:?}",
+            src_id, len
+        );
+        self.handle_peer_recv(src_id, len, true)
+    }
+
+    /// Method which is used internally to decrypt a message received on the radio interface from
+    /// another SED, directly or by broadcast, and act on its [kind](Kind): data is passed on to the
+    /// CPU, whereas the controller's own kinds are handled here, and those which are only ever
+    /// sent to one peer are dropped should they arrive by broadcast
+    ///
+    /// This method is shared by [`handle_scewl_recv`](Controller::handle_scewl_recv) and
+    /// [`handle_brdcst_recv`](Controller::handle_brdcst_recv).
+    fn handle_peer_recv(&mut self, src_id: Id, len: usize, broadcast: bool) -> Result<()> {
+        let mut msg = Message {
+            tgt_id: if broadcast { Id::Broadcast } else { self.id },
+            src_id,
+            len,
+        };
+
+        if self.legacy {
+            return self.handle_legacy_recv(msg);
+        }
+
+        #[cfg(feature = "scripted")]
+        if self.faults.take(Fault::FailDecrypt) {
+            return Err(Error::Unknown);
+        }
+
+        let crypto = self.crypto.as_mut().ok_or(Error::Unregistered)?;
+        let offset = crypto.content_offset();
+        let res = match crypto.decrypt(self.data, msg, self.scratch) {
+            Ok(plain) => {
+                #[cfg(feature = "hexdump")]
+                hexdump::dump(Stage::PostDecrypt, &self.data[offset..][..plain]);
+
+                // the controller acts on the authenticated kind alone, never on what the body
+                // begins with, so that data from a CPU is forwarded whatever it holds
+                let Some((kind, body)) = Kind::split(&self.data[offset..][..plain]) else {
+                    warn!("Dropping message from {:?} of unknown kind", src_id);
+                    self.drop_from_peer(src_id, Reason::Malformed);
+                    self.wipe(len);
+                    return Err(Reason::Malformed.into());
+                };
+                let (offset, plain) = (offset + Kind::SIZE, body.len());
+                msg.len = plain;
+
+                match kind {
+                    Kind::Data => {}
+                    Kind::Hello => {
+                        let res = self.handle_hello(src_id, offset, plain, broadcast);
+                        self.wipe(len);
+                        return res;
+                    }
+                    Kind::Handshake | Kind::Update if broadcast => {
+                        // a secret is agreed with, and an update sent to, one peer at a time
+                        warn!("Dropping broadcast {:?} from {:?}", kind, src_id);
+                        self.drop_from_peer(src_id, Reason::Malformed);
+                        self.wipe(len);
+                        return Err(Reason::Malformed.into());
+                    }
+                    Kind::Handshake => {
+                        let handshake = Handshake::from_bytes(&self.data[offset..][..plain]);
+                        self.wipe(len);
+                        let Some(handshake) = handshake else {
+                            warn!("Dropping malformed handshake from {:?}", src_id);
+                            self.drop_from_peer(src_id, Reason::Malformed);
+                            return Err(Reason::Malformed.into());
+                        };
+                        return self.handle_handshake(src_id, handshake);
+                    }
+                    Kind::Update => {
+                        #[cfg(feature = "update")]
+                        if let Some(res) = self.handle_update(src_id, offset, plain) {
+                            self.wipe(len);
+                            return res;
+                        }
+                        // e.g. the acknowledgements to the source, whose CPU drives the update
+                        let res = self.deliver_envelope(src_id, kind, offset, plain);
+                        self.wipe(len);
+                        return res;
+                    }
+                    Kind::Fragment => {
+                        #[cfg(feature = "fragmentation")]
+                        let res = self.handle_fragment(msg, offset);
+                        #[cfg(not(feature = "fragmentation"))]
+                        let res = {
+                            warn!(
+                                "Dropping fragment from {:?}; nowhere to reassemble it",
+                                src_id
+                            );
+                            self.drops.record(Reason::Oversize);
+                            Err(Reason::Oversize.into())
+                        };
+                        self.wipe(len);
+                        return res;
+                    }
+                    Kind::Port => {
+                        let res = self.handle_port(src_id, offset, plain);
+                        self.wipe(len);
+                        return res;
+                    }
+                }
+
+                self.send_content(INTF::CPU, &msg, Body::Data(offset))
+            }
+            Err(reason) => {
+                let jitter = crypto.jitter();
+                if self.accept_legacy(reason) {
+                    return self.handle_legacy_recv(msg);
+                }
+                self.board.delay(jitter);
+                self.drop_from_peer(msg.src_id, reason);
+                Err(reason.into())
+            }
+        };
+
+        // the plaintext lies within the frame as received, whether or not it was forwarded
+        self.wipe(len);
+        res
+    }
+
+    /// Method which is used internally to gather a fragment received from another SED, the
+    /// decrypted body of which lies in the data buffer at the given offset, forwarding the
+    /// message which it completes, if any, to the CPU (see the [fragment module](crate::fragment))
+    #[cfg(feature = "fragmentation")]
+    fn handle_fragment(&mut self, msg: Message, offset: usize) -> Result<()> {
+        let Some(fragment) = Fragment::from_bytes(&self.data[offset..][..msg.len]) else {
+            warn!("Dropping malformed fragment from {:?}", msg.src_id);
+            self.drop_from_peer(msg.src_id, Reason::Malformed);
+            return Err(Reason::Malformed.into());
+        };
+        let Some(reassembly) = self.reassembly.as_mut() else {
+            warn!(
+                "Dropping fragment from {:?}; nowhere to reassemble it",
+                msg.src_id
+            );
+            self.drops.record(Reason::Oversize);
+            return Err(Reason::Oversize.into());
+        };
+
+        let content = &self.data[offset + Fragment::size()..offset + msg.len];
+        let len = match reassembly.accept(msg.src_id, msg.tgt_id, fragment, content) {
+            Ok(Some(len)) => len,
+            Ok(None) => return Ok(()),
+            Err(reason) => {
+                warn!(
+                    "Dropping fragment {:?} from {:?}: {}",
+                    fragment, msg.src_id, reason
+                );
+                self.drops.record(reason);
+                return Err(reason.into());
+            }
+        };
+
+        debug!("Reassembled {} bytes from {:?}", len, msg.src_id);
+        let hdr = Message { len, ..msg }.to_canonical();
+        let mut cpu = self.get_intf(INTF::CPU);
+        cpu.write(&hdr.to_bytes());
+        if let Some(reassembly) = self.reassembly.as_mut() {
+            cpu.write(reassembly.content());
+            reassembly.clear();
+        }
+        self.traffic.sent(INTF::CPU.into(), len);
+        Ok(())
+    }
+
+    /// Method which is used internally to hand a message to one of the CPU's [ports](crate::port),
+    /// the body of which lies in the data buffer at the given offset, to the CPU in an
+    /// [envelope](crate::content), should the CPU have opened that port
+    fn handle_port(&mut self, src_id: Id, offset: usize, len: usize) -> Result<()> {
+        #[allow(unused_variables)] // port is only logged in semihosting mode, without ports
+        let Some(port) = port::port_of(&self.data[offset..][..len]) else {
+            warn!("Dropping malformed port message from {:?}", src_id);
+            self.drop_from_peer(src_id, Reason::Malformed);
+            return Err(Reason::Malformed.into());
+        };
+
+        // without the ports feature, the CPU cannot open any port
+        #[cfg(feature = "ports")]
+        let open = self.ports.is_open(port);
+        #[cfg(not(feature = "ports"))]
+        let open = false;
+        if !open {
+            warn!("Dropping message from {:?} to closed port {}", src_id, port);
+            self.drops.record(Reason::ClosedPort);
+            return Err(Reason::ClosedPort.into());
+        }
+
+        self.deliver_envelope(src_id, Kind::Port, offset, len)
+    }
+
+    /// Announces this controller's MTU to the given peer, or to every peer should it be the
+    /// broadcast id (see the [MTU module](crate::mtu))
+    fn say_hello(&mut self, tgt_id: Id) -> Result<()> {
+        let offset = self.compose(tgt_id, Kind::Hello);
+        let len = Hello { mtu: self.mtu }.to_bytes(&mut self.data[offset..]);
+        if tgt_id == Id::Broadcast {
+            self.handle_brdcst_send(len)
+        } else {
+            self.handle_scewl_send(tgt_id, len)
+        }
+    }
+
+    /// Method which is used internally to record the MTU announced by a peer's hello, the body of
+    /// which lies in the data buffer at the given offset, which is answered with this controller's
+    /// own should it have been broadcast
+    fn handle_hello(
+        &mut self,
+        src_id: Id,
+        offset: usize,
+        len: usize,
+        broadcast: bool,
+    ) -> Result<()> {
+        let Some(hello) = Hello::from_bytes(&self.data[offset..][..len]) else {
+            warn!("Dropping malformed hello from {:?}", src_id);
+            self.drop_from_peer(src_id, Reason::Malformed);
+            return Err(Reason::Malformed.into());
+        };
+
+        debug!(
+            "Peer {:?} accepts frames of up to {} bytes",
+            src_id, hello.mtu
+        );
+        if !self.peers.record(src_id, hello.mtu) {
+            warn!("No room to record the MTU of {:?}", src_id);
+        }
+
+        if broadcast {
+            // the peer has registered anew, and so forgotten any secret agreed with it
+            if let Some(handshakes) = self.handshakes.as_mut() {
+                handshakes.forget(src_id);
+            }
+            if let Some(crypto) = self.crypto.as_mut() {
+                crypto.rekey(src_id, None);
+            }
+            self.say_hello(src_id)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Offers a handshake to the given peer, should none have begun with it (see the [handshake
+    /// module](crate::secure::Handshakes))
+    fn offer_handshake(&mut self, peer: Id) {
+        let Some(offer) = self.handshakes.as_mut().and_then(|hs| hs.offer(peer)) else {
+            return;
+        };
+
+        let offset = self.compose(peer, Kind::Handshake);
+        let len = offer.to_bytes(&mut self.data[offset..]);
+        #[allow(unused_variables)] // suppress warning for err when not in semihosting mode
+        if let Err(err) = self.handle_scewl_send(peer, len) {
+            warn!("Could not offer a handshake to {:?}: {}", peer, err);
+            if let Some(handshakes) = self.handshakes.as_mut() {
+                handshakes.forget(peer);
+            }
+        }
+    }
+
+    /// Method which is used internally to advance the handshake with a peer by a handshake
+    /// received from it, answering it and rekeying the crypto handler as it requires
+    fn handle_handshake(&mut self, src_id: Id, handshake: Handshake) -> Result<()> {
+        let Some(handshakes) = self.handshakes.as_mut() else {
+            warn!(
+                "Ignoring handshake from {:?}; ephemeral keys are disabled",
+                src_id
+            );
+            return Ok(());
+        };
+        let received = handshakes.receive(src_id, handshake);
+
+        // the answer goes out under the keys which the peer holds until it reads it
+        if let Some(reply) = received.reply {
+            let offset = self.compose(src_id, Kind::Handshake);
+            let len = reply.to_bytes(&mut self.data[offset..]);
+            if let Err(err) = self.handle_scewl_send(src_id, len) {
+                if let Some(handshakes) = self.handshakes.as_mut() {
+                    handshakes.forget(src_id);
+                }
+                return Err(err);
+            }
+        }
+
+        if let Some(secret) = received.secret {
+            debug!("Agreed a secret with {:?}", src_id);
+            if let Some(crypto) = self.crypto.as_mut() {
+                crypto.rekey(src_id, Some(&secret));
+            }
+        }
+        Ok(())
+    }
+
+    /// Method which is used internally to handle a [legacy frame](crate::legacy) from another SED,
+    /// direct or broadcast, which was accepted in mixed mode
+    ///
+    /// The frame is plaintext, so data is forwarded to the CPU without decryption, its body marked
+    /// as unauthenticated; the other kinds are ignored, as nothing unauthenticated may act on the
+    /// controller.
+    fn handle_legacy_recv(&mut self, mut msg: Message) -> Result<()> {
+        let len = msg.len;
+
+        let res = match Kind::split(&self.data[..len]) {
+            Some((Kind::Data, body)) => {
+                info!("Forwarding legacy frame as unauthenticated: {:?}", msg);
+                let body = body.len();
+                self.data.copy_within(Kind::SIZE..len, 0);
+                if let Some(marked) = legacy::mark(self.data, body) {
+                    msg.len = marked;
+                    self.send_msg(INTF::CPU, &msg)
+                } else {
+                    self.drops.record(Reason::Oversize);
+                    Err(Reason::Oversize.into())
+                }
+            }
+            #[allow(unused_variables)] // kind is only logged in semihosting mode
+            Some((kind, _)) => {
+                debug!("Ignoring legacy frame of kind {:?}: {:?}", kind, msg);
+                Ok(())
+            }
+            None => {
+                self.drops.record(Reason::Malformed);
+                Err(Reason::Malformed.into())
+            }
+        };
+
+        self.wipe(len + legacy::MAGIC.len());
+        res
+    }
+
+    /// Method which is used internally to handle messages received on the CPU interface to be sent
+    /// to other SEDs as a broadcast (see [`handle_scewl_send`](Controller::handle_scewl_send) for
+    /// information on how direct messages are handled)
+    ///
+    /// This method will be invoked by the [run loop](Controller::run) in the case that a message to
+    /// be sent is a broadcast. The crypto handler's [encryption operation](crate::crypto::Handler::encrypt)
+    /// will be invoked before this message is passed on to the radio.
+    ///
+    /// The body of the message lies at the [send offset](Controller::send_offset), just after its
+    /// kind.
+    fn handle_brdcst_send(&mut self, len: usize) -> Result<()> {
+        let mut msg = Message {
+            tgt_id: Id::Broadcast,
+            src_id: self.id,
+            len: Kind::SIZE + len,
+        };
+
+        debug!("Handling broadcast send with size {:?}", len);
+
+        msg.len =
+            self.crypto
+                .as_mut()
+                .ok_or(Error::Unregistered)?
+                .encrypt(self.data, msg, self.scratch);
+        if let Some(session) = self.session.as_mut() {
+            session.record_send();
+        }
+
+        let res = self.send_msg(INTF::RAD, &msg);
+        self.wipe(msg.len);
+        res
+    }
+
+    /// Method which is used internally to handle messages received on the radio interface from the
+    /// FAA, including broadcasts
+    ///
+    /// As per the specification, FAA messages will _not_ be encrypted by any mechanism. As such,
+    /// this method simply forwards the message received on the radio directly to the CPU.
+    fn handle_faa_recv(&mut self, tgt_id: Id, len: usize) -> Result<()> {
+        debug!("Handling FAA message received with size {:?}", len);
+
+        let res = self.send_msg(
+            INTF::CPU,
+            &Message {
+                src_id: Id::FAA,
+                tgt_id,
+                len,
+            },
+        );
+        self.wipe(len);
+        res
+    }
+
+    /// Method which is used internally to handle messages to be sent to the FAA from the CPU
+    ///
+    /// As per the specification, FAA messages will _not_ be encrypted by any mechanism. As such,
+    /// this method simply forwards the message to be sent to the radio directly from the CPU.
+    fn handle_faa_send(&mut self, len: usize) -> Result<()> {
+        debug!("Handling FAA message sent with size {:?}", len);
+
+        let res = self.send_msg(
+            INTF::RAD,
+            &Message {
+                src_id: self.id,
+                tgt_id: Id::FAA,
+                len,
+            },
+        );
+        self.wipe(len);
+        res
+    }
+
+    /// The crypto handler, should the controller be registered, to which the authentication
+    /// handler may hand the keys which the SSS pushes (see [`sss_push`](AuthHandler::sss_push))
+    pub fn crypto(&mut self) -> Option<&mut C> {
+        self.crypto.as_mut()
+    }
+
+    /// Replaces the devices revoked by the SSS with those of the list of the given serial, from
+    /// which frames over the radio are [dropped](Reason::Revoked), returning whether it was taken
+    /// up; the authentication handler hands over the lists which the SSS pushes (see
+    /// [`Policy::revoke`])
+    pub fn revoke(&mut self, serial: u32, revoked: impl ExactSizeIterator<Item = Id>) -> bool {
+        self.policy.revoke(serial, revoked)
+    }
+
+    /// Restricts the SEDs to and from which frames are allowed to those of the allowlist
+    /// provisioned by the SSS, or lifts the restriction should there be none, returning whether it
+    /// was taken up; frames to or from any other SED are [dropped](Reason::NotAllowed) until
+    /// deregistration (see [`Policy::allow`])
+    pub fn set_allowlist(&mut self, allowed: Option<impl ExactSizeIterator<Item = Id>>) -> bool {
+        if let Some(allowed) = allowed {
+            self.policy.allow(allowed)
+        } else {
+            self.policy.allow_all();
+            true
+        }
+    }
+
+    /// Method which is used internally to manage registration with the SSS.
+    ///
+    /// The CPU is expected to initiate all (de)registration requests and, as such, this method will
+    /// only be invoked by the [run loop](Controller::run) when the CPU requests to register or
+    /// deregister. The authentication handler is expected to handle _all_ additional communications
+    /// between the controller and the SSS, including any handshakes or exchanges of information.
+    ///
+    /// Should the CPU request to authenticate with the SSS, the authentication handler will be
+    /// requested to perform a registration. Should this registration be successful, the
+    /// authentication handler should instantiate a crypto handler appropriate for the SSS's
+    /// response. This crypto handler will then be used until deregistration (or another successful
+    /// registration) occurs by methods which send/receive non-FAA messages over the radio.
+    ///
+    /// Should the CPU request to register while already registered, the existing registration is
+    /// first [torn down](Controller::tear_down) and a fresh registration performed, so that the CPU
+    /// is answered exactly once, with [`Register`](SSSOp::Register) should it now be registered
+    /// under fresh keys, or [`Already`](SSSOp::Already) otherwise.
+    fn handle_registration(&mut self) -> bool {
+        let msg = SSSMessage::from_bytes(self.data);
+        info!("Handling SCEWL registration: {:?}", msg);
+
+        if msg.op == SSSOp::Rekey {
+            return self.handle_rekey(true);
+        }
+
+        // the secret is locked away while registered, but is proven to the SSS to (de)register
+        if matches!(msg.op, SSSOp::Register | SSSOp::Deregister) {
+            self.board.unlock_secret();
+        }
+
+        let res = match msg.op {
+            SSSOp::Register => self
+                .tear_down()
+                .and_then(|()| self.with_auth(A::sss_register))
+                .map(|c| {
+                    self.crypto = Some(c);
+                    if let Some(session) = self.session.as_mut() {
+                        session.restart();
+                    }
+                    self.next_keepalive = self
+                        .keepalive
+                        .map(|(clock, period)| Deadline::after(clock, period));
+                }),
+            SSSOp::Deregister => self
+                .with_auth(A::sss_deregister)
+                .map(|()| self.forget_registration()),
+            SSSOp::Already
+            | SSSOp::Rotate
+            | SSSOp::Rotated
+            | SSSOp::Revoke
+            | SSSOp::Revoked
+            | SSSOp::Challenge
+            | SSSOp::Rekey
+            | SSSOp::Keepalive
+            | SSSOp::Erased
+            | SSSOp::Unknown => return false,
+        };
+
+        if self.registered() {
+            self.board.lock_secret();
+        }
+
+        #[cfg(feature = "integrity")]
+        if res.is_ok() && msg.op == SSSOp::Register {
+            self.integrity.seal();
+        }
+
+        // the peers are met afresh at each registration, as they may have changed since the last,
+        // and the SSS pushes the revoked devices afresh
+        if res.is_ok() {
+            self.peers.clear();
+            self.policy.clear_revoked();
+        }
+        if res.is_ok() && msg.op == SSSOp::Register {
+            #[allow(unused_variables)] // suppress warning for err when not in semihosting mode
+            if let Err(err) = self.say_hello(Id::Broadcast) {
+                warn!("Could not announce the MTU: {}", err);
+            }
+        }
+
+        #[allow(unused_variables)] // suppress warning for err when not in semihosting mode
+        if let Err(err) = &res {
+            warn!("Registration request failed: {:?} {}", msg.op, err);
+        }
+        // the SSS never answered, so the CPU, which awaits its answer, is refused in its stead
+        if matches!(res, Err(AuthError::Controller(Error::TimedOut))) {
+            self.notify_cpu(SSSOp::Already);
+        }
+
+        res.is_ok()
+    }
+
+    /// Method which is used internally to handle a message read from the CPU, returning whether it
+    /// was handled successfully
+    ///
+    /// Until the controller is registered, only (de)registration requests are handled.
+    fn dispatch_cpu(&mut self, msg: Message) -> bool {
+        #[cfg(feature = "scripted")]
+        if self.faults.take(Fault::DropCpu) {
+            return false;
+        }
+
+        match msg.tgt_id {
+            Id::SSS => self.handle_registration(),
+            #[cfg(feature = "ports")]
+            id @ Id::Other(_)
+                if id.ct_eq(self.id) && Control::from_bytes(&self.data[..msg.len]).is_some() =>
+            {
+                self.handle_port_control(msg.len)
+            }
+            id @ Id::Other(_)
+                if id.ct_eq(self.id) && Envelope::from_bytes(&self.data[..msg.len]).is_some() =>
+            {
+                self.handle_envelope(msg.len)
+            }
+            id @ Id::Other(_) if id.ct_eq(self.id) => self.handle_diag(INTF::CPU, msg.len),
+            _ if !self.registered() => false,
+            Id::Broadcast | Id::Other(_) if self.session.as_ref().is_some_and(Session::expired) => {
+                warn!("Refusing to send to {:?} under expired keys", msg.tgt_id);
+                false
+            }
+            Id::Broadcast => self.handle_brdcst_send(msg.len).is_ok(),
+            Id::FAA => self.handle_faa_send(msg.len).is_ok(),
+            id @ Id::Other(_) => {
+                let sent = self.handle_scewl_send(id, msg.len).is_ok();
+                self.offer_handshake(id);
+                sent
+            }
+        }
+    }
+
+    /// Method which is used internally to handle a message read from the radio, returning whether
+    /// it was handled successfully
+    ///
+    /// Messages which are addressed to neither this controller nor everyone are ignored.
+    fn dispatch_rad(&mut self, msg: Message) -> bool {
+        // messages from other SEDs can only be verified, and so read, while registered
+        if !invariant!(msg.src_id == Id::FAA || self.registered()) {
+            return false;
+        }
+
+        #[cfg(feature = "scripted")]
+        if self.faults.take(Fault::DropRad) {
+            return false;
+        }
+
+        match (msg.src_id, msg.tgt_id) {
+            (Id::FAA, tgt)
+                if tgt.ct_eq(self.id) && Command::from_bytes(&self.data[..msg.len]).is_some() =>
+            {
+                self.handle_diag(INTF::RAD, msg.len)
+            }
+            (Id::FAA, tgt) if tgt.ct_eq(self.id) || tgt == Id::Broadcast => {
+                self.handle_faa_recv(tgt, msg.len).is_ok()
+            }
+            (src, Id::Broadcast) => self.handle_brdcst_recv(src, msg.len).is_ok(),
+            (src, tgt) if tgt.ct_eq(self.id) => self.handle_scewl_recv(src, msg.len).is_ok(),
+            _ => false,
+        }
+    }
+
+    /// Method which is used internally to handle a request of the CPU to open or close one of its
+    /// [ports](crate::port), which is already at the start of the data buffer, returning whether it
+    /// was handled; the request is echoed back to the CPU once it has taken effect
+    #[cfg(feature = "ports")]
+    fn handle_port_control(&mut self, len: usize) -> bool {
+        let Some(control) = Control::from_bytes(&self.data[..len]) else {
+            return false;
+        };
+
+        debug!("Handling port control: {:?}", control);
+        self.ports.apply(control);
+
+        let len = control.to_bytes(self.tx);
+        self.send_tx(
+            INTF::CPU,
+            &Message {
+                tgt_id: self.id,
+                src_id: self.id,
+                len,
+            },
+        )
+        .is_ok()
+    }
+
+    /// Method which is used internally to send the content of an [envelope](crate::content) from
+    /// the CPU, which is already at the start of the data buffer, to its peer as content of its
+    /// kind, returning whether it was sent
+    ///
+    /// Only the kinds which the CPU may send are sent, and only to another SED, or to every SED
+    /// should the kind be broadcast, under the same conditions as data.
+    fn handle_envelope(&mut self, len: usize) -> bool {
+        let Some(envelope) = Envelope::from_bytes(&self.data[..len]) else {
+            return false;
+        };
+        let (kind, peer, body) = (envelope.kind, envelope.peer, envelope.body.len());
+
+        let sendable = match peer {
+            Id::Other(_) => !peer.ct_eq(self.id),
+            Id::Broadcast => kind.broadcasts(),
+            _ => false,
+        };
+        if !kind.enveloped() || !sendable {
+            warn!("Refusing to send {:?} to {:?} for the CPU", kind, peer);
+            return false;
+        }
+        if !self.registered() {
+            return false;
+        }
+        if self.session.as_ref().is_some_and(Session::expired) {
+            warn!("Refusing to send to {:?} under expired keys", peer);
+            return false;
+        }
+
+        let offset = self.send_offset(peer);
+        if offset + body > self.data.len() {
+            warn!("Dropping {:?} to {:?}; too long to encrypt", kind, peer);
+            self.drops.record(Reason::Oversize);
+            return false;
+        }
+        self.data.copy_within(Envelope::HEADER_SIZE..len, offset);
+        self.compose(peer, kind);
+        if peer == Id::Broadcast {
+            self.handle_brdcst_send(body).is_ok()
+        } else {
+            self.handle_scewl_send(peer, body).is_ok()
+        }
+    }
+
+    /// Method which is used internally to hand content of a kind other than data, which was
+    /// received from a peer and which the controller does not consume, to the CPU in an
+    /// [envelope](crate::content) from this controller; its body lies in the data buffer at the
+    /// given offset
+    fn deliver_envelope(
+        &mut self,
+        src_id: Id,
+        kind: Kind,
+        offset: usize,
+        len: usize,
+    ) -> Result<()> {
+        let size = Envelope::HEADER_SIZE + len;
+        if size > self.data.len() {
+            warn!(
+                "Dropping {:?} from {:?}; too long to hand over",
+                kind, src_id
+            );
+            self.drops.record(Reason::Oversize);
+            return Err(Reason::Oversize.into());
+        }
+
+        self.data
+            .copy_within(offset..offset + len, Envelope::HEADER_SIZE);
+        Envelope::write_header(&mut self.data[..], kind, src_id);
+        let res = self.send_content(
+            INTF::CPU,
+            &Message {
+                tgt_id: self.id,
+                src_id: self.id,
+                len: size,
+            },
+            Body::Data(0),
+        );
+        self.wipe(size);
+        res
+    }
+
+    /// Method which is used internally to handle a [diagnostic command](diag) from the CPU or the
+    /// FAA, which is already at the start of the data buffer, returning whether it was handled
+    ///
+    /// The response is sent back on the interface which the command was received on; responses to
+    /// the CPU are sent as from its own controller.
+    fn handle_diag(&mut self, intf: INTF, len: usize) -> bool {
+        let Some(command) = Command::from_bytes(&self.data[..len]) else {
+            warn!("Ignoring message from the CPU to its own controller");
+            return false;
+        };
+
+        debug!("Handling diagnostic command: {:?} {:?}", intf, command);
+
+        let len = match command {
+            Command::Drops => diag::respond_drops(self.tx, &self.drops),
+            Command::Stats => diag::respond_stats(self.tx, &self.traffic, &self.drops),
+            Command::SetLevel(level) => {
+                let level = level::set_max(level);
+                info!("Log level set to {:?}", level);
+                diag::respond_level(self.tx, level)
+            }
+            Command::Version => diag::respond_version(self.tx),
+            Command::Integrity => {
+                #[cfg(feature = "integrity")]
+                let integrity = Some(self.integrity.report());
+                #[cfg(not(feature = "integrity"))]
+                let integrity = None;
+                diag::respond_integrity(self.tx, integrity)
+            }
+        };
+
+        self.send_tx(
+            intf,
+            &Message {
+                tgt_id: if intf == INTF::CPU { self.id } else { Id::FAA },
+                src_id: self.id,
+                len,
+            },
+        )
+        .is_ok()
+    }
+
+    /// Method which is used internally to consume decrypted content of the update kind, should it
+    /// be from the updater's source, returning `None` should it be handed to the CPU instead
+    ///
+    /// The acknowledgement is sent back to the source as any other direct message, after which
+    /// the controller resets should the update have been committed (see the [update
+    /// module](crate::update)).
+    #[cfg(feature = "update")]
+    fn handle_update(&mut self, src_id: Id, offset: usize, len: usize) -> Option<Result<()>> {
+        let update = self
+            .update
+            .as_mut()
+            .filter(|update| src_id.ct_eq(update.source()))?;
+        let Some(frame) = Frame::from_bytes(&self.data[offset..][..len]) else {
+            warn!("Dropping malformed update frame from {:?}", src_id);
+            self.drop_from_peer(src_id, Reason::Malformed);
+            return Some(Err(Reason::Malformed.into()));
+        };
+        let commit = frame == Frame::Commit;
+        let ack = update.handle(frame);
+
+        debug!("Acknowledging update frame from {:?}: {:?}", src_id, ack);
+
+        // written where the content of a message from the CPU to the source would be read
+        let offset = self.compose(src_id, Kind::Update);
+        let len = ack.to_bytes(&mut self.data[offset..]);
+        let res = self.handle_scewl_send(src_id, len);
+
+        if commit && ack.status == Status::Ok {
+            info!("Resetting to apply the committed update");
+            self.board.reset();
+        }
+        Some(res)
+    }
+
+    /// Method which is used internally to send a heartbeat, should one be due
+    ///
+    /// Heartbeats to other SEDs are encrypted as any other direct message, so are skipped while the
+    /// controller is unregistered.
+    #[cfg(feature = "heartbeat")]
+    fn handle_heartbeat(&mut self) {
+        let Some(heartbeat) = self.heartbeat.as_mut() else {
+            return;
+        };
+        let Some(uptime) = heartbeat.poll() else {
+            return;
+        };
+        let target = heartbeat.target();
+
+        // written where the content of a message from the CPU to the target would be read
+        let offset = self.compose(target, Kind::Data);
+        #[allow(clippy::cast_possible_truncation)] // u32 seconds outlast the hardware
+        let len = Beat {
+            uptime: uptime.as_secs() as u32,
+            registered: self.registered(),
+            dropped: self.drops.total(),
+        }
+        .to_bytes(&mut self.data[offset..]);
+
+        debug!("Sending heartbeat to {:?}", target);
+
+        let _ignored = match target {
+            Id::FAA => self.handle_faa_send(len),
+            id @ Id::Other(_) if self.registered() => self.handle_scewl_send(id, len),
+            _ => Ok(()),
+        };
+    }
+
+    /// Method which is used internally to handle a frame from the SSS outside of (de)registration,
+    /// which may be the keys of a new epoch or the revoked devices pushed by the SSS, or a directive
+    /// from the test script
+    /// (see the [script module](crate::script))
+    fn handle_sss(&mut self) {
+        #[allow(clippy::cast_possible_truncation)]
+        // SCEWL_MAX_DATA_SZ is truncated appropriately
+        let Ok(msg) = self.read_sss(SCEWL_MAX_DATA_SZ as u16) else {
+            return;
+        };
+
+        #[cfg(feature = "scripted")]
+        if let Some(directive) = Directive::from_bytes(&self.data[..msg.len]) {
+            self.handle_script(directive);
+            return;
+        }
+
+        let op = (msg.len >= SSSMessage::size()).then(|| SSSMessage::from_bytes(self.data).op);
+        if op == Some(SSSOp::Rekey) && self.registered() {
+            self.handle_rekey(false);
+        } else if matches!(op, Some(SSSOp::Rotate | SSSOp::Revoke)) && self.registered() {
+            self.handle_push(msg.len);
+        } else {
+            warn!("Ignoring unexpected message from the SSS: {:?}", msg);
+        }
+        self.wipe(msg.len);
+    }
+
+    /// Method which is used internally to lend the authentication handler to one of its operations,
+    /// along with the controller, returning the handler to the controller once it completes
+    ///
+    /// The handler is only ever lent to one operation at a time, which cannot reach it through the
+    /// controller, so it is always present to be lent; should it not be, the operation is refused.
+    fn with_auth<R>(
+        &mut self,
+        op: impl FnOnce(&mut A, &mut Self) -> CoreResult<R, AuthError>,
+    ) -> CoreResult<R, AuthError> {
+        let Some(mut auth) = self.auth.take() else {
+            return Err(AuthError::Refused);
+        };
+        let res = op(&mut auth, self);
+        self.auth = Some(auth);
+        res
+    }
+
+    /// Method which is used internally to deregister with the SSS ahead of a fresh registration,
+    /// should the controller be registered
+    ///
+    /// The answer of the SSS to the deregistration is withheld from the CPU, which awaits only the
+    /// answer to its registration. Should the SSS not deregister this SED, it stays registered
+    /// under its existing keys, and the CPU is answered with [`Already`](SSSOp::Already) in the
+    /// SSS's stead, unless the SSS never answered, which
+    /// [`handle_registration`](Controller::handle_registration) answers itself.
+    fn tear_down(&mut self) -> CoreResult<(), AuthError> {
+        if !self.registered() {
+            return Ok(());
+        }
+        info!("Deregistering to register afresh");
+
+        self.withhold_sss = true;
+        let res = self.with_auth(A::sss_deregister);
+        self.withhold_sss = false;
+
+        match res {
+            Ok(()) => self.forget_registration(),
+            Err(AuthError::Controller(Error::TimedOut)) => {}
+            Err(_) => self.notify_cpu(SSSOp::Already),
+        }
+        res
+    }
+
+    /// Method which is used internally to hand the keys of a new epoch or the revoked devices,
+    /// pushed by the SSS while registered, to the authentication handler
+    fn handle_push(&mut self, len: usize) {
+        info!("Handling push from the SSS");
+
+        // pushes are authenticated with the secret, which is otherwise locked away while registered
+        self.board.unlock_secret();
+
+        let res = self.with_auth(|auth, controller| auth.sss_push(controller, len));
+
+        self.board.lock_secret();
+
+        #[allow(unused_variables)] // suppress warning for err when not in semihosting mode
+        if let Err(err) = res {
+            warn!("Push from the SSS failed: {}", err);
+        }
+    }
+
+    /// Method which is used internally to obtain fresh keys from the SSS while registered, as the
+    /// CPU requested, in which case it is notified of the outcome, as the SSS prompted, or as the
+    /// [session](crate::session) expired, returning whether the keys were refreshed
+    ///
+    /// The crypto handler built for the fresh keys replaces the controller's own in one step once
+    /// it is complete, so that frames are handled under the old keys up to the swap and under the
+    /// new keys after it; should the rekey fail, the old handler is kept. The controller stays
+    /// registered either way, and neither the peers met nor the revoked devices are forgotten.
+    fn handle_rekey(&mut self, requested: bool) -> bool {
+        info!("Rekeying with the SSS");
+
+        let res = if self.registered() {
+            // the secret is locked away while registered, but is proven to the SSS to rekey
+            self.board.unlock_secret();
+
+            let res = self.with_auth(A::sss_rekey);
+
+            self.board.lock_secret();
+            res
+        } else {
+            Err(AuthError::Refused)
+        };
+
+        let rekeyed = match res {
+            Ok(crypto) => {
+                self.crypto = Some(crypto);
+                if let Some(session) = self.session.as_mut() {
+                    session.restart();
+                }
+                true
+            }
+            #[allow(unused_variables)] // suppress warning for err when not in semihosting mode
+            Err(err) => {
+                warn!("Rekey failed: {}", err);
+                false
+            }
+        };
+
+        if requested {
+            self.notify_cpu(if rekeyed {
+                SSSOp::Rekey
+            } else {
+                SSSOp::Already
+            });
+        }
+
+        rekeyed
+    }
+
+    /// Method which is used internally to rekey with the SSS once the session has expired, should a
+    /// rekey be due (see the [session module](crate::session))
+    ///
+    /// This is checked before each message is read from the CPU, so that nothing more is sent
+    /// under the expired keys; should the rekey fail, the next is put off.
+    fn handle_expiry(&mut self) {
+        let due = self.registered() && self.session.as_ref().is_some_and(Session::due);
+        if !due {
+            return;
+        }
+
+        info!("Session expired");
+        if !self.handle_rekey(false) {
+            if let Some(session) = self.session.as_mut() {
+                session.defer();
+            }
+        }
+    }
+
+    /// Method which is used internally to keep alive with the SSS, should a keepalive be due (see
+    /// the [keepalive module](crate::secure::keepalive))
+    ///
+    /// Should the SSS answer that it no longer holds this SED registered, the controller forgets
+    /// its registration and notifies the CPU as though it had deregistered; should the SSS not
+    /// answer, the controller stays registered and keeps alive again next period.
+    fn handle_keepalive(&mut self) {
+        let due = self.registered() && self.next_keepalive.is_some_and(|next| next.passed());
+        if !due {
+            return;
+        }
+        self.next_keepalive = self
+            .keepalive
+            .map(|(clock, period)| Deadline::after(clock, period));
+
+        debug!("Keeping alive with the SSS");
+
+        // the answer is authenticated with the secret, which is otherwise locked away while
+        // registered
+        self.board.unlock_secret();
+
+        let res = self.with_auth(A::sss_keepalive);
+
+        match res {
+            Ok(true) => {}
+            Ok(false) => {
+                warn!("The SSS no longer holds this SED registered");
+                self.forget_registration();
+                self.peers.clear();
+                self.policy.clear_revoked();
+                self.notify_cpu(SSSOp::Deregister);
+            }
+            #[allow(unused_variables)] // suppress warning for err when not in semihosting mode
+            Err(err) => {
+                warn!("Keepalive with the SSS failed: {}", err);
+            }
+        }
+
+        if self.registered() {
+            self.board.lock_secret();
+        }
+    }
+
+    /// Method which is used internally to forget the crypto handler and everything else set up by
+    /// the SSS at registration, as on deregistration
+    ///
+    /// The crypto handler [erases](CryptoHandler::erase) its keys before it is dropped, as dropping
+    /// it alone would leave them in memory.
+    fn forget_registration(&mut self) {
+        if let Some(crypto) = self.crypto.as_mut() {
+            crypto.erase();
+        }
+        self.crypto = None;
+        self.header_crc = false;
+        self.handshakes = None;
+        self.next_keepalive = None;
+        self.policy.allow_all();
+        #[cfg(feature = "fragmentation")]
+        if let Some(reassembly) = self.reassembly.as_mut() {
+            reassembly.clear();
+        }
+    }
+
+    /// Method which is used internally to count a frame from another SED dropped for the given
+    /// reason, telling the CPU of it with the `drop-notices` feature (see the [diagnostics
+    /// module](diag))
+    #[cfg_attr(not(feature = "drop-notices"), allow(unused_variables))] // the source is only notified
+    fn drop_from_peer(&mut self, src_id: Id, reason: Reason) {
+        self.drops.record(reason);
+
+        #[cfg(feature = "drop-notices")]
+        {
+            let len = Notice { src_id, reason }.to_bytes(self.tx);
+            let sent = self.send_tx(
+                INTF::CPU,
+                &Message {
+                    tgt_id: self.id,
+                    src_id: self.id,
+                    len,
+                },
+            );
+            #[allow(unused_variables)] // suppress warning for err when not in semihosting mode
+            if let Err(err) = sent {
+                warn!("Could not notify the CPU of a drop: {:?} {}", src_id, err);
+            }
+        }
+    }
+
+    /// Method which is used internally to answer a request of the CPU to the SSS on the SSS's
+    /// behalf, with the given operation
+    fn notify_cpu(&mut self, op: SSSOp) {
+        let notify = SSSMessage {
+            dev_id: self.id,
+            op,
+        };
+        self.tx[..SSSMessage::size()].copy_from_slice(&notify.to_bytes());
+        let sent = self.send_tx(
+            INTF::CPU,
+            &Message {
+                tgt_id: self.id,
+                src_id: Id::SSS,
+                len: SSSMessage::size(),
+            },
+        );
+        #[allow(unused_variables)] // suppress warning for err when not in semihosting mode
+        if let Err(err) = sent {
+            warn!("Could not notify the CPU: {:?} {}", op, err);
+        }
+    }
+
+    /// Method which is used internally to handle a directive from the test script
+    #[cfg(feature = "scripted")]
+    fn handle_script(&mut self, directive: Directive) {
+        debug!("Handling script directive: {:?}", directive);
+
+        let mut report = None;
+        let ok = match directive {
+            Directive::Inject {
+                intf,
+                src_id,
+                tgt_id,
+                len,
+            } => {
+                // placed exactly where the frame would have been read from the interface
+                let offset = if intf == INTF::CPU {
+                    self.compose(tgt_id, Kind::Data)
+                } else {
+                    0
+                };
+                self.data.copy_within(
+                    Directive::INJECT_OFFSET..Directive::INJECT_OFFSET + len,
+                    offset,
+                );
+                self.inject(intf, src_id, tgt_id, len)
+            }
+            Directive::Query => {
+                report = Some(Report::State(State {
+                    id: self.id,
+                    registered: self.registered(),
+                    faults: self.faults,
+                }));
+                true
+            }
+            Directive::Fault(fault) => {
+                self.faults.arm(fault);
+                true
+            }
+            Directive::Crash => {
+                report = self.board.crash().map(Report::Crash);
+                report.is_some()
+            }
+        };
+
+        let len = script::respond(self.tx, directive.op(), ok, report);
+        let _ignored = self.send_tx(
+            INTF::SSS,
+            &Message {
+                tgt_id: Id::SSS,
+                src_id: self.id,
+                len,
+            },
+        );
+    }
+
+    /// Method which is used internally to handle a frame injected by the test script, which is
+    /// already at the start of the data buffer, as though it had been read from the given interface
+    #[cfg(feature = "scripted")]
+    fn inject(&mut self, intf: INTF, src_id: Id, tgt_id: Id, len: usize) -> bool {
+        match intf {
+            INTF::CPU => self.dispatch_cpu(Message {
+                tgt_id,
+                src_id: self.id,
+                len,
+            }),
+            INTF::RAD => {
+                let msg = Message {
+                    tgt_id,
+                    src_id,
+                    len,
+                };
+
+                // injected frames are verified exactly as those read from the radio
+                let verified = src_id == Id::FAA
+                    || match self.crypto.as_mut() {
+                        Some(crypto) if len >= crypto.verification_len() => {
+                            match crypto.verify(self.data, msg, self.scratch) {
+                                Ok(()) => true,
+                                Err(reason) => {
+                                    self.board.delay(crypto.jitter());
+                                    self.drops.record(reason);
+                                    false
+                                }
+                            }
+                        }
+                        _ => false,
+                    };
+
+                verified && self.registered() && self.dispatch_rad(msg)
+            }
+            INTF::SSS => false,
+        }
+    }
+
+    /// Queues a frame for the radio, made up of the given parts, writing the queue out to the
+    /// radio until there is room for it; returns false, for the frame to be written out at once,
+    /// should there be no queue, or the frame not fit even in an empty one
+    #[cfg(feature = "prioritized")]
+    fn enqueue(
+        outbound: Option<&mut Outbound>,
+        rad: &mut Timed<'_, T>,
+        class: Class,
+        parts: &[&[u8]],
+    ) -> bool {
+        let Some(outbound) = outbound else {
+            return false;
+        };
+
+        while !outbound.fits(parts.iter().map(|part| part.len()).sum()) {
+            let Some(frame) = outbound.front() else {
+                return false;
+            };
+            let n = frame.len();
+            rad.write(frame);
+            outbound.consume(n);
+        }
+
+        outbound.push(class, parts)
+    }
+
+    /// Writes the next [burst](outbound::BURST) of the queued frames out to the radio
+    #[cfg(feature = "prioritized")]
+    fn handle_outbound(&mut self) {
+        let Some(outbound) = self.outbound.as_mut() else {
+            return;
+        };
+
+        if let Some(frame) = outbound.front() {
+            let n = frame.len().min(outbound::BURST);
+            self.links.rad.write(&frame[..n]);
+            outbound.consume(n);
+        }
+    }
+
+    /// Determines whether the run loop has work waiting, i.e. a message to read or, with the
+    /// `prioritized` feature, a frame to write out
+    ///
+    /// A firmware which schedules the controller itself, rather than handing it the run loop,
+    /// [polls](Controller::poll) it for as long as this holds.
+    pub fn pending(&self) -> bool {
+        let pending = self.links.sss.avail()
+            || self.links.cpu.avail()
+            || (self.registered() && self.links.rad.avail());
+        #[cfg(feature = "prioritized")]
+        let pending = pending
+            || self
+                .outbound
+                .as_ref()
+                .is_some_and(|queue| !queue.is_empty());
+        pending
+    }
+
+    /// Makes a single pass of the [run loop](Controller::run), handling whatever work is waiting
+    /// without waiting for more
+    ///
+    /// The SSS is serviced first, then the timed work of the loop, then at most one message from
+    /// the CPU or, should the CPU have sent none, from the radio.
+    pub fn poll(&mut self) {
+        #[cfg(feature = "heartbeat")]
+        self.handle_heartbeat();
+
+        #[cfg(feature = "integrity")]
+        self.integrity.poll();
+
+        if self.links.sss.avail() {
+            self.handle_sss();
+        }
+
+        self.handle_expiry();
+        self.handle_keepalive();
+
+        #[cfg(feature = "prioritized")]
+        self.handle_outbound();
+
+        if self.links.cpu.avail() {
+            #[allow(clippy::cast_possible_truncation)]
+            // SCEWL_MAX_DATA_SZ is truncated appropriately
+            if let Ok(msg) = self.read_msg(INTF::CPU, SCEWL_MAX_DATA_SZ as u16) {
+                let _ignored = self.dispatch_cpu(msg);
+                return;
+            }
+        }
+
+        if self.registered() && self.links.rad.avail() {
+            if let Ok(msg) = self.read_msg(INTF::RAD, self.mtu) {
+                let _ignored = self.dispatch_rad(msg);
+            }
+        }
+    }
+
+    /// The run loop for the controller, which will never terminate
+    ///
+    /// This method is a near-exact port of the C implementation's main method, with changes for
+    /// expressions that are more idiomatic for Rust; each pass of the loop is a
+    /// [poll](Controller::poll), after [kicking](Board::kick) the watchdog of the board.
+    ///
+    /// Each pass first lets the board [idle](Board::idle) until the next interrupt, unless the loop
+    /// has [work waiting](Controller::pending); the firmware only sleeps with its
+    /// `interrupt-driven` feature, and as SysTick then interrupts every millisecond, the timed work
+    /// of the loop (heartbeats, keepalives, the integrity check) is still done at least that often.
+    pub fn run(&mut self) -> ! {
+        loop {
+            self.board.kick();
+
+            let board = self.board;
+            board.idle(&|| self.pending());
+
+            self.poll();
+        }
+    }
+}
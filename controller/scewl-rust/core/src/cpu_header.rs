@@ -0,0 +1,312 @@
+//! A C header describing the status frames which the controller sends to the CPU, generated from
+//! the very definitions the firmware uses
+//!
+//! The CPU-side application code parses the controller's notifications (the [boot
+//! banner](crate::banner), [heartbeats](crate::heartbeat), [diagnostic](crate::diag) responses,
+//! [envelopes](crate::content), and [legacy frames](crate::legacy)) through `include/scewl_status.h`, rather than through
+//! struct mirrors maintained by hand. The header is [written](write) by this module, and checked
+//! in; the `cpu_header` host test fails should it be stale, and rewrites it with `SCEWL_BLESS=1`:
+//!
+//! ```text
+//! SCEWL_BLESS=1 cargo test -p scewl-core --test cpu_header --features std,codec --target x86_64-unknown-linux-gnu
+//! ```
+//!
+//! Every multi-byte field is little-endian, as throughout the [codec](crate::codec), and the
+//! structs are packed; the header asserts their sizes against those of the firmware.
+
+use core::fmt::{Debug, Result as FmtResult, Write};
+
+use crate::content::{self, Envelope, Kind};
+use crate::diag::{self, Command, Drops, Integrity, Notice, Reason, Tamper, Traffic};
+use crate::heartbeat::{self, Beat};
+use crate::level::Level;
+use crate::policy::Link;
+use crate::port::{self, Control};
+use crate::{banner, legacy};
+
+/// Writes the header to the output
+pub fn write(out: &mut impl Write) -> FmtResult {
+    writeln!(
+        out,
+        "/*\n * Status frames sent by the SCEWL controller to the CPU\n *\n * Generated by \
+         src/cpu_header.rs; do not edit. Every multi-byte field is little-endian.\n */\n"
+    )?;
+    writeln!(out, "#ifndef SCEWL_STATUS_H\n#define SCEWL_STATUS_H\n")?;
+    writeln!(out, "#include <stdint.h>\n")?;
+
+    writeln!(
+        out,
+        "/* the boot banner: the magic, followed by the banner's text */"
+    )?;
+    magic(out, "SCEWL_BANNER_MAGIC", &banner::MAGIC)?;
+    writeln!(
+        out,
+        "/* the end of the banner's text, should the watchdog have reset the controller */"
+    )?;
+    magic(out, "SCEWL_BANNER_WATCHDOG", banner::WATCHDOG.as_bytes())?;
+
+    writeln!(
+        out,
+        "/* the body of a legacy frame, accepted unauthenticated in mixed mode */"
+    )?;
+    magic(out, "SCEWL_LEGACY_MAGIC", &legacy::MAGIC)?;
+
+    writeln!(
+        out,
+        "/* a heartbeat, sent to the FAA or a monitoring SED */"
+    )?;
+    magic(out, "SCEWL_HEARTBEAT_MAGIC", &heartbeat::MAGIC)?;
+    writeln!(
+        out,
+        "typedef struct __attribute__((packed)) scewl_heartbeat_t {{\n  char magic[{}];\n  \
+         uint32_t uptime;\n  uint8_t registered;\n  uint32_t dropped;\n}} scewl_heartbeat_t;",
+        heartbeat::MAGIC.len()
+    )?;
+    size_assert(out, "scewl_heartbeat_t", Beat::size())?;
+
+    diag_responses(out)?;
+
+    writeln!(
+        out,
+        "/* logical ports: the body of an envelope to or from a port, and a request to open or close \
+         one */"
+    )?;
+    magic(out, "SCEWL_PORT_MAGIC", &port::MAGIC)?;
+    writeln!(
+        out,
+        "typedef struct __attribute__((packed)) scewl_port_hdr_t {{\n  uint8_t port;\n  /* the \
+         data follows */\n}} scewl_port_hdr_t;"
+    )?;
+    size_assert(out, "scewl_port_hdr_t", port::HEADER_SIZE)?;
+    enumeration(
+        out,
+        "scewl_port_op",
+        [("CLOSE", Control::Close(0)), ("OPEN", Control::Open(0))]
+            .iter()
+            .map(|&(name, control)| (name, control.op())),
+    )?;
+    writeln!(
+        out,
+        "typedef struct __attribute__((packed)) scewl_port_control_t {{\n  char magic[{}];\n  \
+         uint8_t op;\n  uint8_t port;\n}} scewl_port_control_t;",
+        port::MAGIC.len()
+    )?;
+    size_assert(out, "scewl_port_control_t", Control::size())?;
+
+    writeln!(
+        out,
+        "/* an envelope between the CPU and its own controller, of content to or from a peer */"
+    )?;
+    magic(out, "SCEWL_ENVELOPE_MAGIC", &content::MAGIC)?;
+    enumeration(
+        out,
+        "scewl_content_kind",
+        [("UPDATE", Kind::Update), ("PORT", Kind::Port)]
+            .iter()
+            .map(|&(name, kind)| (name, kind.into())),
+    )?;
+    writeln!(
+        out,
+        "typedef struct __attribute__((packed)) scewl_envelope_hdr_t {{\n  char magic[{}];\n  \
+         uint8_t kind; /* an enum scewl_content_kind */\n  uint16_t peer;\n  /* the body follows \
+         */\n}} scewl_envelope_hdr_t;",
+        content::MAGIC.len()
+    )?;
+    size_assert(out, "scewl_envelope_hdr_t", Envelope::HEADER_SIZE)?;
+
+    writeln!(
+        out,
+        "/* a notice of a frame from another SED dropped, with the drop-notices feature */"
+    )?;
+    magic(out, "SCEWL_DROP_NOTICE_MAGIC", &diag::NOTICE_MAGIC)?;
+    writeln!(
+        out,
+        "typedef struct __attribute__((packed)) scewl_drop_notice_t {{\n  char magic[{}];\n  \
+         uint16_t src_id;\n  uint8_t reason; /* an enum scewl_drop_reason */\n}} \
+         scewl_drop_notice_t;",
+        diag::NOTICE_MAGIC.len()
+    )?;
+    size_assert(out, "scewl_drop_notice_t", Notice::size())?;
+
+    writeln!(out, "#endif /* SCEWL_STATUS_H */")
+}
+
+/// Writes the definitions of the diagnostic commands and their responses
+fn diag_responses(out: &mut impl Write) -> FmtResult {
+    writeln!(
+        out,
+        "/* diagnostic commands, and the header of their responses */"
+    )?;
+    magic(out, "SCEWL_DIAG_MAGIC", &diag::MAGIC)?;
+    let commands = [
+        ("DROPS", Command::Drops),
+        ("SET_LEVEL", Command::SetLevel(Level::Off)),
+        ("VERSION", Command::Version),
+        ("INTEGRITY", Command::Integrity),
+        ("STATS", Command::Stats),
+    ];
+    enumeration(
+        out,
+        "scewl_diag_op",
+        commands.iter().map(|&(name, command)| (name, command.op())),
+    )?;
+    writeln!(
+        out,
+        "typedef struct __attribute__((packed)) scewl_diag_resp_t {{\n  char magic[{}];\n  \
+         uint8_t op;\n  uint8_t status; /* 0 on success */\n  /* the result follows */\n}} \
+         scewl_diag_resp_t;",
+        diag::MAGIC.len()
+    )?;
+    size_assert(out, "scewl_diag_resp_t", diag::MAGIC.len() + 2)?;
+
+    writeln!(
+        out,
+        "/* the result of a drops command: the number of frames dropped for each reason */"
+    )?;
+    enumeration(
+        out,
+        "scewl_drop_reason",
+        Reason::ALL
+            .iter()
+            .map(|&reason| (screaming(reason), reason as u8)),
+    )?;
+    writeln!(out, "#define SCEWL_DROP_COUNT {}\n", Reason::COUNT)?;
+    writeln!(
+        out,
+        "typedef struct __attribute__((packed)) scewl_drops_t {{\n  \
+         uint32_t counts[SCEWL_DROP_COUNT];\n}} scewl_drops_t;"
+    )?;
+    size_assert(out, "scewl_drops_t", Drops::size())?;
+
+    writeln!(out, "/* the argument and result of a set level command */")?;
+    enumeration(
+        out,
+        "scewl_level",
+        (0..=u8::MAX)
+            .filter_map(Level::from_u8)
+            .map(|level| (screaming(level), level as u8)),
+    )?;
+
+    writeln!(out, "/* the result of an integrity command */")?;
+    enumeration(
+        out,
+        "scewl_tamper",
+        Tamper::ALL
+            .iter()
+            .map(|&state| (screaming(state), state as u8)),
+    )?;
+    writeln!(
+        out,
+        "typedef struct __attribute__((packed)) scewl_integrity_t {{\n  uint8_t state;\n  \
+         uint32_t checks;\n}} scewl_integrity_t;"
+    )?;
+    size_assert(out, "scewl_integrity_t", Integrity::size())?;
+
+    writeln!(
+        out,
+        "/* the result of a stats command: the traffic over each link, then a scewl_drops_t */"
+    )?;
+    enumeration(
+        out,
+        "scewl_link",
+        Link::ALL.iter().map(|&link| (screaming(link), link as u8)),
+    )?;
+    writeln!(out, "#define SCEWL_LINK_COUNT {}\n", Link::COUNT)?;
+    writeln!(
+        out,
+        "typedef struct __attribute__((packed)) scewl_link_stats_t {{\n  uint32_t frames_in;\n  \
+         uint32_t bytes_in;\n  uint32_t frames_out;\n  uint32_t bytes_out;\n}} \
+         scewl_link_stats_t;"
+    )?;
+    size_assert(out, "scewl_link_stats_t", Traffic::size() / Link::COUNT)?;
+    writeln!(
+        out,
+        "typedef struct __attribute__((packed)) scewl_stats_t {{\n  \
+         scewl_link_stats_t links[SCEWL_LINK_COUNT];\n}} scewl_stats_t;"
+    )?;
+    size_assert(out, "scewl_stats_t", Traffic::size())?;
+    Ok(())
+}
+
+/// Writes the definitions of a magic, as a string and as its length
+fn magic(out: &mut impl Write, name: &str, magic: &[u8]) -> FmtResult {
+    write!(out, "#define {name} \"")?;
+    for &byte in magic {
+        if byte.is_ascii_graphic() || byte == b' ' {
+            write!(out, "{}", char::from(byte))?;
+        } else {
+            write!(out, "\\x{byte:02x}")?;
+        }
+    }
+    writeln!(out, "\"\n#define {}_LEN {}\n", name, magic.len())
+}
+
+/// Writes an enumeration of the given names (which are prefixed by that of the enumeration) and
+/// values
+fn enumeration<N: AsRef<str>>(
+    out: &mut impl Write,
+    name: &str,
+    variants: impl Iterator<Item = (N, u8)>,
+) -> FmtResult {
+    let mut prefix = Name::default();
+    Screaming(&mut prefix).write_str(name)?;
+    writeln!(out, "enum {name} {{")?;
+    for (variant, value) in variants {
+        writeln!(
+            out,
+            "  {}_{} = {},",
+            prefix.as_ref(),
+            variant.as_ref(),
+            value
+        )?;
+    }
+    writeln!(out, "}};\n")
+}
+
+/// Writes a static assertion of the size of a struct
+fn size_assert(out: &mut impl Write, name: &str, size: usize) -> FmtResult {
+    writeln!(
+        out,
+        "_Static_assert(sizeof({name}) == {size}, \"{name} is {size} bytes\");\n"
+    )
+}
+
+/// The name of a variant, converted from its `Debug` form to `SCREAMING_SNAKE_CASE`
+fn screaming(variant: impl Debug) -> Name {
+    let mut name = Name::default();
+    let _ = write!(Screaming(&mut name), "{variant:?}");
+    name
+}
+
+/// A short name, held inline as the header is written without allocating
+#[derive(Default)]
+struct Name {
+    /// The bytes of the name, all ASCII
+    bytes: [u8; 32],
+    /// The length of the name
+    len: usize,
+}
+
+impl AsRef<str> for Name {
+    fn as_ref(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+}
+
+/// A writer which converts CamelCase to `SCREAMING_SNAKE_CASE` as it appends to a name
+struct Screaming<'a>(&'a mut Name);
+
+impl Write for Screaming<'_> {
+    fn write_str(&mut self, s: &str) -> FmtResult {
+        for c in s.chars() {
+            let name = &mut *self.0;
+            if c.is_ascii_uppercase() && name.len != 0 {
+                *name.bytes.get_mut(name.len).ok_or(core::fmt::Error)? = b'_';
+                name.len += 1;
+            }
+            *name.bytes.get_mut(name.len).ok_or(core::fmt::Error)? = c.to_ascii_uppercase() as u8;
+            name.len += 1;
+        }
+        Ok(())
+    }
+}
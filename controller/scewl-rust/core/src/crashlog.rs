@@ -0,0 +1,151 @@
+//! The record of a panic, which the firmware persists across the reset which follows it
+//!
+//! Without semihosting, a panic would otherwise leave no trace at all: the controller simply stops
+//! (or, with the panic handler in the firmware entrypoint, resets). Instead, the panic handler
+//! [records](Crash::new) the panic in the persistent region of RAM, from which the firmware collects
+//! it at boot and [offers](crate::board::Board::crash) it to the controller (e.g. to be reported by
+//! a diagnostic command) for the rest of that boot.
+//!
+//! Messages and file names which exceed the space reserved for them are truncated. With the `stripped`
+//! feature, only constant messages (such as those of [fatal errors](crate::fatal)) are recorded, as
+//! formatting any other would link `core::fmt`.
+
+#[cfg(not(feature = "stripped"))]
+use core::fmt::Write;
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use core::panic::PanicInfo;
+use core::str;
+
+/// The maximum length of the recorded file name, beyond which it is truncated
+const FILE_LEN: usize = 64;
+
+/// The maximum length of the recorded message, beyond which it is truncated
+const MESSAGE_LEN: usize = 128;
+
+/// The record of a panic
+///
+/// Its layout is fixed, as the firmware keeps it in a region of RAM which survives resets.
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct Crash {
+    /// The line on which the panic occurred
+    line: u32,
+    /// The column at which the panic occurred
+    column: u32,
+    /// The length of the file name
+    file_len: u32,
+    /// The length of the message
+    message_len: u32,
+    /// The name of the file in which the panic occurred, truncated to fit
+    file: [u8; FILE_LEN],
+    /// The panic message, truncated to fit
+    message: [u8; MESSAGE_LEN],
+}
+
+impl Crash {
+    /// Records the given panic, truncating its file name and message to fit
+    #[allow(clippy::cast_possible_truncation)] // lengths are bounded by the buffers
+    pub fn new(info: &PanicInfo<'_>) -> Self {
+        let mut crash = Crash {
+            line: 0,
+            column: 0,
+            file_len: 0,
+            message_len: 0,
+            file: [0; FILE_LEN],
+            message: [0; MESSAGE_LEN],
+        };
+
+        if let Some(location) = info.location() {
+            crash.line = location.line();
+            crash.column = location.column();
+
+            let file = location.file().as_bytes();
+            let len = file.len().min(FILE_LEN);
+            crash.file[..len].copy_from_slice(&file[..len]);
+            crash.file_len = len as u32;
+        }
+
+        #[cfg(not(feature = "stripped"))]
+        {
+            let mut message = Truncating {
+                buf: &mut crash.message,
+                len: 0,
+            };
+            let _ignored = write!(message, "{}", info.message());
+            crash.message_len = message.len as u32;
+        }
+        #[cfg(feature = "stripped")]
+        if let Some(message) = info.message().as_str() {
+            let len = message.len().min(MESSAGE_LEN);
+            crash.message[..len].copy_from_slice(&message.as_bytes()[..len]);
+            crash.message_len = len as u32;
+        }
+
+        crash
+    }
+
+    /// The line on which the panic occurred
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// The column at which the panic occurred
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+
+    /// The name of the file in which the panic occurred, which may have been truncated
+    pub fn file(&self) -> &str {
+        utf8_prefix(&self.file[..self.file_len as usize])
+    }
+
+    /// The panic message, which may have been truncated
+    pub fn message(&self) -> &str {
+        utf8_prefix(&self.message[..self.message_len as usize])
+    }
+
+    /// Determines whether this record is well-formed, as RAM is arbitrary after power-on
+    pub fn valid(&self) -> bool {
+        self.file_len as usize <= FILE_LEN && self.message_len as usize <= MESSAGE_LEN
+    }
+}
+
+impl Display for Crash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "panicked at {}:{}:{}: {}",
+            self.file(),
+            self.line,
+            self.column,
+            self.message()
+        )
+    }
+}
+
+/// The longest prefix of the bytes which is valid UTF-8, as truncation may split a character
+fn utf8_prefix(bytes: &[u8]) -> &str {
+    str::from_utf8(bytes).unwrap_or_else(|e| {
+        // SAFETY: the bytes up to valid_up_to are valid UTF-8, by definition
+        unsafe { str::from_utf8_unchecked(&bytes[..e.valid_up_to()]) }
+    })
+}
+
+/// A formatter which writes into a fixed buffer, silently truncating what does not fit
+#[cfg(not(feature = "stripped"))]
+struct Truncating<'a> {
+    /// The buffer written to
+    buf: &'a mut [u8],
+    /// The number of bytes written so far
+    len: usize,
+}
+
+#[cfg(not(feature = "stripped"))]
+impl Write for Truncating<'_> {
+    fn write_str(&mut self, s: &str) -> FmtResult {
+        let n = s.len().min(self.buf.len() - self.len);
+        self.buf[self.len..][..n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
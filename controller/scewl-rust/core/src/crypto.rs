@@ -0,0 +1,264 @@
+//! Defines the trait for the crypto handler, which encrypts direct messages to other SEDs and
+//! broadcasts. This is segmented out in its own module mostly for visual clarity.
+//!
+//! See [Handler](Handler) for details on how crypto handlers should be defined.
+//!
+//! The controller is ordinarily generic over its crypto handler, and so is monomorphised for each
+//! handler family. With the `dyn-handlers` feature, registration instead [installs](Slot::install)
+//! the handler in a static slot, and the controller holds it as a `&mut dyn Handler`; the
+//! controller is then monomorphised once, trading a vtable call per operation for flash. This only
+//! pays off in an image which instantiates the controller with several handler families; the
+//! firmware selects exactly one, for which the vtable and the forwarding cost slightly more flash
+//! than they save. The handler then lives in `.bss` rather than on the stack.
+
+#[cfg(feature = "dyn-handlers")]
+use core::cell::UnsafeCell;
+
+use crate::codec::{Id, Message};
+use crate::diag::Reason;
+use crate::scratch::Pool;
+
+/// The error returned by a failed crypto operation, which is the [reason](Reason) that the message
+/// is dropped
+///
+/// Callers conventionally import this as `CryptoError`, alongside `CryptoHandler`. The reasons
+/// which handlers return are chiefly [`BadMac`](Reason::BadMac), [`Replay`](Reason::Replay),
+/// [`BadPadding`](Reason::BadPadding), [`BadLength`](Reason::BadLength), and
+/// [`NoKey`](Reason::NoKey); the controller logs each, and counts it in the
+/// [diagnostics](crate::diag) reported to the CPU.
+pub type Error = Reason;
+
+/// Defines the basic methods for decrypting/encrypting messages to/from the CPU and radio where
+/// appropriate.
+///
+/// Your implementation should ensure that:
+///  - messages are encrypted/decrypted uniformly (the data buffer will hold the exactly same
+///    content at the [content offset](Handler::content_offset) between cleartext and encryption +
+///    decryption), without moving the content within the buffer
+///  - [`encrypt`](Handler::encrypt) writes the verification for the message _first_ so that it may
+///    be extracted and used to verify that a message is legitimate in [`verify`](Handler::verify).
+///  - broadcasts are handled differently than direct messages, where appropriate for the encryption
+///    mechanism chosen
+///  - the internal state of the crypto handler is updated, where appropriate for the encryption
+///    mechanism chosen
+///
+/// The data buffer is lent as a slice rather than as the controller's buffer, so that a handler is
+/// not tied to one buffer size. It is at least as long as the frame, and on encryption has room for
+/// the handler's overhead; a handler should refuse a frame whose `msg.len` exceeds the buffer as a
+/// [bad length](Reason::BadLength) rather than panic.
+///
+/// Each operation is lent the controller's [scratch pool](crate::scratch), from which temporaries
+/// larger than a few words (e.g. the input to a hash) should be borrowed rather than built on the
+/// stack.
+pub trait Handler {
+    /// Verifies that a message is correct before continuing to read the message
+    ///
+    /// Your implementation should read the verification header, the length of which is described in
+    /// [`verification_len`](Handler::verification_len) to ensure that a message is legitimate. If
+    /// the message is not legitimate, return the [reason](Reason) and the controller will drop the
+    /// remainder of the message, counting it against that reason. If it is, return `Ok`, and the
+    /// controller will read the rest of the message and pass the message onto the encryption
+    /// handler for further processing.
+    fn verify(&mut self, data: &[u8], msg: Message, scratch: &Pool) -> Result<(), Error>;
+    /// Defines the length of the verification header to be read
+    ///
+    /// This length will be used to inform the controller of how large the verification header is
+    /// on the message. If no verification header is present, simply return 0.
+    fn verification_len(&self) -> usize;
+
+    /// Defines the offset in the data buffer at which the content of a message lies, both as
+    /// given to [`encrypt`](Handler::encrypt) and as left by [`decrypt`](Handler::decrypt)
+    ///
+    /// This is the size of everything the handler prepends to the content (e.g. its verification
+    /// and content headers). The controller reads content which is to be encrypted directly to
+    /// this offset, and sends decrypted content directly from it, so that the content need never
+    /// be shifted within the buffer. If nothing is prepended, simply return 0.
+    fn content_offset(&self) -> usize;
+    /// Encrypts a message which is outbound to the radio and is not an FAA message
+    ///
+    /// The content of the message lies at [`content_offset`](Handler::content_offset), and is
+    /// `msg.len` bytes long. Your implementation should modify the data structure in-place such
+    /// that the frame, from the start of the buffer, may be immediately sent to the receiving
+    /// SED(s) and immediately decrypted upon reception. The return value should be the length of
+    /// the frame.
+    ///
+    /// This operation must always succeed.
+    fn encrypt(&mut self, data: &mut [u8], msg: Message, scratch: &Pool) -> usize;
+    /// Decrypts a message which is inbound on the radio and is not an FAA message
+    ///
+    /// The frame lies at the start of the buffer, and is `msg.len` bytes long. Your implementation
+    /// should modify the data structure in-place such that the content, at
+    /// [`content_offset`](Handler::content_offset), may be immediately sent to the CPU. The return
+    /// value should be the length of the content.
+    ///
+    /// This operation may fail in the case that decryption (or any other form of message
+    /// verification) fails, in which case the [reason](Reason) is returned.
+    fn decrypt(&mut self, data: &mut [u8], msg: Message, scratch: &Pool) -> Result<usize, Error>;
+
+    /// Defines the length of the frame which [`encrypt`](Handler::encrypt) would make of the
+    /// message in a data buffer of `room` bytes, without encrypting it
+    ///
+    /// The controller checks this against the MTU of the target before encrypting, so that a
+    /// message too large to send uses up none of the handler's state (e.g. its counters). The
+    /// default, for handlers which only prepend to the content, is the content offset and the
+    /// content.
+    fn sealed_len(&self, msg: Message, room: usize) -> usize {
+        let _ = room;
+        self.content_offset() + msg.len
+    }
+
+    /// Defines the size of the blocks in which the handler can decrypt a frame as it is received,
+    /// or 0 (the default) should it only decrypt frames whole
+    ///
+    /// Should this be nonzero, the controller reads the rest of each frame which passes
+    /// [`verify`](Handler::verify) in blocks of this size, handing each to
+    /// [`decrypt_received`](Handler::decrypt_received) as soon as it arrives, so that decryption
+    /// overlaps reception rather than following it.
+    fn stream_block(&self) -> usize {
+        0
+    }
+    /// Decrypts, in place, the part of a frame which has been received so far, i.e. the frame
+    /// up to `received` bytes from the start of the buffer
+    ///
+    /// This is only called between [`verify`](Handler::verify) succeeding and
+    /// [`decrypt`](Handler::decrypt) for the same frame, with `received` growing by
+    /// [`stream_block`](Handler::stream_block) bytes each time (bar the last, which may be
+    /// shorter). Whatever is decrypted here must not be decrypted again by `decrypt`, which is
+    /// still called once the frame has arrived in full to finish decryption and check the
+    /// content; a frame may also be decrypted by `decrypt` alone, should this never be called.
+    fn decrypt_received(&mut self, data: &mut [u8], received: usize) {
+        let _ = (data, received);
+    }
+
+    /// Draws a random delay, in core clock cycles, which the controller waits out before acting
+    /// on a failed [`verify`](Handler::verify) or [`decrypt`](Handler::decrypt), or 0 (the
+    /// default) should the handler not jitter its failures
+    ///
+    /// The checks which a frame may fail (e.g. its counter, MAC, padding, or hash) take different
+    /// times, so without jitter the time taken to drop a frame tells an attacker on the radio
+    /// which check it failed.
+    fn jitter(&mut self) -> u32 {
+        0
+    }
+
+    /// Keys the direct messages exchanged with the given peer with the secret agreed with it in a
+    /// [handshake](crate::secure::Handshakes), or with the deployment's keys again should it be
+    /// `None`; handlers without keys per peer (the default) ignore this
+    ///
+    /// The secret is the raw output of the key agreement, which the handler must derive keys from
+    /// rather than use directly.
+    fn rekey(&mut self, peer: Id, secret: Option<&[u8; 32]>) {
+        let _ = (peer, secret);
+    }
+
+    /// Takes up the deployment's keys of a new epoch, as pushed by the SSS, returning whether it
+    /// did; handlers without keys (the default) refuse them
+    ///
+    /// The keys of an epoch no higher than the handler's own are refused. Otherwise, the handler
+    /// protects the frames it sends under the new keys thereafter, but still accepts frames under
+    /// the keys it held before, as its peers may not have taken up the new keys yet.
+    fn rotate(&mut self, epoch: u32, aes_key: &[u8; 16], hmac_key: &[u8; 64]) -> bool {
+        let _ = (epoch, aes_key, hmac_key);
+        false
+    }
+
+    /// Tags the nonce of the challenge of a deregistration under the deployment's keys held by
+    /// the handler, attesting that the SED of the given id held them before it
+    /// [erases](Handler::erase) them (see the [erasure module](crate::secure::erasure)), and
+    /// returns the tag with the epoch of the keys; handlers without keys (the default) cannot
+    fn attest(&mut self, nonce: &[u8; 32], dev_id: Id) -> Option<(u32, [u8; 32])> {
+        let _ = (nonce, dev_id);
+        None
+    }
+
+    /// Erases the keys held by the handler, as on deregistration, after which it is only dropped;
+    /// handlers without keys (the default) have nothing to erase
+    fn erase(&mut self) {}
+}
+
+/// Lets a reference to a crypto handler, notably a `&mut dyn Handler`, be used as the handler
+#[cfg(feature = "dyn-handlers")]
+impl<H: Handler + ?Sized> Handler for &mut H {
+    fn verify(&mut self, data: &[u8], msg: Message, scratch: &Pool) -> Result<(), Error> {
+        (**self).verify(data, msg, scratch)
+    }
+
+    fn verification_len(&self) -> usize {
+        (**self).verification_len()
+    }
+
+    fn content_offset(&self) -> usize {
+        (**self).content_offset()
+    }
+
+    fn encrypt(&mut self, data: &mut [u8], msg: Message, scratch: &Pool) -> usize {
+        (**self).encrypt(data, msg, scratch)
+    }
+
+    fn decrypt(&mut self, data: &mut [u8], msg: Message, scratch: &Pool) -> Result<usize, Error> {
+        (**self).decrypt(data, msg, scratch)
+    }
+
+    fn sealed_len(&self, msg: Message, room: usize) -> usize {
+        (**self).sealed_len(msg, room)
+    }
+
+    fn stream_block(&self) -> usize {
+        (**self).stream_block()
+    }
+
+    fn decrypt_received(&mut self, data: &mut [u8], received: usize) {
+        (**self).decrypt_received(data, received);
+    }
+
+    fn jitter(&mut self) -> u32 {
+        (**self).jitter()
+    }
+
+    fn rekey(&mut self, peer: Id, secret: Option<&[u8; 32]>) {
+        (**self).rekey(peer, secret);
+    }
+
+    fn rotate(&mut self, epoch: u32, aes_key: &[u8; 16], hmac_key: &[u8; 64]) -> bool {
+        (**self).rotate(epoch, aes_key, hmac_key)
+    }
+
+    fn attest(&mut self, nonce: &[u8; 32], dev_id: Id) -> Option<(u32, [u8; 32])> {
+        (**self).attest(nonce, dev_id)
+    }
+
+    fn erase(&mut self) {
+        (**self).erase();
+    }
+}
+
+/// A static slot in which registration installs a crypto handler, so that the controller may hold
+/// it as a `&'static mut dyn Handler` (see the [module documentation](self))
+#[cfg(feature = "dyn-handlers")]
+pub struct Slot<H>(UnsafeCell<Option<H>>);
+
+// SAFETY: the controller runs on a single thread, which is the only one to install handlers
+#[cfg(feature = "dyn-handlers")]
+unsafe impl<H: Send> Sync for Slot<H> {}
+
+#[cfg(feature = "dyn-handlers")]
+impl<H: Handler + 'static> Slot<H> {
+    /// Instantiates an empty slot
+    #[allow(clippy::new_without_default)] // slots are statics, which need a const constructor
+    pub const fn new() -> Self {
+        Self(UnsafeCell::new(None))
+    }
+
+    /// Installs the handler in this slot, dropping any handler installed previously, and returns
+    /// it as the controller holds it
+    ///
+    /// # Safety
+    ///
+    /// No reference returned by an earlier installation may be used once this is called; the
+    /// controller replaces the handler it holds with the one returned, without using the former in
+    /// between.
+    #[allow(clippy::mut_from_ref)] // the handler is handed out under the contract above
+    pub unsafe fn install(&'static self, handler: H) -> &'static mut dyn Handler {
+        (*self.0.get()).insert(handler)
+    }
+}
@@ -0,0 +1,57 @@
+//! Constant-time comparisons, for values whose comparison must not reveal by its timing where (or
+//! whether) they differ, such as registration secrets, digests, padding, and the ids which frames
+//! are filtered by
+//!
+//! Every byte of the operands is always compared, and the differences are accumulated through an
+//! opaque value, so that the compiler may not exit the comparison early. Only the lengths of the
+//! operands, which are public, may affect the time taken.
+
+use core::hint::black_box;
+
+/// Whether the given byte strings are equal, in time which depends only on their lengths
+pub fn eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let diff = a
+        .iter()
+        .zip(b)
+        .fold(0_u8, |diff, (x, y)| black_box(diff | (x ^ y)));
+    diff == 0
+}
+
+/// Whether the given words are equal, in constant time
+pub fn eq_u16(a: u16, b: u16) -> bool {
+    black_box(a ^ b) == 0
+}
+
+/// A mask of all ones should `a` be less than `b`, else of zeroes, computed without a branch
+fn lt_mask(a: u8, b: u8) -> u8 {
+    // the high byte of the difference is all ones exactly when it wrapped
+    #[allow(clippy::cast_possible_truncation)] // only the high byte is kept
+    let mask = (u16::from(a).wrapping_sub(u16::from(b)) >> 8) as u8;
+    black_box(mask)
+}
+
+/// Whether the data, a whole number of 16-byte blocks, ends in valid PKCS#7 padding, in time
+/// which depends only on its length
+///
+/// Every byte of the final block is examined whatever the length of the padding, so that the
+/// check cannot serve as a padding oracle.
+pub fn pkcs7(data: &[u8]) -> bool {
+    let Some(&pad) = data.last() else {
+        return false;
+    };
+    let block = &data[data.len().saturating_sub(16)..];
+
+    // the padding is of 1 to 16 bytes, and within the data
+    #[allow(clippy::cast_possible_truncation)] // the block is of at most 16 bytes
+    let mut diff = !lt_mask(pad.wrapping_sub(1), block.len() as u8);
+    for (from_end, byte) in block.iter().rev().enumerate() {
+        #[allow(clippy::cast_possible_truncation)] // the block is of at most 16 bytes
+        let padding = lt_mask(from_end as u8, pad);
+        diff = black_box(diff | (padding & (byte ^ pad)));
+    }
+    diff == 0
+}
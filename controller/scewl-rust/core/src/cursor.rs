@@ -4,6 +4,10 @@
 //! These types are meant for internal use for consistent copying and serialisation between the
 //! data buffer of the controller and various types which can be (de)serialised from/to byte arrays.
 //! Note that the cursor methods will **panic** if the respective buffers aren't the correct size.
+//!
+//! Every integer is (de)serialised little-endian, which is the byte order of the wire format
+//! regardless of that of the host, and at a fixed width; a `usize` must be converted to one first
+//! (as [`ContentHeader`](crate::codec::secure::ContentHeader) does to a u32).
 
 use core::cmp::min;
 use core::convert::TryInto;
@@ -28,32 +32,32 @@ impl<'a> ReadCursor<'a> {
         self.buf = &self.buf[n..];
     }
 
-    /// Reads a u16 from the buffer, then advances by the size of one u16
+    /// Reads a u16 from the buffer little-endian, then advances by the size of one u16
     pub fn read_u16(&mut self) -> u16 {
         let buf = self.buf[..size_of::<u16>()].try_into().unwrap();
         self.advance(size_of::<u16>());
-        u16::from_ne_bytes(buf)
+        u16::from_le_bytes(buf)
     }
 
-    /// Reads an i16 from the buffer, then advances by the size of one i16
+    /// Reads an i16 from the buffer little-endian, then advances by the size of one i16
     pub fn read_i16(&mut self) -> i16 {
         let buf = self.buf[..size_of::<i16>()].try_into().unwrap();
         self.advance(size_of::<i16>());
-        i16::from_ne_bytes(buf)
+        i16::from_le_bytes(buf)
+    }
+
+    /// Reads a u32 from the buffer little-endian, then advances by the size of one u32
+    pub fn read_u32(&mut self) -> u32 {
+        let buf = self.buf[..size_of::<u32>()].try_into().unwrap();
+        self.advance(size_of::<u32>());
+        u32::from_le_bytes(buf)
     }
 
-    /// Reads a u64 from the buffer, then advances by the size of one u64
+    /// Reads a u64 from the buffer little-endian, then advances by the size of one u64
     pub fn read_u64(&mut self) -> u64 {
         let buf = self.buf[..size_of::<u64>()].try_into().unwrap();
         self.advance(size_of::<u64>());
-        u64::from_ne_bytes(buf)
-    }
-
-    /// Reads a usize from the buffer, then advances by the size of one usize
-    pub fn read_usize(&mut self) -> usize {
-        let buf = self.buf[..size_of::<usize>()].try_into().unwrap();
-        self.advance(size_of::<usize>());
-        usize::from_ne_bytes(buf)
+        u64::from_le_bytes(buf)
     }
 
     /// Reads an N-byte array from the buffer, then advances by N bytes
@@ -97,27 +101,27 @@ impl<'a> WriteCursor<'a> {
         }
     }
 
-    /// Writes a u16 to the buffer, then advances by the size of one u16
+    /// Writes a u16 to the buffer little-endian, then advances by the size of one u16
     pub fn write_u16(self, n: u16) -> Self {
-        self.buf[..size_of::<u16>()].copy_from_slice(&n.to_ne_bytes());
+        self.buf[..size_of::<u16>()].copy_from_slice(&n.to_le_bytes());
         self.advance(size_of::<u16>())
     }
 
-    /// Writes an i16 to the buffer, then advances by the size of one i16
+    /// Writes an i16 to the buffer little-endian, then advances by the size of one i16
     pub fn write_i16(self, n: i16) -> Self {
-        self.buf[..size_of::<i16>()].copy_from_slice(&n.to_ne_bytes());
+        self.buf[..size_of::<i16>()].copy_from_slice(&n.to_le_bytes());
         self.advance(size_of::<i16>())
     }
 
-    /// Writes a usize to the buffer, then advances by the size of one usize
-    pub fn write_usize(self, n: usize) -> Self {
-        self.buf[..size_of::<usize>()].copy_from_slice(&n.to_ne_bytes());
-        self.advance(size_of::<usize>())
+    /// Writes a u32 to the buffer little-endian, then advances by the size of one u32
+    pub fn write_u32(self, n: u32) -> Self {
+        self.buf[..size_of::<u32>()].copy_from_slice(&n.to_le_bytes());
+        self.advance(size_of::<u32>())
     }
 
-    /// Writes a u64 to the buffer, then advances by the size of one u64
+    /// Writes a u64 to the buffer little-endian, then advances by the size of one u64
     pub fn write_u64(self, n: u64) -> Self {
-        self.buf[..size_of::<u64>()].copy_from_slice(&n.to_ne_bytes());
+        self.buf[..size_of::<u64>()].copy_from_slice(&n.to_le_bytes());
         self.advance(size_of::<u64>())
     }
 
@@ -0,0 +1,9 @@
+//! Settings shared by every SED in the deployment, generated by `build.rs` from the deployment
+//! configuration
+//!
+//! The handlers size their per-peer tables (e.g. the counters of the
+//! [secure handlers](crate::secure)) by these, rather than for the largest deployment possible.
+//! The sizes of the flash and RAM are those of the memory layout, as protected by the
+//! memory protection unit.
+
+include!(concat!(env!("OUT_DIR"), "/deployment.rs"));
@@ -0,0 +1,570 @@
+//! Counters of dropped messages by reason and of the traffic over each link, and the diagnostic
+//! commands which report them
+//!
+//! Every message which the controller drops is counted against the [reason](Reason) it was
+//! dropped for, and every frame which it receives or sends against its [link](Link), so that
+//! failures in the field can be triaged without a debugger attached. The counters are reported in
+//! response to a diagnostic command, which may be sent either by the CPU (as a message addressed
+//! to its own controller) or by the FAA (as a message from the FAA addressed to this controller);
+//! the response is returned to whichever sent the command.
+//!
+//! A command is recognised by a body which begins with [`MAGIC`], laid out as `MAGIC | op: u8`:
+//!
+//!  - `0` [drops](Command::Drops): report the drop counters
+//!  - `1` [set level](Command::SetLevel): `level: u8`, set the maximum [log level](crate::level)
+//!  - `2` [version](Command::Version): report the [build metadata](crate::build_info)
+//!  - `3` [integrity](Command::Integrity): report the state of the runtime code-integrity check
+//!  - `4` [stats](Command::Stats): report the traffic counters, followed by the drop counters
+//!
+//! The response is laid out as `MAGIC | op: u8 | status: u8 | result`, where the status is `0` on
+//! success and `1` otherwise. The result of a drops command is one `u32` per reason, in the order
+//! of their wire values; that of a set level command is the level which took effect, as levels
+//! beyond those compiled in are clamped; that of a version command is described by [`Version`];
+//! that of an integrity command is described by [`Integrity`], and the command fails should the
+//! firmware have been built without the `integrity` feature; that of a stats command is described
+//! by [`Traffic`], and followed by the result of a drops command.
+//! Other messages from the FAA are forwarded to the CPU as before.
+//!
+//! With the `drop-notices` feature, the controller also tells the CPU of each frame from another
+//! SED which it drops on failing verification or decryption (e.g. a replay or a bad MAC), as a
+//! [`Notice`] sent as from its own controller, which the CPU recognises by its [`NOTICE_MAGIC`].
+//! Frames dropped on their header alone, e.g. by the [policy](crate::policy), are only counted, so
+//! that a flood of them cannot in turn flood the CPU.
+//!
+//! Note that messages from the FAA are not authenticated, so anyone on the radio may issue these
+//! commands. Neither reveals anything secret: the counters are not sensitive, and raising the log
+//! level only enables messages which were compiled in, whose contents are
+//! [redacted](crate::redact).
+//!
+//! This module depends only on the [codec](crate::codec), the [log level](crate::level), the
+//! [links](crate::policy::Link), and the [build metadata](crate::build_info), so that host-side
+//! tooling may decode the responses.
+
+use core::convert::TryFrom;
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use core::str;
+
+use crate::build_info;
+use crate::codec::Id;
+use crate::cursor::{ReadCursor, WriteCursor};
+use crate::level::Level;
+use crate::policy::Link;
+
+/// The magic which prefixes the body of every diagnostic command and response
+pub const MAGIC: [u8; 4] = *b"DIAG";
+
+/// A reason for which a message was dropped
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Reason {
+    /// The frame did not begin with the `SC` magic
+    BadMagic = 0,
+    /// The frame was longer than the controller can accept
+    Oversize = 1,
+    /// The frame from another SED carried a stale counter
+    Replay = 2,
+    /// The frame from another SED failed HMAC verification
+    BadMac = 3,
+    /// The content of a frame from another SED was incorrectly padded, or had a corrupted length or
+    /// failed its integrity check, which are not told apart so as not to serve as an oracle
+    BadPadding = 4,
+    /// The content of a frame from another SED was otherwise malformed
+    Malformed = 5,
+    /// The CPU sent a frame claiming to be from another device
+    CpuSpoofed = 6,
+    /// A frame from the radio or the SSS claimed to be from this controller
+    SelfSpoofed = 7,
+    /// A frame used a reserved id where it may not (see the [policy module](crate::policy))
+    Reserved = 8,
+    /// A frame's source exceeded its rate limit on the radio
+    RateLimited = 9,
+    /// The header of a frame from another SED failed its CRC, so its length could not be trusted
+    BadCrc = 10,
+    /// A frame from the CPU would have exceeded the MTU of its target (see the [MTU
+    /// module](crate::mtu))
+    PeerMtu = 11,
+    /// A broadcast from another SED bore no valid signature by its source (see the [signing
+    /// handler](crate::secure::SignedHandler))
+    BadSignature = 12,
+    /// The length of a frame from another SED did not fit the layout of its crypto handler
+    BadLength = 13,
+    /// A frame from another SED was protected under keys which this controller does not hold, such
+    /// as those of an epoch since [rotated](crate::crypto::Handler::rotate) out
+    NoKey = 14,
+    /// A frame from another SED came from a device since deregistered, which the SSS has revoked
+    /// (see the [policy module](crate::policy))
+    Revoked = 15,
+    /// A frame to or from another SED outside the allowlist provisioned by the SSS (see the [policy
+    /// module](crate::policy))
+    NotAllowed = 16,
+    /// The header of a frame from another SED bore an [extension](crate::codec::MessageHeader)
+    /// of a version which this firmware does not read
+    BadVersion = 17,
+    /// A message from another SED was addressed to a [port](crate::port) which the CPU has not
+    /// opened
+    ClosedPort = 18,
+    /// The frames from every source on the radio together exceeded the global rate limit (see the
+    /// [policy module](crate::policy))
+    Flood = 19,
+}
+
+impl Reason {
+    /// The number of reasons, and hence of counters
+    pub const COUNT: usize = 20;
+
+    /// Every reason, in the order of their wire values
+    pub const ALL: [Reason; Reason::COUNT] = [
+        Reason::BadMagic,
+        Reason::Oversize,
+        Reason::Replay,
+        Reason::BadMac,
+        Reason::BadPadding,
+        Reason::Malformed,
+        Reason::CpuSpoofed,
+        Reason::SelfSpoofed,
+        Reason::Reserved,
+        Reason::RateLimited,
+        Reason::BadCrc,
+        Reason::PeerMtu,
+        Reason::BadSignature,
+        Reason::BadLength,
+        Reason::NoKey,
+        Reason::Revoked,
+        Reason::NotAllowed,
+        Reason::BadVersion,
+        Reason::ClosedPort,
+        Reason::Flood,
+    ];
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Reason::BadMagic => "frame did not begin with the magic",
+            Reason::Oversize => "frame was too long",
+            Reason::Replay => "frame carried a stale counter",
+            Reason::BadMac => "frame failed HMAC verification",
+            Reason::BadPadding => "frame was incorrectly padded",
+            Reason::Malformed => "frame was malformed",
+            Reason::CpuSpoofed => "CPU spoofed another device",
+            Reason::SelfSpoofed => "frame spoofed this controller",
+            Reason::Reserved => "frame misused a reserved id",
+            Reason::RateLimited => "source exceeded its rate limit",
+            Reason::BadCrc => "frame's header failed its CRC",
+            Reason::PeerMtu => "frame exceeded its target's MTU",
+            Reason::BadSignature => "broadcast failed signature verification",
+            Reason::BadLength => "frame's length did not fit its handler",
+            Reason::NoKey => "frame was protected under keys not held",
+            Reason::Revoked => "frame came from a revoked device",
+            Reason::NotAllowed => "frame's peer is not on the allowlist",
+            Reason::BadVersion => "frame's header bore an unknown version",
+            Reason::ClosedPort => "message was addressed to a closed port",
+            Reason::Flood => "radio exceeded its global rate limit",
+        })
+    }
+}
+
+/// The number of messages dropped for each reason since boot
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Drops([u32; Reason::COUNT]);
+
+impl Drops {
+    /// Counts a message dropped for the given reason
+    pub fn record(&mut self, reason: Reason) {
+        let count = &mut self.0[reason as usize];
+        *count = count.saturating_add(1);
+    }
+
+    /// The number of messages dropped for the given reason
+    pub fn get(&self, reason: Reason) -> u32 {
+        self.0[reason as usize]
+    }
+
+    /// The total number of messages dropped
+    pub fn total(&self) -> u32 {
+        self.0.iter().fold(0, |sum, &n| sum.saturating_add(n))
+    }
+
+    /// Deserialises the counters from the result of a [drops](Command::Drops) command
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        (buf.len() == Drops::size()).then(|| {
+            let mut cur = ReadCursor::new(buf);
+            let mut drops = Drops::default();
+            for count in &mut drops.0 {
+                *count = cur.read_u32();
+            }
+            drops
+        })
+    }
+
+    /// The constant size of the counters in their serialised form
+    pub const fn size() -> usize {
+        Reason::COUNT * core::mem::size_of::<u32>()
+    }
+}
+
+impl Display for Drops {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        for (i, reason) in Reason::ALL.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{:?}: {}", reason, self.get(*reason))?;
+        }
+        Ok(())
+    }
+}
+
+/// The traffic over one link since boot, each count saturating at `u32::MAX`
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Counts {
+    /// The number of frames received in full
+    pub frames_in: u32,
+    /// The number of bytes of the bodies of those frames
+    pub bytes_in: u32,
+    /// The number of frames sent
+    pub frames_out: u32,
+    /// The number of bytes of the bodies of those frames
+    pub bytes_out: u32,
+}
+
+/// The traffic over each link since boot, laid out as the [`Counts`] of each [`Link`] in the
+/// order of their wire values, each as `frames_in: u32 | bytes_in: u32 | frames_out: u32 |
+/// bytes_out: u32`
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Traffic([Counts; Link::COUNT]);
+
+impl Traffic {
+    /// Counts a frame of the given body length received over the link
+    pub fn received(&mut self, link: Link, len: usize) {
+        let counts = &mut self.0[link as usize];
+        counts.frames_in = counts.frames_in.saturating_add(1);
+        counts.bytes_in = counts.bytes_in.saturating_add(saturate(len));
+    }
+
+    /// Counts a frame of the given body length sent over the link
+    pub fn sent(&mut self, link: Link, len: usize) {
+        let counts = &mut self.0[link as usize];
+        counts.frames_out = counts.frames_out.saturating_add(1);
+        counts.bytes_out = counts.bytes_out.saturating_add(saturate(len));
+    }
+
+    /// The traffic over the given link
+    pub fn get(&self, link: Link) -> Counts {
+        self.0[link as usize]
+    }
+
+    /// Deserialises the counters from the result of a [stats](Command::Stats) command, which the
+    /// drop counters follow
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        (buf.len() == Traffic::size()).then(|| {
+            let mut cur = ReadCursor::new(buf);
+            let mut traffic = Traffic::default();
+            for counts in &mut traffic.0 {
+                *counts = Counts {
+                    frames_in: cur.read_u32(),
+                    bytes_in: cur.read_u32(),
+                    frames_out: cur.read_u32(),
+                    bytes_out: cur.read_u32(),
+                };
+            }
+            traffic
+        })
+    }
+
+    /// The constant size of the counters in their serialised form
+    pub const fn size() -> usize {
+        Link::COUNT * 4 * core::mem::size_of::<u32>()
+    }
+}
+
+/// Converts a length to a count, saturating at `u32::MAX`
+fn saturate(len: usize) -> u32 {
+    u32::try_from(len).unwrap_or(u32::MAX)
+}
+
+/// The state of the runtime code-integrity check of the `integrity` feature
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Tamper {
+    /// No digest of the code has been sealed, as the controller has not yet registered
+    Unsealed = 0,
+    /// Every check of the code has matched the sealed digest
+    Intact = 1,
+    /// A check of the code did not match the sealed digest, since when the state is latched
+    Tampered = 2,
+}
+
+impl Tamper {
+    /// Every state, in the order of their wire values
+    pub const ALL: [Tamper; 3] = [Tamper::Unsealed, Tamper::Intact, Tamper::Tampered];
+
+    /// The state of the given wire value, if any
+    fn from_u8(state: u8) -> Option<Self> {
+        match state {
+            0 => Some(Tamper::Unsealed),
+            1 => Some(Tamper::Intact),
+            2 => Some(Tamper::Tampered),
+            _ => None,
+        }
+    }
+}
+
+/// The result of an integrity command, laid out as `state: u8 | checks: u32`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Integrity {
+    /// The state of the check
+    pub state: Tamper,
+    /// The number of complete checks of the code which have matched the sealed digest
+    pub checks: u32,
+}
+
+impl Integrity {
+    /// Deserialises the state from the result of an [integrity](Command::Integrity) command
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if buf.len() != Integrity::size() {
+            return None;
+        }
+
+        let mut cur = ReadCursor::new(buf);
+        Some(Integrity {
+            state: Tamper::from_u8(cur.read_literal::<1>()[0])?,
+            checks: cur.read_u32(),
+        })
+    }
+
+    /// The constant size of the state in its serialised form
+    pub const fn size() -> usize {
+        core::mem::size_of::<u8>() + core::mem::size_of::<u32>()
+    }
+}
+
+/// The magic which prefixes the body of every [drop notice](Notice)
+pub const NOTICE_MAGIC: [u8; 4] = *b"DROP";
+
+/// A notice to the CPU that a frame which another SED sent it was dropped, sent with the
+/// `drop-notices` feature; laid out as `NOTICE_MAGIC | src: u16 | reason: u8`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Notice {
+    /// The SED which sent the dropped frame
+    pub src_id: Id,
+    /// The reason for which it was dropped
+    pub reason: Reason,
+}
+
+impl Notice {
+    /// Serialises this notice to the buffer, returning its length
+    pub fn to_bytes(self, buf: &mut [u8]) -> usize {
+        WriteCursor::new(buf)
+            .write(&NOTICE_MAGIC)
+            .write_u16(self.src_id.into())
+            .write(&[self.reason as u8]);
+        Notice::size()
+    }
+
+    /// Deserialises a notice from the body of a message, if it is one
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if buf.len() != Notice::size() || !buf.starts_with(&NOTICE_MAGIC) {
+            return None;
+        }
+
+        let mut cur = ReadCursor::new(&buf[NOTICE_MAGIC.len()..]);
+        Some(Notice {
+            src_id: cur.read_u16().into(),
+            reason: *Reason::ALL.get(usize::from(cur.read_literal::<1>()[0]))?,
+        })
+    }
+
+    /// The constant size of a notice in its serialised form
+    pub const fn size() -> usize {
+        NOTICE_MAGIC.len() + core::mem::size_of::<u16>() + core::mem::size_of::<u8>()
+    }
+}
+
+/// A diagnostic command
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Command {
+    /// Report the drop counters
+    Drops,
+    /// Set the maximum log level
+    SetLevel(Level),
+    /// Report the build metadata
+    Version,
+    /// Report the state of the runtime code-integrity check
+    Integrity,
+    /// Report the traffic counters and the drop counters
+    Stats,
+}
+
+impl Command {
+    /// Decodes a command from the body of a message, if it is one
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if !buf.starts_with(&MAGIC) || buf.len() <= MAGIC.len() {
+            return None;
+        }
+
+        match buf[MAGIC.len()] {
+            0 => Some(Command::Drops),
+            1 => buf
+                .get(MAGIC.len() + 1)
+                .copied()
+                .and_then(Level::from_u8)
+                .map(Command::SetLevel),
+            2 => Some(Command::Version),
+            3 => Some(Command::Integrity),
+            4 => Some(Command::Stats),
+            _ => None,
+        }
+    }
+
+    /// The wire value of this command's operation
+    pub fn op(self) -> u8 {
+        match self {
+            Command::Drops => 0,
+            Command::SetLevel(_) => 1,
+            Command::Version => 2,
+            Command::Integrity => 3,
+            Command::Stats => 4,
+        }
+    }
+}
+
+/// The result of a version command, which identifies the image a controller is running
+///
+/// This is laid out as `timestamp: u64 | version | commit | features`, where each string is
+/// prefixed by its length as a `u8` and the features are separated by commas.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Version<'a> {
+    /// The time of the build, in seconds since the Unix epoch
+    pub timestamp: u64,
+    /// The version of the firmware
+    pub version: &'a str,
+    /// The git commit which the firmware was built from
+    pub commit: &'a str,
+    /// The features which the firmware was built with, separated by commas
+    pub features: &'a str,
+}
+
+impl<'a> Version<'a> {
+    /// Deserialises the build metadata from the result of a [version](Command::Version) command
+    pub fn from_bytes(buf: &'a [u8]) -> Option<Self> {
+        /// Splits a length-prefixed string from the front of the buffer
+        fn split_str(buf: &[u8]) -> Option<(&str, &[u8])> {
+            let (&len, rest) = buf.split_first()?;
+            let (s, rest) = (rest.get(..len as usize)?, &rest[len as usize..]);
+            Some((str::from_utf8(s).ok()?, rest))
+        }
+
+        let rest = buf.get(core::mem::size_of::<u64>()..)?;
+        let timestamp = ReadCursor::new(buf).read_u64();
+        let (version, rest) = split_str(rest)?;
+        let (commit, rest) = split_str(rest)?;
+        let (features, rest) = split_str(rest)?;
+
+        rest.is_empty().then_some(Version {
+            timestamp,
+            version,
+            commit,
+            features,
+        })
+    }
+}
+
+impl Display for Version<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{} ({}) built at {} with {}",
+            self.version, self.commit, self.timestamp, self.features
+        )
+    }
+}
+
+/// Writes the response to a drops command to the buffer, returning its length
+pub fn respond_drops(buf: &mut [u8], drops: &Drops) -> usize {
+    let mut cur = WriteCursor::new(buf)
+        .write(&MAGIC)
+        .write(&[Command::Drops.op(), 0]);
+    for count in drops.0 {
+        cur = cur.write_u32(count);
+    }
+    MAGIC.len() + 2 + Drops::size()
+}
+
+/// Writes the response to a stats command to the buffer, returning its length
+pub fn respond_stats(buf: &mut [u8], traffic: &Traffic, drops: &Drops) -> usize {
+    let mut cur = WriteCursor::new(buf)
+        .write(&MAGIC)
+        .write(&[Command::Stats.op(), 0]);
+    for counts in traffic.0 {
+        cur = cur
+            .write_u32(counts.frames_in)
+            .write_u32(counts.bytes_in)
+            .write_u32(counts.frames_out)
+            .write_u32(counts.bytes_out);
+    }
+    for count in drops.0 {
+        cur = cur.write_u32(count);
+    }
+    MAGIC.len() + 2 + Traffic::size() + Drops::size()
+}
+
+/// Writes the response to a set level command to the buffer, returning its length
+pub fn respond_level(buf: &mut [u8], level: Level) -> usize {
+    WriteCursor::new(buf)
+        .write(&MAGIC)
+        .write(&[Command::SetLevel(level).op(), 0, level as u8]);
+    MAGIC.len() + 3
+}
+
+/// Writes the response to an integrity command to the buffer, returning its length; the command
+/// fails should there be no check to report on
+pub fn respond_integrity(buf: &mut [u8], integrity: Option<Integrity>) -> usize {
+    let cur = WriteCursor::new(buf).write(&MAGIC);
+    if let Some(integrity) = integrity {
+        cur.write(&[Command::Integrity.op(), 0, integrity.state as u8])
+            .write_u32(integrity.checks);
+        MAGIC.len() + 2 + Integrity::size()
+    } else {
+        cur.write(&[Command::Integrity.op(), 1]);
+        MAGIC.len() + 2
+    }
+}
+
+/// Writes the response to a version command, reporting this image's [build metadata](build_info),
+/// to the buffer, returning its length
+///
+/// Strings which exceed the `u8` length prefix are truncated.
+pub fn respond_version(buf: &mut [u8]) -> usize {
+    /// Writes a length-prefixed string, returning the number of bytes written
+    fn write_str<'a>(cur: WriteCursor<'a>, s: &[u8]) -> (WriteCursor<'a>, usize) {
+        let s = &s[..s.len().min(u8::MAX as usize)];
+        #[allow(clippy::cast_possible_truncation)] // truncated to fit above
+        let cur = cur.write(&[s.len() as u8]).write(s);
+        (cur, 1 + s.len())
+    }
+
+    let cur = WriteCursor::new(buf)
+        .write(&MAGIC)
+        .write(&[Command::Version.op(), 0])
+        .write_u64(build_info::TIMESTAMP);
+    let (cur, version) = write_str(cur, build_info::VERSION.as_bytes());
+    let (cur, commit) = write_str(cur, build_info::COMMIT.as_bytes());
+
+    // the features are joined in place, after their length prefix
+    let mut features = 0;
+    let mut cur = cur.advance(1);
+    for (i, feature) in build_info::FEATURES.iter().enumerate() {
+        let sep: &[u8] = if i == 0 { b"" } else { b"," };
+        if features + sep.len() + feature.len() > u8::MAX as usize {
+            break;
+        }
+        cur = cur.write(sep).write(feature.as_bytes());
+        features += sep.len() + feature.len();
+    }
+    let offset = MAGIC.len() + 2 + core::mem::size_of::<u64>() + version + commit;
+    #[allow(clippy::cast_possible_truncation)] // bounded by the check above
+    {
+        buf[offset] = features as u8;
+    }
+
+    offset + 1 + features
+}
@@ -0,0 +1,80 @@
+//! The fatal errors of the controller, i.e. the conditions under which it panics, each of which is
+//! numbered
+//!
+//! Every panic in the controller goes through [`Fatal::panic`] rather than `expect`, `unwrap`, or
+//! `unreachable!`, which would format the cause of the failure with its `Debug` impl (and so link
+//! `core::fmt` along with every such impl). Instead, each fatal error panics with a constant
+//! message, which the panic handler may record without formatting anything (see the
+//! [crash log](crate::crashlog)).
+//!
+//! With the `panic-codes` feature (implied by `stripped`), that message is only the number of the
+//! error, e.g. `fatal error 1`, so that the descriptions are not linked either; they are listed
+//! here, and by `Fatal`'s variants, for decoding crash logs.
+
+/// Declares the fatal errors, each with its number, the message with which it panics under
+/// `panic-codes`, and that with which it otherwise panics
+///
+/// Both messages must be literals, as a panic with a formatted message would link `core::fmt`.
+macro_rules! fatal_errors {
+    ($($(#[$doc:meta])* $name:ident = $code:literal, $short:literal, $message:literal;)*) => {
+        /// A fatal error, numbered as in the [module documentation](self)
+        #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+        #[repr(u8)]
+        pub enum Fatal {
+            $($(#[$doc])* $name = $code,)*
+        }
+
+        impl Fatal {
+            /// Panics with this error, at the location of the caller
+            #[cold]
+            #[track_caller]
+            pub fn panic(self) -> ! {
+                match self {
+                    $(
+                        #[cfg(feature = "panic-codes")]
+                        Fatal::$name => panic!($short),
+                        #[cfg(not(feature = "panic-codes"))]
+                        Fatal::$name => panic!($message),
+                    )*
+                }
+            }
+        }
+    };
+}
+
+fatal_errors! {
+    /// A per-peer table is full, i.e. more peers were heard from than the deployment configures
+    PeerTable = 1, "fatal error 1",
+        "a per-peer table is full; the deployment has more peers than configured";
+    /// The HMAC key could not be set, as its buffer was insufficiently sized
+    HmacKey = 2, "fatal error 2",
+        "the HMAC key's buffer was insufficiently sized";
+    /// A crypto handler was given an SSS or FAA message, which are never encrypted
+    Unencrypted = 3, "fatal error 3",
+        "under no circumstances may SSS and FAA messages be encrypted";
+    /// The data buffer was too small to encrypt a message in place
+    Buffer = 4, "fatal error 4",
+        "the controller's data buffer was insufficiently sized";
+    /// The peripherals were taken more than once
+    Peripherals = 5, "fatal error 5",
+        "the peripherals were already taken";
+    /// An interface wraps a UART other than those of the CPU, SSS, and radio
+    Interface = 6, "fatal error 6",
+        "impossible interface; only the CPU, SSS, and radio UARTs exist";
+    /// A handler borrowed more scratch buffers at once than the pool holds
+    Scratch = 7, "fatal error 7",
+        "every scratch buffer was already taken";
+    /// A memory access faulted, e.g. the stack overflowed into its guard (see the firmware's MPU)
+    Fault = 8, "fatal error 8",
+        "a memory access faulted; the stack may have overflowed";
+    /// The two evaluations of a security-critical condition disagreed, as only a fault injected
+    /// into the core can cause (see [glitch](crate::glitch))
+    Glitch = 9, "fatal error 9",
+        "a security-critical check was glitched";
+    /// The session keys of a pair of SEDs could not be expanded from the master secret
+    SessionKey = 10, "fatal error 10",
+        "the session keys' length exceeded what HKDF may expand";
+    /// A resource shared between the firmware's tasks was locked again within its own lock
+    Reentrant = 11, "fatal error 11",
+        "a shared resource was locked within its own lock";
+}
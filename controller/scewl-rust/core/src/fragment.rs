@@ -0,0 +1,155 @@
+//! The fragmentation of messages from the CPU which are too large for the data buffer, and their
+//! reassembly by the receiving controller before delivery to its CPU
+//!
+//! A message no larger than the data buffer is sent in a single frame, as ever. With the
+//! `fragmentation` feature, a larger message (of up to [`CAPACITY`] bytes) is instead split as it
+//! is read from the CPU into fragments of [`CHUNK`] bytes, the last of which may be shorter; each
+//! is sent as a message of its own, i.e. authenticated and encrypted by the crypto handler, with
+//! the same target as the whole. The receiving controller gathers their content into a buffer of
+//! [`CAPACITY`] bytes, and only once the last has arrived forwards it to its CPU as one message.
+//!
+//! Each fragment is content of the [fragment kind](crate::content::Kind::Fragment), which the
+//! crypto handler authenticates, so that data from a CPU is never taken for a fragment. Its body is
+//! laid out as `id: u16 | index: u16 | total: u16 | content`, where `id` numbers the messages of
+//! the sender, and `index` numbers the fragments of each from 0 to `total - 1`. The message
+//! reassembled from them is data.
+//!
+//! Fragments arrive over the radio in the order sent, so a [`Reassembly`] only accepts them in
+//! order, and of one message at a time: the first fragment of a message abandons any other being
+//! reassembled, and any fragment out of sequence abandons its own. A message of which a fragment
+//! is lost is thus dropped as a whole, rather than delivered incomplete.
+
+use core::mem::size_of;
+
+use crate::codec::Id;
+use crate::cursor::{ReadCursor, WriteCursor};
+use crate::diag::Reason;
+
+/// The number of bytes of content carried by each fragment but the last
+pub const CHUNK: usize = 0x1000;
+
+/// The largest message which may be sent in fragments, which is the size of the buffer into which
+/// they are reassembled; it is as large as the RAM left beside the data buffer and stack allows
+pub const CAPACITY: usize = 0x6000;
+
+/// The header which prefixes the content of a fragment
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Fragment {
+    /// The number of the message of which this is a fragment, among those of its sender
+    pub id: u16,
+    /// The position of this fragment within its message, from 0
+    pub index: u16,
+    /// The number of fragments of the message
+    pub total: u16,
+}
+
+impl Fragment {
+    /// Serialises this header to the buffer, returning its length; the content follows it
+    pub fn to_bytes(self, buf: &mut [u8]) -> usize {
+        WriteCursor::new(buf)
+            .write_u16(self.id)
+            .write_u16(self.index)
+            .write_u16(self.total);
+        Fragment::size()
+    }
+
+    /// Deserialises the header of a fragment from the body of content of the fragment kind, should
+    /// it be long enough; its content is the rest of the body
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        (buf.len() >= Fragment::size()).then(|| {
+            let mut cur = ReadCursor::new(buf);
+            Fragment {
+                id: cur.read_u16(),
+                index: cur.read_u16(),
+                total: cur.read_u16(),
+            }
+        })
+    }
+
+    /// The constant size of the header in its serialised form
+    pub const fn size() -> usize {
+        3 * size_of::<u16>()
+    }
+
+    /// The number of fragments into which a message of the given length is split
+    #[allow(clippy::cast_possible_truncation)] // a message is shorter than 64 KiB
+    pub const fn count(len: usize) -> u16 {
+        len.div_ceil(CHUNK) as u16
+    }
+}
+
+/// The message whose fragments are being gathered, as described in the [module
+/// documentation](self)
+pub struct Reassembly<'a> {
+    /// The buffer into which the content of the fragments is gathered
+    buf: &'a mut [u8],
+    /// The source, target, and header of the last fragment accepted, if a message is in progress
+    last: Option<(Id, Id, Fragment)>,
+    /// The number of bytes gathered so far
+    len: usize,
+}
+
+impl<'a> Reassembly<'a> {
+    /// A reassembly into the given buffer, which bounds the size of the messages reassembled
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Reassembly {
+            buf,
+            last: None,
+            len: 0,
+        }
+    }
+
+    /// Gathers the content of a fragment from the given source to the given target (this
+    /// controller, or everyone), returning the length of the message should it now be complete
+    ///
+    /// A fragment which is out of sequence, or would overflow the buffer, is refused, and the
+    /// message in progress abandoned.
+    pub fn accept(
+        &mut self,
+        src_id: Id,
+        tgt_id: Id,
+        fragment: Fragment,
+        content: &[u8],
+    ) -> Result<Option<usize>, Reason> {
+        if fragment.index == 0 {
+            self.clear();
+        }
+
+        let in_sequence = match self.last {
+            None => fragment.index == 0,
+            Some((src, tgt, last)) => {
+                src == src_id
+                    && tgt == tgt_id
+                    && last.id == fragment.id
+                    && last.total == fragment.total
+                    && last.index.checked_add(1) == Some(fragment.index)
+            }
+        };
+        if !in_sequence || fragment.index >= fragment.total {
+            self.clear();
+            return Err(Reason::Malformed);
+        }
+        if self.len + content.len() > self.buf.len() {
+            self.clear();
+            return Err(Reason::Oversize);
+        }
+
+        self.buf[self.len..][..content.len()].copy_from_slice(content);
+        self.len += content.len();
+        self.last = Some((src_id, tgt_id, fragment));
+
+        Ok((fragment.index + 1 == fragment.total).then_some(self.len))
+    }
+
+    /// The content gathered so far, which is the whole message once it is complete
+    pub fn content(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Abandons the message in progress, if any, zeroing its content
+    pub fn clear(&mut self) {
+        self.buf[..self.len].fill(0);
+        self.last = None;
+        self.len = 0;
+    }
+}
@@ -0,0 +1,45 @@
+//! Hardening of the controller's security-critical decisions against fault injection
+//!
+//! A glitch of the clock or supply can skip or corrupt a single instruction, such as the branch
+//! which drops a frame whose MAC did not verify. Each such decision (a MAC verified, a counter
+//! fresh, a registration accepted) is therefore made with [`check`], which evaluates its
+//! condition twice and records the outcomes as complementary multi-bit flags. A single fault can
+//! spoil at most one of them, which then disagrees with the other, and the controller halts with
+//! [`Fatal::Glitch`] rather than act on either.
+//!
+//! The flags are words with many bits both set and clear, rather than `bool`s, so that a fault
+//! which zeroes or saturates a register yields neither outcome.
+
+use core::hint::black_box;
+
+use crate::fatal::Fatal;
+
+/// The flag of a condition which holds
+const HOLDS: u32 = 0x5A3C_96A5;
+/// The flag of a condition which fails, i.e. the complement of [`HOLDS`]
+const FAILS: u32 = !HOLDS;
+
+/// Evaluates a security-critical condition twice, returning whether it holds; should the two
+/// evaluations disagree, which only a fault can cause, the controller halts with
+/// [`Fatal::Glitch`]
+///
+/// The condition is evaluated through an opaque reference, so that the compiler may not merge
+/// the evaluations; it should be cheap, e.g. a comparison of values already computed.
+pub fn check(condition: impl Fn() -> bool) -> bool {
+    let first = if black_box(&condition)() {
+        HOLDS
+    } else {
+        FAILS
+    };
+    let second = if black_box(&condition)() {
+        FAILS
+    } else {
+        HOLDS
+    };
+
+    match (black_box(first), black_box(second)) {
+        (HOLDS, FAILS) => true,
+        (FAILS, HOLDS) => false,
+        _ => Fatal::Glitch.panic(),
+    }
+}
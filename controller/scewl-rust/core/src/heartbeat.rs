@@ -0,0 +1,117 @@
+//! A periodic telemetry frame, which reports the liveness and health of the controller
+//!
+//! With the `heartbeat` feature, the controller sends a heartbeat to a configured target every
+//! period, giving deployment operators visibility of each SED without a debugger attached. The
+//! target is either the FAA or a monitoring SED:
+//!
+//!  - heartbeats to a monitoring SED are sent exactly as a direct message from the CPU would be,
+//!    i.e. authenticated and encrypted by the crypto handler; as such, they are only sent while
+//!    the controller is registered
+//!  - heartbeats to the FAA are sent in the clear, as the FAA shares no keys with the SEDs and
+//!    its channel is unauthenticated by specification
+//!
+//! The body of a heartbeat is laid out as
+//! `MAGIC | uptime (s): u32 | registered: u8 | messages dropped: u32`. It is delivered to the
+//! target's CPU like any other message, so monitors must recognise it by its [`MAGIC`].
+
+use core::mem::size_of;
+use core::time::Duration;
+
+use crate::codec::Id;
+use crate::cursor::{ReadCursor, WriteCursor};
+use crate::time::{Clock, Instant};
+
+/// The magic which prefixes the body of every heartbeat
+pub const MAGIC: [u8; 4] = *b"BEAT";
+
+/// The content of a heartbeat
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Beat {
+    /// The number of seconds since boot
+    pub uptime: u32,
+    /// Whether the controller is registered
+    pub registered: bool,
+    /// The total number of messages dropped since boot
+    pub dropped: u32,
+}
+
+impl Beat {
+    /// Serialises this heartbeat to the buffer, returning its length
+    pub fn to_bytes(self, buf: &mut [u8]) -> usize {
+        WriteCursor::new(buf)
+            .write(&MAGIC)
+            .write_u32(self.uptime)
+            .write(&[u8::from(self.registered)])
+            .write_u32(self.dropped);
+        Beat::size()
+    }
+
+    /// Deserialises a heartbeat from the body of a message, if it is one
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        (buf.len() == Beat::size() && buf.starts_with(&MAGIC)).then(|| {
+            let mut cur = ReadCursor::new(&buf[MAGIC.len()..]);
+            Beat {
+                uptime: cur.read_u32(),
+                registered: cur.read_literal::<1>()[0] != 0,
+                dropped: cur.read_u32(),
+            }
+        })
+    }
+
+    /// The constant size of a heartbeat in its serialised form
+    pub const fn size() -> usize {
+        MAGIC.len() + size_of::<u32>() + size_of::<u8>() + size_of::<u32>()
+    }
+}
+
+/// The schedule on which heartbeats are sent
+pub struct Heartbeat<'a> {
+    /// The recipient of each heartbeat; the FAA, or a monitoring SED
+    target: Id,
+    /// The time between heartbeats
+    period: Duration,
+    /// The clock by which heartbeats are scheduled
+    clock: &'a dyn Clock,
+    /// The instant at which the next heartbeat is due
+    next: Instant,
+}
+
+impl<'a> Heartbeat<'a> {
+    /// Schedules heartbeats to the given target every period, the first of which is due one period
+    /// from now
+    ///
+    /// This panics should the target be neither the FAA nor another SED.
+    pub fn new(target: Id, period: Duration, clock: &'a dyn Clock) -> Self {
+        assert!(
+            matches!(target, Id::FAA | Id::Other(_)),
+            "Heartbeats may only be sent to the FAA or another SED"
+        );
+
+        Self {
+            target,
+            period,
+            clock,
+            next: clock.now() + period,
+        }
+    }
+
+    /// The recipient of each heartbeat
+    pub fn target(&self) -> Id {
+        self.target
+    }
+
+    /// Determines whether a heartbeat is due, in which case the next is scheduled and the current
+    /// uptime is returned
+    ///
+    /// Should heartbeats have been missed (e.g. while a long message was being handled), only one
+    /// is sent, and the schedule resumes from now.
+    pub fn poll(&mut self) -> Option<Duration> {
+        let now = self.clock.now();
+        if now < self.next {
+            return None;
+        }
+
+        self.next = now + self.period;
+        Some(now.saturating_duration_since(Instant::BOOT))
+    }
+}
@@ -0,0 +1,84 @@
+//! Hexdumps of frames at each stage of the controller's pipeline, enabled by the `hexdump` feature
+//!
+//! When bringing up interop with a new SSS or peer implementation, the most useful thing to know
+//! is usually exactly which bytes went in and out at each step. With the `hexdump` feature, the
+//! controller [dumps](dump) each frame at every [stage](Stage) it passes through, e.g.:
+//!
+//! ```text
+//! [DEBUG] hexdump: post-decrypt (55 bytes) 74 68 65 20 71 75 69 63 6b 20 62 72 6f 77 6e 20 .. 20 64 6f 67 3b 20 30 31 32 33 34 35 36 37 38 39
+//! ```
+//!
+//! Only the first and last [`EDGE`] bytes of longer frames are dumped, which covers the headers,
+//! trailers and padding where interop issues tend to show while keeping the log readable. As
+//! decrypted frames are dumped in the clear, this feature implies `insecure-logging`, and is
+//! likewise refused in release builds.
+
+use core::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::debug;
+
+/// The number of bytes dumped at either end of a frame
+pub const EDGE: usize = 16;
+
+/// A stage of the pipeline at which frames are dumped
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Stage {
+    /// As read from an interface, before anything else is done with it; for frames from other
+    /// SEDs, only the verification segment is read at this point
+    RawRx,
+    /// A frame from another SED, once read in full after passing verification
+    PostVerify,
+    /// A frame from another SED, once decrypted
+    PostDecrypt,
+    /// As written to an interface
+    PreTx,
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(match self {
+            Stage::RawRx => "raw rx",
+            Stage::PostVerify => "post-verify",
+            Stage::PostDecrypt => "post-decrypt",
+            Stage::PreTx => "pre-tx",
+        })
+    }
+}
+
+/// The bytes of a frame, displayed as hex, eliding all but the first and last [`EDGE`] bytes
+#[derive(Copy, Clone)]
+pub struct Hexdump<'a>(pub &'a [u8]);
+
+impl Display for Hexdump<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        /// Writes each byte as hex, separated by spaces
+        fn hex(f: &mut Formatter<'_>, bytes: &[u8]) -> FmtResult {
+            for (i, b) in bytes.iter().enumerate() {
+                if i != 0 {
+                    f.write_str(" ")?;
+                }
+                write!(f, "{b:02x}")?;
+            }
+            Ok(())
+        }
+
+        if self.0.len() <= 2 * EDGE {
+            hex(f, self.0)
+        } else {
+            hex(f, &self.0[..EDGE])?;
+            f.write_str(" .. ")?;
+            hex(f, &self.0[self.0.len() - EDGE..])
+        }
+    }
+}
+
+/// Dumps the given frame as it passes the given stage
+#[allow(unused_variables)] // only logged when a logging transport is enabled
+pub fn dump(stage: Stage, bytes: &[u8]) {
+    debug!(
+        "hexdump: {} ({} bytes) {}",
+        stage,
+        bytes.len(),
+        Hexdump(bytes)
+    );
+}
@@ -0,0 +1,101 @@
+//! A runtime check of the firmware's code against a digest sealed at registration, which detects
+//! code modified while the controller runs (e.g. through a debugger attached to the emulated
+//! target, or by an exploit which can write the flash)
+//!
+//! With the `integrity` feature, the controller [seals](Monitor::seal) the SHA-256 digest of the
+//! [code](crate::board::Board::code) (the `.text` section of the firmware) whenever it registers.
+//! From then on, it [rehashes](Monitor::poll) the code [`CHUNK`] bytes at a time on every pass of
+//! its run loop, so that no pass is delayed noticeably, and compares the digest of each complete
+//! pass to the sealed one in constant time. A mismatch is logged as an error and latched: the
+//! [state](Tamper), reported by the integrity [diagnostic command](crate::diag), remains `Tampered`
+//! until the controller is reset, even should it register (and so seal the modified code) again.
+//!
+//! Sealing at registration needs no support from the toolchain to embed a digest in the image, but
+//! leaves code modified before the first registration unnoticed; the check is only intended to
+//! catch modification at runtime.
+
+use sha2::{Digest, Sha256};
+
+use crate::diag::{Integrity, Tamper};
+use crate::{ct, error, glitch, info};
+
+/// The number of bytes of code hashed on each pass of the run loop
+pub const CHUNK: usize = 256;
+
+/// The code-integrity check, as described in the [module documentation](self)
+pub struct Monitor {
+    /// The code checked
+    text: &'static [u8],
+    /// The digest sealed at the last registration, if any
+    sealed: Option<[u8; 32]>,
+    /// The digest of the code hashed so far in this pass
+    sha: Sha256,
+    /// The offset into the code of the next chunk to hash
+    offset: usize,
+    /// The state of the check
+    state: Tamper,
+    /// The number of complete passes which have matched the sealed digest
+    checks: u32,
+}
+
+impl Monitor {
+    /// Checks the given code, once a digest of it is sealed
+    pub fn new(text: &'static [u8]) -> Self {
+        Monitor {
+            text,
+            sealed: None,
+            sha: Sha256::new(),
+            offset: 0,
+            state: Tamper::Unsealed,
+            checks: 0,
+        }
+    }
+
+    /// Seals the digest of the code as it is now, against which every later pass is compared, and
+    /// restarts the pass in progress
+    pub fn seal(&mut self) {
+        self.sealed = Some(Sha256::digest(self.text).into());
+        self.sha.reset();
+        self.offset = 0;
+        if self.state == Tamper::Unsealed {
+            self.state = Tamper::Intact;
+        }
+
+        info!("Sealed the digest of {} bytes of code", self.text.len());
+    }
+
+    /// Hashes the next chunk of the code, should a digest be sealed, and compares the digest to
+    /// the sealed one once the pass is complete
+    pub fn poll(&mut self) {
+        let Some(sealed) = self.sealed else {
+            return;
+        };
+
+        let text = self.text;
+        let end = text.len().min(self.offset + CHUNK);
+        self.sha.update(&text[self.offset..end]);
+        self.offset = end;
+        if self.offset < text.len() {
+            return;
+        }
+
+        let digest = self.sha.finalize_reset();
+        self.offset = 0;
+        if glitch::check(|| ct::eq(&digest, &sealed)) {
+            self.checks = self.checks.saturating_add(1);
+        } else {
+            if self.state != Tamper::Tampered {
+                error!("The code no longer matches the digest sealed at registration");
+            }
+            self.state = Tamper::Tampered;
+        }
+    }
+
+    /// The state of the check, as reported by the integrity diagnostic command
+    pub fn report(&self) -> Integrity {
+        Integrity {
+            state: self.state,
+            checks: self.checks,
+        }
+    }
+}
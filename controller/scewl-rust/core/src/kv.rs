@@ -0,0 +1,291 @@
+//! A small key-value store in flash, for the state which must survive a power cycle (e.g. the
+//! [key epoch](crate::secure::Epochs) of the `anti-rollback` feature of the firmware)
+//!
+//! The store is hardware-free: it reads and writes any [`Medium`] of two pages, such as one in RAM
+//! on the host. With its `flash-store` feature, the firmware reserves a pair of flash pages below
+//! the top of flash when generating `memory.x`, which it opens as a [`Store`].
+//!
+//! Each page starts with a header, of its generation followed by [`MAGIC`], after which records
+//! are appended in the order written. Every record is laid out as follows, in words:
+//!
+//! ```text
+//! | key (low 16 bits), length in bytes (high 16 bits) | value, zero-padded | CRC-32 |
+//! ```
+//!
+//! where the CRC covers the first word and the value. The value of a key is that of its last
+//! intact record, so a value is updated by appending a record rather than erasing the page; a
+//! record torn by a reset fails its CRC, and is ignored. Once the active page is full, the last
+//! value of every key is copied to the other page, which is erased first and only then given a
+//! header of the next generation; the page with the highest generation is active. A reset during
+//! the copy therefore leaves the store as it was, and no value is ever held only in RAM.
+//!
+//! Flash wears out after some tens of thousands of erasures, so writes are kept rare: a value is
+//! not written again should it be unchanged, and the pages are erased alternately, once per page
+//! of writes.
+
+use core::convert::TryInto;
+use core::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::provision::crc32;
+
+/// The size of each page of the store, in bytes
+pub const PAGE: usize = 1024;
+
+/// The number of words in each page of the store
+pub const WORDS: usize = PAGE / 4;
+
+/// The largest value which may be stored, in bytes
+pub const MAX_VALUE: usize = 64;
+
+/// The last word of the header of a page in use, after its generation
+pub const MAGIC: u32 = u32::from_le_bytes(*b"SCKV");
+
+/// The key of the highest key epoch accepted from the SSS
+pub const KEY_EPOCH: u16 = 1;
+
+/// The first of the keys of the [counter reservations](crate::secure::Checkpoints) of each peer, whose id
+/// (bar its top bit) is added to it
+pub const COUNTERS: u16 = 0x8000;
+
+/// The value of a word of erased flash
+const ERASED: u32 = 0xFFFF_FFFF;
+
+/// The word of the first record of a page, after the header
+const FIRST: usize = 2;
+
+/// The words of a record other than its value, i.e. its first word and its CRC
+const OVERHEAD: usize = 2;
+
+/// Error type for operations on the store
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The medium could not be erased or programmed
+    Medium,
+    /// The key is reserved, or the value is larger than [`MAX_VALUE`]
+    Invalid,
+    /// The store holds too many values to fit another
+    Full,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Error::Medium => write!(f, "flash could not be written"),
+            Error::Invalid => write!(f, "invalid key or value"),
+            Error::Full => write!(f, "store is full"),
+        }
+    }
+}
+
+/// The two pages of flash (or an imitation thereof) in which a [`Store`] is kept
+///
+/// As with flash, an erased word reads as all ones, and programming a word may only clear bits.
+pub trait Medium {
+    /// Reads the given word of the given page
+    fn read(&self, page: usize, word: usize) -> u32;
+
+    /// Erases the given page
+    fn erase(&mut self, page: usize) -> Result<(), Error>;
+
+    /// Programs the given word of the given page, which should be erased
+    fn program(&mut self, page: usize, word: usize, value: u32) -> Result<(), Error>;
+}
+
+/// A key-value store in a [`Medium`], as described in the [module documentation](self)
+pub struct Store<M: Medium> {
+    /// The medium holding the store
+    medium: M,
+    /// The active page, which holds the current values
+    active: usize,
+    /// The generation of the active page, or `None` should neither page have a header
+    generation: Option<u32>,
+    /// The word of the active page after its last record
+    end: usize,
+}
+
+impl<M: Medium> Store<M> {
+    /// Opens the store held by the medium, which is written to only once a value is set
+    pub fn open(medium: M) -> Self {
+        let generation = |page| (medium.read(page, 1) == MAGIC).then(|| medium.read(page, 0));
+        let (active, generation) = match (generation(0), generation(1)) {
+            (Some(first), Some(second)) if second > first => (1, Some(second)),
+            (Some(first), _) => (0, Some(first)),
+            (None, second) => (1, second),
+        };
+
+        let mut store = Self {
+            medium,
+            active,
+            generation,
+            end: WORDS,
+        };
+        if store.generation.is_some() {
+            store.end = store.records(active, |_, _, _| ());
+        }
+        store
+    }
+
+    /// Copies the value of the key into the buffer, returning the length of the value (which may
+    /// exceed that of the buffer), or `None` should the store hold no value for the key
+    pub fn get(&self, key: u16, buf: &mut [u8]) -> Option<usize> {
+        let (offset, len) = self.find(key)?;
+        let mut value = [0; MAX_VALUE];
+        self.value(self.active, offset, len, &mut value);
+
+        let copied = len.min(buf.len());
+        buf[..copied].copy_from_slice(&value[..copied]);
+        Some(len)
+    }
+
+    /// Sets the value of the key, unless it is unchanged
+    ///
+    /// Should the active page be full, the values are first copied to the other page, which is
+    /// the only time a page is erased.
+    pub fn set(&mut self, key: u16, value: &[u8]) -> Result<(), Error> {
+        if key == u16::MAX || value.len() > MAX_VALUE {
+            return Err(Error::Invalid);
+        }
+
+        let mut current = [0; MAX_VALUE];
+        if self.get(key, &mut current) == Some(value.len()) && current[..value.len()] == *value {
+            return Ok(());
+        }
+
+        let size = OVERHEAD + words(value.len());
+        if self.generation.is_none() || self.end + size > WORDS {
+            self.compact()?;
+        }
+        if self.end + size > WORDS {
+            return Err(Error::Full);
+        }
+
+        // the value is zero-padded to a whole number of words, as MAX_VALUE is
+        let mut bytes = [0; 4 + MAX_VALUE];
+        bytes[..4].copy_from_slice(&header(key, value.len()).to_le_bytes());
+        bytes[4..][..value.len()].copy_from_slice(value);
+
+        // the first word is programmed first, so that the words of a torn record are never reused
+        let (page, offset) = (self.active, self.end);
+        self.end += size;
+        for (index, word) in bytes[..4 * (size - 1)].chunks_exact(4).enumerate() {
+            let word = u32::from_le_bytes(word.try_into().unwrap());
+            self.medium.program(page, offset + index, word)?;
+        }
+        self.medium
+            .program(page, offset + size - 1, crc32(&bytes[..4 + value.len()]))
+    }
+
+    /// Consumes the store, returning its medium
+    pub fn into_medium(self) -> M {
+        self.medium
+    }
+
+    /// Copies the last intact value of every key to the inactive page, which then becomes active
+    fn compact(&mut self) -> Result<(), Error> {
+        let (from, to) = (self.active, 1 - self.active);
+        self.medium.erase(to)?;
+
+        let mut end = FIRST;
+        if self.generation.is_some() {
+            let mut offsets = [0_u16; WORDS / OVERHEAD];
+            let mut count = 0;
+            self.records(from, |offset, key, _| {
+                if self.find_in(from, key).map(|(last, _)| last) == Some(offset) {
+                    #[allow(clippy::cast_possible_truncation)] // offsets are within a page
+                    {
+                        offsets[count] = offset as u16;
+                    }
+                    count += 1;
+                }
+            });
+
+            for &offset in &offsets[..count] {
+                let offset = usize::from(offset);
+                let len = (self.medium.read(from, offset) >> 16) as usize;
+                for word in 0..OVERHEAD + words(len) {
+                    let value = self.medium.read(from, offset + word);
+                    self.medium.program(to, end + word, value)?;
+                }
+                end += OVERHEAD + words(len);
+            }
+        }
+
+        // the header is programmed last, so that the copy only takes effect once complete
+        let generation = self
+            .generation
+            .map_or(0, |generation| generation.wrapping_add(1));
+        self.medium.program(to, 0, generation)?;
+        self.medium.program(to, 1, MAGIC)?;
+
+        self.active = to;
+        self.generation = Some(generation);
+        self.end = end;
+        Ok(())
+    }
+
+    /// Finds the last intact record of the key in the active page, as its word and length
+    fn find(&self, key: u16) -> Option<(usize, usize)> {
+        self.generation?;
+        self.find_in(self.active, key)
+    }
+
+    /// Finds the last intact record of the key in the given page, as its word and length
+    fn find_in(&self, page: usize, key: u16) -> Option<(usize, usize)> {
+        let mut found = None;
+        self.records(page, |offset, record, len| {
+            if record == key {
+                found = Some((offset, len));
+            }
+        });
+        found
+    }
+
+    /// Calls the function with the word, key, and length of every intact record of the page,
+    /// returning the word after the last record (or the size of the page, should it be corrupt)
+    fn records(&self, page: usize, mut f: impl FnMut(usize, u16, usize)) -> usize {
+        let mut offset = FIRST;
+        while offset < WORDS {
+            let header = self.medium.read(page, offset);
+            if header == ERASED {
+                return offset;
+            }
+
+            #[allow(clippy::cast_possible_truncation)] // the key is the low half of the header
+            let (key, len) = (header as u16, (header >> 16) as usize);
+            if len > MAX_VALUE || offset + OVERHEAD + words(len) > WORDS {
+                break;
+            }
+
+            let mut bytes = [0; 4 + MAX_VALUE];
+            bytes[..4].copy_from_slice(&header.to_le_bytes());
+            self.value(page, offset, len, &mut bytes[4..]);
+            if self.medium.read(page, offset + OVERHEAD + words(len) - 1)
+                == crc32(&bytes[..4 + len])
+            {
+                f(offset, key, len);
+            }
+            offset += OVERHEAD + words(len);
+        }
+
+        WORDS
+    }
+
+    /// Reads the value of the record at the given word of the page into the buffer
+    fn value(&self, page: usize, offset: usize, len: usize, buf: &mut [u8]) {
+        for (index, chunk) in buf[..len].chunks_mut(4).enumerate() {
+            let word = self.medium.read(page, offset + 1 + index).to_le_bytes();
+            chunk.copy_from_slice(&word[..chunk.len()]);
+        }
+    }
+}
+
+/// The first word of a record of the given key and length
+#[allow(clippy::cast_possible_truncation)] // lengths never exceed MAX_VALUE
+fn header(key: u16, len: usize) -> u32 {
+    u32::from(key) | (len as u32) << 16
+}
+
+/// The number of words which hold a value of the given length
+fn words(len: usize) -> usize {
+    len.div_ceil(4)
+}
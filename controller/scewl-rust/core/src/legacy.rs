@@ -0,0 +1,34 @@
+//! The marking of legacy frames, which a controller accepts during a phased rollout of the secure
+//! handlers
+//!
+//! With the `mixed-mode` feature, a controller of the secure suite also accepts frames from other
+//! SEDs in the format of the trivial handlers, i.e. their bare plaintext, so that a deployment may
+//! be moved onto the secure suite one SED at a time. A frame on the radio which is too short to be
+//! a secure frame, or which fails its HMAC, is taken to be such a legacy frame. Should its content
+//! be [data](crate::content::Kind::Data), its body is forwarded to the CPU [marked](mark) as
+//! unauthenticated by [`MAGIC`], e.g.:
+//!
+//! ```text
+//! UNAUTH hello from 10
+//! ```
+//!
+//! Legacy frames of any other kind, such as the hellos of the legacy SEDs, are ignored, as they are
+//! not authenticated. Frames which are authentic but otherwise refused (e.g. replayed) are still
+//! dropped. The controller itself always transmits secure frames, which legacy SEDs cannot read,
+//! so a deployment should only remain in mixed mode until its last legacy SED has been replaced.
+
+/// The magic which prefixes the body of a legacy frame forwarded to the CPU
+pub const MAGIC: [u8; 7] = *b"UNAUTH ";
+
+/// Marks the body of a legacy frame, which is the first `len` bytes of the buffer, as
+/// unauthenticated by prefixing it with [`MAGIC`]
+///
+/// Returns the length of the marked body, or `None` should the buffer have no room for the magic.
+pub fn mark(buf: &mut [u8], len: usize) -> Option<usize> {
+    let marked = len
+        .checked_add(MAGIC.len())
+        .filter(|&marked| marked <= buf.len())?;
+    buf.copy_within(..len, MAGIC.len());
+    buf[..MAGIC.len()].copy_from_slice(&MAGIC);
+    Some(marked)
+}
@@ -0,0 +1,110 @@
+//! The maximum level at which messages are logged, which may be changed at runtime
+//!
+//! Each of the leveled logging macros is compiled out entirely above the maximum level selected at
+//! compile time with the `max-level-*` features, i.e. [`STATIC_MAX`]. Below that, the level may be
+//! raised or lowered at runtime with [`set_max`], e.g. by the [diagnostic command](crate::diag),
+//! so that a misbehaving unit may be made verbose without reflashing it. The level starts at
+//! [`STATIC_MAX`], such that every compiled-in message is logged until it is lowered.
+//!
+//! This crate has no transport of its own for the log, so each message is handed to the [sink]
+//! which the firmware (or a host tool) [installs](set_sink) at boot; until one is installed,
+//! messages are discarded.
+//!
+//! [sink]: set_sink
+
+use core::fmt::Arguments;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, AtomicU8, Ordering};
+
+/// The level of a logged message, in increasing order of verbosity
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
+pub enum Level {
+    /// Nothing is logged at this level; only useful as a maximum
+    Off = 0,
+    /// Logged by [`error!`](crate::error)
+    Error = 1,
+    /// Logged by [`warn!`](crate::warn)
+    Warn = 2,
+    /// Logged by [`info!`](crate::info)
+    Info = 3,
+    /// Logged by [`debug!`](crate::debug)
+    Debug = 4,
+    /// Logged by [`trace!`](crate::trace)
+    Trace = 5,
+}
+
+impl Level {
+    /// Decodes a level from its wire value
+    pub fn from_u8(level: u8) -> Option<Self> {
+        match level {
+            0 => Some(Level::Off),
+            1 => Some(Level::Error),
+            2 => Some(Level::Warn),
+            3 => Some(Level::Info),
+            4 => Some(Level::Debug),
+            5 => Some(Level::Trace),
+            _ => None,
+        }
+    }
+}
+
+/// The maximum level selected at compile time, above which messages are compiled out
+pub const STATIC_MAX: Level = if cfg!(feature = "max-level-off") {
+    Level::Off
+} else if cfg!(feature = "max-level-error") {
+    Level::Error
+} else if cfg!(feature = "max-level-warn") {
+    Level::Warn
+} else if cfg!(feature = "max-level-info") {
+    Level::Info
+} else if cfg!(feature = "max-level-debug") {
+    Level::Debug
+} else {
+    Level::Trace
+};
+
+/// The current maximum level, as the wire value of a [`Level`]
+static MAX: AtomicU8 = AtomicU8::new(STATIC_MAX as u8);
+
+/// The sink to which each message is written, as a `fn(Arguments<'_>)`, or null until installed
+static SINK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// The current maximum level at which messages are logged
+pub fn max() -> Level {
+    Level::from_u8(MAX.load(Ordering::Relaxed)).unwrap_or(STATIC_MAX)
+}
+
+/// Sets the maximum level at which messages are logged, returning the level which took effect
+///
+/// Levels above [`STATIC_MAX`] are clamped to it, as those messages are not compiled in.
+pub fn set_max(level: Level) -> Level {
+    let level = level.min(STATIC_MAX);
+    MAX.store(level as u8, Ordering::Relaxed);
+    level
+}
+
+/// Whether messages at the given level are currently logged
+#[doc(hidden)]
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= MAX.load(Ordering::Relaxed)
+}
+
+/// Installs the function to which every message logged from then on is written, e.g. over RTT or
+/// semihosting, prefixed with its level
+pub fn set_sink(sink: fn(Arguments<'_>)) {
+    SINK.store(sink as *mut (), Ordering::Release);
+}
+
+/// Writes a message to the installed sink, if any; used by the leveled logging macros
+#[doc(hidden)]
+pub fn emit(args: Arguments<'_>) {
+    let sink = SINK.load(Ordering::Acquire);
+    if sink.is_null() {
+        return;
+    }
+
+    // SAFETY: the only non-null pointer ever stored is a `fn(Arguments<'_>)`, by set_sink
+    let sink = unsafe { mem::transmute::<*mut (), fn(Arguments<'_>)>(sink) };
+    sink(args);
+}
@@ -0,0 +1,351 @@
+// Generate this documentation in a prettier form with `cargo doc --release --open`
+
+//! CaptureTheFlaggies controller implementation for MITRE eCTF!
+//!
+//! ## Building
+//!
+//! This crate is built as part of the `controller` firmware, which lies in the directory above it.
+//! To compile the firmware by hand, please ensure that you do the following:
+//!
+//!  - Install the following packages (or equivalent) for your operating system:
+//!    - `build-essential`
+//!    - `binutils-arm-none-eabi`
+//!    - `clang`
+//!    - `gcc-arm-none-eabi`
+//!  - Install Rust 1.51 via [Rustup](https://rustup.rs/)
+//!  - Install the `thumbv7m-none-eabi` target via rustup: `rustup target add thumbv7m-none-eabi`
+//!  - Build it! `SCEWL_ID=${SCEWL_ID} cargo build --release`, where `SCEWL_ID` is your intended id
+//!    for this instance. Optionally use `--features semihosted` to enable QEMU semihosting for
+//!    logging debug information to the host, or `--features rtt` to log over RTT to a debug probe
+//!    instead. You can also build without specifying a `SCEWL_ID`, but this will provide defaults
+//!    for the ID and the SED SSS registration secret.
+//!
+//! To run via QEMU, you need to perform an additional objcopy step, the output of which can then be
+//! used as a `-kernel` argument: `arm-none-eabi-objcopy -O binary target/thumbv7m-none-eabi/release/controller kernel`
+//!
+//! Otherwise, this crate can be used via the typical build process for the MITRE eCTF as specified
+//! in [getting_started.md](https://github.com/mitre-cyber-academy/2021-ectf-insecure-example/blob/master/getting_started.md).
+//!
+//! ## Design
+//!
+//! This implementation of the controller is very similar to the original provided in [MITRE's example implementation](https://github.com/mitre-cyber-academy/2021-ectf-insecure-example)
+//! with a few key differences:
+//!
+//!  - Hopefully, it's not insecure!
+//!  - As you probably already know, this implementation is written in Rust and, as such, does _not_
+//!    depend on the provided lm3s or CMSIS dependencies. Instead, the [cortex-m](https://docs.rs/cortex-m/0.7.2/cortex_m/)
+//!    crate and the [lm3s6965](https://docs.rs/lm3s6965/0.1.3/lm3s6965/) crates provided by the
+//!    [Rust Embedded Cortex-M team](https://github.com/rust-embedded/wg#the-cortex-m-team) and
+//!    [Jorge Aparicio](https://github.com/japaric), respectively, are used to provide the basic
+//!    embedded systems operations necessary to run on the lm3s6965 processor.
+//!  - This crate uses _minimal unsafe operations_, and none at all on the hardware. The firmware
+//!    confines those to its drivers, e.g. the interface, whose read/write operations on the
+//!    UART{0,1,2} peripherals go via memory-mapped registers.
+//!  - The original implementation defined functions which operated on structs; in this crate, we
+//!    define structs with methods to perform the operations, which more idiomatically represents
+//!    the controller's operations.
+//!
+//! ### Structure
+//!
+//! Where the original implementation used functions which interacted with structs in C, this
+//! implementation attempts to more ergonomically represent operations taken by the controller by
+//! recognising that the only communications methods which are permitted to be modified are those
+//! between the SSS and other SEDs (excluding FAA); otherwise, information is effectively
+//! transparently proxied to and from the CPU.
+//!
+//! To account for this abstraction, we separate the controller into the following modules:
+//!
+//!  - The controller itself, the driver for communications, which employs an crypto handler and
+//!    an authentication handler
+//!  - The crypto handler, which decrypts/encrypts information to/from the CPU to other SEDs
+//!  - The authentication handler, which interacts with the SSS and generates the crypto handler
+//!    specified by that SSS
+//!
+//! As we wish to test the basic operation of the communications channel as well as the additional
+//! security features on top, it behooves us to employ [type generics](https://doc.rust-lang.org/book/ch10-01-syntax.html)
+//! to allow for plug-and-play replacements for both the crypto handler and authentication
+//! handler. To do so, we define traits for [encryption](crypto::Handler) and
+//! [authentication](auth::Handler). The [controller implementation](controller::Controller)
+//! is generified to support arbitrary implementations of these handlers, restricting their use to
+//! only the permitted changes as defined in the MITRE eCTF specification.
+//!
+//! ## Implementation
+//!
+//! ### Interface
+//!
+//! As we wished to omit C dependencies entirely, some research was done to identify the mechanism
+//! by which the [interface](https://github.com/mitre-cyber-academy/2021-ectf-insecure-example/blob/master/controller/interface.c)
+//! implemented input/output from/to the sockets over which communication was emulated.
+//!
+//! To match the behaviour of the original interface code, both the original C implementation and
+//! portions of the lm3s dependency were inspected and subsequently ported to Rust. A discussion on
+//! the details of this is available in the documentation of the firmware's interface module.
+//! The controller itself reads and writes each line through the [`Transport`](transport::Transport)
+//! trait, which the interface implements, so that it may be run over other transports too.
+//!
+//! ### Controller
+//!
+//! As previously discussed, the controller was modularised to support plug-and-play compatibility
+//! with various encryption and authentication handlers. The controller implementation in Rust is
+//! a near direct port of the C implementation with minor changes to support different handlers;
+//! further discussion of these changes are available in the [controller module documentation](controller).
+//!
+//! ### Handlers
+//!
+//! Present in this crate are two handler families: a [trivial implementation](trivial), which, as
+//! the name suggests, trivially implements the encryption and authentication schemes (read: none)
+//! leveraged by the [insecure controller implementation](https://github.com/mitre-cyber-academy/2021-ectf-insecure-example/blob/master/controller/controller.c).
+//!
+//! The second handler family is the one used in production to be targeted by live adversaries and
+//! is denoted as the [secure implementation](secure). This implementation leverages multiple
+//! security features to ensure that messages cannot be intercepted, modified, or replayed. A full
+//! discussion on those security features can be found in the documentation for that module.
+//!
+//! ## Features
+//!
+//! This library is the controller itself, and is hardware-free: it reaches the lm3s6965 only
+//! through the [board](board::Board), [transport](transport::Transport), and [clock](time::Clock)
+//! traits, and through the traits by which the handlers [gather entropy](secure::Entropy) and
+//! [persist](secure::Checkpoints) their state, all of which the `controller` firmware implements
+//! over the hardware. The firmware, the host tools (such as the mock SSS), and the tests thus all
+//! share this one implementation. The library is split by feature, so that each may take only as
+//! much of it as it needs:
+//!
+//!  - `codec`: the [frame codec](codec), [cursors](cursor), [diagnostic command](diag),
+//!    [frame policy](policy), [content kinds](content), [MTU negotiation](mtu),
+//!    [fragmentation](fragment), [outbound queue](outbound), [logical ports](port), [key-value
+//!    store](kv), [provisioning record](provision), [update protocol](update), and the generator
+//!    of the [C header](cpu_header) for the CPU, with no dependencies at all
+//!  - `crypto`: additionally the [crypto handler trait](crypto::Handler) and the crypto handlers
+//!    of both handler families
+//!  - `controller`: additionally the [controller](controller), the [board](board) on which it
+//!    runs, and the authentication handlers
+//!  - `std`: links the standard library, for host-side tooling such as the throughput benchmark
+//!  - `bench`: the [throughput benchmark](bench), shared by the host simulation and the target
+//!  - `mock-clock`: a [test-controlled clock](time::MockClock), for testing anything time-dependent
+//!  - `logging`: logs are written to the [sink](level::set_sink) which the firmware installs
+//!
+//! The remaining features (`fragmentation`, `prioritized`, `ports`, `heartbeat`, `drop-notices`,
+//! `integrity`, `update`, `scripted`, `mixed-mode`, `dyn-handlers`, `pq`, `cmac`, `hexdump`,
+//! `insecure-logging`, `panic-codes`, `stripped`, and `dbg-invariants`) each compile the like-named feature
+//! into the controller; the firmware, which enables them, describes each.
+//!
+//! The [time](time), [session](session), [interrupt queue](queue), [log level](level), [fatal
+//! error](fatal), [glitch hardening](glitch), [constant-time comparison](ct), and [masked
+//! secret](masked) modules are always available, as they have no dependencies.
+//!
+//! ## Logging
+//!
+//! Logging is only compiled in with the `logging` feature, which the firmware enables along with a
+//! transport (i.e. its `semihosted` or `rtt` feature), writing each log to the host through the
+//! [sink](level::set_sink) it installs at boot. Messages are logged at one of five levels with
+//! [`error!`], [`warn!`], [`info!`], [`debug!`], and [`trace!`]; every level is compiled in by default, but a maximum level may be selected at
+//! compile time with one of the `max-level-{off,error,warn,info,debug}` features. For example,
+//! `--features semihosted,max-level-info` reports drops and (de)registrations, but none of the
+//! per-message traces. Within that bound, the [level](level) may also be changed at runtime. As
+//! with any macro, the features are those of the crate in which the logging macros are expanded,
+//! so a crate which logs with them must forward these features to this one, as the firmware does.
+//!
+//! With the `dbg-invariants` feature, cheap runtime checks of the controller's internal
+//! consistency are compiled in with [`invariant!`]; a violation is logged as an error and
+//! recovered from (usually by dropping the message at hand) rather than panicking.
+//!
+//! With the `stripped` feature, the firmware links as little formatting code as possible: every
+//! log is compiled out (as with `max-level-off`), a logging transport is refused, [fatal
+//! errors](fatal) panic with only their number (as with `panic-codes`), and the
+//! [crash log](crashlog) records only the location and constant message of a panic. Only the
+//! bounds check panics of `core` still format, which only a nightly toolchain could avoid. Together with the flash budget of
+//! the deployment configuration, this keeps the image within the flash of the lm3s6965.
+//!
+//! Message contents are [redacted](redact) from the log unless the `insecure-logging` feature is
+//! enabled, which is refused in release builds; key material is never logged. The `hexdump`
+//! feature additionally [dumps](hexdump) every frame at each stage of the pipeline.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(clippy::pedantic)] // enforce pedantic checks -- false positive prone
+#![deny(clippy::missing_docs_in_private_items)] // enforce documentation
+#![allow(clippy::upper_case_acronyms)] // SCEWL, SSS, FAA, etc. are named as in the specification
+#![allow(clippy::must_use_candidate, clippy::return_self_not_must_use)] // pure (de)serialisers
+#![allow(clippy::missing_panics_doc)] // panics are documented at the module level
+#![allow(clippy::missing_errors_doc)] // errors are documented by their respective error types
+
+#[cfg(all(feature = "insecure-logging", not(debug_assertions)))]
+compile_error!("insecure-logging leaks plaintext and may not be enabled in release builds");
+
+#[cfg(feature = "controller")]
+pub mod auth;
+#[cfg(feature = "codec")]
+pub mod banner;
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(feature = "controller")]
+pub mod board;
+pub mod build_info;
+#[cfg(feature = "codec")]
+pub mod codec;
+#[cfg(feature = "codec")]
+pub mod content;
+#[cfg(feature = "controller")]
+pub mod controller;
+#[cfg(feature = "codec")]
+pub mod cpu_header;
+#[cfg(feature = "controller")]
+pub mod crashlog;
+#[cfg(feature = "crypto")]
+pub mod crypto;
+pub mod ct;
+#[cfg(feature = "codec")]
+pub mod cursor;
+pub mod deployment;
+#[cfg(feature = "codec")]
+pub mod diag;
+pub mod fatal;
+#[cfg(feature = "codec")]
+pub mod fragment;
+pub mod glitch;
+#[cfg(feature = "codec")]
+pub mod heartbeat;
+#[cfg(feature = "hexdump")]
+pub mod hexdump;
+#[cfg(feature = "integrity")]
+pub mod integrity;
+#[cfg(feature = "codec")]
+pub mod kv;
+#[cfg(feature = "codec")]
+pub mod legacy;
+pub mod level;
+pub mod masked;
+#[cfg(feature = "codec")]
+pub mod mtu;
+#[cfg(feature = "codec")]
+pub mod outbound;
+#[cfg(feature = "codec")]
+pub mod policy;
+#[cfg(feature = "codec")]
+pub mod port;
+#[cfg(feature = "codec")]
+pub mod provision;
+pub mod queue;
+#[cfg(feature = "crypto")]
+pub mod redact;
+pub mod scratch;
+#[cfg(feature = "scripted")]
+pub mod script;
+#[cfg(feature = "crypto")]
+pub mod secure;
+pub mod session;
+pub mod time;
+#[cfg(feature = "codec")]
+pub mod transport;
+#[cfg(feature = "crypto")]
+pub mod trivial;
+#[cfg(feature = "codec")]
+pub mod update;
+
+/// Writes a line of logging information to the [sink](level::set_sink) installed by the firmware,
+/// prefixed with its level; used by the leveled logging macros
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log {
+    ($level: ident, $label: literal, $fmt: literal $(, $args: expr)* $(,)?) => {
+        #[cfg(feature = "logging")]
+        if $crate::level::enabled($crate::level::Level::$level) {
+            $crate::level::emit(format_args!(concat!("[", $label, "] ", $fmt) $(, $args)*));
+        }
+    };
+}
+
+/// Logs an error, i.e. an event which indicates an attack or a fault; compiled out only with
+/// `max-level-off`
+#[macro_export]
+macro_rules! error {
+    ($($args: tt)+) => {
+        #[cfg(not(feature = "max-level-off"))]
+        $crate::__log!(Error, "ERROR", $($args)+);
+    };
+}
+
+/// Logs a warning, e.g. a message which was dropped; compiled out with `max-level-error` or lower
+#[macro_export]
+macro_rules! warn {
+    ($($args: tt)+) => {
+        #[cfg(not(any(feature = "max-level-off", feature = "max-level-error")))]
+        $crate::__log!(Warn, "WARN", $($args)+);
+    };
+}
+
+/// Logs a notable change of state, e.g. a (de)registration; compiled out with `max-level-warn` or
+/// lower
+#[macro_export]
+macro_rules! info {
+    ($($args: tt)+) => {
+        #[cfg(not(any(
+            feature = "max-level-off",
+            feature = "max-level-error",
+            feature = "max-level-warn"
+        )))]
+        $crate::__log!(Info, "INFO", $($args)+);
+    };
+}
+
+/// Logs the handling of a single message; compiled out with `max-level-info` or lower
+#[macro_export]
+macro_rules! debug {
+    ($($args: tt)+) => {
+        #[cfg(not(any(
+            feature = "max-level-off",
+            feature = "max-level-error",
+            feature = "max-level-warn",
+            feature = "max-level-info"
+        )))]
+        $crate::__log!(Debug, "DEBUG", $($args)+);
+    };
+}
+
+/// Logs the detail of handling a single message, including buffer contents; compiled out with
+/// `max-level-debug` or lower
+#[macro_export]
+macro_rules! trace {
+    ($($args: tt)+) => {
+        #[cfg(not(any(
+            feature = "max-level-off",
+            feature = "max-level-error",
+            feature = "max-level-warn",
+            feature = "max-level-info",
+            feature = "max-level-debug"
+        )))]
+        $crate::__log!(Trace, "TRACE", $($args)+);
+    };
+}
+
+/// Checks a cheap runtime invariant when the `dbg-invariants` feature is enabled, evaluating to
+/// whether it held
+///
+/// A violated invariant is logged as an error rather than panicking, so that the caller may
+/// recover (usually by dropping the message at hand) and a test unit keeps running. Without the
+/// feature, the condition is never evaluated and the invariant always holds.
+#[macro_export]
+macro_rules! invariant {
+    ($cond: expr) => {{
+        #[cfg(feature = "dbg-invariants")]
+        let held = $cond;
+        #[cfg(not(feature = "dbg-invariants"))]
+        let held = {
+            // type-checked, such that the operands do not go unused, but never evaluated
+            if false {
+                let _ = $cond;
+            }
+            true
+        };
+
+        if !held {
+            $crate::error!(
+                "Invariant violated at {}:{}: {}",
+                file!(),
+                line!(),
+                stringify!($cond)
+            );
+        }
+        held
+    }};
+}
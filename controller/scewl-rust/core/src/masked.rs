@@ -0,0 +1,81 @@
+//! The registration secret as it is stored in flash, masked, so that it is never present there as
+//! such
+//!
+//! build.rs splits each secret compiled into the image into two shares whose XOR is the secret: a
+//! mask derived from the secret (salted with `SOURCE_DATE_EPOCH`, so that builds are reproducible),
+//! and the XOR of the secret and the mask. The shares are placed in sections of their own
+//! (`.rodata.mask` and `.rodata.secret`), so that a flash dump holds neither the secret nor
+//! anything which may be found by searching for it. The secret is only
+//! [reconstructed](Masked::unmask) on the stack by the authentication handler for as long as it
+//! needs it, and is wiped as soon as it is dropped.
+//!
+//! This is obfuscation rather than encryption: the lm3s6965 has no device-unique key under which
+//! the secret could be sealed, so whoever has the whole image and knows the scheme can still
+//! recombine the shares. With the `mpu` feature, the share of the masked secret is
+//! locked away by the firmware's MPU while registered, without which the mask is of no use.
+
+use core::ops::Deref;
+use core::ptr;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+/// A share of zeros, which masks a secret stored as it is
+static ZEROS: [u8; 64] = [0; 64];
+
+/// A registration secret, stored as two shares whose XOR is the secret
+#[derive(Copy, Clone)]
+pub struct Masked {
+    /// The mask with which the secret was split
+    mask: &'static [u8; 64],
+    /// The XOR of the secret and the mask
+    masked: &'static [u8; 64],
+}
+
+impl Masked {
+    /// A secret stored as the given shares, as split by build.rs
+    pub const fn new(mask: &'static [u8; 64], masked: &'static [u8; 64]) -> Self {
+        Self { mask, masked }
+    }
+
+    /// A secret stored as it is, such as that of a [provisioning record](crate::provision), which
+    /// is masked with zeros
+    pub const fn plain(secret: &'static [u8; 64]) -> Self {
+        Self::new(&ZEROS, secret)
+    }
+
+    /// The share of the masked secret, which the firmware's MPU guards
+    pub fn share(&self) -> &'static [u8; 64] {
+        self.masked
+    }
+
+    /// Reconstructs the secret on the stack, where it stays until the result is dropped
+    ///
+    /// The result should be bound and borrowed rather than moved, so that no copy of the secret is
+    /// left behind where the wipe does not reach.
+    pub fn unmask(&self) -> Unmasked {
+        let mut secret = Unmasked([0; 64]);
+        for ((b, mask), masked) in secret.0.iter_mut().zip(self.mask).zip(self.masked) {
+            *b = mask ^ masked;
+        }
+        secret
+    }
+}
+
+/// A registration secret reconstructed by [`Masked::unmask`], which is wiped once dropped
+pub struct Unmasked([u8; 64]);
+
+impl Deref for Unmasked {
+    type Target = [u8; 64];
+
+    fn deref(&self) -> &[u8; 64] {
+        &self.0
+    }
+}
+
+impl Drop for Unmasked {
+    fn drop(&mut self) {
+        // SAFETY: the pointer is derived from a mutable reference to the secret, so is valid and
+        // aligned; the write is volatile so that it is not elided as dead, being the last
+        unsafe { ptr::write_volatile(ptr::from_mut(&mut self.0), [0; 64]) };
+        compiler_fence(Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,104 @@
+//! The negotiation of the largest frame which each SED accepts over the radio, i.e. its MTU
+//!
+//! Every controller enforces its own MTU on the frames it receives over the radio, which is
+//! configured per SED (`[radio] mtu`) and defaults to what the data buffer holds. So that a
+//! sender need not assume that every peer can buffer that much, each controller announces its MTU
+//! in a [`Hello`] as soon as it registers: broadcast to every peer, each of which records it and
+//! answers with a direct hello of its own, so that the newcomer learns theirs in turn. Hellos are
+//! sent and received as any other message between SEDs, i.e. authenticated and encrypted by the
+//! crypto handler, but as content of their own [kind](crate::content::Kind::Hello), so that they
+//! are consumed by the controller rather than forwarded to the CPU.
+//!
+//! A message to a peer whose MTU it would exceed once encrypted is refused rather than sent. The
+//! MTU of a peer which has yet to say hello is taken to be [`DEFAULT`], as before negotiation.
+//!
+//! The body of a hello is laid out as `mtu: u16`. Only content of the hello kind is taken for a
+//! hello, so data from a CPU is never mistaken for one, whatever it begins with.
+
+use core::mem::size_of;
+
+use crate::codec::{Id, SCEWL_MAX_DATA_SZ};
+use crate::cursor::{ReadCursor, WriteCursor};
+use crate::deployment::PEER_CAPACITY;
+
+/// The MTU of a peer which has not said hello, which is the size of the data buffer
+#[allow(clippy::cast_possible_truncation)] // the data buffer is smaller than 64 KiB
+pub const DEFAULT: u16 = SCEWL_MAX_DATA_SZ as u16;
+
+/// The announcement of a controller's MTU to its peers
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Hello {
+    /// The largest frame which the controller accepts over the radio, in bytes
+    pub mtu: u16,
+}
+
+impl Hello {
+    /// Serialises this hello to the buffer, returning its length
+    pub fn to_bytes(self, buf: &mut [u8]) -> usize {
+        WriteCursor::new(buf).write_u16(self.mtu);
+        Hello::size()
+    }
+
+    /// Deserialises a hello from the body of content of the hello kind, should it be the size of one
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        (buf.len() == Hello::size()).then(|| Hello {
+            mtu: ReadCursor::new(buf).read_u16(),
+        })
+    }
+
+    /// The constant size of a hello in its serialised form
+    pub const fn size() -> usize {
+        size_of::<u16>()
+    }
+}
+
+/// The MTUs announced by the peers of a controller
+///
+/// The table has room for every peer of the deployment; should more say hello, the later ones
+/// keep the [`DEFAULT`] MTU.
+pub struct Peers {
+    /// The id and MTU of each peer which has said hello, in the order they did
+    mtus: [(Id, u16); PEER_CAPACITY as usize],
+    /// The number of peers in the table
+    len: usize,
+}
+
+impl Default for Peers {
+    fn default() -> Self {
+        Peers {
+            mtus: [(Id::Broadcast, DEFAULT); PEER_CAPACITY as usize],
+            len: 0,
+        }
+    }
+}
+
+impl Peers {
+    /// Records the MTU announced by the given peer, returning whether there was room to
+    pub fn record(&mut self, id: Id, mtu: u16) -> bool {
+        if let Some(entry) = self.mtus[..self.len]
+            .iter_mut()
+            .find(|(peer, _)| *peer == id)
+        {
+            entry.1 = mtu;
+        } else if self.len < self.mtus.len() {
+            self.mtus[self.len] = (id, mtu);
+            self.len += 1;
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// The MTU of the given peer, which is [`DEFAULT`] should it not have said hello
+    pub fn mtu(&self, id: Id) -> u16 {
+        self.mtus[..self.len]
+            .iter()
+            .find(|(peer, _)| *peer == id)
+            .map_or(DEFAULT, |&(_, mtu)| mtu)
+    }
+
+    /// Forgets every peer, e.g. when the controller deregisters
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
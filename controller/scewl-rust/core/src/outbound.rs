@@ -0,0 +1,173 @@
+//! The queue of frames awaiting transmission over the radio, which are sent in order of priority
+//! rather than of arrival
+//!
+//! Ordinarily, a frame is written to the radio as soon as it is ready, so that a large broadcast
+//! holds up whatever the CPU sends next, however urgent. With the `prioritized` feature, each frame
+//! for the radio is instead queued, complete with its header, in a buffer of [`CAPACITY`] bytes,
+//! and the controller's run loop writes the queue out [`BURST`] bytes at a time, between which it
+//! goes on reading from the CPU. Each time a frame has been written out in full, the next is the
+//! oldest of the highest [`Class`] queued; a frame is never interrupted once begun, so that frames
+//! are not interleaved on the radio.
+//!
+//! A frame for which the queue has no room waits for the queue to be written out until there is,
+//! or, should it be larger than the whole queue, until the queue is empty, and is then written out
+//! at once.
+//!
+//! Each frame is held as `class: u8 | len: u16 | frame`; the frame being written out is always at
+//! the front.
+
+use core::convert::TryFrom;
+use core::mem::size_of;
+
+use crate::codec::Id;
+use crate::cursor::{ReadCursor, WriteCursor};
+
+/// The size of the queue, which holds most frames but the very largest
+pub const CAPACITY: usize = 0x1800;
+
+/// The most bytes written out of the queue on each pass of the run loop
+pub const BURST: usize = 64;
+
+/// The size of the record which precedes each frame in the queue
+const RECORD: usize = size_of::<u8>() + size_of::<u16>();
+
+/// The priority classes of frames on the radio, of which the lesser are sent first
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Class {
+    /// Frames to the FAA, which are the most urgent
+    Faa = 0,
+    /// Frames to another SED
+    Direct = 1,
+    /// Broadcasts to every SED, which are sent as bulk
+    Broadcast = 2,
+}
+
+impl Class {
+    /// The class of a frame to the given target
+    pub fn of(tgt_id: Id) -> Class {
+        match tgt_id {
+            Id::FAA => Class::Faa,
+            Id::Broadcast => Class::Broadcast,
+            _ => Class::Direct,
+        }
+    }
+
+    /// The class of the given wire value, as held in the queue
+    fn from_u8(value: u8) -> Class {
+        match value {
+            0 => Class::Faa,
+            1 => Class::Direct,
+            _ => Class::Broadcast,
+        }
+    }
+}
+
+/// The frames awaiting transmission, as described in the [module documentation](self)
+pub struct Outbound<'a> {
+    /// The buffer in which the frames are held, back to back
+    buf: &'a mut [u8],
+    /// The number of bytes of the buffer in use
+    len: usize,
+    /// The number of bytes of the frame at the front written out so far, should it have been begun
+    sent: Option<usize>,
+}
+
+impl<'a> Outbound<'a> {
+    /// An empty queue in the given buffer
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Outbound {
+            buf,
+            len: 0,
+            sent: None,
+        }
+    }
+
+    /// Whether no frame is queued
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether there is room to queue a frame of the given length
+    pub fn fits(&self, len: usize) -> bool {
+        u16::try_from(len).is_ok() && self.len + RECORD + len <= self.buf.len()
+    }
+
+    /// Queues the frame made up of the given parts (e.g. its header and body) in the given class,
+    /// returning whether there was room to
+    pub fn push(&mut self, class: Class, parts: &[&[u8]]) -> bool {
+        let len: usize = parts.iter().map(|part| part.len()).sum();
+        if !self.fits(len) {
+            return false;
+        }
+
+        #[allow(clippy::cast_possible_truncation)] // checked to fit by fits
+        WriteCursor::new(&mut self.buf[self.len..])
+            .write(&[class as u8])
+            .write_u16(len as u16);
+        self.len += RECORD;
+        for part in parts {
+            self.buf[self.len..][..part.len()].copy_from_slice(part);
+            self.len += part.len();
+        }
+        true
+    }
+
+    /// The rest of the frame being written out, beginning the next should none be; this is the
+    /// oldest frame of the highest class queued
+    pub fn front(&mut self) -> Option<&[u8]> {
+        if self.is_empty() {
+            return None;
+        }
+
+        if self.sent.is_none() {
+            self.promote();
+        }
+        let sent = *self.sent.get_or_insert(0);
+        let len = self.frame_len(0);
+        Some(&self.buf[RECORD + sent..RECORD + len])
+    }
+
+    /// Marks the given number of bytes of the [front](Outbound::front) frame as written out,
+    /// dropping the frame from the queue once it has been written out in full
+    pub fn consume(&mut self, n: usize) {
+        let Some(sent) = self.sent else {
+            return;
+        };
+
+        let len = self.frame_len(0);
+        let sent = sent + n;
+        if sent < len {
+            self.sent = Some(sent);
+            return;
+        }
+
+        self.buf.copy_within(RECORD + len..self.len, 0);
+        self.len -= RECORD + len;
+        self.buf[self.len..].fill(0);
+        self.sent = None;
+    }
+
+    /// The length of the frame whose record is at the given offset
+    fn frame_len(&self, at: usize) -> usize {
+        usize::from(ReadCursor::new(&self.buf[at + 1..]).read_u16())
+    }
+
+    /// Moves the oldest frame of the highest class queued to the front, keeping the order of the
+    /// rest
+    fn promote(&mut self) {
+        let mut best: Option<(Class, usize, usize)> = None;
+        let mut at = 0;
+        while at < self.len {
+            let class = Class::from_u8(self.buf[at]);
+            let size = RECORD + self.frame_len(at);
+            if best.is_none_or(|(best, _, _)| class < best) {
+                best = Some((class, at, size));
+            }
+            at += size;
+        }
+
+        if let Some((_, at, size)) = best {
+            self.buf[..at + size].rotate_right(size);
+        }
+    }
+}
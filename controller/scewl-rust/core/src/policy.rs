@@ -0,0 +1,279 @@
+//! The hard limits which every frame must meet before the controller reads its body, kept in one
+//! place rather than scattered through the handlers
+//!
+//! The controller's [`read_msg`](crate::controller::Controller::read_msg) hands the header of each
+//! frame to its [`Policy`], which [admits](Policy::admit) it or gives the [reason](Reason) for
+//! which it is dropped; a dropped frame's body is discarded unread. A frame is dropped should:
+//!
+//!  - either of its ids be non-canonical, i.e. one of the reserved values `0` to `2` held as
+//!    [`Id::Other`], which no wire value decodes to but which a bug might construct
+//!  - its source be a reserved id which may not send on its link: nothing sends as the broadcast
+//!    id, and the SSS is never heard on the radio (nor addressed over it)
+//!  - its source be spoofed: only the CPU may send as this controller, and the CPU only as it
+//!  - its length exceed the room for its body, which is the least of the caller's limit and the
+//!    space in the data buffer after where the body would be read
+//!  - its source have exhausted its budget of frames over the radio, or every source together
+//!    the radio's, should the policy have been given a [rate limit](Policy::with_rate_limit)
+//!  - its source on the radio have been [revoked](Policy::revoke) by the SSS since it deregistered,
+//!    even should the frame be protected under keys which this controller holds
+//!  - its source on the radio be another SED outside the [allowlist](Policy::allow) provisioned by
+//!    the SSS at registration, should the deployment partition its SEDs into groups
+//!
+//! Rate limits are token buckets, refilled continuously at their rate and holding at most a
+//! second's worth of frames, so that a source may burst up to its rate after a quiet spell but
+//! never sustain more. Each source is limited in one of [`BUCKETS`] buckets, selected by id, so
+//! that the state is of a fixed size regardless of the deployment; sources which share a bucket
+//! share its budget. Every source together is limited by one more bucket, so that a transmitter
+//! cycling through spoofed ids still cannot keep the controller busy verifying garbage. A frame
+//! refused by either is not charged to the other.
+//! Likewise, at most [`REVOKED`] sources are held as revoked; a longer list is refused whole, so
+//! that no revoked source is silently admitted. The allowlist holds at most [`ALLOWED`] SEDs, and a
+//! longer one is refused whole, so that no SED outside it is silently admitted either.
+//!
+//! The allowlist only ever restricts the other SEDs: the FAA, the SSS, and broadcasts (by target)
+//! are always allowed. The controller checks the targets of direct messages from the CPU against
+//! it likewise, by [`is_allowed`](Policy::is_allowed).
+
+use core::time::Duration;
+
+use crate::codec::{Id, MessageHeader};
+use crate::diag::Reason;
+use crate::time::{Clock, Instant};
+
+/// The number of frames per second which the firmware accepts from each source on the radio, far
+/// more than any CPU legitimately sends but few enough that a flood cannot monopolise the controller
+pub const DEFAULT_RATE: u16 = 200;
+
+/// The number of frames per second which the firmware accepts from every source on the radio
+/// together, which bounds the time spent verifying frames however many ids a flood cycles through
+pub const DEFAULT_GLOBAL_RATE: u16 = 400;
+
+/// The number of buckets in which sources are rate limited
+pub const BUCKETS: usize = 64;
+
+/// The most sources which may be held as revoked at once
+pub const REVOKED: usize = 32;
+
+/// The most SEDs which may be held on the allowlist at once
+pub const ALLOWED: usize = 32;
+
+/// The number of tokens which a frame costs, so that buckets refilled every millisecond at a rate
+/// in frames per second gain a whole number of tokens
+const FRAME: u32 = 1000;
+
+/// The link on which a frame was received
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Link {
+    /// The link to the CPU
+    Cpu = 0,
+    /// The link to the SSS
+    Sss = 1,
+    /// The radio, shared with the other SEDs and the FAA
+    Radio = 2,
+}
+
+impl Link {
+    /// The number of links
+    pub const COUNT: usize = 3;
+
+    /// Every link, in the order of their wire values in the [stats](crate::diag::Traffic)
+    pub const ALL: [Link; Link::COUNT] = [Link::Cpu, Link::Sss, Link::Radio];
+}
+
+/// The token buckets by which frames received over the radio are rate limited, each holding
+/// [`FRAME`] tokens per frame of its budget
+struct Rate<'a> {
+    /// The clock by which the buckets are refilled
+    clock: &'a dyn Clock,
+    /// The most frames per second which each bucket of sources may receive
+    per_source: u16,
+    /// The most frames per second which every source together may receive
+    global: u16,
+    /// When the buckets were last refilled
+    refilled: Instant,
+    /// The tokens of each bucket of sources
+    sources: [u32; BUCKETS],
+    /// The tokens shared by every source
+    shared: u32,
+}
+
+impl Rate<'_> {
+    /// Refills a bucket of the given rate for the given number of milliseconds
+    fn refill(tokens: &mut u32, rate: u16, millis: u32) {
+        *tokens = tokens
+            .saturating_add(millis * u32::from(rate))
+            .min(u32::from(rate) * FRAME);
+    }
+
+    /// Charges a frame from the given source, returning the reason for which it is refused should
+    /// either its bucket or the shared one be exhausted
+    fn admit(&mut self, src: Id) -> Result<(), Reason> {
+        let now = self.clock.now();
+        let elapsed = now.saturating_duration_since(self.refilled);
+        if elapsed > Duration::ZERO {
+            // a second refills any bucket, so longer spells need not be counted
+            #[allow(clippy::cast_possible_truncation)] // at most a second's worth of milliseconds
+            let millis = elapsed.as_millis().min(1000) as u32;
+            for tokens in &mut self.sources {
+                Rate::refill(tokens, self.per_source, millis);
+            }
+            Rate::refill(&mut self.shared, self.global, millis);
+            self.refilled = now;
+        }
+
+        let tokens = &mut self.sources[usize::from(u16::from(src)) % BUCKETS];
+        if *tokens < FRAME {
+            return Err(Reason::RateLimited);
+        }
+        if self.shared < FRAME {
+            return Err(Reason::Flood);
+        }
+        *tokens -= FRAME;
+        self.shared -= FRAME;
+        Ok(())
+    }
+}
+
+/// The limits enforced on the frames received by a controller, as described in the [module
+/// documentation](self)
+pub struct Policy<'a> {
+    /// The id of the controller
+    id: Id,
+    /// The rate limit on frames received over the radio, if any
+    rate: Option<Rate<'a>>,
+    /// The sources revoked by the SSS, of which the first `revoked_len` are held
+    revoked: [Id; REVOKED],
+    /// The number of sources held as revoked
+    revoked_len: usize,
+    /// The serial of the list of revoked sources last taken up, if any
+    serial: Option<u32>,
+    /// The SEDs allowed by the SSS, of which the first `allowed_len` are held
+    allowed: [Id; ALLOWED],
+    /// The number of SEDs held as allowed, should the SSS have provisioned an allowlist at all
+    allowed_len: Option<usize>,
+}
+
+impl<'a> Policy<'a> {
+    /// The policy of the controller of the given id, without a rate limit
+    pub fn new(id: Id) -> Self {
+        Policy {
+            id,
+            rate: None,
+            revoked: [Id::Broadcast; REVOKED],
+            revoked_len: 0,
+            serial: None,
+            allowed: [Id::Broadcast; ALLOWED],
+            allowed_len: None,
+        }
+    }
+
+    /// Limits each source on the radio to the first number of frames per second, and every source
+    /// together to the second, as timed by the clock
+    pub fn with_rate_limit(mut self, clock: &'a dyn Clock, per_source: u16, global: u16) -> Self {
+        self.rate = Some(Rate {
+            clock,
+            per_source,
+            global,
+            refilled: clock.now(),
+            sources: [u32::from(per_source) * FRAME; BUCKETS],
+            shared: u32::from(global) * FRAME,
+        });
+        self
+    }
+
+    /// Replaces the sources held as revoked with those of a list pushed by the SSS, returning
+    /// whether it was taken up: a list is refused should its serial be no higher than that of the
+    /// list last taken up, or should it hold more than [`REVOKED`] sources
+    pub fn revoke(&mut self, serial: u32, revoked: impl ExactSizeIterator<Item = Id>) -> bool {
+        if self.serial.is_some_and(|last| serial <= last) || revoked.len() > REVOKED {
+            return false;
+        }
+
+        self.revoked_len = 0;
+        for id in revoked {
+            self.revoked[self.revoked_len] = id;
+            self.revoked_len += 1;
+        }
+        self.serial = Some(serial);
+        true
+    }
+
+    /// Forgets the sources held as revoked, and the serial of their list, as the SSS pushes its
+    /// list afresh at each registration
+    pub fn clear_revoked(&mut self) {
+        self.revoked_len = 0;
+        self.serial = None;
+    }
+
+    /// Whether the given source has been revoked by the SSS
+    pub fn is_revoked(&self, src: Id) -> bool {
+        self.revoked[..self.revoked_len].contains(&src)
+    }
+
+    /// Restricts the other SEDs to those of the allowlist provisioned by the SSS, returning whether
+    /// it was taken up: an allowlist holding more than [`ALLOWED`] SEDs is refused
+    pub fn allow(&mut self, allowed: impl ExactSizeIterator<Item = Id>) -> bool {
+        if allowed.len() > ALLOWED {
+            return false;
+        }
+
+        let mut len = 0;
+        for id in allowed {
+            self.allowed[len] = id;
+            len += 1;
+        }
+        self.allowed_len = Some(len);
+        true
+    }
+
+    /// Lifts the allowlist, so that every SED is allowed once more, as on deregistration or should
+    /// the SSS provision none
+    pub fn allow_all(&mut self) {
+        self.allowed_len = None;
+    }
+
+    /// Whether frames to or from the given device are allowed: the FAA, the SSS, and broadcasts
+    /// always are, and the other SEDs should there be no allowlist or should they be on it
+    pub fn is_allowed(&self, peer: Id) -> bool {
+        match (peer, self.allowed_len) {
+            (Id::Other(_), Some(len)) => self.allowed[..len].contains(&peer),
+            _ => true,
+        }
+    }
+
+    /// Admits the header of a frame received on the given link, whose body may be at most `room`
+    /// bytes long, or returns the reason for which the frame is to be dropped
+    pub fn admit(&mut self, link: Link, hdr: &MessageHeader, room: usize) -> Result<(), Reason> {
+        let canonical = |id: Id| Id::from(u16::from(id)) == id;
+        if !canonical(hdr.src_id) || !canonical(hdr.tgt_id) || hdr.src_id == Id::Broadcast {
+            return Err(Reason::Reserved);
+        }
+        if link == Link::Radio && (hdr.src_id == Id::SSS || hdr.tgt_id == Id::SSS) {
+            return Err(Reason::Reserved);
+        }
+
+        let from_self = hdr.src_id.ct_eq(self.id);
+        if link == Link::Cpu && !from_self {
+            return Err(Reason::CpuSpoofed);
+        }
+        if link != Link::Cpu && from_self {
+            return Err(Reason::SelfSpoofed);
+        }
+
+        if usize::from(hdr.len) > room {
+            return Err(Reason::Oversize);
+        }
+
+        if link == Link::Radio && self.is_revoked(hdr.src_id) {
+            return Err(Reason::Revoked);
+        }
+        if link == Link::Radio && !self.is_allowed(hdr.src_id) {
+            return Err(Reason::NotAllowed);
+        }
+
+        match self.rate.as_mut() {
+            Some(rate) if link == Link::Radio => rate.admit(hdr.src_id),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Logical ports, which multiplex several services of a CPU over the messages between two SEDs
+//!
+//! A message to a port is content of the [port kind](crate::content::Kind::Port), which the crypto
+//! handler authenticates, so that data from a CPU is never taken for a message to a port. Its body
+//! begins with a port header, laid out as `port: u8`, followed by the data for that port. The CPU
+//! sends it to another SED, or to every SED, in an [envelope](crate::content::Envelope) to its own
+//! controller. With the `ports` feature, the receiving controller hands it to its CPU, in an
+//! envelope of its own, only should the CPU have [opened](Ports::open) that port, and drops it
+//! otherwise, so that a message for a service which is not running never reaches the CPU; without
+//! it, every port is closed. Data is forwarded as ever, whatever it begins with.
+//!
+//! The CPU opens and closes its ports with a [`Control`] message to its own controller, laid out
+//! as `MAGIC | op: u8 | port: u8`, where the op is `0` to close the port and `1` to open it. The
+//! controller answers with the same message once it has taken effect. Every port starts closed.
+
+use core::mem::size_of;
+
+use crate::cursor::{ReadCursor, WriteCursor};
+
+/// The magic which prefixes every control message
+pub const MAGIC: [u8; 4] = *b"\0PRT";
+
+/// The size of the port header which prefixes the data of a message to a port
+pub const HEADER_SIZE: usize = size_of::<u8>();
+
+/// Determines the port to which the body of content of the port kind is addressed, should it hold
+/// a port header
+pub fn port_of(body: &[u8]) -> Option<u8> {
+    body.first().copied()
+}
+
+/// Writes a port header for the given port to the buffer, returning its length
+pub fn write_header(buf: &mut [u8], port: u8) -> usize {
+    WriteCursor::new(buf).write(&[port]);
+    HEADER_SIZE
+}
+
+/// A request of the CPU to open or close one of its ports
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Control {
+    /// Drop the messages to the port
+    Close(u8),
+    /// Forward the messages to the port to the CPU
+    Open(u8),
+}
+
+impl Control {
+    /// The op which identifies the request on the wire
+    pub fn op(self) -> u8 {
+        match self {
+            Control::Close(_) => 0,
+            Control::Open(_) => 1,
+        }
+    }
+
+    /// The port which the request is for
+    pub fn port(self) -> u8 {
+        match self {
+            Control::Close(port) | Control::Open(port) => port,
+        }
+    }
+
+    /// Serialises this request to the buffer, returning its length
+    pub fn to_bytes(self, buf: &mut [u8]) -> usize {
+        WriteCursor::new(buf)
+            .write(&MAGIC)
+            .write(&[self.op(), self.port()]);
+        Control::size()
+    }
+
+    /// Deserialises a request from the body of a message, if it is one
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if buf.len() != Control::size() || !buf.starts_with(&MAGIC) {
+            return None;
+        }
+
+        let [op, port] = ReadCursor::new(&buf[MAGIC.len()..]).read_literal::<2>();
+        match op {
+            0 => Some(Control::Close(port)),
+            1 => Some(Control::Open(port)),
+            _ => None,
+        }
+    }
+
+    /// The constant size of a request in its serialised form
+    pub const fn size() -> usize {
+        MAGIC.len() + 2 * size_of::<u8>()
+    }
+}
+
+/// The set of ports which the CPU has opened
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Ports([u32; 8]);
+
+impl Ports {
+    /// Opens the port, so that messages to it are forwarded to the CPU
+    pub fn open(&mut self, port: u8) {
+        self.0[usize::from(port / 32)] |= 1 << (port % 32);
+    }
+
+    /// Closes the port, so that messages to it are dropped
+    pub fn close(&mut self, port: u8) {
+        self.0[usize::from(port / 32)] &= !(1 << (port % 32));
+    }
+
+    /// Whether the port is open
+    pub fn is_open(&self, port: u8) -> bool {
+        self.0[usize::from(port / 32)] & 1 << (port % 32) != 0
+    }
+
+    /// Applies the request of the CPU
+    pub fn apply(&mut self, control: Control) {
+        match control {
+            Control::Close(port) => self.close(port),
+            Control::Open(port) => self.open(port),
+        }
+    }
+}
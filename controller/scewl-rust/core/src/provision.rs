@@ -0,0 +1,99 @@
+//! The provisioning record, which personalises a generic image for a particular SED
+//!
+//! Ordinarily, the SED's id and registration secret are compiled into the image. With the
+//! `provisioned` feature of the firmware, they are instead read at boot from a dedicated flash page,
+//! which build.rs reserves at the top of flash when generating `memory.x`; one image may then be flashed
+//! to every SED of a deployment, and each personalised afterwards by writing its record to that
+//! page (e.g. with `tools/provision.py`).
+//!
+//! The record is laid out as follows, with every integer little-endian:
+//!
+//! ```text
+//! | magic (4) | version (1) | flags (1) | id (2) | secret (64) | CRC-32 of the preceding (4) |
+//! ```
+//!
+//! Should bit 0 of the flags be clear, the record carries no secret (the secret field is ignored),
+//! and the secret compiled into the image is used instead. An erased page, or one whose record is
+//! corrupt, is not a record at all; the SED is then unprovisioned.
+
+use core::convert::TryInto;
+
+use crate::codec::Id;
+use crate::cursor::{ReadCursor, WriteCursor};
+
+/// The magic at the start of every record
+pub const MAGIC: &[u8; 4] = b"PROV";
+
+/// The version of the record layout described in this module
+pub const VERSION: u8 = 1;
+
+/// The size of the flash page reserved for the record
+pub const PAGE: usize = 1024;
+
+/// The flag which indicates that the record carries a secret
+const HAS_SECRET: u8 = 1;
+
+/// The personalisation of a SED
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Record<'a> {
+    /// The id of the SED
+    pub id: Id,
+    /// The registration secret of the SED, unless that compiled into the image is to be used
+    pub secret: Option<&'a [u8; 64]>,
+}
+
+impl<'a> Record<'a> {
+    /// The constant size of the record in its serialised form
+    pub const fn size() -> usize {
+        MAGIC.len() + 2 + 2 + 64 + 4
+    }
+
+    /// Deserialises a record, should the bytes start with a valid record of this version
+    pub fn from_bytes(data: &'a [u8]) -> Option<Self> {
+        let data = data.get(..Record::size())?;
+        let (body, crc) = data.split_at(Record::size() - 4);
+        if !body.starts_with(MAGIC) || ReadCursor::new(crc).read_u32() != crc32(body) {
+            return None;
+        }
+
+        let mut cur = ReadCursor::new(&body[MAGIC.len()..]);
+        let version: [u8; 1] = cur.read_literal();
+        let flags: [u8; 1] = cur.read_literal();
+        let id = cur.read_u16();
+        if version[0] != VERSION {
+            return None;
+        }
+
+        let secret = &body[body.len() - 64..];
+        Some(Record {
+            id: id.into(),
+            secret: (flags[0] & HAS_SECRET != 0).then(|| secret.try_into().unwrap()),
+        })
+    }
+
+    /// Serialises this record into the buffer, returning the length of the record
+    pub fn to_bytes(&self, buf: &mut [u8]) -> usize {
+        let flags = if self.secret.is_some() { HAS_SECRET } else { 0 };
+        WriteCursor::new(buf)
+            .write(MAGIC)
+            .write(&[VERSION, flags])
+            .write_u16(self.id.into())
+            .write(self.secret.unwrap_or(&[0; 64]));
+
+        let crc = crc32(&buf[..Record::size() - 4]);
+        WriteCursor::new(&mut buf[Record::size() - 4..]).write_u32(crc);
+        Record::size()
+    }
+}
+
+/// Computes the CRC-32 (as used by zlib) of the data, which guards the record against a partial
+/// or corrupted write
+///
+/// The record is read once per boot, so a bitwise implementation suffices.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0_u32, |crc, &byte| {
+        (0..8).fold(crc ^ u32::from(byte), |crc, _| {
+            (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+        })
+    })
+}
@@ -0,0 +1,164 @@
+//! A lock-free single-producer, single-consumer ring buffer, for handing data from an interrupt
+//! handler to the main loop
+//!
+//! The producer (typically a UART receive interrupt) must never block, so a full queue drops the
+//! pushed value and counts it instead; the consumer (the main loop) may then report how much was
+//! lost. Neither side ever takes a lock or disables interrupts.
+//!
+//! The queue is split into a [`Producer`] and a [`Consumer`], each of which may be moved to its own
+//! context. Each index is written by exactly one side:
+//!
+//!  - `tail` (the next slot to write) only by the producer, after the slot has been written
+//!  - `head` (the next slot to read) only by the consumer, after the slot has been read
+//!
+//! Both indices run freely and wrap at `usize::MAX`, so the number of queued values is always
+//! `tail - head` (wrapping); `N` must be a power of two so that slot indices stay continuous
+//! across that wrap. Release stores paired with acquire loads ensure that a slot's contents are
+//! visible before the index which publishes it.
+//!
+//! As this code is only ever exercised by interrupts on the target, it is stress-tested with host
+//! threads in `tests/queue.rs`.
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+/// A ring buffer of up to `N` values of type `T`
+pub struct Queue<T: Copy, const N: usize> {
+    /// The slots of the ring buffer, of which only those in `head..tail` are initialised
+    buf: UnsafeCell<[MaybeUninit<T>; N]>,
+    /// The index of the next slot to be read, written only by the consumer
+    head: AtomicUsize,
+    /// The index of the next slot to be written, written only by the producer
+    tail: AtomicUsize,
+    /// The number of values dropped because the queue was full, incremented by the producer and
+    /// reset by the consumer (both atomically)
+    overflows: AtomicU32,
+}
+
+// SAFETY: the slots are only accessed through the producer and consumer, which access disjoint
+// slots as described by the module documentation
+unsafe impl<T: Copy + Send, const N: usize> Sync for Queue<T, N> {}
+
+impl<T: Copy, const N: usize> Queue<T, N> {
+    /// Instantiates an empty queue
+    ///
+    /// This panics (at compile time, for statics) should `N` not be a power of two.
+    pub const fn new() -> Self {
+        assert!(N.is_power_of_two(), "queue capacity must be a power of two");
+
+        Self {
+            buf: UnsafeCell::new([MaybeUninit::uninit(); N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            overflows: AtomicU32::new(0),
+        }
+    }
+
+    /// The maximum number of values held by this queue
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Splits this queue into its producer and consumer halves
+    ///
+    /// The exclusive borrow ensures that at most one of each exists at any time.
+    pub fn split(&mut self) -> (Producer<'_, T, N>, Consumer<'_, T, N>) {
+        (Producer { queue: self }, Consumer { queue: self })
+    }
+
+    /// The number of values currently held, as observed with the given orderings
+    fn len(&self, head: Ordering, tail: Ordering) -> usize {
+        self.tail.load(tail).wrapping_sub(self.head.load(head))
+    }
+}
+
+impl<T: Copy, const N: usize> Default for Queue<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The writing half of a [`Queue`]
+pub struct Producer<'a, T: Copy, const N: usize> {
+    /// The queue written by this producer
+    queue: &'a Queue<T, N>,
+}
+
+// SAFETY: the producer only accesses the slots it owns, as described by the module documentation
+unsafe impl<T: Copy + Send, const N: usize> Send for Producer<'_, T, N> {}
+
+impl<T: Copy, const N: usize> Producer<'_, T, N> {
+    /// Pushes a value onto the queue, never blocking
+    ///
+    /// Should the queue be full, the value is dropped and counted as an overflow, and false is
+    /// returned.
+    pub fn push(&mut self, value: T) -> bool {
+        let tail = self.queue.tail.load(Ordering::Relaxed);
+
+        if tail.wrapping_sub(self.queue.head.load(Ordering::Acquire)) == N {
+            self.queue.overflows.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        // SAFETY: the slot at tail is outside head..tail, so the consumer will not read it until
+        // tail is published below
+        unsafe {
+            (*self.queue.buf.get())[tail % N] = MaybeUninit::new(value);
+        }
+        self.queue
+            .tail
+            .store(tail.wrapping_add(1), Ordering::Release);
+
+        true
+    }
+
+    /// Determines whether the next push would overflow
+    pub fn is_full(&self) -> bool {
+        self.queue.len(Ordering::Acquire, Ordering::Relaxed) == N
+    }
+}
+
+/// The reading half of a [`Queue`]
+pub struct Consumer<'a, T: Copy, const N: usize> {
+    /// The queue read by this consumer
+    queue: &'a Queue<T, N>,
+}
+
+// SAFETY: the consumer only accesses the slots it owns, as described by the module documentation
+unsafe impl<T: Copy + Send, const N: usize> Send for Consumer<'_, T, N> {}
+
+impl<T: Copy, const N: usize> Consumer<'_, T, N> {
+    /// Pops the oldest value from the queue, if any
+    pub fn pop(&mut self) -> Option<T> {
+        let head = self.queue.head.load(Ordering::Relaxed);
+
+        if self.queue.tail.load(Ordering::Acquire) == head {
+            return None;
+        }
+
+        // SAFETY: the slot at head is within head..tail, so it was initialised by the producer
+        // before tail was published and will not be overwritten until head is published below
+        let value = unsafe { (*self.queue.buf.get())[head % N].assume_init() };
+        self.queue
+            .head
+            .store(head.wrapping_add(1), Ordering::Release);
+
+        Some(value)
+    }
+
+    /// The number of values waiting to be popped
+    pub fn len(&self) -> usize {
+        self.queue.len(Ordering::Relaxed, Ordering::Acquire)
+    }
+
+    /// Determines whether there are no values waiting to be popped
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of values dropped by the producer since the last call, resetting the count
+    pub fn take_overflows(&mut self) -> u32 {
+        self.queue.overflows.swap(0, Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,43 @@
+//! Redaction of secrets and message contents from log output
+//!
+//! By default, the controller logs in a "secure" mode: message contents are only ever logged as a
+//! [`Payload`], which reports the length of the contents and a short fingerprint of their SHA256
+//! hash, but never the bytes themselves. This is enough to follow a message through the pipeline
+//! (and to match it against what the peer or the CPU saw) without leaking plaintext to whoever
+//! happens to hold the debug probe. Key material is never logged at all, in any mode; the
+//! `Debug` implementations of the [secure SSS frames](crate::codec::secure) omit it.
+//!
+//! The `insecure-logging` feature logs the full contents of payloads instead. As this would leak
+//! plaintext from a deployed controller, it is only permitted in debug builds.
+
+use core::fmt::{Debug, Formatter, Result as FmtResult};
+
+#[cfg(not(feature = "insecure-logging"))]
+use sha2::{Digest, Sha256};
+
+/// The number of bytes of the SHA256 hash reported as the fingerprint of a payload
+#[cfg(not(feature = "insecure-logging"))]
+const FINGERPRINT_LEN: usize = 4;
+
+/// The contents of a message, which are logged either redacted or in full depending on the
+/// logging mode
+#[derive(Copy, Clone)]
+pub struct Payload<'a>(pub &'a [u8]);
+
+#[cfg(not(feature = "insecure-logging"))]
+impl Debug for Payload<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "<{} bytes, sha256 ", self.0.len())?;
+        for b in &Sha256::digest(self.0)[..FINGERPRINT_LEN] {
+            write!(f, "{b:02x}")?;
+        }
+        write!(f, "..>")
+    }
+}
+
+#[cfg(feature = "insecure-logging")]
+impl Debug for Payload<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        self.0.fmt(f)
+    }
+}
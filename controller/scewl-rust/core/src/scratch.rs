@@ -0,0 +1,101 @@
+//! A small pool of fixed-size scratch buffers, which the controller lends to its handlers for their
+//! temporaries (e.g. the input to a hash, or a structure as serialised)
+//!
+//! The controller runs on a single stack, shared with every handler it calls; a handler which
+//! builds a few hundred bytes of temporaries on that stack eats into the headroom of everything
+//! below it. Instead, the controller owns a [`Pool`], which (like its data buffer) lives in the
+//! `.buffers` section, and passes it to each crypto operation; authentication handlers reach it
+//! with [`Controller::scratch`](crate::controller::Controller::scratch).
+//!
+//! A buffer is borrowed with [`Pool::take`], which returns a [`Scratch`] guard that derefs to the
+//! buffer and returns it to the pool when dropped. A borrowed buffer is always zeroed, so that
+//! nothing written to it (e.g. a key, or a decrypted header) outlives the borrow. The pool is not
+//! `Sync`, as the controller only ever uses it from its main loop.
+
+use core::cell::{Cell, UnsafeCell};
+use core::ops::{Deref, DerefMut};
+
+/// The size, in bytes, of each scratch buffer
+pub const SIZE: usize = 256;
+
+/// The number of scratch buffers in a pool
+pub const COUNT: usize = 4;
+
+/// A pool of [`COUNT`] scratch buffers of [`SIZE`] bytes each
+///
+/// A pool in which every byte is zero is a valid, empty pool, so that a pool in a `NOLOAD` section
+/// (which, unlike `.bss`, is not zeroed at boot) may be initialised in place with
+/// `ptr::write_bytes` rather than through a temporary on the stack.
+pub struct Pool {
+    /// The buffers, of which only those not taken are zero
+    bufs: UnsafeCell<[[u8; SIZE]; COUNT]>,
+    /// A bitmask of the buffers which are taken
+    taken: Cell<u8>,
+}
+
+impl Pool {
+    /// Instantiates a pool with every buffer free
+    #[allow(clippy::new_without_default)] // a pool is deliberately const-constructed or zeroed
+    pub const fn new() -> Self {
+        Self {
+            bufs: UnsafeCell::new([[0; SIZE]; COUNT]),
+            taken: Cell::new(0),
+        }
+    }
+
+    /// Borrows a zeroed buffer from the pool, or `None` should every buffer be taken
+    pub fn take(&self) -> Option<Scratch<'_>> {
+        let taken = self.taken.get();
+        let index = (0..COUNT).find(|&index| taken & (1 << index) == 0)?;
+        self.taken.set(taken | (1 << index));
+
+        Some(Scratch { pool: self, index })
+    }
+
+    /// The number of buffers which may currently be taken
+    pub fn free(&self) -> usize {
+        COUNT - self.taken.get().count_ones() as usize
+    }
+}
+
+/// A buffer borrowed from a [`Pool`], which is zeroed and returned to the pool when dropped
+pub struct Scratch<'a> {
+    /// The pool from which the buffer was borrowed
+    pool: &'a Pool,
+    /// The index of the buffer within the pool
+    index: usize,
+}
+
+impl Scratch<'_> {
+    /// A pointer to the borrowed buffer
+    fn buf(&self) -> *mut [u8; SIZE] {
+        // SAFETY: the index is always within the pool, as given by take
+        unsafe { self.pool.bufs.get().cast::<[u8; SIZE]>().add(self.index) }
+    }
+}
+
+impl Deref for Scratch<'_> {
+    type Target = [u8; SIZE];
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: the buffer is marked as taken for as long as this guard lives, so it is
+        // referenced through no other guard
+        unsafe { &*self.buf() }
+    }
+}
+
+impl DerefMut for Scratch<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: as in deref, and this guard is borrowed mutably
+        unsafe { &mut *self.buf() }
+    }
+}
+
+impl Drop for Scratch<'_> {
+    fn drop(&mut self) {
+        self.fill(0);
+        self.pool
+            .taken
+            .set(self.pool.taken.get() & !(1 << self.index));
+    }
+}
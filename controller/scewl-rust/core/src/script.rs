@@ -0,0 +1,195 @@
+//! Test directives accepted over the SSS interface when the `scripted` feature is enabled
+//!
+//! This allows a host script, standing in for the SSS, to drive regression scenarios on the actual
+//! firmware without recompiling per test case. Directives are ordinary SCEWL frames from the SSS
+//! whose body begins with [`MAGIC`]; any other frame from the SSS outside of (de)registration is
+//! ignored. Directives must not be sent while a (de)registration is in flight, as the
+//! authentication handler would receive them in place of the SSS's response.
+//!
+//! Each directive body is laid out as `MAGIC | op: u8 | arguments`:
+//!
+//!  - `0` [inject](Directive::Inject): `intf: u8 | src_id: u16 | tgt_id: u16 | frame body`, where
+//!    the frame body is handled as though it had been received on the given interface (`0` for the
+//!    CPU, `2` for the radio); the source of frames injected from the CPU is always this controller
+//!  - `1` [query](Directive::Query): no arguments
+//!  - `2` [fault](Directive::Fault): `fault: u8`, see [`Fault`]
+//!  - `3` [crash](Directive::Crash): no arguments
+//!
+//! Every directive is answered on the SSS interface with `MAGIC | op: u8 | status: u8 | result`,
+//! where the status is `0` on success and `1` otherwise. A query's result is
+//! `id: u16 | registered: u8 | armed faults: u8`; a crash's result is
+//! `line: u32 | column: u32 | file length: u8 | file | message`, describing the panic which ended
+//! the previous boot, and it fails if there was none. Other directives carry no result.
+
+use crate::codec::Id;
+use crate::crashlog::Crash;
+use crate::cursor::{ReadCursor, WriteCursor};
+use crate::transport::INTF;
+
+/// The magic which prefixes the body of every directive and response
+pub const MAGIC: [u8; 4] = *b"TEST";
+
+/// The size of the arguments of an injection preceding the injected frame body
+const INJECT_ARGS: usize = 1 + 2 + 2;
+
+/// A fault which may be forced by the script; each fires exactly once after being armed
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Fault {
+    /// The next message from the CPU is dropped before it is handled
+    DropCpu = 0,
+    /// The next message from the radio is dropped before it is handled
+    DropRad = 1,
+    /// The next decryption of a message from another SED fails
+    FailDecrypt = 2,
+}
+
+impl Fault {
+    /// Decodes a fault from its wire value
+    fn from_u8(fault: u8) -> Option<Self> {
+        match fault {
+            0 => Some(Fault::DropCpu),
+            1 => Some(Fault::DropRad),
+            2 => Some(Fault::FailDecrypt),
+            _ => None,
+        }
+    }
+}
+
+/// The set of faults currently armed
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Faults(u8);
+
+impl Faults {
+    /// Arms the given fault
+    pub fn arm(&mut self, fault: Fault) {
+        self.0 |= 1 << fault as u8;
+    }
+
+    /// Disarms the given fault, returning whether it was armed (i.e. whether it should fire now)
+    pub fn take(&mut self, fault: Fault) -> bool {
+        let armed = self.0 & (1 << fault as u8) != 0;
+        self.0 &= !(1 << fault as u8);
+        armed
+    }
+
+    /// The armed faults as a bitmask, indexed by the wire value of each fault
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+}
+
+/// A test directive received from the script
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Directive {
+    /// Handle a frame as though it had been received on the given interface
+    ///
+    /// The frame body is located at [`INJECT_OFFSET`](Directive::INJECT_OFFSET) of the directive.
+    Inject {
+        /// The interface which the frame is received on
+        intf: INTF,
+        /// The source of the frame
+        src_id: Id,
+        /// The target of the frame
+        tgt_id: Id,
+        /// The length of the frame body
+        len: usize,
+    },
+    /// Report the state of the controller
+    Query,
+    /// Arm the given fault
+    Fault(Fault),
+    /// Report the panic which ended the previous boot
+    Crash,
+}
+
+impl Directive {
+    /// The offset of the frame body within an injection directive
+    pub const INJECT_OFFSET: usize = MAGIC.len() + 1 + INJECT_ARGS;
+
+    /// Decodes a directive from the body of a frame received from the SSS, if it is one
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if !buf.starts_with(&MAGIC) || buf.len() <= MAGIC.len() {
+            return None;
+        }
+
+        let mut cur = ReadCursor::new(&buf[MAGIC.len() + 1..]);
+        match buf[MAGIC.len()] {
+            0 if buf.len() >= Directive::INJECT_OFFSET => {
+                let intf = match cur.read_literal::<1>()[0] {
+                    0 => INTF::CPU,
+                    2 => INTF::RAD,
+                    _ => return None,
+                };
+
+                Some(Directive::Inject {
+                    intf,
+                    src_id: cur.read_u16().into(),
+                    tgt_id: cur.read_u16().into(),
+                    len: buf.len() - Directive::INJECT_OFFSET,
+                })
+            }
+            1 => Some(Directive::Query),
+            2 if buf.len() > MAGIC.len() + 1 => {
+                Fault::from_u8(cur.read_literal::<1>()[0]).map(Directive::Fault)
+            }
+            3 => Some(Directive::Crash),
+            _ => None,
+        }
+    }
+
+    /// The wire value of this directive's operation
+    pub fn op(self) -> u8 {
+        match self {
+            Directive::Inject { .. } => 0,
+            Directive::Query => 1,
+            Directive::Fault(_) => 2,
+            Directive::Crash => 3,
+        }
+    }
+}
+
+/// The state of the controller, as reported in response to a [query](Directive::Query)
+#[derive(Debug, Copy, Clone)]
+pub struct State {
+    /// The id of the controller
+    pub id: Id,
+    /// Whether the controller is registered
+    pub registered: bool,
+    /// The faults currently armed
+    pub faults: Faults,
+}
+
+/// The result of a directive, if it carries one
+#[derive(Copy, Clone)]
+pub enum Report {
+    /// The state of the controller, in response to a [query](Directive::Query)
+    State(State),
+    /// The panic which ended the previous boot, in response to a [crash](Directive::Crash)
+    Crash(&'static Crash),
+}
+
+/// Writes the response to a directive to the buffer, returning its length
+#[allow(clippy::cast_possible_truncation)] // recorded file names are far shorter than u8::MAX
+pub fn respond(buf: &mut [u8], op: u8, ok: bool, report: Option<Report>) -> usize {
+    let cur = WriteCursor::new(buf)
+        .write(&MAGIC)
+        .write(&[op, u8::from(!ok)]);
+
+    match report {
+        Some(Report::State(state)) => {
+            cur.write_u16(state.id.into())
+                .write(&[u8::from(state.registered), state.faults.bits()]);
+            MAGIC.len() + 2 + 4
+        }
+        Some(Report::Crash(crash)) => {
+            let (file, message) = (crash.file().as_bytes(), crash.message().as_bytes());
+            cur.write_u32(crash.line())
+                .write_u32(crash.column())
+                .write(&[file.len() as u8])
+                .write(file)
+                .write(message);
+            MAGIC.len() + 2 + 9 + file.len() + message.len()
+        }
+        None => MAGIC.len() + 2,
+    }
+}
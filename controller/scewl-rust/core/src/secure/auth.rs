@@ -0,0 +1,813 @@
+//! The authentication module for the secure implementation of the SSS registration for the
+//! controller
+//!
+//! This implementation differs very little from the original design; the only distinctions are:
+//!
+//!  - the SSS [challenges](super::challenge) each registration and deregistration with a nonce,
+//!    over which the controller proves a secret (unique per SED) without ever sending it, which the
+//!    SSS checks to confirm a successful registration
+//!  - the identifier of the cipher suite the SED was built with follows the operation, so that the
+//!    SSS refuses SEDs which would not interoperate with the rest of the deployment
+//!  - a global AES key, a global HMAC key, and a unique (runtime-generated) seed is sent by the SSS
+//!    as the response to a successful registration, [wrapped](super::keywrap) with AES-KW under a
+//!    key derived from the secret, which the SED unwraps and checks before taking them up
+//!  - a response is only accepted should it be addressed to this SED, as compared in
+//!    [constant time](crate::ct), and [tagged](super::challenge::tag) by the SSS under a key
+//!    derived from the secret, over the nonce of the challenge which it answers
+//!  - the epoch of the global keys accompanies them, so that, should the handler be given a
+//!    [record](Handler::with_epochs) of them, the SED refuses keys older than any it has accepted
+//!    before
+//!  - the SED advertises its [capabilities](crate::codec::secure::CAPS) after the suite, and the
+//!    SSS responds with those which the deployment enables, such as the [header
+//!    CRC](crate::codec::secure::CAP_HEADER_CRC), or the [GCM](crate::codec::secure::CAP_AES_GCM)
+//!    or [GCM-SIV](crate::codec::secure::CAP_AES_GCM_SIV) crypto handlers
+//!  - should the deployment [partition its SEDs](crate::codec::secure::CAP_ALLOWLIST), the secrets
+//!    are followed by the SEDs with which this SED may exchange frames, which the controller's
+//!    [policy](crate::policy) restricts it to until deregistration
+//!  - should the deployment [sign broadcasts](crate::codec::secure::CAP_BROADCAST_SIGS), the
+//!    secrets (and the allowlist) are followed by the seed of the SED's signing key and the public keys of the other
+//!    SEDs, which are wiped from the data buffer once read
+//!  - should the deployment enable [ephemeral keys](crate::codec::secure::CAP_EPHEMERAL_KEYS),
+//!    the controller is given [handshakes](crate::secure::Handshakes), seeded from the seed
+//!    distributed for its CSPRNG
+//!  - with the experimental `pq` feature, an ML-KEM-512 encapsulation key follows the proof,
+//!    and should the deployment [enable it](crate::codec::secure::CAP_PQ_KEM), the response is
+//!    [sealed](super::pq) under a secret encapsulated to it, which the SED opens in place
+//!  - while registered, the SSS may push the keys of a new epoch, [wrapped](super::rotation) under
+//!    the SED's secret, which the SED hands to its crypto handler and acknowledges with
+//!    [`SSSOp::Rotated`], or with [`SSSOp::Already`] should it refuse them
+//!  - while registered, the SSS may also push the devices since deregistered, [tagged](super::revocation)
+//!    under the SED's secret, which the SED hands to its [policy](crate::policy) and acknowledges
+//!    with [`SSSOp::Revoked`], or with [`SSSOp::Already`] should it refuse them
+//!  - while registered, the SED may [rekey](SSSOp::Rekey), as the CPU requests or the SSS
+//!    prompts it to, which is exchanged exactly as a registration is, and is answered with the
+//!    deployment's current keys and a fresh seed, for which a new crypto handler is built
+//!  - while registered, the SED periodically [keeps alive](super::keepalive) with the SSS, which
+//!    answers, tagged under the SED's secret, whether it still holds the SED registered
+//!  - once the SSS answers a deregistration, the SED [attests](super::erasure) to erasing its
+//!    keys under those very keys, and the SSS only deregisters it, and the SED only erases them,
+//!    once the SSS acknowledges that with [`SSSOp::Erased`]
+//!
+//! Otherwise, this implementation matches the original SSS registration pattern nearly identically.
+
+use core::convert::TryInto;
+
+use crate::auth::{Error as AuthError, Handler as AuthHandler};
+#[cfg(feature = "pq")]
+use crate::codec::secure::CAP_PQ_KEM;
+use crate::codec::secure::{
+    SecureSSSAlive, SecureSSSAllowlist, SecureSSSChallenge, SecureSSSErasure, SecureSSSKeepalive,
+    SecureSSSMessage, SecureSSSProof, SecureSSSResponse, SecureSSSRevocation, SecureSSSRotation,
+    SecureSSSSecrets, SecureSSSSigningKeys, CAPS, CAP_AES_GCM, CAP_AES_GCM_SIV, CAP_ALLOWLIST,
+    CAP_BROADCAST_SIGS, CAP_EPHEMERAL_KEYS, CAP_HEADER_CRC,
+};
+use crate::controller::{Controller, Error, Id, Message, SSSMessage, SSSOp};
+use crate::crypto::Handler as _;
+use crate::cursor::WriteCursor;
+use crate::deployment;
+use crate::glitch;
+use crate::masked::Masked;
+use crate::policy::{ALLOWED, REVOKED};
+#[cfg(feature = "pq")]
+use crate::secure::pq::{self, kem, KeyPair};
+use crate::secure::rotation::{self, Keys};
+use crate::secure::{challenge, keywrap};
+use crate::secure::{keepalive, revocation};
+use crate::secure::{
+    register, CryptoHandler, GcmHandler, Handshakes, Registered, SignedHandler, SigningKeys,
+    SivHandler, Suite,
+};
+use crate::secure::{Checkpoints, Entropy, Epochs};
+use crate::transport::{Transport, INTF};
+use crate::{debug, info};
+
+/// The length of the sealing which may follow the secrets of the registration response
+#[cfg(feature = "pq")]
+const SEALING: usize = pq::SEALING;
+
+/// The length of the sealing which may follow the secrets of the registration response, of which
+/// there is none without the `pq` feature
+#[cfg(not(feature = "pq"))]
+const SEALING: usize = 0;
+
+/// Authentication handler for the secure implementation of the controller
+#[derive(Copy, Clone)]
+pub struct Handler {
+    /// The shared secret used for registration, as stored [masked](crate::masked)
+    secret: Masked,
+    /// The identifier of the cipher suite advertised to the SSS
+    suite: u8,
+    /// The source of the fresh entropy for nonces, and with which the crypto handlers are reseeded
+    entropy: &'static dyn Entropy,
+    /// The store in which the crypto handlers reserve their counters, if any
+    checkpoints: Option<&'static dyn Checkpoints>,
+    /// The record of the highest epoch of keys accepted, if any
+    epochs: Option<&'static dyn Epochs>,
+}
+
+impl Handler {
+    /// Instantiates a new authentication handler with the given shared secret for registration,
+    /// advertising the given cipher suite, which should be [`SUITE`](crate::codec::secure::SUITE)
+    /// for an SSS to accept it, and drawing fresh entropy from the given source
+    pub fn new(secret: Masked, suite: u8, entropy: &'static dyn Entropy) -> Self {
+        Self {
+            secret,
+            suite,
+            entropy,
+            checkpoints: None,
+            epochs: None,
+        }
+    }
+
+    /// Gives each crypto handler built at registration the given store, in which it reserves its
+    /// counters ahead of their use so that they survive a reset (see
+    /// [`Checkpoints`](crate::secure::Checkpoints))
+    pub fn with_checkpoints(mut self, store: &'static dyn Checkpoints) -> Self {
+        self.checkpoints = Some(store);
+        self
+    }
+
+    /// Refuses keys of an epoch older than the highest in the given record, and records each
+    /// higher epoch accepted
+    pub fn with_epochs(mut self, record: &'static dyn Epochs) -> Self {
+        self.epochs = Some(record);
+        self
+    }
+}
+
+/// Reads the response of the SSS to a (de)registration, or its challenge, of at most the given
+/// length, returning it and its length
+///
+/// The SSS may have pushed the keys of a new epoch or the revoked devices, or prompted a rekey,
+/// before it read the request, which are skipped: a registration (or a rekey) supersedes them, and
+/// a deregistration has no use for them.
+fn read_response<T: Transport>(
+    controller: &mut Controller<Handler, Registered, T>,
+    max: usize,
+) -> Result<(SecureSSSResponse, usize), AuthError> {
+    loop {
+        #[allow(clippy::cast_possible_truncation)]
+        // truncation permissible for these response sizes
+        let len = controller.read_sss(max as u16)?.len;
+        let resp =
+            SecureSSSResponse::from_bytes(&controller.data()[..len]).ok_or(AuthError::Malformed)?;
+        // a prompt to rekey bears nothing but the operation, unlike the response to a rekey
+        let pushed = matches!(resp.op, SSSOp::Rotate | SSSOp::Revoke)
+            || (resp.op == SSSOp::Rekey && len == SSSMessage::size());
+        if !pushed {
+            return Ok((resp, len));
+        }
+
+        debug!("Skipping secure SSS push");
+        controller.data()[..len].fill(0);
+    }
+}
+
+/// Reads the response of the SSS to the proof answering the challenge of the given nonce, of at
+/// most the given length less its tag, as [`read_response`] does, and checks that the SSS
+/// [tagged](challenge::tag) it, returning it and its length less the tag, which is wiped
+fn read_tagged<T: Transport>(
+    controller: &mut Controller<Handler, Registered, T>,
+    secret: &[u8; 64],
+    nonce: &[u8; 32],
+    max: usize,
+) -> Result<(SecureSSSResponse, usize), AuthError> {
+    let (_, len) = read_response(controller, max + challenge::TAG)?;
+    let len = len
+        .checked_sub(challenge::TAG)
+        .ok_or(AuthError::Malformed)?;
+
+    let id = controller.id();
+    let (resp, tag) = controller.data()[..len + challenge::TAG].split_at(len);
+    if !challenge::authentic(secret, nonce, id, resp, tag) {
+        return Err(AuthError::Forged);
+    }
+    controller.data()[len..len + challenge::TAG].fill(0);
+
+    let resp =
+        SecureSSSResponse::from_bytes(&controller.data()[..len]).ok_or(AuthError::Malformed)?;
+    Ok((resp, len))
+}
+
+/// Attests to the SSS, once it has answered the deregistration challenged with the nonce, that this
+/// SED is erasing the keys of its registration, returning whether the SSS acknowledged it (see
+/// [`erasure`](super::erasure))
+fn attest_erasure<T: Transport>(
+    controller: &mut Controller<Handler, Registered, T>,
+    secret: &[u8; 64],
+    nonce: &[u8; 32],
+) -> Result<bool, AuthError> {
+    let id = controller.id();
+    let (epoch, tag) = controller
+        .crypto()
+        .and_then(|crypto| crypto.attest(nonce, id))
+        .ok_or(AuthError::Refused)?;
+    let erasure = SecureSSSErasure {
+        dev_id: id,
+        op: SSSOp::Erased,
+        epoch,
+        tag,
+    };
+    debug!("Sending secure SSS erasure: {:?}", erasure);
+
+    WriteCursor::new(controller.tx()).write(&erasure.to_bytes());
+    controller.send_tx(
+        INTF::SSS,
+        &Message {
+            tgt_id: Id::SSS,
+            src_id: id,
+            len: SecureSSSErasure::size(),
+        },
+    )?;
+
+    let (ack, _) = read_tagged(controller, secret, nonce, SecureSSSResponse::size())?;
+    debug!("Received secure SSS acknowledgement: {:?}", ack);
+    Ok(glitch::check(|| {
+        ack.dev_id.ct_eq(id) && ack.op == SSSOp::Erased
+    }))
+}
+
+/// Requests the operation of the SSS, advertising the given suite, then answers the challenge with
+/// which the SSS responds, leaving the proof of this SED's secret at the start of the transmit
+/// buffer for the caller to send, and returning the nonce of the challenge and the length of the proof
+fn request<T: Transport>(
+    controller: &mut Controller<Handler, Registered, T>,
+    suite: u8,
+    secret: &[u8; 64],
+    op: SSSOp,
+) -> Result<([u8; 32], usize), AuthError> {
+    let msg = SecureSSSMessage {
+        dev_id: controller.id(),
+        op,
+        suite,
+        caps: CAPS,
+    };
+    debug!("Sending secure SSS message: {:?}", msg);
+
+    let len = msg.to_bytes(controller.tx());
+    controller.send_tx(
+        INTF::SSS,
+        &Message {
+            tgt_id: Id::SSS,
+            src_id: controller.id(),
+            len,
+        },
+    )?;
+
+    let (_, len) = read_response(controller, SecureSSSChallenge::size())?;
+    let challenge =
+        SecureSSSChallenge::from_bytes(&controller.data()[..len]).ok_or(AuthError::Malformed)?;
+    debug!("Received secure SSS challenge: {:?}", challenge);
+
+    let id = controller.id();
+    if !glitch::check(|| challenge.dev_id.ct_eq(id) && challenge.op == SSSOp::Challenge) {
+        return Err(AuthError::Refused);
+    }
+    let proof = SecureSSSProof {
+        dev_id: id,
+        op,
+        proof: challenge::prove(secret, &challenge.nonce, id),
+    };
+    debug!("Sending secure SSS proof: {:?}", proof);
+    Ok((challenge.nonce, proof.to_bytes(controller.tx())))
+}
+
+/// Generates a keypair for this registration, appending its encapsulation key to the proof of
+/// the given length in the transmit buffer, and returns it with the new length of the proof
+#[cfg(feature = "pq")]
+fn append_encapsulation_key<T: Transport>(
+    controller: &mut Controller<Handler, Registered, T>,
+    entropy: &dyn Entropy,
+    secret: &[u8; 64],
+    len: usize,
+) -> (KeyPair, usize) {
+    let mut fresh = [0_u8; 32];
+    entropy.fill(&mut fresh);
+    let ek = (&mut controller.tx()[len..len + kem::ENCAPSULATION_KEY])
+        .try_into()
+        .unwrap();
+    let pair = KeyPair::generate(secret, &fresh, ek);
+    fresh.fill(0);
+
+    (pair, len + kem::ENCAPSULATION_KEY)
+}
+
+/// Opens the registration response of the given length in place, should the deployment have
+/// [sealed](pq) it, returning its secrets and the length of the opened response
+#[cfg(feature = "pq")]
+fn open<T: Transport>(
+    controller: &mut Controller<Handler, Registered, T>,
+    pair: &KeyPair,
+    secrets: SecureSSSSecrets,
+    len: usize,
+) -> Result<(SecureSSSSecrets, usize), AuthError> {
+    if secrets.caps & CAP_PQ_KEM == 0 {
+        return Ok((secrets, len));
+    }
+
+    let len = pair
+        .open(controller.id(), controller.data(), len)
+        .ok_or(AuthError::Malformed)?;
+    info!("Opened the response sealed by ML-KEM-512");
+
+    let secrets = SecureSSSResponse::from_bytes(&controller.data()[..len])
+        .and_then(|resp| resp.secrets)
+        .ok_or(AuthError::Malformed)?;
+    Ok((secrets, len))
+}
+
+/// Reads the allowlist which follows the secrets of the registration response of the given length,
+/// copying the ids out of the data buffer, and returns them with the number of allowed SEDs and
+/// the offset of what follows the allowlist
+fn allowlist<T: Transport>(
+    controller: &mut Controller<Handler, Registered, T>,
+    len: usize,
+) -> Result<([Id; ALLOWED], usize, usize), AuthError> {
+    let resp = &controller.data()[SecureSSSResponse::size()..len];
+    let list = SecureSSSAllowlist::from_bytes(resp).ok_or(AuthError::Malformed)?;
+    debug!("Received allowlist: {:?}", list);
+    // a longer allowlist is refused whole, rather than admitting SEDs which are not on it
+    if list.count() > ALLOWED {
+        return Err(AuthError::Refused);
+    }
+
+    let mut allowed = [Id::Broadcast; ALLOWED];
+    allowed
+        .iter_mut()
+        .zip(list.allowed())
+        .for_each(|(slot, id)| *slot = id);
+    Ok((
+        allowed,
+        list.count(),
+        SecureSSSResponse::size() + list.serialised_len(),
+    ))
+}
+
+/// Reads the signing keys which lie from the given offset to the end of the registration response
+/// of the given length, then wipes the response from the data buffer
+fn signing_keys<T: Transport>(
+    controller: &mut Controller<Handler, Registered, T>,
+    start: usize,
+    len: usize,
+) -> Result<SigningKeys, AuthError> {
+    let resp = &controller.data()[start..len];
+    let keys = SecureSSSSigningKeys::from_bytes(resp).ok_or(AuthError::Malformed)?;
+    debug!("Received signing keys: {:?}", keys);
+
+    let keys = keys
+        .peers()
+        .fold(SigningKeys::new(keys.seed), |keys, (id, public)| {
+            keys.with_peer(id, public)
+        });
+    // the data buffer lives as long as the controller, so the seed is not left in it
+    controller.data()[..len].fill(0);
+
+    Ok(keys)
+}
+
+/// Hands the keys of a new epoch to the controller's crypto handler, should it take them up, and,
+/// should the given record of epochs be kept, should their epoch be no older than any accepted
+/// before
+fn rotate<T: Transport>(
+    controller: &mut Controller<Handler, Registered, T>,
+    epochs: Option<&dyn Epochs>,
+    keys: &Keys,
+) -> Result<(), AuthError> {
+    if epochs.is_some_and(|epochs| !epochs.accept(keys.epoch)) {
+        return Err(AuthError::Rollback);
+    }
+
+    let rotated = controller
+        .crypto()
+        .is_some_and(|crypto| crypto.rotate(keys.epoch, &keys.aes_key, &keys.hmac_key));
+    if !glitch::check(|| rotated) {
+        return Err(AuthError::Rollback);
+    }
+    info!("Rotated to the keys of epoch {}", keys.epoch);
+
+    if let Some(epochs) = epochs {
+        epochs.record(keys.epoch);
+    }
+    Ok(())
+}
+
+/// Takes up the keys of a new epoch pushed to this SED in the push of the given length, should
+/// they be authentic
+fn push_rotation<T: Transport>(
+    controller: &mut Controller<Handler, Registered, T>,
+    epochs: Option<&dyn Epochs>,
+    secret: &[u8; 64],
+    len: usize,
+) -> Result<(), AuthError> {
+    let push =
+        SecureSSSRotation::from_bytes(&controller.data()[..len]).ok_or(AuthError::Malformed)?;
+
+    debug!("Received secure SSS push: {:?}", push);
+
+    let addressed =
+        glitch::check(|| push.dev_id.ct_eq(controller.id()) && push.op == SSSOp::Rotate);
+    match rotation::unwrap(secret, &push).filter(|_| addressed) {
+        Some(mut keys) => {
+            let res = rotate(controller, epochs, &keys);
+            keys.clear();
+            res
+        }
+        None => Err(AuthError::Refused),
+    }
+}
+
+/// Hands the devices revoked in the push of the given length to the controller's policy, should
+/// the list be authentic
+fn push_revocation<T: Transport>(
+    controller: &mut Controller<Handler, Registered, T>,
+    secret: &[u8; 64],
+    len: usize,
+) -> Result<(), AuthError> {
+    let id = controller.id();
+    let push =
+        SecureSSSRevocation::from_bytes(&controller.data()[..len]).ok_or(AuthError::Malformed)?;
+
+    debug!("Received secure SSS push: {:?}", push);
+
+    let addressed = glitch::check(|| push.dev_id.ct_eq(id) && push.op == SSSOp::Revoke);
+    if !(addressed && revocation::verify(secret, &push)) || push.count() > REVOKED {
+        return Err(AuthError::Refused);
+    }
+
+    // the ids are copied out of the data buffer, which the controller is borrowed to update
+    let (serial, count) = (push.serial, push.count());
+    let mut revoked = [Id::Broadcast; REVOKED];
+    revoked
+        .iter_mut()
+        .zip(push.revoked())
+        .for_each(|(slot, id)| *slot = id);
+    if !controller.revoke(serial, revoked[..count].iter().copied()) {
+        return Err(AuthError::Refused);
+    }
+    info!("Revoked {} devices under serial {}", count, serial);
+    Ok(())
+}
+
+/// Builds the crypto handler for the unwrapped secrets of a registration response, and the
+/// handshakes, header CRC, allowlist, and signing keys which the deployment's capabilities call for,
+/// drawing on the entropy and checkpoints of the given authentication handler
+fn build<T: Transport>(
+    controller: &mut Controller<Handler, Registered, T>,
+    handler: &Handler,
+    secrets: &SecureSSSSecrets,
+    allowed: Option<&[Id]>,
+    signing: Option<SigningKeys>,
+) -> Registered {
+    controller.set_header_crc(secrets.caps & CAP_HEADER_CRC != 0);
+    // the allowlist is no longer than ALLOWED, as checked as it was read
+    controller.set_allowlist(allowed.map(|allowed| allowed.iter().copied()));
+    let handshakes = if secrets.caps & CAP_EPHEMERAL_KEYS == 0 {
+        None
+    } else {
+        info!("Agreeing ephemeral keys with peers");
+        Some(Handshakes::new(controller.id(), &secrets.seed).with_entropy(handler.entropy))
+    };
+    controller.set_handshakes(handshakes);
+    let suite = if secrets.caps & CAP_AES_GCM_SIV != 0 {
+        info!("Initialising AES-GCM-SIV crypto handler");
+        Suite::GcmSiv(SivHandler::new(secrets.seed, secrets.aes_key))
+    } else if secrets.caps & CAP_AES_GCM != 0 {
+        info!("Initialising AES-GCM crypto handler");
+        Suite::Gcm(GcmHandler::new(secrets.seed, secrets.aes_key))
+    } else {
+        info!("Initialising AES-CBC/HMAC crypto handler");
+        Suite::CbcHmac(CryptoHandler::new(
+            secrets.seed,
+            secrets.aes_key,
+            secrets.hmac_key,
+        ))
+    };
+    let suite = suite
+        .with_epoch(secrets.epoch)
+        .with_entropy(handler.entropy)
+        .with_buckets(deployment::BUCKETS);
+    let suite = match handler.checkpoints {
+        Some(store) => suite.with_checkpoints(store),
+        None => suite,
+    };
+
+    register(SignedHandler::new(suite, signing))
+}
+
+/// What a registration response provisions, once it has passed every check: the unwrapped
+/// secrets, the allowlist (should the deployment enable one) with the number of allowed SEDs, and
+/// the signing keys (should the deployment sign broadcasts)
+struct Provisions {
+    /// The unwrapped secrets of the response
+    secrets: SecureSSSSecrets,
+    /// The allowed SEDs, of which only the first of the count are allowed
+    allowed: [Id; ALLOWED],
+    /// The number of allowed SEDs, should the deployment enable an allowlist
+    count: Option<usize>,
+    /// The signing keys, should the deployment sign broadcasts
+    signing: Option<SigningKeys>,
+}
+
+/// Obtains the deployment's keys from the SSS by the given operation, either a registration or a
+/// rekey, and builds the crypto handler for them, notifying the CPU of the response should it be
+/// a registration
+///
+/// The CPU is only told that it registered once the response has passed every check, just before
+/// the crypto handler is built, as nothing may fail after that; should any check fail once the SSS
+/// has answered, the CPU is refused with [`Already`](SSSOp::Already) instead. Should the SSS never
+/// answer, the controller refuses the CPU itself.
+///
+/// A rekey is exchanged exactly as a registration is, save for its operation, so the SSS
+/// distributes the deployment's current keys and a fresh seed alike, and the handler built for
+/// them starts its counters afresh (or from its [checkpoints](Handler::with_checkpoints), should
+/// the handler have been given them) as at registration.
+fn provision<T: Transport>(
+    controller: &mut Controller<Handler, Registered, T>,
+    handler: &Handler,
+    secret: &[u8; 64],
+    op: SSSOp,
+) -> Result<Registered, AuthError> {
+    let (nonce, len) = request(controller, handler.suite, secret, op)?;
+    #[cfg(feature = "pq")]
+    let (pair, len) = append_encapsulation_key(controller, handler.entropy, secret, len);
+    controller.send_tx(
+        INTF::SSS,
+        &Message {
+            tgt_id: Id::SSS,
+            src_id: controller.id(),
+            len,
+        },
+    )?;
+
+    let answered = read_tagged(
+        controller,
+        secret,
+        &nonce,
+        SecureSSSResponse::size()
+            + SEALING
+            + SecureSSSAllowlist::max_size()
+            + SecureSSSSigningKeys::max_size(),
+    );
+    #[cfg(feature = "pq")]
+    let accepted =
+        answered.and_then(|(resp, len)| accept(controller, handler, secret, op, resp, len, &pair));
+    #[cfg(not(feature = "pq"))]
+    let accepted =
+        answered.and_then(|(resp, len)| accept(controller, handler, secret, op, resp, len));
+
+    let unanswered = matches!(accepted, Err(AuthError::Controller(Error::TimedOut)));
+    if op == SSSOp::Register && !unanswered {
+        let cpu_notify = SSSMessage {
+            dev_id: controller.id(),
+            op: if accepted.is_ok() {
+                SSSOp::Register
+            } else {
+                SSSOp::Already
+            },
+        };
+
+        debug!("Notifying CPU of response: {:?}", cpu_notify);
+
+        WriteCursor::new(controller.tx()).write(&cpu_notify.to_bytes());
+
+        controller.send_tx(
+            INTF::CPU,
+            &Message {
+                tgt_id: controller.id(),
+                src_id: Id::SSS,
+                len: SSSMessage::size(),
+            },
+        )?;
+    }
+
+    let Provisions {
+        secrets,
+        allowed,
+        count,
+        signing,
+    } = accepted?;
+    if let Some(epochs) = handler.epochs {
+        epochs.record(secrets.epoch);
+    }
+
+    let allowed = count.map(|count| &allowed[..count]);
+    Ok(build(controller, handler, &secrets, allowed, signing))
+}
+
+/// Checks the response of the SSS to the given operation, of the given length, and unwraps what it
+/// provisions: the response must be addressed to this SED and carry secrets of the operation, only
+/// enable capabilities which this SED advertised, unwrap under the secret, carry a well-formed
+/// allowlist and signing keys should the deployment enable them, and, should the given handler
+/// keep a record of epochs, be of an epoch no older than any accepted before
+fn accept<T: Transport>(
+    controller: &mut Controller<Handler, Registered, T>,
+    handler: &Handler,
+    secret: &[u8; 64],
+    op: SSSOp,
+    resp: SecureSSSResponse,
+    len: usize,
+    #[cfg(feature = "pq")] pair: &KeyPair,
+) -> Result<Provisions, AuthError> {
+    debug!("Received secure SSS response: {:?}", resp);
+
+    let accepted = glitch::check(|| {
+        resp.dev_id.ct_eq(controller.id()) && resp.op == op && resp.secrets.is_some()
+    });
+    let secrets = resp
+        .secrets
+        .filter(|_| accepted)
+        .ok_or(AuthError::Refused)?;
+    // the SSS only enables what was advertised, so anything else is not a response to this SED
+    if secrets.caps & !CAPS != 0 {
+        return Err(AuthError::Malformed);
+    }
+    #[cfg(feature = "pq")]
+    let (secrets, len) = open(controller, pair, secrets, len)?;
+    let secrets = keywrap::unwrap(secret, controller.id(), secrets).ok_or(AuthError::Malformed)?;
+    let (allowed, count, start) = if secrets.caps & CAP_ALLOWLIST == 0 {
+        ([Id::Broadcast; ALLOWED], None, SecureSSSResponse::size())
+    } else {
+        let (allowed, count, start) = allowlist(controller, len)?;
+        info!("Restricted to {} allowed SEDs", count);
+        (allowed, Some(count), start)
+    };
+    let signing = if secrets.caps & CAP_BROADCAST_SIGS == 0 {
+        None
+    } else {
+        info!("Broadcasts are signed");
+        Some(signing_keys(controller, start, len)?)
+    };
+
+    if handler
+        .epochs
+        .is_some_and(|epochs| !epochs.accept(secrets.epoch))
+    {
+        return Err(AuthError::Rollback);
+    }
+
+    Ok(Provisions {
+        secrets,
+        allowed,
+        count,
+        signing,
+    })
+}
+
+impl AuthHandler<Registered> for Handler {
+    fn sss_register<T: Transport>(
+        &mut self,
+        controller: &mut Controller<Self, Registered, T>,
+    ) -> Result<Registered, AuthError> {
+        // the secret is only ever unmasked for as long as it is needed, and wiped once dropped
+        let secret = self.secret.unmask();
+        provision(controller, self, &secret, SSSOp::Register)
+    }
+
+    fn sss_deregister<T: Transport>(
+        &mut self,
+        controller: &mut Controller<Self, Registered, T>,
+    ) -> Result<(), AuthError> {
+        let secret = self.secret.unmask();
+        let (nonce, len) = request(controller, self.suite, &secret, SSSOp::Deregister)?;
+        controller.send_tx(
+            INTF::SSS,
+            &Message {
+                tgt_id: Id::SSS,
+                src_id: controller.id(),
+                len,
+            },
+        )?;
+
+        let (resp, _) = read_tagged(controller, &secret, &nonce, SecureSSSResponse::size())?;
+
+        debug!("Received secure SSS response: {:?}", resp);
+
+        // the SSS only deregisters this SED once it attests to erasing its keys, which it only
+        // erases once the SSS acknowledges that
+        let answered =
+            glitch::check(|| resp.dev_id.ct_eq(controller.id()) && resp.op == SSSOp::Deregister);
+        let erased = answered && attest_erasure(controller, &secret, &nonce)?;
+
+        let cpu_notify = SSSMessage {
+            dev_id: controller.id(),
+            op: if erased {
+                SSSOp::Deregister
+            } else {
+                SSSOp::Already
+            },
+        };
+
+        debug!("Notifying CPU of response: {:?}", cpu_notify);
+
+        WriteCursor::new(controller.tx()).write(&cpu_notify.to_bytes());
+
+        controller.send_tx(
+            INTF::CPU,
+            &Message {
+                tgt_id: controller.id(),
+                src_id: Id::SSS,
+                len: SSSMessage::size(),
+            },
+        )?;
+
+        if erased {
+            Ok(())
+        } else {
+            Err(AuthError::Refused)
+        }
+    }
+
+    fn sss_push<T: Transport>(
+        &mut self,
+        controller: &mut Controller<Self, Registered, T>,
+        len: usize,
+    ) -> Result<(), AuthError> {
+        let op = SecureSSSResponse::from_bytes(&controller.data()[..len])
+            .ok_or(AuthError::Malformed)?
+            .op;
+        let secret = self.secret.unmask();
+        let res = if op == SSSOp::Revoke {
+            push_revocation(controller, &secret, len)
+        } else {
+            push_rotation(controller, self.epochs, &secret, len)
+        };
+        drop(secret);
+        // the data buffer lives as long as the controller, so the wrapped keys are not left in it
+        controller.data()[..len].fill(0);
+
+        let ack = SecureSSSResponse {
+            dev_id: controller.id(),
+            op: match (op, res.is_ok()) {
+                (SSSOp::Revoke, true) => SSSOp::Revoked,
+                (_, true) => SSSOp::Rotated,
+                (_, false) => SSSOp::Already,
+            },
+            secrets: None,
+        };
+        debug!("Acknowledging secure SSS push: {:?}", ack);
+
+        let len = ack.to_bytes(controller.tx());
+        controller.send_tx(
+            INTF::SSS,
+            &Message {
+                tgt_id: Id::SSS,
+                src_id: controller.id(),
+                len,
+            },
+        )?;
+
+        res
+    }
+
+    fn sss_rekey<T: Transport>(
+        &mut self,
+        controller: &mut Controller<Self, Registered, T>,
+    ) -> Result<Registered, AuthError> {
+        let secret = self.secret.unmask();
+        provision(controller, self, &secret, SSSOp::Rekey)
+    }
+
+    fn sss_keepalive<T: Transport>(
+        &mut self,
+        controller: &mut Controller<Self, Registered, T>,
+    ) -> Result<bool, AuthError> {
+        let mut fresh = [0_u8; 32];
+        self.entropy.fill(&mut fresh);
+        let nonce: [u8; 16] = fresh[..16].try_into().unwrap();
+        let msg = SecureSSSKeepalive {
+            dev_id: controller.id(),
+            op: SSSOp::Keepalive,
+            nonce,
+        };
+        debug!("Sending secure SSS keepalive: {:?}", msg);
+
+        WriteCursor::new(controller.tx()).write(&msg.to_bytes());
+        controller.send_tx(
+            INTF::SSS,
+            &Message {
+                tgt_id: Id::SSS,
+                src_id: controller.id(),
+                len: SecureSSSKeepalive::size(),
+            },
+        )?;
+
+        // the longest push which the SSS may have sent before it read the keepalive is read whole,
+        // so that it is skipped rather than failing the read
+        let (_, len) = read_response(controller, SecureSSSRevocation::max_size())?;
+        let alive =
+            SecureSSSAlive::from_bytes(&controller.data()[..len]).ok_or(AuthError::Malformed)?;
+        debug!("Received secure SSS keepalive answer: {:?}", alive);
+
+        let secret = self.secret.unmask();
+        let id = controller.id();
+        if !(glitch::check(|| alive.dev_id.ct_eq(id)) && keepalive::verify(&secret, &nonce, &alive))
+        {
+            return Err(AuthError::Forged);
+        }
+        match alive.op {
+            SSSOp::Keepalive => Ok(true),
+            SSSOp::Deregister => Ok(false),
+            _ => Err(AuthError::Malformed),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! The proof of an SED's registration secret, in answer to a challenge of the SSS, and of the
+//! SSS's in its response
+//!
+//! The SED never sends its secret, so that nobody who reads the SSS interface learns it. Instead,
+//! each (de)registration takes two round trips:
+//!
+//! ```text
+//! SED -> SSS: dev_id || op || suite || caps                   (SecureSSSMessage)
+//! SSS -> SED: dev_id || Challenge || nonce                    (SecureSSSChallenge)
+//! SED -> SSS: dev_id || op || HMAC(secret, nonce || dev_id)   (SecureSSSProof)
+//! SSS -> SED: the response to the operation || tag             (SecureSSSResponse)
+//! ```
+//!
+//! The SSS draws the nonce afresh for each request, so that a proof is of no use once answered,
+//! and only checks the SED's secret, suite, and capabilities once it has its proof. With the `pq`
+//! feature, the encapsulation key follows the proof rather than the request.
+//!
+//! The response is only as good as the SSS which sent it, so the SSS proves in turn that it holds
+//! the secret, which is compiled into the SED's image, by tagging the response as it is sent
+//! (sealed, should it be) under a key derived from the secret:
+//!
+//! ```text
+//! key = HKDF-Expand(HKDF-Extract(dev_id, secret), "SCEWL respond")
+//! tag = HMAC(key, nonce || response)
+//! ```
+//!
+//! The SED refuses a response whose tag does not check out before it takes anything from it, so
+//! that neither a rogue SSS nor anyone between the SED and the SSS may hand it keys of their own
+//! choosing, and, as the tag covers the nonce, nor may they replay a response to an earlier request.
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+use crate::codec::secure::SecureSSSProof;
+use crate::codec::Id;
+use crate::fatal::Fatal;
+use crate::{ct, glitch};
+
+/// The length of the tag which follows each response of the SSS to a proof
+pub const TAG: usize = 32;
+
+/// The info label from which the key of the tag of a response is expanded
+const RESPOND_LABEL: &[u8] = b"SCEWL respond";
+
+/// Proves the registration secret of the SED of the given id over the nonce of a challenge
+pub fn prove(secret: &[u8; 64], nonce: &[u8; 32], dev_id: Id) -> [u8; 32] {
+    let mut hmac = Hmac::<Sha256>::new_varkey(secret).unwrap_or_else(|_| Fatal::HmacKey.panic());
+    hmac.update(nonce);
+    hmac.update(&u16::from(dev_id).to_le_bytes());
+    hmac.finalize().into_bytes().into()
+}
+
+/// Whether the proof is of the given registration secret over the nonce, as the SSS checks it
+pub fn verify(secret: &[u8; 64], nonce: &[u8; 32], proof: &SecureSSSProof) -> bool {
+    ct::eq(&prove(secret, nonce, proof.dev_id), &proof.proof)
+}
+
+/// Tags the response of the SSS, as sent, to the SED of the given registration secret and id,
+/// answering the challenge of the given nonce
+pub fn tag(secret: &[u8; 64], nonce: &[u8; 32], dev_id: Id, response: &[u8]) -> [u8; TAG] {
+    let mut key = [0_u8; 32];
+    Hkdf::<Sha256>::new(Some(&u16::from(dev_id).to_le_bytes()), secret)
+        .expand(RESPOND_LABEL, &mut key)
+        .unwrap_or_else(|_| Fatal::SessionKey.panic());
+
+    let mut hmac = Hmac::<Sha256>::new_varkey(&key).unwrap_or_else(|_| Fatal::HmacKey.panic());
+    key.fill(0);
+    hmac.update(nonce);
+    hmac.update(response);
+    hmac.finalize().into_bytes().into()
+}
+
+/// Whether the tag of the response to this SED, whose registration secret and id are given, is by
+/// the SSS, answering the challenge of the given nonce
+pub fn authentic(
+    secret: &[u8; 64],
+    nonce: &[u8; 32],
+    dev_id: Id,
+    response: &[u8],
+    tag: &[u8],
+) -> bool {
+    let expected = self::tag(secret, nonce, dev_id, response);
+    glitch::check(|| ct::eq(&expected, tag))
+}
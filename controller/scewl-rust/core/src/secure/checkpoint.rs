@@ -0,0 +1,183 @@
+//! Reservations of the message counters of the secure handlers, which are persisted (e.g. to
+//! flash) so that a reset does not return the counters to zero
+//!
+//! # Design
+//!
+//! The counters of the secure handlers otherwise live only in RAM, so after a reset (or a new
+//! registration, which instantiates a new handler) the SED would send counters its peers have
+//! already received, which they drop as replays, and accept again the frames it had received
+//! before, which an attacker on the radio may replay. Rather than persisting every counter as it
+//! changes, which would wear the flash out within days, the handler persists a _reservation_ for
+//! each peer: a bound which every counter used with the peer is kept at or below.
+//!
+//! Once a counter passes its bound, the handler raises the bound by [`STRIDE`] past it and
+//! persists it, before the frame bearing the counter is sent, or forwarded to the CPU. After a
+//! reset, the handler resumes from the bounds it persisted: the first direct message (or
+//! broadcast) sent bears a counter above the bound, and every counter at or below the bound of a
+//! peer is taken to be received already. No counter is thereby ever sent, or accepted, twice, at
+//! the cost of the frames which a peer sent between the counter last received from it and the
+//! bound, should the SED reset in between: at most [`STRIDE`] frames per peer, which the peer's
+//! counters soon pass. The flash is written once per [`STRIDE`] frames sent to or received from
+//! each peer.
+//!
+//! The bounds are loaded from the [store](Checkpoints) the first time the handler hears of each
+//! peer, and kept alongside its counters from then on. The bounds of a peer are:
+//!
+//!  - that of the direct messages sent to it, which for the broadcast id is that of the broadcasts
+//!    this SED sends
+//!  - that of the direct messages received from it
+//!  - that of its broadcasts
+
+use core::convert::TryInto;
+use core::mem::size_of;
+
+use heapless::FnvIndexMap;
+
+use crate::codec::{Id, Message};
+use crate::deployment::PeerCapacity;
+use crate::fatal::Fatal;
+
+/// The number of counters by which a bound is raised past the counter which passed it
+pub const STRIDE: u64 = 256;
+
+/// The bounds reserved for the counters of a peer, as described in the [module
+/// documentation](self)
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Reserved {
+    /// The bound on the counters of the direct messages sent to the peer
+    pub sent: u64,
+    /// The bound on the counters of the direct messages received from the peer
+    pub received: u64,
+    /// The bound on the counters of the peer's broadcasts
+    pub brdcst: u64,
+}
+
+impl Reserved {
+    /// Serialises the bounds to the buffer, returning their length
+    pub fn to_bytes(self, buf: &mut [u8]) -> usize {
+        for (chunk, bound) in
+            buf.chunks_exact_mut(size_of::<u64>())
+                .zip([self.sent, self.received, self.brdcst])
+        {
+            chunk.copy_from_slice(&bound.to_le_bytes());
+        }
+        Reserved::size()
+    }
+
+    /// Deserialises the bounds from the buffer, should it be of their size
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if buf.len() != Reserved::size() {
+            return None;
+        }
+
+        let bound = |index: usize| {
+            let bytes = &buf[index * size_of::<u64>()..][..size_of::<u64>()];
+            u64::from_le_bytes(bytes.try_into().unwrap_or_else(|_| Fatal::Buffer.panic()))
+        };
+        Some(Reserved {
+            sent: bound(0),
+            received: bound(1),
+            brdcst: bound(2),
+        })
+    }
+
+    /// The constant size of the bounds in their serialised form
+    pub const fn size() -> usize {
+        3 * size_of::<u64>()
+    }
+}
+
+/// The counters of a peer which are bounded by a reservation
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Counter {
+    /// The counter of the direct messages sent to the peer
+    Sent,
+    /// The counter of the direct messages received from the peer
+    Received,
+    /// The counter of the peer's broadcasts
+    Brdcst,
+}
+
+impl Counter {
+    /// The counter borne by a message received, by whether it is a broadcast
+    pub(super) fn inbound(msg: Message) -> Self {
+        if msg.tgt_id == Id::Broadcast {
+            Counter::Brdcst
+        } else {
+            Counter::Received
+        }
+    }
+}
+
+/// The store in which the reservations of each peer are persisted
+///
+/// A reservation which cannot be stored is best logged and otherwise ignored by the implementation:
+/// the counters then merely restart from the last bound which was stored, as without a store.
+pub trait Checkpoints: Sync {
+    /// The bounds last stored for the peer, if any
+    fn load(&self, peer: Id) -> Option<Reserved>;
+
+    /// Stores the bounds of the peer, replacing those stored before
+    fn store(&self, peer: Id, reserved: Reserved);
+}
+
+/// The reservations of a handler, as loaded from and persisted to its store, if it has one
+pub(super) struct Reservations {
+    /// The store in which the reservations are persisted
+    store: Option<&'static dyn Checkpoints>,
+    /// The reservations of each peer heard of, as loaded on first use
+    peers: FnvIndexMap<Id, Reserved, PeerCapacity>,
+}
+
+impl Reservations {
+    /// The reservations persisted to the given store, or none should there be no store
+    pub(super) fn new(store: Option<&'static dyn Checkpoints>) -> Self {
+        Self {
+            store,
+            peers: FnvIndexMap::new(),
+        }
+    }
+
+    /// The reservation of the peer, which is loaded should this be its first use
+    fn of(&mut self, store: &dyn Checkpoints, peer: Id) -> &mut Reserved {
+        if !self.peers.contains_key(&peer) {
+            let reserved = store.load(peer).unwrap_or_default();
+            self.peers
+                .insert(peer, reserved)
+                .unwrap_or_else(|_| Fatal::PeerTable.panic());
+        }
+        self.peers
+            .get_mut(&peer)
+            .unwrap_or_else(|| Fatal::PeerTable.panic())
+    }
+
+    /// The bound on the given counter of the peer, from which the counter resumes should the
+    /// handler hold no counter of its own for it; 0 without a store
+    pub(super) fn floor(&mut self, peer: Id, counter: Counter) -> u64 {
+        let Some(store) = self.store else { return 0 };
+        let reserved = self.of(store, peer);
+        match counter {
+            Counter::Sent => reserved.sent,
+            Counter::Received => reserved.received,
+            Counter::Brdcst => reserved.brdcst,
+        }
+    }
+
+    /// Raises the bound on the given counter of the peer, and persists it, should the counter
+    /// have passed it
+    pub(super) fn reserve(&mut self, peer: Id, counter: Counter, ctr: u64) {
+        let Some(store) = self.store else { return };
+        let reserved = self.of(store, peer);
+        let bound = match counter {
+            Counter::Sent => &mut reserved.sent,
+            Counter::Received => &mut reserved.received,
+            Counter::Brdcst => &mut reserved.brdcst,
+        };
+        if ctr <= *bound {
+            return;
+        }
+
+        *bound = ctr.saturating_add(STRIDE);
+        store.store(peer, *reserved);
+    }
+}
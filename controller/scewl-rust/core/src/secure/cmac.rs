@@ -0,0 +1,128 @@
+//! AES-CMAC (RFC 4493), which authenticates the verification segment of the CBC crypto handler in
+//! place of HMAC-SHA256 with the `cmac` feature
+//!
+//! The MAC absorbs its input as it arrives, as the HMAC does when a frame is streamed, so a full
+//! block is only processed once more input follows it: the last block is masked with a subkey
+//! before it is processed, which depends on whether it is whole.
+//!
+//! [`derive`] is the KDF in counter mode of NIST SP 800-108 with AES-CMAC as its PRF, from which
+//! the key of the MAC is derived from the AES key of a pair without a hash.
+
+use aes::cipher::block::Block;
+use aes::{Aes128, BlockCipher, NewBlockCipher};
+
+/// The size of an AES block, and of a tag
+const BLOCK: usize = 16;
+
+/// Doubles the block in GF(2^128), without branching on its top bit
+fn dbl(block: [u8; BLOCK]) -> [u8; BLOCK] {
+    let v = u128::from_be_bytes(block);
+    let reduce = (v >> 127).wrapping_neg() & 0x87;
+    ((v << 1) ^ reduce).to_be_bytes()
+}
+
+/// An AES-CMAC, whose AES key schedule and subkeys are expanded once, such that each message only
+/// clones them
+#[derive(Clone)]
+pub struct Cmac {
+    /// The cipher of the key
+    cipher: Aes128,
+    /// The subkey which masks a whole last block
+    k1: [u8; BLOCK],
+    /// The subkey which masks a padded last block
+    k2: [u8; BLOCK],
+    /// The chaining value of the blocks processed so far
+    state: [u8; BLOCK],
+    /// The block absorbed but not yet processed, as it may be the last
+    pending: [u8; BLOCK],
+    /// The length of the pending block
+    len: usize,
+}
+
+impl Cmac {
+    /// A MAC keyed with the given AES key
+    pub fn new(key: &[u8; BLOCK]) -> Self {
+        let cipher = Aes128::new(key.into());
+        let mut l = Block::<Aes128>::default();
+        cipher.encrypt_block(&mut l);
+        let k1 = dbl(l.into());
+        let k2 = dbl(k1);
+
+        Self {
+            cipher,
+            k1,
+            k2,
+            state: [0; BLOCK],
+            pending: [0; BLOCK],
+            len: 0,
+        }
+    }
+
+    /// Encrypts the block chained with the state, as the next state
+    fn chain(&mut self, block: &[u8; BLOCK]) {
+        let mut chained = Block::<Aes128>::default();
+        for ((out, state), byte) in chained.iter_mut().zip(&self.state).zip(block) {
+            *out = state ^ byte;
+        }
+        self.cipher.encrypt_block(&mut chained);
+        self.state = chained.into();
+    }
+
+    /// Absorbs the input
+    pub fn update(&mut self, mut input: &[u8]) {
+        while !input.is_empty() {
+            if self.len == BLOCK {
+                let pending = self.pending;
+                self.chain(&pending);
+                self.len = 0;
+            }
+            let take = (BLOCK - self.len).min(input.len());
+            self.pending[self.len..self.len + take].copy_from_slice(&input[..take]);
+            self.len += take;
+            input = &input[take..];
+        }
+    }
+
+    /// The tag of the input absorbed
+    pub fn finalize(mut self) -> [u8; BLOCK] {
+        let mut last = self.pending;
+        let subkey = if self.len == BLOCK {
+            self.k1
+        } else {
+            last[self.len] = 0x80;
+            last[self.len + 1..].fill(0);
+            self.k2
+        };
+        last.iter_mut()
+            .zip(&subkey)
+            .for_each(|(byte, key)| *byte ^= key);
+        self.chain(&last);
+        last.fill(0);
+        self.state
+    }
+}
+
+/// Fills the output with key material derived from the key by the KDF in counter mode of NIST SP
+/// 800-108, with AES-CMAC as its PRF: the concatenation of the blocks
+///
+/// ```text
+/// CMAC(key, [i]_32 || label || 0x00 || context || [L]_32)
+/// ```
+///
+/// for `i` counting from 1, where `L` is the length of the output in bits
+pub fn derive(key: &[u8; BLOCK], label: &[u8], context: &[u8], out: &mut [u8]) {
+    #[allow(clippy::cast_possible_truncation)] // the output is at most a few blocks
+    let bits = (out.len() * 8) as u32;
+    let prf = Cmac::new(key);
+    for (i, chunk) in (1_u32..).zip(out.chunks_mut(BLOCK)) {
+        let mut mac = prf.clone();
+        mac.update(&i.to_be_bytes());
+        mac.update(label);
+        mac.update(&[0]);
+        mac.update(context);
+        mac.update(&bits.to_be_bytes());
+        let mut block = mac.finalize();
+        chunk.copy_from_slice(&block[..chunk.len()]);
+        block.fill(0);
+    }
+}
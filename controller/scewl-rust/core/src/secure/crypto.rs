@@ -0,0 +1,1133 @@
+//! The cryptography module for the secure implementation of the security features for the
+//! controller
+//!
+//! # Design
+//!
+//! The crypto handler defined in this module constructs messages to other SCEWL devices in the
+//! following layout:
+//!
+//! ```text
+//! TRANSPORT
+//!  | b'SC'    ; header magic
+//!  | tgt_id   ; Target device's ID
+//!  | src_id   ; Source device's ID
+//!  | len      ; length of the remaining sections
+//! VERIFICATION
+//!  | iv       ; initialisation vector for the content segment
+//!  | ctr      ; message counter
+//!  | epoch    ; epoch of the keys
+//!  | hmac     ; HMAC(TRANSPORT || iv || ctr || epoch || CONTENT)
+//! CONTENT (encrypted)
+//!  | msg_len  ; length of msg
+//!  | msg      ; content intended to be sent by the CPU
+//!  | padding  ; PKCS7 generated padding
+//! ```
+//!
+//! ## Transport Segment
+//!
+//! The transport segment is the message header consistent with the original specification and is
+//! not customised or modified by this crypto handler. For further information on this data type,
+//! see the [struct's documentation](crate::codec::MessageHeader).
+//!
+//! ## Verification Segment
+//!
+//! The verification segment of the header contains four main values: an initialisation vector,
+//! a counter, the epoch of the keys, and a HMAC. This is the segment which will be inspected during
+//! the [`verify`](crate::crypto::Handler::verify) method, and will inform the controller to drop
+//! the remainder of the message should its length, counter, or epoch not be admissible. As the
+//! HMAC also covers the encrypted content, it is only verified once the frame has been received in
+//! full, by [`decrypt`](crate::crypto::Handler::decrypt).
+//!
+//! ### Counter Verification
+//!
+//! Each verification segment bears a counter, which identifies the number of messages which have
+//! been sent so far to the receiver, including the current message. The radio may deliver frames
+//! out of order, so rather than only accepting counters above the highest seen, the handler keeps
+//! a sliding window of the last [`WINDOW`] counters below it for each source (one for its direct
+//! messages, one for its broadcasts), with a bit marking each counter received:
+//!
+//! ```text
+//!   too old         window (bit set: received)      new
+//! ...........|[ 0 1 1 0 1 ... 1 1 ]top|...........
+//! ```
+//!
+//! A counter above the highest seen is accepted and slides the window up to it; one within the
+//! window is accepted only should its bit be clear; any other is discarded as a replay. A counter
+//! is only recorded once the frame bearing it is authenticated, so a forged frame can neither
+//! slide the window nor mark a counter as received.
+//!
+//! The counters sent are kept apart from those received: one for the direct messages sent to each
+//! peer, and a single one for this SED's broadcasts, which no broadcast received ever moves.
+//!
+//! Counters are not falsifiable as they are authenticated by the HMAC. Should the handler be given
+//! [checkpoints](Handler::with_checkpoints), the counters of each peer resume from the bounds
+//! persisted before the last reset, rather than from zero (see [`Checkpoints`]).
+//!
+//! The counter, HMAC, padding, and length checks are each made with [`glitch::check`], so that a
+//! single fault injected into the core cannot flip any of them into accepting a frame.
+//!
+//! ### Session Keys
+//!
+//! The AES and HMAC keys distributed by the SSS are not used directly. Together, they are the
+//! master secret of the deployment, from which the keys of each pair of source and target (the
+//! broadcast id being the target of broadcasts) are derived with HKDF-SHA256, with an info label
+//! naming the pair:
+//!
+//! ```text
+//! aes_key' || hmac_key' = HKDF-Expand(HKDF-Extract(aes_key || hmac_key), "SCEWL pair" || src || tgt)
+//! ```
+//!
+//! so that no two pairs share a key, and a key recovered from the traffic of one pair (e.g. by a
+//! side channel) says nothing of those of any other. As every SED still holds the master secret,
+//! this does not keep an SED which is itself compromised from deriving the keys of other pairs.
+//!
+//! Deriving the keys of a pair, and expanding their AES key schedule and HMAC hash states, costs
+//! several hashes, so the keys of the last [`SESSIONS`] pairs are kept, in a bounded map; should
+//! it be full, an arbitrary pair is evicted, and its keys are derived anew should it be heard from
+//! again.
+//!
+//! ### Forward Secrecy
+//!
+//! Should the SSS enable [`CAP_EPHEMERAL_KEYS`](crate::codec::secure::CAP_EPHEMERAL_KEYS), the
+//! controller agrees a secret with each peer it exchanges direct messages with in a
+//! [handshake](super::Handshakes), which it hands to the handler by
+//! [`rekey`](crate::crypto::Handler::rekey). The keys of the pairs of this SED and that peer (in
+//! either direction) are then extracted anew from the secret, salted with the keys derived from
+//! the master secret as above:
+//!
+//! ```text
+//! okm = HKDF-Expand(HKDF-Extract(aes_key || hmac_key), "SCEWL pair" || src || tgt)
+//! aes_key' || hmac_key' = HKDF-Expand(HKDF-Extract(okm, secret), "SCEWL pair" || src || tgt)
+//! ```
+//!
+//! so that the master secret alone no longer recovers them. The frames which the peer sent before
+//! it learnt of the secret are under the keys of the master secret, so an HMAC which fails to
+//! verify under the agreed keys is tried under those, until the first frame under the agreed keys
+//! has been received from the peer. Until then, a frame is absorbed by both HMACs as it is
+//! received, and only decrypted once it is known which keys it was sent under.
+//!
+//! ### Key Rotation
+//!
+//! The SSS may replace the deployment's keys while SEDs are registered, pushing the keys of a new
+//! [epoch](super::rotation) to each, which the handler takes up by
+//! [`rotate`](crate::crypto::Handler::rotate). The SSS reaches the SEDs one at a time, so the
+//! handler keeps the keys of the epoch it held before alongside the new ones: each frame bears
+//! the epoch of the keys it was sent under, and a frame under the previous epoch is verified and
+//! decrypted under the keys derived from that epoch's master secret, whereas a frame under any
+//! other epoch is dropped as [keys not held](Reason::NoKey). A frame sent under the new keys to a peer which has not
+//! yet taken them up is dropped likewise, until the SSS reaches that peer. The session keys are
+//! kept by epoch as well as by pair, and those of an epoch are dropped once it is superseded
+//! twice. The counters carry on across epochs, so that a frame of the previous epoch is still
+//! checked against the replay windows.
+//!
+//! ### HMAC Verification
+//!
+//! Each verification segment bears an HMAC which ensures the integrity and authenticity of the
+//! whole frame: the transport and verification segments, and the encrypted content segment. The
+//! crypto handler implements this HMAC using the 64-byte HMAC key of the pair and the SHA256
+//! hashing algorithm.
+//!
+//! The HMAC is calculated in the typical fashion and is the result of:
+//!
+//! ```text
+//! HMAC(TRANSPORT || iv || ctr || epoch || CONTENT)
+//! ```
+//!
+//! where `||` is the concatenation operator, and `CONTENT` is the ciphertext (encrypt-then-MAC).
+//!
+//! Should any part of the transport header, the initialisation vector, the counter, the epoch, or
+//! the ciphertext be corrupted or modified, the HMAC will not be verifiable. In addition, should
+//! the HMAC itself be corrupted or modified, it will not be verifiable. The HMAC is verified
+//! before any of the content is examined, so a frame whose HMAC fails says nothing of its
+//! plaintext, and the counter is only recorded once the HMAC is verified.
+//!
+//! ### AES-CMAC Verification
+//!
+//! With the `cmac` feature, the verification segment instead bears an AES-CMAC (RFC 4493, see
+//! [`cmac`](crate::secure::cmac)) of the same input, keyed with a key derived from the AES key of
+//! the pair by the KDF in counter mode of NIST SP 800-108, with AES-CMAC as its PRF:
+//!
+//! ```text
+//! cmac_key = KDF-CMAC(aes_key, "SCEWL cmac", "")
+//! ```
+//!
+//! The 16-byte tag is followed by 16 zeroes, so that frames keep the layout above, and the image
+//! advertises [`SUITE_CBC_CMAC`](crate::codec::secure::SUITE_CBC_CMAC) to the SSS, as an SED of
+//! one suite cannot verify the frames of the other. Only the per-frame path is then free of
+//! SHA-256: the session keys are still expanded by HKDF-SHA256, and the registration, the key
+//! wrap, the reseeding, and the keepalives still rely on HMAC-SHA256, so the `sha2` and `hmac`
+//! crates are linked either way.
+//!
+//! ## Content Segment
+//!
+//! The content segment of the header contains the length of the original message, the original
+//! message, and padding as generated by PKCS7. This segment's contents are encrypted using AES128
+//! in the cipher block-chaining (CBC) mode with the initialisation vector specified in the
+//! verification segment. As the HMAC covers the ciphertext, the content bears no hash of its own.
+//!
+//! ### Bucket Padding
+//!
+//! The length of a frame is in the clear, and so, but for the padding to the AES block, is that of
+//! the CPU's message. Should the handler be given [buckets](Handler::with_buckets), the content is
+//! instead followed by zeroes up to the smallest bucket its ciphertext fits, before its PKCS7
+//! padding, so that the radio only reveals which bucket a message fits. The receiver needs no
+//! buckets of its own: the authenticated length in the content header already bounds the content,
+//! and the zeroes are left behind it. A message too long for every bucket, or for a bucket which
+//! fits within the data buffer, is only padded to the AES block.
+//!
+//! ### Length and Padding Verification
+//!
+//! Once the HMAC is verified, the padding is checked, and the length is checked to fit within the
+//! encrypted segment, so that a sender which erred cannot have the receiver read past the content.
+//! A failure of either will cause the message to be dropped. Both are checked before the message
+//! is dropped, and in [constant time](crate::ct), though as they are only checked once the frame
+//! is known to be authentic, they cannot serve as a padding oracle.
+//!
+//! ### Streaming Decryption
+//!
+//! As CBC decryption of a block only needs that block and the ciphertext of the one before it, the
+//! handler [streams](crate::crypto::Handler::stream_block) decryption: once a frame's verification
+//! segment is admitted, each block of its content segment is absorbed by the HMAC and then
+//! decrypted as soon as it arrives, while the rest of the frame is still being received. The CBC
+//! state carries the last ciphertext block across the calls, as it has since been overwritten by
+//! its plaintext. The HMAC, padding, and length are only verified once the frame has arrived in
+//! full, as above, and no plaintext is released before they are.
+//!
+//! ### Failure Jitter
+//!
+//! A frame which fails any of the checks above is dropped only after a random
+//! [delay](crate::crypto::Handler::jitter) of up to [`JITTER`] cycles, drawn from the CSPRNG, so
+//! that the time taken to drop a frame does not tell an attacker on the radio which check failed.
+//!
+//! # Security Requirement Compliance
+//!
+//! This implementation provides security requirements 5.1-5.4 of the specification. Requirement
+//! 5.5 is implemented in the [controller run loop](crate::controller::Controller::run).
+//!
+//! ## Confidentiality (5.1)
+//!
+//! The confidentiality of messages is protected as described in Content Segment. No message from a
+//! SED to any other SED, including broadcasts, will be sent in cleartext; instead, they will be
+//! sent as encrypted by a globally shared AES key, which will only be provisioned to appropriately
+//! registered and authenticated SEDs, as guaranteed by the SSS implementation.
+//!
+//! ## Integrity (5.2)
+//!
+//! The integrity of messages is verified by the counter and HMAC described in Verification Segment
+//! and the length and padding described in the Content Segment section. Messages which fail
+//! integrity checks will be dropped as though no message was ever received.
+//!
+//! ## Authentication (5.3)
+//!
+//! Messages are authenticated by the HMAC described in Verification Segment. Should HMAC
+//! verification fail, the message is simply dropped. Only properly provisioned
+//! SEDs will be able to send an authentic (and non-replayed) HMAC, as ensured by the SSS
+//! implementation.
+//!
+//! ## Replay Protection (5.4)
+//!
+//! Messages are verified to be new by checking their counter field as described in Verification
+//! Segment. If the counter was already received, or is too old to tell, the message will be
+//! dropped. The counter itself is verified by the HMAC as described in Verification Segment.
+
+use core::convert::TryFrom;
+use core::mem::{replace, size_of};
+use core::slice;
+
+use aes::cipher::block::Block;
+use aes::{Aes128, NewBlockCipher};
+use block_modes::block_padding::Pkcs7;
+use block_modes::{BlockMode, Cbc};
+use heapless::consts::U4;
+use heapless::FnvIndexMap;
+use hkdf::Hkdf;
+#[cfg(not(feature = "cmac"))]
+use hmac::{Hmac, Mac, NewMac};
+use rand_core::RngCore;
+use rand_hc::Hc128Rng;
+use sha2::Sha256;
+
+use crate::codec::secure::{ContentHeader, VerificationSegment};
+use crate::codec::{Id, Message, MessageHeader};
+use crate::crypto::Handler as CryptoHandler;
+use crate::ct;
+use crate::cursor::WriteCursor;
+use crate::deployment::PeerCapacity;
+use crate::diag::Reason;
+use crate::fatal::Fatal;
+use crate::glitch;
+use crate::scratch::Pool;
+use crate::secure::checkpoint::{Checkpoints, Counter, Reservations};
+#[cfg(feature = "cmac")]
+use crate::secure::cmac::{self, Cmac};
+use crate::secure::erasure;
+use crate::secure::handshake::Agreements;
+use crate::secure::reseed::{Entropy, Reseeding};
+use crate::{debug, invariant, trace, warn};
+
+/// Shorthand for the AES mode used by the crypto handler
+type Aes128Cbc = Cbc<Aes128, Pkcs7>;
+/// Shorthand for the HMAC algorithm used by the crypto handler
+#[cfg(not(feature = "cmac"))]
+type HmacSha256 = Hmac<Sha256>;
+/// Shorthand for the MAC which authenticates the verification segment
+#[cfg(not(feature = "cmac"))]
+type Tag = HmacSha256;
+/// Shorthand for the MAC which authenticates the verification segment, AES-CMAC with the `cmac`
+/// feature (see [AES-CMAC Verification](self#aes-cmac-verification))
+#[cfg(feature = "cmac")]
+type Tag = Cmac;
+/// Shorthand for the counter tables, which are looked up several times per message and so are
+/// hash-indexed by id, with room for every peer in the deployment
+type Counters = FnvIndexMap<Id, u64, PeerCapacity>;
+
+/// The number of counters below the highest received from a source which are still accepted, each
+/// at most once, from frames delivered out of order
+pub const WINDOW: u64 = 64;
+
+/// The counters received from a source, as a window below the highest of them
+#[derive(Copy, Clone)]
+struct Window {
+    /// The highest counter received
+    top: u64,
+    /// The counters received within the window, of which bit `n` marks `top - n`
+    seen: u64,
+}
+
+impl Window {
+    /// The window of a source which has sent nothing since the counters were reset to the given
+    /// floor (see [`Checkpoints`](super::Checkpoints)), at or below which every counter is taken
+    /// to be received; counters start at 1, so 0 is never accepted
+    fn resumed(floor: u64) -> Self {
+        Window {
+            top: floor,
+            seen: !0,
+        }
+    }
+
+    /// Whether the counter is yet to be received, and not too old to tell
+    fn admits(self, ctr: u64) -> bool {
+        match self.top.checked_sub(ctr) {
+            None => true,
+            Some(age) => age < WINDOW && self.seen & 1 << age == 0,
+        }
+    }
+
+    /// Marks the counter as received, sliding the window up to it should it be the highest
+    fn record(&mut self, ctr: u64) {
+        if ctr > self.top {
+            let ahead = ctr - self.top;
+            self.seen = if ahead < WINDOW {
+                self.seen << ahead | 1
+            } else {
+                1
+            };
+            self.top = ctr;
+        } else if self.top - ctr < WINDOW {
+            self.seen |= 1 << (self.top - ctr);
+        }
+    }
+}
+
+/// The replay windows of the sources heard from, as described in [Counter
+/// Verification](self#counter-verification)
+#[derive(Default)]
+pub(super) struct Windows {
+    /// The windows of the direct messages of each source
+    dm: FnvIndexMap<Id, Window, PeerCapacity>,
+    /// The windows of the broadcasts of each source
+    brdcst: FnvIndexMap<Id, Window, PeerCapacity>,
+}
+
+impl Windows {
+    /// The windows in which the counter of the message lies, by whether it is a broadcast
+    fn of(&mut self, msg: Message) -> &mut FnvIndexMap<Id, Window, PeerCapacity> {
+        match msg.tgt_id {
+            Id::Broadcast => &mut self.brdcst,
+            Id::Other(_) => &mut self.dm,
+            _ => Fatal::Unencrypted.panic(),
+        }
+    }
+
+    /// Whether the counter borne by the message is yet to be received from its source, whose
+    /// window resumes from the given floor should it have sent nothing yet
+    pub(super) fn admits(&mut self, msg: Message, ctr: u64, floor: u64) -> bool {
+        let window = self.of(msg).get(&msg.src_id).copied();
+        window.unwrap_or_else(|| Window::resumed(floor)).admits(ctr)
+    }
+
+    /// Marks the counter borne by the message, which must be authentic, as received from its
+    /// source, whose window resumes from the given floor should it have sent nothing yet
+    pub(super) fn record(&mut self, msg: Message, ctr: u64, floor: u64) {
+        let windows = self.of(msg);
+        let mut window = windows
+            .get(&msg.src_id)
+            .copied()
+            .unwrap_or_else(|| Window::resumed(floor));
+        window.record(ctr);
+        // a full table refuses to insert even a key it holds, so the window is updated in place
+        match windows.get_mut(&msg.src_id) {
+            Some(held) => *held = window,
+            None => windows
+                .insert(msg.src_id, window)
+                .map_or_else(|_| Fatal::PeerTable.panic(), drop),
+        }
+    }
+}
+
+/// The counters of the frames sent, as described in [Counter Verification](self#counter-verification)
+///
+/// Those of broadcasts are kept apart from the [windows](Windows) of the broadcasts received, as
+/// the counter of this SED's broadcasts is its own, not that of any source heard from.
+#[derive(Default)]
+pub(super) struct Sent {
+    /// The counters of the direct messages sent to each peer
+    dm: Counters,
+    /// The counter of the broadcasts sent, should any have been
+    brdcst: Option<u64>,
+}
+
+impl Sent {
+    /// Counts a frame sent with the message, returning the counter it bears; should nothing have
+    /// been sent to its target yet, the counter resumes from the bound reserved for it, which is
+    /// raised past the counter should it be passed
+    ///
+    /// The bound of the broadcasts sent is that of the direct messages sent to the broadcast id.
+    pub(super) fn next(&mut self, msg: Message, reserved: &mut Reservations) -> u64 {
+        let prev = match msg.tgt_id {
+            // bounds persisted by earlier firmware reserved the broadcasts sent as those of this
+            // SED's own id, so are honoured too; skipping counters is harmless
+            Id::Broadcast => self.brdcst.unwrap_or_else(|| {
+                let bound = reserved.floor(Id::Broadcast, Counter::Sent);
+                bound.max(reserved.floor(msg.src_id, Counter::Brdcst))
+            }),
+            id @ Id::Other(_) => {
+                let prev = self.dm.get(&id).copied();
+                prev.unwrap_or_else(|| reserved.floor(id, Counter::Sent))
+            }
+            _ => Fatal::Unencrypted.panic(),
+        };
+        let ctr = prev.wrapping_add(1);
+        invariant!(ctr > prev);
+
+        match msg.tgt_id {
+            Id::Broadcast => self.brdcst = Some(ctr),
+            // as for the windows received, the counter of a peer already held is updated in place
+            id => match self.dm.get_mut(&id) {
+                Some(held) => *held = ctr,
+                None => self
+                    .dm
+                    .insert(id, ctr)
+                    .map_or_else(|_| Fatal::PeerTable.panic(), drop),
+            },
+        }
+        reserved.reserve(msg.tgt_id, Counter::Sent, ctr);
+        ctr
+    }
+}
+
+/// The length to which plaintext of the given length is filled with zeroes before its PKCS7
+/// padding, so that its ciphertext is the smallest of the buckets which it fits within the room
+/// left in the data buffer, or its own length should it fit none
+fn filled(buckets: &[usize], plain: usize, room: usize) -> usize {
+    buckets
+        .iter()
+        .find(|&&bucket| bucket > plain && bucket <= room)
+        .map_or(plain, |&bucket| plain.max(bucket.saturating_sub(16)))
+}
+
+/// The number of pairs of SEDs whose session keys are kept, of about 1 KB each
+pub const SESSIONS: usize = 4;
+
+/// Shorthand for the session keys kept, by [slot](slot)
+type Sessions = FnvIndexMap<u64, Session, U4>;
+
+/// The info label from which the keys of a pair are expanded, before the ids of the pair
+const PAIR_LABEL: &[u8] = b"SCEWL pair";
+
+/// The label from which the key of the CMAC of a pair is derived from its AES key
+#[cfg(feature = "cmac")]
+const CMAC_LABEL: &[u8] = b"SCEWL cmac";
+
+/// The bound (exclusive) on the delay before a failed frame is dropped, in core clock cycles;
+/// about 160us at the lm3s6965's 50 MHz, which is of the order of an HMAC
+pub const JITTER: u32 = 8192;
+
+/// The keys under which a frame may have been sent, of which the HMAC absorbs the frame as it is
+/// received
+struct Candidate {
+    /// The AES cipher of the keys
+    aes: Aes128,
+    /// The HMAC of the keys, which has absorbed the frame so far
+    hmac: Tag,
+    /// Whether the keys are those agreed with the peer, which a frame verified under them confirms
+    agreed: bool,
+}
+
+/// The verification and decryption of a frame which are in progress as it is received
+struct Stream {
+    /// The keys of the pair of the frame
+    keys: Candidate,
+    /// The keys of the master secret, should the peer not have confirmed the secret agreed with it
+    fallback: Option<Candidate>,
+    /// The CBC state, which holds the last ciphertext block decrypted, should the frame be
+    /// decrypted as it is received; it only is when there is no fallback, as the AES key is then
+    /// known before the HMAC is verified
+    cbc: Option<Aes128Cbc>,
+    /// The offset in the data buffer up to which the frame has been absorbed by the HMACs
+    absorbed: usize,
+    /// The offset in the data buffer up to which the frame has been decrypted
+    decrypted: usize,
+}
+
+impl Stream {
+    /// Absorbs the ciphertext up to the given offset in the data buffer into the HMACs
+    fn absorb(&mut self, data: &[u8], end: usize) {
+        let ciphertext = &data[self.absorbed..end];
+        self.keys.hmac.update(ciphertext);
+        if let Some(fallback) = self.fallback.as_mut() {
+            fallback.hmac.update(ciphertext);
+        }
+        self.absorbed = end;
+    }
+}
+
+/// Keys the MAC which authenticates the verification segment with the HMAC key of a pair
+#[cfg(not(feature = "cmac"))]
+fn keyed(_aes_key: &[u8; 16], hmac_key: &[u8]) -> Tag {
+    HmacSha256::new_varkey(hmac_key).unwrap_or_else(|_| Fatal::HmacKey.panic())
+}
+
+/// Keys the MAC which authenticates the verification segment with a key derived from the AES key
+/// of a pair by the [CMAC-based KDF](cmac::derive)
+#[cfg(feature = "cmac")]
+fn keyed(aes_key: &[u8; 16], _hmac_key: &[u8]) -> Tag {
+    let mut cmac_key = [0_u8; 16];
+    cmac::derive(aes_key, CMAC_LABEL, &[], &mut cmac_key);
+    let cmac = Cmac::new(&cmac_key);
+    cmac_key.fill(0);
+    cmac
+}
+
+/// The tag of the MAC, as borne by the verification segment
+#[cfg(not(feature = "cmac"))]
+fn finish(mac: Tag) -> [u8; 32] {
+    mac.finalize().into_bytes().into()
+}
+
+/// The tag of the MAC, as borne by the verification segment: the 16-byte tag followed by zeroes, so
+/// that the layout of the frame is that of HMAC-SHA256
+#[cfg(feature = "cmac")]
+fn finish(mac: Tag) -> [u8; 32] {
+    let mut tag = [0_u8; 32];
+    tag[..16].copy_from_slice(&mac.finalize());
+    tag
+}
+
+/// Reinterprets whole blocks of a buffer as cipher blocks, as the CBC mode takes them
+fn blocks(buf: &mut [u8]) -> &mut [Block<Aes128>] {
+    // SAFETY: a block is a byte array, of alignment 1 and without padding, and only the whole
+    // blocks of the buffer are covered
+    unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr().cast::<Block<Aes128>>(), buf.len() / 16) }
+}
+
+/// The secrets distributed by the SSS on registration, as received
+struct Secrets {
+    /// The seed of the CSPRNG
+    seed: [u8; 32],
+    /// The AES key
+    aes_key: [u8; 16],
+    /// The HMAC key
+    hmac_key: [u8; 64],
+    /// The epoch of the keys
+    epoch: u32,
+}
+
+impl Secrets {
+    /// Extracts the HKDF's pseudorandom key from the master secret
+    fn extract(&self) -> Hkdf<Sha256> {
+        let mut ikm = [0_u8; 16 + 64];
+        WriteCursor::new(&mut ikm)
+            .write(&self.aes_key)
+            .write(&self.hmac_key);
+        let hkdf = Hkdf::new(None, &ikm);
+        ikm.fill(0);
+        hkdf
+    }
+}
+
+/// The pair of source and target of a message, from which its session keys are derived
+fn pair(msg: Message) -> u32 {
+    u32::from(u16::from(msg.src_id)) << 16 | u32::from(u16::from(msg.tgt_id))
+}
+
+/// The epoch and pair of a message, by which its session keys are kept
+fn slot(msg: Message, epoch: u32) -> u64 {
+    u64::from(epoch) << 32 | u64::from(pair(msg))
+}
+
+/// The secrets as expanded for use
+struct Keys {
+    /// A CSPRNG which is used to generate random IVs
+    rng: Hc128Rng,
+    /// The HKDF, extracted once from the master secret such that each pair only expands it
+    hkdf: Hkdf<Sha256>,
+    /// The epoch of the master secret
+    epoch: u32,
+    /// The epoch and HKDF of the master secret held before the last rotation, if any
+    previous: Option<(u32, Hkdf<Sha256>)>,
+    /// The session keys of the pairs last heard from
+    sessions: Sessions,
+}
+
+impl Keys {
+    /// Seeds the CSPRNG, mixing in fresh entropy should there be a source, and extracts the
+    /// HKDF's pseudorandom key from the master secret
+    fn expand(secrets: &Secrets, reseed: &Reseeding) -> Self {
+        Self {
+            rng: reseed.seed(&secrets.seed),
+            hkdf: secrets.extract(),
+            epoch: secrets.epoch,
+            previous: None,
+            sessions: Sessions::new(),
+        }
+    }
+
+    /// The HKDF of the master secret of the given epoch, should it be held
+    fn hkdf(&self, epoch: u32) -> Option<&Hkdf<Sha256>> {
+        if epoch == self.epoch {
+            return Some(&self.hkdf);
+        }
+        match &self.previous {
+            Some((previous, hkdf)) if *previous == epoch => Some(hkdf),
+            _ => None,
+        }
+    }
+
+    /// The session keys of the pair of the message under the master secret of the given epoch,
+    /// which are derived, from the secret agreed with the peer if any, should they not be kept, or
+    /// `None` should that master secret not be held
+    fn session(&mut self, msg: Message, epoch: u32, agreed: Option<&[u8; 32]>) -> Option<&Session> {
+        let slot = slot(msg, epoch);
+        if !self.sessions.contains_key(&slot) {
+            let session = Session::derive(self.hkdf(epoch)?, pair(msg), agreed);
+            if self.sessions.len() == SESSIONS {
+                let evicted = self.sessions.keys().next().copied();
+                if let Some(evicted) = evicted {
+                    self.sessions.swap_remove(&evicted);
+                }
+            }
+            self.sessions
+                .insert(slot, session)
+                .unwrap_or_else(|_| Fatal::PeerTable.panic());
+        }
+        self.sessions.get(&slot)
+    }
+
+    /// Extracts the HKDF of the master secret of the new epoch, keeping that of the epoch before,
+    /// and drops the session keys of the epoch which is no longer held
+    fn rotate(&mut self, secrets: &Secrets) {
+        let hkdf = replace(&mut self.hkdf, secrets.extract());
+        let epoch = replace(&mut self.epoch, secrets.epoch);
+        self.previous = Some((epoch, hkdf));
+
+        let held = |slot: &u64| {
+            let of = |epoch: u32| *slot >> 32 == u64::from(epoch);
+            of(epoch) || of(secrets.epoch)
+        };
+        loop {
+            let stale = self.sessions.keys().copied().find(|slot| !held(slot));
+            let Some(slot) = stale else { break };
+            self.sessions.swap_remove(&slot);
+        }
+    }
+}
+
+/// The session keys of a pair of SEDs, as expanded for use
+struct Session {
+    /// The AES cipher, whose key schedule is expanded once
+    aes: Aes128,
+    /// The HMAC, keyed once such that each message only clones its hash states
+    hmac: Tag,
+}
+
+impl Session {
+    /// Derives the keys of the pair from the master secret, and from the secret agreed with the
+    /// peer if any, expands the AES key schedule, and computes the HMAC's keyed hash states
+    fn derive(hkdf: &Hkdf<Sha256>, pair: u32, agreed: Option<&[u8; 32]>) -> Self {
+        let mut info = [0_u8; PAIR_LABEL.len() + size_of::<u32>()];
+        WriteCursor::new(&mut info)
+            .write(PAIR_LABEL)
+            .write_u32(pair);
+        let mut okm = [0_u8; 16 + 64];
+        hkdf.expand(&info, &mut okm)
+            .unwrap_or_else(|_| Fatal::SessionKey.panic());
+        if let Some(agreed) = agreed {
+            let mut salt = okm;
+            Hkdf::<Sha256>::new(Some(&salt), agreed)
+                .expand(&info, &mut okm)
+                .unwrap_or_else(|_| Fatal::SessionKey.panic());
+            salt.fill(0);
+        }
+
+        let (aes_key, hmac_key) = okm.split_at(16);
+        let aes_key = <&[u8; 16]>::try_from(aes_key).unwrap_or_else(|_| Fatal::SessionKey.panic());
+        let session = Self {
+            aes: Aes128::new(aes_key.into()),
+            hmac: keyed(aes_key, hmac_key),
+        };
+        okm.fill(0);
+        session
+    }
+
+    /// Begins the HMAC which authenticates a message, i.e. HMAC(TRANSPORT || iv || ctr || epoch ||
+    /// ciphertext), having absorbed all but the ciphertext, the input to which is assembled in a
+    /// scratch buffer
+    fn mac(&self, msg: Message, seg: &VerificationSegment, scratch: &Pool) -> Tag {
+        /// The length of the authenticated input
+        const LEN: usize = MessageHeader::size() + 16 + size_of::<u64>() + size_of::<u32>();
+
+        let mut input = scratch.take().unwrap_or_else(|| Fatal::Scratch.panic());
+        WriteCursor::new(&mut input[..LEN])
+            .write(&msg.to_canonical().to_bytes())
+            .write(&seg.iv)
+            .write_u64(seg.ctr)
+            .write_u32(seg.epoch);
+
+        let mut hmac = self.hmac.clone();
+        hmac.update(&input[..LEN]);
+        hmac
+    }
+}
+
+/// The secure crypto handler, which performs encryption, decryption, and verification of messages
+pub struct Handler {
+    /// The secrets received on registration, from which the keys are expanded
+    secrets: Secrets,
+    /// The keys, once expanded on first use
+    keys: Option<Keys>,
+    /// The counters of the outbound direct messages and broadcasts
+    sent: Sent,
+    /// The replay windows of the inbound direct messages and broadcasts
+    recv: Windows,
+    /// The decryption of the frame last verified, if it is in progress
+    stream: Option<Stream>,
+    /// The secrets agreed with peers in handshakes
+    agreed: Agreements,
+    /// The reservations of the counters, should they be persisted
+    reserved: Reservations,
+    /// The reseeding of the CSPRNG, should there be an entropy source
+    reseed: Reseeding,
+    /// The sizes to which the ciphertext of each frame is padded, ascending
+    buckets: &'static [usize],
+}
+
+impl Handler {
+    /// Instantiates a new instance of the crypto handler with the given CSPRNG seed and keys
+    ///
+    /// The keys are only expanded (seeding the CSPRNG, expanding the AES key schedule, and
+    /// computing the HMAC's keyed hash states) on the first message sent or received, and then
+    /// only once, rather than here; the registration round trip seen by the CPU then does not
+    /// wait on them, which is slowest on emulated cores.
+    pub fn new(seed: [u8; 32], aes_key: [u8; 16], hmac_key: [u8; 64]) -> Self {
+        Self {
+            secrets: Secrets {
+                seed,
+                aes_key,
+                hmac_key,
+                epoch: 0,
+            },
+            keys: None,
+            sent: Sent::default(),
+            recv: Windows::default(),
+            stream: None,
+            agreed: Agreements::default(),
+            reserved: Reservations::new(None),
+            reseed: Reseeding::new(None),
+            buckets: &[],
+        }
+    }
+
+    /// Pads the ciphertext of each frame to the smallest of the buckets it fits, which are in
+    /// ascending multiples of the AES block, rather than only to the AES block (see [Bucket
+    /// Padding](self#bucket-padding))
+    #[must_use]
+    pub fn with_buckets(mut self, buckets: &'static [usize]) -> Self {
+        self.buckets = buckets;
+        self
+    }
+
+    /// Persists reservations of the counters to the store, from which they resume after a reset
+    /// rather than from zero (see [`Checkpoints`])
+    #[must_use]
+    pub fn with_checkpoints(mut self, store: &'static dyn Checkpoints) -> Self {
+        self.reserved = Reservations::new(Some(store));
+        self
+    }
+
+    /// Marks the keys as those of the given epoch, as distributed alongside them by the SSS,
+    /// rather than of epoch 0
+    #[must_use]
+    pub fn with_epoch(mut self, epoch: u32) -> Self {
+        self.secrets.epoch = epoch;
+        self
+    }
+
+    /// Mixes fresh entropy from the source into the CSPRNG, both when it is first seeded and
+    /// periodically thereafter (see [reseeding](super::reseed))
+    #[must_use]
+    pub fn with_entropy(mut self, source: &'static dyn Entropy) -> Self {
+        self.reseed = Reseeding::new(Some(source));
+        self
+    }
+
+    /// The expanded keys, which are expanded should this be their first use
+    fn keys(&mut self) -> &mut Keys {
+        let (secrets, reseed) = (&self.secrets, &self.reseed);
+        self.keys
+            .get_or_insert_with(|| Keys::expand(secrets, reseed))
+    }
+
+    /// The CSPRNG, for a draw which is counted towards its reseeding
+    fn rng(&mut self) -> &mut Hc128Rng {
+        let (secrets, reseed) = (&self.secrets, &mut self.reseed);
+        let keys = self
+            .keys
+            .get_or_insert_with(|| Keys::expand(secrets, reseed));
+        reseed.draw(&mut keys.rng)
+    }
+
+    /// Begins the verification of a frame whose verification segment is given, under the keys of
+    /// its pair and, should the peer not have confirmed the secret agreed with it, under those of
+    /// the master secret, or refuses it should the keys of its epoch not be held
+    fn begin(
+        &mut self,
+        msg: Message,
+        ct_hdr: &VerificationSegment,
+        scratch: &Pool,
+    ) -> Result<Stream, Reason> {
+        let (secrets, reseed) = (&self.secrets, &self.reseed);
+        let keys = self
+            .keys
+            .get_or_insert_with(|| Keys::expand(secrets, reseed));
+        let agreed = self.agreed.of(msg);
+        let Some(session) = keys.session(msg, ct_hdr.epoch, agreed.map(|agreed| &agreed.secret))
+        else {
+            warn!("Keys of epoch {} not held; ignoring.", ct_hdr.epoch);
+            return Err(Reason::NoKey);
+        };
+        let candidate = Candidate {
+            aes: session.aes.clone(),
+            hmac: session.mac(msg, ct_hdr, scratch),
+            agreed: agreed.is_some(),
+        };
+
+        let fallback = agreed
+            .filter(|agreed| !agreed.confirmed)
+            .and_then(|_| keys.hkdf(ct_hdr.epoch))
+            .map(|hkdf| {
+                let fallback = Session::derive(hkdf, pair(msg), None);
+                Candidate {
+                    hmac: fallback.mac(msg, ct_hdr, scratch),
+                    aes: fallback.aes,
+                    agreed: false,
+                }
+            });
+        let cbc = fallback
+            .is_none()
+            .then(|| Aes128Cbc::new(candidate.aes.clone(), &ct_hdr.iv.into()));
+
+        Ok(Stream {
+            keys: candidate,
+            fallback,
+            cbc,
+            absorbed: VerificationSegment::size(),
+            decrypted: VerificationSegment::size(),
+        })
+    }
+
+    /// The session keys of the pair of the message under the current epoch, as derived from the
+    /// secret agreed with the peer, should there be one
+    fn session(&mut self, msg: Message) -> &Session {
+        let (secrets, reseed) = (&self.secrets, &self.reseed);
+        let agreed = self.agreed.of(msg).map(|agreed| &agreed.secret);
+        self.keys
+            .get_or_insert_with(|| Keys::expand(secrets, reseed))
+            .session(msg, secrets.epoch, agreed)
+            .unwrap_or_else(|| Fatal::SessionKey.panic())
+    }
+}
+
+impl CryptoHandler for Handler {
+    fn verify(&mut self, data: &[u8], msg: Message, scratch: &Pool) -> Result<(), Reason> {
+        debug!("Verifying message: {:?}", msg);
+
+        // whatever was streamed before belonged to another frame
+        self.stream = None;
+
+        // aes-128 needs a subblock size that's a multiple of 16
+        if msg.len < VerificationSegment::size()
+            || msg.len > data.len()
+            || !(msg.len - VerificationSegment::size()).is_multiple_of(16)
+        {
+            warn!("Length is incorrect; bad length: {}", msg.len);
+            return Err(Reason::BadLength);
+        }
+
+        let ct_hdr = VerificationSegment::from_bytes(data);
+
+        let floor = self.reserved.floor(msg.src_id, Counter::inbound(msg));
+        let admitted = self.recv.admits(msg, ct_hdr.ctr, floor);
+        if !glitch::check(|| admitted) {
+            warn!("Counter already received, or too old: {}", ct_hdr.ctr);
+            return Err(Reason::Replay); // bad counter; this is a replay
+        }
+
+        // the HMAC covers the ciphertext, so it is only verified once the frame has arrived in full
+        self.stream = Some(self.begin(msg, &ct_hdr, scratch)?);
+        trace!("Verification segment admitted; permitting the rest of the frame.");
+        Ok(())
+    }
+
+    fn verification_len(&self) -> usize {
+        VerificationSegment::size()
+    }
+
+    fn stream_block(&self) -> usize {
+        16
+    }
+
+    fn decrypt_received(&mut self, data: &mut [u8], received: usize) {
+        if let Some(stream) = self.stream.as_mut() {
+            // only whole blocks are absorbed; the remainder waits for the rest of its block
+            let whole = received.saturating_sub(stream.absorbed) / 16 * 16;
+            let end = stream.absorbed + whole;
+            // each block is absorbed by the HMACs while it is still ciphertext
+            stream.absorb(data, end);
+            if let Some(cbc) = stream.cbc.as_mut() {
+                cbc.decrypt_blocks(blocks(&mut data[stream.decrypted..end]));
+                stream.decrypted = end;
+            }
+        }
+    }
+
+    fn jitter(&mut self) -> u32 {
+        self.rng().next_u32() % JITTER
+    }
+
+    fn rekey(&mut self, peer: Id, secret: Option<&[u8; 32]>) {
+        self.agreed.rekey(peer, secret);
+        // the keys of the peer's pairs, which are no longer those agreed, are derived anew
+        let peer = u64::from(u16::from(peer));
+        let of_peer = |slot: &u64| *slot >> 16 & 0xffff == peer || *slot & 0xffff == peer;
+        if let Some(keys) = self.keys.as_mut() {
+            loop {
+                let stale = keys.sessions.keys().copied().find(of_peer);
+                let Some(slot) = stale else { break };
+                keys.sessions.swap_remove(&slot);
+            }
+        }
+    }
+
+    fn rotate(&mut self, epoch: u32, aes_key: &[u8; 16], hmac_key: &[u8; 64]) -> bool {
+        if epoch <= self.secrets.epoch {
+            warn!("Keys of epoch {} not newer than our own; refusing.", epoch);
+            return false;
+        }
+
+        // the keys are expanded before they are replaced, so that the CSPRNG carries on from its
+        // state rather than being seeded anew
+        self.keys();
+        self.secrets.aes_key = *aes_key;
+        self.secrets.hmac_key = *hmac_key;
+        self.secrets.epoch = epoch;
+        let secrets = &self.secrets;
+        if let Some(keys) = self.keys.as_mut() {
+            keys.rotate(secrets);
+        }
+        // whatever was streamed was under keys which may no longer be held
+        self.stream = None;
+        true
+    }
+
+    fn attest(&mut self, nonce: &[u8; 32], dev_id: Id) -> Option<(u32, [u8; 32])> {
+        let tag = erasure::tag(&self.secrets.hmac_key, nonce, dev_id);
+        Some((self.secrets.epoch, tag))
+    }
+
+    fn erase(&mut self) {
+        self.secrets.seed.fill(0);
+        self.secrets.aes_key.fill(0);
+        self.secrets.hmac_key.fill(0);
+        self.keys = None;
+        self.stream = None;
+        self.agreed = Agreements::default();
+    }
+
+    fn content_offset(&self) -> usize {
+        VerificationSegment::size() + ContentHeader::size()
+    }
+
+    fn encrypt(&mut self, data: &mut [u8], mut msg: Message, scratch: &Pool) -> usize {
+        debug!("Encrypting message: {:?}", msg);
+
+        // the content is already in place, after the verification segment and content header
+        let enc_hdr = ContentHeader { len: msg.len };
+
+        let ctr = self.sent.next(msg, &mut self.reserved);
+
+        // randomise IV
+        let mut iv = [0_u8; 16];
+        self.rng().fill_bytes(&mut iv);
+
+        let mut ct_hdr = VerificationSegment {
+            iv,
+            ctr,
+            epoch: self.secrets.epoch,
+            hmac: [0_u8; 32],
+        };
+
+        // data = [ct_hdr, enc(en_hdr, msg)]
+
+        trace!(
+            "Range to be encrypted: {:?}..{:?}",
+            VerificationSegment::size(),
+            VerificationSegment::size() + ContentHeader::size() + enc_hdr.len
+        );
+
+        WriteCursor::new(&mut data[VerificationSegment::size()..]).write(&enc_hdr.to_bytes());
+
+        // fill up to the bucket, should there be one, which the PKCS7 padding then completes
+        let plain = ContentHeader::size() + enc_hdr.len;
+        let filled = filled(
+            self.buckets,
+            plain,
+            data.len().saturating_sub(VerificationSegment::size()),
+        );
+        data[VerificationSegment::size()..][plain..filled].fill(0);
+
+        // encrypt
+        let aes = Aes128Cbc::new(self.session(msg).aes.clone(), &ct_hdr.iv.into());
+        let enc_len = aes
+            .encrypt(&mut data[VerificationSegment::size()..], filled)
+            .unwrap_or_else(|_| Fatal::Buffer.panic())
+            .len();
+
+        msg.len = VerificationSegment::size() + enc_len;
+
+        let mut hmac = self.session(msg).mac(msg, &ct_hdr, scratch);
+        hmac.update(&data[VerificationSegment::size()..msg.len]);
+        ct_hdr.hmac = finish(hmac);
+
+        // serialise cleartext header and encrypted header
+        WriteCursor::new(data).write(&ct_hdr.to_bytes());
+
+        trace!("Generated cleartext header: {:?}", ct_hdr);
+        trace!("Generated encrypted header: {:?}", enc_hdr);
+        trace!("Encrypted buffer; prepared for sending.");
+
+        msg.len
+    }
+
+    fn sealed_len(&self, msg: Message, room: usize) -> usize {
+        // as encrypt fills and pads the content header and content
+        let plain = ContentHeader::size() + msg.len;
+        let filled = filled(
+            self.buckets,
+            plain,
+            room.saturating_sub(VerificationSegment::size()),
+        );
+        VerificationSegment::size() + (filled / 16 + 1) * 16
+    }
+
+    fn decrypt(&mut self, data: &mut [u8], msg: Message, scratch: &Pool) -> Result<usize, Reason> {
+        debug!("Decrypting message: {:?}", msg);
+
+        if msg.len < VerificationSegment::size() || msg.len > data.len() {
+            return Err(Reason::BadLength);
+        }
+        let ct_hdr = VerificationSegment::from_bytes(data);
+
+        trace!("Found cleartext header: {:?}", ct_hdr);
+
+        // the counter was checked by verify, which must always precede decryption; it is checked
+        // again, so that a single fault injected into verify cannot let a replay through
+        let floor = self.reserved.floor(msg.src_id, Counter::inbound(msg));
+        let admitted = self.recv.admits(msg, ct_hdr.ctr, floor);
+        if !glitch::check(|| admitted) {
+            return Err(Reason::Replay);
+        }
+
+        // absorb whatever was not streamed as the frame was received, and verify the HMAC before
+        // anything more is decrypted
+        let mut stream = match self.stream.take() {
+            Some(stream) => stream,
+            None => self.begin(msg, &ct_hdr, scratch)?,
+        };
+        if msg.len < stream.absorbed || !(msg.len - stream.absorbed).is_multiple_of(16) {
+            warn!("Length is incorrect; bad length: {}", msg.len);
+            return Err(Reason::BadLength);
+        }
+        stream.absorb(data, msg.len);
+
+        let tag = finish(stream.keys.hmac.clone());
+        let verified = if glitch::check(|| ct::eq(&tag, &ct_hdr.hmac)) {
+            Some(stream.keys)
+        } else if let Some(fallback) = stream.fallback {
+            trace!("Trying the master secret, as the peer may not have agreed a secret yet.");
+            let tag = finish(fallback.hmac.clone());
+            glitch::check(|| ct::eq(&tag, &ct_hdr.hmac)).then_some(fallback)
+        } else {
+            None
+        };
+        let Some(keys) = verified else {
+            warn!("HMAC not verified; ignoring.");
+            return Err(Reason::BadMac);
+        };
+        if keys.agreed {
+            self.agreed.confirm(msg);
+        }
+        self.recv.record(msg, ct_hdr.ctr, floor);
+        self.reserved
+            .reserve(msg.src_id, Counter::inbound(msg), ct_hdr.ctr);
+
+        trace!(
+            "HMAC verified; range to be decrypted: {:?}",
+            stream.decrypted..msg.len
+        );
+
+        // decrypt whatever was not streamed as the frame was received
+        let mut cbc = stream
+            .cbc
+            .unwrap_or_else(|| Aes128Cbc::new(keys.aes, &ct_hdr.iv.into()));
+        cbc.decrypt_blocks(blocks(&mut data[stream.decrypted..msg.len]));
+
+        // the padding and length are both checked before either is acted on, so that the time
+        // taken does not reveal which failed
+        let padded = ct::pkcs7(&data[VerificationSegment::size()..msg.len]);
+
+        let enc_hdr = ContentHeader::from_bytes(&data[VerificationSegment::size()..]);
+
+        trace!("Found encrypted header: {:?}", enc_hdr);
+
+        let max = msg
+            .len
+            .saturating_sub(VerificationSegment::size() + ContentHeader::size());
+        let in_range = enc_hdr.len <= max;
+        if !glitch::check(|| padded & in_range) {
+            warn!("Padding or length check failed; discarding.");
+            return Err(Reason::BadPadding);
+        }
+
+        // the content is left in place, after the verification segment and content header
+        #[allow(unused_variables)] // only logged when a logging transport is enabled
+        let content = &data[(VerificationSegment::size() + ContentHeader::size())..][..enc_hdr.len];
+
+        trace!(
+            "Successfully decrypted content: {:?}",
+            crate::redact::Payload(content)
+        );
+
+        Ok(enc_hdr.len)
+    }
+}
@@ -0,0 +1,58 @@
+//! The attestation with which a deregistering SED confirms to the SSS that it is erasing its keys
+//!
+//! Once the SSS answers a deregistration, the SED attests, as a [`SecureSSSErasure`], that it held
+//! the deployment's keys of its registration and is erasing them. The attestation is tagged under
+//! those very keys, which the SSS distributed and so also holds, over the nonce with which the SSS
+//! challenged the deregistration:
+//!
+//! ```text
+//! key = HKDF-Expand(HKDF-Extract(dev_id, deployment key), "SCEWL erased")
+//! tag = HMAC(key, nonce || dev_id)
+//! ```
+//!
+//! The deployment key is the HMAC key for the CBC handlers and the AES key for the AEAD handlers,
+//! which hold no HMAC key, as of the epoch given with the tag. Only once the SSS has checked the
+//! attestation does it deregister the SED, acknowledging it with [`SSSOp::Erased`] tagged as its
+//! [responses](super::challenge) are, and only once the SED has that acknowledgement does it
+//! erase its keys. Neither side then deregisters without the other: an attestation or
+//! acknowledgement which is lost leaves the SED registered on both sides, or, should only the
+//! acknowledgement be lost, leaves the SED to find out from its next
+//! [keepalive](super::keepalive).
+//!
+//! As the nonce is fresh for each deregistration, an attestation cannot be replayed. As with
+//! [revocation](super::revocation), only HKDF and HMAC-SHA256 are used, so that the SSS may check
+//! attestations with nothing but the Python standard library.
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+use crate::codec::secure::SecureSSSErasure;
+use crate::codec::Id;
+use crate::fatal::Fatal;
+use crate::{ct, glitch};
+
+/// The info label from which the tag key is expanded
+const ERASURE_LABEL: &[u8] = b"SCEWL erased";
+
+/// Computes the tag with which the SED of the given id attests to holding the given deployment
+/// key, over the nonce of the challenge of its deregistration
+pub fn tag(key: &[u8], nonce: &[u8; 32], dev_id: Id) -> [u8; 32] {
+    let mut expanded = [0_u8; 32];
+    Hkdf::<Sha256>::new(Some(&u16::from(dev_id).to_le_bytes()), key)
+        .expand(ERASURE_LABEL, &mut expanded)
+        .unwrap_or_else(|_| Fatal::SessionKey.panic());
+
+    let mut hmac = Hmac::<Sha256>::new_varkey(&expanded).unwrap_or_else(|_| Fatal::HmacKey.panic());
+    expanded.fill(0);
+    hmac.update(nonce);
+    hmac.update(&u16::from(dev_id).to_le_bytes());
+    hmac.finalize().into_bytes().into()
+}
+
+/// Whether the attestation is of the given deployment key, over the nonce of the challenge of the
+/// deregistration, as the SSS checks it
+pub fn verify(key: &[u8], nonce: &[u8; 32], erasure: &SecureSSSErasure) -> bool {
+    let expected = tag(key, nonce, erasure.dev_id);
+    glitch::check(|| ct::eq(&expected, &erasure.tag))
+}
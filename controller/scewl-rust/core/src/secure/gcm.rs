@@ -0,0 +1,422 @@
+//! An alternative crypto handler for the secure implementation, which protects messages with
+//! AES-128-GCM rather than with AES-128-CBC and HMAC-SHA256
+//!
+//! # Design
+//!
+//! The handler is selected on registration should the SSS enable
+//! [`CAP_AES_GCM`](crate::codec::secure::CAP_AES_GCM) for the deployment, and constructs messages
+//! to other SCEWL devices in the following layout:
+//!
+//! ```text
+//! TRANSPORT
+//!  | b'SC'    ; header magic
+//!  | tgt_id   ; Target device's ID
+//!  | src_id   ; Source device's ID
+//!  | len      ; length of the remaining sections
+//! VERIFICATION
+//!  | nonce    ; random nonce for the content segment
+//!  | ctr      ; message counter
+//!  | epoch    ; epoch of the keys
+//!  | tag      ; GCM tag over (TRANSPORT || nonce || ctr || epoch) and the content
+//! CONTENT (encrypted)
+//!  | msg      ; content intended to be sent by the CPU
+//! ```
+//!
+//! The transport segment is as for the [CBC handler](super::CryptoHandler), as is the counter,
+//! which is checked by [`verify`](crate::crypto::Handler::verify) against the sender's replay
+//! window, which admits each counter once. The transport segment, nonce, counter, and epoch are
+//! the additional data of the AEAD, so that the tag authenticates them alongside the content; it
+//! thereby replaces both the HMAC and the hash of the content, and a frame is encrypted (and
+//! decrypted) in one pass over its content rather than two. As GCM is a stream mode, the content
+//! is neither padded nor prefixed with its length, which is that of the frame less the
+//! verification segment.
+//!
+//! Unlike the HMAC, the tag covers the content, so it is only checked once the frame has arrived
+//! in full, by [`decrypt`](crate::crypto::Handler::decrypt); a frame which fails it is dropped as
+//! a bad MAC. For the same reason, decryption is not [streamed](crate::crypto::Handler::stream_block)
+//! as the frame is received: no plaintext may be released before the tag is checked.
+//!
+//! Should the SSS enable [`CAP_EPHEMERAL_KEYS`](crate::codec::secure::CAP_EPHEMERAL_KEYS), the
+//! direct messages exchanged with a peer with which a secret was agreed in a
+//! [handshake](super::Handshakes) are protected under a key of their own, extracted from that
+//! secret with the global key as salt:
+//!
+//! ```text
+//! key' = HKDF-Expand(HKDF-Extract(aes_key, secret), "SCEWL agreed")
+//! ```
+//!
+//! A frame from that peer whose tag fails under that key is tried under the global key, as the
+//! peer may have sent it before it learnt of the secret, until the first frame under that key has
+//! been received from the peer; as the AEAD checks the tag before it decrypts, a failed attempt
+//! leaves the frame intact for the next.
+//!
+//! Should the SSS push the keys of a new [epoch](super::rotation), the handler keeps the key of
+//! the epoch before alongside, as does the [CBC handler](super::CryptoHandler#key-rotation): a
+//! frame is opened under the key of the epoch which it bears, should that be either, and is
+//! otherwise dropped as a bad MAC.
+//!
+//! Nonces are drawn from the CSPRNG seeded by the SSS, which is unique per SED, so that no two
+//! messages under the global key share a nonce in any practical deployment. Failures are
+//! [jittered](crate::crypto::Handler::jitter) as by the CBC handler.
+//!
+//! The handler is [generic](AeadHandler) over its [AEAD](Aead), so that the [GCM-SIV
+//! handler](super::SivHandler) shares it in all but the AEAD.
+
+use core::mem::replace;
+
+use aes::{Aes128, NewBlockCipher};
+use aes_gcm::aead::consts::{U12, U16};
+use aes_gcm::aead::AeadInPlace;
+use aes_gcm::{Aes128Gcm, Tag};
+use hkdf::Hkdf;
+use rand_core::RngCore;
+use rand_hc::Hc128Rng;
+use sha2::Sha256;
+
+use crate::codec::secure::GcmSegment;
+use crate::codec::{Id, Message, MessageHeader};
+use crate::crypto::Handler as CryptoHandler;
+use crate::cursor::WriteCursor;
+use crate::diag::Reason;
+use crate::fatal::Fatal;
+use crate::glitch;
+use crate::scratch::Pool;
+use crate::secure::checkpoint::{Checkpoints, Counter, Reservations};
+use crate::secure::crypto::{Sent, Windows, JITTER};
+use crate::secure::erasure;
+use crate::secure::handshake::Agreements;
+use crate::secure::reseed::{Entropy, Reseeding};
+use crate::{debug, trace, warn};
+
+/// The length of the additional data authenticated by the tag, i.e. TRANSPORT || nonce || ctr ||
+/// epoch
+const AAD_LEN: usize = MessageHeader::size() + 12 + 8 + 4;
+
+/// The info label from which the key of the secret agreed with a peer is expanded
+const AGREED_LABEL: &[u8] = b"SCEWL agreed";
+
+/// An AEAD with which the handler may protect frames, i.e. AES-128-GCM or AES-128-GCM-SIV, which
+/// is keyed by an AES-128 cipher and takes 12-byte nonces and 16-byte tags
+pub trait Aead: AeadInPlace<NonceSize = U12, TagSize = U16> + From<Aes128> {}
+
+impl<A: AeadInPlace<NonceSize = U12, TagSize = U16> + From<Aes128>> Aead for A {}
+
+/// The secrets as expanded for use
+struct Keys<A> {
+    /// A CSPRNG which is used to generate random nonces
+    rng: Hc128Rng,
+    /// The AEAD, whose key schedule is expanded once
+    gcm: A,
+    /// The keys of the epoch held before the last rotation, if any
+    previous: Option<Previous<A>>,
+}
+
+impl<A: Aead> Keys<A> {
+    /// Seeds the CSPRNG, mixing in fresh entropy should there be a source, and expands the AES key
+    /// schedule
+    fn expand(seed: &[u8; 32], aes_key: [u8; 16], reseed: &Reseeding) -> Self {
+        Self {
+            rng: reseed.seed(seed),
+            gcm: Aes128::new(&aes_key.into()).into(),
+            previous: None,
+        }
+    }
+}
+
+/// The keys of the epoch held before the last rotation
+struct Previous<A> {
+    /// The epoch of the keys
+    epoch: u32,
+    /// The AES key, from which the keys of agreed secrets are extracted
+    aes_key: [u8; 16],
+    /// The AEAD
+    gcm: A,
+}
+
+/// The GCM crypto handler, which performs encryption, decryption, and verification of messages
+pub type Handler = AeadHandler<Aes128Gcm>;
+
+/// The crypto handler of either AEAD, as described in the [module documentation](self)
+pub struct AeadHandler<A: Aead> {
+    /// The seed of the CSPRNG, as received on registration
+    seed: [u8; 32],
+    /// The AES key, as received on registration or last pushed by the SSS
+    aes_key: [u8; 16],
+    /// The epoch of the AES key
+    epoch: u32,
+    /// The keys, once expanded on first use
+    keys: Option<Keys<A>>,
+    /// The counters of the outbound direct messages and broadcasts
+    sent: Sent,
+    /// The replay windows of the inbound direct messages and broadcasts
+    recv: Windows,
+    /// The secrets agreed with peers in handshakes
+    agreed: Agreements,
+    /// The reservations of the counters, should they be persisted
+    reserved: Reservations,
+    /// The reseeding of the CSPRNG, should there be an entropy source
+    reseed: Reseeding,
+}
+
+impl<A: Aead> AeadHandler<A> {
+    /// Instantiates a new instance of the crypto handler with the given CSPRNG seed and AES key
+    ///
+    /// As for the CBC handler, the keys are only expanded on the first message sent or received.
+    pub fn new(seed: [u8; 32], aes_key: [u8; 16]) -> Self {
+        Self {
+            seed,
+            aes_key,
+            epoch: 0,
+            keys: None,
+            sent: Sent::default(),
+            recv: Windows::default(),
+            agreed: Agreements::default(),
+            reserved: Reservations::new(None),
+            reseed: Reseeding::new(None),
+        }
+    }
+
+    /// Persists reservations of the counters to the store, as for the [CBC
+    /// handler](super::CryptoHandler::with_checkpoints)
+    #[must_use]
+    pub fn with_checkpoints(mut self, store: &'static dyn Checkpoints) -> Self {
+        self.reserved = Reservations::new(Some(store));
+        self
+    }
+
+    /// Marks the AES key as that of the given epoch, as for the [CBC
+    /// handler](super::CryptoHandler::with_epoch)
+    #[must_use]
+    pub fn with_epoch(mut self, epoch: u32) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    /// Mixes fresh entropy from the source into the CSPRNG, as for the [CBC
+    /// handler](super::CryptoHandler::with_entropy)
+    #[must_use]
+    pub fn with_entropy(mut self, source: &'static dyn Entropy) -> Self {
+        self.reseed = Reseeding::new(Some(source));
+        self
+    }
+
+    /// The expanded keys, which are expanded should this be their first use
+    fn keys(&mut self) -> &mut Keys<A> {
+        let (seed, aes_key, reseed) = (&self.seed, self.aes_key, &self.reseed);
+        self.keys
+            .get_or_insert_with(|| Keys::expand(seed, aes_key, reseed))
+    }
+
+    /// The CSPRNG, for a draw which is counted towards its reseeding
+    fn rng(&mut self) -> &mut Hc128Rng {
+        let (seed, aes_key, reseed) = (&self.seed, self.aes_key, &mut self.reseed);
+        let keys = self
+            .keys
+            .get_or_insert_with(|| Keys::expand(seed, aes_key, reseed));
+        reseed.draw(&mut keys.rng)
+    }
+
+    /// The AES key and AEAD of the given epoch, should they be held; the keys must have been
+    /// expanded
+    fn epoch(&self, epoch: u32) -> Option<(&[u8; 16], &A)> {
+        let keys = self.keys.as_ref()?;
+        if epoch == self.epoch {
+            return Some((&self.aes_key, &keys.gcm));
+        }
+        match &keys.previous {
+            Some(previous) if previous.epoch == epoch => Some((&previous.aes_key, &previous.gcm)),
+            _ => None,
+        }
+    }
+
+    /// The AEAD of the secret agreed with the peer of the message, extracted with the given AES key
+    /// as salt, should there be one
+    fn agreed(&self, msg: Message, aes_key: &[u8; 16]) -> Option<A> {
+        let agreed = self.agreed.of(msg)?;
+        let mut key = [0_u8; 16];
+        Hkdf::<Sha256>::new(Some(aes_key), &agreed.secret)
+            .expand(AGREED_LABEL, &mut key)
+            .unwrap_or_else(|_| Fatal::SessionKey.panic());
+        let gcm = Aes128::new(&key.into()).into();
+        key.fill(0);
+        Some(gcm)
+    }
+}
+
+/// Assembles the additional data authenticated by the tag in a scratch buffer, and passes it to
+/// the function
+fn with_aad<T>(msg: Message, seg: &GcmSegment, scratch: &Pool, f: impl FnOnce(&[u8]) -> T) -> T {
+    let mut aad = scratch.take().unwrap_or_else(|| Fatal::Scratch.panic());
+    WriteCursor::new(&mut aad[..AAD_LEN])
+        .write(&msg.to_canonical().to_bytes())
+        .write(&seg.nonce)
+        .write_u64(seg.ctr)
+        .write_u32(seg.epoch);
+    f(&aad[..AAD_LEN])
+}
+
+impl<A: Aead> CryptoHandler for AeadHandler<A> {
+    fn verify(&mut self, data: &[u8], msg: Message, _: &Pool) -> Result<(), Reason> {
+        debug!("Verifying message: {:?}", msg);
+
+        if msg.len < GcmSegment::size() || msg.len > data.len() {
+            warn!("Length is incorrect; bad length: {}", msg.len);
+            return Err(Reason::BadLength);
+        }
+
+        let seg = GcmSegment::from_bytes(data);
+        let floor = self.reserved.floor(msg.src_id, Counter::inbound(msg));
+        let admitted = self.recv.admits(msg, seg.ctr, floor);
+        if glitch::check(|| admitted) {
+            Ok(())
+        } else {
+            warn!("Counter already received, or too old: {}", seg.ctr);
+            Err(Reason::Replay)
+        }
+    }
+
+    fn verification_len(&self) -> usize {
+        GcmSegment::size()
+    }
+
+    fn content_offset(&self) -> usize {
+        GcmSegment::size()
+    }
+
+    fn encrypt(&mut self, data: &mut [u8], mut msg: Message, scratch: &Pool) -> usize {
+        debug!("Encrypting message: {:?}", msg);
+
+        let ctr = self.sent.next(msg, &mut self.reserved);
+
+        let mut seg = GcmSegment {
+            nonce: [0; 12],
+            ctr,
+            epoch: self.epoch,
+            tag: [0; 16],
+        };
+        let agreed = self.agreed(msg, &self.aes_key);
+        self.rng().fill_bytes(&mut seg.nonce);
+        let gcm = agreed.as_ref().unwrap_or(&self.keys().gcm);
+
+        // the content is already in place, after the verification segment
+        let content = GcmSegment::size()..GcmSegment::size() + msg.len;
+        msg.len += GcmSegment::size();
+        let tag = with_aad(msg, &seg, scratch, |aad| {
+            gcm.encrypt_in_place_detached(&seg.nonce.into(), aad, &mut data[content])
+                .unwrap_or_else(|_| Fatal::Buffer.panic())
+        });
+        seg.tag.copy_from_slice(&tag);
+
+        WriteCursor::new(data).write(&seg.to_bytes());
+
+        trace!("Generated verification segment: {:?}", seg);
+        trace!("Encrypted buffer; prepared for sending.");
+
+        msg.len
+    }
+
+    fn decrypt(&mut self, data: &mut [u8], msg: Message, scratch: &Pool) -> Result<usize, Reason> {
+        debug!("Decrypting message: {:?}", msg);
+
+        if msg.len < GcmSegment::size() || msg.len > data.len() {
+            return Err(Reason::BadLength);
+        }
+        let seg = GcmSegment::from_bytes(data);
+
+        // the counter was checked by verify, which must always precede decryption; it is checked
+        // again, so that a single fault injected into verify cannot let a replay through
+        let floor = self.reserved.floor(msg.src_id, Counter::inbound(msg));
+        let admitted = self.recv.admits(msg, seg.ctr, floor);
+        if !glitch::check(|| admitted) {
+            return Err(Reason::Replay);
+        }
+
+        // the tag is compared in constant time by the AEAD, and the counter only recorded once it
+        // is authentic, so that a forged frame cannot advance it
+        self.keys();
+        let Some((aes_key, global)) = self.epoch(seg.epoch) else {
+            warn!("Keys of epoch {} not held; dropping.", seg.epoch);
+            return Err(Reason::NoKey);
+        };
+        let agreed = self.agreed(msg, aes_key);
+        let fallback = self.agreed.of(msg).is_some_and(|agreed| !agreed.confirmed);
+        let content = &mut data[GcmSegment::size()..msg.len];
+        let opened = with_aad(msg, &seg, scratch, |aad| {
+            let mut open = |gcm: &A| {
+                gcm.decrypt_in_place_detached(
+                    &seg.nonce.into(),
+                    aad,
+                    content,
+                    Tag::from_slice(&seg.tag),
+                )
+                .is_ok()
+            };
+            match &agreed {
+                Some(gcm) if open(gcm) => Some(true),
+                Some(_) if fallback && open(global) => Some(false),
+                Some(_) => None,
+                None => open(global).then_some(false),
+            }
+        });
+        if !glitch::check(|| opened.is_some()) {
+            warn!("GCM tag not verified; dropping.");
+            return Err(Reason::BadMac);
+        }
+        if opened == Some(true) {
+            self.agreed.confirm(msg);
+        }
+
+        self.recv.record(msg, seg.ctr, floor);
+        self.reserved
+            .reserve(msg.src_id, Counter::inbound(msg), seg.ctr);
+
+        let len = msg.len - GcmSegment::size();
+        trace!(
+            "Successfully decrypted content: {:?}",
+            crate::redact::Payload(&data[GcmSegment::size()..][..len])
+        );
+
+        Ok(len)
+    }
+
+    fn jitter(&mut self) -> u32 {
+        self.rng().next_u32() % JITTER
+    }
+
+    fn rekey(&mut self, peer: Id, secret: Option<&[u8; 32]>) {
+        self.agreed.rekey(peer, secret);
+    }
+
+    fn rotate(&mut self, epoch: u32, aes_key: &[u8; 16], _: &[u8; 64]) -> bool {
+        if epoch <= self.epoch {
+            warn!("Keys of epoch {} not newer than our own; refusing.", epoch);
+            return false;
+        }
+
+        // the keys are expanded before they are replaced, so that the CSPRNG carries on from its
+        // state rather than being seeded anew
+        self.keys();
+        let previous_key = replace(&mut self.aes_key, *aes_key);
+        let previous_epoch = replace(&mut self.epoch, epoch);
+        if let Some(keys) = self.keys.as_mut() {
+            let gcm = replace(&mut keys.gcm, Aes128::new(&(*aes_key).into()).into());
+            keys.previous = Some(Previous {
+                epoch: previous_epoch,
+                aes_key: previous_key,
+                gcm,
+            });
+        }
+        true
+    }
+
+    fn attest(&mut self, nonce: &[u8; 32], dev_id: Id) -> Option<(u32, [u8; 32])> {
+        Some((self.epoch, erasure::tag(&self.aes_key, nonce, dev_id)))
+    }
+
+    fn erase(&mut self) {
+        self.seed.fill(0);
+        self.aes_key.fill(0);
+        self.keys = None;
+        self.agreed = Agreements::default();
+    }
+}
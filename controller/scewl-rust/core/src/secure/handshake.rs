@@ -0,0 +1,340 @@
+//! An ephemeral X25519 key agreement between each pair of SEDs which exchange direct messages, so
+//! that the deployment's keys, should they ever be captured, do not decrypt the traffic recorded
+//! before
+//!
+//! # Design
+//!
+//! Should the SSS enable [`CAP_EPHEMERAL_KEYS`](crate::codec::secure::CAP_EPHEMERAL_KEYS), the
+//! controller [offers](Handshakes::offer) a handshake to a peer the first time its CPU sends that
+//! peer a direct message: a fresh X25519 public key, in a [`Handshake`] which is sent as any other
+//! direct message, i.e. protected by the crypto handler under the deployment's keys, but consumed
+//! by the peer's controller rather than forwarded to its CPU. The peer draws a key pair of its own
+//! and answers with its public key, upon which both hold the same secret, with which the crypto
+//! handlers [key](crate::crypto::Handler::rekey) the direct messages between the two from then on.
+//! The ephemeral secret keys are wiped once the secret is agreed, and the secret is only held by
+//! the crypto handler, until the controller deregisters or the peer says hello again (i.e. has
+//! registered anew, and so forgotten it).
+//!
+//! The handshake with each peer goes through the following states:
+//!
+//! ```text
+//!          offer sent              accept received
+//!  (none) ------------> Offered ---------------------> Agreed
+//!     |                                                  ^
+//!     +-------------- offer received, accept sent -------+
+//! ```
+//!
+//! Should both peers offer at once, the offer of the lower id is accepted and the other ignored.
+//!
+//! Handshakes are authenticated by the deployment's keys, so only SEDs of the deployment agree a
+//! secret, but the secret cannot be derived from those keys. The message which prompts the offer,
+//! and any others sent before the answer arrives, remain under the deployment's keys; so that
+//! none is dropped, a handler accepts frames from a peer under the deployment's keys until it
+//! first receives one under the agreed secret. Broadcasts are always under the deployment's keys.
+//!
+//! Ephemeral keys are drawn from a CSPRNG of their own, seeded from the seed distributed at
+//! registration, which is fresh at each registration rather than provisioned with the deployment.
+//!
+//! The body of a handshake is laid out as `kind: u8 | public: [u8; 32]`. Handshakes are sent as
+//! content of their own [kind](crate::content::Kind::Handshake), which the crypto handler
+//! authenticates, and only such content is taken for a handshake; data from a CPU never is,
+//! whatever it begins with, so that a CPU can never have its peer rekey the pair.
+
+use core::mem::size_of;
+
+use ed25519_compact::x25519::{DHOutput, PublicKey, SecretKey};
+use heapless::FnvIndexMap;
+use hkdf::Hkdf;
+use rand_core::{RngCore, SeedableRng};
+use rand_hc::Hc128Rng;
+use sha2::Sha256;
+
+use crate::codec::{Id, Message};
+use crate::cursor::WriteCursor;
+use crate::deployment::PeerCapacity;
+use crate::fatal::Fatal;
+use crate::secure::reseed::{Entropy, Reseeding};
+
+/// The info label from which the seed of the handshakes' CSPRNG is expanded
+const SEED_LABEL: &[u8] = b"SCEWL handshake";
+
+/// The step of a handshake which a message is
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Kind {
+    /// The initiator's public key
+    Offer = 0,
+    /// The responder's public key
+    Accept = 1,
+}
+
+/// A message of a handshake, which carries the public key of its sender
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Handshake {
+    /// The step of the handshake
+    pub kind: Kind,
+    /// The sender's ephemeral X25519 public key
+    pub public: [u8; 32],
+}
+
+impl Handshake {
+    /// Serialises this handshake to the buffer, returning its length
+    pub fn to_bytes(self, buf: &mut [u8]) -> usize {
+        WriteCursor::new(buf)
+            .write(&[self.kind as u8])
+            .write(&self.public);
+        Handshake::size()
+    }
+
+    /// Deserialises a handshake from the body of content of the handshake kind, should it be one
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if buf.len() != Handshake::size() {
+            return None;
+        }
+
+        let kind = match buf[0] {
+            0 => Kind::Offer,
+            1 => Kind::Accept,
+            _ => return None,
+        };
+        let mut public = [0_u8; 32];
+        public.copy_from_slice(&buf[1..]);
+
+        Some(Handshake { kind, public })
+    }
+
+    /// The constant size of a handshake in its serialised form
+    pub const fn size() -> usize {
+        size_of::<u8>() + size_of::<[u8; 32]>()
+    }
+}
+
+/// The state of the handshake with a peer
+enum State {
+    /// An offer was sent, with the public key of this secret key
+    Offered(SecretKey),
+    /// A secret was agreed, and handed to the crypto handler
+    Agreed,
+}
+
+/// The outcome of a handshake received from a peer
+pub struct Received {
+    /// The handshake with which to answer the peer, if any
+    pub reply: Option<Handshake>,
+    /// The secret agreed with the peer, should the handshake complete it
+    pub secret: Option<DHOutput>,
+}
+
+impl Received {
+    /// The outcome of a handshake which is ignored
+    const IGNORED: Received = Received {
+        reply: None,
+        secret: None,
+    };
+}
+
+/// The handshakes of a controller with each of its peers, as described in the [module
+/// documentation](self)
+pub struct Handshakes {
+    /// The id of the controller, which breaks ties between simultaneous offers
+    id: Id,
+    /// The CSPRNG from which ephemeral keys are drawn
+    rng: Hc128Rng,
+    /// The reseeding of the CSPRNG, should there be an entropy source
+    reseed: Reseeding,
+    /// The state of the handshake with each peer which has begun one
+    peers: FnvIndexMap<Id, State, PeerCapacity>,
+}
+
+impl Handshakes {
+    /// Instantiates the handshakes of the controller of the given id, seeding their CSPRNG from
+    /// the seed distributed at registration, with no peers
+    pub fn new(id: Id, seed: &[u8; 32]) -> Self {
+        let mut rng_seed = [0_u8; 32];
+        Hkdf::<Sha256>::new(None, seed)
+            .expand(SEED_LABEL, &mut rng_seed)
+            .unwrap_or_else(|_| Fatal::SessionKey.panic());
+        let rng = Hc128Rng::from_seed(rng_seed);
+        rng_seed.fill(0);
+
+        Self {
+            id,
+            rng,
+            reseed: Reseeding::new(None),
+            peers: FnvIndexMap::new(),
+        }
+    }
+
+    /// Mixes fresh entropy from the source into the CSPRNG, at once and periodically thereafter,
+    /// so that the ephemeral keys drawn after a reset are not those drawn before it (see
+    /// [reseeding](super::reseed))
+    #[must_use]
+    pub fn with_entropy(mut self, source: &'static dyn Entropy) -> Self {
+        self.reseed = Reseeding::new(Some(source));
+        let mut prior = [0_u8; 32];
+        self.rng.fill_bytes(&mut prior);
+        self.rng = self.reseed.seed(&prior);
+        prior.fill(0);
+        self
+    }
+
+    /// Draws an ephemeral key pair, returning the secret key and the public key
+    fn draw(&mut self) -> (SecretKey, [u8; 32]) {
+        let mut bytes = [0_u8; 32];
+        self.reseed.draw(&mut self.rng).fill_bytes(&mut bytes);
+        let secret = SecretKey::new(bytes);
+        bytes.fill(0);
+
+        let public = secret
+            .recover_public_key()
+            .unwrap_or_else(|_| Fatal::SessionKey.panic());
+        (secret, *public)
+    }
+
+    /// Records the state of the handshake with the peer
+    fn set(&mut self, peer: Id, state: State) {
+        // a full table refuses to insert even a key it holds, so the state is updated in place
+        match self.peers.get_mut(&peer) {
+            Some(held) => *held = state,
+            None => self
+                .peers
+                .insert(peer, state)
+                .map_or_else(|_| Fatal::PeerTable.panic(), drop),
+        }
+    }
+
+    /// The offer with which to begin a handshake with the peer, should none have begun
+    pub fn offer(&mut self, peer: Id) -> Option<Handshake> {
+        if self.peers.contains_key(&peer) {
+            return None;
+        }
+
+        let (secret, public) = self.draw();
+        self.set(peer, State::Offered(secret));
+        Some(Handshake {
+            kind: Kind::Offer,
+            public,
+        })
+    }
+
+    /// Advances the handshake with the peer by the handshake received from it
+    ///
+    /// An offer is accepted, unless this controller's own offer to the peer takes precedence, and
+    /// an accept completes this controller's offer; an accept which answers no offer is ignored,
+    /// as is a public key of low order, with which no secret would be agreed.
+    pub fn receive(&mut self, peer: Id, handshake: Handshake) -> Received {
+        let public = PublicKey::new(handshake.public);
+        match (handshake.kind, self.peers.get(&peer)) {
+            (Kind::Offer, Some(State::Offered(_))) if u16::from(self.id) < u16::from(peer) => {
+                Received::IGNORED
+            }
+            (Kind::Offer, _) => {
+                let (secret, reply) = self.draw();
+                let Ok(agreed) = public.dh(&secret) else {
+                    self.peers.remove(&peer);
+                    return Received::IGNORED;
+                };
+                self.set(peer, State::Agreed);
+
+                Received {
+                    reply: Some(Handshake {
+                        kind: Kind::Accept,
+                        public: reply,
+                    }),
+                    secret: Some(agreed),
+                }
+            }
+            (Kind::Accept, Some(State::Offered(secret))) => {
+                let agreed = public.dh(secret).ok();
+                if agreed.is_some() {
+                    self.set(peer, State::Agreed);
+                } else {
+                    self.peers.remove(&peer);
+                }
+
+                Received {
+                    reply: None,
+                    secret: agreed,
+                }
+            }
+            (Kind::Accept, _) => Received::IGNORED,
+        }
+    }
+
+    /// Forgets the handshake with the peer, e.g. once it has registered anew
+    pub fn forget(&mut self, peer: Id) {
+        self.peers.remove(&peer);
+    }
+}
+
+/// A secret agreed with a peer, as held by a crypto handler
+pub(super) struct Agreed {
+    /// The raw output of the key agreement
+    pub(super) secret: [u8; 32],
+    /// Whether a frame keyed by the secret has been received from the peer, after which frames
+    /// under the deployment's keys are no longer accepted from it
+    pub(super) confirmed: bool,
+}
+
+impl Drop for Agreed {
+    fn drop(&mut self) {
+        self.secret.fill(0);
+    }
+}
+
+/// The secrets agreed with each peer, as held by a crypto handler
+#[derive(Default)]
+pub(super) struct Agreements(FnvIndexMap<Id, Agreed, PeerCapacity>);
+
+impl Agreements {
+    /// Records the secret agreed with the peer, or forgets the last should it be `None`, as by
+    /// [`rekey`](crate::crypto::Handler::rekey)
+    pub(super) fn rekey(&mut self, peer: Id, secret: Option<&[u8; 32]>) {
+        match secret {
+            Some(&secret) => {
+                let agreed = Agreed {
+                    secret,
+                    confirmed: false,
+                };
+                // as for the handshakes, the secret of a peer already held is replaced in place
+                match self.0.get_mut(&peer) {
+                    Some(held) => *held = agreed,
+                    None => self
+                        .0
+                        .insert(peer, agreed)
+                        .map_or_else(|_| Fatal::PeerTable.panic(), drop),
+                }
+            }
+            None => {
+                self.0.remove(&peer);
+            }
+        }
+    }
+
+    /// The peer of the message, should it be a direct message between this controller and a peer
+    /// with which a secret was agreed
+    fn peer(&self, msg: Message) -> Option<Id> {
+        if msg.tgt_id == Id::Broadcast {
+            None
+        } else if self.0.contains_key(&msg.src_id) {
+            Some(msg.src_id)
+        } else if self.0.contains_key(&msg.tgt_id) {
+            Some(msg.tgt_id)
+        } else {
+            None
+        }
+    }
+
+    /// The secret which keys the message, should it be a direct message between this controller
+    /// and a peer with which a secret was agreed
+    pub(super) fn of(&self, msg: Message) -> Option<&Agreed> {
+        self.peer(msg).and_then(|peer| self.0.get(&peer))
+    }
+
+    /// Marks the secret which keys the message as confirmed, the message having been received
+    /// under it
+    pub(super) fn confirm(&mut self, msg: Message) {
+        if let Some(agreed) = self.peer(msg).and_then(|peer| self.0.get_mut(&peer)) {
+            agreed.confirmed = true;
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! The authentication of the SSS's answer to the keepalive which a registered SED sends it
+//! periodically
+//!
+//! While registered, the controller sends the SSS a [`SecureSSSKeepalive`] bearing a fresh nonce
+//! every so often, from which the SSS learns that the SED is still alive. The SSS answers with
+//! whether it still holds the SED registered, as a [`SecureSSSAlive`]: it may not, should it have
+//! restarted or have lost the SED's connection. The answer is tagged under a key derived from the
+//! registration secret of the SED, which only that SED and the SSS hold:
+//!
+//! ```text
+//! key = HKDF-Expand(HKDF-Extract(dev_id, secret), "SCEWL keepalive")
+//! tag = HMAC(key, nonce || dev_id || op)
+//! ```
+//!
+//! As an answer that the SED is no longer registered makes it forget its keys, nobody but the SSS
+//! may give one, and, as the tag covers the nonce, nor may an earlier answer be replayed. The
+//! keepalive itself is not authenticated, as it asks nothing of the SSS but to be noted: a forged
+//! keepalive at worst makes an SED look alive which is not. As with
+//! [revocation](super::revocation), only HKDF and HMAC-SHA256 are used, so that the SSS may tag
+//! answers with nothing but the Python standard library.
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+use crate::codec::secure::{SecureSSSAlive, SecureSSSKeepalive};
+use crate::codec::{Id, SSSOp};
+use crate::fatal::Fatal;
+use crate::{ct, glitch};
+
+/// The info label from which the tag key is expanded
+const KEEPALIVE_LABEL: &[u8] = b"SCEWL keepalive";
+
+/// Computes the tag of the answer of the given operation to the keepalive of the given nonce from
+/// the SED of the given registration secret
+fn tag(secret: &[u8; 64], nonce: &[u8; 16], dev_id: Id, op: SSSOp) -> [u8; 32] {
+    let mut key = [0_u8; 32];
+    Hkdf::<Sha256>::new(Some(&u16::from(dev_id).to_le_bytes()), secret)
+        .expand(KEEPALIVE_LABEL, &mut key)
+        .unwrap_or_else(|_| Fatal::SessionKey.panic());
+
+    let mut hmac = Hmac::<Sha256>::new_varkey(&key).unwrap_or_else(|_| Fatal::HmacKey.panic());
+    key.fill(0);
+    hmac.update(nonce);
+    hmac.update(&u16::from(dev_id).to_le_bytes());
+    hmac.update(&i16::from(op).to_le_bytes());
+    hmac.finalize().into_bytes().into()
+}
+
+/// Answers the keepalive from the SED of the given registration secret, as the SSS does, with
+/// whether the SSS holds it registered
+pub fn answer(
+    secret: &[u8; 64],
+    keepalive: &SecureSSSKeepalive,
+    registered: bool,
+) -> SecureSSSAlive {
+    let op = if registered {
+        SSSOp::Keepalive
+    } else {
+        SSSOp::Deregister
+    };
+    SecureSSSAlive {
+        dev_id: keepalive.dev_id,
+        op,
+        tag: tag(secret, &keepalive.nonce, keepalive.dev_id, op),
+    }
+}
+
+/// Whether the answer to this SED's keepalive of the given nonce, whose registration secret is
+/// given, is authentic
+pub fn verify(secret: &[u8; 64], nonce: &[u8; 16], alive: &SecureSSSAlive) -> bool {
+    let expected = tag(secret, nonce, alive.dev_id, alive.op);
+    glitch::check(|| ct::eq(&expected, &alive.tag))
+}
@@ -0,0 +1,155 @@
+//! The wrapping of the secrets distributed at registration, under a key derived from the SED's
+//! registration secret
+//!
+//! The SSS would otherwise send the deployment's keys and the SED's seed as they are, so anyone
+//! who reads the SSS interface learns them all. Instead, the SSS wraps them with AES-KW (RFC 3394)
+//! under a key encryption key which only the SED and the SSS can derive:
+//!
+//! ```text
+//! kek = HKDF-Expand(HKDF-Extract(dev_id, secret), "SCEWL wrap")[..16]
+//! integrity || wrapped = AES-KW(kek, aes_key || seed || hmac_key)
+//! ```
+//!
+//! The wrapped secrets take the place of the secrets in the [`SecureSSSSecrets`], and the 64-bit
+//! integrity check value follows the capabilities, so the rest of the response keeps its layout.
+//! The SED [unwraps](unwrap) them before it builds its crypto handler, and refuses the response
+//! should the integrity check fail. The epoch and the capabilities are not secret, so they are
+//! left as they are; the seed of the SED's signing key, which follows the response should the
+//! deployment sign broadcasts, is not wrapped either. As the secret is only ever
+//! [proven](super::challenge) to the SSS, never sent, this protects the keys from anyone who reads
+//! the SSS interface; with the `pq` feature, the wrapped secrets are also [sealed](super::pq).
+
+use aes::cipher::block::Block;
+use aes::{Aes128, BlockCipher, NewBlockCipher};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use crate::codec::secure::SecureSSSSecrets;
+use crate::codec::Id;
+use crate::cursor::WriteCursor;
+use crate::fatal::Fatal;
+use crate::{ct, glitch};
+
+/// The info label from which the key encryption key is expanded
+const WRAP_LABEL: &[u8] = b"SCEWL wrap";
+
+/// The initial value of AES-KW, which the integrity check value unwraps to
+const IV: [u8; 8] = [0xA6; 8];
+
+/// The length of the wrapped secrets, i.e. the AES key, the seed, then the HMAC key
+const WRAPPED: usize = 16 + 32 + 64;
+
+/// The number of passes over the blocks which AES-KW makes
+const PASSES: u64 = 6;
+
+/// Derives the key encryption key of the SED of the given registration secret
+fn kek(secret: &[u8; 64], dev_id: Id) -> [u8; 16] {
+    let mut kek = [0_u8; 16];
+    Hkdf::<Sha256>::new(Some(&u16::from(dev_id).to_le_bytes()), secret)
+        .expand(WRAP_LABEL, &mut kek)
+        .unwrap_or_else(|_| Fatal::SessionKey.panic());
+    kek
+}
+
+/// Wraps the 64-bit blocks of the data in place with AES-KW under the key encryption key,
+/// returning the integrity check value which precedes them in RFC 3394
+///
+/// # Panics
+///
+/// Panics should the data not be a whole number of 64-bit blocks.
+pub fn wrap_in_place(kek: &[u8; 16], data: &mut [u8]) -> [u8; 8] {
+    assert!(data.len().is_multiple_of(8), "wrapped a partial block");
+
+    let cipher = Aes128::new(kek.into());
+    let mut value = IV;
+    let mut block = Block::<Aes128>::default();
+    let mut step = 0_u64;
+    for _ in 0..PASSES {
+        for half in data.chunks_exact_mut(8) {
+            step += 1;
+            block[..8].copy_from_slice(&value);
+            block[8..].copy_from_slice(half);
+            cipher.encrypt_block(&mut block);
+            value.copy_from_slice(&block[..8]);
+            value = (u64::from_be_bytes(value) ^ step).to_be_bytes();
+            half.copy_from_slice(&block[8..]);
+        }
+    }
+    block.fill(0);
+    value
+}
+
+/// Unwraps the 64-bit blocks of the data in place with AES-KW under the key encryption key,
+/// returning whether the integrity check value which preceded them unwrapped to the initial value
+///
+/// # Panics
+///
+/// Panics should the data not be a whole number of 64-bit blocks.
+pub fn unwrap_in_place(kek: &[u8; 16], integrity: &[u8; 8], data: &mut [u8]) -> bool {
+    assert!(data.len().is_multiple_of(8), "unwrapped a partial block");
+
+    let cipher = Aes128::new(kek.into());
+    let mut value = *integrity;
+    let mut block = Block::<Aes128>::default();
+    #[allow(clippy::cast_possible_truncation)] // there are only ever a handful of blocks
+    let mut step = PASSES * (data.len() / 8) as u64;
+    for _ in 0..PASSES {
+        for half in data.chunks_exact_mut(8).rev() {
+            block[..8].copy_from_slice(&(u64::from_be_bytes(value) ^ step).to_be_bytes());
+            block[8..].copy_from_slice(half);
+            cipher.decrypt_block(&mut block);
+            value.copy_from_slice(&block[..8]);
+            half.copy_from_slice(&block[8..]);
+            step -= 1;
+        }
+    }
+    block.fill(0);
+    ct::eq(&value, &IV)
+}
+
+/// Copies the wrapped secrets out of the response's secrets
+fn gather(secrets: &SecureSSSSecrets) -> [u8; WRAPPED] {
+    let mut data = [0_u8; WRAPPED];
+    WriteCursor::new(&mut data)
+        .write(&secrets.aes_key)
+        .write(&secrets.seed)
+        .write(&secrets.hmac_key);
+    data
+}
+
+/// Copies the wrapped secrets back into the response's secrets
+fn scatter(data: &[u8; WRAPPED], secrets: &mut SecureSSSSecrets) {
+    let (aes_key, rest) = data.split_at(16);
+    let (seed, hmac_key) = rest.split_at(32);
+    secrets.aes_key.copy_from_slice(aes_key);
+    secrets.seed.copy_from_slice(seed);
+    secrets.hmac_key.copy_from_slice(hmac_key);
+}
+
+/// Wraps the secrets of the response to the SED of the given registration secret, as the SSS does
+pub fn wrap(secret: &[u8; 64], dev_id: Id, mut secrets: SecureSSSSecrets) -> SecureSSSSecrets {
+    let (mut data, mut kek) = (gather(&secrets), kek(secret, dev_id));
+    secrets.integrity = wrap_in_place(&kek, &mut data);
+    scatter(&data, &mut secrets);
+    data.fill(0);
+    kek.fill(0);
+    secrets
+}
+
+/// Unwraps the secrets of the response to this SED, whose registration secret is given, or `None`
+/// should they not be authentic
+pub fn unwrap(
+    secret: &[u8; 64],
+    dev_id: Id,
+    mut secrets: SecureSSSSecrets,
+) -> Option<SecureSSSSecrets> {
+    let (mut data, mut kek) = (gather(&secrets), kek(secret, dev_id));
+    let unwrapped = unwrap_in_place(&kek, &secrets.integrity, &mut data);
+    kek.fill(0);
+    let authentic = glitch::check(|| unwrapped);
+    if authentic {
+        scatter(&data, &mut secrets);
+    }
+    data.fill(0);
+    authentic.then_some(secrets)
+}
@@ -0,0 +1,42 @@
+//! A theoretically secure implementation of the controller security features, which employs a
+//! variety of protections against interception, modification, or replay attacks.
+//!
+//! See the associated modules for further details.
+
+#[cfg(feature = "controller")]
+pub use auth::Handler as AuthHandler;
+pub use checkpoint::{Checkpoints, Counter, Reserved, STRIDE};
+pub use crypto::Handler as CryptoHandler;
+pub use crypto::{JITTER, SESSIONS, WINDOW};
+pub use gcm::{Aead, AeadHandler, Handler as GcmHandler};
+pub use handshake::{Handshake, Handshakes, Kind as HandshakeKind, Received as HandshakeReceived};
+pub use reseed::{Entropy, RESEED_INTERVAL};
+pub use rotation::Epochs;
+pub use signed::{Handler as SignedHandler, Keys as SigningKeys, SIGNATURE};
+pub use siv::Handler as SivHandler;
+#[cfg(feature = "controller")]
+pub use suite::{register, Registered, Suite};
+#[cfg(feature = "controller")]
+pub use test_auth::Handler as TestAuthHandler;
+
+#[cfg(feature = "controller")]
+mod auth;
+pub mod challenge;
+mod checkpoint;
+pub mod cmac;
+mod crypto;
+pub mod erasure;
+mod gcm;
+mod handshake;
+pub mod keepalive;
+pub mod keywrap;
+pub mod pq;
+mod reseed;
+pub mod revocation;
+pub mod rotation;
+mod signed;
+mod siv;
+#[cfg(feature = "controller")]
+mod suite;
+#[cfg(feature = "controller")]
+mod test_auth;
@@ -0,0 +1,209 @@
+//! The Keccak-f\[1600\] permutation, and the sponges of the SHA-3 hashes and SHAKE XOFs which
+//! ML-KEM is built on (FIPS 202)
+//!
+//! None of the SHA-3 family is otherwise needed by the firmware, so this is the smallest sponge
+//! which serves ML-KEM: one byte is absorbed or squeezed at a time, and nothing is unrolled. It is
+//! public only so that the host tests may check it against the known answers of FIPS 202.
+
+/// The round constants of Keccak-f\[1600\], by round
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000_0000_0000_0001,
+    0x0000_0000_0000_8082,
+    0x8000_0000_0000_808A,
+    0x8000_0000_8000_8000,
+    0x0000_0000_0000_808B,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8009,
+    0x0000_0000_0000_008A,
+    0x0000_0000_0000_0088,
+    0x0000_0000_8000_8009,
+    0x0000_0000_8000_000A,
+    0x0000_0000_8000_808B,
+    0x8000_0000_0000_008B,
+    0x8000_0000_0000_8089,
+    0x8000_0000_0000_8003,
+    0x8000_0000_0000_8002,
+    0x8000_0000_0000_0080,
+    0x0000_0000_0000_800A,
+    0x8000_0000_8000_000A,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8080,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8008,
+];
+
+/// The rotation of each lane in the rho step, by `x + 5 * y`
+const ROTATIONS: [u32; 25] = [
+    0, 1, 62, 28, 27, 36, 44, 6, 55, 20, 3, 10, 43, 25, 39, 41, 45, 15, 21, 8, 18, 2, 61, 56, 14,
+];
+
+/// The rate of SHAKE128, in bytes
+pub const SHAKE128_RATE: usize = 168;
+
+/// The rate of SHAKE256, in bytes
+pub const SHAKE256_RATE: usize = 136;
+
+/// The rate of SHA3-256, in bytes
+const SHA3_256_RATE: usize = 136;
+
+/// The rate of SHA3-512, in bytes
+const SHA3_512_RATE: usize = 72;
+
+/// The domain separation and first padding bit of the SHA-3 hashes
+const SHA3_PAD: u8 = 0x06;
+
+/// The domain separation and first padding bit of the SHAKE XOFs
+const SHAKE_PAD: u8 = 0x1F;
+
+/// Applies Keccak-f\[1600\] to the state, whose lanes are indexed by `x + 5 * y`
+fn permute(a: &mut [u64; 25]) {
+    for rc in ROUND_CONSTANTS {
+        // theta
+        let mut c = [0_u64; 5];
+        for (x, c) in c.iter_mut().enumerate() {
+            *c = a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                a[x + 5 * y] ^= d;
+            }
+        }
+
+        // rho and pi
+        let mut b = [0_u64; 25];
+        for x in 0..5 {
+            for y in 0..5 {
+                b[y + 5 * ((2 * x + 3 * y) % 5)] = a[x + 5 * y].rotate_left(ROTATIONS[x + 5 * y]);
+            }
+        }
+
+        // chi
+        for y in 0..5 {
+            for x in 0..5 {
+                a[x + 5 * y] = b[x + 5 * y] ^ (!b[(x + 1) % 5 + 5 * y] & b[(x + 2) % 5 + 5 * y]);
+            }
+        }
+
+        // iota
+        a[0] ^= rc;
+    }
+}
+
+/// A Keccak sponge of the given rate, which absorbs until it is first squeezed
+pub struct Sponge {
+    /// The state, whose lanes are indexed by `x + 5 * y`
+    state: [u64; 25],
+    /// The rate, in bytes
+    rate: usize,
+    /// The position within the rate of the next byte absorbed or squeezed
+    pos: usize,
+    /// The padding which ends the input, or `None` once it has been applied
+    pad: Option<u8>,
+}
+
+impl Sponge {
+    /// A sponge of the given rate, whose input is ended with the given padding
+    const fn new(rate: usize, pad: u8) -> Self {
+        Self {
+            state: [0; 25],
+            rate,
+            pos: 0,
+            pad: Some(pad),
+        }
+    }
+
+    /// A SHAKE128 sponge
+    pub const fn shake128() -> Self {
+        Self::new(SHAKE128_RATE, SHAKE_PAD)
+    }
+
+    /// A SHAKE256 sponge
+    pub const fn shake256() -> Self {
+        Self::new(SHAKE256_RATE, SHAKE_PAD)
+    }
+
+    /// XORs a byte into the state at the given position
+    fn xor(&mut self, pos: usize, byte: u8) {
+        self.state[pos / 8] ^= u64::from(byte) << (8 * (pos % 8));
+    }
+
+    /// Absorbs the input
+    ///
+    /// # Panics
+    ///
+    /// Panics should the sponge have been squeezed already.
+    pub fn absorb(&mut self, input: &[u8]) -> &mut Self {
+        assert!(self.pad.is_some(), "absorbed after squeezing");
+        for &byte in input {
+            self.xor(self.pos, byte);
+            self.pos += 1;
+            if self.pos == self.rate {
+                permute(&mut self.state);
+                self.pos = 0;
+            }
+        }
+        self
+    }
+
+    /// Squeezes the sponge into the output, padding the input first should it not have been
+    /// squeezed before
+    pub fn squeeze(&mut self, output: &mut [u8]) {
+        if let Some(pad) = self.pad.take() {
+            self.xor(self.pos, pad);
+            self.xor(self.rate - 1, 0x80);
+            permute(&mut self.state);
+            self.pos = 0;
+        }
+
+        for byte in output {
+            if self.pos == self.rate {
+                permute(&mut self.state);
+                self.pos = 0;
+            }
+            #[allow(clippy::cast_possible_truncation)] // the byte at the position is taken
+            let lane = (self.state[self.pos / 8] >> (8 * (self.pos % 8))) as u8;
+            *byte = lane;
+            self.pos += 1;
+        }
+    }
+}
+
+impl Drop for Sponge {
+    fn drop(&mut self) {
+        // the state may be keyed, as with the PRF of ML-KEM
+        self.state.fill(0);
+    }
+}
+
+/// SHA3-256 of the concatenation of the parts
+pub fn sha3_256(parts: &[&[u8]]) -> [u8; 32] {
+    let mut sponge = Sponge::new(SHA3_256_RATE, SHA3_PAD);
+    for part in parts {
+        sponge.absorb(part);
+    }
+    let mut digest = [0; 32];
+    sponge.squeeze(&mut digest);
+    digest
+}
+
+/// SHA3-512 of the concatenation of the parts
+pub fn sha3_512(parts: &[&[u8]]) -> [u8; 64] {
+    let mut sponge = Sponge::new(SHA3_512_RATE, SHA3_PAD);
+    for part in parts {
+        sponge.absorb(part);
+    }
+    let mut digest = [0; 64];
+    sponge.squeeze(&mut digest);
+    digest
+}
+
+/// SHAKE256 of the concatenation of the parts, of the length of the output
+pub fn shake256(parts: &[&[u8]], output: &mut [u8]) {
+    let mut sponge = Sponge::shake256();
+    for part in parts {
+        sponge.absorb(part);
+    }
+    sponge.squeeze(output);
+}
@@ -0,0 +1,491 @@
+//! ML-KEM-512, the module-lattice key encapsulation mechanism of FIPS 203 at its smallest parameter
+//! set (formerly Kyber512)
+//!
+//! Only what registration needs is implemented: the SED [generates](keypair) a keypair from a
+//! seed and [decapsulates](decapsulate), while the SSS (or the mock SSS) [encapsulates](encapsulate)
+//! to it. Coefficients are kept fully reduced in `[0, q)` as `u16`s, and every reduction (and the
+//! division by q in compression) is a Barrett reduction rather than a `%`, which the Cortex-M3
+//! would compute in a data-dependent number of cycles.
+
+use super::keccak::{self, Sponge, SHAKE128_RATE};
+use crate::ct;
+
+/// The number of coefficients of each polynomial
+const N: usize = 256;
+
+/// The modulus
+const Q: u32 = 3329;
+
+/// The rank of the module
+const K: usize = 2;
+
+/// The parameter of the distribution of the secret and the noise of key generation
+const ETA1: usize = 3;
+
+/// The parameter of the distribution of the noise of encryption
+const ETA2: usize = 2;
+
+/// The bits to which each coefficient of `u` is compressed
+const DU: u32 = 10;
+
+/// The bits to which each coefficient of `v` is compressed
+const DV: u32 = 4;
+
+/// The size of an encoded polynomial of fully reduced coefficients
+const POLY_BYTES: usize = 384;
+
+/// The size of an encapsulation key
+pub const ENCAPSULATION_KEY: usize = K * POLY_BYTES + 32;
+
+/// The size of a decapsulation key
+pub const DECAPSULATION_KEY: usize = K * POLY_BYTES + ENCAPSULATION_KEY + 32 + 32;
+
+/// The size of a ciphertext
+pub const CIPHERTEXT: usize = K * N * DU as usize / 8 + N * DV as usize / 8;
+
+/// The size of a shared secret
+pub const SHARED_SECRET: usize = 32;
+
+/// `floor(2^32 / q)`, the multiplier of the Barrett reduction
+const BARRETT: u64 = (1 << 32) / Q as u64;
+
+/// `128^-1 mod q`, by which the inverse NTT is scaled
+const INV_128: u32 = 3303;
+
+/// A polynomial, in either the normal or the NTT domain
+type Poly = [u16; N];
+
+/// `17^e mod q`
+const fn pow17(e: u32) -> u16 {
+    let mut acc = 1;
+    let mut i = 0;
+    while i < e {
+        acc = acc * 17 % Q;
+        i += 1;
+    }
+    #[allow(clippy::cast_possible_truncation)] // q is less than 2^16
+    let acc = acc as u16;
+    acc
+}
+
+/// The seven-bit reversal of `i`
+const fn bit_rev7(i: u32) -> u32 {
+    (i.reverse_bits() >> 25) & 0x7F
+}
+
+/// `17^BitRev7(i)`, the twiddle factors of the NTT
+const ZETAS: [u16; 128] = {
+    let mut zetas = [0; 128];
+    let mut i = 0_u32;
+    while i < 128 {
+        zetas[i as usize] = pow17(bit_rev7(i));
+        i += 1;
+    }
+    zetas
+};
+
+/// `17^(2 * BitRev7(i) + 1)`, the moduli of the products of the NTT domain
+const GAMMAS: [u16; 128] = {
+    let mut gammas = [0; 128];
+    let mut i = 0_u32;
+    while i < 128 {
+        gammas[i as usize] = pow17(2 * bit_rev7(i) + 1);
+        i += 1;
+    }
+    gammas
+};
+
+/// The quotient and remainder of `a` divided by q, in constant time
+fn div_rem(a: u32) -> (u32, u32) {
+    #[allow(clippy::cast_possible_truncation)] // the quotient of a `u32` by q fits in a `u32`
+    let quot = ((u64::from(a) * BARRETT) >> 32) as u32;
+    // the estimate is at most one less than the quotient
+    let rem = a - quot * Q;
+    let over = rem.wrapping_sub(Q);
+    let short = over >> 31;
+    let mask = 0_u32.wrapping_sub(short);
+    (quot + (short ^ 1), (rem & mask) | (over & !mask))
+}
+
+/// `a mod q`, in constant time
+fn reduce(a: u32) -> u16 {
+    #[allow(clippy::cast_possible_truncation)] // the remainder is less than q
+    let rem = div_rem(a).1 as u16;
+    rem
+}
+
+/// `a + b mod q`
+fn add(a: u16, b: u16) -> u16 {
+    reduce(u32::from(a) + u32::from(b))
+}
+
+/// `a - b mod q`
+fn sub(a: u16, b: u16) -> u16 {
+    reduce(u32::from(a) + Q - u32::from(b))
+}
+
+/// `a * b mod q`
+fn mul(a: u16, b: u16) -> u16 {
+    reduce(u32::from(a) * u32::from(b))
+}
+
+/// Transforms the polynomial into the NTT domain in place (algorithm 9 of FIPS 203)
+fn ntt(f: &mut Poly) {
+    let mut i = 1;
+    let mut len = 128;
+    while len >= 2 {
+        for start in (0..N).step_by(2 * len) {
+            let zeta = ZETAS[i];
+            i += 1;
+            for j in start..start + len {
+                let t = mul(zeta, f[j + len]);
+                f[j + len] = sub(f[j], t);
+                f[j] = add(f[j], t);
+            }
+        }
+        len /= 2;
+    }
+}
+
+/// Transforms the polynomial out of the NTT domain in place (algorithm 10 of FIPS 203)
+fn inv_ntt(f: &mut Poly) {
+    let mut i = 127;
+    let mut len = 2;
+    while len <= 128 {
+        for start in (0..N).step_by(2 * len) {
+            let zeta = ZETAS[i];
+            i -= 1;
+            for j in start..start + len {
+                let t = f[j];
+                f[j] = add(t, f[j + len]);
+                f[j + len] = mul(zeta, sub(f[j + len], t));
+            }
+        }
+        len *= 2;
+    }
+    for c in f.iter_mut() {
+        *c = reduce(u32::from(*c) * INV_128);
+    }
+}
+
+/// Adds the product of the polynomials in the NTT domain to the accumulator (algorithms 11 and 12
+/// of FIPS 203)
+fn mul_acc(acc: &mut Poly, f: &Poly, g: &Poly) {
+    for (i, gamma) in GAMMAS.iter().enumerate() {
+        let (a0, a1) = (u32::from(f[2 * i]), u32::from(f[2 * i + 1]));
+        let (b0, b1) = (u32::from(g[2 * i]), u32::from(g[2 * i + 1]));
+        let c0 = a0 * b0 + u32::from(reduce(a1 * b1)) * u32::from(*gamma);
+        let c1 = a0 * b1 + a1 * b0;
+        acc[2 * i] = add(acc[2 * i], reduce(c0));
+        acc[2 * i + 1] = add(acc[2 * i + 1], reduce(c1));
+    }
+}
+
+/// Adds the second polynomial to the first in place
+fn add_assign(f: &mut Poly, g: &Poly) {
+    for (f, g) in f.iter_mut().zip(g) {
+        *f = add(*f, *g);
+    }
+}
+
+/// Samples the entry of the matrix `Â` at the row and column from the seed (algorithm 7 of
+/// FIPS 203), by rejection, which need not be constant time as the matrix is public
+fn sample_ntt(rho: &[u8], row: u8, col: u8) -> Poly {
+    let mut xof = Sponge::shake128();
+    xof.absorb(rho).absorb(&[col, row]);
+
+    let mut f = [0; N];
+    let mut j = 0;
+    let mut block = [0_u8; SHAKE128_RATE];
+    while j < N {
+        xof.squeeze(&mut block);
+        for c in block.chunks_exact(3) {
+            let d1 = u16::from(c[0]) | u16::from(c[1] & 0x0F) << 8;
+            let d2 = u16::from(c[1] >> 4) | u16::from(c[2]) << 4;
+            for d in [d1, d2] {
+                if u32::from(d) < Q && j < N {
+                    f[j] = d;
+                    j += 1;
+                }
+            }
+        }
+    }
+    f
+}
+
+/// Samples a polynomial from the centred binomial distribution of the given parameter, from the
+/// output of the PRF on the seed and counter (algorithms 8 and 2 of FIPS 203)
+fn sample_cbd(eta: usize, seed: &[u8; 32], counter: u8) -> Poly {
+    let mut bytes = [0_u8; 64 * ETA1];
+    let bytes = &mut bytes[..64 * eta];
+    keccak::shake256(&[seed, &[counter]], bytes);
+
+    let bit = |i: usize| u16::from(bytes[i / 8] >> (i % 8) & 1);
+    let mut f = [0; N];
+    for (i, c) in f.iter_mut().enumerate() {
+        let x: u16 = (0..eta).map(|j| bit(2 * i * eta + j)).sum();
+        let y: u16 = (0..eta).map(|j| bit(2 * i * eta + eta + j)).sum();
+        *c = sub(x, y);
+    }
+    bytes.fill(0);
+    f
+}
+
+/// Encodes the polynomial, of coefficients of `d` bits, to the output (algorithm 5 of FIPS 203)
+fn encode(d: u32, f: &Poly, out: &mut [u8]) {
+    let (mut acc, mut bits, mut pos) = (0_u32, 0, 0);
+    for &c in f {
+        acc |= u32::from(c) << bits;
+        bits += d;
+        while bits >= 8 {
+            #[allow(clippy::cast_possible_truncation)] // the low byte is taken
+            let low = acc as u8;
+            out[pos] = low;
+            pos += 1;
+            acc >>= 8;
+            bits -= 8;
+        }
+    }
+}
+
+/// Decodes a polynomial of coefficients of `d` bits from the input (algorithm 6 of FIPS 203),
+/// reducing them modulo q
+fn decode(d: u32, input: &[u8]) -> Poly {
+    let (mut acc, mut bits) = (0_u32, 0);
+    let mut bytes = input.iter();
+    let mut f = [0; N];
+    for c in &mut f {
+        while bits < d {
+            acc |= u32::from(*bytes.next().unwrap_or(&0)) << bits;
+            bits += 8;
+        }
+        *c = reduce(acc & ((1 << d) - 1));
+        acc >>= d;
+        bits -= d;
+    }
+    f
+}
+
+/// Compresses each coefficient of the polynomial to `d` bits, as `round(2^d * x / q) mod 2^d`
+fn compress(d: u32, f: &mut Poly) {
+    for c in f.iter_mut() {
+        let (quot, _) = div_rem((u32::from(*c) << d) + Q / 2);
+        #[allow(clippy::cast_possible_truncation)] // the quotient is masked to d bits
+        let compressed = (quot & ((1 << d) - 1)) as u16;
+        *c = compressed;
+    }
+}
+
+/// Decompresses each coefficient of the polynomial from `d` bits, as `round(q * y / 2^d)`
+fn decompress(d: u32, f: &mut Poly) {
+    for c in f.iter_mut() {
+        #[allow(clippy::cast_possible_truncation)] // the result is less than q
+        let decompressed = ((u32::from(*c) * Q + (1 << (d - 1))) >> d) as u16;
+        *c = decompressed;
+    }
+}
+
+/// Generates a K-PKE keypair from the seed (algorithm 13 of FIPS 203), writing the encryption key
+/// to `ek` and the decryption key to `dk`
+fn pke_keypair(d: &[u8; 32], ek: &mut [u8], dk: &mut [u8]) {
+    #[allow(clippy::cast_possible_truncation)] // truncation impossible, as k is 2
+    let mut g = keccak::sha3_512(&[d, &[K as u8]]);
+    let (rho, sigma) = g.split_at(32);
+    let mut sigma_seed = [0_u8; 32];
+    sigma_seed.copy_from_slice(sigma);
+
+    let mut s = [[0; N]; K];
+    let mut counter = 0;
+    for s in &mut s {
+        *s = sample_cbd(ETA1, &sigma_seed, counter);
+        ntt(s);
+        counter += 1;
+    }
+
+    for (i, t) in ek.chunks_exact_mut(POLY_BYTES).take(K).enumerate() {
+        let mut e = sample_cbd(ETA1, &sigma_seed, counter);
+        counter += 1;
+        ntt(&mut e);
+        for (j, s) in s.iter().enumerate() {
+            #[allow(clippy::cast_possible_truncation)] // truncation impossible, as k is 2
+            mul_acc(&mut e, &sample_ntt(rho, i as u8, j as u8), s);
+        }
+        encode(12, &e, t);
+        e.fill(0);
+    }
+    ek[K * POLY_BYTES..].copy_from_slice(rho);
+
+    for (s, out) in s.iter_mut().zip(dk.chunks_exact_mut(POLY_BYTES)) {
+        encode(12, s, out);
+        s.fill(0);
+    }
+    sigma_seed.fill(0);
+    g.fill(0);
+}
+
+/// Encrypts the message under the encryption key with the randomness (algorithm 14 of FIPS 203),
+/// writing the ciphertext to `ct`
+fn pke_encrypt(ek: &[u8], m: &[u8; 32], r: &[u8; 32], ct: &mut [u8; CIPHERTEXT]) {
+    let rho = &ek[K * POLY_BYTES..];
+
+    let mut y = [[0; N]; K];
+    let mut counter = 0;
+    for y in &mut y {
+        *y = sample_cbd(ETA1, r, counter);
+        ntt(y);
+        counter += 1;
+    }
+
+    let (cu, cv) = ct.split_at_mut(K * N * DU as usize / 8);
+    for (i, cu) in cu.chunks_exact_mut(N * DU as usize / 8).enumerate() {
+        let mut u = [0; N];
+        for (j, y) in y.iter().enumerate() {
+            // the transpose of Â, i.e. the entry at row j and column i
+            #[allow(clippy::cast_possible_truncation)] // truncation impossible, as k is 2
+            mul_acc(&mut u, &sample_ntt(rho, j as u8, i as u8), y);
+        }
+        inv_ntt(&mut u);
+        add_assign(&mut u, &sample_cbd(ETA2, r, counter));
+        counter += 1;
+        compress(DU, &mut u);
+        encode(DU, &u, cu);
+    }
+
+    let mut v = [0; N];
+    for (t, y) in ek.chunks_exact(POLY_BYTES).zip(&y) {
+        mul_acc(&mut v, &decode(12, t), y);
+    }
+    inv_ntt(&mut v);
+    add_assign(&mut v, &sample_cbd(ETA2, r, counter));
+    let mut mu = decode(1, m);
+    decompress(1, &mut mu);
+    add_assign(&mut v, &mu);
+    compress(DV, &mut v);
+    encode(DV, &v, cv);
+
+    for y in &mut y {
+        y.fill(0);
+    }
+    v.fill(0);
+    mu.fill(0);
+}
+
+/// Decrypts the ciphertext with the decryption key (algorithm 15 of FIPS 203)
+fn pke_decrypt(dk: &[u8], ct: &[u8; CIPHERTEXT]) -> [u8; 32] {
+    let (cu, cv) = ct.split_at(K * N * DU as usize / 8);
+
+    let mut w = [0; N];
+    for (cu, s) in cu
+        .chunks_exact(N * DU as usize / 8)
+        .zip(dk.chunks_exact(POLY_BYTES))
+    {
+        let mut u = decode(DU, cu);
+        decompress(DU, &mut u);
+        ntt(&mut u);
+        let mut s = decode(12, s);
+        mul_acc(&mut w, &s, &u);
+        s.fill(0);
+    }
+    inv_ntt(&mut w);
+
+    let mut v = decode(DV, cv);
+    decompress(DV, &mut v);
+    for (v, w) in v.iter_mut().zip(&w) {
+        *v = sub(*v, *w);
+    }
+    compress(1, &mut v);
+
+    let mut m = [0; 32];
+    encode(1, &v, &mut m);
+    v.fill(0);
+    w.fill(0);
+    m
+}
+
+/// Generates a keypair from the seeds `d` and `z` (algorithm 16 of FIPS 203), writing the
+/// encapsulation key to `ek` and the decapsulation key to `dk`
+pub fn keypair(
+    d: &[u8; 32],
+    z: &[u8; 32],
+    ek: &mut [u8; ENCAPSULATION_KEY],
+    dk: &mut [u8; DECAPSULATION_KEY],
+) {
+    let (dk_pke, rest) = dk.split_at_mut(K * POLY_BYTES);
+    pke_keypair(d, ek, dk_pke);
+
+    let (ek_copy, rest) = rest.split_at_mut(ENCAPSULATION_KEY);
+    let (h, z_copy) = rest.split_at_mut(32);
+    ek_copy.copy_from_slice(ek);
+    h.copy_from_slice(&keccak::sha3_256(&[ek]));
+    z_copy.copy_from_slice(z);
+}
+
+/// Whether every coefficient of the encapsulation key is reduced, i.e. the modulus check of
+/// section 7.2 of FIPS 203, without which it may not be encapsulated to
+pub fn well_formed(ek: &[u8; ENCAPSULATION_KEY]) -> bool {
+    let mut reduced = [0_u8; POLY_BYTES];
+    ek.chunks_exact(POLY_BYTES).take(K).all(|t| {
+        encode(12, &decode(12, t), &mut reduced);
+        reduced[..] == t[..]
+    })
+}
+
+/// Encapsulates a shared secret to the encapsulation key with the randomness `m` (algorithm 17 of
+/// FIPS 203), writing the ciphertext to `ct`, or returns `None` should the key not be
+/// [well formed](well_formed)
+pub fn encapsulate(
+    ek: &[u8; ENCAPSULATION_KEY],
+    m: &[u8; 32],
+    ct: &mut [u8; CIPHERTEXT],
+) -> Option<[u8; SHARED_SECRET]> {
+    if !well_formed(ek) {
+        return None;
+    }
+
+    let mut g = keccak::sha3_512(&[m, &keccak::sha3_256(&[ek])]);
+    let (shared, r) = g.split_at(32);
+    let mut r_seed = [0_u8; 32];
+    r_seed.copy_from_slice(r);
+    pke_encrypt(ek, m, &r_seed, ct);
+
+    let mut key = [0_u8; SHARED_SECRET];
+    key.copy_from_slice(shared);
+    r_seed.fill(0);
+    g.fill(0);
+    Some(key)
+}
+
+/// Decapsulates the shared secret from the ciphertext with the decapsulation key (algorithm 18 of
+/// FIPS 203)
+///
+/// Should the ciphertext have been tampered with, the shared secret is implicitly rejected: a
+/// pseudorandom secret derived from `z` and the ciphertext is returned, which no encapsulation
+/// agrees with, selected in constant time so that nothing reveals which was returned.
+pub fn decapsulate(dk: &[u8; DECAPSULATION_KEY], ct: &[u8; CIPHERTEXT]) -> [u8; SHARED_SECRET] {
+    let (dk_pke, rest) = dk.split_at(K * POLY_BYTES);
+    let (ek, rest) = rest.split_at(ENCAPSULATION_KEY);
+    let (ek_hash, implicit) = rest.split_at(32);
+
+    let mut message = pke_decrypt(dk_pke, ct);
+    let mut derived = keccak::sha3_512(&[&message, ek_hash]);
+    let (shared, randomness) = derived.split_at(32);
+    let mut r_seed = [0_u8; 32];
+    r_seed.copy_from_slice(randomness);
+
+    let mut rejected = [0_u8; SHARED_SECRET];
+    keccak::shake256(&[implicit, ct], &mut rejected);
+
+    let mut reencrypted = [0_u8; CIPHERTEXT];
+    pke_encrypt(ek, &message, &r_seed, &mut reencrypted);
+    let mask = 0_u8.wrapping_sub(u8::from(ct::eq(ct, &reencrypted)));
+
+    let mut key = [0_u8; SHARED_SECRET];
+    for ((key, shared), rejected) in key.iter_mut().zip(shared).zip(&rejected) {
+        *key = (shared & mask) | (rejected & !mask);
+    }
+    message.fill(0);
+    derived.fill(0);
+    r_seed.fill(0);
+    rejected.fill(0);
+    key
+}
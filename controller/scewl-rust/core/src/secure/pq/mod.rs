@@ -0,0 +1,213 @@
+//! Experimental: the establishment of the secrets distributed at registration by ML-KEM-512, a
+//! post-quantum key encapsulation mechanism
+//!
+//! # Design
+//!
+//! The registration response carries the deployment's keys and the SED's seed as they are, so an
+//! eavesdropper on the SSS interface learns them all. With the `pq` feature, the SED instead
+//! appends a fresh ML-KEM-512 [encapsulation key](kem::ENCAPSULATION_KEY) to its registration
+//! request and advertises [`CAP_PQ_KEM`](crate::codec::secure::CAP_PQ_KEM). Should the deployment
+//! enable the capability, the SSS [encapsulates](kem::encapsulate) a shared secret to that key, and
+//! [seals](seal) the response under it:
+//!
+//! ```text
+//! okm = HKDF-Expand(HKDF-Extract(dev_id, shared), "SCEWL pq")
+//! wrapped = (aes_key || seed || hmac_key) ^ okm[..112], and signing seed ^ okm[112..144]
+//! tag = HMAC(okm[144..], response || ciphertext || signing keys)
+//! sealed = response || ciphertext || tag || signing keys
+//! ```
+//!
+//! where `response` is the [`SecureSSSResponse`] of the wrapped secrets, and the signing keys (of
+//! the wrapped seed) are present only should the deployment sign broadcasts. The SED
+//! [opens](KeyPair::open) the response in place, leaving it exactly as an unsealed response would
+//! be, so that nothing after is aware of the KEM.
+//!
+//! # Caveats
+//!
+//! This is experimental, and protects only the confidentiality of the response: nothing yet
+//! authenticates the SSS, so an attacker in the middle of the SSS interface may substitute its own
+//! encapsulation key. The keypair is derived from the registration secret and the
+//! runtime entropy, so it is no stronger than the latter should the secret be
+//! learnt, which is still sent in the registration request. The KEM adds 800 bytes to the request
+//! and 800 to the response, and a few tens of milliseconds of work to each registration.
+
+use core::convert::TryInto;
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+use crate::codec::secure::SecureSSSResponse;
+use crate::codec::Id;
+use crate::fatal::Fatal;
+use crate::{ct, glitch};
+
+pub mod keccak;
+pub mod kem;
+
+/// The info label from which the sealing key is expanded
+const PQ_LABEL: &[u8] = b"SCEWL pq";
+
+/// The info label from which the seeds of the keypair are expanded
+const KEYGEN_LABEL: &[u8] = b"SCEWL pq keygen";
+
+/// The offset of the secrets within the response, i.e. after the id and the operation
+const SECRETS: usize = 4;
+
+/// The length of the secrets of the response which are wrapped, i.e. the AES key, the seed, then
+/// the HMAC key
+const WRAPPED: usize = 16 + 32 + 64;
+
+/// The length of the pad, i.e. the wrapped secrets, then the seed of the signing key
+const PAD: usize = WRAPPED + 32;
+
+/// The length of the sealing of a response, i.e. the ciphertext then the tag
+pub const SEALING: usize = kem::CIPHERTEXT + 32;
+
+/// An ML-KEM-512 keypair of this SED, generated for a single registration
+///
+/// The decapsulation key is overwritten with zeroes once the keypair is dropped.
+pub struct KeyPair {
+    /// The decapsulation key
+    dk: [u8; kem::DECAPSULATION_KEY],
+}
+
+impl Drop for KeyPair {
+    fn drop(&mut self) {
+        self.dk.fill(0);
+    }
+}
+
+/// Derives the pad and the tag key with which a response to the given SED is sealed
+fn derive(shared: &[u8; kem::SHARED_SECRET], dev_id: Id) -> [u8; PAD + 32] {
+    let mut okm = [0_u8; PAD + 32];
+    Hkdf::<Sha256>::new(Some(&u16::from(dev_id).to_le_bytes()), shared)
+        .expand(PQ_LABEL, &mut okm)
+        .unwrap_or_else(|_| Fatal::SessionKey.panic());
+    okm
+}
+
+/// Computes the tag of a sealed response, under the tag key, over the response and its ciphertext
+/// then the signing keys which follow the tag, if any
+fn tag(key: &[u8], sealed: &[u8]) -> [u8; 32] {
+    let (sealing, keys) = sealed.split_at(SecureSSSResponse::size() + SEALING);
+    let mut hmac = Hmac::<Sha256>::new_varkey(key).unwrap_or_else(|_| Fatal::HmacKey.panic());
+    hmac.update(&sealing[..SecureSSSResponse::size() + kem::CIPHERTEXT]);
+    hmac.update(keys);
+    hmac.finalize().into_bytes().into()
+}
+
+/// XORs the pad over the secrets of the response, and over the seed of the signing key should
+/// there be one
+fn xor_pad(pad: &[u8], response: &mut [u8], signing: Option<&mut [u8]>) {
+    let (wrapped, seed) = pad.split_at(WRAPPED);
+    let secrets = &mut response[SECRETS..SECRETS + WRAPPED];
+    secrets
+        .iter_mut()
+        .zip(wrapped)
+        .for_each(|(byte, pad)| *byte ^= pad);
+    if let Some(signing) = signing {
+        signing[..32]
+            .iter_mut()
+            .zip(seed)
+            .for_each(|(byte, pad)| *byte ^= pad);
+    }
+}
+
+impl KeyPair {
+    /// Generates a keypair from the registration secret and fresh entropy, writing the
+    /// encapsulation key, to be appended to the registration request, to `ek`
+    pub fn generate(
+        secret: &[u8; 64],
+        entropy: &[u8; 32],
+        ek: &mut [u8; kem::ENCAPSULATION_KEY],
+    ) -> Self {
+        let mut seeds = [0_u8; 64];
+        Hkdf::<Sha256>::new(Some(entropy), secret)
+            .expand(KEYGEN_LABEL, &mut seeds)
+            .unwrap_or_else(|_| Fatal::SessionKey.panic());
+        let (d, z) = seeds.split_at(32);
+
+        let mut pair = Self {
+            dk: [0; kem::DECAPSULATION_KEY],
+        };
+        kem::keypair(
+            d.try_into().unwrap(),
+            z.try_into().unwrap(),
+            ek,
+            &mut pair.dk,
+        );
+        seeds.fill(0);
+        pair
+    }
+
+    /// Opens the sealed response to the given SED, the first `len` bytes of the buffer, in place,
+    /// returning the length of the opened response, or `None` should it not be authentic
+    ///
+    /// The ciphertext and the tag are removed, and the bytes after the opened response are
+    /// overwritten with zeroes.
+    pub fn open(&self, dev_id: Id, buf: &mut [u8], len: usize) -> Option<usize> {
+        let response = SecureSSSResponse::size();
+        if len < response + SEALING {
+            return None;
+        }
+
+        let ciphertext = buf[response..][..kem::CIPHERTEXT].try_into().unwrap();
+        let mut shared = kem::decapsulate(&self.dk, ciphertext);
+        let mut okm = derive(&shared, dev_id);
+        shared.fill(0);
+        let (pad, key) = okm.split_at(PAD);
+
+        let expected = tag(key, &buf[..len]);
+        if !glitch::check(|| ct::eq(&expected, &buf[response + kem::CIPHERTEXT..][..32])) {
+            okm.fill(0);
+            return None;
+        }
+
+        let (head, signing) = buf[..len].split_at_mut(response + SEALING);
+        xor_pad(pad, head, (signing.len() >= 32).then_some(signing));
+        okm.fill(0);
+
+        buf.copy_within(response + SEALING..len, response);
+        buf[len - SEALING..len].fill(0);
+        Some(len - SEALING)
+    }
+}
+
+/// Seals the response to the given SED, the first `len` bytes of the buffer, in place, by
+/// encapsulating to its encapsulation key with the randomness `m`, as the SSS does, returning the
+/// length of the sealed response, or `None` should the key not be well formed
+///
+/// # Panics
+///
+/// Panics should the buffer be too short for the sealed response, or the response carry no
+/// secrets.
+pub fn seal(
+    ek: &[u8; kem::ENCAPSULATION_KEY],
+    m: &[u8; 32],
+    dev_id: Id,
+    buf: &mut [u8],
+    len: usize,
+) -> Option<usize> {
+    let response = SecureSSSResponse::size();
+    assert!(len >= response, "sealed a response without secrets");
+
+    let mut ciphertext = [0_u8; kem::CIPHERTEXT];
+    let mut shared = kem::encapsulate(ek, m, &mut ciphertext)?;
+    let mut okm = derive(&shared, dev_id);
+    shared.fill(0);
+    let (pad, key) = okm.split_at(PAD);
+
+    buf.copy_within(response..len, response + SEALING);
+    buf[response..][..kem::CIPHERTEXT].copy_from_slice(&ciphertext);
+    let sealed = len + SEALING;
+
+    let (head, signing) = buf[..sealed].split_at_mut(response + SEALING);
+    xor_pad(pad, head, (signing.len() >= 32).then_some(signing));
+
+    let tag = tag(key, &buf[..sealed]);
+    buf[response + kem::CIPHERTEXT..][..32].copy_from_slice(&tag);
+    okm.fill(0);
+
+    Some(sealed)
+}
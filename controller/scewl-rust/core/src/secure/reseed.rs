@@ -0,0 +1,92 @@
+//! The reseeding of the CSPRNGs of the secure handlers from entropy gathered at runtime
+//!
+//! # Design
+//!
+//! The CSPRNG of each secure handler (and of the [handshakes](super::Handshakes)) is seeded from
+//! the seed which the SSS distributes at registration, which is the same for the SED every time it
+//! registers. Without more, an SED which resets and registers again draws the very IVs, nonces,
+//! and ephemeral keys it drew before the reset. Should the handler be given an
+//! [entropy source](Entropy) (e.g. the firmware's runtime source), its seed is
+//! instead mixed with fresh entropy before the CSPRNG is first seeded, and the CSPRNG is reseeded
+//! once every [`RESEED_INTERVAL`] draws:
+//!
+//! ```text
+//! seed' = SHA256("SCEWL reseed" || prior || entropy)
+//! ```
+//!
+//! where `prior` is the seed distributed by the SSS at first, and 32 bytes drawn from the CSPRNG
+//! thereafter, so that its state carries on into the next: entropy of poor quality (or none at
+//! all, as on an emulator) never leaves the CSPRNG weaker than it would be without reseeding.
+
+use rand_core::{RngCore, SeedableRng};
+use rand_hc::Hc128Rng;
+use sha2::{Digest, Sha256};
+
+/// The number of draws from a CSPRNG after which it is reseeded
+pub const RESEED_INTERVAL: u32 = 256;
+
+/// The label which prefixes the input from which a seed is derived
+const RESEED_LABEL: &[u8] = b"SCEWL reseed";
+
+/// A source of entropy gathered at runtime, with which the secure handlers reseed their CSPRNGs
+pub trait Entropy: Sync {
+    /// Fills the buffer with fresh entropy, which need not be uniform, as it is hashed before use
+    fn fill(&self, buf: &mut [u8; 32]);
+}
+
+/// The reseeding of a CSPRNG from an entropy source, should it have one, as described in the
+/// [module documentation](self)
+pub(super) struct Reseeding {
+    /// The entropy source, if any
+    source: Option<&'static dyn Entropy>,
+    /// The draws from the CSPRNG since it was last (re)seeded
+    draws: u32,
+}
+
+impl Reseeding {
+    /// The reseeding of a CSPRNG from the given entropy source, or none at all
+    pub(super) fn new(source: Option<&'static dyn Entropy>) -> Self {
+        Self { source, draws: 0 }
+    }
+
+    /// Seeds a CSPRNG from the seed, mixed with fresh entropy should there be a source
+    pub(super) fn seed(&self, seed: &[u8; 32]) -> Hc128Rng {
+        match self.source {
+            Some(source) => {
+                let mut mixed = mix(seed, source);
+                let rng = Hc128Rng::from_seed(mixed);
+                mixed.fill(0);
+                rng
+            }
+            None => Hc128Rng::from_seed(*seed),
+        }
+    }
+
+    /// Counts a draw from the CSPRNG, which is reseeded first should it be due, and returns it
+    pub(super) fn draw<'a>(&mut self, rng: &'a mut Hc128Rng) -> &'a mut Hc128Rng {
+        if self.source.is_some() {
+            self.draws += 1;
+            if self.draws >= RESEED_INTERVAL {
+                self.draws = 0;
+                let mut prior = [0_u8; 32];
+                rng.fill_bytes(&mut prior);
+                *rng = self.seed(&prior);
+                prior.fill(0);
+            }
+        }
+        rng
+    }
+}
+
+/// Derives a seed from the prior seed and fresh entropy from the source
+fn mix(prior: &[u8; 32], source: &dyn Entropy) -> [u8; 32] {
+    let mut entropy = [0_u8; 32];
+    source.fill(&mut entropy);
+
+    let mut sha = Sha256::new();
+    sha.update(RESEED_LABEL);
+    sha.update(prior);
+    sha.update(entropy);
+    entropy.fill(0);
+    sha.finalize().into()
+}
@@ -0,0 +1,75 @@
+//! The authentication of the list of deregistered devices, which the SSS pushes to each registered
+//! SED
+//!
+//! Once a device deregisters, the SSS adds it to a list of revoked devices, and removes it again
+//! should it register once more. Whenever the list changes, the SSS raises its serial and pushes it
+//! to every SED registered with it as a [`SecureSSSRevocation`], unsolicited, so that the SEDs drop
+//! frames from the revoked devices even though they may still hold the deployment's keys. A SED
+//! which registers is pushed the list as it stands. The list is tagged under a key derived from
+//! the registration secret of the SED which it is pushed to, which only that SED and the SSS hold:
+//!
+//! ```text
+//! key = HKDF-Expand(HKDF-Extract(dev_id, secret), "SCEWL revoke")
+//! tag = HMAC(key, dev_id || serial || count || ids)
+//! ```
+//!
+//! The tag binds the list to the SED and the serial, so that a push cannot be replayed to another
+//! SED; that the serial is higher than that of any list taken up before, so that an old list
+//! cannot be replayed to the same SED, is left to the controller's [policy](crate::policy). The
+//! list is not secret, so it is sent as it is. As with [rotation](super::rotation), only HKDF and
+//! HMAC-SHA256 are used, so that the SSS may tag lists with nothing but the Python standard
+//! library.
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+use crate::codec::secure::SecureSSSRevocation;
+use crate::codec::{Id, SSSOp};
+use crate::fatal::Fatal;
+use crate::{ct, glitch};
+
+/// The info label from which the tag key is expanded
+const REVOKE_LABEL: &[u8] = b"SCEWL revoke";
+
+/// Computes the tag of the list of revoked ids, serialised, pushed to the SED of the given
+/// registration secret
+fn tag(secret: &[u8; 64], dev_id: Id, serial: u32, ids: &[u8]) -> [u8; 32] {
+    let mut key = [0_u8; 32];
+    Hkdf::<Sha256>::new(Some(&u16::from(dev_id).to_le_bytes()), secret)
+        .expand(REVOKE_LABEL, &mut key)
+        .unwrap_or_else(|_| Fatal::SessionKey.panic());
+
+    let mut hmac = Hmac::<Sha256>::new_varkey(&key).unwrap_or_else(|_| Fatal::HmacKey.panic());
+    key.fill(0);
+    #[allow(clippy::cast_possible_truncation)] // a push holds at most 255 ids
+    let count = (ids.len() / SecureSSSRevocation::ID_SIZE) as u8;
+    hmac.update(&u16::from(dev_id).to_le_bytes());
+    hmac.update(&serial.to_le_bytes());
+    hmac.update(&[count]);
+    hmac.update(ids);
+    hmac.finalize().into_bytes().into()
+}
+
+/// Tags the serialised list of revoked ids for the SED of the given registration secret, as the
+/// SSS pushes it
+pub fn sign<'a>(
+    secret: &[u8; 64],
+    dev_id: Id,
+    serial: u32,
+    ids: &'a [u8],
+) -> SecureSSSRevocation<'a> {
+    SecureSSSRevocation {
+        dev_id,
+        op: SSSOp::Revoke,
+        serial,
+        ids,
+        tag: tag(secret, dev_id, serial, ids),
+    }
+}
+
+/// Whether the list pushed to this SED, whose registration secret is given, is authentic
+pub fn verify(secret: &[u8; 64], push: &SecureSSSRevocation) -> bool {
+    let expected = tag(secret, push.dev_id, push.serial, push.ids);
+    glitch::check(|| ct::eq(&expected, &push.tag))
+}
@@ -0,0 +1,162 @@
+//! The wrapping of the keys of a new epoch, which the SSS pushes to each registered SED
+//!
+//! Should the deployment replace its keys, the SSS raises their epoch and pushes the new keys to
+//! every SED registered with it as a [`SecureSSSRotation`], unsolicited, rather than waiting on
+//! each to register again. The keys are wrapped under a key derived from the registration secret
+//! of the SED which they are pushed to, which only that SED and the SSS hold:
+//!
+//! ```text
+//! okm = HKDF-Expand(HKDF-Extract(epoch, secret), "SCEWL rotate")
+//! wrapped = (aes_key || hmac_key) ^ okm[..80]
+//! tag = HMAC(okm[80..], dev_id || epoch || wrapped)
+//! ```
+//!
+//! As the epoch salts the derivation, no two epochs share a wrapping key, so the XOR is a one-time
+//! pad. The tag binds the keys to the SED and the epoch, so that a push cannot be replayed to
+//! another SED, nor under another epoch; that the epoch is higher than any taken up before is
+//! left to the [crypto handler](crate::crypto::Handler::rotate) (and, should the authentication
+//! handler be given one, to the [record](Epochs) of the highest epoch). Only HKDF and HMAC-SHA256 are
+//! used, so that the SSS may wrap keys with nothing but the Python standard library.
+
+use core::fmt::{Debug, Formatter, Result as FmtResult};
+use core::mem::size_of;
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+use crate::codec::secure::SecureSSSRotation;
+use crate::codec::{Id, SSSOp};
+use crate::cursor::WriteCursor;
+use crate::fatal::Fatal;
+use crate::{ct, glitch};
+
+/// The info label from which the wrapping key is expanded
+const ROTATE_LABEL: &[u8] = b"SCEWL rotate";
+
+/// The length of the wrapped keys, i.e. the AES key then the HMAC key
+const WRAPPED: usize = 16 + 64;
+
+/// The length of the authenticated input, i.e. `dev_id || epoch || wrapped`
+const AUTHENTICATED: usize = size_of::<u16>() + size_of::<u32>() + WRAPPED;
+
+/// The keys of an epoch, as pushed by the SSS
+///
+/// The keys are omitted from the `Debug` output, so that they never reach the log.
+#[derive(Copy, Clone)]
+pub struct Keys {
+    /// The epoch of the keys
+    pub epoch: u32,
+    /// The AES key
+    pub aes_key: [u8; 16],
+    /// The HMAC key
+    pub hmac_key: [u8; 64],
+}
+
+impl Debug for Keys {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Keys")
+            .field("epoch", &self.epoch)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The record of the highest epoch of keys which the SED has accepted, which survives a reset so
+/// that the SED never rolls back onto older keys; the firmware keeps it in flash with its
+/// `anti-rollback` feature
+pub trait Epochs: Sync {
+    /// Determines whether keys of the given epoch may be accepted, i.e. whether no higher epoch
+    /// has been recorded
+    fn accept(&self, epoch: u32) -> bool;
+
+    /// Records that keys of the given epoch were accepted, should it be higher than any yet
+    /// recorded
+    fn record(&self, epoch: u32);
+}
+
+impl Keys {
+    /// Overwrites the keys with zeroes, once they have been handed on
+    pub fn clear(&mut self) {
+        self.aes_key.fill(0);
+        self.hmac_key.fill(0);
+    }
+}
+
+/// Derives the pad and the tag key with which the keys of the epoch are wrapped for the SED
+fn derive(secret: &[u8; 64], epoch: u32) -> [u8; WRAPPED + 32] {
+    let mut okm = [0_u8; WRAPPED + 32];
+    Hkdf::<Sha256>::new(Some(&epoch.to_le_bytes()), secret)
+        .expand(ROTATE_LABEL, &mut okm)
+        .unwrap_or_else(|_| Fatal::SessionKey.panic());
+    okm
+}
+
+/// Computes the tag of the wrapped keys pushed to the SED, under the tag key
+fn tag(key: &[u8], dev_id: Id, epoch: u32, wrapped: &[u8; WRAPPED]) -> [u8; 32] {
+    let mut input = [0_u8; AUTHENTICATED];
+    WriteCursor::new(&mut input)
+        .write_u16(dev_id.into())
+        .write_u32(epoch)
+        .write(wrapped);
+
+    let mut hmac = Hmac::<Sha256>::new_varkey(key).unwrap_or_else(|_| Fatal::HmacKey.panic());
+    hmac.update(&input);
+    hmac.finalize().into_bytes().into()
+}
+
+/// Wraps the keys of the epoch for the SED of the given registration secret, as the SSS pushes
+/// them
+pub fn wrap(secret: &[u8; 64], dev_id: Id, keys: &Keys) -> SecureSSSRotation {
+    let mut okm = derive(secret, keys.epoch);
+    let (pad, key) = okm.split_at(WRAPPED);
+
+    let mut wrapped = [0_u8; WRAPPED];
+    WriteCursor::new(&mut wrapped)
+        .write(&keys.aes_key)
+        .write(&keys.hmac_key);
+    wrapped
+        .iter_mut()
+        .zip(pad)
+        .for_each(|(byte, pad)| *byte ^= pad);
+    let tag = tag(key, dev_id, keys.epoch, &wrapped);
+    okm.fill(0);
+
+    SecureSSSRotation {
+        dev_id,
+        op: SSSOp::Rotate,
+        epoch: keys.epoch,
+        wrapped,
+        tag,
+    }
+}
+
+/// Unwraps the keys pushed to this SED, whose registration secret is given, or `None` should the
+/// push not be authentic
+pub fn unwrap(secret: &[u8; 64], push: &SecureSSSRotation) -> Option<Keys> {
+    let mut okm = derive(secret, push.epoch);
+    let (pad, key) = okm.split_at(WRAPPED);
+
+    let expected = tag(key, push.dev_id, push.epoch, &push.wrapped);
+    if !glitch::check(|| ct::eq(&expected, &push.tag)) {
+        okm.fill(0);
+        return None;
+    }
+
+    let mut plain = push.wrapped;
+    plain
+        .iter_mut()
+        .zip(pad)
+        .for_each(|(byte, pad)| *byte ^= pad);
+    okm.fill(0);
+
+    let (aes_key, hmac_key) = plain.split_at(16);
+    let mut keys = Keys {
+        epoch: push.epoch,
+        aes_key: [0; 16],
+        hmac_key: [0; 64],
+    };
+    keys.aes_key.copy_from_slice(aes_key);
+    keys.hmac_key.copy_from_slice(hmac_key);
+    plain.fill(0);
+    Some(keys)
+}
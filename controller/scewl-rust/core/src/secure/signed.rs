@@ -0,0 +1,223 @@
+//! A crypto handler which signs broadcasts with a key unique to each SED, wrapping the handler
+//! selected on registration
+//!
+//! # Design
+//!
+//! The key shared by every SED of a deployment authenticates a broadcast as coming from _some_ SED
+//! of the deployment, but not from the one whose id is in its header: any SED may forge a broadcast
+//! from any other. Should the SSS enable
+//! [`CAP_BROADCAST_SIGS`](crate::codec::secure::CAP_BROADCAST_SIGS), it distributes at registration
+//! the seed of an Ed25519 key unique to the registering SED and the public keys of every other SED
+//! of the deployment (see [`SecureSSSSigningKeys`](crate::codec::secure::SecureSSSSigningKeys)),
+//! and this handler appends to each broadcast a signature by its source:
+//!
+//! ```text
+//! TRANSPORT
+//!  | b'SC'    ; header magic
+//!  | tgt_id   ; the broadcast id
+//!  | src_id   ; Source device's ID
+//!  | len      ; length of the remaining sections, including the signature
+//! FRAME
+//!  | ...      ; the verification and content segments of the inner handler
+//! SIGNATURE
+//!  | sig      ; Ed25519(FRAME), by the key of src_id
+//! ```
+//!
+//! The signature covers the frame as protected by the inner handler, whose verification segment
+//! authenticates the transport header, and binds the source by the public key which verifies it;
+//! a broadcast from a source without a provisioned key, or whose signature does not verify, is
+//! dropped as a [bad signature](Reason::BadSignature) before the inner handler decrypts it. The
+//! inner handler otherwise checks the frame exactly as it would without the signature, its length
+//! being that of the frame less the signature.
+//!
+//! As the signature covers the encrypted frame, which must be left intact until it is verified,
+//! signed broadcasts are not [streamed](crate::crypto::Handler::stream_block). Direct messages are
+//! neither signed nor affected, and a broadcast costs an Ed25519 signature to send and a
+//! verification to receive.
+
+use ed25519_compact::{KeyPair, PublicKey, Seed, Signature};
+use heapless::FnvIndexMap;
+
+use crate::codec::{Id, Message};
+use crate::crypto::Handler as CryptoHandler;
+use crate::deployment::PeerCapacity;
+use crate::diag::Reason;
+use crate::fatal::Fatal;
+use crate::glitch;
+use crate::scratch::Pool;
+use crate::{debug, warn};
+
+/// The length of the signature appended to each broadcast
+pub const SIGNATURE: usize = Signature::BYTES;
+
+/// The signing key of this SED and the public keys of the others, as provisioned by the SSS
+pub struct Keys {
+    /// The key with which this SED signs its broadcasts
+    pair: KeyPair,
+    /// The keys with which the broadcasts of every other SED are verified
+    peers: FnvIndexMap<Id, PublicKey, PeerCapacity>,
+}
+
+impl Keys {
+    /// Expands the signing key of this SED from its seed, with no public keys of other SEDs
+    pub fn new(seed: [u8; 32]) -> Self {
+        Self {
+            pair: KeyPair::from_seed(Seed::new(seed)),
+            peers: FnvIndexMap::new(),
+        }
+    }
+
+    /// The public key of this SED, with which the others verify its broadcasts
+    pub fn public_key(&self) -> [u8; 32] {
+        *self.pair.pk
+    }
+
+    /// Adds the public key of another SED, with which its broadcasts are verified
+    pub fn with_peer(mut self, id: Id, public: [u8; 32]) -> Self {
+        self.peers
+            .insert(id, PublicKey::new(public))
+            .unwrap_or_else(|_| Fatal::PeerTable.panic());
+        self
+    }
+}
+
+/// The signing crypto handler, which signs and verifies broadcasts around an inner handler
+pub struct Handler<H> {
+    /// The handler which protects every frame, signed or not
+    inner: H,
+    /// The signing keys, should the deployment enable signatures
+    keys: Option<Keys>,
+    /// Whether the frame being received is a signed broadcast, as found by the last verification
+    signed: bool,
+}
+
+impl<H> Handler<H> {
+    /// Wraps the inner handler, signing broadcasts should keys be given and passing every frame
+    /// straight through otherwise
+    pub fn new(inner: H, keys: Option<Keys>) -> Self {
+        Self {
+            inner,
+            keys,
+            signed: false,
+        }
+    }
+
+    /// Whether the message is signed, i.e. is a broadcast and the deployment enables signatures
+    fn signs(&self, msg: Message) -> bool {
+        self.keys.is_some() && msg.tgt_id == Id::Broadcast
+    }
+}
+
+/// The message as protected by the inner handler, i.e. less its signature
+fn unsigned(msg: Message) -> Message {
+    Message {
+        len: msg.len - SIGNATURE,
+        ..msg
+    }
+}
+
+impl<H: CryptoHandler> CryptoHandler for Handler<H> {
+    fn verify(&mut self, data: &[u8], msg: Message, scratch: &Pool) -> Result<(), Reason> {
+        self.signed = self.signs(msg);
+        if !self.signed {
+            return self.inner.verify(data, msg, scratch);
+        }
+
+        if msg.len < SIGNATURE + self.inner.verification_len() {
+            warn!("Broadcast too short to be signed; bad length: {}", msg.len);
+            return Err(Reason::BadLength);
+        }
+        self.inner.verify(data, unsigned(msg), scratch)
+    }
+
+    fn verification_len(&self) -> usize {
+        self.inner.verification_len()
+    }
+
+    fn content_offset(&self) -> usize {
+        self.inner.content_offset()
+    }
+
+    fn encrypt(&mut self, data: &mut [u8], msg: Message, scratch: &Pool) -> usize {
+        let len = self.inner.encrypt(data, msg, scratch);
+        let Some(keys) = self.keys.as_ref().filter(|_| msg.tgt_id == Id::Broadcast) else {
+            return len;
+        };
+
+        let sig = keys.pair.sk.sign(&data[..len], None);
+        data.get_mut(len..len + SIGNATURE)
+            .unwrap_or_else(|| Fatal::Buffer.panic())
+            .copy_from_slice(sig.as_ref());
+
+        debug!("Signed broadcast of {} bytes", len);
+        len + SIGNATURE
+    }
+
+    fn decrypt(&mut self, data: &mut [u8], msg: Message, scratch: &Pool) -> Result<usize, Reason> {
+        let Some(keys) = self.keys.as_ref().filter(|_| msg.tgt_id == Id::Broadcast) else {
+            return self.inner.decrypt(data, msg, scratch);
+        };
+        if msg.len < SIGNATURE || msg.len > data.len() {
+            return Err(Reason::BadLength);
+        }
+
+        let msg = unsigned(msg);
+        let Some(public) = keys.peers.get(&msg.src_id) else {
+            warn!("No public key for {:?}; dropping broadcast.", msg.src_id);
+            return Err(Reason::BadSignature);
+        };
+
+        // the signature is verified once, as it is costly; only its outcome is checked twice
+        let sig = Signature::from_slice(&data[msg.len..][..SIGNATURE])
+            .unwrap_or_else(|_| Fatal::Buffer.panic());
+        let verified = public.verify(&data[..msg.len], &sig).is_ok();
+        if !glitch::check(|| verified) {
+            warn!("Signature of {:?} not verified; dropping.", msg.src_id);
+            return Err(Reason::BadSignature);
+        }
+
+        self.inner.decrypt(data, msg, scratch)
+    }
+
+    fn sealed_len(&self, msg: Message, room: usize) -> usize {
+        let len = self.inner.sealed_len(msg, room);
+        if self.keys.is_some() && msg.tgt_id == Id::Broadcast {
+            len + SIGNATURE
+        } else {
+            len
+        }
+    }
+
+    fn stream_block(&self) -> usize {
+        self.inner.stream_block()
+    }
+
+    fn decrypt_received(&mut self, data: &mut [u8], received: usize) {
+        // the signature covers the ciphertext, so a signed broadcast is decrypted once it is
+        // verified, in full
+        if !self.signed {
+            self.inner.decrypt_received(data, received);
+        }
+    }
+
+    fn jitter(&mut self) -> u32 {
+        self.inner.jitter()
+    }
+
+    fn rekey(&mut self, peer: Id, secret: Option<&[u8; 32]>) {
+        self.inner.rekey(peer, secret);
+    }
+
+    fn rotate(&mut self, epoch: u32, aes_key: &[u8; 16], hmac_key: &[u8; 64]) -> bool {
+        self.inner.rotate(epoch, aes_key, hmac_key)
+    }
+
+    fn attest(&mut self, nonce: &[u8; 32], dev_id: Id) -> Option<(u32, [u8; 32])> {
+        self.inner.attest(nonce, dev_id)
+    }
+
+    fn erase(&mut self) {
+        self.inner.erase();
+        self.keys = None;
+    }
+}
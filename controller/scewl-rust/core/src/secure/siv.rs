@@ -0,0 +1,32 @@
+//! A crypto handler for the secure implementation which protects messages with AES-128-GCM-SIV,
+//! so that a repeated nonce does not break the confidentiality of the frames which bear it
+//!
+//! # Design
+//!
+//! The handler is selected on registration should the SSS enable
+//! [`CAP_AES_GCM_SIV`](crate::codec::secure::CAP_AES_GCM_SIV) for the deployment, which takes
+//! precedence over [`CAP_AES_GCM`](crate::codec::secure::CAP_AES_GCM). It is the [GCM
+//! handler](super::GcmHandler) in all but its AEAD: frames are laid out alike, the nonce, counter,
+//! and transport segment are authenticated alongside the content, and direct messages are keyed
+//! by the secrets agreed in [handshakes](super::Handshakes) alike.
+//!
+//! The nonces of either handler are drawn from a CSPRNG seeded by the SSS at registration, so a
+//! seed which is ever reused (e.g. should an SSS hand out the same seed again) repeats them. Under
+//! GCM, two frames which share a nonce reveal the XOR of their contents, and the tag's
+//! authentication key, with which any frame may then be forged. GCM-SIV (RFC 8452) instead derives
+//! the keys of each frame from its nonce, and the counter of the CTR keystream from the tag, which
+//! is computed over the additional data and the content: frames which share a nonce then only
+//! reveal whether they are identical, which the counter in their additional data rules out.
+//!
+//! The price is a second pass over the content, as the tag must be computed before the content is
+//! encrypted. A frame is decrypted before its tag is checked, rather than after; should the tag
+//! fail, the AEAD encrypts the content again, so that no plaintext is released, and the frame is
+//! left intact for the next key to be tried, as under GCM.
+
+use aes_gcm_siv::Aes128GcmSiv;
+
+use crate::secure::gcm;
+
+/// The GCM-SIV crypto handler, which performs encryption, decryption, and verification of
+/// messages
+pub type Handler = gcm::AeadHandler<Aes128GcmSiv>;
@@ -0,0 +1,206 @@
+//! The selection of the secure crypto handler on registration, by the capabilities which the SSS
+//! enables for the deployment
+//!
+//! Every SED of a deployment must protect its frames alike, so the SSS, rather than the build,
+//! selects between the [CBC handler](super::CryptoHandler), the [GCM handler](super::GcmHandler),
+//! and the [GCM-SIV handler](super::SivHandler): the last is used should the registration response
+//! enable [`CAP_AES_GCM_SIV`](crate::codec::secure::CAP_AES_GCM_SIV), the GCM handler should it
+//! enable [`CAP_AES_GCM`](crate::codec::secure::CAP_AES_GCM), and the CBC handler otherwise. Each
+//! is wrapped by the [signing handler](super::SignedHandler), which signs broadcasts should the SSS
+//! also enable [`CAP_BROADCAST_SIGS`](crate::codec::secure::CAP_BROADCAST_SIGS).
+
+use crate::codec::{Id, Message};
+#[cfg(feature = "dyn-handlers")]
+use crate::crypto::Slot;
+use crate::crypto::{Error, Handler as CryptoHandler};
+use crate::scratch::Pool;
+use crate::secure::{crypto, gcm, signed, siv, Checkpoints, Entropy};
+
+/// The secure crypto handler selected on registration
+#[allow(clippy::large_enum_variant)] // there is no heap to box a handler in, and only one is held
+pub enum Suite {
+    /// AES-128-CBC content with an HMAC-SHA256 verification segment
+    CbcHmac(crypto::Handler),
+    /// AES-128-GCM, should the SSS enable it
+    Gcm(gcm::Handler),
+    /// AES-128-GCM-SIV, should the SSS enable it
+    GcmSiv(siv::Handler),
+}
+
+impl Suite {
+    /// Persists the reservations of the selected handler's counters to the store, as by
+    /// [`with_checkpoints`](crypto::Handler::with_checkpoints)
+    #[must_use]
+    pub fn with_checkpoints(self, store: &'static dyn Checkpoints) -> Self {
+        match self {
+            Suite::CbcHmac(handler) => Suite::CbcHmac(handler.with_checkpoints(store)),
+            Suite::Gcm(handler) => Suite::Gcm(handler.with_checkpoints(store)),
+            Suite::GcmSiv(handler) => Suite::GcmSiv(handler.with_checkpoints(store)),
+        }
+    }
+
+    /// Reseeds the CSPRNG of the selected handler from the entropy source, as by
+    /// [`with_entropy`](crypto::Handler::with_entropy)
+    #[must_use]
+    pub fn with_entropy(self, source: &'static dyn Entropy) -> Self {
+        match self {
+            Suite::CbcHmac(handler) => Suite::CbcHmac(handler.with_entropy(source)),
+            Suite::Gcm(handler) => Suite::Gcm(handler.with_entropy(source)),
+            Suite::GcmSiv(handler) => Suite::GcmSiv(handler.with_entropy(source)),
+        }
+    }
+
+    /// Pads the frames of the selected handler to the buckets, as by
+    /// [`with_buckets`](crypto::Handler::with_buckets); only the CBC handler does so, as the AEAD
+    /// handlers carry no length of their own by which to strip the padding
+    #[must_use]
+    pub fn with_buckets(self, buckets: &'static [usize]) -> Self {
+        match self {
+            Suite::CbcHmac(handler) => Suite::CbcHmac(handler.with_buckets(buckets)),
+            suite => suite,
+        }
+    }
+
+    /// Marks the keys of the selected handler as those of the given epoch, as by
+    /// [`with_epoch`](crypto::Handler::with_epoch)
+    #[must_use]
+    pub fn with_epoch(self, epoch: u32) -> Self {
+        match self {
+            Suite::CbcHmac(handler) => Suite::CbcHmac(handler.with_epoch(epoch)),
+            Suite::Gcm(handler) => Suite::Gcm(handler.with_epoch(epoch)),
+            Suite::GcmSiv(handler) => Suite::GcmSiv(handler.with_epoch(epoch)),
+        }
+    }
+}
+
+impl CryptoHandler for Suite {
+    fn verify(&mut self, data: &[u8], msg: Message, scratch: &Pool) -> Result<(), Error> {
+        match self {
+            Suite::CbcHmac(handler) => handler.verify(data, msg, scratch),
+            Suite::Gcm(handler) => handler.verify(data, msg, scratch),
+            Suite::GcmSiv(handler) => handler.verify(data, msg, scratch),
+        }
+    }
+
+    fn verification_len(&self) -> usize {
+        match self {
+            Suite::CbcHmac(handler) => handler.verification_len(),
+            Suite::Gcm(handler) => handler.verification_len(),
+            Suite::GcmSiv(handler) => handler.verification_len(),
+        }
+    }
+
+    fn content_offset(&self) -> usize {
+        match self {
+            Suite::CbcHmac(handler) => handler.content_offset(),
+            Suite::Gcm(handler) => handler.content_offset(),
+            Suite::GcmSiv(handler) => handler.content_offset(),
+        }
+    }
+
+    fn encrypt(&mut self, data: &mut [u8], msg: Message, scratch: &Pool) -> usize {
+        match self {
+            Suite::CbcHmac(handler) => handler.encrypt(data, msg, scratch),
+            Suite::Gcm(handler) => handler.encrypt(data, msg, scratch),
+            Suite::GcmSiv(handler) => handler.encrypt(data, msg, scratch),
+        }
+    }
+
+    fn decrypt(&mut self, data: &mut [u8], msg: Message, scratch: &Pool) -> Result<usize, Error> {
+        match self {
+            Suite::CbcHmac(handler) => handler.decrypt(data, msg, scratch),
+            Suite::Gcm(handler) => handler.decrypt(data, msg, scratch),
+            Suite::GcmSiv(handler) => handler.decrypt(data, msg, scratch),
+        }
+    }
+
+    fn sealed_len(&self, msg: Message, room: usize) -> usize {
+        match self {
+            Suite::CbcHmac(handler) => handler.sealed_len(msg, room),
+            Suite::Gcm(handler) => handler.sealed_len(msg, room),
+            Suite::GcmSiv(handler) => handler.sealed_len(msg, room),
+        }
+    }
+
+    fn stream_block(&self) -> usize {
+        match self {
+            Suite::CbcHmac(handler) => handler.stream_block(),
+            Suite::Gcm(handler) => handler.stream_block(),
+            Suite::GcmSiv(handler) => handler.stream_block(),
+        }
+    }
+
+    fn decrypt_received(&mut self, data: &mut [u8], received: usize) {
+        match self {
+            Suite::CbcHmac(handler) => handler.decrypt_received(data, received),
+            Suite::Gcm(handler) => handler.decrypt_received(data, received),
+            Suite::GcmSiv(handler) => handler.decrypt_received(data, received),
+        }
+    }
+
+    fn jitter(&mut self) -> u32 {
+        match self {
+            Suite::CbcHmac(handler) => handler.jitter(),
+            Suite::Gcm(handler) => handler.jitter(),
+            Suite::GcmSiv(handler) => handler.jitter(),
+        }
+    }
+
+    fn rekey(&mut self, peer: Id, secret: Option<&[u8; 32]>) {
+        match self {
+            Suite::CbcHmac(handler) => handler.rekey(peer, secret),
+            Suite::Gcm(handler) => handler.rekey(peer, secret),
+            Suite::GcmSiv(handler) => handler.rekey(peer, secret),
+        }
+    }
+
+    fn rotate(&mut self, epoch: u32, aes_key: &[u8; 16], hmac_key: &[u8; 64]) -> bool {
+        match self {
+            Suite::CbcHmac(handler) => handler.rotate(epoch, aes_key, hmac_key),
+            Suite::Gcm(handler) => handler.rotate(epoch, aes_key, hmac_key),
+            Suite::GcmSiv(handler) => handler.rotate(epoch, aes_key, hmac_key),
+        }
+    }
+
+    fn attest(&mut self, nonce: &[u8; 32], dev_id: Id) -> Option<(u32, [u8; 32])> {
+        match self {
+            Suite::CbcHmac(handler) => handler.attest(nonce, dev_id),
+            Suite::Gcm(handler) => handler.attest(nonce, dev_id),
+            Suite::GcmSiv(handler) => handler.attest(nonce, dev_id),
+        }
+    }
+
+    fn erase(&mut self) {
+        match self {
+            Suite::CbcHmac(handler) => handler.erase(),
+            Suite::Gcm(handler) => handler.erase(),
+            Suite::GcmSiv(handler) => handler.erase(),
+        }
+    }
+}
+
+/// The crypto handler as held by a registered controller: the handler itself or, with the
+/// `dyn-handlers` feature, a reference to it as installed in its [slot](crate::crypto::Slot)
+#[cfg(not(feature = "dyn-handlers"))]
+pub type Registered = signed::Handler<Suite>;
+/// The crypto handler as held by a registered controller: the handler itself or, with the
+/// `dyn-handlers` feature, a reference to it as installed in its [slot](crate::crypto::Slot)
+#[cfg(feature = "dyn-handlers")]
+pub type Registered = &'static mut dyn CryptoHandler;
+
+/// Hands the handler instantiated on registration over to the controller, as it holds it
+#[cfg(not(feature = "dyn-handlers"))]
+pub fn register(handler: signed::Handler<Suite>) -> Registered {
+    handler
+}
+
+/// Hands the handler instantiated on registration over to the controller, as it holds it
+#[cfg(feature = "dyn-handlers")]
+pub fn register(handler: signed::Handler<Suite>) -> Registered {
+    /// The handler installed by the latest registration
+    static HANDLER: Slot<signed::Handler<Suite>> = Slot::new();
+
+    // SAFETY: this is only called by the authentication handlers upon a successful registration,
+    // upon which the controller replaces the handler it holds with the one returned
+    unsafe { HANDLER.install(handler) }
+}
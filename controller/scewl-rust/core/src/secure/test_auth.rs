@@ -0,0 +1,75 @@
+// for use in testing crypto w/o an SSS implementation; uses the default SSS implementation
+
+#![doc(hidden)]
+
+use crate::auth::{Error as AuthError, Handler as AuthHandler};
+use crate::controller::{Controller, Id, Message, SSSMessage, SSSOp};
+use crate::cursor::ReadCursor;
+use crate::secure::{register, CryptoHandler, Registered, SignedHandler, Suite};
+use crate::transport::{Transport, INTF};
+
+#[derive(Copy, Clone)]
+pub struct Handler;
+
+impl AuthHandler<Registered> for Handler {
+    fn sss_register<T: Transport>(
+        &mut self,
+        controller: &mut Controller<Self, Registered, T>,
+    ) -> Result<Registered, AuthError> {
+        let msg = SSSMessage {
+            dev_id: controller.id(),
+            op: SSSOp::Register,
+        };
+        let len = ReadCursor::new(&msg.to_bytes()).copy_to(controller.tx());
+
+        controller.send_tx(
+            INTF::SSS,
+            &Message {
+                src_id: controller.id(),
+                tgt_id: Id::SSS,
+                len,
+            },
+        )?;
+
+        let res = controller.read_sss(4)?;
+
+        controller.send_msg(INTF::CPU, &res)?;
+
+        if SSSMessage::from_bytes(controller.data()).op == SSSOp::Register {
+            let suite = Suite::CbcHmac(CryptoHandler::new([0_u8; 32], [0_u8; 16], [0_u8; 64]));
+            Ok(register(SignedHandler::new(suite, None)))
+        } else {
+            Err(AuthError::Refused)
+        }
+    }
+
+    fn sss_deregister<T: Transport>(
+        &mut self,
+        controller: &mut Controller<Self, Registered, T>,
+    ) -> Result<(), AuthError> {
+        let msg = SSSMessage {
+            dev_id: controller.id(),
+            op: SSSOp::Deregister,
+        };
+        let len = ReadCursor::new(&msg.to_bytes()).copy_to(controller.tx());
+
+        controller.send_tx(
+            INTF::SSS,
+            &Message {
+                src_id: controller.id(),
+                tgt_id: Id::SSS,
+                len,
+            },
+        )?;
+
+        let res = controller.read_sss(4)?;
+
+        controller.send_msg(INTF::CPU, &res)?;
+
+        if SSSMessage::from_bytes(controller.data()).op == SSSOp::Deregister {
+            Ok(())
+        } else {
+            Err(AuthError::Refused)
+        }
+    }
+}
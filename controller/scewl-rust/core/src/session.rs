@@ -0,0 +1,91 @@
+//! The lifetime of the keys distributed at registration, beyond which the controller fetches fresh
+//! ones before it sends anything more
+//!
+//! A long-running mission would otherwise send every frame under the keys of a single
+//! registration. The deployment may instead bound each session by the number of frames sent under
+//! its keys, by its age, or by both, as set by a [`Lifetime`]. Once either bound is exceeded, the
+//! [`Session`] expires, and the controller's run loop [rekeys](crate::codec::SSSOp::Rekey) with the
+//! SSS before it reads another message from the CPU; the session starts afresh with the keys of
+//! each registration or rekey. Should the rekey fail, the controller refuses to send to other SEDs
+//! under the expired keys, and tries again once [`RETRY`] has passed.
+//!
+//! Frames from other SEDs are still received while the session is expired, as the peers may not
+//! have rekeyed yet either.
+
+use core::time::Duration;
+
+use crate::time::{Clock, Instant};
+
+/// How long the controller waits to rekey again after a rekey of an expired session failed, so that
+/// an unreachable SSS does not stall the run loop on every pass
+pub const RETRY: Duration = Duration::from_secs(10);
+
+/// The bounds on a session, either of which expires it; a session without bounds never expires
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Lifetime {
+    /// The most frames which may be sent under the keys of a session, if there is a limit
+    pub sends: Option<u32>,
+    /// The longest time for which the keys of a session may be used, if there is a limit
+    pub age: Option<Duration>,
+}
+
+/// The use made of the keys of the current registration, as described in the [module
+/// documentation](self)
+pub struct Session<'a> {
+    /// The clock by which the age of the session is told
+    clock: &'a dyn Clock,
+    /// The bounds on the session
+    lifetime: Lifetime,
+    /// The instant at which the session started
+    start: Instant,
+    /// The number of frames sent since the session started
+    sends: u32,
+    /// The instant after which a failed rekey may be tried again, should one have failed
+    retry: Option<Instant>,
+}
+
+impl<'a> Session<'a> {
+    /// A session of the given lifetime starting now, as told by the clock
+    pub fn new(clock: &'a dyn Clock, lifetime: Lifetime) -> Self {
+        Session {
+            clock,
+            lifetime,
+            start: clock.now(),
+            sends: 0,
+            retry: None,
+        }
+    }
+
+    /// Starts the session afresh, as for the keys of a new registration or rekey
+    pub fn restart(&mut self) {
+        self.start = self.clock.now();
+        self.sends = 0;
+        self.retry = None;
+    }
+
+    /// Counts a frame sent under the keys of the session
+    pub fn record_send(&mut self) {
+        self.sends = self.sends.saturating_add(1);
+    }
+
+    /// Whether either bound of the session has been reached
+    pub fn expired(&self) -> bool {
+        let sent_out = self.lifetime.sends.is_some_and(|sends| self.sends >= sends);
+        let aged_out = self
+            .lifetime
+            .age
+            .is_some_and(|age| self.start.has_elapsed(age, self.clock.now()));
+        sent_out || aged_out
+    }
+
+    /// Whether the session has expired and a rekey is due, i.e. none has failed in the last
+    /// [`RETRY`]
+    pub fn due(&self) -> bool {
+        self.expired() && self.retry.is_none_or(|retry| self.clock.now() >= retry)
+    }
+
+    /// Puts off the next rekey by [`RETRY`], as a rekey of the expired session failed
+    pub fn defer(&mut self) {
+        self.retry = Some(self.clock.now() + RETRY);
+    }
+}
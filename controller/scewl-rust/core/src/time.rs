@@ -0,0 +1,153 @@
+//! A minimal notion of time for the controller, for use by timeouts, retransmissions, and session
+//! expiry
+//!
+//! The controller has no wall clock; time is only ever measured relative to boot, at a resolution
+//! of one millisecond. Anything time-dependent should take a [`Clock`] rather than reading a timer
+//! directly, so that its behaviour may be tested deterministically with a [`MockClock`] (behind the
+//! `mock-clock` feature).
+
+use core::ops::{Add, AddAssign};
+use core::time::Duration;
+
+#[cfg(feature = "mock-clock")]
+use core::cell::Cell;
+
+/// A point in time, measured in milliseconds since boot
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Default)]
+pub struct Instant {
+    /// Milliseconds since boot
+    millis: u64,
+}
+
+impl Instant {
+    /// The instant at which the controller booted
+    pub const BOOT: Instant = Instant { millis: 0 };
+
+    /// Instantiates an instant the given number of milliseconds after boot
+    pub const fn from_millis(millis: u64) -> Self {
+        Self { millis }
+    }
+
+    /// The number of milliseconds between boot and this instant
+    pub const fn as_millis(self) -> u64 {
+        self.millis
+    }
+
+    /// The time elapsed from `earlier` to this instant, or zero if `earlier` is later than this
+    pub fn saturating_duration_since(self, earlier: Instant) -> Duration {
+        Duration::from_millis(self.millis.saturating_sub(earlier.millis))
+    }
+
+    /// Determines whether at least `timeout` has passed between this instant and `now`
+    pub fn has_elapsed(self, timeout: Duration, now: Instant) -> bool {
+        now.saturating_duration_since(self) >= timeout
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    #[allow(clippy::cast_possible_truncation)] // u64 milliseconds outlast the hardware
+    fn add(self, rhs: Duration) -> Instant {
+        Instant {
+            millis: self.millis.saturating_add(rhs.as_millis() as u64),
+        }
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+/// A monotonic source of [instants](Instant)
+pub trait Clock {
+    /// The current instant; successive calls must never go backwards
+    fn now(&self) -> Instant;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+}
+
+/// An instant by which something must be done, as told by a clock
+///
+/// Blocking operations which could otherwise wait forever, such as reads from the SSS, give up
+/// once their deadline has passed (see [`Timed::until`](crate::transport::Timed::until)).
+#[derive(Copy, Clone)]
+pub struct Deadline<'a> {
+    /// The clock by which the deadline is told
+    clock: &'a dyn Clock,
+    /// The instant at which the deadline passes
+    at: Instant,
+}
+
+impl<'a> Deadline<'a> {
+    /// A deadline the given timeout from now, as told by the clock
+    pub fn after(clock: &'a dyn Clock, timeout: Duration) -> Self {
+        Self {
+            clock,
+            at: clock.now() + timeout,
+        }
+    }
+
+    /// Whether the deadline has passed
+    pub fn passed(&self) -> bool {
+        self.clock.now() >= self.at
+    }
+}
+
+/// A clock whose time only moves when the test says so
+///
+/// The clock is shared by reference between the test and the code under test (see the blanket
+/// implementation of [`Clock`] for references), so time may be advanced while that code holds it:
+///
+/// ```text
+/// let clock = MockClock::new();
+/// let lifetime = Lifetime { sends: None, age: Some(Duration::from_secs(30)) };
+/// let session = Session::new(&clock, lifetime);
+/// clock.advance(Duration::from_secs(30));
+/// assert!(session.expired());
+/// ```
+#[cfg(feature = "mock-clock")]
+#[derive(Debug, Default)]
+pub struct MockClock {
+    /// The current instant of this clock
+    now: Cell<Instant>,
+}
+
+#[cfg(feature = "mock-clock")]
+impl MockClock {
+    /// Instantiates a mock clock at boot
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves this clock forward by the given duration
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+
+    /// Moves this clock forward by the given number of milliseconds
+    pub fn advance_millis(&self, millis: u64) {
+        self.advance(Duration::from_millis(millis));
+    }
+
+    /// Moves this clock forward to the given instant
+    ///
+    /// This panics should the instant be in the past, as no real clock may go backwards.
+    pub fn set(&self, to: Instant) {
+        assert!(to >= self.now.get(), "mock clock moved backwards");
+        self.now.set(to);
+    }
+}
+
+#[cfg(feature = "mock-clock")]
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+}
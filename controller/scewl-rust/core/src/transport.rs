@@ -0,0 +1,219 @@
+//! The serial lines over which the controller talks to the CPU, the SSS, and the radio, as far as
+//! the controller needs to know of them
+//!
+//! The controller reads and writes each line through a [`Transport`], which the firmware
+//! implements over the memory-mapped UARTs of the lm3s6965 (see its `interface`
+//! module), and which tests may implement over whatever suits them. Each line
+//! is [named](INTF) by the peripheral it leads to, rather than by how it is reached, so that the
+//! controller may refer to the lines without also referring to their transports.
+//!
+//! Blocking reads wait forever, unless the transport is [`Timed`] with a deadline, after which a
+//! blocking read fails as though no more data were received.
+
+use core::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use core::hint;
+use core::result::Result as CoreResult;
+
+use crate::policy::Link;
+use crate::time::Deadline;
+use crate::transport::Error::{NoData, SomeData};
+
+/// The serial lines of the controller, which are named by the peripheral at their other end
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Copy, Clone)]
+pub enum INTF {
+    /// The line which connects to the CPU
+    CPU,
+    /// The line which connects to the SSS
+    SSS,
+    /// The line which connects to the radio
+    RAD,
+}
+
+impl From<INTF> for Link {
+    fn from(intf: INTF) -> Link {
+        match intf {
+            INTF::CPU => Link::Cpu,
+            INTF::SSS => Link::Sss,
+            INTF::RAD => Link::Radio,
+        }
+    }
+}
+
+/// Generic error type for transport operations
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// An unknown error occurred during the transport operation
+    Unknown,
+    /// No data was received during the read operation
+    NoData,
+    /// Only some data was received during the read operation
+    SomeData(usize),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Error::Unknown => write!(f, "unknown interface error"),
+            Error::NoData => write!(f, "no data available"),
+            Error::SomeData(n) => write!(f, "read ended after {n} bytes"),
+        }
+    }
+}
+
+/// Result type for transport operations
+pub type Result<T> = CoreResult<T, Error>;
+
+/// A serial line to one of the peripherals of the controller, which is read and written a byte at
+/// a time
+///
+/// The controller takes a clone of the transport of a line for each message it reads or writes, so
+/// clones must share the line rather than copy what is waiting on it.
+pub trait Transport: Clone {
+    /// The line which this transport leads to
+    fn named(&self) -> INTF;
+
+    /// Determines if data is available to be read
+    fn avail(&self) -> bool;
+
+    /// Reads a byte, optionally blocking until one is available
+    fn readb(&mut self, blocking: bool) -> Result<u8>;
+
+    /// Writes a byte -- always blocking
+    fn writeb(&mut self, data: u8);
+
+    /// Reads a buffer, blocking until it is filled
+    fn read(&mut self, buf: &mut [u8]) -> Result<()> {
+        for (i, b) in buf.iter_mut().enumerate() {
+            *b = self.readb(true).map_err(|_| SomeData(i))?;
+        }
+
+        Ok(())
+    }
+
+    /// Discards the given number of bytes, without blocking
+    fn discard(&mut self, n: usize) {
+        for _ in 0..n {
+            for _ in 0..10_000 {
+                // some delay for buffering
+                hint::spin_loop();
+            }
+
+            if self.readb(false).is_err() {
+                break; // fail fast
+            }
+        }
+    }
+
+    /// Discards bytes that match the supplied predicate; on success, returns the first byte that
+    /// does not match the predicate
+    fn discard_while(&mut self, predicate: impl Fn(u8) -> bool) -> Result<u8> {
+        loop {
+            let b = self.readb(true)?;
+
+            if !predicate(b) {
+                return Ok(b);
+            }
+        }
+    }
+
+    /// Writes a buffer -- always blocking
+    fn write(&mut self, buf: &[u8]) {
+        for b in buf {
+            self.writeb(*b);
+        }
+    }
+}
+
+/// The serial lines of a controller, one [transport](Transport) to each of its peripherals
+#[derive(Clone)]
+pub struct Links<T: Transport> {
+    /// The line to the CPU
+    pub cpu: T,
+    /// The line to the SSS
+    pub sss: T,
+    /// The line to the radio
+    pub rad: T,
+}
+
+impl<T: Transport> Links<T> {
+    /// The transport of the given line
+    pub fn get(&self, intf: INTF) -> &T {
+        match intf {
+            INTF::CPU => &self.cpu,
+            INTF::SSS => &self.sss,
+            INTF::RAD => &self.rad,
+        }
+    }
+
+    /// The transport of the given line, mutably
+    pub fn get_mut(&mut self, intf: INTF) -> &mut T {
+        match intf {
+            INTF::CPU => &mut self.cpu,
+            INTF::SSS => &mut self.sss,
+            INTF::RAD => &mut self.rad,
+        }
+    }
+}
+
+/// A transport whose blocking reads give up once a deadline has passed, should it be given one,
+/// rather than waiting forever for a peer which may never send
+#[derive(Clone)]
+pub struct Timed<'d, T: Transport> {
+    /// The transport read through
+    inner: T,
+    /// The deadline after which blocking reads give up, if any
+    deadline: Option<Deadline<'d>>,
+}
+
+impl<'d, T: Transport> Timed<'d, T> {
+    /// Reads through the given transport, without a deadline
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            deadline: None,
+        }
+    }
+
+    /// Gives this transport a deadline, after which a blocking read fails as though no more data
+    /// were received
+    pub fn until<'e>(self, deadline: Deadline<'e>) -> Timed<'e, T>
+    where
+        'd: 'e,
+    {
+        Timed {
+            inner: self.inner,
+            deadline: Some(deadline),
+        }
+    }
+}
+
+impl<T: Transport> Transport for Timed<'_, T> {
+    fn named(&self) -> INTF {
+        self.inner.named()
+    }
+
+    fn avail(&self) -> bool {
+        self.inner.avail()
+    }
+
+    fn readb(&mut self, blocking: bool) -> Result<u8> {
+        while blocking && !self.inner.avail() {
+            if self.deadline.is_some_and(|deadline| deadline.passed()) {
+                return Err(NoData);
+            }
+        }
+
+        self.inner.readb(false)
+    }
+
+    fn writeb(&mut self, data: u8) {
+        self.inner.writeb(data);
+    }
+}
+
+impl<T: Transport> Debug for Timed<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{:?}", self.named())
+    }
+}
@@ -0,0 +1,75 @@
+//! The authentication module for the trivial implementation of the security features for the
+//! controller, which emulates the original behaviour of `sss_register` and `sss_deregister` from
+//! the [original C implementation](https://github.com/mitre-cyber-academy/2021-ectf-insecure-example/blob/master/controller/controller.c)
+
+use crate::auth::{Error as AuthError, Handler as AuthHandler};
+use crate::controller::{Controller, Id, Message, SSSMessage, SSSOp};
+use crate::cursor::ReadCursor;
+use crate::transport::{Transport, INTF};
+use crate::trivial::{register, CryptoHandler, Registered};
+
+/// A trivial authentication handler which simply passes the CPU-formatted SSS message to the SSS
+#[derive(Copy, Clone)]
+pub struct Handler;
+
+impl AuthHandler<Registered> for Handler {
+    fn sss_register<T: Transport>(
+        &mut self,
+        controller: &mut Controller<Self, Registered, T>,
+    ) -> Result<Registered, AuthError> {
+        let msg = SSSMessage {
+            dev_id: controller.id(),
+            op: SSSOp::Register,
+        };
+        let len = ReadCursor::new(&msg.to_bytes()).copy_to(controller.tx());
+
+        controller.send_tx(
+            INTF::SSS,
+            &Message {
+                src_id: controller.id(),
+                tgt_id: Id::SSS,
+                len,
+            },
+        )?;
+
+        let res = controller.read_sss(4)?;
+
+        controller.send_msg(INTF::CPU, &res)?;
+
+        if SSSMessage::from_bytes(controller.data()).op == SSSOp::Register {
+            Ok(register(CryptoHandler))
+        } else {
+            Err(AuthError::Refused)
+        }
+    }
+
+    fn sss_deregister<T: Transport>(
+        &mut self,
+        controller: &mut Controller<Self, Registered, T>,
+    ) -> Result<(), AuthError> {
+        let msg = SSSMessage {
+            dev_id: controller.id(),
+            op: SSSOp::Deregister,
+        };
+        let len = ReadCursor::new(&msg.to_bytes()).copy_to(controller.tx());
+
+        controller.send_tx(
+            INTF::SSS,
+            &Message {
+                src_id: controller.id(),
+                tgt_id: Id::SSS,
+                len,
+            },
+        )?;
+
+        let res = controller.read_sss(4)?;
+
+        controller.send_msg(INTF::CPU, &res)?;
+
+        if SSSMessage::from_bytes(controller.data()).op == SSSOp::Deregister {
+            Ok(())
+        } else {
+            Err(AuthError::Refused)
+        }
+    }
+}
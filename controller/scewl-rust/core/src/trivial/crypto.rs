@@ -0,0 +1,60 @@
+//! The cryptography module for the trivial implementation of the security features for the
+//! controller -- which does absolutely nothing!
+
+use crate::codec::Message;
+use crate::crypto::Handler as CryptoHandler;
+#[cfg(feature = "dyn-handlers")]
+use crate::crypto::Slot;
+use crate::diag::Reason;
+use crate::scratch::Pool;
+
+/// A trivial crypto handler, which does nothing!
+pub struct Handler;
+
+impl CryptoHandler for Handler {
+    fn verify(&mut self, _: &[u8], _: Message, _: &Pool) -> Result<(), Reason> {
+        Ok(())
+    }
+
+    fn verification_len(&self) -> usize {
+        0
+    }
+
+    fn content_offset(&self) -> usize {
+        0
+    }
+
+    fn encrypt(&mut self, _: &mut [u8], msg: Message, _: &Pool) -> usize {
+        msg.len
+    }
+
+    fn decrypt(&mut self, _: &mut [u8], msg: Message, _: &Pool) -> Result<usize, Reason> {
+        Ok(msg.len)
+    }
+}
+
+/// The crypto handler as held by a registered controller: the handler itself or, with the
+/// `dyn-handlers` feature, a reference to it as installed in its [slot](crate::crypto::Slot)
+#[cfg(all(feature = "controller", not(feature = "dyn-handlers")))]
+pub type Registered = Handler;
+/// The crypto handler as held by a registered controller: the handler itself or, with the
+/// `dyn-handlers` feature, a reference to it as installed in its [slot](crate::crypto::Slot)
+#[cfg(feature = "dyn-handlers")]
+pub type Registered = &'static mut dyn CryptoHandler;
+
+/// Hands the handler instantiated on registration over to the controller, as it holds it
+#[cfg(all(feature = "controller", not(feature = "dyn-handlers")))]
+pub fn register(handler: Handler) -> Registered {
+    handler
+}
+
+/// Hands the handler instantiated on registration over to the controller, as it holds it
+#[cfg(feature = "dyn-handlers")]
+pub fn register(handler: Handler) -> Registered {
+    /// The handler installed by the latest registration
+    static SLOT: Slot<Handler> = Slot::new();
+
+    // SAFETY: this is only called by the authentication handlers upon a successful registration,
+    // upon which the controller replaces the handler it holds with the one returned
+    unsafe { SLOT.install(handler) }
+}
@@ -1,7 +1,11 @@
 //! A trivial implementation of controller security, as defined by the [original C implementation](https://github.com/mitre-cyber-academy/2021-ectf-insecure-example/blob/master/controller/controller.c)
 
+#[cfg(feature = "controller")]
 pub use auth::Handler as AuthHandler;
 pub use crypto::Handler as CryptoHandler;
+#[cfg(feature = "controller")]
+pub use crypto::{register, Registered};
 
+#[cfg(feature = "controller")]
 mod auth;
 mod crypto;
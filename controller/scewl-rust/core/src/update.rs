@@ -0,0 +1,185 @@
+//! Firmware updates over SCEWL, which the controller accepts in chunks from a designated source
+//! SED and applies at the next reboot should the image bear the deployment's signature
+//!
+//! An update is a sequence of direct messages from the source (see `[update]` in the deployment
+//! configuration), each of which is content of the [update kind](crate::content::Kind::Update),
+//! and is authenticated and decrypted like any other before the controller consumes it rather than
+//! forwarding it to the CPU. The CPU of the source has its controller send each frame in an
+//! [envelope](crate::content::Envelope), and data from a CPU is never taken for a frame, whatever
+//! it begins with. Each frame of the source is laid out as `op: u8 | ...`, and is one of:
+//!
+//!  - `Begin` (op 0): `size: u32 | signature: [u8; 64]`, which starts an update to an image of
+//!    the given size, bearing the given Ed25519 signature
+//!  - `Chunk` (op 1): `offset: u32 | data`, the next chunk of the image; chunks are sent in order,
+//!    and every chunk but the last is a whole number of words
+//!  - `Commit` (op 2), after the last chunk
+//!
+//! to each of which the controller answers the source with an [`Ack`] (op 3) of its [`Status`]
+//! and the offset of the next chunk it expects, so that the source may resume after a drop. The
+//! controller of the source hands each acknowledgement to its CPU in an envelope.
+//!
+//! The controller hands each frame to an [`Updater`], which the firmware implements over a staging
+//! region of the flash: with its `update` feature, build.rs reserves `update.staging` bytes at the
+//! bottom of the flash reserved for runtime use, and refuses an image which could not be staged
+//! within it. Its first page holds a header of the image's size and signature, and the image
+//! follows in the pages after. On commit, the updater verifies the signature over the staged image
+//! against the deployment's public key (`update.key`), marks the header committed, and the
+//! controller resets. At the next boot, before anything else, the firmware verifies the image
+//! again and copies it over the running firmware from RAM, as the core may not fetch from the flash
+//! while it is erased; the header is then marked swapped, and the controller resets into the new
+//! image.
+//!
+//! The signature is over the image alone, so an image may be staged by any source holding the
+//! deployment's keys but only ever applied should the holder of the update key have signed it.
+
+use core::convert::TryInto;
+use core::mem::size_of;
+
+use crate::codec::Id;
+use crate::cursor::{ReadCursor, WriteCursor};
+
+/// The op of a [`Frame::Begin`]
+const OP_BEGIN: u8 = 0;
+/// The op of a [`Frame::Chunk`]
+const OP_CHUNK: u8 = 1;
+/// The op of a [`Frame::Commit`]
+const OP_COMMIT: u8 = 2;
+/// The op of an [`Ack`]
+const OP_ACK: u8 = 3;
+
+/// A frame of an update, from the source
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Frame<'a> {
+    /// Starts an update to an image of the given size and signature, discarding any staged image
+    Begin {
+        /// The size of the image, in bytes
+        size: u32,
+        /// The Ed25519 signature over the image
+        signature: &'a [u8; 64],
+    },
+    /// The chunk of the image at the given offset
+    Chunk {
+        /// The offset of the chunk in the image
+        offset: u32,
+        /// The content of the chunk
+        data: &'a [u8],
+    },
+    /// Applies the update, once every chunk has been received
+    Commit,
+}
+
+impl<'a> Frame<'a> {
+    /// Deserialises a frame from the body of content of the update kind, should it be one
+    pub fn from_bytes(buf: &'a [u8]) -> Option<Self> {
+        let (&op, body) = buf.split_first()?;
+        match op {
+            OP_BEGIN if body.len() == size_of::<u32>() + 64 => Some(Frame::Begin {
+                size: ReadCursor::new(body).read_u32(),
+                signature: body[size_of::<u32>()..].try_into().ok()?,
+            }),
+            OP_CHUNK if body.len() > size_of::<u32>() => Some(Frame::Chunk {
+                offset: ReadCursor::new(body).read_u32(),
+                data: &body[size_of::<u32>()..],
+            }),
+            OP_COMMIT if body.is_empty() => Some(Frame::Commit),
+            _ => None,
+        }
+    }
+
+    /// Serialises this frame to the buffer, returning its length
+    pub fn to_bytes(&self, buf: &mut [u8]) -> usize {
+        let cur = WriteCursor::new(buf);
+        match *self {
+            Frame::Begin { size, signature } => {
+                cur.write(&[OP_BEGIN]).write_u32(size).write(signature);
+                1 + size_of::<u32>() + signature.len()
+            }
+            Frame::Chunk { offset, data } => {
+                cur.write(&[OP_CHUNK]).write_u32(offset).write(data);
+                1 + size_of::<u32>() + data.len()
+            }
+            Frame::Commit => {
+                cur.write(&[OP_COMMIT]);
+                1
+            }
+        }
+    }
+}
+
+/// The outcome of a frame of an update
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[repr(u8)]
+pub enum Status {
+    /// The frame was applied
+    Ok = 0,
+    /// The frame was out of order, or described an image which does not fit the staging region
+    Rejected = 1,
+    /// The staging region could not be erased or programmed
+    Flash = 2,
+    /// The staged image does not bear the deployment's signature
+    Signature = 3,
+}
+
+impl Status {
+    /// The status of the given byte, if any
+    fn from_u8(status: u8) -> Option<Self> {
+        match status {
+            0 => Some(Status::Ok),
+            1 => Some(Status::Rejected),
+            2 => Some(Status::Flash),
+            3 => Some(Status::Signature),
+            _ => None,
+        }
+    }
+}
+
+/// The answer of the controller to each frame of an update, sent back to the source
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Ack {
+    /// The outcome of the frame
+    pub status: Status,
+    /// The offset of the next chunk expected, or 0 should no update be in progress
+    pub next: u32,
+}
+
+impl Ack {
+    /// Serialises this acknowledgement to the buffer, returning its length
+    pub fn to_bytes(self, buf: &mut [u8]) -> usize {
+        WriteCursor::new(buf)
+            .write(&[OP_ACK, self.status as u8])
+            .write_u32(self.next);
+        Ack::size()
+    }
+
+    /// Deserialises an acknowledgement from the body of content of the update kind, should it be
+    /// one
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if buf.len() != Ack::size() || buf[0] != OP_ACK {
+            return None;
+        }
+
+        let mut cur = ReadCursor::new(&buf[1..]);
+        Some(Ack {
+            status: Status::from_u8(cur.read_literal::<1>()[0])?,
+            next: cur.read_u32(),
+        })
+    }
+
+    /// The constant size of an acknowledgement in its serialised form
+    pub const fn size() -> usize {
+        2 * size_of::<u8>() + size_of::<u32>()
+    }
+}
+
+/// The receiver of updates from the designated source, which stages each image for the firmware
+/// to apply at the next boot, as described in the [module documentation](self)
+pub trait Updater {
+    /// The SED from which updates are accepted
+    fn source(&self) -> Id;
+
+    /// Handles a frame from the source, returning the acknowledgement to send it
+    ///
+    /// Once a commit is acknowledged as [`Status::Ok`], the controller resets so that the image is
+    /// swapped in.
+    fn handle(&mut self, frame: Frame<'_>) -> Ack;
+}
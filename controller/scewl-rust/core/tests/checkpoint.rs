@@ -0,0 +1,274 @@
+//! Host tests that the secure handlers resume their counters from the
+//! [reservations](scewl::secure::Checkpoints) they persisted, as though the SED had reset
+//!
+//! Run with `cargo test -p scewl-core --test checkpoint --features std,crypto --target x86_64-unknown-linux-gnu`.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use scewl::codec::secure::VerificationSegment;
+use scewl::codec::{Id, Message, SCEWL_MAX_DATA_SZ};
+use scewl::crypto::Handler;
+use scewl::deployment::PEER_CAPACITY;
+use scewl::diag::Reason;
+use scewl::scratch::Pool;
+use scewl::secure::{Checkpoints, CryptoHandler, GcmHandler, Reserved, STRIDE};
+
+/// The keys which every handler is given
+const SEED: [u8; 32] = [7; 32];
+/// The AES key which every handler is given
+const AES_KEY: [u8; 16] = [3; 16];
+/// The HMAC key which every handler is given
+const HMAC_KEY: [u8; 64] = [5; 64];
+
+/// A store of reservations in RAM, which counts the reservations stored
+#[derive(Default)]
+struct Memory {
+    /// The reservation of each peer
+    peers: Mutex<HashMap<u16, Reserved>>,
+    /// The number of reservations stored
+    stores: AtomicUsize,
+}
+
+impl Memory {
+    /// An empty store, which outlives the handlers given it
+    fn leaked() -> &'static Memory {
+        Box::leak(Box::default())
+    }
+}
+
+impl Checkpoints for Memory {
+    fn load(&self, peer: Id) -> Option<Reserved> {
+        self.peers.lock().unwrap().get(&u16::from(peer)).copied()
+    }
+
+    fn store(&self, peer: Id, reserved: Reserved) {
+        self.peers.lock().unwrap().insert(u16::from(peer), reserved);
+        self.stores.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// A CBC handler, persisting its reservations to the store should one be given
+fn cbc(store: Option<&'static Memory>) -> CryptoHandler {
+    let handler = CryptoHandler::new(SEED, AES_KEY, HMAC_KEY);
+    match store {
+        Some(store) => handler.with_checkpoints(store),
+        None => handler,
+    }
+}
+
+/// A GCM handler, persisting its reservations to the store should one be given
+fn gcm(store: Option<&'static Memory>) -> GcmHandler {
+    let handler = GcmHandler::new(SEED, AES_KEY);
+    match store {
+        Some(store) => handler.with_checkpoints(store),
+        None => handler,
+    }
+}
+
+/// A zeroed data buffer
+fn buffer() -> Vec<u8> {
+    vec![0_u8; SCEWL_MAX_DATA_SZ]
+}
+
+/// Encrypts a direct message from SED 10 to SED 11, returning the frame and its message
+fn send(sender: &mut impl Handler, scratch: &Pool) -> (Vec<u8>, Message) {
+    send_from(sender, Id::Other(10), Id::Other(11), scratch)
+}
+
+/// Encrypts a message from the source to the target, returning the frame and its message
+fn send_from(
+    sender: &mut impl Handler,
+    src_id: Id,
+    tgt_id: Id,
+    scratch: &Pool,
+) -> (Vec<u8>, Message) {
+    let content = b"counted";
+    let mut data = buffer();
+    data[sender.content_offset()..][..content.len()].copy_from_slice(content);
+
+    let mut msg = Message {
+        tgt_id,
+        src_id,
+        len: content.len(),
+    };
+    msg.len = sender.encrypt(&mut data, msg, scratch);
+    (data, msg)
+}
+
+/// Verifies and decrypts the frame, returning why it was refused, if it was
+fn receive(
+    receiver: &mut impl Handler,
+    data: &[u8],
+    msg: Message,
+    scratch: &Pool,
+) -> Result<(), Reason> {
+    receiver.verify(data, msg, scratch)?;
+    let mut data = data.to_vec();
+    receiver.decrypt(&mut data, msg, scratch).map(drop)
+}
+
+/// A sender which resets resumes above the counters it sent before, which it would otherwise
+/// repeat, and its peer drop
+#[test]
+fn sender_resumes_above_its_reservation() {
+    let scratch = Pool::new();
+    let store = Memory::leaked();
+    let mut receiver = cbc(None);
+
+    let mut sender = cbc(Some(store));
+    for _ in 0..3 {
+        let (data, msg) = send(&mut sender, &scratch);
+        assert_eq!(receive(&mut receiver, &data, msg, &scratch), Ok(()));
+    }
+
+    let mut sender = cbc(Some(store));
+    let (data, msg) = send(&mut sender, &scratch);
+    assert_eq!(receive(&mut receiver, &data, msg, &scratch), Ok(()));
+
+    let mut sender = cbc(None);
+    let (data, msg) = send(&mut sender, &scratch);
+    assert_eq!(
+        receive(&mut receiver, &data, msg, &scratch),
+        Err(Reason::Replay)
+    );
+}
+
+/// A receiver which resets refuses the frames it had received before
+#[test]
+fn receiver_refuses_replays_after_reset() {
+    let scratch = Pool::new();
+    let store = Memory::leaked();
+    let mut sender = cbc(None);
+
+    let mut receiver = cbc(Some(store));
+    let (replayed, replayed_msg) = send(&mut sender, &scratch);
+    assert_eq!(
+        receive(&mut receiver, &replayed, replayed_msg, &scratch),
+        Ok(())
+    );
+
+    let mut receiver = cbc(Some(store));
+    assert_eq!(
+        receive(&mut receiver, &replayed, replayed_msg, &scratch),
+        Err(Reason::Replay)
+    );
+
+    let mut receiver = cbc(None);
+    assert_eq!(
+        receive(&mut receiver, &replayed, replayed_msg, &scratch),
+        Ok(())
+    );
+}
+
+/// The GCM handler resumes from its reservations alike
+#[test]
+fn gcm_resumes_from_its_reservations() {
+    let scratch = Pool::new();
+    let sender_store = Memory::leaked();
+    let receiver_store = Memory::leaked();
+
+    let mut sender = gcm(Some(sender_store));
+    let mut receiver = gcm(Some(receiver_store));
+    let (replayed, replayed_msg) = send(&mut sender, &scratch);
+    assert_eq!(
+        receive(&mut receiver, &replayed, replayed_msg, &scratch),
+        Ok(())
+    );
+
+    let mut sender = gcm(Some(sender_store));
+    let mut receiver = gcm(Some(receiver_store));
+    assert_eq!(
+        receive(&mut receiver, &replayed, replayed_msg, &scratch),
+        Err(Reason::Replay)
+    );
+    let (data, msg) = send(&mut sender, &scratch);
+    assert_eq!(receive(&mut receiver, &data, msg, &scratch), Ok(()));
+}
+
+/// The reservations are only stored once per [`STRIDE`] counters, by the sender and the receiver
+/// each
+#[test]
+fn reservations_are_stored_once_per_stride() {
+    let scratch = Pool::new();
+    let sender_store = Memory::leaked();
+    let receiver_store = Memory::leaked();
+    let mut sender = cbc(Some(sender_store));
+    let mut receiver = cbc(Some(receiver_store));
+
+    for _ in 0..2 * STRIDE {
+        let (data, msg) = send(&mut sender, &scratch);
+        assert_eq!(receive(&mut receiver, &data, msg, &scratch), Ok(()));
+    }
+
+    assert_eq!(sender_store.stores.load(Ordering::SeqCst), 2);
+    assert_eq!(receiver_store.stores.load(Ordering::SeqCst), 2);
+    let sent = sender_store.peers.lock().unwrap()[&11].sent;
+    let received = receiver_store.peers.lock().unwrap()[&10].received;
+    // the bound passed by the first counter, then by the first above it
+    assert_eq!(sent, (1 + STRIDE) + 1 + STRIDE);
+    assert_eq!(received, sent);
+}
+
+/// The broadcasts an SED sends are reserved under the broadcast id, apart from those it receives,
+/// which are reserved under their source; each resumes from its own bound after a reset
+#[test]
+fn broadcasts_are_reserved_apart() {
+    let scratch = Pool::new();
+    let store = Memory::leaked();
+    let mut peer = gcm(None);
+    let mut sed = gcm(Some(store));
+
+    let (heard, heard_msg) = send_from(&mut peer, Id::Other(10), Id::Broadcast, &scratch);
+    assert_eq!(receive(&mut sed, &heard, heard_msg, &scratch), Ok(()));
+    for _ in 0..3 {
+        let (data, msg) = send_from(&mut sed, Id::Other(11), Id::Broadcast, &scratch);
+        assert_eq!(receive(&mut peer, &data, msg, &scratch), Ok(()));
+    }
+
+    {
+        let peers = store.peers.lock().unwrap();
+        assert_eq!(peers[&u16::from(Id::Broadcast)].sent, 1 + STRIDE);
+        assert_eq!(peers[&10].brdcst, 1 + STRIDE);
+        assert!(!peers.contains_key(&11));
+    }
+
+    let mut sed = gcm(Some(store));
+    assert_eq!(
+        receive(&mut sed, &heard, heard_msg, &scratch),
+        Err(Reason::Replay)
+    );
+    let (data, msg) = send_from(&mut sed, Id::Other(11), Id::Broadcast, &scratch);
+    assert_eq!(receive(&mut peer, &data, msg, &scratch), Ok(()));
+}
+
+/// Once a sender has sent to, and a receiver received from, as many peers as their tables hold,
+/// each still counts those peers, rather than refusing to update the full table
+#[test]
+fn full_tables_still_count_the_peers_they_hold() {
+    let scratch = Pool::new();
+    let (mut sender, mut receiver) = (cbc(None), cbc(None));
+    // which sends as every peer, to the receiver only
+    let mut impostor = cbc(None);
+
+    for round in 0..2 {
+        for peer in 0..PEER_CAPACITY {
+            let id = Id::Other(0x100 + peer);
+            let (data, _) = send_from(&mut sender, Id::Other(10), id, &scratch);
+            assert_eq!(
+                VerificationSegment::from_bytes(&data).ctr,
+                round + 1,
+                "to {:?}",
+                id
+            );
+            let (data, msg) = send_from(&mut impostor, id, Id::Other(10), &scratch);
+            assert_eq!(receive(&mut receiver, &data, msg, &scratch), Ok(()));
+            // a replay is still refused once the table is full
+            assert_eq!(
+                receive(&mut receiver, &data, msg, &scratch),
+                Err(Reason::Replay)
+            );
+        }
+    }
+}
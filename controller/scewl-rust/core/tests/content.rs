@@ -0,0 +1,92 @@
+//! Host tests for the [content kinds](scewl::content)
+//!
+//! Run with `cargo test -p scewl-core --test content --features std,codec --target x86_64-unknown-linux-gnu`.
+
+use scewl::codec::Id;
+use scewl::content::{Envelope, Kind, MAGIC};
+
+/// Every kind round-trips through its byte, and content splits into its kind and body
+#[test]
+fn kinds() {
+    for kind in [
+        Kind::Data,
+        Kind::Hello,
+        Kind::Handshake,
+        Kind::Update,
+        Kind::Fragment,
+        Kind::Port,
+    ] {
+        assert_eq!(Kind::from_byte(kind.into()), Some(kind));
+    }
+    assert_eq!(u8::from(Kind::Data), 0);
+
+    assert_eq!(
+        Kind::split(b"\x01\x00\x02"),
+        Some((Kind::Hello, &b"\x00\x02"[..]))
+    );
+    assert_eq!(Kind::split(b"\x00"), Some((Kind::Data, &b""[..])));
+}
+
+/// Content with no kind, or a kind which this firmware does not know, is not split
+#[test]
+fn unknown() {
+    assert_eq!(Kind::split(b""), None);
+    assert_eq!(Kind::split(b"\xFFdata"), None);
+}
+
+/// Data is data whatever its body begins with, even the magics which once marked the controllers'
+/// own messages
+#[test]
+fn reserved_prefix() {
+    for content in [
+        &b"\x00\0MTU\x10\x00"[..],
+        b"\x00\0KEX\x00",
+        b"\x00UPDT\x02",
+        b"\x00\0FRG\x00\x00",
+        b"\x00\0PRT\x07",
+    ] {
+        let (kind, body) = Kind::split(content).unwrap();
+        assert_eq!(kind, Kind::Data);
+        assert_eq!(body, &content[1..]);
+    }
+}
+
+/// Envelopes round-trip through their serialised form, and carry only the kinds the CPU may send
+#[test]
+fn envelopes() {
+    let mut buf = [0_u8; 16];
+    let len = Envelope::write_header(&mut buf, Kind::Update, Id::Other(0x1234));
+    assert_eq!(&buf[..len], b"\0KND\x03\x34\x12");
+    buf[len..][..2].copy_from_slice(b"\x02\x00");
+
+    assert_eq!(
+        Envelope::from_bytes(&buf[..len + 2]),
+        Some(Envelope {
+            kind: Kind::Update,
+            peer: Id::Other(0x1234),
+            body: b"\x02\x00",
+        })
+    );
+    assert_eq!(Envelope::from_bytes(&buf[..len - 1]), None);
+    assert!(MAGIC.starts_with(b"\0"));
+
+    buf[MAGIC.len()] = 0xFF;
+    assert_eq!(Envelope::from_bytes(&buf[..len]), None);
+
+    assert!(Kind::Update.enveloped());
+    assert!(Kind::Port.enveloped());
+    assert!(!Kind::Data.enveloped());
+    assert!(!Kind::Hello.enveloped());
+    assert!(!Kind::Handshake.enveloped());
+    assert!(!Kind::Fragment.enveloped());
+}
+
+/// Handshakes and updates are only ever sent to a single peer
+#[test]
+fn broadcasts() {
+    for kind in [Kind::Data, Kind::Hello, Kind::Fragment, Kind::Port] {
+        assert!(kind.broadcasts());
+    }
+    assert!(!Kind::Handshake.broadcasts());
+    assert!(!Kind::Update.broadcasts());
+}
@@ -0,0 +1,29 @@
+//! Host test that the checked-in [C header](scewl::cpu_header) for the CPU is up to date
+//!
+//! Run with `cargo test -p scewl-core --test cpu_header --features std,codec --target x86_64-unknown-linux-gnu`,
+//! setting `SCEWL_BLESS=1` to rewrite the header instead.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use scewl::cpu_header;
+
+/// The header matches what the codec generates
+#[test]
+fn up_to_date() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../include/scewl_status.h");
+    let mut generated = String::new();
+    cpu_header::write(&mut generated).unwrap();
+
+    if env::var_os("SCEWL_BLESS").is_some() {
+        fs::write(&path, &generated).unwrap();
+    }
+
+    let checked_in = fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        checked_in == generated,
+        "{} is stale; rerun with SCEWL_BLESS=1 to regenerate it",
+        path.display()
+    );
+}
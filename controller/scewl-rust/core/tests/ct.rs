@@ -0,0 +1,37 @@
+//! Host tests for the [constant-time comparisons](scewl::ct)
+//!
+//! Run with `cargo test -p scewl-core --test ct --features std,codec --target x86_64-unknown-linux-gnu`.
+
+use scewl::codec::Id;
+use scewl::ct;
+
+/// Byte strings are equal exactly when every byte is, wherever the first difference lies
+#[test]
+fn byte_strings() {
+    let secret = [0x5A_u8; 64];
+    assert!(ct::eq(&secret, &secret));
+    assert!(ct::eq(&[], &[]));
+
+    for i in 0..secret.len() {
+        let mut other = secret;
+        other[i] ^= 0x80;
+        assert!(!ct::eq(&secret, &other));
+    }
+}
+
+/// Byte strings of different lengths are never equal, even should one prefix the other
+#[test]
+fn lengths() {
+    assert!(!ct::eq(b"secret", b"secre"));
+    assert!(!ct::eq(b"", b"s"));
+}
+
+/// Ids compare as their serialised forms do
+#[test]
+fn ids() {
+    assert!(Id::Other(10).ct_eq(Id::Other(10)));
+    assert!(!Id::Other(10).ct_eq(Id::Other(11)));
+    assert!(Id::SSS.ct_eq(Id::from(1)));
+    assert!(!Id::Broadcast.ct_eq(Id::FAA));
+    assert!(ct::eq_u16(0xBEEF, 0xBEEF) && !ct::eq_u16(0xBEEF, 0xBEEE));
+}
@@ -0,0 +1,42 @@
+//! Host tests for the [deadlines](scewl::time::Deadline) by which reads from the SSS give up
+//!
+//! Run with `cargo test -p scewl-core --test deadline --features std,mock-clock --target x86_64-unknown-linux-gnu`.
+
+use core::time::Duration;
+
+use scewl::time::{Deadline, MockClock};
+
+/// A deadline passes once its timeout has elapsed, and not a millisecond before
+#[test]
+fn passes_after_timeout() {
+    let clock = MockClock::new();
+    clock.advance_millis(1234);
+    let deadline = Deadline::after(&clock, Duration::from_millis(5000));
+    assert!(!deadline.passed());
+
+    clock.advance_millis(4999);
+    assert!(!deadline.passed());
+
+    clock.advance_millis(1);
+    assert!(deadline.passed());
+    clock.advance(Duration::from_secs(60));
+    assert!(deadline.passed(), "a deadline stays passed");
+}
+
+/// A deadline of no time at all has passed as soon as it is set
+#[test]
+fn zero_timeout_passes_at_once() {
+    let clock = MockClock::new();
+    assert!(Deadline::after(&clock, Duration::ZERO).passed());
+}
+
+/// Copies of a deadline share its instant, rather than each starting afresh
+#[test]
+fn copies_share_the_instant() {
+    let clock = MockClock::new();
+    let deadline = Deadline::after(&clock, Duration::from_millis(100));
+    clock.advance_millis(60);
+    let copy = deadline;
+    clock.advance_millis(40);
+    assert!(copy.passed());
+}
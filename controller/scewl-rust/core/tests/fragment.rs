@@ -0,0 +1,135 @@
+//! Host tests for the [fragmentation](scewl::fragment) of large messages
+//!
+//! Run with `cargo test -p scewl-core --test fragment --features std,codec --target x86_64-unknown-linux-gnu`.
+
+use scewl::codec::Id;
+use scewl::diag::Reason;
+use scewl::fragment::{Fragment, Reassembly, CAPACITY, CHUNK};
+
+/// The fragment of the given index of a message of the given number of fragments
+fn fragment(id: u16, index: u16, total: u16) -> Fragment {
+    Fragment { id, index, total }
+}
+
+/// Fragment headers round-trip, and only bodies long enough to hold one are taken for one
+#[test]
+fn header() {
+    let mut buf = [0_u8; 16];
+    let len = fragment(7, 1, 3).to_bytes(&mut buf);
+    assert_eq!(len, Fragment::size());
+    assert_eq!(&buf[..len], b"\x07\x00\x01\x00\x03\x00");
+    assert_eq!(Fragment::from_bytes(&buf), Some(fragment(7, 1, 3)));
+    assert_eq!(Fragment::from_bytes(&buf[..len]), Some(fragment(7, 1, 3)));
+
+    assert_eq!(Fragment::from_bytes(&buf[..len - 1]), None);
+    assert_eq!(Fragment::from_bytes(b""), None);
+
+    assert_eq!(Fragment::count(CHUNK), 1);
+    assert_eq!(Fragment::count(CHUNK + 1), 2);
+    assert_eq!(Fragment::count(CAPACITY), (CAPACITY / CHUNK) as u16);
+}
+
+/// The fragments of a message are gathered in order, and the message completed by the last
+#[test]
+fn reassembly() {
+    let mut buf = [0_u8; 16];
+    let mut reassembly = Reassembly::new(&mut buf);
+    let (src, tgt) = (Id::Other(10), Id::Other(11));
+
+    assert_eq!(
+        reassembly.accept(src, tgt, fragment(1, 0, 3), b"abc"),
+        Ok(None)
+    );
+    assert_eq!(
+        reassembly.accept(src, tgt, fragment(1, 1, 3), b"def"),
+        Ok(None)
+    );
+    assert_eq!(
+        reassembly.accept(src, tgt, fragment(1, 2, 3), b"gh"),
+        Ok(Some(8))
+    );
+    assert_eq!(reassembly.content(), b"abcdefgh");
+
+    reassembly.clear();
+    assert!(reassembly.content().is_empty());
+}
+
+/// A fragment out of sequence abandons its message, and the first of another abandons the last
+#[test]
+fn out_of_sequence() {
+    let mut buf = [0_u8; 16];
+    let mut reassembly = Reassembly::new(&mut buf);
+    let (src, tgt) = (Id::Other(10), Id::Other(11));
+
+    // a skipped fragment
+    assert_eq!(
+        reassembly.accept(src, tgt, fragment(1, 0, 3), b"abc"),
+        Ok(None)
+    );
+    assert_eq!(
+        reassembly.accept(src, tgt, fragment(1, 2, 3), b"gh"),
+        Err(Reason::Malformed)
+    );
+    assert!(reassembly.content().is_empty());
+
+    // a fragment of another message, source, or target
+    for (src_id, tgt_id, next) in [
+        (src, tgt, fragment(2, 1, 3)),
+        (Id::Other(12), tgt, fragment(1, 1, 3)),
+        (src, Id::Broadcast, fragment(1, 1, 3)),
+        (src, tgt, fragment(1, 1, 4)),
+    ] {
+        assert_eq!(
+            reassembly.accept(src, tgt, fragment(1, 0, 3), b"abc"),
+            Ok(None)
+        );
+        assert_eq!(
+            reassembly.accept(src_id, tgt_id, next, b"def"),
+            Err(Reason::Malformed)
+        );
+    }
+
+    // a message begun anew
+    assert_eq!(
+        reassembly.accept(src, tgt, fragment(1, 0, 2), b"abc"),
+        Ok(None)
+    );
+    assert_eq!(
+        reassembly.accept(src, tgt, fragment(2, 0, 2), b"xyz"),
+        Ok(None)
+    );
+    assert_eq!(
+        reassembly.accept(src, tgt, fragment(2, 1, 2), b"!"),
+        Ok(Some(4))
+    );
+    assert_eq!(reassembly.content(), b"xyz!");
+
+    // a fragment beyond the total, or after the message is complete
+    assert_eq!(
+        reassembly.accept(src, tgt, fragment(3, 0, 0), b""),
+        Err(Reason::Malformed)
+    );
+    assert_eq!(
+        reassembly.accept(src, tgt, fragment(2, 2, 2), b""),
+        Err(Reason::Malformed)
+    );
+}
+
+/// A message which would overflow the buffer is abandoned
+#[test]
+fn oversize() {
+    let mut buf = [0_u8; 8];
+    let mut reassembly = Reassembly::new(&mut buf);
+    let (src, tgt) = (Id::Other(10), Id::Broadcast);
+
+    assert_eq!(
+        reassembly.accept(src, tgt, fragment(1, 0, 2), b"abcdef"),
+        Ok(None)
+    );
+    assert_eq!(
+        reassembly.accept(src, tgt, fragment(1, 1, 2), b"ghi"),
+        Err(Reason::Oversize)
+    );
+    assert!(reassembly.content().is_empty());
+    assert_eq!(buf, [0; 8]);
+}
@@ -0,0 +1,47 @@
+//! Host tests for the [glitch hardening](scewl::glitch) of security-critical checks
+//!
+//! A fault is simulated with a condition whose evaluations disagree, as the two evaluations of a
+//! glitched check would.
+//!
+//! Run with `cargo test -p scewl-core --test glitch --features std --target x86_64-unknown-linux-gnu`.
+
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+
+use scewl::glitch;
+
+/// Conditions which hold or fail consistently are reported as such
+#[test]
+fn consistent_conditions() {
+    let ctr = 7_u64;
+    assert!(glitch::check(|| ctr >= 7));
+    assert!(!glitch::check(|| ctr >= 8));
+}
+
+/// Each check evaluates its condition exactly twice
+#[test]
+fn evaluated_twice() {
+    let evaluations = Cell::new(0);
+    glitch::check(|| {
+        evaluations.set(evaluations.get() + 1);
+        true
+    });
+    assert_eq!(evaluations.get(), 2);
+}
+
+/// A condition whose evaluations disagree halts the controller, whichever way it flipped
+#[test]
+fn disagreement_is_fatal() {
+    for first in [true, false] {
+        let flipped = Cell::new(first);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            glitch::check(|| {
+                let outcome = flipped.get();
+                flipped.set(!outcome);
+                outcome
+            })
+        }));
+        let message = *result.unwrap_err().downcast::<&str>().unwrap();
+        assert!(message.contains("glitched"), "{}", message);
+    }
+}
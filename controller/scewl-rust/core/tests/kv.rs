@@ -0,0 +1,176 @@
+//! Host tests for the [key-value store](scewl::kv), kept in an imitation of flash in RAM
+//!
+//! Run with `cargo test -p scewl-core --test kv --features std,codec --target x86_64-unknown-linux-gnu`.
+
+use scewl::kv::{Error, Medium, Store, MAX_VALUE, WORDS};
+
+/// Two pages of imitation flash, which may be made to fail after some number of writes
+#[derive(Clone)]
+struct Ram {
+    /// The words of both pages
+    words: Vec<u32>,
+    /// The number of times each page has been erased
+    erasures: [usize; 2],
+    /// The number of words which have been programmed
+    programmed: usize,
+    /// The number of further writes to succeed, as though the SED then reset
+    budget: Option<usize>,
+}
+
+impl Ram {
+    /// Two erased pages
+    fn new() -> Self {
+        Ram {
+            words: vec![0xFFFF_FFFF; 2 * WORDS],
+            erasures: [0; 2],
+            programmed: 0,
+            budget: None,
+        }
+    }
+
+    /// Spends one write of the budget, failing should there be none left
+    fn spend(&mut self) -> Result<(), Error> {
+        match &mut self.budget {
+            Some(0) => Err(Error::Medium),
+            Some(budget) => {
+                *budget -= 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Medium for Ram {
+    fn read(&self, page: usize, word: usize) -> u32 {
+        self.words[page * WORDS + word]
+    }
+
+    fn erase(&mut self, page: usize) -> Result<(), Error> {
+        self.spend()?;
+        self.erasures[page] += 1;
+        self.words[page * WORDS..][..WORDS].fill(0xFFFF_FFFF);
+        Ok(())
+    }
+
+    fn program(&mut self, page: usize, word: usize, value: u32) -> Result<(), Error> {
+        self.spend()?;
+        self.programmed += 1;
+        self.words[page * WORDS + word] &= value;
+        Ok(())
+    }
+}
+
+/// Reads the value of a key as a vector
+fn get(store: &Store<Ram>, key: u16) -> Option<Vec<u8>> {
+    let mut buf = [0; MAX_VALUE];
+    store.get(key, &mut buf).map(|len| buf[..len].to_vec())
+}
+
+/// A blank store holds nothing, and keeps what is set in it across being reopened
+#[test]
+fn set_and_reopen() {
+    let mut store = Store::open(Ram::new());
+    assert_eq!(get(&store, 1), None);
+
+    store.set(1, b"one").unwrap();
+    store.set(2, &[]).unwrap();
+    store.set(1, b"uno").unwrap();
+    assert_eq!(get(&store, 1).as_deref(), Some(&b"uno"[..]));
+    assert_eq!(get(&store, 2).as_deref(), Some(&[][..]));
+
+    let store = Store::open(store.into_medium());
+    assert_eq!(get(&store, 1).as_deref(), Some(&b"uno"[..]));
+    assert_eq!(get(&store, 2).as_deref(), Some(&[][..]));
+    assert_eq!(get(&store, 3), None);
+}
+
+/// Setting a key to its current value writes nothing
+#[test]
+fn unchanged() {
+    let mut store = Store::open(Ram::new());
+    store.set(1, b"value").unwrap();
+    let medium = store.into_medium();
+    let programmed = medium.programmed;
+
+    let mut store = Store::open(medium);
+    store.set(1, b"value").unwrap();
+    assert_eq!(store.into_medium().programmed, programmed);
+}
+
+/// Many writes alternate the pages, keeping the last value of every key
+#[test]
+fn compaction() {
+    let mut store = Store::open(Ram::new());
+    store.set(7, b"constant").unwrap();
+    for counter in 0_u32..2000 {
+        store.set(1, &counter.to_le_bytes()).unwrap();
+        store.set(2, &(!counter).to_le_bytes()).unwrap();
+    }
+
+    let store = Store::open(store.into_medium());
+    assert_eq!(get(&store, 1), Some(1999_u32.to_le_bytes().to_vec()));
+    assert_eq!(get(&store, 2), Some((!1999_u32).to_le_bytes().to_vec()));
+    assert_eq!(get(&store, 7).as_deref(), Some(&b"constant"[..]));
+
+    let erasures = store.into_medium().erasures;
+    assert!(erasures[0] > 10 && erasures[0].abs_diff(erasures[1]) <= 1);
+}
+
+/// A reset at any point of a write leaves either the old or the new value
+#[test]
+fn torn_writes() {
+    let mut store = Store::open(Ram::new());
+    store.set(1, &[0xAA; MAX_VALUE]).unwrap();
+    for _ in 0..12 {
+        store.set(2, &[0x55; MAX_VALUE]).unwrap();
+        store.set(2, &[0x5A; MAX_VALUE]).unwrap();
+    }
+    let medium = store.into_medium();
+
+    // enough writes to straddle a compaction
+    for budget in 0..WORDS {
+        let mut torn = medium.clone();
+        torn.budget = Some(budget);
+        let mut store = Store::open(torn);
+        for _ in 0..4 {
+            let _ = store.set(1, &[0xBB; MAX_VALUE]);
+            let _ = store.set(1, &[0xCC; MAX_VALUE]);
+        }
+
+        let mut medium = store.into_medium();
+        medium.budget = None;
+        let mut store = Store::open(medium);
+        let value = get(&store, 1).unwrap();
+        assert!(
+            [0xAA, 0xBB, 0xCC]
+                .iter()
+                .any(|&byte| value == [byte; MAX_VALUE]),
+            "{:?}",
+            value
+        );
+        assert_eq!(get(&store, 2), Some(vec![0x5A; MAX_VALUE]));
+
+        // the store remains writable after the reset
+        store.set(1, b"after").unwrap();
+        assert_eq!(get(&store, 1).as_deref(), Some(&b"after"[..]));
+    }
+}
+
+/// Reserved keys and oversized values are refused, as is more live data than fits a page
+#[test]
+fn limits() {
+    let mut store = Store::open(Ram::new());
+    assert_eq!(store.set(u16::MAX, b""), Err(Error::Invalid));
+    assert_eq!(store.set(1, &[0; MAX_VALUE + 1]), Err(Error::Invalid));
+
+    let mut key = 0;
+    let full = loop {
+        match store.set(key, &[0; MAX_VALUE]) {
+            Ok(()) => key += 1,
+            Err(err) => break err,
+        }
+    };
+    assert_eq!(full, Error::Full);
+    assert_eq!(usize::from(key), (WORDS - 2) / (2 + MAX_VALUE / 4));
+}
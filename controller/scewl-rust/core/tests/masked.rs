@@ -0,0 +1,55 @@
+//! Host tests for the [masked registration secret](scewl::masked)
+//!
+//! Run with `cargo test -p scewl-core --test masked --features std --target x86_64-unknown-linux-gnu`.
+
+use scewl::masked::Masked;
+
+/// The secret under test
+static SECRET: [u8; 64] = [0x5a; 64];
+
+/// A mask of the secret under test
+static MASK: [u8; 64] = {
+    let mut mask = [0; 64];
+    let mut i = 0;
+    while i < 64 {
+        mask[i] = (i * 37 + 11) as u8;
+        i += 1;
+    }
+    mask
+};
+
+/// The XOR of the secret under test and its mask, as build.rs would compile it in
+static MASKED: [u8; 64] = {
+    let mut masked = [0; 64];
+    let mut i = 0;
+    while i < 64 {
+        masked[i] = SECRET[i] ^ MASK[i];
+        i += 1;
+    }
+    masked
+};
+
+/// The shares recombine to the secret, though neither is the secret itself
+#[test]
+fn unmasks() {
+    let secret = Masked::new(&MASK, &MASKED);
+    assert_ne!(MASKED, SECRET);
+    assert_eq!(*secret.unmask(), SECRET);
+    assert_eq!(secret.share(), &MASKED);
+}
+
+/// A secret stored as it is, as provisioning records store it, unmasks to itself
+#[test]
+fn plain_unmasks_to_itself() {
+    let secret = Masked::plain(&SECRET);
+    assert_eq!(*secret.unmask(), SECRET);
+    assert_eq!(secret.share(), &SECRET);
+}
+
+/// Each unmasking is a copy of its own, so dropping one leaves the stored shares intact
+#[test]
+fn unmasking_leaves_the_shares() {
+    let secret = Masked::new(&MASK, &MASKED);
+    drop(secret.unmask());
+    assert_eq!(*secret.unmask(), SECRET);
+}
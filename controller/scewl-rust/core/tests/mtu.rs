@@ -0,0 +1,54 @@
+//! Host tests for the [MTU negotiation](scewl::mtu)
+//!
+//! Run with `cargo test -p scewl-core --test mtu --features std,codec --target x86_64-unknown-linux-gnu`.
+
+use scewl::codec::Id;
+use scewl::deployment::PEER_CAPACITY;
+use scewl::mtu::{Hello, Peers, DEFAULT};
+
+/// Hellos round-trip, and only bodies of exactly their size are taken for one
+#[test]
+fn hello() {
+    let mut buf = [0_u8; 16];
+    let len = Hello { mtu: 0x0200 }.to_bytes(&mut buf);
+    assert_eq!(len, Hello::size());
+    assert_eq!(&buf[..len], b"\x00\x02");
+    assert_eq!(Hello::from_bytes(&buf[..len]), Some(Hello { mtu: 0x0200 }));
+
+    assert_eq!(Hello::from_bytes(&buf[..len + 1]), None);
+    assert_eq!(Hello::from_bytes(&buf[..len - 1]), None);
+    assert_eq!(Hello::from_bytes(b"HELLO!"), None);
+}
+
+/// Peers keep the default MTU until they say hello, and the last hello of each counts
+#[test]
+fn peers() {
+    let mut peers = Peers::default();
+    assert_eq!(peers.mtu(Id::Other(11)), DEFAULT);
+
+    assert!(peers.record(Id::Other(11), 512));
+    assert!(peers.record(Id::Other(12), 1024));
+    assert!(peers.record(Id::Other(11), 768));
+    assert_eq!(peers.mtu(Id::Other(11)), 768);
+    assert_eq!(peers.mtu(Id::Other(12)), 1024);
+    assert_eq!(peers.mtu(Id::Other(13)), DEFAULT);
+
+    peers.clear();
+    assert_eq!(peers.mtu(Id::Other(11)), DEFAULT);
+}
+
+/// Peers beyond the capacity of the table keep the default MTU, without displacing the others
+#[test]
+fn capacity() {
+    let mut peers = Peers::default();
+    for id in 0..PEER_CAPACITY {
+        assert!(peers.record(Id::Other(100 + id), 300));
+    }
+    assert!(!peers.record(Id::Other(99), 300));
+    assert_eq!(peers.mtu(Id::Other(99)), DEFAULT);
+    assert_eq!(peers.mtu(Id::Other(100)), 300);
+
+    // a peer already recorded may still update its MTU
+    assert!(peers.record(Id::Other(100), 400));
+    assert_eq!(peers.mtu(Id::Other(100)), 400);
+}
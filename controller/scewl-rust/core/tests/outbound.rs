@@ -0,0 +1,88 @@
+//! Host tests for the [outbound queue](scewl::outbound) of frames for the radio
+//!
+//! Run with `cargo test -p scewl-core --test outbound --features std,codec --target x86_64-unknown-linux-gnu`.
+
+use scewl::codec::Id;
+use scewl::outbound::{Class, Outbound};
+
+/// Writes the whole of the front frame out of the queue, in bursts of the given size
+fn drain(queue: &mut Outbound, burst: usize) -> Option<Vec<u8>> {
+    let mut frame = Vec::new();
+    loop {
+        let rest = queue.front()?;
+        let n = rest.len().min(burst);
+        frame.extend_from_slice(&rest[..n]);
+        let done = n == rest.len();
+        queue.consume(n);
+        if done {
+            return Some(frame);
+        }
+    }
+}
+
+/// Frames are classed by their target
+#[test]
+fn class() {
+    assert_eq!(Class::of(Id::FAA), Class::Faa);
+    assert_eq!(Class::of(Id::Other(10)), Class::Direct);
+    assert_eq!(Class::of(Id::Broadcast), Class::Broadcast);
+    assert!(Class::Faa < Class::Direct && Class::Direct < Class::Broadcast);
+}
+
+/// Frames are written out whole, the highest class first, and in order of arrival within a class
+#[test]
+fn priority() {
+    let mut buf = [0_u8; 64];
+    let mut queue = Outbound::new(&mut buf);
+    assert!(queue.is_empty());
+    assert_eq!(queue.front(), None);
+
+    assert!(queue.push(Class::Broadcast, &[b"bc", b"ast"]));
+    assert!(queue.push(Class::Direct, &[b"one"]));
+    assert!(queue.push(Class::Faa, &[b"faa"]));
+    assert!(queue.push(Class::Direct, &[b"two"]));
+
+    assert_eq!(drain(&mut queue, 2).as_deref(), Some(&b"faa"[..]));
+    assert_eq!(drain(&mut queue, 2).as_deref(), Some(&b"one"[..]));
+    assert_eq!(drain(&mut queue, 2).as_deref(), Some(&b"two"[..]));
+    assert_eq!(drain(&mut queue, 2).as_deref(), Some(&b"bcast"[..]));
+    assert!(queue.is_empty());
+    assert_eq!(buf, [0; 64]);
+}
+
+/// A frame once begun is finished before any other, however urgent
+#[test]
+fn in_flight() {
+    let mut buf = [0_u8; 64];
+    let mut queue = Outbound::new(&mut buf);
+
+    assert!(queue.push(Class::Broadcast, &[b"broadcast"]));
+    assert_eq!(queue.front(), Some(&b"broadcast"[..]));
+    queue.consume(5);
+
+    assert!(queue.push(Class::Faa, &[b"faa"]));
+    assert_eq!(queue.front(), Some(&b"cast"[..]));
+    queue.consume(4);
+    assert_eq!(drain(&mut queue, 64).as_deref(), Some(&b"faa"[..]));
+    assert!(queue.is_empty());
+}
+
+/// A frame is refused when there is no room for it, and fits once the queue has been written out
+#[test]
+fn overflow() {
+    let mut buf = [0_u8; 16];
+    let mut queue = Outbound::new(&mut buf);
+
+    assert!(queue.fits(13));
+    assert!(!queue.fits(14));
+    assert!(!queue.push(Class::Faa, &[&[0xAA; 14]]));
+    assert!(queue.is_empty());
+
+    assert!(queue.push(Class::Direct, &[&[1; 8]]));
+    assert!(!queue.fits(8));
+    assert!(!queue.push(Class::Faa, &[&[2; 8]]));
+
+    assert_eq!(drain(&mut queue, 3), Some(vec![1; 8]));
+    assert!(queue.push(Class::Faa, &[&[2; 8]]));
+    assert_eq!(drain(&mut queue, 3), Some(vec![2; 8]));
+}
@@ -0,0 +1,269 @@
+//! Host tests for the [frame policy](scewl::policy)
+//!
+//! Run with `cargo test -p scewl-core --test policy --features std,codec,mock-clock --target x86_64-unknown-linux-gnu`.
+
+use scewl::codec::{Id, MessageHeader};
+use scewl::diag::Reason;
+use scewl::policy::{Link, Policy, ALLOWED, BUCKETS, REVOKED};
+use scewl::time::MockClock;
+
+/// The id of the controller under test
+const ID: Id = Id::Other(10);
+
+/// A header from the given source to the given target, of the given length
+fn header(src_id: Id, tgt_id: Id, len: u16) -> MessageHeader {
+    MessageHeader {
+        tgt_id,
+        src_id,
+        len,
+    }
+}
+
+/// Frames of the expected shape are admitted on each link
+#[test]
+fn admits() {
+    let mut policy = Policy::new(ID);
+    let cases = [
+        (Link::Cpu, header(ID, Id::SSS, 4)),
+        (Link::Cpu, header(ID, Id::Broadcast, 100)),
+        (Link::Cpu, header(ID, Id::Other(11), 100)),
+        (Link::Sss, header(Id::SSS, ID, 4)),
+        (Link::Radio, header(Id::FAA, ID, 100)),
+        (Link::Radio, header(Id::Other(11), Id::Broadcast, 100)),
+    ];
+    for (link, hdr) in &cases {
+        assert_eq!(
+            policy.admit(*link, hdr, 100),
+            Ok(()),
+            "{:?} {:?}",
+            link,
+            hdr
+        );
+    }
+}
+
+/// Reserved ids are refused where they may not appear, as are non-canonical ids
+#[test]
+fn reserved() {
+    let mut policy = Policy::new(ID);
+    let cases = [
+        (Link::Radio, header(Id::Broadcast, ID, 0)),
+        (Link::Radio, header(Id::SSS, ID, 0)),
+        (Link::Radio, header(Id::Other(11), Id::SSS, 0)),
+        (Link::Radio, header(Id::Other(2), ID, 0)),
+        (Link::Cpu, header(ID, Id::Other(0), 0)),
+        (Link::Sss, header(Id::Other(1), ID, 0)),
+    ];
+    for (link, hdr) in &cases {
+        assert_eq!(
+            policy.admit(*link, hdr, 100),
+            Err(Reason::Reserved),
+            "{:?} {:?}",
+            link,
+            hdr
+        );
+    }
+}
+
+/// Only the CPU may send as this controller, and the CPU only as it
+#[test]
+fn spoofing() {
+    let mut policy = Policy::new(ID);
+    assert_eq!(
+        policy.admit(Link::Cpu, &header(Id::Other(11), Id::Broadcast, 0), 100),
+        Err(Reason::CpuSpoofed)
+    );
+    assert_eq!(
+        policy.admit(Link::Radio, &header(ID, Id::Broadcast, 0), 100),
+        Err(Reason::SelfSpoofed)
+    );
+    assert_eq!(
+        policy.admit(Link::Sss, &header(ID, ID, 0), 100),
+        Err(Reason::SelfSpoofed)
+    );
+}
+
+/// Frames longer than the room for their body are refused
+#[test]
+fn oversize() {
+    let mut policy = Policy::new(ID);
+    let hdr = header(Id::FAA, ID, 101);
+    assert_eq!(policy.admit(Link::Radio, &hdr, 100), Err(Reason::Oversize));
+    assert_eq!(policy.admit(Link::Radio, &hdr, 101), Ok(()));
+}
+
+/// Each source on the radio is limited to its rate, refilled continuously, independently of the
+/// others, while the CPU is not limited at all
+#[test]
+fn rate_limit() {
+    let clock = MockClock::new();
+    let mut policy = Policy::new(ID).with_rate_limit(&clock, 3, 100);
+    let noisy = header(Id::Other(11), ID, 0);
+    let quiet = header(Id::Other(12), ID, 0);
+
+    for _ in 0..3 {
+        assert_eq!(policy.admit(Link::Radio, &noisy, 0), Ok(()));
+    }
+    assert_eq!(
+        policy.admit(Link::Radio, &noisy, 0),
+        Err(Reason::RateLimited)
+    );
+    assert_eq!(policy.admit(Link::Radio, &quiet, 0), Ok(()));
+    for _ in 0..10 {
+        assert_eq!(
+            policy.admit(Link::Cpu, &header(ID, Id::Other(11), 0), 0),
+            Ok(())
+        );
+    }
+
+    // a third of a second buys another frame at three a second
+    clock.advance_millis(333);
+    assert_eq!(
+        policy.admit(Link::Radio, &noisy, 0),
+        Err(Reason::RateLimited)
+    );
+    clock.advance_millis(1);
+    assert_eq!(policy.admit(Link::Radio, &noisy, 0), Ok(()));
+    assert_eq!(
+        policy.admit(Link::Radio, &noisy, 0),
+        Err(Reason::RateLimited)
+    );
+
+    // a long quiet spell refills the bucket to a second's worth, and no more
+    clock.advance_millis(10_000);
+    for _ in 0..3 {
+        assert_eq!(policy.admit(Link::Radio, &noisy, 0), Ok(()));
+    }
+    assert_eq!(
+        policy.admit(Link::Radio, &noisy, 0),
+        Err(Reason::RateLimited)
+    );
+
+    // sources in the same bucket share its budget
+    clock.advance_millis(1000);
+    let sharing = header(Id::Other(11 + BUCKETS as u16), ID, 0);
+    assert_eq!(policy.admit(Link::Radio, &sharing, 0), Ok(()));
+    assert_eq!(policy.admit(Link::Radio, &sharing, 0), Ok(()));
+    assert_eq!(policy.admit(Link::Radio, &noisy, 0), Ok(()));
+    assert_eq!(
+        policy.admit(Link::Radio, &noisy, 0),
+        Err(Reason::RateLimited)
+    );
+}
+
+/// Every source on the radio together is limited to the global rate, however many ids a flood
+/// cycles through, and frames refused by a source's own limit are not charged to it
+#[test]
+fn global_rate_limit() {
+    let clock = MockClock::new();
+    let mut policy = Policy::new(ID).with_rate_limit(&clock, 2, 5);
+    let from = |n: u16| header(Id::Other(20 + n), ID, 0);
+
+    assert_eq!(policy.admit(Link::Radio, &from(0), 0), Ok(()));
+    assert_eq!(policy.admit(Link::Radio, &from(0), 0), Ok(()));
+    assert_eq!(
+        policy.admit(Link::Radio, &from(0), 0),
+        Err(Reason::RateLimited)
+    );
+    assert_eq!(policy.admit(Link::Radio, &from(1), 0), Ok(()));
+    assert_eq!(policy.admit(Link::Radio, &from(1), 0), Ok(()));
+    assert_eq!(policy.admit(Link::Radio, &from(2), 0), Ok(()));
+    assert_eq!(policy.admit(Link::Radio, &from(2), 0), Err(Reason::Flood));
+    assert_eq!(policy.admit(Link::Radio, &from(3), 0), Err(Reason::Flood));
+    assert_eq!(
+        policy.admit(Link::Cpu, &header(ID, Id::Other(11), 0), 0),
+        Ok(())
+    );
+
+    // a fifth of a second buys another frame at five a second, for whichever source comes first
+    clock.advance_millis(200);
+    assert_eq!(policy.admit(Link::Radio, &from(3), 0), Ok(()));
+    assert_eq!(policy.admit(Link::Radio, &from(4), 0), Err(Reason::Flood));
+}
+
+/// Sources revoked by the SSS are dropped on the radio only, and only lists of a higher serial and
+/// within the capacity are taken up
+#[test]
+fn revoked() {
+    let mut policy = Policy::new(ID);
+    let revoked = header(Id::Other(11), ID, 0);
+    let other = header(Id::Other(12), ID, 0);
+
+    assert!(policy.revoke(1, [Id::Other(11)].iter().copied()));
+    assert_eq!(policy.admit(Link::Radio, &revoked, 0), Err(Reason::Revoked));
+    assert_eq!(policy.admit(Link::Radio, &other, 0), Ok(()));
+    assert_eq!(
+        policy.admit(Link::Cpu, &header(ID, Id::Other(11), 0), 0),
+        Ok(())
+    );
+
+    // a stale list is refused, so a revoked source cannot be reinstated by a replay
+    assert!(!policy.revoke(1, [].iter().copied()));
+    assert!(!policy.revoke(0, [].iter().copied()));
+    assert_eq!(policy.admit(Link::Radio, &revoked, 0), Err(Reason::Revoked));
+
+    // a longer list is refused whole, leaving the last in place
+    let many: Vec<_> = (0..=REVOKED as u16).map(|id| Id::Other(100 + id)).collect();
+    assert!(!policy.revoke(2, many.iter().copied()));
+    assert!(policy.is_revoked(Id::Other(11)));
+    assert!(policy.revoke(2, many[..REVOKED].iter().copied()));
+    assert!(!policy.is_revoked(Id::Other(11)));
+    assert!(policy.is_revoked(Id::Other(100 + REVOKED as u16 - 1)));
+
+    // the list is forgotten at (de)registration, when any serial is taken up once more
+    policy.clear_revoked();
+    assert!(!policy.is_revoked(Id::Other(100)));
+    assert!(policy.revoke(0, [Id::Other(12)].iter().copied()));
+    assert_eq!(policy.admit(Link::Radio, &other, 0), Err(Reason::Revoked));
+}
+
+/// SEDs outside the allowlist provisioned by the SSS are dropped on the radio only, whereas the
+/// FAA and broadcasts always pass, and a longer allowlist is refused whole
+#[test]
+fn allowlist() {
+    let mut policy = Policy::new(ID);
+    let allowed = header(Id::Other(11), ID, 0);
+    let other = header(Id::Other(12), ID, 0);
+    let faa = header(Id::FAA, ID, 0);
+
+    // every SED is allowed until the SSS provisions an allowlist
+    assert!(policy.is_allowed(Id::Other(12)));
+    assert_eq!(policy.admit(Link::Radio, &other, 0), Ok(()));
+
+    assert!(policy.allow([Id::Other(11)].iter().copied()));
+    assert_eq!(policy.admit(Link::Radio, &allowed, 0), Ok(()));
+    assert_eq!(
+        policy.admit(Link::Radio, &other, 0),
+        Err(Reason::NotAllowed)
+    );
+    assert_eq!(policy.admit(Link::Radio, &faa, 0), Ok(()));
+    assert_eq!(
+        policy.admit(Link::Radio, &header(Id::Other(11), Id::Broadcast, 0), 0),
+        Ok(())
+    );
+    assert_eq!(
+        policy.admit(Link::Cpu, &header(ID, Id::Other(12), 0), 0),
+        Ok(())
+    );
+    assert!(!policy.is_allowed(Id::Other(12)));
+    assert!(policy.is_allowed(Id::Broadcast));
+    assert!(policy.is_allowed(Id::FAA));
+
+    // an empty allowlist cuts the SED off from every other SED
+    assert!(policy.allow([].iter().copied()));
+    assert_eq!(
+        policy.admit(Link::Radio, &allowed, 0),
+        Err(Reason::NotAllowed)
+    );
+
+    // a longer allowlist is refused whole, leaving the last in place
+    let many: Vec<_> = (0..=ALLOWED as u16).map(|id| Id::Other(100 + id)).collect();
+    assert!(!policy.allow(many.iter().copied()));
+    assert!(!policy.is_allowed(Id::Other(100)));
+    assert!(policy.allow(many[..ALLOWED].iter().copied()));
+    assert!(policy.is_allowed(Id::Other(100)));
+
+    // the allowlist is lifted on deregistration
+    policy.allow_all();
+    assert_eq!(policy.admit(Link::Radio, &other, 0), Ok(()));
+}
@@ -0,0 +1,56 @@
+//! Host tests for the [logical ports](scewl::port)
+//!
+//! Run with `cargo test -p scewl-core --test port --features std,codec --target x86_64-unknown-linux-gnu`.
+
+use scewl::port::{self, Control, Ports, HEADER_SIZE, MAGIC};
+
+/// Port headers round-trip, and need not be followed by any data
+#[test]
+fn header() {
+    let mut buf = [0_u8; 16];
+    let len = port::write_header(&mut buf, 7);
+    assert_eq!(len, HEADER_SIZE);
+    assert_eq!(&buf[..len], b"\x07");
+    buf[len..len + 2].copy_from_slice(b"hi");
+
+    assert_eq!(port::port_of(&buf[..len]), Some(7));
+    assert_eq!(port::port_of(&buf[..len + 2]), Some(7));
+    assert_eq!(port::port_of(&buf[..len - 1]), None);
+    assert!(MAGIC.starts_with(b"\0"));
+}
+
+/// Control messages round-trip, and only decode with a known op and exactly their size
+#[test]
+fn control() {
+    let mut buf = [0_u8; 16];
+    for control in [Control::Open(0), Control::Close(0xFF)] {
+        let len = control.to_bytes(&mut buf);
+        assert_eq!(len, Control::size());
+        assert_eq!(Control::from_bytes(&buf[..len]), Some(control));
+        assert_eq!(Control::from_bytes(&buf[..len + 1]), None);
+        assert_eq!(Control::from_bytes(&buf[..len - 1]), None);
+    }
+    assert_eq!(
+        Control::from_bytes(b"\0PRT\x01\x09"),
+        Some(Control::Open(9))
+    );
+    assert_eq!(Control::from_bytes(b"\0PRT\x02\x09"), None);
+}
+
+/// Ports start closed, and open and close as the CPU requests
+#[test]
+fn ports() {
+    let mut ports = Ports::default();
+    assert!((0..=u8::MAX).all(|port| !ports.is_open(port)));
+
+    ports.apply(Control::Open(31));
+    ports.apply(Control::Open(32));
+    ports.open(u8::MAX);
+    assert!(ports.is_open(31) && ports.is_open(32) && ports.is_open(u8::MAX));
+    assert!(!ports.is_open(30) && !ports.is_open(33));
+
+    ports.apply(Control::Close(32));
+    assert!(!ports.is_open(32) && ports.is_open(31));
+    ports.close(u8::MAX);
+    assert!(!ports.is_open(u8::MAX));
+}
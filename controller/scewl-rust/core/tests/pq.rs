@@ -0,0 +1,180 @@
+//! Host tests of the [Keccak sponges](scewl::secure::pq::keccak) and
+//! [ML-KEM-512](scewl::secure::pq::kem) against known answers
+//!
+//! The SHA-3 and SHAKE answers are those of FIPS 202 (as published in NIST's examples, and as
+//! Python's `hashlib` computes them), for an empty input, `"abc"`, and the 1600-bit message of
+//! `0xA3` bytes which spans more than one block at every rate. The ML-KEM-512 answers were computed
+//! by OpenSSL 3.5 from the seeds and the randomness below:
+//!
+//! ```text
+//! openssl genpkey -algorithm ML-KEM-512 -pkeyopt hexseed:<d || z> -out dk.pem
+//! openssl pkeyutl -encap -inkey ek.pem -pubin -pkeyopt hexikme:<m> -out ct -secret ss
+//! openssl pkeyutl -decap -inkey dk.pem -in ct -out ss
+//! ```
+//!
+//! The keys and the ciphertext are compared by their SHA-256 digests, rather than spelt out whole.
+//!
+//! Run with `cargo test -p scewl-core --test pq --features std,pq --target x86_64-unknown-linux-gnu`.
+
+use std::convert::TryFrom;
+
+use sha2::{Digest, Sha256};
+
+use scewl::secure::pq::keccak::{self, Sponge};
+use scewl::secure::pq::kem;
+
+/// The 1600-bit message of FIPS 202's examples
+const A3: [u8; 200] = [0xA3; 200];
+
+/// The bytes of the given hex string
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// The seed `d` of key generation: the bytes 0 to 31
+fn d() -> [u8; 32] {
+    core::array::from_fn(|i| u8::try_from(i).unwrap())
+}
+
+/// The seed `z` of implicit rejection: the bytes 32 to 63
+fn z() -> [u8; 32] {
+    core::array::from_fn(|i| u8::try_from(32 + i).unwrap())
+}
+
+/// The randomness `m` of encapsulation: the bytes 64 to 95
+fn m() -> [u8; 32] {
+    core::array::from_fn(|i| u8::try_from(64 + i).unwrap())
+}
+
+/// SHA3-256 matches FIPS 202, whether its input is absorbed whole or in parts
+#[test]
+fn sha3_256() {
+    assert_eq!(
+        keccak::sha3_256(&[]).to_vec(),
+        hex("a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a")
+    );
+    assert_eq!(
+        keccak::sha3_256(&[b"abc"]).to_vec(),
+        hex("3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532")
+    );
+
+    let expected = hex("79f38adec5c20307a98ef76e8324afbfd46cfd81b22e3973c65fa1bd9de31787");
+    assert_eq!(keccak::sha3_256(&[&A3]).to_vec(), expected);
+    for split in [1, 135, 136, 137, 199] {
+        let (head, tail) = A3.split_at(split);
+        assert_eq!(keccak::sha3_256(&[head, tail]).to_vec(), expected);
+    }
+}
+
+/// SHA3-512 matches FIPS 202
+#[test]
+fn sha3_512() {
+    assert_eq!(
+        keccak::sha3_512(&[b"abc"]).to_vec(),
+        hex(concat!(
+            "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e",
+            "10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0",
+        ))
+    );
+    assert_eq!(
+        keccak::sha3_512(&[&A3]).to_vec(),
+        hex(concat!(
+            "e76dfad22084a8b1467fcf2ffa58361bec7628edf5f3fdc0e4805dc48caeeca8",
+            "1b7c13c30adf52a3659584739a2df46be589c51ca1a4a8416df6545a1ce8ba00",
+        ))
+    );
+}
+
+/// SHAKE128 matches FIPS 202, including far past the first block squeezed
+#[test]
+fn shake128() {
+    let mut out = [0_u8; 512];
+    Sponge::shake128().squeeze(&mut out[..32]);
+    assert_eq!(
+        out[..32].to_vec(),
+        hex("7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26")
+    );
+
+    Sponge::shake128().absorb(&A3).squeeze(&mut out);
+    assert_eq!(
+        out[480..].to_vec(),
+        hex("44c9fb359fd56ac0a9a75a743cff6862f17d7259ab075216c0699511643b6439")
+    );
+
+    // squeezed a little at a time, as ML-KEM samples its matrix
+    let mut sponge = Sponge::shake128();
+    sponge.absorb(&A3);
+    let mut pieces = [0_u8; 512];
+    for piece in pieces.chunks_mut(keccak::SHAKE128_RATE / 2 + 1) {
+        sponge.squeeze(piece);
+    }
+    assert_eq!(pieces, out);
+}
+
+/// SHAKE256 matches FIPS 202, including far past the first block squeezed
+#[test]
+fn shake256() {
+    let mut out = [0_u8; 512];
+    keccak::shake256(&[], &mut out[..32]);
+    assert_eq!(
+        out[..32].to_vec(),
+        hex("46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762f")
+    );
+
+    keccak::shake256(&[&A3], &mut out);
+    assert_eq!(
+        out[480..].to_vec(),
+        hex("6a1a9d7846436e4dca5728b6f760eef0ca92bf0be5615e96959d767197a0beeb")
+    );
+}
+
+/// Key generation, encapsulation, and decapsulation agree with OpenSSL from the same seeds and
+/// randomness, as does the implicit rejection of a tampered ciphertext
+#[test]
+fn ml_kem_512() {
+    let mut ek = [0_u8; kem::ENCAPSULATION_KEY];
+    let mut dk = [0_u8; kem::DECAPSULATION_KEY];
+    kem::keypair(&d(), &z(), &mut ek, &mut dk);
+    assert_eq!(
+        Sha256::digest(&ek).to_vec(),
+        hex("3ae268dccc5456ac0d0f9b39257dc48fe081383b97c400512d712b739762daee")
+    );
+    assert_eq!(
+        Sha256::digest(&dk).to_vec(),
+        hex("17fb29b8c4baf74fb81eea15ffd583b3e37f5a5b8dcf6db96c72c3b3751d6f17")
+    );
+
+    let mut ct = [0_u8; kem::CIPHERTEXT];
+    let shared = kem::encapsulate(&ek, &m(), &mut ct).unwrap();
+    assert_eq!(
+        Sha256::digest(&ct).to_vec(),
+        hex("81efe667826848514dcae46fc10cfd34f7b95ed6900e094f727c9e7cccc34df2")
+    );
+    let expected = hex("14cace3e48771b316676afad2cfcfe8488daaa4fad954e57236caa3f24a42cf7");
+    assert_eq!(shared.to_vec(), expected);
+    assert_eq!(kem::decapsulate(&dk, &ct).to_vec(), expected);
+
+    ct[0] ^= 1;
+    assert_eq!(
+        kem::decapsulate(&dk, &ct).to_vec(),
+        hex("32ee1fb3f7bd2915218e9c1b2d0d2da88f0edce6804278bab3a6123c5bb64fc4")
+    );
+}
+
+/// An encapsulation key with a coefficient of at least q is refused, as FIPS 203 requires
+#[test]
+fn ml_kem_512_modulus_check() {
+    let mut ek = [0_u8; kem::ENCAPSULATION_KEY];
+    let mut dk = [0_u8; kem::DECAPSULATION_KEY];
+    kem::keypair(&d(), &z(), &mut ek, &mut dk);
+
+    // the first coefficient, of 12 bits, set to 4095
+    ek[0] = 0xFF;
+    ek[1] |= 0x0F;
+    let mut ct = [0_u8; kem::CIPHERTEXT];
+    assert!(!kem::well_formed(&ek));
+    assert_eq!(kem::encapsulate(&ek, &m(), &mut ct), None);
+}
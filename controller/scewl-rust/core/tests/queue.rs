@@ -0,0 +1,108 @@
+//! Host-threaded stress tests for the [interrupt queue](scewl::queue)
+//!
+//! On the target, the producer runs in an interrupt which may preempt the consumer at any
+//! instruction; here, the two run on separate threads so that every interleaving of their index
+//! loads and stores is eventually exercised. Small capacities are used so that the indices wrap
+//! around the ring many times over.
+//!
+//! Run with `cargo test -p scewl-core --test queue --features std --target x86_64-unknown-linux-gnu`.
+
+use std::thread;
+
+use scewl::queue::Queue;
+
+/// The number of values pushed by each test
+const COUNT: u32 = 1_000_000;
+
+/// Pushes `0..COUNT` from one thread while popping from another, returning what was popped and the
+/// number of overflows the consumer observed beyond those of the sentinel
+///
+/// When `lossless`, the producer spins on a full queue instead of dropping values, as a
+/// well-behaved main loop would need to; otherwise, it behaves as an interrupt, which never waits.
+fn hammer<const N: usize>(lossless: bool) -> (Vec<u32>, u32) {
+    let queue: &'static mut Queue<u32, N> = Box::leak(Box::new(Queue::new()));
+    let (mut producer, mut consumer) = queue.split();
+
+    thread::scope(|s| {
+        let producer = s.spawn(move || {
+            for i in 0..COUNT {
+                while lossless && producer.is_full() {
+                    thread::yield_now();
+                }
+                producer.push(i);
+            }
+            // a sentinel, which is always delivered, returning the failed attempts to deliver it
+            let mut retries = 0;
+            while !producer.push(u32::MAX) {
+                retries += 1;
+                thread::yield_now();
+            }
+            retries
+        });
+
+        let mut popped = Vec::new();
+        let mut overflows = 0;
+        loop {
+            match consumer.pop() {
+                Some(u32::MAX) => break,
+                Some(i) => popped.push(i),
+                None => thread::yield_now(),
+            }
+            overflows += consumer.take_overflows();
+        }
+        overflows += consumer.take_overflows();
+        assert!(consumer.is_empty());
+
+        (popped, overflows - producer.join().unwrap())
+    })
+}
+
+/// Values are never lost, duplicated, or reordered while the queue has room
+#[test]
+fn lossless_in_order() {
+    for (popped, overflows) in [hammer::<1>(true), hammer::<4>(true), hammer::<64>(true)] {
+        assert_eq!(overflows, 0);
+        assert_eq!(popped.len(), COUNT as usize);
+        assert!(popped.iter().copied().eq(0..COUNT));
+    }
+}
+
+/// Every value pushed is either popped in order or counted as an overflow, never both
+#[test]
+fn overflow_accounting() {
+    for (popped, overflows) in [
+        hammer::<2>(false),
+        hammer::<16>(false),
+        hammer::<256>(false),
+    ] {
+        assert_eq!(popped.len() as u32 + overflows, COUNT);
+        assert!(
+            popped.windows(2).all(|w| w[0] < w[1]),
+            "values reordered or duplicated"
+        );
+        assert!(popped.iter().all(|&i| i < COUNT));
+    }
+}
+
+/// The indices wrap around the ring exactly at its capacity, without a thread in sight
+#[test]
+fn wrap_around() {
+    let mut queue = Queue::<u8, 4>::new();
+    let (mut producer, mut consumer) = queue.split();
+
+    for round in 0..10_u8 {
+        for i in 0..4 {
+            assert!(producer.push(round * 4 + i));
+        }
+        assert!(producer.is_full());
+        assert!(!producer.push(0xFF));
+        assert_eq!(consumer.len(), 4);
+
+        for i in 0..4 {
+            assert_eq!(consumer.pop(), Some(round * 4 + i));
+        }
+        assert_eq!(consumer.pop(), None);
+        assert_eq!(consumer.take_overflows(), 1);
+        assert_eq!(consumer.take_overflows(), 0);
+    }
+}
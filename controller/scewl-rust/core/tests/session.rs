@@ -0,0 +1,88 @@
+//! Host tests for the [sessions](scewl::session) which bound the use of the keys of a registration
+//!
+//! Run with `cargo test -p scewl-core --test session --features std,mock-clock --target x86_64-unknown-linux-gnu`.
+
+use core::time::Duration;
+
+use scewl::session::{Lifetime, Session, RETRY};
+use scewl::time::MockClock;
+
+/// A session without bounds never expires
+#[test]
+fn unbounded() {
+    let clock = MockClock::new();
+    let mut session = Session::new(&clock, Lifetime::default());
+    for _ in 0..1000 {
+        session.record_send();
+    }
+    clock.advance(Duration::from_secs(365 * 24 * 3600));
+    assert!(!session.expired());
+    assert!(!session.due());
+}
+
+/// A session expires once as many frames as allowed have been sent, and starts afresh
+#[test]
+fn expires_by_sends() {
+    let clock = MockClock::new();
+    let lifetime = Lifetime {
+        sends: Some(3),
+        age: None,
+    };
+    let mut session = Session::new(&clock, lifetime);
+    session.record_send();
+    session.record_send();
+    assert!(!session.expired());
+    session.record_send();
+    assert!(session.expired());
+    assert!(session.due());
+
+    session.restart();
+    assert!(!session.expired());
+}
+
+/// A session expires once its age is reached, counted from its (re)start
+#[test]
+fn expires_by_age() {
+    let clock = MockClock::new();
+    clock.advance_millis(500);
+    let lifetime = Lifetime {
+        sends: None,
+        age: Some(Duration::from_secs(60)),
+    };
+    let mut session = Session::new(&clock, lifetime);
+    clock.advance_millis(59_999);
+    assert!(!session.expired());
+    clock.advance_millis(1);
+    assert!(session.expired());
+
+    session.restart();
+    assert!(!session.expired());
+    clock.advance(Duration::from_secs(60));
+    assert!(session.expired());
+}
+
+/// A failed rekey puts off the next, but leaves the session expired meanwhile
+#[test]
+fn failed_rekeys_are_deferred() {
+    let clock = MockClock::new();
+    let lifetime = Lifetime {
+        sends: Some(1),
+        age: None,
+    };
+    let mut session = Session::new(&clock, lifetime);
+    session.record_send();
+    session.defer();
+    assert!(session.expired());
+    assert!(!session.due());
+
+    clock.advance(RETRY - Duration::from_millis(1));
+    assert!(!session.due());
+    clock.advance_millis(1);
+    assert!(session.due());
+
+    // a successful rekey forgets the deferral
+    session.defer();
+    session.restart();
+    session.record_send();
+    assert!(session.due());
+}
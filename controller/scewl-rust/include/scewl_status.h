@@ -0,0 +1,182 @@
+/*
+ * Status frames sent by the SCEWL controller to the CPU
+ *
+ * Generated by src/cpu_header.rs; do not edit. Every multi-byte field is little-endian.
+ */
+
+#ifndef SCEWL_STATUS_H
+#define SCEWL_STATUS_H
+
+#include <stdint.h>
+
+/* the boot banner: the magic, followed by the banner's text */
+#define SCEWL_BANNER_MAGIC "BOOT "
+#define SCEWL_BANNER_MAGIC_LEN 5
+
+/* the end of the banner's text, should the watchdog have reset the controller */
+#define SCEWL_BANNER_WATCHDOG " after watchdog reset"
+#define SCEWL_BANNER_WATCHDOG_LEN 21
+
+/* the body of a legacy frame, accepted unauthenticated in mixed mode */
+#define SCEWL_LEGACY_MAGIC "UNAUTH "
+#define SCEWL_LEGACY_MAGIC_LEN 7
+
+/* a heartbeat, sent to the FAA or a monitoring SED */
+#define SCEWL_HEARTBEAT_MAGIC "BEAT"
+#define SCEWL_HEARTBEAT_MAGIC_LEN 4
+
+typedef struct __attribute__((packed)) scewl_heartbeat_t {
+  char magic[4];
+  uint32_t uptime;
+  uint8_t registered;
+  uint32_t dropped;
+} scewl_heartbeat_t;
+_Static_assert(sizeof(scewl_heartbeat_t) == 13, "scewl_heartbeat_t is 13 bytes");
+
+/* diagnostic commands, and the header of their responses */
+#define SCEWL_DIAG_MAGIC "DIAG"
+#define SCEWL_DIAG_MAGIC_LEN 4
+
+enum scewl_diag_op {
+  SCEWL_DIAG_OP_DROPS = 0,
+  SCEWL_DIAG_OP_SET_LEVEL = 1,
+  SCEWL_DIAG_OP_VERSION = 2,
+  SCEWL_DIAG_OP_INTEGRITY = 3,
+  SCEWL_DIAG_OP_STATS = 4,
+};
+
+typedef struct __attribute__((packed)) scewl_diag_resp_t {
+  char magic[4];
+  uint8_t op;
+  uint8_t status; /* 0 on success */
+  /* the result follows */
+} scewl_diag_resp_t;
+_Static_assert(sizeof(scewl_diag_resp_t) == 6, "scewl_diag_resp_t is 6 bytes");
+
+/* the result of a drops command: the number of frames dropped for each reason */
+enum scewl_drop_reason {
+  SCEWL_DROP_REASON_BAD_MAGIC = 0,
+  SCEWL_DROP_REASON_OVERSIZE = 1,
+  SCEWL_DROP_REASON_REPLAY = 2,
+  SCEWL_DROP_REASON_BAD_MAC = 3,
+  SCEWL_DROP_REASON_BAD_PADDING = 4,
+  SCEWL_DROP_REASON_MALFORMED = 5,
+  SCEWL_DROP_REASON_CPU_SPOOFED = 6,
+  SCEWL_DROP_REASON_SELF_SPOOFED = 7,
+  SCEWL_DROP_REASON_RESERVED = 8,
+  SCEWL_DROP_REASON_RATE_LIMITED = 9,
+  SCEWL_DROP_REASON_BAD_CRC = 10,
+  SCEWL_DROP_REASON_PEER_MTU = 11,
+  SCEWL_DROP_REASON_BAD_SIGNATURE = 12,
+  SCEWL_DROP_REASON_BAD_LENGTH = 13,
+  SCEWL_DROP_REASON_NO_KEY = 14,
+  SCEWL_DROP_REASON_REVOKED = 15,
+  SCEWL_DROP_REASON_NOT_ALLOWED = 16,
+  SCEWL_DROP_REASON_BAD_VERSION = 17,
+  SCEWL_DROP_REASON_CLOSED_PORT = 18,
+  SCEWL_DROP_REASON_FLOOD = 19,
+};
+
+#define SCEWL_DROP_COUNT 20
+
+typedef struct __attribute__((packed)) scewl_drops_t {
+  uint32_t counts[SCEWL_DROP_COUNT];
+} scewl_drops_t;
+_Static_assert(sizeof(scewl_drops_t) == 80, "scewl_drops_t is 80 bytes");
+
+/* the argument and result of a set level command */
+enum scewl_level {
+  SCEWL_LEVEL_OFF = 0,
+  SCEWL_LEVEL_ERROR = 1,
+  SCEWL_LEVEL_WARN = 2,
+  SCEWL_LEVEL_INFO = 3,
+  SCEWL_LEVEL_DEBUG = 4,
+  SCEWL_LEVEL_TRACE = 5,
+};
+
+/* the result of an integrity command */
+enum scewl_tamper {
+  SCEWL_TAMPER_UNSEALED = 0,
+  SCEWL_TAMPER_INTACT = 1,
+  SCEWL_TAMPER_TAMPERED = 2,
+};
+
+typedef struct __attribute__((packed)) scewl_integrity_t {
+  uint8_t state;
+  uint32_t checks;
+} scewl_integrity_t;
+_Static_assert(sizeof(scewl_integrity_t) == 5, "scewl_integrity_t is 5 bytes");
+
+/* the result of a stats command: the traffic over each link, then a scewl_drops_t */
+enum scewl_link {
+  SCEWL_LINK_CPU = 0,
+  SCEWL_LINK_SSS = 1,
+  SCEWL_LINK_RADIO = 2,
+};
+
+#define SCEWL_LINK_COUNT 3
+
+typedef struct __attribute__((packed)) scewl_link_stats_t {
+  uint32_t frames_in;
+  uint32_t bytes_in;
+  uint32_t frames_out;
+  uint32_t bytes_out;
+} scewl_link_stats_t;
+_Static_assert(sizeof(scewl_link_stats_t) == 16, "scewl_link_stats_t is 16 bytes");
+
+typedef struct __attribute__((packed)) scewl_stats_t {
+  scewl_link_stats_t links[SCEWL_LINK_COUNT];
+} scewl_stats_t;
+_Static_assert(sizeof(scewl_stats_t) == 48, "scewl_stats_t is 48 bytes");
+
+/* logical ports: the body of an envelope to or from a port, and a request to open or close one */
+#define SCEWL_PORT_MAGIC "\x00PRT"
+#define SCEWL_PORT_MAGIC_LEN 4
+
+typedef struct __attribute__((packed)) scewl_port_hdr_t {
+  uint8_t port;
+  /* the data follows */
+} scewl_port_hdr_t;
+_Static_assert(sizeof(scewl_port_hdr_t) == 1, "scewl_port_hdr_t is 1 bytes");
+
+enum scewl_port_op {
+  SCEWL_PORT_OP_CLOSE = 0,
+  SCEWL_PORT_OP_OPEN = 1,
+};
+
+typedef struct __attribute__((packed)) scewl_port_control_t {
+  char magic[4];
+  uint8_t op;
+  uint8_t port;
+} scewl_port_control_t;
+_Static_assert(sizeof(scewl_port_control_t) == 6, "scewl_port_control_t is 6 bytes");
+
+/* an envelope between the CPU and its own controller, of content to or from a peer */
+#define SCEWL_ENVELOPE_MAGIC "\x00KND"
+#define SCEWL_ENVELOPE_MAGIC_LEN 4
+
+enum scewl_content_kind {
+  SCEWL_CONTENT_KIND_UPDATE = 3,
+  SCEWL_CONTENT_KIND_PORT = 5,
+};
+
+typedef struct __attribute__((packed)) scewl_envelope_hdr_t {
+  char magic[4];
+  uint8_t kind; /* an enum scewl_content_kind */
+  uint16_t peer;
+  /* the body follows */
+} scewl_envelope_hdr_t;
+_Static_assert(sizeof(scewl_envelope_hdr_t) == 7, "scewl_envelope_hdr_t is 7 bytes");
+
+/* a notice of a frame from another SED dropped, with the drop-notices feature */
+#define SCEWL_DROP_NOTICE_MAGIC "DROP"
+#define SCEWL_DROP_NOTICE_MAGIC_LEN 4
+
+typedef struct __attribute__((packed)) scewl_drop_notice_t {
+  char magic[4];
+  uint16_t src_id;
+  uint8_t reason; /* an enum scewl_drop_reason */
+} scewl_drop_notice_t;
+_Static_assert(sizeof(scewl_drop_notice_t) == 7, "scewl_drop_notice_t is 7 bytes");
+
+#endif /* SCEWL_STATUS_H */
@@ -0,0 +1,35 @@
+//! A mock of the secure SSS, for end-to-end testing of the controller without `sss.py`
+//!
+//! This implements the same secure registration protocol as the deployment's SSS: a registering
+//! SED sends its id and the requested operation, the SSS challenges it with a fresh nonce, and
+//! once the SED has proven its registration secret over the nonce, the SSS responds with the
+//! deployment's AES and HMAC keys along with a fresh seed. Deregistration, repeated operations,
+//! and bad proofs are handled exactly as `sss.py` handles them; see [`Sss::handle`] for details.
+//!
+//! The frames themselves are built and parsed with the controller's own [codec](scewl::codec), so
+//! this mock can never disagree with the controller on the wire format.
+//!
+//! The mock may be used in two ways:
+//!
+//!  - as a library, where tests [serve](transport::serve) an [`Sss`] on a socket of their choosing
+//!    and register against it with [`request`](transport::request), as done in `tests/e2e.rs`
+//!  - as a drop-in replacement for `sss.py`, with `cargo run -p mock-sss --features std --target
+//!    x86_64-unknown-linux-gnu -- <socket> [secrets directory]`
+//!
+//! As the mock requires the standard library, everything here is behind the `std` feature.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(clippy::pedantic)]
+#![deny(clippy::missing_docs_in_private_items)]
+#![allow(clippy::upper_case_acronyms)] // SSS is named as in the specification
+#![allow(clippy::must_use_candidate, clippy::return_self_not_must_use)] // as in the controller
+#![allow(clippy::missing_panics_doc)] // panics only on a poisoned lock, i.e. a prior panic
+#![allow(clippy::missing_errors_doc)] // errors are those of the underlying sockets and files
+
+#[cfg(feature = "std")]
+pub use sss::{Deployment, Sss};
+
+#[cfg(feature = "std")]
+mod sss;
+#[cfg(feature = "std")]
+pub mod transport;
@@ -0,0 +1,89 @@
+//! A drop-in replacement for `sss.py`, serving the [mock SSS](mock_sss) on a Unix socket
+//!
+//! Usage: `mock-sss <socket> [secrets directory]`, where the secrets directory defaults to
+//! `/secrets` as for `sss.py`.
+//!
+//! As `sss.py` does, the mock checks the secrets directory for keys of a new epoch once a second,
+//! and pushes them to every registered SED should `key_epoch` have been raised; the new keys must
+//! be written before the epoch is.
+
+#![warn(clippy::pedantic)]
+#![deny(clippy::missing_docs_in_private_items)]
+
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read};
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+use mock_sss::{transport, Deployment, Sss};
+use scewl::secure::rotation::Keys;
+
+/// How often the secrets directory is checked for keys of a new epoch
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args_os().skip(1);
+    let Some(sockf) = args.next().map(PathBuf::from) else {
+        eprintln!("usage: mock-sss <socket> [secrets directory]");
+        process::exit(2);
+    };
+    let secrets = args
+        .next()
+        .map_or_else(|| PathBuf::from("/secrets"), PathBuf::from);
+
+    let deployment = Deployment::load(&secrets)?;
+
+    // seeds are random here, unlike in tests
+    let mut seed = [0_u8; 32];
+    File::open("/dev/urandom")?.read_exact(&mut seed)?;
+
+    // make sure the socket does not already exist
+    match fs::remove_file(&sockf) {
+        Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+
+    let (rotations, received) = mpsc::channel();
+    let epoch = deployment.epoch;
+    thread::spawn(move || watch(&secrets, epoch, &rotations));
+
+    let listener = UnixListener::bind(&sockf)?;
+    transport::serve_rotating(Sss::new(deployment, seed), &listener, received)?;
+
+    Ok(())
+}
+
+/// Checks the secrets directory for keys of an epoch above the given one every
+/// [`WATCH_INTERVAL`], sending each to be pushed
+fn watch(dir: &Path, mut epoch: u32, rotations: &Sender<Keys>) {
+    loop {
+        thread::sleep(WATCH_INTERVAL);
+
+        let deployment = match Deployment::load(dir) {
+            Ok(deployment) => deployment,
+            Err(e) => {
+                eprintln!(":Could not reload {}: {e}", dir.display());
+                continue;
+            }
+        };
+        if deployment.epoch <= epoch {
+            continue;
+        }
+
+        epoch = deployment.epoch;
+        let keys = Keys {
+            epoch,
+            aes_key: deployment.aes_key,
+            hmac_key: deployment.hmac_key,
+        };
+        if rotations.send(keys).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,558 @@
+//! The registration logic of the mock SSS, independent of any transport
+
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryInto;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::time::Instant;
+
+use ed25519_compact::{KeyPair, Seed};
+use rand_core::{RngCore, SeedableRng};
+use rand_hc::Hc128Rng;
+use scewl::codec::secure::{
+    SecureSSSAlive, SecureSSSAllowlist, SecureSSSChallenge, SecureSSSErasure, SecureSSSKeepalive,
+    SecureSSSMessage, SecureSSSProof, SecureSSSResponse, SecureSSSRevocation, SecureSSSRotation,
+    SecureSSSSecrets, SecureSSSSigningKeys, CAP_AES_GCM, CAP_AES_GCM_SIV, CAP_ALLOWLIST,
+    CAP_BROADCAST_SIGS, CAP_PQ_KEM, SUITE,
+};
+use scewl::codec::{Id, SSSOp};
+use scewl::secure::pq::{self, kem};
+use scewl::secure::rotation::{self, Keys};
+use scewl::secure::{challenge, erasure, keywrap};
+use scewl::secure::{keepalive, revocation};
+
+/// The deployment-wide secrets known to the SSS
+#[derive(Clone, Debug)]
+pub struct Deployment {
+    /// The global AES key, distributed at registration
+    pub aes_key: [u8; 16],
+    /// The global HMAC key, distributed at registration
+    pub hmac_key: [u8; 64],
+    /// The epoch of the global keys, distributed at registration
+    pub epoch: u32,
+    /// The capabilities enabled across the deployment, which every SED must implement
+    pub caps: u8,
+    /// The cipher suite of the deployment, which every SED must have been built with
+    pub suite: u8,
+    /// Whether the SSS pushes the SEDs since deregistered to every registered SED
+    pub revokes: bool,
+    /// The registration secret of each SED in the deployment
+    secrets: HashMap<u16, [u8; 64]>,
+    /// The seed of the signing key of each SED which has one
+    signing_seeds: HashMap<u16, [u8; 32]>,
+    /// The mission groups into which the SEDs are partitioned, within which they may exchange
+    /// frames should the deployment enable [`CAP_ALLOWLIST`]
+    groups: Vec<BTreeSet<u16>>,
+}
+
+impl Deployment {
+    /// Instantiates a deployment with the given keys, at epoch 0, with no capabilities enabled,
+    /// the cipher suite [`SUITE`], no revocation, and no SEDs
+    pub fn new(aes_key: [u8; 16], hmac_key: [u8; 64]) -> Self {
+        Self {
+            aes_key,
+            hmac_key,
+            epoch: 0,
+            caps: 0,
+            suite: SUITE,
+            revokes: false,
+            secrets: HashMap::new(),
+            signing_seeds: HashMap::new(),
+            groups: Vec::new(),
+        }
+    }
+
+    /// Adds an SED with the given registration secret to this deployment
+    pub fn with_device(mut self, id: u16, secret: [u8; 64]) -> Self {
+        self.secrets.insert(id, secret);
+        self
+    }
+
+    /// Gives an SED of this deployment the seed of its signing key, which is distributed to it (and
+    /// its public key to every other SED) at registration should the deployment enable
+    /// [`CAP_BROADCAST_SIGS`]
+    pub fn with_signing_seed(mut self, id: u16, seed: [u8; 32]) -> Self {
+        self.signing_seeds.insert(id, seed);
+        self
+    }
+
+    /// Adds a mission group of SEDs to this deployment, each of which is allowed to exchange frames
+    /// with the others of every group it is in (and with no other SED) should the deployment
+    /// enable [`CAP_ALLOWLIST`]
+    pub fn with_group(mut self, ids: impl IntoIterator<Item = u16>) -> Self {
+        self.groups.push(ids.into_iter().collect());
+        self
+    }
+
+    /// Sets the epoch of this deployment's keys
+    pub fn with_epoch(mut self, epoch: u32) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    /// Enables the given capabilities across this deployment, e.g.
+    /// [`CAP_HEADER_CRC`](scewl::codec::secure::CAP_HEADER_CRC)
+    pub fn with_caps(mut self, caps: u8) -> Self {
+        self.caps = caps;
+        self
+    }
+
+    /// Sets the cipher suite of this deployment, e.g.
+    /// [`SUITE_CBC_CMAC`](scewl::codec::secure::SUITE_CBC_CMAC)
+    pub fn with_suite(mut self, suite: u8) -> Self {
+        self.suite = suite;
+        self
+    }
+
+    /// Has the SSS push the SEDs since deregistered to every registered SED whenever they change,
+    /// and to each SED as it registers (see [`revocation`])
+    ///
+    /// This is left off unless asked for, as tests drive several SEDs over one connection and would
+    /// otherwise have to read past the pushes to each.
+    pub fn with_revocation(mut self) -> Self {
+        self.revokes = true;
+        self
+    }
+
+    /// Loads a deployment from a secrets directory laid out as by the deployment's dockerfiles
+    ///
+    /// The directory holds the keys in `aes_key` and `hmac_key`, optionally their epoch in
+    /// `key_epoch`, the enabled capabilities in `caps`, the cipher suite in `suite`, and the
+    /// mission groups in `groups`, one per line of comma-separated ids, and the registration
+    /// secret of each SED in `<id>_secret` and the seed of its signing key in `<id>_sign_seed`, as
+    /// expected by `sss.py`. As with `sss.py`, the deployment pushes the
+    /// SEDs since deregistered.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut deployment = Self::new(
+            read_secret(&dir.join("aes_key"))?,
+            read_secret(&dir.join("hmac_key"))?,
+        )
+        .with_revocation();
+
+        let epoch = dir.join("key_epoch");
+        if epoch.exists() {
+            deployment = deployment.with_epoch(u32::from_le_bytes(read_secret(&epoch)?));
+        }
+
+        let caps = dir.join("caps");
+        if caps.exists() {
+            let [caps] = read_secret(&caps)?;
+            deployment = deployment.with_caps(caps);
+        }
+
+        let suite = dir.join("suite");
+        if suite.exists() {
+            let [suite] = read_secret(&suite)?;
+            deployment = deployment.with_suite(suite);
+        }
+
+        let groups = dir.join("groups");
+        if groups.exists() {
+            for line in fs::read_to_string(&groups)?.lines() {
+                let group = line
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|id| !id.is_empty())
+                    .map(str::parse)
+                    .collect::<std::result::Result<BTreeSet<u16>, _>>()
+                    .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+                deployment = deployment.with_group(group);
+            }
+        }
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let id = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix("_secret"))
+                .and_then(|id| id.parse().ok());
+
+            if let Some(id) = id {
+                deployment = deployment.with_device(id, read_secret(&path)?);
+            }
+
+            let id = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix("_sign_seed"))
+                .and_then(|id| id.parse().ok());
+
+            if let Some(id) = id {
+                deployment = deployment.with_signing_seed(id, read_secret(&path)?);
+            }
+        }
+
+        Ok(deployment)
+    }
+}
+
+/// Reads a secret of a fixed size from the start of a file, as `sss.py` does
+fn read_secret<const N: usize>(path: &Path) -> Result<[u8; N]> {
+    let bytes = fs::read(path)?;
+    bytes
+        .get(..N)
+        .and_then(|secret| secret.try_into().ok())
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{} is too short", path.display()),
+            )
+        })
+}
+
+/// The state of the mock SSS
+pub struct Sss {
+    /// The secrets of the deployment served by this SSS
+    deployment: Deployment,
+    /// The last successful operation of each SED
+    devices: HashMap<u16, SSSOp>,
+    /// The SEDs deregistered, and not registered since
+    revoked: BTreeSet<u16>,
+    /// The serial of the list of revoked SEDs, raised whenever it changes
+    serial: u32,
+    /// When each SED last kept alive
+    last_seen: HashMap<u16, Instant>,
+    /// The source of the seeds distributed at registration
+    rng: Hc128Rng,
+}
+
+impl Sss {
+    /// Instantiates an SSS for the given deployment, with no SEDs registered
+    ///
+    /// The seeds distributed at registration are derived from `seed`, so that tests may be
+    /// reproduced exactly.
+    pub fn new(deployment: Deployment, seed: [u8; 32]) -> Self {
+        Self {
+            deployment,
+            devices: HashMap::new(),
+            revoked: BTreeSet::new(),
+            serial: 0,
+            last_seen: HashMap::new(),
+            rng: Hc128Rng::from_seed(seed),
+        }
+    }
+
+    /// The last successful operation of the given SED, if any
+    pub fn status(&self, id: u16) -> Option<SSSOp> {
+        self.devices.get(&id).copied()
+    }
+
+    /// When the given SED last kept alive, if it ever has
+    pub fn last_seen(&self, id: u16) -> Option<Instant> {
+        self.last_seen.get(&id).copied()
+    }
+
+    /// Answers the keepalive of an SED with whether it is still registered, noting that it is
+    /// live, as `sss.py` does (see [`keepalive`]); an SED which is not part of the deployment is not
+    /// answered
+    pub fn keepalive(&mut self, msg: &SecureSSSKeepalive) -> Option<SecureSSSAlive> {
+        let id = u16::from(msg.dev_id);
+        let secret = self.deployment.secrets.get(&id)?;
+        self.last_seen.insert(id, Instant::now());
+        let registered = self.status(id) == Some(SSSOp::Register);
+        Some(keepalive::answer(secret, msg, registered))
+    }
+
+    /// Forgets the given SED, as done by `sss.py` when its connection is closed
+    pub fn forget(&mut self, id: u16) {
+        self.devices.remove(&id);
+    }
+
+    /// Replaces the deployment's keys with those of a new epoch, which are distributed at
+    /// registration thereafter, returning whether they were taken up, i.e. were of a higher epoch
+    pub fn rotate(&mut self, keys: &Keys) -> bool {
+        if keys.epoch <= self.deployment.epoch {
+            return false;
+        }
+
+        self.deployment.aes_key = keys.aes_key;
+        self.deployment.hmac_key = keys.hmac_key;
+        self.deployment.epoch = keys.epoch;
+        true
+    }
+
+    /// The deployment's keys [wrapped](rotation) for the given SED, to be pushed to it, or `None`
+    /// should it not be registered
+    pub fn rotation(&self, id: u16) -> Option<SecureSSSRotation> {
+        let secret = self.deployment.secrets.get(&id)?;
+        (self.status(id) == Some(SSSOp::Register)).then(|| {
+            rotation::wrap(
+                secret,
+                Id::from(id),
+                &Keys {
+                    epoch: self.deployment.epoch,
+                    aes_key: self.deployment.aes_key,
+                    hmac_key: self.deployment.hmac_key,
+                },
+            )
+        })
+    }
+
+    /// The serial of the list of revoked SEDs, which is raised whenever an SED deregisters or a
+    /// revoked SED registers once more
+    pub fn serial(&self) -> u32 {
+        self.serial
+    }
+
+    /// The list of revoked SEDs [tagged](revocation) for the given SED, serialised to be pushed to
+    /// it, or `None` should it not be registered or the deployment not revoke SEDs
+    pub fn revocation(&self, id: u16) -> Option<Vec<u8>> {
+        let secret = self.deployment.secrets.get(&id)?;
+        let registered = self.status(id) == Some(SSSOp::Register);
+        (registered && self.deployment.revokes).then(|| {
+            let ids: Vec<_> = self
+                .revoked
+                .iter()
+                .flat_map(|id| id.to_le_bytes())
+                .collect();
+            let push = revocation::sign(secret, Id::from(id), self.serial, &ids);
+            let mut buf = vec![0_u8; SecureSSSRevocation::size(push.count())];
+            push.to_bytes(&mut buf);
+            buf
+        })
+    }
+
+    /// Serialises the allowlist which follows the secrets of a registration response to the given
+    /// SED, which holds every other SED of the groups which it is in, returning the number of
+    /// bytes written, which is 0 should the deployment not enable [`CAP_ALLOWLIST`]
+    pub fn allowlist(&self, id: u16, buf: &mut [u8]) -> usize {
+        if self.deployment.caps & CAP_ALLOWLIST == 0 {
+            return 0;
+        }
+
+        let allowed: BTreeSet<_> = self
+            .deployment
+            .groups
+            .iter()
+            .filter(|group| group.contains(&id))
+            .flatten()
+            .filter(|&&peer| peer != id)
+            .map(|&peer| Id::from(peer))
+            .collect();
+        SecureSSSAllowlist::to_bytes(allowed.into_iter(), buf)
+    }
+
+    /// Serialises the signing keys which follow the secrets of a registration response (and the
+    /// allowlist) to the
+    /// given SED, returning the number of bytes written, which is 0 should the deployment not
+    /// sign broadcasts
+    pub fn signing_keys(&self, id: u16, buf: &mut [u8]) -> usize {
+        let seeds = &self.deployment.signing_seeds;
+        let Some(seed) = seeds
+            .get(&id)
+            .filter(|_| self.deployment.caps & CAP_BROADCAST_SIGS != 0)
+        else {
+            return 0;
+        };
+
+        let mut peers: Vec<_> = seeds
+            .iter()
+            .filter(|(&peer, _)| peer != id)
+            .map(|(&peer, &seed)| (Id::from(peer), *KeyPair::from_seed(Seed::new(seed)).pk))
+            .collect();
+        peers.sort_by_key(|&(peer, _)| u16::from(peer));
+
+        SecureSSSSigningKeys::to_bytes(seed, peers.into_iter(), buf)
+    }
+
+    /// Challenges a request with a fresh nonce, which the SED is to prove its secret over before
+    /// the request is [handled](Sss::handle)
+    pub fn challenge(&mut self, msg: &SecureSSSMessage) -> SecureSSSChallenge {
+        let mut nonce = [0_u8; 32];
+        self.rng.fill_bytes(&mut nonce);
+        SecureSSSChallenge {
+            dev_id: msg.dev_id,
+            op: SSSOp::Challenge,
+            nonce,
+        }
+    }
+
+    /// Handles a request as [`handle`](Sss::handle) does, serialising the response to the buffer
+    /// (followed by the [allowlist](Sss::allowlist) and the [signing keys](Sss::signing_keys) should
+    /// it carry secrets), and returns it
+    /// with the number of bytes written
+    ///
+    /// Should the deployment enable [`CAP_PQ_KEM`], a registration is refused unless the request
+    /// is followed by a well-formed encapsulation key, to which the response is then
+    /// [sealed](pq::seal). The response is then [tagged](challenge::tag) under the SED's secret
+    /// over the nonce of the challenge, unless the SED is not part of the deployment.
+    ///
+    /// # Panics
+    ///
+    /// Panics should the buffer be too short for the response.
+    pub fn respond(
+        &mut self,
+        msg: &SecureSSSMessage,
+        challenge: &SecureSSSChallenge,
+        proof: &SecureSSSProof,
+        ek: Option<&[u8; kem::ENCAPSULATION_KEY]>,
+        buf: &mut [u8],
+    ) -> (SecureSSSResponse, usize) {
+        let sealed = self.deployment.caps & CAP_PQ_KEM != 0;
+        let ek = ek.filter(|ek| kem::well_formed(ek));
+        let provisions = matches!(msg.op, SSSOp::Register | SSSOp::Rekey);
+        let resp = if sealed && provisions && ek.is_none() {
+            SecureSSSResponse {
+                dev_id: msg.dev_id,
+                op: SSSOp::Already,
+                secrets: None,
+            }
+        } else {
+            self.handle(msg, challenge, proof)
+        };
+
+        let mut len = resp.to_bytes(buf);
+        if resp.secrets.is_some() {
+            len += self.allowlist(resp.dev_id.into(), &mut buf[len..]);
+            len += self.signing_keys(resp.dev_id.into(), &mut buf[len..]);
+
+            if let Some(ek) = ek.filter(|_| sealed) {
+                let mut m = [0_u8; 32];
+                self.rng.fill_bytes(&mut m);
+                len = pq::seal(ek, &m, resp.dev_id, buf, len).expect("the key is well formed");
+            }
+        }
+
+        if let Some(secret) = self.deployment.secrets.get(&msg.dev_id.into()) {
+            let tag = challenge::tag(secret, &challenge.nonce, msg.dev_id, &buf[..len]);
+            buf[len..len + challenge::TAG].copy_from_slice(&tag);
+            len += challenge::TAG;
+        }
+        (resp, len)
+    }
+
+    /// Handles a registration or deregistration request, once the SED has answered its challenge
+    /// with a proof, returning the response to be sent
+    ///
+    /// As with `sss.py`, the response is [`Already`](SSSOp::Already) when the SED is not part of
+    /// the deployment, when its proof is not of its secret over the nonce of the challenge (or is
+    /// not of the request challenged), when it was built with another cipher
+    /// suite than the deployment's, when it lacks a capability which the deployment enables, or
+    /// when it is already in the requested state, as well as when the deployment signs broadcasts
+    /// but the SED has no signing key. Otherwise, a registration is answered with the deployment's
+    /// keys and a fresh seed, [wrapped](keywrap) under the SED's secret, and its capabilities
+    /// (followed by the [allowlist](Sss::allowlist) and the [signing keys](Sss::signing_keys)
+    /// should the deployment enable them),
+    /// as is a [rekey](SSSOp::Rekey) of a registered SED, which leaves its state as it is, and any
+    /// other operation is answered with [`Deregister`](SSSOp::Deregister), though the SED is only
+    /// deregistered once it [attests](Sss::erase) to erasing its keys.
+    pub fn handle(
+        &mut self,
+        msg: &SecureSSSMessage,
+        challenge: &SecureSSSChallenge,
+        proof: &SecureSSSProof,
+    ) -> SecureSSSResponse {
+        let id = u16::from(msg.dev_id);
+        let already = SecureSSSResponse {
+            dev_id: msg.dev_id,
+            op: SSSOp::Already,
+            secrets: None,
+        };
+
+        let (caps, signing_seeds) = (self.deployment.caps, &self.deployment.signing_seeds);
+        let suite = self.deployment.suite;
+        let challenged = proof.dev_id == msg.dev_id && proof.op == msg.op;
+        match self.deployment.secrets.get(&id) {
+            Some(secret) if !challenged || !challenge::verify(secret, &challenge.nonce, proof) => {
+                already
+            }
+            Some(_) if msg.suite != suite => already,
+            Some(_) if msg.caps & caps != caps => already,
+            Some(_) if caps & CAP_BROADCAST_SIGS != 0 && !signing_seeds.contains_key(&id) => {
+                already
+            }
+            None => already,
+            Some(_) if self.status(id) == Some(msg.op) => already,
+            Some(_) if msg.op == SSSOp::Rekey && self.status(id) != Some(SSSOp::Register) => {
+                already
+            }
+            Some(&secret) if matches!(msg.op, SSSOp::Register | SSSOp::Rekey) => {
+                self.devices.insert(id, SSSOp::Register);
+                if self.revoked.remove(&id) {
+                    self.serial += 1;
+                }
+
+                let mut seed = [0_u8; 32];
+                self.rng.fill_bytes(&mut seed);
+
+                let secrets = SecureSSSSecrets {
+                    aes_key: self.deployment.aes_key,
+                    seed,
+                    hmac_key: self.deployment.hmac_key,
+                    epoch: self.deployment.epoch,
+                    caps,
+                    integrity: [0; 8],
+                };
+
+                SecureSSSResponse {
+                    dev_id: msg.dev_id,
+                    op: msg.op,
+                    secrets: Some(keywrap::wrap(&secret, msg.dev_id, secrets)),
+                }
+            }
+            Some(_) => SecureSSSResponse {
+                dev_id: msg.dev_id,
+                op: SSSOp::Deregister,
+                secrets: None,
+            },
+        }
+    }
+
+    /// Deregisters the SED whose deregistration, challenged as given, was answered, should it
+    /// attest to erasing the deployment's current keys (see [`erasure`]), serialising the
+    /// acknowledgement to the buffer, [tagged](challenge::tag) as responses are, and returning it
+    /// with the number of bytes written
+    ///
+    /// The attestation is acknowledged with [`Erased`](SSSOp::Erased), or refused with
+    /// [`Already`](SSSOp::Already), leaving the SED registered, should it be of other keys, as
+    /// `sss.py` does. The keys attested to are the HMAC key, or the AES key should the deployment
+    /// enable one of the AEADs. A deregistered SED is revoked until it registers once more, and the
+    /// [serial](Sss::serial) is raised whenever that changes.
+    ///
+    /// # Panics
+    ///
+    /// Panics should the buffer be too short for the acknowledgement.
+    pub fn erase(
+        &mut self,
+        msg: &SecureSSSMessage,
+        challenge: &SecureSSSChallenge,
+        attestation: &SecureSSSErasure,
+        buf: &mut [u8],
+    ) -> (SecureSSSResponse, usize) {
+        let id = u16::from(msg.dev_id);
+        let key: &[u8] = if self.deployment.caps & (CAP_AES_GCM | CAP_AES_GCM_SIV) != 0 {
+            &self.deployment.aes_key
+        } else {
+            &self.deployment.hmac_key
+        };
+        let attested = attestation.dev_id == msg.dev_id
+            && attestation.op == SSSOp::Erased
+            && attestation.epoch == self.deployment.epoch
+            && erasure::verify(key, &challenge.nonce, attestation);
+
+        let op = if attested {
+            self.devices.insert(id, SSSOp::Deregister);
+            if self.revoked.insert(id) {
+                self.serial += 1;
+            }
+            SSSOp::Erased
+        } else {
+            SSSOp::Already
+        };
+        let ack = SecureSSSResponse {
+            dev_id: msg.dev_id,
+            op,
+            secrets: None,
+        };
+
+        let mut len = ack.to_bytes(buf);
+        if let Some(secret) = self.deployment.secrets.get(&id) {
+            let tag = challenge::tag(secret, &challenge.nonce, msg.dev_id, &buf[..len]);
+            buf[len..len + challenge::TAG].copy_from_slice(&tag);
+            len += challenge::TAG;
+        }
+        (ack, len)
+    }
+}
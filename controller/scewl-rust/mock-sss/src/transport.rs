@@ -0,0 +1,586 @@
+//! The socket transport of the mock SSS, framed as the controller frames its SSS messages
+//!
+//! Every request and response is prefixed with a [`MessageHeader`], exactly as the controller sends
+//! and expects them on its SSS interface. Each request is answered with a challenge, and the proof
+//! which answers the challenge with the response, tagged by the SSS (see [`challenge`]). Each
+//! connection may carry any number of transactions, and
+//! the SEDs registered or deregistered over a connection are forgotten once it closes, matching
+//! `sss.py`.
+//!
+//! Should the SSS be served [with rotations](serve_rotating), the keys of each new epoch are
+//! pushed, unsolicited, over the connection of every SED registered with it, and the SED's
+//! acknowledgement is read on that connection in between its transactions. Likewise, should the
+//! deployment [revoke](crate::Deployment::with_revocation) SEDs, the list of revoked SEDs is pushed
+//! to every registered SED whenever an SED (de)registers and so changes it, and to each SED as it
+//! registers, after its response. The SSS may also [prompt](prompt_rekey) a registered SED to rekey,
+//! which it does with a request of its own. A registered SED may keep alive in between its
+//! transactions, which is [answered](Sss::keepalive) with whether it is still registered. An SED
+//! whose deregistration is answered is only deregistered once it [attests](Sss::erase) to erasing
+//! its keys, which it does next on the same connection.
+
+use std::collections::{HashMap, HashSet};
+use std::convert::{TryFrom, TryInto};
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use scewl::codec::secure::{
+    SecureSSSAlive, SecureSSSAllowlist, SecureSSSChallenge, SecureSSSErasure, SecureSSSKeepalive,
+    SecureSSSMessage, SecureSSSProof, SecureSSSResponse, SecureSSSRevocation, SecureSSSRotation,
+    SecureSSSSigningKeys,
+};
+use scewl::codec::{Id, MessageHeader, SSSMessage, SSSOp};
+use scewl::crypto::Handler as CryptoHandler;
+use scewl::secure::pq::{kem, SEALING};
+use scewl::secure::rotation::Keys;
+use scewl::secure::{challenge, keepalive};
+
+use crate::Sss;
+
+/// The connection over which each SED is registered, by id, to which the keys of new epochs and
+/// the revoked SEDs are pushed; each is locked while a frame is written to it, so that pushes and responses never
+/// interleave
+pub type Registered = Mutex<HashMap<u16, Arc<Mutex<UnixStream>>>>;
+
+/// Serves the given SSS on the listener forever, handling each connection on its own thread
+pub fn serve(sss: Sss, listener: &UnixListener) -> Result<()> {
+    serve_rotating(sss, listener, mpsc::channel().1)
+}
+
+/// Serves the given SSS on the listener forever, as [`serve`], replacing the deployment's keys
+/// with each of the keys received and pushing them to every registered SED
+pub fn serve_rotating(sss: Sss, listener: &UnixListener, rotations: Receiver<Keys>) -> Result<()> {
+    let sss = Arc::new(Mutex::new(sss));
+    let registered = Arc::new(Registered::default());
+
+    {
+        let (sss, registered) = (Arc::clone(&sss), Arc::clone(&registered));
+        thread::spawn(move || {
+            for keys in rotations {
+                rotate(&sss, &registered, &keys);
+            }
+        });
+    }
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let (sss, registered) = (Arc::clone(&sss), Arc::clone(&registered));
+
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(&sss, &registered, stream) {
+                eprintln!(":Connection failed: {e}");
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Replaces the deployment's keys with those given, should they be of a higher epoch, and pushes
+/// them to every registered SED, returning the number of SEDs they were pushed to
+///
+/// A push which cannot be written is logged, and the SED left to take the keys up at its next
+/// registration.
+pub fn rotate(sss: &Mutex<Sss>, registered: &Registered, keys: &Keys) -> usize {
+    let mut sss = sss.lock().unwrap();
+    if !sss.rotate(keys) {
+        eprintln!(":Epoch {} is not newer; not rotating", keys.epoch);
+        return 0;
+    }
+
+    let mut pushed = 0;
+    for (&id, stream) in registered.lock().unwrap().iter() {
+        let Some(push) = sss.rotation(id) else {
+            continue;
+        };
+        let mut stream = stream.lock().unwrap();
+        match write_frame(&mut stream, Id::from(id), Id::SSS, &push.to_bytes()) {
+            Ok(()) => pushed += 1,
+            Err(e) => eprintln!("{id}:Push failed: {e}"),
+        }
+    }
+    eprintln!(":Rotated to epoch {}", keys.epoch);
+
+    pushed
+}
+
+/// Pushes the list of revoked SEDs to each of the given SEDs which is registered, returning the
+/// number of SEDs it was pushed to
+///
+/// A push which cannot be written is logged, and the SED left to be pushed the list at its next
+/// registration.
+pub fn revoke(sss: &Mutex<Sss>, registered: &Registered, ids: &[u16]) -> usize {
+    let sss = sss.lock().unwrap();
+    let registered = registered.lock().unwrap();
+
+    let mut pushed = 0;
+    for &id in ids {
+        let (Some(push), Some(stream)) = (sss.revocation(id), registered.get(&id)) else {
+            continue;
+        };
+        let mut stream = stream.lock().unwrap();
+        match write_frame(&mut stream, Id::from(id), Id::SSS, &push) {
+            Ok(()) => pushed += 1,
+            Err(e) => eprintln!("{id}:Push failed: {e}"),
+        }
+    }
+
+    pushed
+}
+
+/// Prompts each of the given SEDs which is registered to [rekey](SSSOp::Rekey), returning the
+/// number of SEDs prompted
+///
+/// The prompt carries no keys, so it is not authenticated: the SED answers it with a rekey of its
+/// own, exchanged as any other request is. A prompt which cannot be written is logged.
+pub fn prompt_rekey(registered: &Registered, ids: &[u16]) -> usize {
+    let registered = registered.lock().unwrap();
+
+    let mut prompted = 0;
+    for &id in ids {
+        let Some(stream) = registered.get(&id) else {
+            continue;
+        };
+        let prompt = SSSMessage {
+            dev_id: Id::from(id),
+            op: SSSOp::Rekey,
+        };
+        let mut stream = stream.lock().unwrap();
+        match write_frame(&mut stream, Id::from(id), Id::SSS, &prompt.to_bytes()) {
+            Ok(()) => prompted += 1,
+            Err(e) => eprintln!("{id}:Prompt failed: {e}"),
+        }
+    }
+
+    prompted
+}
+
+/// Handles transactions on a connection until it is closed by the SED
+pub fn handle_connection(
+    sss: &Mutex<Sss>,
+    registered: &Registered,
+    mut stream: UnixStream,
+) -> Result<()> {
+    let mut attributed = HashSet::new();
+    let mut pending = None;
+    let mut erasing = None;
+    let writer = Arc::new(Mutex::new(stream.try_clone()?));
+
+    let result = loop {
+        let (hdr, body) = match read_frame(&mut stream) {
+            Ok(frame) => frame,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break Ok(()),
+            Err(e) => break Err(e),
+        };
+
+        // the acknowledgement of a push, in between transactions
+        if body.len() == SSSMessage::size() {
+            let ack = SSSMessage::from_bytes(&body);
+            eprintln!("{}:{:?}", u16::from(ack.dev_id), ack.op);
+            continue;
+        }
+
+        // a keepalive, in between transactions
+        if let Some(msg) = SecureSSSKeepalive::from_bytes(&body) {
+            if let Err(e) = answer_keepalive(sss, &writer, &msg) {
+                break Err(e);
+            }
+            continue;
+        }
+
+        // the attestation of a deregistering SED that it is erasing its keys, once its
+        // deregistration was answered, without which it is not deregistered
+        if let Some(attestation) = SecureSSSErasure::from_bytes(&body) {
+            match answer_erasure(sss, registered, &writer, erasing.take(), &attestation) {
+                Ok(erased) => attributed.extend(erased),
+                Err(e) => break Err(e),
+            }
+            continue;
+        }
+
+        // a request, which is challenged before it is handled
+        if let Some(msg) = SecureSSSMessage::from_bytes(&body) {
+            let challenge = sss.lock().unwrap().challenge(&msg);
+            pending = Some((msg, challenge));
+            erasing = None;
+            let written = write_frame(
+                &mut writer.lock().unwrap(),
+                msg.dev_id,
+                Id::SSS,
+                &challenge.to_bytes(),
+            );
+            match written {
+                Ok(()) => continue,
+                Err(e) => break Err(e),
+            }
+        }
+
+        // the proof answering the challenge, and the encapsulation key which may follow it
+        let (proof, ek) = if body.len() == SecureSSSProof::size() + kem::ENCAPSULATION_KEY {
+            let (proof, ek) = body.split_at(SecureSSSProof::size());
+            (proof, ek.try_into().ok())
+        } else {
+            (&body[..], None)
+        };
+        let (Some(proof), Some((msg, challenge))) =
+            (SecureSSSProof::from_bytes(proof), pending.take())
+        else {
+            break Err(invalid(format!("malformed request from {:?}", hdr.src_id)));
+        };
+
+        let mut state = sss.lock().unwrap();
+        let serial = state.serial();
+        let mut buf = vec![
+            0_u8;
+            SecureSSSResponse::size()
+                + SEALING
+                + SecureSSSAllowlist::max_size()
+                + SecureSSSSigningKeys::max_size()
+                + challenge::TAG
+        ];
+        let (resp, len) = state.respond(&msg, &challenge, &proof, ek, &mut buf);
+        eprintln!("{}:{:?}", u16::from(resp.dev_id), resp.op);
+        let id = u16::from(resp.dev_id);
+        match resp.op {
+            SSSOp::Register => {
+                attributed.insert(id);
+                registered.lock().unwrap().insert(id, Arc::clone(&writer));
+            }
+            SSSOp::Deregister => erasing = Some((msg, challenge)),
+            _ => {}
+        }
+        // a changed list is pushed to every registered SED, and any list to an SED which registers
+        let revoked: Vec<_> = if state.serial() != serial {
+            registered.lock().unwrap().keys().copied().collect()
+        } else if resp.op == SSSOp::Register {
+            vec![id]
+        } else {
+            Vec::new()
+        };
+        drop(state);
+
+        if let Err(e) = write_frame(
+            &mut writer.lock().unwrap(),
+            resp.dev_id,
+            Id::SSS,
+            &buf[..len],
+        ) {
+            break Err(e);
+        }
+        revoke(sss, registered, &revoked);
+    };
+
+    let mut sss = sss.lock().unwrap();
+    let mut registered = registered.lock().unwrap();
+    for id in attributed {
+        sss.forget(id);
+        registered.remove(&id);
+    }
+
+    result
+}
+
+/// Answers the keepalive of an SED over its connection, as [`Sss::keepalive`] does
+fn answer_keepalive(
+    sss: &Mutex<Sss>,
+    writer: &Mutex<UnixStream>,
+    msg: &SecureSSSKeepalive,
+) -> Result<()> {
+    let alive = sss
+        .lock()
+        .unwrap()
+        .keepalive(msg)
+        .ok_or_else(|| invalid(format!("keepalive from unknown {:?}", msg.dev_id)))?;
+    write_frame(
+        &mut writer.lock().unwrap(),
+        msg.dev_id,
+        Id::SSS,
+        &alive.to_bytes(),
+    )
+}
+
+/// Answers the attestation of an SED that it is erasing its keys, once its deregistration,
+/// challenged as given, was answered, as [`Sss::erase`] does, returning the SED should it have been
+/// deregistered, in which case the changed list of revoked SEDs is pushed to every registered SED
+fn answer_erasure(
+    sss: &Mutex<Sss>,
+    registered: &Registered,
+    writer: &Mutex<UnixStream>,
+    erasing: Option<(SecureSSSMessage, SecureSSSChallenge)>,
+    attestation: &SecureSSSErasure,
+) -> Result<Option<u16>> {
+    let (msg, challenge) = erasing
+        .ok_or_else(|| invalid(format!("unsolicited erasure from {:?}", attestation.dev_id)))?;
+    let mut state = sss.lock().unwrap();
+    let serial = state.serial();
+    let mut buf = [0_u8; SSSMessage::size() + challenge::TAG];
+    let (ack, len) = state.erase(&msg, &challenge, attestation, &mut buf);
+    eprintln!("{}:{:?}", u16::from(ack.dev_id), ack.op);
+    let erased = (ack.op == SSSOp::Erased).then(|| u16::from(ack.dev_id));
+    if let Some(id) = erased {
+        registered.lock().unwrap().remove(&id);
+    }
+    let revoked: Vec<_> = if state.serial() == serial {
+        Vec::new()
+    } else {
+        registered.lock().unwrap().keys().copied().collect()
+    };
+    drop(state);
+
+    write_frame(
+        &mut writer.lock().unwrap(),
+        ack.dev_id,
+        Id::SSS,
+        &buf[..len],
+    )?;
+    revoke(sss, registered, &revoked);
+    Ok(erased)
+}
+
+/// Sends a request to the SSS on behalf of an SED of the given secret, proving the secret in
+/// answer to the challenge of the SSS, and waits for its response
+pub fn request(
+    stream: &mut UnixStream,
+    msg: &SecureSSSMessage,
+    secret: &[u8; 64],
+) -> Result<SecureSSSResponse> {
+    let body = exchange(stream, msg, secret)?;
+    SecureSSSResponse::from_bytes(&body).ok_or_else(|| invalid("malformed response".into()))
+}
+
+/// Sends a request to the SSS on behalf of an SED as [`request`] does, returning the body of the
+/// response as received, e.g. for the [allowlist](SecureSSSAllowlist) or the [signing
+/// keys](SecureSSSSigningKeys) which may follow its secrets
+pub fn exchange(
+    stream: &mut UnixStream,
+    msg: &SecureSSSMessage,
+    secret: &[u8; 64],
+) -> Result<Vec<u8>> {
+    exchange_with_key(stream, msg, secret, None)
+}
+
+/// Sends a request to the SSS on behalf of an SED as [`exchange`] does, following the proof with
+/// the SED's encapsulation key should it be given, so that the response is [sealed](scewl::secure::pq)
+/// should the deployment enable it
+///
+/// The response is checked to have been [tagged](challenge::tag) by the SSS over the nonce of the
+/// challenge, as the controller checks it, and is returned without its tag.
+pub fn exchange_with_key(
+    stream: &mut UnixStream,
+    msg: &SecureSSSMessage,
+    secret: &[u8; 64],
+    ek: Option<&[u8; kem::ENCAPSULATION_KEY]>,
+) -> Result<Vec<u8>> {
+    challenged(stream, *msg, secret, ek).map(|(_, body)| body)
+}
+
+/// Deregisters an SED of the given secret, whose keys the crypto handler holds, attesting to
+/// erasing them once the SSS answers (see [`erasure`](scewl::secure::erasure)), and returns the
+/// operation with which the SSS acknowledged the attestation, or with which it answered the
+/// request should it have refused it
+///
+/// The handler erases its keys should the SSS acknowledge the attestation with
+/// [`Erased`](SSSOp::Erased), as the controller's does. Both the response and the acknowledgement
+/// are checked to have been [tagged](challenge::tag) by the SSS over the nonce of the challenge.
+pub fn deregister(
+    stream: &mut UnixStream,
+    msg: &SecureSSSMessage,
+    secret: &[u8; 64],
+    handler: &mut impl CryptoHandler,
+) -> Result<SSSOp> {
+    let (nonce, body) = challenged(stream, *msg, secret, None)?;
+    let resp =
+        SecureSSSResponse::from_bytes(&body).ok_or_else(|| invalid("malformed response".into()))?;
+    if resp.op != SSSOp::Deregister {
+        return Ok(resp.op);
+    }
+
+    let (epoch, tag) = handler
+        .attest(&nonce, msg.dev_id)
+        .ok_or_else(|| invalid("no keys to attest to".into()))?;
+    let attestation = SecureSSSErasure {
+        dev_id: msg.dev_id,
+        op: SSSOp::Erased,
+        epoch,
+        tag,
+    };
+    write_frame(stream, Id::SSS, msg.dev_id, &attestation.to_bytes())?;
+
+    let (_, body) = read_frame(stream)?;
+    let body = untag(secret, &nonce, msg.dev_id, body)?;
+    let ack = SecureSSSResponse::from_bytes(&body)
+        .ok_or_else(|| invalid("malformed acknowledgement".into()))?;
+    if ack.op == SSSOp::Erased {
+        handler.erase();
+    }
+    Ok(ack.op)
+}
+
+/// Sends a request to the SSS on behalf of an SED as [`exchange_with_key`] does, returning the
+/// nonce of the challenge with the body of the response
+fn challenged(
+    stream: &mut UnixStream,
+    msg: SecureSSSMessage,
+    secret: &[u8; 64],
+    ek: Option<&[u8; kem::ENCAPSULATION_KEY]>,
+) -> Result<([u8; 32], Vec<u8>)> {
+    let challenge = open(stream, &msg)?;
+    let proof = SecureSSSProof {
+        dev_id: msg.dev_id,
+        op: msg.op,
+        proof: challenge::prove(secret, &challenge.nonce, msg.dev_id),
+    };
+    let body = prove(stream, &proof, ek)?;
+    Ok((
+        challenge.nonce,
+        untag(secret, &challenge.nonce, msg.dev_id, body)?,
+    ))
+}
+
+/// Checks that the body of a response to the SED of the given secret was tagged by the SSS over
+/// the nonce of the challenge, returning it without its tag
+fn untag(secret: &[u8; 64], nonce: &[u8; 32], dev_id: Id, mut body: Vec<u8>) -> Result<Vec<u8>> {
+    let len = body
+        .len()
+        .checked_sub(challenge::TAG)
+        .ok_or_else(|| invalid("untagged response".into()))?;
+    let (resp, tag) = body.split_at(len);
+    if !challenge::authentic(secret, nonce, dev_id, resp, tag) {
+        return Err(invalid("response not tagged by the SSS".into()));
+    }
+    body.truncate(len);
+    Ok(body)
+}
+
+/// Sends a request to the SSS on behalf of an SED and waits for the challenge with which the SSS
+/// answers it
+pub fn open(stream: &mut UnixStream, msg: &SecureSSSMessage) -> Result<SecureSSSChallenge> {
+    let mut buf = [0_u8; SecureSSSMessage::size()];
+    let len = msg.to_bytes(&mut buf);
+    write_frame(stream, Id::SSS, msg.dev_id, &buf[..len])?;
+
+    let (_, body) = read_frame(stream)?;
+    SecureSSSChallenge::from_bytes(&body).ok_or_else(|| invalid("malformed challenge".into()))
+}
+
+/// Answers the challenge of the SSS on behalf of an SED with the proof, followed by the SED's
+/// encapsulation key should it be given, and waits for the response, returning its body as
+/// received, tag and all
+pub fn prove(
+    stream: &mut UnixStream,
+    proof: &SecureSSSProof,
+    ek: Option<&[u8; kem::ENCAPSULATION_KEY]>,
+) -> Result<Vec<u8>> {
+    let mut buf = [0_u8; SecureSSSProof::size() + kem::ENCAPSULATION_KEY];
+    let mut len = proof.to_bytes(&mut buf);
+    if let Some(ek) = ek {
+        buf[len..].copy_from_slice(ek);
+        len += ek.len();
+    }
+    write_frame(stream, Id::SSS, proof.dev_id, &buf[..len])?;
+
+    let (_, body) = read_frame(stream)?;
+    Ok(body)
+}
+
+/// Keeps alive with the SSS on behalf of an SED of the given secret, returning whether the SSS
+/// answered that it is still registered
+///
+/// The answer is checked to have been [tagged](keepalive) by the SSS over the nonce of the
+/// keepalive, as the controller checks it.
+pub fn keep_alive(
+    stream: &mut UnixStream,
+    dev_id: Id,
+    secret: &[u8; 64],
+    nonce: [u8; 16],
+) -> Result<bool> {
+    let msg = SecureSSSKeepalive {
+        dev_id,
+        op: SSSOp::Keepalive,
+        nonce,
+    };
+    write_frame(stream, Id::SSS, dev_id, &msg.to_bytes())?;
+
+    let (_, body) = read_frame(stream)?;
+    let alive =
+        SecureSSSAlive::from_bytes(&body).ok_or_else(|| invalid("malformed answer".into()))?;
+    if alive.dev_id != dev_id || !keepalive::verify(secret, &nonce, &alive) {
+        return Err(invalid("answer not tagged by the SSS".into()));
+    }
+    Ok(alive.op == SSSOp::Keepalive)
+}
+
+/// Waits for the SSS to push the keys of a new epoch to an SED registered over the connection
+pub fn read_push(stream: &mut UnixStream) -> Result<SecureSSSRotation> {
+    let (_, body) = read_frame(stream)?;
+    SecureSSSRotation::from_bytes(&body).ok_or_else(|| invalid("malformed push".into()))
+}
+
+/// Waits for the SSS to push the list of revoked SEDs to an SED registered over the connection,
+/// returning the push as received, from which a [`SecureSSSRevocation`] borrows its ids
+pub fn read_revocation(stream: &mut UnixStream) -> Result<Vec<u8>> {
+    let (_, body) = read_frame(stream)?;
+    SecureSSSRevocation::from_bytes(&body).ok_or_else(|| invalid("malformed push".into()))?;
+    Ok(body)
+}
+
+/// Waits for the SSS to prompt an SED registered over the connection to [rekey](SSSOp::Rekey)
+pub fn read_prompt(stream: &mut UnixStream) -> Result<SSSMessage> {
+    let (_, body) = read_frame(stream)?;
+    (body.len() == SSSMessage::size())
+        .then(|| SSSMessage::from_bytes(&body))
+        .filter(|prompt| prompt.op == SSSOp::Rekey)
+        .ok_or_else(|| invalid("malformed prompt".into()))
+}
+
+/// Acknowledges a push on behalf of an SED, with [`SSSOp::Rotated`] should it have taken up the
+/// keys, or [`SSSOp::Already`] should it have refused them
+pub fn acknowledge(stream: &mut UnixStream, dev_id: Id, rotated: bool) -> Result<()> {
+    let ack = SecureSSSResponse {
+        dev_id,
+        op: if rotated {
+            SSSOp::Rotated
+        } else {
+            SSSOp::Already
+        },
+        secrets: None,
+    };
+    let mut buf = [0_u8; SSSMessage::size()];
+    let len = ack.to_bytes(&mut buf);
+    write_frame(stream, Id::SSS, dev_id, &buf[..len])
+}
+
+/// Reads a single frame, returning its header and body
+fn read_frame(stream: &mut UnixStream) -> Result<(MessageHeader, Vec<u8>)> {
+    let mut hdr = [0_u8; MessageHeader::size()];
+    stream.read_exact(&mut hdr)?;
+
+    if !MessageHeader::verify_magic(&hdr) {
+        return Err(invalid(format!("bad header magic: {:?}", &hdr[..2])));
+    }
+
+    let hdr = MessageHeader::from_bytes(hdr);
+    let mut body = vec![0_u8; hdr.len.into()];
+    stream.read_exact(&mut body)?;
+
+    Ok((hdr, body))
+}
+
+/// Writes a single frame with the given body
+fn write_frame(stream: &mut UnixStream, tgt_id: Id, src_id: Id, body: &[u8]) -> Result<()> {
+    let hdr = MessageHeader {
+        tgt_id,
+        src_id,
+        len: u16::try_from(body.len()).map_err(|_| invalid("frame too large".into()))?,
+    };
+
+    stream.write_all(&hdr.to_bytes())?;
+    stream.write_all(body)
+}
+
+/// Creates an error for data which does not follow the protocol
+fn invalid(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,984 @@
+//! End-to-end tests of registration, messaging, and deregistration against the mock SSS
+//!
+//! Run with `cargo test -p mock-sss --features std --target x86_64-unknown-linux-gnu`.
+
+use std::convert::TryFrom;
+use std::env;
+use std::io::ErrorKind;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::process;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use mock_sss::transport::{self, Registered};
+use mock_sss::{Deployment, Sss};
+use scewl::codec::secure::{
+    SecureSSSAllowlist, SecureSSSKeepalive, SecureSSSMessage, SecureSSSProof, SecureSSSResponse,
+    SecureSSSRevocation, SecureSSSSigningKeys, CAPS, CAP_AES_GCM, CAP_AES_GCM_SIV, CAP_ALLOWLIST,
+    CAP_BROADCAST_SIGS, CAP_HEADER_CRC, CAP_PQ_KEM, SUITE, SUITE_CBC_CMAC, SUITE_CBC_HMAC,
+};
+use scewl::codec::{Id, Message, MessageHeader, SSSOp, SCEWL_MAX_DATA_SZ};
+use scewl::crypto::Handler as _;
+use scewl::diag::Reason;
+use scewl::policy::{Link, Policy};
+use scewl::scratch::Pool;
+use scewl::secure::pq::{kem, KeyPair, SEALING};
+use scewl::secure::rotation::{self, Keys};
+use scewl::secure::{challenge, keywrap};
+use scewl::secure::{keepalive, revocation};
+use scewl::secure::{CryptoHandler, GcmHandler, SignedHandler, SigningKeys, SivHandler};
+
+/// The AES key of the test deployment
+const AES_KEY: [u8; 16] = [0xA5; 16];
+/// The HMAC key of the test deployment
+const HMAC_KEY: [u8; 64] = [0x5A; 64];
+/// The epoch of the test deployment's keys
+const EPOCH: u32 = 3;
+/// The registration secret of SED 10
+const SECRET_10: [u8; 64] = [10; 64];
+/// The registration secret of SED 11
+const SECRET_11: [u8; 64] = [11; 64];
+
+/// The test deployment of SEDs 10 and 11
+fn deployment() -> Deployment {
+    Deployment::new(AES_KEY, HMAC_KEY)
+        .with_epoch(EPOCH)
+        .with_device(10, SECRET_10)
+        .with_device(11, SECRET_11)
+}
+
+/// Serves a mock SSS for the test deployment on a fresh socket, returning its path
+fn spawn_sss(name: &str) -> PathBuf {
+    spawn_sss_for(name, deployment())
+}
+
+/// Serves a mock SSS for the given deployment on a fresh socket, returning its path
+fn spawn_sss_for(name: &str, deployment: Deployment) -> PathBuf {
+    let path = env::temp_dir().join(format!("mock-sss-{}-{}.sock", process::id(), name));
+    let _ = std::fs::remove_file(&path);
+
+    let listener = UnixListener::bind(&path).unwrap();
+    thread::spawn(move || transport::serve(Sss::new(deployment, [0; 32]), &listener));
+
+    path
+}
+
+/// Serves a mock SSS for the test deployment on a fresh socket, returning its path and the sender
+/// of the keys which it rotates to
+fn spawn_rotating_sss(name: &str) -> (PathBuf, Sender<Keys>) {
+    let path = env::temp_dir().join(format!("mock-sss-{}-{}.sock", process::id(), name));
+    let _ = std::fs::remove_file(&path);
+
+    let (rotations, received) = mpsc::channel();
+    let listener = UnixListener::bind(&path).unwrap();
+    thread::spawn(move || {
+        transport::serve_rotating(Sss::new(deployment(), [0; 32]), &listener, received)
+    });
+
+    (path, rotations)
+}
+
+/// Serves a mock SSS for the test deployment on a fresh socket, returning its path and the
+/// connections of the SEDs registered with it, over which it may prompt them to rekey
+fn spawn_prompting_sss(name: &str) -> (PathBuf, Arc<Registered>) {
+    let path = env::temp_dir().join(format!("mock-sss-{}-{}.sock", process::id(), name));
+    let _ = std::fs::remove_file(&path);
+
+    let sss = Arc::new(Mutex::new(Sss::new(deployment(), [0; 32])));
+    let registered = Arc::new(Registered::default());
+    let listener = UnixListener::bind(&path).unwrap();
+    {
+        let registered = Arc::clone(&registered);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let (sss, registered) = (Arc::clone(&sss), Arc::clone(&registered));
+                thread::spawn(move || {
+                    transport::handle_connection(&sss, &registered, stream.unwrap())
+                });
+            }
+        });
+    }
+
+    (path, registered)
+}
+
+/// Performs a single transaction with the SSS on behalf of an SED
+fn transact(stream: &mut UnixStream, id: u16, op: SSSOp, secret: &[u8; 64]) -> SecureSSSResponse {
+    transact_with_suite(stream, id, op, secret, SUITE, CAPS)
+}
+
+/// Performs a single transaction with the SSS on behalf of an SED built with the given suite and
+/// capabilities, unwrapping the secrets of the response should it carry any
+fn transact_with_suite(
+    stream: &mut UnixStream,
+    id: u16,
+    op: SSSOp,
+    secret: &[u8; 64],
+    suite: u8,
+    caps: u8,
+) -> SecureSSSResponse {
+    let resp = transport::request(
+        stream,
+        &SecureSSSMessage {
+            dev_id: Id::Other(id),
+            op,
+            suite,
+            caps,
+        },
+        secret,
+    )
+    .unwrap();
+    assert_eq!(resp.dev_id, Id::Other(id));
+    SecureSSSResponse {
+        secrets: resp
+            .secrets
+            .map(|secrets| keywrap::unwrap(secret, Id::Other(id), secrets).unwrap()),
+        ..resp
+    }
+}
+
+/// Registers an SED, returning the crypto handler built from the distributed secrets
+fn register(stream: &mut UnixStream, id: u16, secret: &[u8; 64]) -> CryptoHandler {
+    let resp = transact(stream, id, SSSOp::Register, secret);
+    assert_eq!(resp.op, SSSOp::Register);
+
+    let secrets = resp.secrets.unwrap();
+    assert_eq!(secrets.aes_key, AES_KEY);
+    assert_eq!(secrets.hmac_key, HMAC_KEY);
+    assert_eq!(secrets.epoch, EPOCH);
+    assert_eq!(secrets.caps, 0);
+    CryptoHandler::new(secrets.seed, secrets.aes_key, secrets.hmac_key).with_epoch(secrets.epoch)
+}
+
+/// Deregisters an SED whose keys the crypto handler holds, attesting to erasing them, and returns
+/// the operation with which the SSS acknowledged the attestation (or answered the request)
+fn deregister(
+    stream: &mut UnixStream,
+    id: u16,
+    secret: &[u8; 64],
+    handler: &mut CryptoHandler,
+) -> SSSOp {
+    let msg = SecureSSSMessage {
+        dev_id: Id::Other(id),
+        op: SSSOp::Deregister,
+        suite: SUITE,
+        caps: CAPS,
+    };
+    transport::deregister(stream, &msg, secret, handler).unwrap()
+}
+
+/// Sends a direct message from one SED to another, returning why it was refused, if it was
+fn deliver(
+    sender: &mut CryptoHandler,
+    receiver: &mut CryptoHandler,
+    src: u16,
+    tgt: u16,
+) -> Result<(), Reason> {
+    let mut data = vec![0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let content = b"delivered";
+    data[sender.content_offset()..][..content.len()].copy_from_slice(content);
+
+    let mut msg = Message {
+        tgt_id: Id::Other(tgt),
+        src_id: Id::Other(src),
+        len: content.len(),
+    };
+    msg.len = sender.encrypt(&mut data, msg, &scratch);
+    receiver.verify(&data, msg, &scratch)?;
+    assert_eq!(receiver.decrypt(&mut data, msg, &scratch)?, content.len());
+    assert_eq!(&data[receiver.content_offset()..][..content.len()], content);
+    Ok(())
+}
+
+#[test]
+fn register_message_deregister() {
+    let path = spawn_sss("lifecycle");
+    let mut sed_10 = UnixStream::connect(&path).unwrap();
+    let mut sed_11 = UnixStream::connect(&path).unwrap();
+
+    let mut sender = register(&mut sed_10, 10, &SECRET_10);
+    let mut receiver = register(&mut sed_11, 11, &SECRET_11);
+
+    let mut data = vec![0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let content = b"hello from 10";
+    data[sender.content_offset()..][..content.len()].copy_from_slice(content);
+
+    let mut msg = Message {
+        tgt_id: Id::Other(11),
+        src_id: Id::Other(10),
+        len: content.len(),
+    };
+    msg.len = sender.encrypt(&mut data, msg, &scratch);
+    assert_eq!(receiver.verify(&data, msg, &scratch), Ok(()));
+    assert_eq!(
+        receiver.decrypt(&mut data, msg, &scratch),
+        Ok(content.len())
+    );
+    assert_eq!(&data[receiver.content_offset()..][..content.len()], content);
+
+    assert_eq!(
+        deregister(&mut sed_10, 10, &SECRET_10, &mut sender),
+        SSSOp::Erased
+    );
+
+    let resp = transact(&mut sed_10, 10, SSSOp::Deregister, &SECRET_10);
+    assert_eq!(resp.op, SSSOp::Already);
+}
+
+#[test]
+fn repeated_registration_is_refused() {
+    let path = spawn_sss("repeated");
+    let mut sed = UnixStream::connect(&path).unwrap();
+
+    register(&mut sed, 10, &SECRET_10);
+
+    let resp = transact(&mut sed, 10, SSSOp::Register, &SECRET_10);
+    assert_eq!(resp.op, SSSOp::Already);
+    assert!(resp.secrets.is_none());
+}
+
+/// A registered SED registers afresh by first deregistering, as the controller does should its CPU
+/// request to register while registered; a deregistration refused by the SSS leaves it registered
+#[test]
+fn reregistration_deregisters_first() {
+    let path = spawn_sss("reregistration");
+    let mut sed_10 = UnixStream::connect(&path).unwrap();
+    let mut sed_11 = UnixStream::connect(&path).unwrap();
+
+    let mut stale = register(&mut sed_10, 10, &SECRET_10);
+    let mut receiver = register(&mut sed_11, 11, &SECRET_11);
+
+    // keys other than the deployment's are not attested to, so the SED stays registered
+    let mut forged = CryptoHandler::new([0; 32], [0; 16], [0; 64]);
+    assert_eq!(
+        deregister(&mut sed_10, 10, &SECRET_10, &mut forged),
+        SSSOp::Already
+    );
+    let resp = transact(&mut sed_10, 10, SSSOp::Register, &SECRET_10);
+    assert_eq!(resp.op, SSSOp::Already);
+
+    assert_eq!(
+        deregister(&mut sed_10, 10, &SECRET_10, &mut stale),
+        SSSOp::Erased
+    );
+    let mut fresh = register(&mut sed_10, 10, &SECRET_10);
+    assert_eq!(deliver(&mut fresh, &mut receiver, 10, 11), Ok(()));
+    assert_eq!(deliver(&mut receiver, &mut fresh, 11, 10), Ok(()));
+}
+
+#[test]
+fn keys_are_wrapped_under_the_secret() {
+    let path = spawn_sss("wrapped");
+    let mut sed = UnixStream::connect(&path).unwrap();
+    let msg = SecureSSSMessage {
+        dev_id: Id::Other(10),
+        op: SSSOp::Register,
+        suite: SUITE,
+        caps: CAPS,
+    };
+
+    let body = transport::exchange(&mut sed, &msg, &SECRET_10).unwrap();
+    assert!(!body.windows(16).any(|window| window == AES_KEY));
+    assert!(!body.windows(64).any(|window| window == HMAC_KEY));
+
+    // only the SED of the secret may unwrap the keys, which are checked as they are
+    let wrapped = SecureSSSResponse::from_bytes(&body)
+        .unwrap()
+        .secrets
+        .unwrap();
+    assert!(keywrap::unwrap(&SECRET_11, Id::Other(10), wrapped).is_none());
+    assert!(keywrap::unwrap(&SECRET_10, Id::Other(11), wrapped).is_none());
+    let mut tampered = wrapped;
+    tampered.seed[0] ^= 1;
+    assert!(keywrap::unwrap(&SECRET_10, Id::Other(10), tampered).is_none());
+
+    let secrets = keywrap::unwrap(&SECRET_10, Id::Other(10), wrapped).unwrap();
+    assert_eq!(secrets.aes_key, AES_KEY);
+    assert_eq!(secrets.hmac_key, HMAC_KEY);
+    assert_eq!(secrets.epoch, EPOCH);
+}
+
+#[test]
+fn bad_secrets_ids_and_suites_are_refused() {
+    let path = spawn_sss("refused");
+    let mut sed = UnixStream::connect(&path).unwrap();
+
+    // the refusal of an SED of another's secret, or of an id outside the deployment, is tagged
+    // under the secret which the SSS holds for the id, if any, so the SED cannot take it up either
+    for (id, secret) in [(10, &SECRET_11), (12, &SECRET_10)] {
+        let msg = SecureSSSMessage {
+            dev_id: Id::Other(id),
+            op: SSSOp::Register,
+            suite: SUITE,
+            caps: CAPS,
+        };
+        let err = transport::request(&mut sed, &msg, secret).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    let resp = transact_with_suite(&mut sed, 10, SSSOp::Register, &SECRET_10, SUITE + 1, CAPS);
+    assert_eq!(resp.op, SSSOp::Already);
+    assert!(resp.secrets.is_none());
+
+    // the refusals must not have affected the legitimate SED
+    register(&mut sed, 10, &SECRET_10);
+}
+
+#[test]
+fn proofs_are_bound_to_their_challenge() {
+    let path = spawn_sss("challenge");
+    let mut sed = UnixStream::connect(&path).unwrap();
+    let id = Id::Other(10);
+    let open = |sed: &mut UnixStream, op| {
+        let msg = SecureSSSMessage {
+            dev_id: id,
+            op,
+            suite: SUITE,
+            caps: CAPS,
+        };
+        transport::open(sed, &msg).unwrap()
+    };
+    let prove = |sed: &mut UnixStream, op, nonce| {
+        let proof = SecureSSSProof {
+            dev_id: id,
+            op,
+            proof: challenge::prove(&SECRET_10, nonce, id),
+        };
+        transport::prove(sed, &proof, None).unwrap()
+    };
+    let op = |body: &[u8]| SecureSSSResponse::from_bytes(body).unwrap().op;
+
+    let first = open(&mut sed, SSSOp::Register);
+    assert_eq!((first.dev_id, first.op), (id, SSSOp::Challenge));
+
+    // each request is challenged afresh, so a proof over an earlier nonce is refused
+    let second = open(&mut sed, SSSOp::Register);
+    assert_ne!(first.nonce, second.nonce);
+    assert_eq!(
+        op(&prove(&mut sed, SSSOp::Register, &first.nonce)),
+        SSSOp::Already
+    );
+
+    // as is a proof of another operation than the one challenged
+    let third = open(&mut sed, SSSOp::Register);
+    assert_eq!(
+        op(&prove(&mut sed, SSSOp::Deregister, &third.nonce)),
+        SSSOp::Already
+    );
+
+    let fourth = open(&mut sed, SSSOp::Register);
+    let body = prove(&mut sed, SSSOp::Register, &fourth.nonce);
+    assert_eq!(op(&body), SSSOp::Register);
+
+    // the SSS tags its response over the nonce of the challenge it answers, so that it cannot be
+    // passed off as the response to any other
+    let (resp, tag) = body.split_at(body.len() - challenge::TAG);
+    assert!(challenge::authentic(
+        &SECRET_10,
+        &fourth.nonce,
+        id,
+        resp,
+        tag
+    ));
+    assert!(!challenge::authentic(
+        &SECRET_10,
+        &third.nonce,
+        id,
+        resp,
+        tag
+    ));
+    assert!(!challenge::authentic(
+        &SECRET_11,
+        &fourth.nonce,
+        id,
+        resp,
+        tag
+    ));
+}
+
+#[test]
+fn suite_is_selected_by_the_deployment() {
+    let path = spawn_sss_for("suite", deployment().with_suite(SUITE_CBC_CMAC));
+    let mut sed = UnixStream::connect(&path).unwrap();
+
+    // an SED built without the cmac feature could not verify the frames of the rest
+    let resp = transact_with_suite(
+        &mut sed,
+        10,
+        SSSOp::Register,
+        &SECRET_10,
+        SUITE_CBC_HMAC,
+        CAPS,
+    );
+    assert_eq!(resp.op, SSSOp::Already);
+    assert!(resp.secrets.is_none());
+
+    let resp = transact_with_suite(
+        &mut sed,
+        10,
+        SSSOp::Register,
+        &SECRET_10,
+        SUITE_CBC_CMAC,
+        CAPS,
+    );
+    assert_eq!(resp.op, SSSOp::Register);
+    assert!(resp.secrets.is_some());
+}
+
+#[test]
+fn capabilities_are_negotiated() {
+    let path = spawn_sss_for("caps", deployment().with_caps(CAP_HEADER_CRC));
+    let mut sed = UnixStream::connect(&path).unwrap();
+
+    // an SED without the header CRC could not interoperate with the rest of the deployment
+    let resp = transact_with_suite(&mut sed, 10, SSSOp::Register, &SECRET_10, SUITE, 0);
+    assert_eq!(resp.op, SSSOp::Already);
+    assert!(resp.secrets.is_none());
+
+    let resp = transact(&mut sed, 10, SSSOp::Register, &SECRET_10);
+    assert_eq!(resp.op, SSSOp::Register);
+    assert_eq!(resp.secrets.unwrap().caps, CAP_HEADER_CRC);
+}
+
+#[test]
+fn gcm_is_selected_by_the_deployment() {
+    let path = spawn_sss_for("gcm", deployment().with_caps(CAP_AES_GCM));
+    let mut sed_10 = UnixStream::connect(&path).unwrap();
+    let mut sed_11 = UnixStream::connect(&path).unwrap();
+
+    let mut handlers = [(&mut sed_10, 10, &SECRET_10), (&mut sed_11, 11, &SECRET_11)].map(
+        |(stream, id, secret)| {
+            let resp = transact(stream, id, SSSOp::Register, secret);
+            let secrets = resp.secrets.unwrap();
+            assert_eq!(secrets.caps & CAP_AES_GCM, CAP_AES_GCM);
+            GcmHandler::new(secrets.seed, secrets.aes_key)
+        },
+    );
+    let [sender, receiver] = &mut handlers;
+
+    let mut data = vec![0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let content = b"hello over gcm";
+    data[sender.content_offset()..][..content.len()].copy_from_slice(content);
+
+    let mut msg = Message {
+        tgt_id: Id::Other(11),
+        src_id: Id::Other(10),
+        len: content.len(),
+    };
+    msg.len = sender.encrypt(&mut data, msg, &scratch);
+    assert_eq!(receiver.verify(&data, msg, &scratch), Ok(()));
+    assert_eq!(
+        receiver.decrypt(&mut data, msg, &scratch),
+        Ok(content.len())
+    );
+    assert_eq!(&data[receiver.content_offset()..][..content.len()], content);
+}
+
+#[test]
+fn gcm_siv_is_selected_by_the_deployment() {
+    let path = spawn_sss_for("siv", deployment().with_caps(CAP_AES_GCM_SIV));
+    let mut sed_10 = UnixStream::connect(&path).unwrap();
+    let mut sed_11 = UnixStream::connect(&path).unwrap();
+
+    let [sender, receiver] = [(&mut sed_10, 10, &SECRET_10), (&mut sed_11, 11, &SECRET_11)].map(
+        |(stream, id, secret)| {
+            let resp = transact(stream, id, SSSOp::Register, secret);
+            let secrets = resp.secrets.unwrap();
+            assert_eq!(secrets.caps & CAP_AES_GCM_SIV, CAP_AES_GCM_SIV);
+            (secrets.seed, secrets.aes_key)
+        },
+    );
+    let mut sender = SivHandler::new(sender.0, sender.1);
+    let mut gcm = GcmHandler::new(receiver.0, receiver.1);
+    let mut receiver = SivHandler::new(receiver.0, receiver.1);
+
+    let mut data = vec![0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let content = b"hello over gcm-siv";
+    data[sender.content_offset()..][..content.len()].copy_from_slice(content);
+
+    let mut msg = Message {
+        tgt_id: Id::Other(11),
+        src_id: Id::Other(10),
+        len: content.len(),
+    };
+    msg.len = sender.encrypt(&mut data, msg, &scratch);
+
+    // the frames are laid out alike, but only a GCM-SIV handler opens them
+    assert_eq!(gcm.verify(&data, msg, &scratch), Ok(()));
+    assert_eq!(
+        gcm.decrypt(&mut data.clone(), msg, &scratch),
+        Err(Reason::BadMac)
+    );
+    assert_eq!(receiver.verify(&data, msg, &scratch), Ok(()));
+    assert_eq!(
+        receiver.decrypt(&mut data, msg, &scratch),
+        Ok(content.len())
+    );
+    assert_eq!(&data[receiver.content_offset()..][..content.len()], content);
+}
+
+#[test]
+fn broadcasts_are_signed_by_their_source() {
+    let deployment = deployment()
+        .with_caps(CAP_BROADCAST_SIGS)
+        .with_device(12, [12; 64])
+        .with_device(13, [13; 64])
+        .with_signing_seed(10, [0x10; 32])
+        .with_signing_seed(11, [0x11; 32])
+        .with_signing_seed(12, [0x12; 32]);
+    let path = spawn_sss_for("sigs", deployment);
+    let mut sed = UnixStream::connect(&path).unwrap();
+
+    // an SED without a signing key could not sign its broadcasts
+    let resp = transact(&mut sed, 13, SSSOp::Register, &[13; 64]);
+    assert_eq!(resp.op, SSSOp::Already);
+
+    let mut handlers = [10, 11, 12].map(|id| {
+        let secret = [u8::try_from(id).unwrap(); 64];
+        let msg = SecureSSSMessage {
+            dev_id: Id::Other(id),
+            op: SSSOp::Register,
+            suite: SUITE,
+            caps: CAPS,
+        };
+        let body = transport::exchange(&mut sed, &msg, &secret).unwrap();
+        let wrapped = SecureSSSResponse::from_bytes(&body)
+            .unwrap()
+            .secrets
+            .unwrap();
+        let secrets = keywrap::unwrap(&secret, Id::Other(id), wrapped).unwrap();
+        let keys = SecureSSSSigningKeys::from_bytes(&body[SecureSSSResponse::size()..]).unwrap();
+        assert!(keys.peers().all(|(peer, _)| peer != Id::Other(id)));
+        assert_eq!(keys.peers().count(), 2);
+
+        let keys = keys
+            .peers()
+            .fold(SigningKeys::new(keys.seed), |keys, (peer, public)| {
+                keys.with_peer(peer, public)
+            });
+        let suite = CryptoHandler::new(secrets.seed, secrets.aes_key, secrets.hmac_key);
+        SignedHandler::new(suite, Some(keys))
+    });
+    let [sender, receiver, forger] = &mut handlers;
+
+    let mut data = vec![0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let content = b"hello, signed";
+    let msg = Message {
+        tgt_id: Id::Broadcast,
+        src_id: Id::Other(10),
+        len: content.len(),
+    };
+
+    data[sender.content_offset()..][..content.len()].copy_from_slice(content);
+    let sent = Message {
+        len: sender.encrypt(&mut data, msg, &scratch),
+        ..msg
+    };
+    assert_eq!(receiver.verify(&data, sent, &scratch), Ok(()));
+    assert_eq!(
+        receiver.decrypt(&mut data, sent, &scratch),
+        Ok(content.len())
+    );
+    assert_eq!(&data[receiver.content_offset()..][..content.len()], content);
+
+    // SED 12 holds the deployment's keys, but not the signing key of SED 10; its first broadcast
+    // as SED 10 bears the counter already received, so the second is the one which is forged
+    forger.encrypt(&mut data, msg, &scratch);
+    data[forger.content_offset()..][..content.len()].copy_from_slice(content);
+    let forged = Message {
+        len: forger.encrypt(&mut data, msg, &scratch),
+        ..msg
+    };
+    assert_eq!(receiver.verify(&data, forged, &scratch), Ok(()));
+    assert_eq!(
+        receiver.decrypt(&mut data, forged, &scratch),
+        Err(Reason::BadSignature)
+    );
+}
+
+#[test]
+fn allowlists_follow_the_mission_groups() {
+    let deployment = deployment()
+        .with_caps(CAP_ALLOWLIST | CAP_BROADCAST_SIGS)
+        .with_device(12, [12; 64])
+        .with_signing_seed(10, [0x10; 32])
+        .with_signing_seed(11, [0x11; 32])
+        .with_signing_seed(12, [0x12; 32])
+        .with_group([10, 11])
+        .with_group([11, 12]);
+    let path = spawn_sss_for("allowlist", deployment);
+    let mut sed = UnixStream::connect(&path).unwrap();
+
+    let mut allowlist = |id: u16| {
+        let msg = SecureSSSMessage {
+            dev_id: Id::Other(id),
+            op: SSSOp::Register,
+            suite: SUITE,
+            caps: CAPS,
+        };
+        let body = transport::exchange(&mut sed, &msg, &[u8::try_from(id).unwrap(); 64]).unwrap();
+        let list = SecureSSSAllowlist::from_bytes(&body[SecureSSSResponse::size()..]).unwrap();
+
+        // the signing keys follow the allowlist, to the end of the response
+        let start = SecureSSSResponse::size() + list.serialised_len();
+        let keys = SecureSSSSigningKeys::from_bytes(&body[start..]).unwrap();
+        assert_eq!(keys.peers().count(), 2);
+        list.allowed().collect::<Vec<_>>()
+    };
+
+    // SED 11 is in both groups, so may talk to either of the others, which may not talk to each
+    // other
+    assert_eq!(allowlist(10), [Id::Other(11)]);
+    assert_eq!(allowlist(11), [Id::Other(10), Id::Other(12)]);
+    assert_eq!(allowlist(12), [Id::Other(11)]);
+
+    let mut policy = Policy::new(Id::Other(10));
+    let from = |src| MessageHeader {
+        tgt_id: Id::Other(10),
+        src_id: Id::Other(src),
+        len: 0,
+    };
+    assert!(policy.allow([Id::Other(11)].iter().copied()));
+    assert_eq!(policy.admit(Link::Radio, &from(11), 0), Ok(()));
+    assert_eq!(
+        policy.admit(Link::Radio, &from(12), 0),
+        Err(Reason::NotAllowed)
+    );
+}
+
+#[test]
+fn responses_are_sealed_by_ml_kem() {
+    let deployment = deployment()
+        .with_caps(CAP_PQ_KEM | CAP_BROADCAST_SIGS)
+        .with_signing_seed(10, [0x10; 32])
+        .with_signing_seed(11, [0x11; 32]);
+    let path = spawn_sss_for("pq", deployment);
+    let mut sed = UnixStream::connect(&path).unwrap();
+    let request = |id: u16| SecureSSSMessage {
+        dev_id: Id::Other(id),
+        op: SSSOp::Register,
+        suite: SUITE,
+        caps: CAPS | CAP_PQ_KEM,
+    };
+
+    // an SED without an encapsulation key would receive the keys as they are
+    let body = transport::exchange(&mut sed, &request(10), &SECRET_10).unwrap();
+    assert_eq!(
+        SecureSSSResponse::from_bytes(&body).unwrap().op,
+        SSSOp::Already
+    );
+
+    let mut ek = [0_u8; kem::ENCAPSULATION_KEY];
+    let pair = KeyPair::generate(&SECRET_10, &[1; 32], &mut ek);
+    let mut body =
+        transport::exchange_with_key(&mut sed, &request(10), &SECRET_10, Some(&ek)).unwrap();
+    let keys = 32 + 1 + SecureSSSSigningKeys::PEER_SIZE;
+    let sealed = body.len();
+    assert_eq!(sealed, SecureSSSResponse::size() + SEALING + keys);
+    assert!(!body.windows(16).any(|window| window == AES_KEY));
+    assert!(!body.windows(32).any(|window| window == [0x10; 32]));
+
+    // only the SED of the decapsulation key may open the response
+    let mut other = [0_u8; kem::ENCAPSULATION_KEY];
+    let imposter = KeyPair::generate(&SECRET_10, &[2; 32], &mut other);
+    assert_eq!(
+        imposter.open(Id::Other(10), &mut body.clone(), sealed),
+        None
+    );
+    assert_eq!(pair.open(Id::Other(11), &mut body.clone(), sealed), None);
+    let mut tampered = body.clone();
+    tampered[SecureSSSResponse::size() + 1] ^= 1;
+    assert_eq!(pair.open(Id::Other(10), &mut tampered, sealed), None);
+
+    let len = pair.open(Id::Other(10), &mut body, sealed).unwrap();
+    assert_eq!(len, SecureSSSResponse::size() + keys);
+    let resp = SecureSSSResponse::from_bytes(&body[..len]).unwrap();
+    assert_eq!(resp.op, SSSOp::Register);
+    let secrets = keywrap::unwrap(&SECRET_10, Id::Other(10), resp.secrets.unwrap()).unwrap();
+    assert_eq!(secrets.aes_key, AES_KEY);
+    assert_eq!(secrets.hmac_key, HMAC_KEY);
+    assert_eq!(secrets.caps, CAP_PQ_KEM | CAP_BROADCAST_SIGS);
+    let signing = SecureSSSSigningKeys::from_bytes(&body[SecureSSSResponse::size()..len]).unwrap();
+    assert_eq!(signing.seed, [0x10; 32]);
+    assert_eq!(signing.peers().next().unwrap().0, Id::Other(11));
+
+    // a malformed encapsulation key is refused as a missing one is
+    let resp = SecureSSSResponse::from_bytes(
+        &transport::exchange_with_key(&mut sed, &request(11), &SECRET_11, Some(&[0xFF; 800]))
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(resp.op, SSSOp::Already);
+}
+
+#[test]
+fn closing_the_connection_forgets_the_sed() {
+    let path = spawn_sss("forget");
+
+    let mut sed = UnixStream::connect(&path).unwrap();
+    register(&mut sed, 10, &SECRET_10);
+    drop(sed);
+
+    // registration must succeed again once the first connection has been handled as closed
+    let mut sed = UnixStream::connect(&path).unwrap();
+    let resp = (0..100)
+        .map(|_| {
+            thread::sleep(std::time::Duration::from_millis(10));
+            transact(&mut sed, 10, SSSOp::Register, &SECRET_10)
+        })
+        .find(|resp| resp.op == SSSOp::Register);
+    assert!(resp.is_some());
+}
+
+#[test]
+fn keys_are_rotated_by_the_sss() {
+    let (path, rotations) = spawn_rotating_sss("rotation");
+    let mut sed_10 = UnixStream::connect(&path).unwrap();
+    let mut sed_11 = UnixStream::connect(&path).unwrap();
+    let mut handler_10 = register(&mut sed_10, 10, &SECRET_10);
+    let mut handler_11 = register(&mut sed_11, 11, &SECRET_11);
+    // an SED which never takes up the keys of a later epoch
+    let mut stale = CryptoHandler::new([12; 32], AES_KEY, HMAC_KEY).with_epoch(EPOCH);
+
+    let rotate = |epoch| Keys {
+        epoch,
+        aes_key: [epoch as u8; 16],
+        hmac_key: [!epoch as u8; 64],
+    };
+    rotations.send(rotate(EPOCH + 1)).unwrap();
+
+    // SED 10 takes up the new keys first, and is read by SED 11 only once it does too, but reads
+    // SED 11 under the keys it held before in the meantime
+    let push = transport::read_push(&mut sed_10).unwrap();
+    assert_eq!(
+        (push.dev_id, push.op, push.epoch),
+        (Id::Other(10), SSSOp::Rotate, EPOCH + 1)
+    );
+    assert!(rotation::unwrap(&SECRET_11, &push).is_none());
+    let keys = rotation::unwrap(&SECRET_10, &push).unwrap();
+    assert!(handler_10.rotate(keys.epoch, &keys.aes_key, &keys.hmac_key));
+    transport::acknowledge(&mut sed_10, Id::Other(10), true).unwrap();
+
+    assert_eq!(
+        deliver(&mut handler_10, &mut handler_11, 10, 11),
+        Err(Reason::NoKey)
+    );
+    assert_eq!(deliver(&mut handler_11, &mut handler_10, 11, 10), Ok(()));
+
+    let push = transport::read_push(&mut sed_11).unwrap();
+    let keys = rotation::unwrap(&SECRET_11, &push).unwrap();
+    assert!(handler_11.rotate(keys.epoch, &keys.aes_key, &keys.hmac_key));
+    assert!(!handler_11.rotate(keys.epoch, &keys.aes_key, &keys.hmac_key));
+    transport::acknowledge(&mut sed_11, Id::Other(11), true).unwrap();
+
+    assert_eq!(deliver(&mut handler_10, &mut handler_11, 10, 11), Ok(()));
+    assert_eq!(deliver(&mut stale, &mut handler_11, 12, 11), Ok(()));
+
+    // the keys of an epoch are refused once superseded twice
+    rotations.send(rotate(EPOCH + 2)).unwrap();
+    for (stream, handler, secret) in [
+        (&mut sed_10, &mut handler_10, &SECRET_10),
+        (&mut sed_11, &mut handler_11, &SECRET_11),
+    ] {
+        let keys = rotation::unwrap(secret, &transport::read_push(stream).unwrap()).unwrap();
+        assert!(handler.rotate(keys.epoch, &keys.aes_key, &keys.hmac_key));
+    }
+    assert_eq!(deliver(&mut handler_10, &mut handler_11, 10, 11), Ok(()));
+    assert_eq!(
+        deliver(&mut stale, &mut handler_11, 12, 11),
+        Err(Reason::NoKey)
+    );
+
+    // SEDs registering after a rotation are given the keys of the new epoch
+    let mut rejoined = UnixStream::connect(&path).unwrap();
+    assert_eq!(
+        deregister(&mut rejoined, 10, &SECRET_10, &mut handler_10),
+        SSSOp::Erased
+    );
+    let resp = transact(&mut rejoined, 10, SSSOp::Register, &SECRET_10);
+    let secrets = resp.secrets.unwrap();
+    assert_eq!(
+        (secrets.epoch, secrets.aes_key),
+        (EPOCH + 2, [(EPOCH + 2) as u8; 16])
+    );
+}
+
+#[test]
+fn deregistered_seds_are_revoked_by_the_sss() {
+    let path = spawn_sss_for("revocation", deployment().with_revocation());
+    let mut sed_10 = UnixStream::connect(&path).unwrap();
+    let mut sed_11 = UnixStream::connect(&path).unwrap();
+    let mut policy = Policy::new(Id::Other(11));
+    let from_10 = MessageHeader {
+        tgt_id: Id::Other(11),
+        src_id: Id::Other(10),
+        len: 0,
+    };
+
+    // each SED is pushed the list as it stands once it registers, which is empty
+    let mut handler_10 = register(&mut sed_10, 10, &SECRET_10);
+    let body = transport::read_revocation(&mut sed_10).unwrap();
+    let push = SecureSSSRevocation::from_bytes(&body).unwrap();
+    assert_eq!(
+        (push.dev_id, push.serial, push.count()),
+        (Id::Other(10), 0, 0)
+    );
+    register(&mut sed_11, 11, &SECRET_11);
+    transport::read_revocation(&mut sed_11).unwrap();
+
+    // SED 10 is revoked at SED 11 once it deregisters, by a list which only SED 11 can verify
+    assert_eq!(
+        deregister(&mut sed_10, 10, &SECRET_10, &mut handler_10),
+        SSSOp::Erased
+    );
+    let stale = transport::read_revocation(&mut sed_11).unwrap();
+    let push = SecureSSSRevocation::from_bytes(&stale).unwrap();
+    assert_eq!(
+        (push.dev_id, push.op, push.serial),
+        (Id::Other(11), SSSOp::Revoke, 1)
+    );
+    assert_eq!(push.revoked().collect::<Vec<_>>(), [Id::Other(10)]);
+    assert!(!revocation::verify(&SECRET_10, &push));
+    assert!(revocation::verify(&SECRET_11, &push));
+    assert!(policy.revoke(push.serial, push.revoked()));
+    assert_eq!(policy.admit(Link::Radio, &from_10, 0), Err(Reason::Revoked));
+
+    // a tampered list fails verification
+    let mut tampered = stale.clone();
+    tampered[SecureSSSRevocation::size(0) - 32] ^= 1;
+    let push = SecureSSSRevocation::from_bytes(&tampered).unwrap();
+    assert!(!revocation::verify(&SECRET_11, &push));
+
+    // SED 10 is reinstated once it registers once more, and the older list cannot be replayed
+    register(&mut sed_10, 10, &SECRET_10);
+    let body = transport::read_revocation(&mut sed_10).unwrap();
+    assert_eq!(SecureSSSRevocation::from_bytes(&body).unwrap().serial, 2);
+    let body = transport::read_revocation(&mut sed_11).unwrap();
+    let push = SecureSSSRevocation::from_bytes(&body).unwrap();
+    assert_eq!((push.serial, push.count()), (2, 0));
+    assert!(revocation::verify(&SECRET_11, &push));
+    assert!(policy.revoke(push.serial, push.revoked()));
+    assert_eq!(policy.admit(Link::Radio, &from_10, 0), Ok(()));
+
+    let push = SecureSSSRevocation::from_bytes(&stale).unwrap();
+    assert!(!policy.revoke(push.serial, push.revoked()));
+    assert_eq!(policy.admit(Link::Radio, &from_10, 0), Ok(()));
+}
+
+#[test]
+fn seds_are_rekeyed_without_deregistering() {
+    let (path, registered) = spawn_prompting_sss("rekey");
+    let mut sed_10 = UnixStream::connect(&path).unwrap();
+    let mut sed_11 = UnixStream::connect(&path).unwrap();
+
+    // only a registered SED may rekey
+    let resp = transact(&mut sed_10, 10, SSSOp::Rekey, &SECRET_10);
+    assert_eq!(resp.op, SSSOp::Already);
+
+    let resp = transact(&mut sed_10, 10, SSSOp::Register, &SECRET_10);
+    let first = resp.secrets.unwrap();
+    let mut handler_11 = register(&mut sed_11, 11, &SECRET_11);
+
+    // the SED is given the deployment's keys and a fresh seed, and stays registered
+    let resp = transact(&mut sed_10, 10, SSSOp::Rekey, &SECRET_10);
+    assert_eq!(resp.op, SSSOp::Rekey);
+    let secrets = resp.secrets.unwrap();
+    assert_eq!(
+        (secrets.aes_key, secrets.hmac_key, secrets.epoch),
+        (AES_KEY, HMAC_KEY, EPOCH)
+    );
+    assert_ne!(secrets.seed, first.seed);
+    let resp = transact(&mut sed_10, 10, SSSOp::Register, &SECRET_10);
+    assert_eq!(resp.op, SSSOp::Already);
+
+    let mut handler_10 = CryptoHandler::new(secrets.seed, secrets.aes_key, secrets.hmac_key)
+        .with_epoch(secrets.epoch);
+    assert_eq!(deliver(&mut handler_10, &mut handler_11, 10, 11), Ok(()));
+    assert_eq!(deliver(&mut handler_11, &mut handler_10, 11, 10), Ok(()));
+
+    // the SSS may prompt a registered SED to rekey, which it does with a request of its own
+    assert_eq!(transport::prompt_rekey(&registered, &[11, 12]), 1);
+    let prompt = transport::read_prompt(&mut sed_11).unwrap();
+    assert_eq!((prompt.dev_id, prompt.op), (Id::Other(11), SSSOp::Rekey));
+    let resp = transact(&mut sed_11, 11, SSSOp::Rekey, &SECRET_11);
+    assert_eq!(resp.op, SSSOp::Rekey);
+
+    assert_eq!(
+        deregister(&mut sed_10, 10, &SECRET_10, &mut handler_10),
+        SSSOp::Erased
+    );
+    let resp = transact(&mut sed_10, 10, SSSOp::Rekey, &SECRET_10);
+    assert_eq!(resp.op, SSSOp::Already);
+}
+
+#[test]
+fn keepalives_tell_whether_the_sed_is_registered() {
+    let path = spawn_sss("keepalive");
+    let mut sed = UnixStream::connect(&path).unwrap();
+
+    let mut handler = register(&mut sed, 10, &SECRET_10);
+    assert!(transport::keep_alive(&mut sed, Id::Other(10), &SECRET_10, [1; 16]).unwrap());
+
+    // the answer is tagged under the SED's secret, so is not taken from anyone else
+    let forged = transport::keep_alive(&mut sed, Id::Other(10), &SECRET_11, [2; 16]);
+    assert_eq!(forged.unwrap_err().kind(), ErrorKind::InvalidData);
+
+    // once deregistered, the SED is told that it is no longer registered
+    assert_eq!(
+        deregister(&mut sed, 10, &SECRET_10, &mut handler),
+        SSSOp::Erased
+    );
+    assert!(!transport::keep_alive(&mut sed, Id::Other(10), &SECRET_10, [3; 16]).unwrap());
+
+    // the SSS notes when each SED last kept alive, and does not answer SEDs it does not know
+    let mut sss = Sss::new(deployment(), [0; 32]);
+    let keepalive = |id| SecureSSSKeepalive {
+        dev_id: Id::Other(id),
+        op: SSSOp::Keepalive,
+        nonce: [4; 16],
+    };
+    assert!(sss.last_seen(11).is_none());
+    let alive = sss.keepalive(&keepalive(11)).unwrap();
+    assert_eq!(alive.op, SSSOp::Deregister);
+    assert!(keepalive::verify(&SECRET_11, &[4; 16], &alive));
+    assert!(sss.last_seen(11).is_some());
+    assert!(sss.keepalive(&keepalive(12)).is_none());
+}
+
+#[test]
+fn deregistration_awaits_the_erasure_of_keys() {
+    let path = spawn_sss("erasure");
+    let mut sed = UnixStream::connect(&path).unwrap();
+    let mut handler = register(&mut sed, 10, &SECRET_10);
+
+    // a deregistration which is answered but never attested to leaves the SED registered
+    let resp = transact(&mut sed, 10, SSSOp::Deregister, &SECRET_10);
+    assert_eq!(resp.op, SSSOp::Deregister);
+    let resp = transact(&mut sed, 10, SSSOp::Rekey, &SECRET_10);
+    assert_eq!(resp.op, SSSOp::Rekey);
+
+    // as does an attestation to erasing keys other than the deployment's, which are kept
+    let mut impostor = CryptoHandler::new([0; 32], AES_KEY, [0; 64]).with_epoch(EPOCH);
+    assert_eq!(
+        deregister(&mut sed, 10, &SECRET_10, &mut impostor),
+        SSSOp::Already
+    );
+    assert!(impostor.attest(&[0; 32], Id::Other(10)).is_some());
+    let resp = transact(&mut sed, 10, SSSOp::Rekey, &SECRET_10);
+    assert_eq!(resp.op, SSSOp::Rekey);
+
+    // the SED only erases its keys once the SSS acknowledges its attestation
+    assert_eq!(
+        deregister(&mut sed, 10, &SECRET_10, &mut handler),
+        SSSOp::Erased
+    );
+    let resp = transact(&mut sed, 10, SSSOp::Rekey, &SECRET_10);
+    assert_eq!(resp.op, SSSOp::Already);
+}
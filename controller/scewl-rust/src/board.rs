@@ -0,0 +1,97 @@
+//! The [board](scewl::board::Board) of the controller, i.e. the lm3s6965 itself
+
+use core::ptr::addr_of;
+use core::slice;
+
+use cortex_m::asm;
+use cortex_m::peripheral::SCB;
+
+use scewl::board::Board;
+use scewl::crashlog::Crash;
+
+#[cfg(feature = "mpu")]
+use crate::mpu;
+#[cfg(feature = "interrupt-driven")]
+use crate::rx;
+#[cfg(feature = "watchdog")]
+use crate::watchdog::Watchdog;
+use crate::{crashlog, entropy};
+
+extern "C" {
+    /// The start of the `.text` section, as placed by `cortex-m-rt`
+    static _stext: u8;
+    /// The end of the `.text` section, as placed by `cortex-m-rt`
+    static __etext: u8;
+}
+
+/// The lm3s6965, on which the controller runs
+///
+/// The registration secret is only locked away with the `mpu` feature, and the run loop only
+/// sleeps until the next interrupt with the `interrupt-driven` feature.
+#[derive(Default)]
+pub struct Lm3s6965 {
+    /// The watchdog kicked on every pass of the run loop, if it is running
+    #[cfg(feature = "watchdog")]
+    watchdog: Option<Watchdog>,
+}
+
+impl Lm3s6965 {
+    /// Kicks the given watchdog on every pass of the run loop, so that it only resets the
+    /// controller should the loop wedge (see the [watchdog module](crate::watchdog))
+    #[cfg(feature = "watchdog")]
+    pub fn with_watchdog(mut self, watchdog: Watchdog) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+}
+
+impl Board for Lm3s6965 {
+    fn delay(&self, cycles: u32) {
+        asm::delay(cycles);
+    }
+
+    fn stir(&self, sample: u32) {
+        entropy::stir(sample);
+    }
+
+    fn unlock_secret(&self) {
+        #[cfg(feature = "mpu")]
+        mpu::unlock_secret();
+    }
+
+    fn lock_secret(&self) {
+        #[cfg(feature = "mpu")]
+        mpu::lock_secret();
+    }
+
+    fn kick(&self) {
+        #[cfg(feature = "watchdog")]
+        if let Some(watchdog) = self.watchdog.as_ref() {
+            watchdog.kick();
+        }
+    }
+
+    #[allow(unused_variables)] // only waited on with the `interrupt-driven` feature
+    fn idle(&self, ready: &dyn Fn() -> bool) {
+        #[cfg(feature = "interrupt-driven")]
+        rx::sleep_unless(ready);
+    }
+
+    /// The code of the firmware, i.e. the `.text` section
+    fn code(&self) -> &'static [u8] {
+        // SAFETY: the section lies in flash, between the symbols which cortex-m-rt places around
+        // it, and is never written by the firmware
+        unsafe {
+            let start = addr_of!(_stext);
+            slice::from_raw_parts(start, addr_of!(__etext) as usize - start as usize)
+        }
+    }
+
+    fn crash(&self) -> Option<&'static Crash> {
+        crashlog::previous()
+    }
+
+    fn reset(&self) -> ! {
+        SCB::sys_reset()
+    }
+}
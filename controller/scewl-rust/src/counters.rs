@@ -0,0 +1,60 @@
+//! The reservations of the message counters of the secure handlers, kept in flash so that the
+//! counters survive a reset
+//!
+//! With the `persist-counters` feature, the authentication handler gives the crypto handler it
+//! selects at registration the [`FLASH`] store, in which the handler
+//! [reserves](scewl::secure::Checkpoints) the counters of each peer ahead of their use. Once the
+//! SED resets, or registers anew, its handler resumes from the reservations: it sends no counter
+//! its peers have already received, and accepts no frame it had received before.
+//!
+//! The reservation of each peer (and that of the broadcasts this SED sends, under the broadcast id)
+//! is kept in the [key-value store](scewl::kv) under the key [`COUNTERS`] plus its id,
+//! preceded by the id itself: two ids which differ only in their top bit share a key, and a
+//! reservation is only ever loaded for the id which stored it. Each record takes 36 bytes of a
+//! page, so a deployment of some two dozen peers fills the store; the reservations which cannot be
+//! stored are logged, and their counters restart from zero as without the feature.
+
+use scewl::codec::Id;
+use scewl::kv::COUNTERS;
+use scewl::secure::{Checkpoints, Reserved};
+use scewl::warn;
+
+use crate::kv;
+
+/// The reservations kept in the flash store
+pub static FLASH: Flash = Flash(());
+
+/// The [store](Checkpoints) of the reservations in flash, as described in the [module
+/// documentation](self)
+pub struct Flash(());
+
+/// The length of the value of a reservation: the id of its peer, then its bounds
+const VALUE: usize = 2 + Reserved::size();
+
+/// The key under which the reservation of the peer is kept
+fn key(peer: Id) -> u16 {
+    COUNTERS | (u16::from(peer) & !COUNTERS)
+}
+
+impl Checkpoints for Flash {
+    fn load(&self, peer: Id) -> Option<Reserved> {
+        let mut value = [0; VALUE];
+        match kv::open().get(key(peer), &mut value) {
+            Some(VALUE) if value[..2] == u16::from(peer).to_le_bytes() => {
+                Reserved::from_bytes(&value[2..])
+            }
+            _ => None,
+        }
+    }
+
+    fn store(&self, peer: Id, reserved: Reserved) {
+        let mut value = [0; VALUE];
+        value[..2].copy_from_slice(&u16::from(peer).to_le_bytes());
+        reserved.to_bytes(&mut value[2..]);
+
+        #[allow(unused_variables)] // only logged when a logging transport is enabled
+        if let Err(err) = kv::open().set(key(peer), &value) {
+            warn!("Could not reserve the counters of {:?}: {}", peer, err);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Persists the location and message of a panic across the reset which follows it
+//!
+//! The panic handler [records](record) the [panic](Crash) in the persistent region of RAM, which
+//! build.rs reserves at the top of RAM when generating `memory.x` and which is therefore neither
+//! zeroed nor initialised at boot. Once the controller has reset, the record is [collected](init)
+//! at boot, after which it is [available](previous) to the rest of the firmware (e.g. to be
+//! logged, or to be reported by a diagnostic command) for the rest of that boot.
+//!
+//! A record moves through three states, identified by its magic:
+//!
+//!  - `PANICKED`: written by the panic handler, immediately before the reset
+//!  - `COLLECTED`: the record was found at boot, i.e. the previous boot ended in this panic
+//!  - anything else: there is no record; e.g. after power-on, or after a reset which was not
+//!    preceded by a panic, the stale record of an older boot is discarded
+
+use core::mem::MaybeUninit;
+#[cfg(not(any(feature = "semihosted", feature = "panic-halt")))]
+use core::panic::PanicInfo;
+use core::ptr::{self, addr_of, addr_of_mut};
+
+use scewl::crashlog::Crash;
+
+/// The magic of a record written by the panic handler, but not yet collected
+const PANICKED: u32 = 0x5041_4E43; // "PANC"
+
+/// The magic of a record collected at boot, which describes the panic ending the previous boot
+const COLLECTED: u32 = 0x434F_4C4C; // "COLL"
+
+/// A panic, as kept in the reserved region
+#[derive(Copy, Clone)]
+#[repr(C)]
+struct Record {
+    /// The state of this record
+    magic: u32,
+    /// The panic itself
+    crash: Crash,
+}
+
+/// The reserved region, which survives resets
+#[link_section = ".persist.crashlog"]
+static mut RECORD: MaybeUninit<Record> = MaybeUninit::uninit();
+
+/// Records the given panic in the reserved region, to be collected at the next boot
+///
+/// This is intended to be called only by the panic handler, immediately before a reset.
+#[cfg(not(any(feature = "semihosted", feature = "panic-halt")))]
+pub fn record(info: &PanicInfo<'_>) {
+    let record = Record {
+        magic: PANICKED,
+        crash: Crash::new(info),
+    };
+
+    // SAFETY: the panic handler does not return, so nothing else accesses the region afterwards
+    unsafe {
+        ptr::write_volatile(addr_of_mut!(RECORD).cast::<Record>(), record);
+    }
+}
+
+/// Collects the record of a panic which ended the previous boot, if any; to be called once at boot
+///
+/// Any older record is discarded, so that each panic is only ever reported for the boot which
+/// immediately follows it.
+pub fn init() -> Option<&'static Crash> {
+    // SAFETY: this is called once at boot, before anything else may access the region; every bit
+    // pattern is a valid Record, and its lengths are validated before use
+    unsafe {
+        let record = addr_of_mut!(RECORD).cast::<Record>();
+        let magic = ptr::read_volatile(addr_of!((*record).magic));
+
+        ptr::write_volatile(
+            addr_of_mut!((*record).magic),
+            if magic == PANICKED && (*record).crash.valid() {
+                COLLECTED
+            } else {
+                0
+            },
+        );
+    }
+
+    previous()
+}
+
+/// The record of the panic which ended the previous boot, if any, once [collected](init)
+pub fn previous() -> Option<&'static Crash> {
+    // SAFETY: the region is only written at boot and by the panic handler, which never returns
+    let record = unsafe { &*addr_of!(RECORD).cast::<Record>() };
+    (record.magic == COLLECTED).then_some(&record.crash)
+}
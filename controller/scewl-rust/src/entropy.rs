@@ -0,0 +1,93 @@
+//! The [entropy source](scewl::secure::Entropy) of the firmware, from which the secure handlers
+//! reseed their CSPRNGs
+//!
+//! The lm3s6965 has no true random number generator, so entropy is gathered from what little
+//! varies between boots and between SEDs:
+//!
+//!  - The pool, a few bytes of the persistent region of RAM (see [crashlog](crate::crashlog)),
+//!    which hold the power-on state of the SRAM after a power cycle, and the output of the last
+//!    [fill](Entropy::fill) after a reset, so that no two boots since the last power cycle start
+//!    from the same pool
+//!  - The timing of the frames received, as [stirred](stir) in by the controller: the current value
+//!    of SysTick when each frame arrives depends on the CPU, the radio, and the SSS, none of which
+//!    run from the clock of this SED
+//!  - The current value of SysTick at each fill, between which the controller does a varying
+//!    amount of work
+//!
+//! None of these may be relied upon alone (and under emulation, the SRAM is zeroed at power-on),
+//! which is why the entropy is only ever [mixed](scewl::secure::Entropy) into seeds which are
+//! already secret. The ADC is not sampled, as QEMU does not emulate it, and the lm3s6965's internal
+//! temperature sensor varies too slowly between samples to be worth its power.
+
+use core::cell::Cell;
+use core::mem::MaybeUninit;
+use core::ptr::{self, addr_of_mut};
+
+use cortex_m::interrupt::{self, Mutex};
+use cortex_m::peripheral::SYST;
+use sha2::{Digest, Sha256};
+
+use scewl::secure::Entropy;
+
+/// The label which prefixes the input from which entropy is drawn
+const FILL_LABEL: &[u8] = b"SCEWL entropy";
+
+/// The label which prefixes the input from which the next pool is derived
+const POOL_LABEL: &[u8] = b"SCEWL pool";
+
+/// The number of SysTick samples taken at each fill
+const SAMPLES: usize = 8;
+
+/// The pool, which survives resets
+#[link_section = ".persist.entropy"]
+static mut POOL: MaybeUninit<[u8; 32]> = MaybeUninit::uninit();
+
+/// The accumulated samples [stirred](stir) in since boot
+static STIRRED: Mutex<Cell<u64>> = Mutex::new(Cell::new(0));
+
+/// Stirs a sample, along with the current value of SysTick, into the accumulated samples; cheap
+/// enough to be called on every frame received
+pub fn stir(sample: u32) {
+    let sample = u64::from(sample) << 32 | u64::from(SYST::get_current());
+    interrupt::free(|cs| {
+        let stirred = STIRRED.borrow(cs);
+        // a multiply-rotate, which need only spread the samples, as they are hashed before use
+        stirred.set(
+            (stirred.get() ^ sample)
+                .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                .rotate_left(29),
+        );
+    });
+}
+
+/// The runtime entropy source of the firmware, as described in the [module documentation](self)
+pub struct Runtime;
+
+/// The runtime entropy source, with which the authentication handler builds the secure handlers
+pub static RUNTIME: Runtime = Runtime;
+
+impl Entropy for Runtime {
+    fn fill(&self, buf: &mut [u8; 32]) {
+        interrupt::free(|cs| {
+            // SAFETY: the pool is only accessed here, with interrupts disabled; every bit pattern
+            // is a valid array of bytes
+            let pool = unsafe { ptr::read_volatile(addr_of_mut!(POOL).cast::<[u8; 32]>()) };
+
+            let mut sha = Sha256::new();
+            sha.update(FILL_LABEL);
+            sha.update(pool);
+            sha.update(STIRRED.borrow(cs).get().to_le_bytes());
+            for _ in 0..SAMPLES {
+                sha.update(SYST::get_current().to_le_bytes());
+            }
+            buf.copy_from_slice(&sha.finalize());
+
+            let mut sha = Sha256::new();
+            sha.update(POOL_LABEL);
+            sha.update(&buf[..]);
+            let next: [u8; 32] = sha.finalize().into();
+            // SAFETY: as above
+            unsafe { ptr::write_volatile(addr_of_mut!(POOL).cast::<[u8; 32]>(), next) };
+        });
+    }
+}
@@ -0,0 +1,127 @@
+//! A driver for the flash controller of the lm3s6965, with which the firmware [erases](erase) and
+//! [programs](program) the flash pages that build.rs reserves for what it records at runtime, i.e.
+//! the [key-value store](scewl::kv)
+//!
+//! The flash is erased a page of [`PAGE`] bytes at a time, which sets every bit; programming a
+//! word then clears the bits which are clear in the value written, so a word may only be
+//! programmed once between erasures. The core stalls on any access to the flash while it is being
+//! erased or programmed, so neither operation returns until it has completed; each is then checked
+//! by reading the flash back.
+//!
+//! The flash controller is unaffected by the [MPU](crate::mpu), which only restricts the accesses
+//! of the core: the flash remains read-only to the firmware itself.
+
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use core::ptr;
+
+use volatile_register::{RO, RW};
+
+/// The size of a flash page, the unit of erasure, in bytes
+pub const PAGE: usize = 1024;
+
+/// The value of a word of erased flash
+pub const ERASED: u32 = 0xFFFF_FFFF;
+
+/// The address of the flash controller
+const FLASH_CTRL: usize = 0x400F_D000;
+
+/// `FMC`: the key which must accompany every command written
+const FMC_WRKEY: u32 = 0xA442 << 16;
+/// `FMC`: programs the word in `FMD` to the address in `FMA`
+const FMC_WRITE: u32 = 1;
+/// `FMC`: erases the page at the address in `FMA`
+const FMC_ERASE: u32 = 1 << 1;
+
+/// `FCRIS` and `FCMISC`: a command attempted to erase or program protected flash
+const FCRIS_ARIS: u32 = 1;
+/// `FCRIS` and `FCMISC`: a command has completed
+const FCRIS_PRIS: u32 = 1 << 1;
+
+/// The registers of the flash controller
+#[repr(C)]
+struct FlashCtrl {
+    /// Flash memory address register
+    fma: RW<u32>,
+    /// Flash memory data register
+    fmd: RW<u32>,
+    /// Flash memory control register
+    fmc: RW<u32>,
+    /// Flash controller raw interrupt status register
+    fcris: RO<u32>,
+    /// Flash controller interrupt mask register
+    fcim: RW<u32>,
+    /// Flash controller masked interrupt status and clear register
+    fcmisc: RW<u32>,
+}
+
+/// Error type for flash operations
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The flash is protected from being erased or programmed
+    Protected,
+    /// The flash did not read back as erased or programmed
+    Verify,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Error::Protected => write!(f, "flash is protected"),
+            Error::Verify => write!(f, "flash did not read back as written"),
+        }
+    }
+}
+
+/// Runs a command of the flash controller on the given address, waiting for it to complete
+fn run(address: usize, command: u32, data: u32) -> Result<(), Error> {
+    // SAFETY: the flash controller is only ever used from the main loop, through this function
+    let ctrl = unsafe { &*(FLASH_CTRL as *const FlashCtrl) };
+
+    // SAFETY: the caller is responsible for the address, and the command is one of this module's
+    #[allow(clippy::cast_possible_truncation)] // addresses are 32 bits wide on the controller
+    unsafe {
+        ctrl.fcmisc.write(FCRIS_ARIS | FCRIS_PRIS);
+        ctrl.fma.write(address as u32);
+        ctrl.fmd.write(data);
+        ctrl.fmc.write(FMC_WRKEY | command);
+    }
+    while ctrl.fmc.read() & command != 0 {}
+
+    if ctrl.fcris.read() & FCRIS_ARIS == 0 {
+        Ok(())
+    } else {
+        Err(Error::Protected)
+    }
+}
+
+/// Erases the flash page at the given address, which must be aligned to [`PAGE`]
+///
+/// # Safety
+///
+/// The page must be reserved for the caller, i.e. hold none of the firmware, and not be borrowed
+/// by any reference for as long as it is erased.
+pub unsafe fn erase(page: *const u32) -> Result<(), Error> {
+    run(page as usize, FMC_ERASE, 0)?;
+
+    let erased = (0..PAGE / 4).all(|word| ptr::read_volatile(page.add(word)) == ERASED);
+    if erased {
+        Ok(())
+    } else {
+        Err(Error::Verify)
+    }
+}
+
+/// Programs the word of flash at the given address, which should be erased
+///
+/// # Safety
+///
+/// As for [`erase`], the word must be reserved for the caller and borrowed by no reference.
+pub unsafe fn program(word: *const u32, value: u32) -> Result<(), Error> {
+    run(word as usize, FMC_WRITE, value)?;
+
+    if ptr::read_volatile(word) == value {
+        Ok(())
+    } else {
+        Err(Error::Verify)
+    }
+}
@@ -5,24 +5,36 @@
 //! implementation, where some amount of code review was used to extract the [UART{0,1,2} addresses](https://github.com/mitre-cyber-academy/2021-ectf-insecure-example/blob/master/controller/lm3s/lm3s_cmsis.h#L876)
 //! (later confirmed by reviewing the [original specification](https://www.ti.com/lit/ds/symlink/lm3s6965.pdf))
 //! as well as the [struct defining the UART peripheral](https://github.com/mitre-cyber-academy/2021-ectf-insecure-example/blob/master/controller/lm3s/lm3s_cmsis.h#L620).
-//! The addresses for the memory-mapped UART peripherals were stored in the [INTF](INTF) enum and
-//! these addresses were upcasted to mutable [UART](UART) references for use internally. Further
+//! The addresses for the memory-mapped UART peripherals of each [line](INTF) were stored in
+//! [`address`] and these addresses were upcasted to mutable [UART](UART) references for use internally. Further
 //! details, including the research that went into defining each type, can be found in their
 //! respective type documentation.
 //!
+//! The controller reads and writes the UARTs through the [`Transport`] trait, which [`Interface`]
+//! implements; it knows of nothing specific to the lm3s6965.
+//!
 //! It was unnecessary to provide the device vector table or interrupt bindings as these are
 //! helpfully defined for us by the [lm3s6965 crate](https://github.com/japaric/lm3s6965/blob/master/src/lib.rs)
 //! (thanks, [Jorge Aparicio](https://github.com/japaric)!).
 
-use core::fmt::Formatter;
-use core::fmt::{Debug, Result as FmtResult};
-use core::result::Result as CoreResult;
+use core::fmt::{Debug, Formatter, Result as FmtResult};
+use core::ptr;
 
-use cortex_m::asm;
 use volatile_register::{RO, RW, WO};
 
-use crate::interface::Error::{NoData, SomeData};
+use scewl::fatal::Fatal;
+#[cfg(feature = "pipelined")]
+use scewl::queue::Producer;
+use scewl::transport::Error::NoData;
+use scewl::transport::{Links, Result, Transport, INTF};
+
 use crate::interface::RWStatusMask::{RXFE, TXFF};
+#[cfg(feature = "pipelined")]
+use crate::rx;
+
+/// The receive and receive timeout interrupts, in the interrupt mask and clear registers
+#[cfg(feature = "pipelined")]
+const RX_INTERRUPTS: u32 = 0x50;
 
 /// The UART struct as specified by the CMSIS specification (and, more specifically, [line 620 of `lm3s_cmsis.h`](https://github.com/mitre-cyber-academy/2021-ectf-insecure-example/blob/master/controller/lm3s/lm3s_cmsis.h#L620))
 ///
@@ -45,7 +57,7 @@ struct UART {
     fr: RO<u32>,
     /// A reserved region with no explicit use
     reserved2: [u8; 4],
-    /// UART IrDA low-power register
+    /// UART `IrDA` low-power register
     ilpr: RW<u32>,
     /// Integer baud rate divisor register
     ibrd: RW<u32>,
@@ -77,38 +89,23 @@ pub enum RWStatusMask {
     TXFF = 0x20,
 }
 
-/// Memory-mapped UART peripheral addresses for the different serial lines, which will then be
+/// The address of the memory-mapped UART peripheral of each serial line, which will then be
 /// copied to the sockets for the respective data lines being emulated
-#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Copy, Clone)]
-pub enum INTF {
-    /// Address of the UART which connects to the CPU
-    CPU = 0x4000_C000,
-    /// Address of the UART which connects to the SSS
-    SSS = 0x4000_D000,
-    /// Address of the UART which connects to the radio
-    RAD = 0x4000_E000,
-}
-
-/// Generic error type for interface operations
-#[allow(dead_code)]
-#[derive(Debug)]
-pub enum Error {
-    /// An unknown error occurred during the interface operation
-    Unknown,
-    /// No data was received during the read operation
-    NoData,
-    /// Only some data was received during the read operation
-    SomeData(usize),
+fn address(intf: INTF) -> usize {
+    match intf {
+        INTF::CPU => 0x4000_C000,
+        INTF::SSS => 0x4000_D000,
+        INTF::RAD => 0x4000_E000,
+    }
 }
 
-/// Result type for interface operations
-pub type Result<T> = CoreResult<T, Error>;
-
 /// Wrapper type for interfacing with the UART peripherals
 ///
 /// This type is effectively equivalent to the struct defined in the original C implementation, but
 /// methods are defined on the interface instead to restrict operations to a theoretically safe
 /// subset of operations on the UART peripheral.
+///
+/// Blocking reads wait forever, unless the interface is [timed](scewl::transport::Timed).
 pub struct Interface {
     /// The UART adapter to be manipulated by this wrapper
     uart: &'static mut UART,
@@ -125,7 +122,7 @@ impl Interface {
         // memory-mapped UART peripherals, and the struct has been confirmed as the correct per the
         // TI specification linked above
         unsafe {
-            let uart = &mut *(intf as usize as *mut UART);
+            let uart = &mut *(address(intf) as *mut UART);
             uart.ctl.write(uart.ctl.read() & 0xffff_fffe);
             uart.ibrd.write((uart.ibrd.read() & 0xffff_0000) | 0x000a);
             uart.fbrd.write((uart.fbrd.read() & 0xffff_0000) | 0x0036);
@@ -135,97 +132,130 @@ impl Interface {
         }
     }
 
-    /// Determines if data is available to be read
+    /// Instantiates the interfaces of every UART, as the [links](Links) of a controller
+    pub fn links() -> Links<Self> {
+        Links {
+            cpu: Interface::new(INTF::CPU),
+            sss: Interface::new(INTF::SSS),
+            rad: Interface::new(INTF::RAD),
+        }
+    }
+
+    /// Determines if data is waiting in the UART's receive FIFO
     #[inline]
-    pub fn avail(&self) -> bool {
+    fn fifo_avail(&self) -> bool {
         self.uart.fr.read() & (RXFE as u32) == 0
     }
 
-    /// Reads a byte from the UART data register, optionally blocking
-    pub fn readb(&mut self, blocking: bool) -> Result<u8> {
-        while blocking && !self.avail() {}
+    /// The UART which this interface names, should it be [received by interrupt](rx)
+    #[cfg(feature = "pipelined")]
+    #[inline]
+    fn queued(&self) -> Option<INTF> {
+        let intf = self.named();
+        rx::started(intf).then_some(intf)
+    }
+
+    /// Moves the bytes waiting in the UART's receive FIFO into the queue, until either is
+    /// exhausted; returns whether the FIFO was emptied
+    ///
+    /// This is intended to be called only by the receive interrupt (see the [rx](rx) module).
+    #[cfg(feature = "pipelined")]
+    pub fn drain<const N: usize>(&mut self, queue: &mut Producer<'_, u8, N>) -> bool {
+        while self.fifo_avail() {
+            if queue.is_full() {
+                return false;
+            }
 
-        if self.avail() {
             #[allow(clippy::cast_possible_truncation)]
             // truncation reviewed; this will only ever be a single byte
-            Ok(self.uart.dr.read() as u8)
-        } else {
-            Err(NoData)
+            queue.push(self.uart.dr.read() as u8);
         }
+
+        true
     }
 
-    /// Reads a buffer from the UART data register; returns the number of bytes successfully read
-    ///
-    /// Note that, unlike the original implementation, this does not unnecessarily perform a nop
-    /// loop while blocking.
-    pub fn read(&mut self, buf: &mut [u8]) -> Result<()> {
-        for (i, b) in buf.iter_mut().enumerate() {
-            *b = self.readb(true).map_err(|_| SomeData(i))?;
+    /// Unmasks or masks the receive interrupts of the UART, i.e. those raised when its receive
+    /// FIFO passes its trigger level or data has waited there for a while; the pending interrupts
+    /// are also cleared
+    #[cfg(feature = "pipelined")]
+    pub fn listen(&mut self, enabled: bool) {
+        // SAFETY: only the receive interrupt bits of the mask are changed, and they are cleared
+        // before they are unmasked so that a stale interrupt does not fire
+        unsafe {
+            self.uart.icr.write(RX_INTERRUPTS);
+            self.uart.im.modify(|im| {
+                if enabled {
+                    im | RX_INTERRUPTS
+                } else {
+                    im & !RX_INTERRUPTS
+                }
+            });
         }
-
-        Ok(())
     }
+}
 
-    /// Discards the given number of bytes, without blocking
-    pub fn discard(&mut self, n: usize) {
-        for _ in 0..n {
-            for _ in 0..10_000 {
-                // some delay for buffering
-                asm::nop()
-            }
+impl Transport for Interface {
+    /// Converts this interface into its named form instead of a wrapper, allowing references to
+    /// specific UARTs without also referencing how to read and write to them
+    fn named(&self) -> INTF {
+        match ptr::from_ref::<UART>(self.uart) as usize {
+            0x4000_C000 => INTF::CPU,
+            0x4000_D000 => INTF::SSS,
+            0x4000_E000 => INTF::RAD,
+            _ => Fatal::Interface.panic(),
+        }
+    }
 
-            if self.readb(false).is_err() {
-                break; // fail fast
-            }
+    /// Determines if data is available to be read
+    ///
+    /// With the `pipelined` feature, the radio's data is read from its [receive queue](rx), into
+    /// which its interrupt drains the UART, as is that of every UART with `interrupt-driven`.
+    #[inline]
+    fn avail(&self) -> bool {
+        #[cfg(feature = "pipelined")]
+        if let Some(intf) = self.queued() {
+            return rx::avail(intf);
         }
+
+        self.fifo_avail()
     }
 
-    /// Discards bytes that match the supplied predicate; on success, returns the first byte that
-    /// does not match the predicate
-    pub fn discard_while(&mut self, predicate: impl Fn(u8) -> bool) -> Result<u8> {
-        loop {
-            let b = self.readb(true)?;
+    /// Reads a byte from the UART data register (or its [receive queue](rx)), optionally blocking
+    ///
+    /// Note that, unlike the original implementation, this does not unnecessarily perform a nop
+    /// loop while blocking.
+    fn readb(&mut self, blocking: bool) -> Result<u8> {
+        while blocking && !self.avail() {}
+
+        #[cfg(feature = "pipelined")]
+        if let Some(intf) = self.queued() {
+            return rx::pop(intf).ok_or(NoData);
+        }
 
-            if !predicate(b) {
-                return Ok(b);
-            }
+        if self.fifo_avail() {
+            #[allow(clippy::cast_possible_truncation)]
+            // truncation reviewed; this will only ever be a single byte
+            Ok(self.uart.dr.read() as u8)
+        } else {
+            Err(NoData)
         }
     }
 
     /// Write a byte to the UART data register -- always blocking
-    pub fn writeb(&mut self, data: u8) {
+    fn writeb(&mut self, data: u8) {
         while self.uart.fr.read() & (TXFF as u32) != 0 {}
         // SAFETY: we ensure that the DR register is writable by checking the write mask above
         unsafe {
             self.uart.dr.write(data.into());
         }
     }
-
-    /// Write a buffer to the UART data register -- always blocking
-    pub fn write(&mut self, buf: &[u8]) {
-        for b in buf {
-            self.writeb(*b);
-        }
-    }
-
-    /// Converts this interface into its named form instead of a wrapper, allowing references to
-    /// specific UARTs without also referencing how to read and write to them
-    pub fn named(&self) -> INTF {
-        match self.uart as *const UART as usize {
-            0x4000_C000 => INTF::CPU,
-            0x4000_D000 => INTF::SSS,
-            0x4000_E000 => INTF::RAD,
-            _ => unreachable!("Impossible branch; only these addresses can be used"),
-        }
-    }
 }
 
 impl Clone for Interface {
-    #[allow(clippy::cast_ref_to_mut)]
     fn clone(&self) -> Self {
         // SAFETY: UARTs are cloneable in this manner as they DO NOT MOVE for any reason; they are
-        // explicitly mapped to a specific address
-        let uart = unsafe { &mut *(self.uart as *const UART as *mut UART) };
+        // explicitly mapped to a specific address, which we re-derive from the named interface
+        let uart = unsafe { &mut *(address(self.named()) as *mut UART) };
         Self { uart }
     }
 }
@@ -0,0 +1,46 @@
+//! The [key-value store](scewl::kv) in the pages of flash which build.rs reserves for it below the
+//! top of flash, when generating `memory.x`
+
+use scewl::kv::{Error, Medium, Store, WORDS};
+
+/// The pages of flash reserved for the store, defined by the generated `memory.x`
+pub struct Pages(());
+
+extern "C" {
+    /// The start of the pages of flash reserved for the store
+    static __sstore: [u32; 2 * WORDS];
+}
+
+impl Pages {
+    /// A pointer to the given word of the given page
+    fn word(page: usize, word: usize) -> *const u32 {
+        core::ptr::addr_of!(__sstore)
+            .cast::<u32>()
+            .wrapping_add(page * WORDS + word)
+    }
+}
+
+impl Medium for Pages {
+    fn read(&self, page: usize, word: usize) -> u32 {
+        // SAFETY: the pages are reserved for the store, and only ever read through volatile reads
+        // as the firmware programs them at runtime
+        unsafe { core::ptr::read_volatile(Pages::word(page, word)) }
+    }
+
+    fn erase(&mut self, page: usize) -> Result<(), Error> {
+        // SAFETY: as above, and the store is only ever opened from the main loop
+        unsafe { crate::flash::erase(Pages::word(page, 0)) }.map_err(|_| Error::Medium)
+    }
+
+    fn program(&mut self, page: usize, word: usize, value: u32) -> Result<(), Error> {
+        // SAFETY: as above
+        unsafe { crate::flash::program(Pages::word(page, word), value) }.map_err(|_| Error::Medium)
+    }
+}
+
+/// Opens the store in the pages of flash reserved for it
+///
+/// The store holds no state but the flash, so it may be opened wherever it is needed.
+pub fn open() -> Store<Pages> {
+    Store::open(Pages(()))
+}
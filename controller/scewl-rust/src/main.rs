@@ -1,151 +1,438 @@
-// Generate this documentation in a prettier form with `cargo doc --release --open`
-
-//! CaptureTheFlaggies controller implementation for MITRE eCTF!
-//!
-//! ## Building
-//!
-//! To compile this crate by hand, please ensure that you do the following:
-//!
-//!  - Install the following packages (or equivalent) for your operating system:
-//!    - `build-essential`
-//!    - `binutils-arm-none-eabi`
-//!    - `clang`
-//!    - `gcc-arm-none-eabi`
-//!  - Install Rust 1.51 via [Rustup](https://rustup.rs/)
-//!  - Install the `thumbv7m-none-eabi` target via rustup: `rustup target add thumbv7m-none-eabi`
-//!  - Build it! `SCEWL_ID=${SCEWL_ID} cargo build --release`, where `SCEWL_ID` is your intended id
-//!    for this instance. Optionally use `--features semihosted` to enable QEMU semihosting for
-//!    logging debug information to the host. You can also build without specifying a `SCEWL_ID`,
-//!    but this will provide defaults for the ID and the SED SSS registration secret.
-//!
-//! To run via QEMU, you need to perform an additional objcopy step, the output of which can then be
-//! used as a `-kernel` argument: `arm-none-eabi-objcopy -O binary target/thumbv7m-none-eabi/release/controller kernel`
-//!
-//! Otherwise, this crate can be used via the typical build process for the MITRE eCTF as specified
-//! in [getting_started.md](https://github.com/mitre-cyber-academy/2021-ectf-insecure-example/blob/master/getting_started.md).
-//!
-//! ## Design
-//!
-//! This implementation of the controller is very similar to the original provided in [MITRE's example implementation](https://github.com/mitre-cyber-academy/2021-ectf-insecure-example)
-//! with a few key differences:
-//!
-//!  - Hopefully, it's not insecure!
-//!  - As you probably already know, this implementation is written in Rust and, as such, does _not_
-//!    depend on the provided lm3s or CMSIS dependencies. Instead, the [cortex-m](https://docs.rs/cortex-m/0.7.2/cortex_m/)
-//!    crate and the [lm3s6965](https://docs.rs/lm3s6965/0.1.3/lm3s6965/) crates provided by the
-//!    [Rust Embedded Cortex-M team](https://github.com/rust-embedded/wg#the-cortex-m-team) and
-//!    [Jorge Aparicio](https://github.com/japaric), respectively, are used to provide the basic
-//!    embedded systems operations necessary to run on the lm3s6965 processor.
-//!  - This crate uses _minimal unsafe operations_. All unsafe code is present in [Interface](interface::Interface)
-//!    as read/write operations on the UART{0,1,2} peripherals via memory-mapped registers.
-//!  - The original implementation defined functions which operated on structs; in this crate, we
-//!    define structs with methods to perform the operations, which more idiomatically represents
-//!    the controller's operations.
-//!
-//! ### Structure
-//!
-//! Where the original implementation used functions which interacted with structs in C, this
-//! implementation attempts to more ergonomically represent operations taken by the controller by
-//! recognising that the only communications methods which are permitted to be modified are those
-//! between the SSS and other SEDs (excluding FAA); otherwise, information is effectively
-//! transparently proxied to and from the CPU.
-//!
-//! To account for this abstraction, we separate the controller into the following modules:
-//!
-//!  - The controller itself, the driver for communications, which employs an crypto handler and
-//!    an authentication handler
-//!  - The crypto handler, which decrypts/encrypts information to/from the CPU to other SEDs
-//!  - The authentication handler, which interacts with the SSS and generates the crypto handler
-//!    specified by that SSS
-//!
-//! As we wish to test the basic operation of the communications channel as well as the additional
-//! security features on top, it behooves us to employ [type generics](https://doc.rust-lang.org/book/ch10-01-syntax.html)
-//! to allow for plug-and-play replacements for both the crypto handler and authentication
-//! handler. To do so, we define traits for [encryption](crypto::Handler) and
-//! [authentication](auth::Handler). The [controller implementation](controller::Controller)
-//! is generified to support arbitrary implementations of these handlers, restricting their use to
-//! only the permitted changes as defined in the MITRE eCTF specification.
-//!
-//! ## Implementation
-//!
-//! ### Interface
-//!
-//! As we wished to omit C dependencies entirely, some research was done to identify the mechanism
-//! by which the [interface](https://github.com/mitre-cyber-academy/2021-ectf-insecure-example/blob/master/controller/interface.c)
-//! implemented input/output from/to the sockets over which communication was emulated.
-//!
-//! To match the behaviour of the original interface code, both the original C implementation and
-//! portions of the lm3s dependency were inspected and subsequently ported to Rust. A discussion on
-//! the details of this is available in the [interface module documentation](interface).
-//!
-//! ### Controller
-//!
-//! As previously discussed, the controller was modularised to support plug-and-play compatibility
-//! with various encryption and authentication handlers. The controller implementation in Rust is
-//! a near direct port of the C implementation with minor changes to support different handlers;
-//! further discussion of these changes are available in the [controller module documentation](controller).
-//!
-//! ### Handlers
+//! The firmware entrypoint for the CaptureTheFlaggies controller, which selects the handlers used
+//! by the controller and runs it on the lm3s6965.
 //!
-//! Present in this crate are two handler families: a [trivial implementation](trivial), which, as
-//! the name suggests, trivially implements the encryption and authentication schemes (read: none)
-//! leveraged by the [insecure controller implementation](https://github.com/mitre-cyber-academy/2021-ectf-insecure-example/blob/master/controller/controller.c).
-//!
-//! The second handler family is the one used in production to be targeted by live adversaries and
-//! is denoted as the [secure implementation](secure). This implementation leverages multiple
-//! security features to ensure that messages cannot be intercepted, modified, or replayed. A full
-//! discussion on those security features can be found in the documentation for that module.
+//! The controller itself, and the documentation of its design, live in the hardware-free [scewl]
+//! library (the `scewl-core` crate), which the host tools and tests share. This crate holds only
+//! what needs the hardware: the drivers of the UARTs, the flash, the MPU, the watchdog, and the
+//! clock, the [board](board) through which the controller reaches them, and the entrypoint.
 
 #![no_std]
 #![no_main]
 #![warn(clippy::pedantic)] // enforce pedantic checks -- false positive prone
 #![deny(clippy::missing_docs_in_private_items)] // enforce documentation
+#![allow(clippy::upper_case_acronyms)] // SCEWL, SSS, FAA, etc. are named as in the specification
+#![cfg_attr(feature = "selftest", allow(dead_code, unused_imports))] // the controller is not run
+
+#[cfg(all(feature = "stripped", any(feature = "semihosted", feature = "rtt")))]
+compile_error!("stripped images have no logging, so cannot be built with a logging transport");
+
+#[cfg(feature = "logging")]
+use core::fmt::Arguments;
+use core::mem::MaybeUninit;
+#[cfg(not(any(feature = "semihosted", feature = "panic-halt")))]
+use core::panic::PanicInfo;
+use core::ptr::{self, addr_of_mut};
 
+#[cfg(feature = "provisioned")]
+use cortex_m::asm;
+#[cfg(not(any(feature = "semihosted", feature = "panic-halt")))]
+use cortex_m::peripheral::SCB;
 use cortex_m_rt::entry;
 use cortex_m_rt::exception;
+#[cfg(feature = "mpu")]
+use cortex_m_rt::ExceptionFrame;
 use lm3s6965 as _;
-#[cfg(not(feature = "semihosted"))]
+#[cfg(feature = "pipelined")]
+use lm3s6965::interrupt;
+#[cfg(all(feature = "panic-halt", not(feature = "semihosted")))]
 use panic_halt as _;
 #[cfg(feature = "semihosted")]
 use panic_semihosting as _;
 
-use crate::controller::{Controller, SCEWL_MAX_DATA_SZ};
+use core::time::Duration;
+
+use scewl::banner::Banner;
+use scewl::codec::Id;
+use scewl::controller::{Controller, SCEWL_MAX_DATA_SZ, SCEWL_MAX_TX_SZ};
+use scewl::fatal::Fatal;
+#[cfg(feature = "fragmentation")]
+use scewl::fragment;
+use scewl::masked::Masked;
+#[cfg(feature = "heartbeat")]
+use scewl::heartbeat::Heartbeat;
+#[cfg(feature = "prioritized")]
+use scewl::outbound;
+use scewl::policy;
+use scewl::scratch::Pool;
+use scewl::session::Lifetime;
+#[cfg(feature = "multi-identity")]
+use scewl::transport::Transport;
+#[cfg(any(feature = "multi-identity", feature = "pipelined"))]
+use scewl::transport::INTF;
+#[cfg(feature = "suite-trivial")]
+use scewl::trivial;
+#[cfg(any(feature = "multi-identity", feature = "mpu", feature = "suite-test"))]
+use scewl::warn;
+use scewl::{build_info, error, info};
+#[cfg(feature = "suite-aes-cbc-hmac")]
+use scewl::codec;
+#[cfg(any(feature = "suite-aes-cbc-hmac", feature = "suite-test"))]
+use scewl::secure;
+
+use board::Lm3s6965;
+use interface::Interface;
+use systick::SysTickClock;
+#[cfg(feature = "update")]
+use update::Staging;
+#[cfg(feature = "watchdog")]
+use watchdog::Watchdog;
 
-mod auth;
-mod controller;
-mod crypto;
-mod cursor;
+mod board;
+#[cfg(feature = "persist-counters")]
+mod counters;
+mod crashlog;
+#[cfg_attr(not(feature = "suite-aes-cbc-hmac"), allow(dead_code))] // only the secure suite draws
+mod entropy;
+#[cfg(any(feature = "flash-store", feature = "update"))]
+#[cfg_attr(
+    not(any(feature = "anti-rollback", feature = "persist-counters", feature = "update")),
+    allow(dead_code) // the store is built without any of its users
+)]
+mod flash;
 mod interface;
-mod secure;
-mod trivial;
-
-#[macro_export]
-macro_rules! debug {
-    ($($args: expr),+) => {
-        #[cfg(feature = "semihosted")]
-        ::cortex_m_semihosting::hprintln!($($args),+).unwrap();
-    }
-}
+#[cfg(feature = "flash-store")]
+#[cfg_attr(
+    not(any(feature = "anti-rollback", feature = "persist-counters")),
+    allow(dead_code) // as for the flash driver
+)]
+mod kv;
+#[cfg(feature = "mpu")]
+mod mpu;
+#[cfg(feature = "provisioned")]
+mod provision;
+#[cfg(feature = "anti-rollback")]
+mod rollback;
+#[cfg(feature = "rtt")]
+mod rtt;
+#[cfg(feature = "pipelined")]
+mod rx;
+#[cfg(feature = "selftest")]
+mod selftest;
+mod systick;
+#[cfg(feature = "rtic")]
+mod tasks;
+#[cfg(feature = "update")]
+mod update;
+#[cfg(feature = "watchdog")]
+mod watchdog;
 
 // includes the code generated by build.rs; these are the values specified at build time
 include!(concat!(env!("OUT_DIR"), "/values.rs"));
 
+// the suite advertised to the SSS must be that which the secure handlers implement
+#[cfg(feature = "suite-aes-cbc-hmac")]
+const _: () = assert!(
+    SUITE == codec::secure::SUITE,
+    "build.rs and the secure handlers disagree on the suite identifier"
+);
+
+// the largest message in the deployment must fit the data buffer along with the crypto overhead
+const _: () = assert!(
+    MAX_MESSAGE <= SCEWL_MAX_DATA_SZ - 0x100,
+    "the deployment's max_message does not fit in the controller's data buffer"
+);
+
+// nor may the radio accept frames larger than the data buffer
+const _: () = assert!(
+    MTU as usize <= SCEWL_MAX_DATA_SZ,
+    "the radio MTU does not fit in the controller's data buffer"
+);
+
+/// The controller's data buffer, placed in the `.buffers` section so that the linker accounts for
+/// it against the RAM left for the stack (see build.rs); the section is not zeroed at boot
+#[link_section = ".buffers.data"]
+static mut DATA: MaybeUninit<[u8; SCEWL_MAX_DATA_SZ]> = MaybeUninit::uninit();
+
+/// The buffer in which the controller composes the messages it sends itself, placed alongside the
+/// data buffer
+#[link_section = ".buffers.tx"]
+static mut TX: MaybeUninit<[u8; SCEWL_MAX_TX_SZ]> = MaybeUninit::uninit();
+
+/// The scratch buffers which the controller lends to its handlers, placed alongside the data buffer
+#[link_section = ".buffers.scratch"]
+static mut SCRATCH: MaybeUninit<Pool> = MaybeUninit::uninit();
+
+/// The buffer in which messages received in [fragments](fragment) are reassembled, placed
+/// alongside the data buffer
+#[cfg(feature = "fragmentation")]
+#[link_section = ".buffers.reassembly"]
+static mut REASSEMBLY: MaybeUninit<[u8; fragment::CAPACITY]> = MaybeUninit::uninit();
+
+/// The buffer in which frames for the radio are [queued](outbound), placed alongside the data
+/// buffer
+#[cfg(feature = "prioritized")]
+#[link_section = ".buffers.outbound"]
+static mut OUTBOUND: MaybeUninit<[u8; outbound::CAPACITY]> = MaybeUninit::uninit();
+
 /// Entrypoint for the controller embedded software, which instantiates the controller with the
 /// selected authentication and crypto handlers, then enters the controller run loop
+#[cfg(not(feature = "selftest"))]
 #[entry]
 fn main() -> ! {
-    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
-    let mut client = Controller::new(
-        SCEWL_ID.into(),
-        &mut data,
-        secure::AuthHandler::new(&SECRET),
+    #[cfg(feature = "logging")]
+    scewl::level::set_sink(log);
+
+    // the swap runs from RAM, so must precede the MPU, and replaces the firmware before it starts
+    #[cfg(feature = "update")]
+    update::swap_pending(&UPDATE_KEY);
+
+    let core = cortex_m::Peripherals::take().unwrap_or_else(|| Fatal::Peripherals.panic());
+    #[cfg(feature = "rtic")]
+    tasks::init(core.NVIC, core.SCB);
+    #[cfg(feature = "mpu")]
+    if !mpu::enable(&core.MPU) {
+        warn!("No memory protection unit; running unprotected");
+    }
+
+    #[allow(unused_variables)] // suppress warning for crash when not logging
+    if let Some(crash) = crashlog::init() {
+        error!("Reset after a panic in the previous boot: {}", crash);
+    }
+
+    #[cfg(any(feature = "provisioned", feature = "multi-identity"))]
+    let (id, secret) = personalisation();
+    #[cfg(not(any(feature = "provisioned", feature = "multi-identity")))]
+    let (id, secret) = (
+        Id::from(SCEWL_ID),
+        Masked::new(&SECRET_MASK.0, &SECRET.0),
     );
+    #[cfg(feature = "mpu")]
+    mpu::guard_secret(&core.MPU, secret.share());
+
+    let (handlers, auth) = auth_handler(secret);
+
+    let banner = Banner {
+        version: build_info::VERSION,
+        build: build_info::COMMIT,
+        handlers,
+        id,
+        #[cfg(feature = "watchdog")]
+        watchdog: watchdog::reset_by_watchdog(),
+        #[cfg(not(feature = "watchdog"))]
+        watchdog: false,
+    };
+    info!("{}", banner);
 
+    // SAFETY: main is entered once and never returns, so these are the only references to the
+    // buffers; a zeroed pool is an empty one
+    let data = unsafe { zeroed(addr_of_mut!(DATA)) };
+    let tx = unsafe { zeroed(addr_of_mut!(TX)) };
+    let scratch: &Pool = unsafe { zeroed(addr_of_mut!(SCRATCH)) };
+    #[cfg(feature = "fragmentation")]
+    let reassembly = unsafe { zeroed(addr_of_mut!(REASSEMBLY)) };
+    #[cfg(feature = "prioritized")]
+    let queue = unsafe { zeroed(addr_of_mut!(OUTBOUND)) };
+    let board = Lm3s6965::default();
+    #[cfg(feature = "watchdog")]
+    let board = board.with_watchdog(Watchdog::start(Duration::from_millis(WATCHDOG_TIMEOUT)));
+    #[cfg(feature = "update")]
+    let mut staging = Staging::new(UPDATE_SOURCE.into(), &UPDATE_KEY);
+    let clock = SysTickClock::start(core.SYST);
+    let builder = Controller::builder(id, Interface::links(), data, tx, scratch, auth, &board)
+        .rate_limit(&clock, policy::DEFAULT_RATE, policy::DEFAULT_GLOBAL_RATE)
+        .sss_timeout(&clock, Duration::from_millis(SSS_TIMEOUT))
+        .frame_timeout(&clock, Duration::from_millis(FRAME_TIMEOUT))
+        .session_lifetime(
+            &clock,
+            Lifetime {
+                sends: SESSION_SENDS,
+                age: SESSION_LIFETIME.map(Duration::from_secs),
+            },
+        )
+        .mtu(MTU);
+    let builder = match SSS_KEEPALIVE {
+        Some(period) => builder.sss_keepalive(&clock, Duration::from_secs(period)),
+        None => builder,
+    };
+    #[cfg(feature = "fragmentation")]
+    let builder = builder.reassembly(reassembly);
+    #[cfg(feature = "prioritized")]
+    let builder = builder.outbound(queue);
+    #[cfg(feature = "heartbeat")]
+    let builder = builder.heartbeat(Heartbeat::new(
+        HEARTBEAT_TARGET.into(),
+        Duration::from_secs(HEARTBEAT_PERIOD),
+        &clock,
+    ));
+    #[cfg(feature = "update")]
+    let builder = builder.update(&mut staging);
+    let mut client = builder.build();
+    #[cfg(feature = "pipelined")]
+    rx::start(Interface::new(INTF::RAD));
+    #[cfg(feature = "interrupt-driven")]
+    {
+        rx::start(Interface::new(INTF::CPU));
+        rx::start(Interface::new(INTF::SSS));
+    }
+    let _ignored = client.announce(&banner);
+
+    #[cfg(feature = "rtic")]
+    tasks::run(&board, &mut client);
+    #[cfg(not(feature = "rtic"))]
     client.run()
 }
 
+/// Zeroes the given buffer in place, as zeroing it through a temporary would put the buffer on the
+/// stack, and returns a reference to it
+///
+/// # Safety
+///
+/// The buffer must not be referenced elsewhere for as long as the reference returned is used, and
+/// zeroes must be a valid value of it.
+#[cfg(not(feature = "selftest"))]
+unsafe fn zeroed<T>(buf: *mut MaybeUninit<T>) -> &'static mut T {
+    ptr::write_bytes(buf, 0, 1);
+    (*buf).assume_init_mut()
+}
+
+/// The authentication handler of the cipher suite selected by feature, of which build.rs ensures
+/// one, with the name of its handler family for the banner
+#[cfg(feature = "suite-aes-cbc-hmac")]
+fn auth_handler(secret: Masked) -> (&'static str, secure::AuthHandler) {
+    let auth = secure::AuthHandler::new(secret, SUITE, &entropy::RUNTIME);
+    #[cfg(feature = "persist-counters")]
+    let auth = auth.with_checkpoints(&counters::FLASH);
+    #[cfg(feature = "anti-rollback")]
+    let auth = auth.with_epochs(&rollback::FLASH);
+    ("secure", auth)
+}
+
+/// The authentication handler of the trivial handlers, which need no secret
+#[cfg(feature = "suite-trivial")]
+fn auth_handler(_secret: Masked) -> (&'static str, trivial::AuthHandler) {
+    ("trivial", trivial::AuthHandler)
+}
+
+/// The test authentication handler, which registers the secure crypto handlers with fixed keys
+/// rather than those of the SSS, so needs no secret
+#[cfg(feature = "suite-test")]
+fn auth_handler(_secret: Masked) -> (&'static str, secure::TestAuthHandler) {
+    warn!("Built with the test handlers, whose keys are fixed; not for deployment");
+    ("test", secure::TestAuthHandler)
+}
+
+/// Reads the id and secret of this SED from the record written when it was personalised, which
+/// lets one generic image serve every SED; should the SED not have been provisioned, it halts, as
+/// it could never register
+#[cfg(all(feature = "provisioned", not(feature = "multi-identity")))] // exclusive, see build.rs
+fn personalisation() -> (Id, Masked) {
+    if let Some(record) = provision::read() {
+        let secret = record.secret.map_or_else(
+            || Masked::new(&SECRET_MASK.0, &SECRET.0),
+            Masked::plain,
+        );
+        return (record.id, secret);
+    }
+
+    error!("This SED has not been provisioned, so cannot run");
+    loop {
+        asm::wfi();
+    }
+}
+
+/// Selects which of the identities embedded in this image to emulate, by its index in `SCEWL_IDS`
+/// as given by the first byte from the CPU; lets one image stand in for any SED of a bench setup
+///
+/// Bytes which select no identity are ignored, so that a stray byte does not prevent selection.
+#[cfg(feature = "multi-identity")]
+fn personalisation() -> (Id, Masked) {
+    let mut cpu = Interface::new(INTF::CPU);
+    info!(
+        "Awaiting the selection of one of {} identities",
+        IDENTITIES.len()
+    );
+
+    loop {
+        let selected = cpu.readb(true).ok().map(usize::from);
+        if let Some(index) = selected.filter(|&index| index < IDENTITIES.len()) {
+            let (id, secret) = &IDENTITIES[index];
+            info!("Emulating SED {}", id);
+            return ((*id).into(), Masked::new(&IDENTITY_MASKS[index].0, &secret.0));
+        }
+        warn!("Ignoring the selection of an unknown identity");
+    }
+}
+
+/// Writes each log of the library to the host, over RTT should it be enabled, as semihosting halts
+/// the core on every call; the on-target test runner still uses semihosting to report its exit
+/// status in that case
+#[cfg(feature = "logging")]
+fn log(args: Arguments<'_>) {
+    #[cfg(feature = "rtt")]
+    rtt::println(args);
+    #[cfg(all(feature = "semihosted", not(feature = "rtt")))]
+    cortex_m_semihosting::hprintln!("{}", args).unwrap();
+}
+
+/// Handler for panics when neither semihosting nor `panic-halt` is selected, which records the
+/// panic for the next boot (see [crashlog]) and resets the controller cleanly
+///
+/// Halting, as `panic-halt` does, would leave the SED unresponsive until power cycled; resetting
+/// instead returns it to its unregistered state, from which the CPU may register it again.
+#[cfg(not(any(feature = "semihosted", feature = "panic-halt")))]
+#[panic_handler]
+fn panic(info: &PanicInfo<'_>) -> ! {
+    cortex_m::interrupt::disable();
+    crashlog::record(info);
+    SCB::sys_reset()
+}
+
+/// Counts each millisecond for the [SysTick clock](systick), which times the rate limits of the
+/// [policy](scewl::policy) and heartbeats
+#[cfg(not(feature = "selftest"))]
+#[exception]
+fn SysTick() {
+    systick::tick();
+    #[cfg(feature = "rtic")]
+    tasks::spawn();
+}
+
+/// Drains the radio into the [receive queue](rx), so that reception overlaps decryption
+#[cfg(all(feature = "pipelined", not(feature = "selftest")))]
+#[interrupt]
+fn UART2() {
+    rx::on_interrupt(INTF::RAD);
+    #[cfg(feature = "rtic")]
+    tasks::spawn();
+}
+
+/// Drains the CPU into its [receive queue](rx), waking the run loop
+#[cfg(all(feature = "interrupt-driven", not(feature = "selftest")))]
+#[interrupt]
+fn UART0() {
+    rx::on_interrupt(INTF::CPU);
+    #[cfg(feature = "rtic")]
+    tasks::spawn();
+}
+
+/// Drains the SSS into its [receive queue](rx), waking the run loop
+#[cfg(all(feature = "interrupt-driven", not(feature = "selftest")))]
+#[interrupt]
+fn UART1() {
+    rx::on_interrupt(INTF::SSS);
+    #[cfg(feature = "rtic")]
+    tasks::spawn();
+}
+
+/// Dispatches the crypto task of the [task architecture](tasks), which runs the controller
+#[cfg(all(feature = "rtic", not(feature = "selftest")))]
+#[interrupt]
+fn SSI0() {
+    tasks::dispatch();
+}
+
+/// Reports a fault, e.g. a stack overflow into the [MPU](mpu)'s guard, as a fatal error; the MPU
+/// is disabled while the handler runs, so it may use the guard as stack
+#[cfg(all(feature = "mpu", not(feature = "selftest")))]
+#[exception]
+fn HardFault(_frame: &ExceptionFrame) -> ! {
+    Fatal::Fault.panic()
+}
+
 /// Handler for exceptions generated by the processor. In our case, we are not handling them as they
-/// do not pertain to our use case (we are not asynchronously processing input from UART{0,1,2})
+/// do not pertain to our use case (we are not asynchronously processing input from UART{0,1,2},
+/// save for the radio's UART2 with the `pipelined` feature, and every UART with `interrupt-driven`,
+/// which have their own handlers, as does the dispatcher of the crypto task with `rtic`)
 #[exception]
 #[allow(non_snake_case)]
 fn DefaultHandler(_irqn: i16) {}
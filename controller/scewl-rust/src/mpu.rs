@@ -0,0 +1,188 @@
+//! Configuration of the Cortex-M3 memory protection unit, which the firmware [enables](enable) at
+//! boot so that a memory-corruption bug (e.g. in a parser) is harder to turn into an exploit
+//!
+//! The MPU is given three regions, the later of which take precedence where they overlap:
+//!
+//!  0. the flash, which is executable but read-only, so that code cannot be patched
+//!  1. the RAM (including the persistent region), which is writable but never executable, so that
+//!     neither the stack nor any buffer can be jumped into
+//!  2. a guard of [`GUARD`] bytes at the bottom of the stack, i.e. just above the statics, which
+//!     may not be accessed at all, so that a stack overflow faults rather than silently
+//!     overwriting the buffers
+//!
+//! Everything else (e.g. the peripherals and the system control space) keeps the default memory
+//! map, as the controller only ever runs privileged. The MPU is disabled while handling a hard
+//! fault, which every MPU violation escalates to, so that the firmware's `HardFault` handler may
+//! use the guard as stack to report the fault.
+//!
+//! The sizes of the flash and RAM are those of the deployment configuration, rounded up to a
+//! power of two as the MPU requires; the guard is carved from the stack reserve (`memory.stack`).
+//!
+//! A fourth region covers the registration secret, or rather its [masked](scewl::masked) share,
+//! without which the secret cannot be rebuilt, which the firmware [guards](guard_secret) at boot.
+//! It is only enabled, so that the secret may not be accessed at all, while the controller is
+//! registered: the controller [locks](lock_secret) it once registration has derived the
+//! session's keys, and [unlocks](unlock_secret) it only to (de)register again, which proves the
+//! secret to the SSS. Mid-session, neither the firmware nor an exploit of it can read the secret.
+
+use cortex_m::asm;
+use cortex_m::peripheral::MPU;
+
+use scewl::deployment;
+
+/// The size of the guard region, in bytes, which also leaves the `HardFault` handler room to run
+pub const GUARD: u32 = 256;
+
+/// The number of the region which covers the registration secret, above the others so that it
+/// takes precedence over the flash
+const SECRET_REGION: u32 = 3;
+
+/// The origin of the flash on the lm3s6965
+const FLASH_ORIGIN: u32 = 0x0000_0000;
+/// The origin of the RAM on the lm3s6965
+const RAM_ORIGIN: u32 = 0x2000_0000;
+
+/// `MPU_CTRL`: enables the MPU
+const CTRL_ENABLE: u32 = 1;
+/// `MPU_CTRL`: privileged accesses outside of any region use the default memory map
+const CTRL_PRIVDEFENA: u32 = 1 << 2;
+
+/// `MPU_RBAR`: the region number in the register is to be used, rather than `MPU_RNR`
+const RBAR_VALID: u32 = 1 << 4;
+
+/// `MPU_RASR`: enables the region
+const RASR_ENABLE: u32 = 1;
+/// `MPU_RASR`: normal memory, write-through and not write-allocated (TEX 0, C 1, B 0)
+const RASR_NORMAL: u32 = 1 << 17;
+/// `MPU_RASR`: the memory is shared
+const RASR_SHAREABLE: u32 = 1 << 18;
+/// `MPU_RASR`: no access at all
+const RASR_NO_ACCESS: u32 = 0b000 << 24;
+/// `MPU_RASR`: read and write access
+const RASR_READ_WRITE: u32 = 0b011 << 24;
+/// `MPU_RASR`: read-only access
+const RASR_READ_ONLY: u32 = 0b110 << 24;
+/// `MPU_RASR`: instructions may not be fetched from the region
+const RASR_EXECUTE_NEVER: u32 = 1 << 28;
+
+extern "C" {
+    /// The end of the statics, as placed by `cortex-m-rt`, below which the stack must not grow
+    static __sheap: u8;
+}
+
+/// The `MPU_RASR` size field of a region of at least the given size, which the MPU requires to
+/// be a power of two of at least 32 bytes
+fn size(bytes: u32) -> u32 {
+    let log2 = bytes.max(32).next_power_of_two().trailing_zeros();
+    (log2 - 1) << 1
+}
+
+/// Describes a region to the MPU, whose base must be aligned to its size
+fn region(mpu: &MPU, number: u32, base: u32, bytes: u32, attributes: u32) {
+    // SAFETY: the MPU is not yet enabled as the regions are written, so no access may fault
+    unsafe {
+        mpu.rbar.write(base | RBAR_VALID | number);
+        mpu.rasr.write(attributes | size(bytes) | RASR_ENABLE);
+    }
+}
+
+/// Configures and enables the MPU as described in the [module documentation](self); returns
+/// whether the core has an MPU at all, as the firmware then runs unprotected
+pub fn enable(mpu: &MPU) -> bool {
+    // DREGION, the number of regions supported (bits 8 to 15), is zero should there be no MPU
+    if mpu._type.read().to_le_bytes()[1] == 0 {
+        return false;
+    }
+
+    // the guard is aligned to its size, at the first such address above the statics
+    let sheap = core::ptr::addr_of!(__sheap) as u32;
+    let guard = (sheap + GUARD - 1) & !(GUARD - 1);
+
+    // SAFETY: the MPU is disabled while its regions are written
+    unsafe { mpu.ctrl.write(0) };
+    region(
+        mpu,
+        0,
+        FLASH_ORIGIN,
+        deployment::FLASH,
+        RASR_NORMAL | RASR_READ_ONLY,
+    );
+    region(
+        mpu,
+        1,
+        RAM_ORIGIN,
+        deployment::RAM,
+        RASR_NORMAL | RASR_SHAREABLE | RASR_READ_WRITE | RASR_EXECUTE_NEVER,
+    );
+    region(
+        mpu,
+        2,
+        guard,
+        GUARD,
+        RASR_NORMAL | RASR_SHAREABLE | RASR_NO_ACCESS | RASR_EXECUTE_NEVER,
+    );
+
+    // SAFETY: the regions cover all that the firmware executes and accesses, bar the guard
+    unsafe { mpu.ctrl.write(CTRL_ENABLE | CTRL_PRIVDEFENA) };
+    asm::dsb();
+    asm::isb();
+
+    true
+}
+
+/// Describes the region of the registration secret to the MPU, but leaves it disabled until the
+/// secret is [locked](lock_secret); to be called once at boot, after [`enable`]
+///
+/// The region is the smallest which the MPU can describe that covers the secret. A secret which
+/// is not aligned to its size, such as that of a provisioning record, shares the region with its
+/// neighbours, which may then not be read while the secret is locked either.
+pub fn guard_secret(mpu: &MPU, secret: &'static [u8; 64]) {
+    let start = secret.as_ptr() as u32;
+    let end = start + 64;
+    let mut bytes = 32;
+    while start / bytes != (end - 1) / bytes {
+        bytes *= 2;
+    }
+
+    // SAFETY: the region is written disabled, so no access may fault
+    unsafe {
+        mpu.rbar
+            .write((start & !(bytes - 1)) | RBAR_VALID | SECRET_REGION);
+        mpu.rasr
+            .write(RASR_NORMAL | RASR_NO_ACCESS | RASR_EXECUTE_NEVER | size(bytes));
+    }
+}
+
+/// Enables the region of the registration secret, such that any access to it faults, until it
+/// is [unlocked](unlock_secret)
+pub fn lock_secret() {
+    set_secret_region(true);
+}
+
+/// Disables the region of the registration secret, such that it may be read again
+pub fn unlock_secret() {
+    set_secret_region(false);
+}
+
+/// Enables or disables the region of the registration secret, as [guarded](guard_secret) at boot
+fn set_secret_region(enabled: bool) {
+    // SAFETY: the MPU is only ever written from the main loop, at boot and then only here, and
+    // only the enable bit of the secret's region is changed
+    let mpu = unsafe { &*MPU::ptr() };
+    if mpu._type.read().to_le_bytes()[1] == 0 {
+        return;
+    }
+
+    // SAFETY: as above; the secret's region covers only the secret and, at most, its neighbours
+    unsafe {
+        mpu.rnr.write(SECRET_REGION);
+        let rasr = mpu.rasr.read();
+        mpu.rasr.write(if enabled {
+            rasr | RASR_ENABLE
+        } else {
+            rasr & !RASR_ENABLE
+        });
+    }
+    asm::dsb();
+    asm::isb();
+}
@@ -0,0 +1,15 @@
+//! Reads the [provisioning record](scewl::provision) from the flash page which build.rs reserves
+//! for it at the top of flash, when generating `memory.x`
+
+use scewl::provision::{Record, PAGE};
+
+/// Reads the record from the flash page reserved for it, should the SED have been provisioned
+pub fn read() -> Option<Record<'static>> {
+    extern "C" {
+        /// The start of the flash page reserved for the record, defined by the generated `memory.x`
+        static __sprovision: [u8; PAGE];
+    }
+
+    // SAFETY: the page lies in flash, which is never written while the firmware runs
+    Record::from_bytes(unsafe { &__sprovision })
+}
@@ -0,0 +1,56 @@
+//! The highest key epoch which the SED has accepted, so that it never rolls back onto older keys
+//!
+//! The SSS distributes the [epoch](scewl::codec::secure::SecureSSSSecrets::epoch) of the
+//! deployment's keys alongside them, which the deployment raises whenever it replaces (e.g.
+//! revokes) them. With the `anti-rollback` feature, the authentication handler is given the
+//! [`FLASH`] record, so that the SED refuses keys of an epoch older than the highest it has ever
+//! accepted, and records each higher epoch in the [key-value store](scewl::kv) in flash. An
+//! attacker who captured an old key package can then not replay it to a rebooted SED, as the
+//! record survives the reboot.
+
+use scewl::kv::KEY_EPOCH;
+use scewl::secure::Epochs;
+use scewl::{glitch, warn};
+
+use crate::kv;
+
+/// The record of the highest epoch in the flash store
+pub static FLASH: Flash = Flash(());
+
+/// The [record](Epochs) of the highest epoch in flash, as described in the [module
+/// documentation](self)
+pub struct Flash(());
+
+impl Flash {
+    /// The highest epoch recorded, or 0 should none have been
+    fn highest() -> u32 {
+        let mut epoch = [0; 4];
+        match kv::open().get(KEY_EPOCH, &mut epoch) {
+            Some(4) => u32::from_le_bytes(epoch),
+            _ => 0,
+        }
+    }
+}
+
+impl Epochs for Flash {
+    fn accept(&self, epoch: u32) -> bool {
+        let highest = Flash::highest();
+        glitch::check(|| epoch >= highest)
+    }
+
+    /// Records that keys of the given epoch were accepted, should it be higher than any yet
+    /// recorded
+    ///
+    /// A failure to record the epoch is logged, but otherwise ignored: the keys have been accepted
+    /// regardless, and the epoch is recorded again at the next registration.
+    fn record(&self, epoch: u32) {
+        if epoch <= Flash::highest() {
+            return;
+        }
+
+        #[allow(unused_variables)] // only logged when a logging transport is enabled
+        if let Err(err) = kv::open().set(KEY_EPOCH, &epoch.to_le_bytes()) {
+            warn!("Could not record key epoch {}: {}", epoch, err);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! A minimal SEGGER RTT (Real-Time Transfer) transport for debug output, used by [`debug!`](scewl::debug)
+//! when the `rtt` feature is enabled
+//!
+//! Unlike semihosting, which halts the core for the debugger on every call, RTT only ever copies
+//! the output into a ring buffer in RAM; a debug probe (or anything else able to read the target's
+//! memory, e.g. `probe-rs`, the J-Link RTT Viewer, or the `rtt` commands of `openocd`) locates the
+//! buffer by its control block and drains it in the background. This makes logging cheap enough to
+//! leave enabled on real hardware and keeps timing under QEMU representative.
+//!
+//! Only a single up (target to host) channel is provided, named "Terminal" as is customary. The
+//! channel never blocks: should the host not drain the buffer quickly enough (or not at all, when
+//! no probe is attached), output which does not fit is simply truncated.
+//!
+//! The layout of the [control block](ControlBlock) follows the SEGGER RTT specification exactly,
+//! as probes rely on it; it is exported as `_SEGGER_RTT` so that tools may also find it by symbol.
+
+use core::fmt::{Arguments, Result as FmtResult, Write};
+use core::ptr::{self, addr_of, addr_of_mut};
+use core::sync::atomic::{compiler_fence, AtomicBool, Ordering};
+
+use cortex_m::interrupt;
+
+/// The size of the ring buffer for the up channel
+const BUFFER_SIZE: usize = 1024;
+
+/// The identifier by which probes locate the control block in RAM
+const ID: &[u8; 16] = b"SEGGER RTT\0\0\0\0\0\0";
+
+/// The name of the up channel, as displayed by the host
+const NAME: &[u8] = b"Terminal\0";
+
+/// The channel mode in which writes that do not fit are truncated rather than blocking
+const MODE_NO_BLOCK_TRIM: u32 = 1;
+
+/// An RTT channel descriptor, laid out as specified by SEGGER
+#[repr(C)]
+struct Channel {
+    /// A NUL-terminated name for the channel
+    name: *const u8,
+    /// The ring buffer of the channel
+    buffer: *mut u8,
+    /// The size of the ring buffer
+    size: u32,
+    /// The offset at which the target writes next, written only by the target
+    write: u32,
+    /// The offset at which the host reads next, written only by the host
+    read: u32,
+    /// The channel mode
+    flags: u32,
+}
+
+/// The RTT control block, laid out as specified by SEGGER, with one up and no down channels
+#[repr(C)]
+pub struct ControlBlock {
+    /// The identifier which probes search for; only written once the block is initialised
+    id: [u8; 16],
+    /// The number of up channels
+    max_up: u32,
+    /// The number of down channels
+    max_down: u32,
+    /// The single up channel
+    up: Channel,
+}
+
+/// The control block itself, located by probes through its identifier or symbol
+#[no_mangle]
+#[used]
+static mut _SEGGER_RTT: ControlBlock = ControlBlock {
+    id: [0; 16],
+    max_up: 0,
+    max_down: 0,
+    up: Channel {
+        name: ptr::null(),
+        buffer: ptr::null_mut(),
+        size: 0,
+        write: 0,
+        read: 0,
+        flags: 0,
+    },
+};
+
+/// The ring buffer of the up channel
+static mut BUFFER: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
+
+/// Whether the control block has been initialised
+static INITIALISED: AtomicBool = AtomicBool::new(false);
+
+/// Initialises the control block, such that a probe attaching afterwards can find it
+///
+/// The identifier is written last so that a probe scanning memory concurrently never finds a
+/// partially initialised block.
+///
+/// # Safety
+///
+/// Must be called with interrupts disabled, as it takes exclusive access of the control block.
+#[allow(clippy::cast_possible_truncation)] // the buffer size is far below u32::MAX
+unsafe fn init() {
+    let cb = addr_of_mut!(_SEGGER_RTT);
+
+    ptr::write_volatile(
+        addr_of_mut!((*cb).up),
+        Channel {
+            name: NAME.as_ptr(),
+            buffer: addr_of_mut!(BUFFER).cast(),
+            size: BUFFER_SIZE as u32,
+            write: 0,
+            read: 0,
+            flags: MODE_NO_BLOCK_TRIM,
+        },
+    );
+    ptr::write_volatile(addr_of_mut!((*cb).max_up), 1);
+    ptr::write_volatile(addr_of_mut!((*cb).max_down), 0);
+
+    compiler_fence(Ordering::SeqCst);
+    ptr::write_volatile(addr_of_mut!((*cb).id), *ID);
+}
+
+/// Writes as many of the given bytes to the up channel as fit, returning how many were written
+pub fn write(bytes: &[u8]) -> usize {
+    interrupt::free(|_| {
+        // SAFETY: interrupts are disabled, so this is the only access from the target; the host
+        // only ever writes the read offset, which is read volatile
+        unsafe {
+            if !INITIALISED.swap(true, Ordering::Relaxed) {
+                init();
+            }
+
+            let up = addr_of_mut!(_SEGGER_RTT.up);
+            let size = BUFFER_SIZE;
+            let mut write = ptr::read_volatile(addr_of!((*up).write)) as usize;
+            let read = ptr::read_volatile(addr_of!((*up).read)) as usize;
+
+            // one byte always remains free, so that a full buffer is distinguishable from empty
+            let free = (read + size - write - 1) % size;
+            let len = bytes.len().min(free);
+
+            for &b in &bytes[..len] {
+                ptr::write_volatile(addr_of_mut!(BUFFER).cast::<u8>().add(write), b);
+                write = (write + 1) % size;
+            }
+
+            compiler_fence(Ordering::SeqCst);
+            #[allow(clippy::cast_possible_truncation)] // offsets are below BUFFER_SIZE
+            ptr::write_volatile(addr_of_mut!((*up).write), write as u32);
+
+            len
+        }
+    })
+}
+
+/// A formatter which writes to the up channel
+struct Writer;
+
+impl Write for Writer {
+    fn write_str(&mut self, s: &str) -> FmtResult {
+        write(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Prints a formatted line to the up channel; used by [`debug!`](scewl::debug)
+pub fn println(args: Arguments<'_>) {
+    let _ignored = Writer.write_fmt(args);
+    write(b"\n");
+}
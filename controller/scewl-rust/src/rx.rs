@@ -0,0 +1,296 @@
+//! Interrupt-driven reception from the UARTs, which overlaps the reception of a frame with the
+//! processing (e.g. decryption) of the one before it
+//!
+//! Ordinarily, the radio's UART is polled, so that while the controller decrypts a frame, the
+//! UART's 16 byte receive FIFO fills and holds the sender off. With the `pipelined` feature, the
+//! UART's receive interrupts instead drain the FIFO into a [queue](scewl::queue) large enough to
+//! hold a frame, so that the next frame arrives while the controller is busy with the last; the
+//! controller then reads it from the queue at the speed of memory. The data buffer and the queue
+//! thus form a double buffer.
+//!
+//! With the `interrupt-driven` feature, the CPU's and SSS's UARTs are received into queues of their
+//! own in the same way, so that every byte the controller reads has been queued by an interrupt.
+//! Having nothing to do but wait for one, the controller's run loop then [sleeps](sleep_unless)
+//! until the next interrupt rather than polling the UARTs; the [SysTick](crate::systick) interrupt
+//! wakes it every millisecond regardless, so that its timers are still served.
+//!
+//! Should a queue fill, the interrupts of its UART are masked, leaving the rest of the frame in the
+//! FIFO (holding the sender off, as when polling) until the controller has read from the queue, so
+//! no byte is ever dropped. The controller then drains the FIFO itself, and unmasks the interrupts
+//! once it has emptied it.
+//!
+//! An interface reads from its queue only once its UART has been [started](start), and from the
+//! FIFO until then.
+//!
+//! With the `rtic` feature, the receive interrupts are the hardware tasks of the
+//! [task architecture](crate::tasks), and the controller masks only them, rather than every
+//! interrupt, while it drains a paused FIFO.
+//!
+//! As a library may not define interrupt handlers for the binary which links it, the firmware
+//! entrypoint does so:
+//!
+//! ```text
+//! #[interrupt]
+//! fn UART2() {
+//!     rx::on_interrupt(INTF::RAD);
+//! }
+//! ```
+
+use core::cell::UnsafeCell;
+use core::ptr::addr_of_mut;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "interrupt-driven")]
+use cortex_m::asm;
+use cortex_m::interrupt;
+use cortex_m::peripheral::NVIC;
+use lm3s6965::Interrupt;
+
+#[cfg(not(feature = "interrupt-driven"))]
+use scewl::fatal::Fatal;
+use scewl::queue::{Consumer, Producer, Queue};
+use scewl::transport::{Transport, INTF};
+
+use crate::interface::Interface;
+#[cfg(feature = "rtic")]
+use crate::tasks;
+
+/// The capacity of the radio's queue, which holds all but the largest frames in their entirety
+const RAD_CAPACITY: usize = 16384;
+
+/// The capacity of the CPU's queue; the CPU is not raced by a peer, so a full queue holding it off
+/// costs little
+#[cfg(feature = "interrupt-driven")]
+const CPU_CAPACITY: usize = 4096;
+
+/// The capacity of the SSS's queue, which holds any of its messages in their entirety
+#[cfg(feature = "interrupt-driven")]
+const SSS_CAPACITY: usize = 1024;
+
+/// The queue into which the receive interrupts drain the radio's UART
+static mut RAD_QUEUE: Queue<u8, RAD_CAPACITY> = Queue::new();
+
+/// The radio's UART and its queue
+static RAD: Channel<RAD_CAPACITY> = Channel::new();
+
+/// The queue into which the receive interrupts drain the CPU's UART
+#[cfg(feature = "interrupt-driven")]
+static mut CPU_QUEUE: Queue<u8, CPU_CAPACITY> = Queue::new();
+
+/// The CPU's UART and its queue
+#[cfg(feature = "interrupt-driven")]
+static CPU: Channel<CPU_CAPACITY> = Channel::new();
+
+/// The queue into which the receive interrupts drain the SSS's UART
+#[cfg(feature = "interrupt-driven")]
+static mut SSS_QUEUE: Queue<u8, SSS_CAPACITY> = Queue::new();
+
+/// The SSS's UART and its queue
+#[cfg(feature = "interrupt-driven")]
+static SSS: Channel<SSS_CAPACITY> = Channel::new();
+
+/// A UART received by interrupt, with the halves of the queue into which it is drained
+struct Channel<const N: usize> {
+    /// The UART and the writing half of the queue, used only by its receive interrupt (or by the
+    /// controller, with the interrupt masked)
+    producer: UnsafeCell<Option<(Interface, Producer<'static, u8, N>)>>,
+    /// The reading half of the queue, used only by the controller
+    consumer: UnsafeCell<Option<Consumer<'static, u8, N>>>,
+    /// Whether the interrupts are masked because the queue filled
+    paused: AtomicBool,
+}
+
+// SAFETY: the producer is only accessed by the UART's receive interrupt, which does not preempt
+// itself, or with it masked; the consumer only by the controller
+unsafe impl<const N: usize> Sync for Channel<N> {}
+
+impl<const N: usize> Channel<N> {
+    /// A channel which has not yet been started
+    const fn new() -> Self {
+        Channel {
+            producer: UnsafeCell::new(None),
+            consumer: UnsafeCell::new(None),
+            paused: AtomicBool::new(false),
+        }
+    }
+
+    /// Starts receiving from the UART into the queue
+    ///
+    /// # Safety
+    ///
+    /// This must be called once, before the UART's receive interrupt is unmasked, so that nothing
+    /// else accesses the queue or its halves yet.
+    unsafe fn start(&self, queue: &'static mut Queue<u8, N>, mut uart: Interface) {
+        let (producer, consumer) = queue.split();
+        uart.listen(true);
+        *self.producer.get() = Some((uart, producer));
+        *self.consumer.get() = Some(consumer);
+    }
+
+    /// Drains the UART into the queue, masking its interrupts should the queue fill
+    ///
+    /// # Safety
+    ///
+    /// This must only be called from the UART's receive interrupt handler.
+    unsafe fn on_interrupt(&self) {
+        if let Some((uart, producer)) = (*self.producer.get()).as_mut() {
+            if !uart.drain(producer) {
+                uart.listen(false);
+                self.paused.store(true, Ordering::Release);
+            }
+        }
+    }
+
+    /// The reading half of the queue, once started
+    #[allow(clippy::mut_from_ref)] // the consumer is only ever borrowed by one call at a time
+    fn consumer(&self) -> Option<&mut Consumer<'static, u8, N>> {
+        // SAFETY: the consumer is only used by the controller, from which this is called
+        unsafe { (*self.consumer.get()).as_mut() }
+    }
+
+    /// Determines whether the channel has been started
+    fn started(&self) -> bool {
+        self.consumer().is_some()
+    }
+
+    /// Determines whether received data is waiting in the queue
+    fn avail(&self) -> bool {
+        self.consumer().is_some_and(|consumer| !consumer.is_empty())
+    }
+
+    /// Pops the oldest received byte from the queue, if any
+    fn pop(&self) -> Option<u8> {
+        let byte = self.consumer()?.pop();
+
+        if byte.is_some() && self.paused.load(Ordering::Acquire) {
+            self.resume();
+        }
+
+        byte
+    }
+
+    /// Drains the FIFO, which the receive interrupts left as the queue was full, into the room
+    /// which has since been made; unmasks the interrupts once the FIFO is empty
+    fn resume(&self) {
+        // SAFETY: the receive interrupts are masked while paused, and all of them within the
+        // critical section, so the producer is not in use
+        #[cfg(not(feature = "rtic"))]
+        interrupt::free(|_| unsafe { self.unpause() });
+        #[cfg(feature = "rtic")]
+        tasks::lock(tasks::RX, || unsafe { self.unpause() });
+    }
+
+    /// Drains the FIFO into the queue, and unmasks the interrupts should it empty
+    ///
+    /// # Safety
+    ///
+    /// This must only be called with the receive interrupts masked, as [`resume`](Self::resume)
+    /// does.
+    unsafe fn unpause(&self) {
+        if let Some((uart, producer)) = (*self.producer.get()).as_mut() {
+            if uart.drain(producer) {
+                self.paused.store(false, Ordering::Relaxed);
+                uart.listen(true);
+            }
+        }
+    }
+}
+
+/// Starts receiving from the given UART in the background; to be called once for each UART, at
+/// boot
+///
+/// Without the `interrupt-driven` feature, only the radio may be started.
+pub fn start(uart: Interface) {
+    // SAFETY: this is called once for each UART, before its receive interrupts are unmasked, so
+    // nothing else accesses its queue or their halves yet
+    unsafe {
+        match uart.named() {
+            INTF::RAD => {
+                RAD.start(&mut *addr_of_mut!(RAD_QUEUE), uart);
+                NVIC::unmask(Interrupt::UART2);
+            }
+            #[cfg(feature = "interrupt-driven")]
+            INTF::CPU => {
+                CPU.start(&mut *addr_of_mut!(CPU_QUEUE), uart);
+                NVIC::unmask(Interrupt::UART0);
+            }
+            #[cfg(feature = "interrupt-driven")]
+            INTF::SSS => {
+                SSS.start(&mut *addr_of_mut!(SSS_QUEUE), uart);
+                NVIC::unmask(Interrupt::UART1);
+            }
+            #[cfg(not(feature = "interrupt-driven"))]
+            _ => Fatal::Interface.panic(),
+        }
+    }
+}
+
+/// Drains the given UART into its queue; to be called from that UART's interrupt handler only
+pub fn on_interrupt(intf: INTF) {
+    // SAFETY: this is only called from the interrupt handler of the given UART
+    unsafe {
+        match intf {
+            INTF::RAD => RAD.on_interrupt(),
+            #[cfg(feature = "interrupt-driven")]
+            INTF::CPU => CPU.on_interrupt(),
+            #[cfg(feature = "interrupt-driven")]
+            INTF::SSS => SSS.on_interrupt(),
+            #[cfg(not(feature = "interrupt-driven"))]
+            _ => {}
+        }
+    }
+}
+
+/// Determines whether the given UART is received by interrupt, i.e. has been started
+pub fn started(intf: INTF) -> bool {
+    match intf {
+        INTF::RAD => RAD.started(),
+        #[cfg(feature = "interrupt-driven")]
+        INTF::CPU => CPU.started(),
+        #[cfg(feature = "interrupt-driven")]
+        INTF::SSS => SSS.started(),
+        #[cfg(not(feature = "interrupt-driven"))]
+        _ => false,
+    }
+}
+
+/// Determines whether data received from the given UART is waiting in its queue
+pub fn avail(intf: INTF) -> bool {
+    match intf {
+        INTF::RAD => RAD.avail(),
+        #[cfg(feature = "interrupt-driven")]
+        INTF::CPU => CPU.avail(),
+        #[cfg(feature = "interrupt-driven")]
+        INTF::SSS => SSS.avail(),
+        #[cfg(not(feature = "interrupt-driven"))]
+        _ => false,
+    }
+}
+
+/// Pops the oldest byte received from the given UART from its queue, if any
+pub fn pop(intf: INTF) -> Option<u8> {
+    match intf {
+        INTF::RAD => RAD.pop(),
+        #[cfg(feature = "interrupt-driven")]
+        INTF::CPU => CPU.pop(),
+        #[cfg(feature = "interrupt-driven")]
+        INTF::SSS => SSS.pop(),
+        #[cfg(not(feature = "interrupt-driven"))]
+        _ => None,
+    }
+}
+
+/// Sleeps until the next interrupt, unless the given condition (e.g. that received data is
+/// waiting) already holds
+///
+/// The condition is checked with interrupts disabled, so that an interrupt between the check and
+/// the sleep is not missed: it still wakes the core, and is handled once interrupts are enabled
+/// again.
+#[cfg(feature = "interrupt-driven")]
+pub fn sleep_unless(ready: impl FnOnce() -> bool) {
+    interrupt::free(|_| {
+        if !ready() {
+            asm::wfi();
+        }
+    });
+}
@@ -0,0 +1,104 @@
+//! The on-target half of the [throughput benchmark](scewl::bench), timed by SysTick
+//!
+//! SysTick is a 24-bit down-counter clocked by the core; each time it wraps, its exception counts
+//! the wrap so that the combination forms a monotonic 64-bit cycle counter.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use cortex_m::peripheral::syst::SystClkSource;
+use cortex_m::peripheral::SYST;
+use cortex_m_rt::exception;
+use scewl::bench::{self, Clock, SIZES};
+use scewl::controller::SCEWL_MAX_DATA_SZ;
+use scewl::info;
+use scewl::scratch::Pool;
+use scewl::{secure, trivial};
+
+/// The value SysTick counts down from, which is its maximum
+const RELOAD: u32 = 0x00FF_FFFF;
+/// The frequency of the core clock, which is the lm3s6965's 12 MHz oscillator out of reset
+const CORE_CLOCK_HZ: u64 = 12_000_000;
+/// The approximate number of bytes sent for each measurement; far less than on the host, as the
+/// emulated core is slow
+const BUDGET: usize = 256 * 1024;
+
+/// The number of times SysTick has wrapped since the benchmark began
+static WRAPS: AtomicU32 = AtomicU32::new(0);
+
+/// The buffer used by the benchmark, as the controller would use
+static mut DATA: [u8; SCEWL_MAX_DATA_SZ] = [0_u8; SCEWL_MAX_DATA_SZ];
+
+/// The scratch buffers used by the benchmark, as the controller would lend them
+static mut SCRATCH: Pool = Pool::new();
+
+/// Counts each wrap of SysTick
+#[exception]
+fn SysTick() {
+    WRAPS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The number of core clock cycles since SysTick was started
+fn now() -> u64 {
+    loop {
+        let wraps = WRAPS.load(Ordering::Relaxed);
+        let current = SYST::get_current();
+
+        // should SysTick have wrapped in between, the current value belongs to the next wrap
+        if wraps == WRAPS.load(Ordering::Relaxed) {
+            return u64::from(wraps) * (u64::from(RELOAD) + 1) + u64::from(RELOAD - current);
+        }
+    }
+}
+
+/// Runs the benchmark for every handler family and message size, reporting over semihosting
+pub fn run() {
+    let mut syst = cortex_m::Peripherals::take()
+        .expect("The peripherals were already taken")
+        .SYST;
+    syst.set_clock_source(SystClkSource::Core);
+    syst.set_reload(RELOAD);
+    syst.clear_current();
+    syst.enable_interrupt();
+    syst.enable_counter();
+
+    // SAFETY: the benchmark runs to completion on a single thread of execution and is the only
+    // user of these buffers
+    let data = unsafe { &mut *core::ptr::addr_of_mut!(DATA) };
+    // SAFETY: as above
+    let scratch = unsafe { &*core::ptr::addr_of!(SCRATCH) };
+    let mut clock = Clock {
+        ticks_per_sec: CORE_CLOCK_HZ,
+        now,
+    };
+
+    for &size in &SIZES {
+        let iterations = bench::iterations(size, BUDGET);
+
+        let report = bench::measure(
+            "trivial",
+            &mut trivial::CryptoHandler,
+            &mut trivial::CryptoHandler,
+            data,
+            scratch,
+            size,
+            iterations,
+            &mut clock,
+        );
+        info!("{}", report);
+
+        let report = bench::measure(
+            "secure",
+            &mut secure::CryptoHandler::new([1; 32], [2; 16], [3; 64]),
+            &mut secure::CryptoHandler::new([4; 32], [2; 16], [3; 64]),
+            data,
+            scratch,
+            size,
+            iterations,
+            &mut clock,
+        );
+        info!("{}", report);
+    }
+
+    syst.disable_interrupt();
+    syst.disable_counter();
+}
@@ -0,0 +1,529 @@
+//! On-target tests for the [frame codec](scewl::codec)
+
+use scewl::banner::Banner;
+use scewl::build_info;
+use scewl::codec::secure::{
+    SecureSSSAlive, SecureSSSAllowlist, SecureSSSChallenge, SecureSSSErasure, SecureSSSKeepalive,
+    SecureSSSMessage, SecureSSSProof, SecureSSSResponse, SecureSSSRevocation, SecureSSSRotation,
+    SecureSSSSecrets, VerificationSegment, CAPS, CAP_HEADER_CRC, SUITE,
+};
+use scewl::codec::{self, Id, Message, MessageHeader, SSSMessage, SSSOp};
+use scewl::diag::{
+    self, Command, Counts, Drops, Integrity, Notice, Reason, Tamper, Traffic, Version,
+};
+use scewl::legacy;
+use scewl::level::{self, Level};
+use scewl::policy::Link;
+use scewl::provision::{self, Record};
+use scewl::update::{Ack, Frame, Status};
+
+/// Reserved ids map onto their variants and every other id round-trips
+pub fn id() {
+    assert_eq!(Id::from(0), Id::Broadcast);
+    assert_eq!(Id::from(1), Id::SSS);
+    assert_eq!(Id::from(2), Id::FAA);
+    assert_eq!(Id::from(3), Id::Other(3));
+    assert_eq!(u16::from(Id::Other(u16::MAX)), u16::MAX);
+}
+
+/// Headers carry their magic and round-trip through their serialised form
+pub fn header() {
+    let msg = Message {
+        tgt_id: Id::Other(12),
+        src_id: Id::FAA,
+        len: 0x4100,
+    };
+    let bytes = msg.to_canonical().to_bytes();
+    assert!(MessageHeader::verify_magic(&bytes));
+
+    let hdr = MessageHeader::from_bytes(bytes);
+    assert_eq!(hdr.tgt_id, Id::Other(12));
+    assert_eq!(hdr.src_id, Id::FAA);
+    assert_eq!(hdr.len, 0x4100);
+    assert_eq!(Message::from(hdr).len, msg.len);
+}
+
+/// The header CRC is CRC-16/CCITT-FALSE, covers the version of the extension, and detects a
+/// corrupted length
+pub fn header_crc() {
+    assert_eq!(codec::crc16(b"123456789"), 0x29B1);
+    assert_eq!(codec::crc16(&[]), 0xFFFF);
+
+    let hdr = MessageHeader {
+        tgt_id: Id::Other(12),
+        src_id: Id::Other(13),
+        len: 0x10,
+    };
+    let crc = hdr.crc(MessageHeader::VERSION);
+    assert_eq!(crc, codec::crc16(b"SC\x0c\x00\x0d\x00\x10\x00\x01"));
+    assert_ne!(hdr.crc(MessageHeader::VERSION + 1), crc);
+    for bit in 0..16 {
+        let corrupt = MessageHeader {
+            len: hdr.len ^ 1 << bit,
+            ..hdr
+        };
+        assert_ne!(corrupt.crc(MessageHeader::VERSION), crc);
+    }
+
+    let [version, lo, hi] = hdr.extension();
+    assert_eq!(version, MessageHeader::VERSION);
+    assert_eq!(u16::from_le_bytes([lo, hi]), crc);
+}
+
+/// Legacy frames are marked in place, should the buffer have room for the magic
+pub fn legacy_mark() {
+    let mut buf = [0_u8; 16];
+    buf[..5].copy_from_slice(b"hello");
+    assert_eq!(legacy::mark(&mut buf, 5), Some(12));
+    assert_eq!(&buf[..12], b"UNAUTH hello");
+
+    assert_eq!(legacy::mark(&mut buf, 10), None);
+    assert_eq!(&buf[..12], b"UNAUTH hello");
+}
+
+/// SSS messages round-trip, and corrupt operations are recognised as such
+pub fn sss_message() {
+    let msg = SSSMessage {
+        dev_id: Id::Other(42),
+        op: SSSOp::Deregister,
+    };
+    let parsed = SSSMessage::from_bytes(&msg.to_bytes());
+    assert_eq!(parsed.dev_id, Id::Other(42));
+    assert_eq!(parsed.op, SSSOp::Deregister);
+
+    let corrupt = SSSMessage::from_bytes(&[42, 0, 0x7F, 0x7F]);
+    assert_eq!(corrupt.op, SSSOp::Unknown);
+}
+
+/// Secure SSS messages, challenges and proofs, both forms of secure SSS responses, and the
+/// allowlists which may follow them round-trip
+pub fn secure_sss() {
+    let msg = SecureSSSMessage {
+        dev_id: Id::Other(42),
+        op: SSSOp::Register,
+        suite: SUITE,
+        caps: CAPS,
+    };
+    let mut bytes = [0_u8; SecureSSSMessage::size()];
+    assert_eq!(msg.to_bytes(&mut bytes), SecureSSSMessage::size());
+    let parsed = SecureSSSMessage::from_bytes(&bytes).unwrap();
+    assert_eq!(parsed.dev_id, Id::Other(42));
+    assert_eq!(parsed.op, SSSOp::Register);
+    assert_eq!(parsed.suite, SUITE);
+    assert_eq!(parsed.caps, CAPS);
+    assert!(SecureSSSMessage::from_bytes(&bytes[1..]).is_none());
+
+    let challenge = SecureSSSChallenge {
+        dev_id: Id::Other(42),
+        op: SSSOp::Challenge,
+        nonce: [3; 32],
+    };
+    let parsed = SecureSSSChallenge::from_bytes(&challenge.to_bytes()).unwrap();
+    assert_eq!(parsed.dev_id, Id::Other(42));
+    assert_eq!(parsed.op, SSSOp::Challenge);
+    assert_eq!(parsed.nonce, [3; 32]);
+    assert!(SecureSSSChallenge::from_bytes(&challenge.to_bytes()[1..]).is_none());
+
+    let proof = SecureSSSProof {
+        dev_id: Id::Other(42),
+        op: SSSOp::Deregister,
+        proof: [4; 32],
+    };
+    let mut bytes = [0_u8; SecureSSSProof::size()];
+    assert_eq!(proof.to_bytes(&mut bytes), SecureSSSProof::size());
+    let parsed = SecureSSSProof::from_bytes(&bytes).unwrap();
+    assert_eq!(parsed.dev_id, Id::Other(42));
+    assert_eq!(parsed.op, SSSOp::Deregister);
+    assert_eq!(parsed.proof, [4; 32]);
+    assert!(SecureSSSProof::from_bytes(&bytes[1..]).is_none());
+
+    let mut buf = [0_u8; SecureSSSResponse::size()];
+    let resp = SecureSSSResponse {
+        dev_id: Id::Other(42),
+        op: SSSOp::Register,
+        secrets: Some(SecureSSSSecrets {
+            aes_key: [1; 16],
+            seed: [2; 32],
+            hmac_key: [3; 64],
+            epoch: 4,
+            caps: CAP_HEADER_CRC,
+            integrity: [5; 8],
+        }),
+    };
+    let len = resp.to_bytes(&mut buf);
+    assert_eq!(len, SecureSSSResponse::size());
+    let secrets = SecureSSSResponse::from_bytes(&buf[..len])
+        .unwrap()
+        .secrets
+        .unwrap();
+    assert_eq!(secrets.aes_key, [1; 16]);
+    assert_eq!(secrets.seed, [2; 32]);
+    assert_eq!(secrets.hmac_key, [3; 64]);
+    assert_eq!(secrets.epoch, 4);
+    assert_eq!(secrets.caps, CAP_HEADER_CRC);
+    assert_eq!(secrets.integrity, [5; 8]);
+
+    let resp = SecureSSSResponse {
+        op: SSSOp::Already,
+        secrets: None,
+        ..resp
+    };
+    let len = resp.to_bytes(&mut buf);
+    let parsed = SecureSSSResponse::from_bytes(&buf[..len]).unwrap();
+    assert_eq!(parsed.op, SSSOp::Already);
+    assert!(parsed.secrets.is_none());
+    assert!(SecureSSSResponse::from_bytes(&buf[..1]).is_none());
+
+    let allowed = [Id::Other(10), Id::Other(267)];
+    let mut bytes = [0xFF_u8; SecureSSSAllowlist::size(2) + 1];
+    let len = SecureSSSAllowlist::to_bytes(allowed.iter().copied(), &mut bytes);
+    assert_eq!(len, SecureSSSAllowlist::size(2));
+    let parsed = SecureSSSAllowlist::from_bytes(&bytes).unwrap();
+    assert_eq!(parsed.serialised_len(), len);
+    assert!(parsed.allowed().eq(allowed.iter().copied()));
+    assert!(SecureSSSAllowlist::from_bytes(&bytes[..len - 1]).is_none());
+    assert!(SecureSSSAllowlist::from_bytes(&[]).is_none());
+}
+
+/// Pushes of new keys and of revoked devices round-trip
+pub fn sss_push() {
+    let push = SecureSSSRotation {
+        dev_id: Id::Other(42),
+        op: SSSOp::Rotate,
+        epoch: 5,
+        wrapped: [6; 80],
+        tag: [7; 32],
+    };
+    let bytes = push.to_bytes();
+    let parsed = SecureSSSRotation::from_bytes(&bytes).unwrap();
+    assert_eq!(parsed.dev_id, Id::Other(42));
+    assert_eq!(parsed.op, SSSOp::Rotate);
+    assert_eq!(parsed.epoch, 5);
+    assert_eq!(parsed.wrapped, [6; 80]);
+    assert_eq!(parsed.tag, [7; 32]);
+    assert!(SecureSSSRotation::from_bytes(&bytes[1..]).is_none());
+
+    let ids = [10, 0, 11, 1];
+    let push = SecureSSSRevocation {
+        dev_id: Id::Other(42),
+        op: SSSOp::Revoke,
+        serial: 5,
+        ids: &ids,
+        tag: [7; 32],
+    };
+    let mut bytes = [0_u8; SecureSSSRevocation::size(2)];
+    assert_eq!(push.to_bytes(&mut bytes), bytes.len());
+    let parsed = SecureSSSRevocation::from_bytes(&bytes).unwrap();
+    assert_eq!(parsed.dev_id, Id::Other(42));
+    assert_eq!(parsed.op, SSSOp::Revoke);
+    assert_eq!(parsed.serial, 5);
+    assert!(parsed.revoked().eq([Id::Other(10), Id::Other(267)]));
+    assert_eq!(parsed.tag, [7; 32]);
+    assert!(SecureSSSRevocation::from_bytes(&bytes[1..]).is_none());
+    assert!(SecureSSSRevocation::from_bytes(&bytes[..SecureSSSRevocation::size(1)]).is_none());
+    assert_eq!(i16::from(SSSOp::Revoked), 5);
+    assert_eq!(SSSOp::from(7), SSSOp::Rekey);
+}
+
+/// Keepalives and their answers round-trip
+pub fn sss_keepalive() {
+    let keepalive = SecureSSSKeepalive {
+        dev_id: Id::Other(42),
+        op: SSSOp::Keepalive,
+        nonce: [6; 16],
+    };
+    let bytes = keepalive.to_bytes();
+    let parsed = SecureSSSKeepalive::from_bytes(&bytes).unwrap();
+    assert_eq!(parsed.dev_id, Id::Other(42));
+    assert_eq!(parsed.op, SSSOp::Keepalive);
+    assert_eq!(parsed.nonce, [6; 16]);
+    assert!(SecureSSSKeepalive::from_bytes(&bytes[1..]).is_none());
+
+    let alive = SecureSSSAlive {
+        dev_id: Id::Other(42),
+        op: SSSOp::Deregister,
+        tag: [7; 32],
+    };
+    let bytes = alive.to_bytes();
+    let parsed = SecureSSSAlive::from_bytes(&bytes).unwrap();
+    assert_eq!(parsed.dev_id, Id::Other(42));
+    assert_eq!(parsed.op, SSSOp::Deregister);
+    assert_eq!(parsed.tag, [7; 32]);
+    assert!(SecureSSSAlive::from_bytes(&bytes[1..]).is_none());
+    assert_eq!(SSSOp::from(8), SSSOp::Keepalive);
+}
+
+/// Attestations to erasing keys round-trip
+pub fn sss_erasure() {
+    let erasure = SecureSSSErasure {
+        dev_id: Id::Other(42),
+        op: SSSOp::Erased,
+        epoch: 3,
+        tag: [8; 32],
+    };
+    let bytes = erasure.to_bytes();
+    let parsed = SecureSSSErasure::from_bytes(&bytes).unwrap();
+    assert_eq!(parsed.dev_id, Id::Other(42));
+    assert_eq!(parsed.op, SSSOp::Erased);
+    assert_eq!(parsed.epoch, 3);
+    assert_eq!(parsed.tag, [8; 32]);
+    assert!(SecureSSSErasure::from_bytes(&bytes[1..]).is_none());
+    assert_eq!(SSSOp::from(9), SSSOp::Erased);
+}
+
+/// Verification segments round-trip through their serialised form
+pub fn verification_segment() {
+    let seg = VerificationSegment {
+        iv: [7; 16],
+        ctr: 0x1_0000_0001,
+        epoch: 0x0203_0405,
+        hmac: [9; 32],
+    };
+    let parsed = VerificationSegment::from_bytes(&seg.to_bytes());
+    assert_eq!(parsed.iv, seg.iv);
+    assert_eq!(parsed.ctr, seg.ctr);
+    assert_eq!(parsed.epoch, seg.epoch);
+    assert_eq!(parsed.hmac, seg.hmac);
+}
+
+/// Drop counters saturate, and round-trip through a diagnostic response
+pub fn drops() {
+    let mut drops = Drops::default();
+    drops.record(Reason::Replay);
+    drops.record(Reason::Replay);
+    drops.record(Reason::CpuSpoofed);
+    assert_eq!(drops.get(Reason::Replay), 2);
+    assert_eq!(drops.get(Reason::BadMac), 0);
+    assert_eq!(drops.total(), 3);
+
+    let mut buf = [0_u8; 2 * Drops::size()];
+    let len = diag::respond_drops(&mut buf, &drops);
+    assert_eq!(Command::from_bytes(&buf[..len]), Some(Command::Drops));
+    assert_eq!(buf[diag::MAGIC.len() + 1], 0);
+    let parsed = Drops::from_bytes(&buf[diag::MAGIC.len() + 2..len]).unwrap();
+    assert_eq!(parsed, drops);
+    assert!(Drops::from_bytes(&buf[..len]).is_none());
+
+    let mut full = Drops::from_bytes(&[0xFF; Drops::size()]).unwrap();
+    full.record(Reason::BadMagic);
+    assert_eq!(full.get(Reason::BadMagic), u32::MAX);
+}
+
+/// Traffic counters saturate, and round-trip through a diagnostic response ahead of the drops
+pub fn stats() {
+    assert_eq!(Command::from_bytes(b"DIAG\x04"), Some(Command::Stats));
+
+    let mut traffic = Traffic::default();
+    traffic.received(Link::Radio, 100);
+    traffic.received(Link::Radio, 20);
+    traffic.sent(Link::Cpu, 120);
+    assert_eq!(
+        traffic.get(Link::Radio),
+        Counts {
+            frames_in: 2,
+            bytes_in: 120,
+            ..Counts::default()
+        }
+    );
+    assert_eq!(traffic.get(Link::Sss), Counts::default());
+
+    let mut drops = Drops::default();
+    drops.record(Reason::BadMac);
+
+    let mut buf = [0_u8; 2 * (Traffic::size() + Drops::size())];
+    let len = diag::respond_stats(&mut buf, &traffic, &drops);
+    assert_eq!(&buf[..6], b"DIAG\x04\x00");
+    let (counts, rest) = buf[6..len].split_at(Traffic::size());
+    assert_eq!(Traffic::from_bytes(counts), Some(traffic));
+    assert_eq!(Drops::from_bytes(rest), Some(drops));
+    assert_eq!(Traffic::from_bytes(&buf[6..len]), None);
+
+    let mut full = Traffic::from_bytes(&[0xFF; Traffic::size()]).unwrap();
+    full.sent(Link::Sss, 1);
+    assert_eq!(full.get(Link::Sss).frames_out, u32::MAX);
+    assert_eq!(full.get(Link::Sss).bytes_out, u32::MAX);
+}
+
+/// Drop notices round-trip, and only decode with their magic and a valid reason
+pub fn notice() {
+    let notice = Notice {
+        src_id: Id::Other(0x1234),
+        reason: Reason::Replay,
+    };
+    let mut buf = [0_u8; 16];
+    let len = notice.to_bytes(&mut buf);
+    assert_eq!(&buf[..len], b"DROP\x34\x12\x02");
+    assert_eq!(Notice::from_bytes(&buf[..len]), Some(notice));
+    assert_eq!(Notice::from_bytes(&buf[..len - 1]), None);
+    assert!(Command::from_bytes(&buf[..len]).is_none());
+
+    buf[len - 1] = 0xFF;
+    assert_eq!(Notice::from_bytes(&buf[..len]), None);
+    buf[len - 1] = 0;
+    buf[0] = b'D' ^ 1;
+    assert_eq!(Notice::from_bytes(&buf[..len]), None);
+}
+
+/// Integrity reports round-trip through a diagnostic response, which fails without a report
+pub fn integrity() {
+    assert_eq!(Command::from_bytes(b"DIAG\x03"), Some(Command::Integrity));
+
+    let integrity = Integrity {
+        state: Tamper::Tampered,
+        checks: 0x0102_0304,
+    };
+    let mut buf = [0_u8; 16];
+    let len = diag::respond_integrity(&mut buf, Some(integrity));
+    assert_eq!(&buf[..len], b"DIAG\x03\x00\x02\x04\x03\x02\x01");
+    assert_eq!(Integrity::from_bytes(&buf[6..len]), Some(integrity));
+    assert_eq!(Integrity::from_bytes(&buf[6..len - 1]), None);
+
+    buf[6] = 3;
+    assert_eq!(Integrity::from_bytes(&buf[6..len]), None);
+
+    let len = diag::respond_integrity(&mut buf, None);
+    assert_eq!(&buf[..len], b"DIAG\x03\x01");
+}
+
+/// Diagnostic commands to set the log level decode only with a valid level, and report the level
+/// which took effect
+pub fn set_level() {
+    let cmd = *b"DIAG\x01\x04";
+    assert_eq!(
+        Command::from_bytes(&cmd),
+        Some(Command::SetLevel(Level::Debug))
+    );
+    assert_eq!(Command::from_bytes(b"DIAG\x01\x06"), None);
+    assert_eq!(Command::from_bytes(b"DIAG\x01"), None);
+
+    let mut buf = [0_u8; 8];
+    let len = diag::respond_level(&mut buf, Level::Info);
+    assert_eq!(&buf[..len], b"DIAG\x01\x00\x03");
+
+    let previous = level::max();
+    assert_eq!(level::set_max(Level::Error), Level::Error);
+    assert!(level::enabled(Level::Error));
+    assert!(!level::enabled(Level::Warn));
+    assert_eq!(level::set_max(Level::Trace), level::STATIC_MAX);
+    level::set_max(previous);
+}
+
+/// The banner's status frame carries its text after the magic
+pub fn banner() {
+    let banner = Banner {
+        version: "0.1.0",
+        build: "3f2a9c1d07e4",
+        handlers: "secure",
+        id: Id::Other(10),
+        watchdog: false,
+    };
+    let mut buf = [0_u8; 80];
+    let len = banner.to_bytes(&mut buf);
+    assert_eq!(
+        &buf[..len],
+        b"BOOT controller 0.1.0 (3f2a9c1d07e4) secure id 10"
+    );
+
+    let len = Banner {
+        watchdog: true,
+        ..banner
+    }
+    .to_bytes(&mut buf);
+    assert_eq!(
+        &buf[..len],
+        b"BOOT controller 0.1.0 (3f2a9c1d07e4) secure id 10 after watchdog reset"
+    );
+}
+
+/// Diagnostic commands to report the version answer with this image's build metadata
+pub fn version() {
+    assert_eq!(Command::from_bytes(b"DIAG\x02"), Some(Command::Version));
+
+    let mut buf = [0_u8; 512];
+    let len = diag::respond_version(&mut buf);
+    assert_eq!(&buf[..6], b"DIAG\x02\x00");
+
+    let version = Version::from_bytes(&buf[6..len]).expect("The version did not decode");
+    assert_eq!(version.timestamp, build_info::TIMESTAMP);
+    assert_eq!(version.version, build_info::VERSION);
+    assert_eq!(version.commit, build_info::COMMIT);
+    assert!(version
+        .features
+        .split(',')
+        .eq(build_info::FEATURES.iter().copied()));
+
+    assert_eq!(Version::from_bytes(&buf[6..len - 1]), None);
+}
+
+/// Provisioning records round-trip with and without a secret, and corrupt records are rejected
+pub fn provision() {
+    assert_eq!(provision::crc32(b"123456789"), 0xCBF4_3926);
+
+    let secret = [0x5A_u8; 64];
+    let record = Record {
+        id: Id::Other(42),
+        secret: Some(&secret),
+    };
+    let mut buf = [0xFF_u8; 128];
+    let len = record.to_bytes(&mut buf);
+    assert_eq!(len, Record::size());
+    assert_eq!(Record::from_bytes(&buf), Some(record));
+    assert_eq!(Record::from_bytes(&buf[..len - 1]), None);
+
+    buf[6] ^= 1;
+    assert_eq!(Record::from_bytes(&buf), None);
+
+    let record = Record {
+        secret: None,
+        ..record
+    };
+    record.to_bytes(&mut buf);
+    assert_eq!(Record::from_bytes(&buf), Some(record));
+
+    assert_eq!(Record::from_bytes(&[0xFF; provision::PAGE]), None);
+}
+
+/// Update frames round-trip through their serialised form, and malformed frames are not taken
+/// for updates
+pub fn update() {
+    let signature = [0xA5_u8; 64];
+    let data = [1_u8, 2, 3, 4, 5];
+    let frames = [
+        Frame::Begin {
+            size: 0x1_2345,
+            signature: &signature,
+        },
+        Frame::Chunk {
+            offset: 0x400,
+            data: &data,
+        },
+        Frame::Commit,
+    ];
+
+    let mut buf = [0_u8; 128];
+    for frame in frames {
+        let len = frame.to_bytes(&mut buf);
+        assert_eq!(Frame::from_bytes(&buf[..len]), Some(frame));
+        assert_eq!(
+            Frame::from_bytes(&buf[..=len]).is_some(),
+            matches!(frame, Frame::Chunk { .. })
+        );
+    }
+
+    assert_eq!(Frame::from_bytes(b"\x01\x00\x04\x00\x00"), None);
+    assert_eq!(Frame::from_bytes(b"\x07"), None);
+    assert_eq!(Frame::from_bytes(b"\x02\x00"), None);
+    assert_eq!(Frame::from_bytes(b""), None);
+
+    let ack = Ack {
+        status: Status::Signature,
+        next: 0x2_0000,
+    };
+    let len = ack.to_bytes(&mut buf);
+    assert_eq!(len, Ack::size());
+    assert_eq!(Ack::from_bytes(&buf[..len]), Some(ack));
+    assert_eq!(Frame::from_bytes(&buf[..len]), None);
+
+    buf[1] = 9;
+    assert_eq!(Ack::from_bytes(&buf[..len]), None);
+}
@@ -0,0 +1,868 @@
+//! On-target tests for the [controller](scewl::controller), which is built with its
+//! [builder](scewl::controller::ControllerBuilder) over in-memory transports rather than the UARTs
+//!
+//! Most tests use the trivial handlers; those of registration with the secure handlers answer for
+//! the SSS with a challenge of a fixed nonce, so that its response may be tagged in advance.
+
+use core::cell::{Cell, Ref, RefCell};
+use core::convert::TryFrom;
+use core::ptr::{addr_of, addr_of_mut};
+use core::time::Duration;
+
+use scewl::auth::Handler as AuthHandler;
+use scewl::banner::Banner;
+use scewl::board::Bare;
+use scewl::codec::secure::{
+    SecureSSSAllowlist, SecureSSSChallenge, SecureSSSResponse, SecureSSSSecrets, CAP_ALLOWLIST,
+    SUITE,
+};
+use scewl::content::{Envelope, Kind};
+use scewl::controller::{
+    Controller, ControllerBuilder, Error, Id, MessageHeader, SSSMessage, SSSOp, SCEWL_MAX_DATA_SZ,
+    SCEWL_MAX_TX_SZ,
+};
+use scewl::crypto::Handler as CryptoHandler;
+use scewl::diag::Reason;
+use scewl::masked::Masked;
+use scewl::policy::ALLOWED;
+use scewl::scratch::Pool;
+use scewl::secure::{self, challenge, keywrap};
+use scewl::time::{Clock, Instant, MockClock};
+use scewl::transport::{
+    Error as TransportError, Links, Result as TransportResult, Transport, INTF,
+};
+use scewl::trivial;
+
+/// The id of the controller under test
+const ID: u16 = 10;
+/// The id of the peer of the controller under test
+const PEER: u16 = 20;
+/// The largest number of bytes which a test expects the controller to write to one line, or feeds
+/// it on one
+const CAPACITY: usize = 512;
+/// The registration secret of the controller under test, with the secure handlers
+pub(super) static SECRET: [u8; 64] = [10; 64];
+/// A secret other than that of the controller under test, which the SSS may wrongly wrap its keys
+/// under
+static OTHER_SECRET: [u8; 64] = [11; 64];
+/// The nonce with which the SSS challenges the controller under test
+const NONCE: [u8; 32] = [0xC4; 32];
+/// The epoch of the keys which the SSS provisions
+pub(super) const EPOCH: u32 = 3;
+/// The deployment's AES key, which the SSS provisions
+pub(super) const AES_KEY: [u8; 16] = [0xA5; 16];
+/// The deployment's HMAC key, which the SSS provisions
+pub(super) const HMAC_KEY: [u8; 64] = [0x5A; 64];
+
+/// The data buffer of the controller under test
+static mut DATA: [u8; SCEWL_MAX_DATA_SZ] = [0_u8; SCEWL_MAX_DATA_SZ];
+
+/// The transmit buffer of the controller under test
+static mut TX: [u8; SCEWL_MAX_TX_SZ] = [0_u8; SCEWL_MAX_TX_SZ];
+
+/// The scratch buffers of the controller under test
+static mut SCRATCH: Pool = Pool::new();
+
+/// A line whose peer sends whatever the test feeds it, and which keeps whatever the controller
+/// writes to it
+///
+/// Reads never block, as nothing more arrives until the test feeds it, or until the controller
+/// writes what the pipe holds the answer to; should the pipe be given a clock, each time it is
+/// found empty it advances the clock instead, so that a deadline passes while the controller waits
+/// on it.
+pub(super) struct Pipe<'p> {
+    /// The line which this pipe stands in for
+    intf: INTF,
+    /// Everything the peer sent, up to the capacity
+    input: RefCell<[u8; CAPACITY]>,
+    /// How much the peer sent
+    fed: Cell<usize>,
+    /// How much of the input has been read
+    read: Cell<usize>,
+    /// Whether the input is held back until the controller next writes to the pipe
+    held: Cell<bool>,
+    /// Everything the controller wrote, up to the capacity
+    output: RefCell<[u8; CAPACITY]>,
+    /// How much the controller wrote
+    written: Cell<usize>,
+    /// The clock advanced while the controller waits on an empty pipe, if any
+    clock: Option<&'p MockClock>,
+}
+
+impl<'p> Pipe<'p> {
+    /// A pipe to the given line, on which nothing has been sent
+    pub(super) fn new(intf: INTF) -> Self {
+        Self {
+            intf,
+            input: RefCell::new([0_u8; CAPACITY]),
+            fed: Cell::new(0),
+            read: Cell::new(0),
+            held: Cell::new(false),
+            output: RefCell::new([0_u8; CAPACITY]),
+            written: Cell::new(0),
+            clock: None,
+        }
+    }
+
+    /// Advances the given clock by a millisecond each time the controller finds this pipe empty
+    fn stalling(mut self, clock: &'p MockClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Sends the given bytes to the controller, after those already sent and yet to be read
+    pub(super) fn feed(&self, bytes: &[u8]) {
+        if self.read.get() == self.fed.get() {
+            self.read.set(0);
+            self.fed.set(0);
+        }
+        let fed = self.fed.get();
+        self.input.borrow_mut()[fed..][..bytes.len()].copy_from_slice(bytes);
+        self.fed.set(fed + bytes.len());
+    }
+
+    /// Sends the given bytes to the controller as the answer to whatever it next writes to this
+    /// pipe, holding them back until then
+    fn answer(&self, bytes: &[u8]) {
+        self.feed(bytes);
+        self.held.set(true);
+    }
+
+    /// Whether the controller wrote exactly the given bytes to this pipe since it was last cleared
+    pub(super) fn wrote(&self, expected: &[u8]) -> bool {
+        &self.output.borrow()[..self.written.get()] == expected
+    }
+
+    /// Forgets what the controller wrote to this pipe
+    pub(super) fn clear(&self) {
+        self.written.set(0);
+    }
+
+    /// What the controller wrote to this pipe since it was last cleared
+    pub(super) fn written(&self) -> Ref<'_, [u8]> {
+        Ref::map(self.output.borrow(), |output| &output[..self.written.get()])
+    }
+
+    /// Sends what the controller wrote to this pipe to the controller of another, as the radio
+    /// carries it between SEDs, then forgets it
+    pub(super) fn pass(&self, to: &Pipe<'_>) {
+        to.feed(&self.written());
+        self.clear();
+    }
+}
+
+impl Transport for &Pipe<'_> {
+    fn named(&self) -> INTF {
+        self.intf
+    }
+
+    fn avail(&self) -> bool {
+        let avail = !self.held.get() && self.read.get() < self.fed.get();
+        if let (false, Some(clock)) = (avail, self.clock) {
+            clock.advance_millis(1);
+        }
+        avail
+    }
+
+    fn readb(&mut self, _blocking: bool) -> TransportResult<u8> {
+        let read = self.read.get();
+        if self.held.get() || read == self.fed.get() {
+            return Err(TransportError::NoData);
+        }
+        self.read.set(read + 1);
+        Ok(self.input.borrow()[read])
+    }
+
+    fn writeb(&mut self, data: u8) {
+        self.held.set(false);
+        let written = self.written.get();
+        self.output.borrow_mut()[written] = data;
+        self.written.set(written + 1);
+    }
+}
+
+/// An entropy source which always yields the same bytes, standing in for the firmware's
+struct Fixed;
+
+impl secure::Entropy for Fixed {
+    fn fill(&self, buf: &mut [u8; 32]) {
+        buf.fill(7);
+    }
+}
+
+/// The entropy source of the secure handlers in these tests
+static ENTROPY: Fixed = Fixed;
+
+/// A record of epochs which accepts none older than its own, standing in for the firmware's flash
+pub(super) struct Floor(u32);
+
+impl secure::Epochs for Floor {
+    fn accept(&self, epoch: u32) -> bool {
+        epoch >= self.0
+    }
+
+    fn record(&self, _epoch: u32) {}
+}
+
+/// A record under which the keys provisioned by the SSS are current
+pub(super) static CURRENT: Floor = Floor(EPOCH);
+/// A record under which the keys provisioned by the SSS have been superseded
+static SUPERSEDED: Floor = Floor(EPOCH + 1);
+
+/// Begins building a controller over the given pipes, using the trivial handlers and the buffers
+/// of this module
+fn builder<'p>(
+    cpu: &'p Pipe<'p>,
+    sss: &'p Pipe<'p>,
+    rad: &'p Pipe<'p>,
+) -> ControllerBuilder<'p, trivial::AuthHandler, trivial::Registered, &'p Pipe<'p>> {
+    builder_with(cpu, sss, rad, trivial::AuthHandler)
+}
+
+/// Begins building a controller over the given pipes, using the given authentication handler and
+/// the buffers of this module
+fn builder_with<'p, A: AuthHandler<C>, C: CryptoHandler>(
+    cpu: &'p Pipe<'p>,
+    sss: &'p Pipe<'p>,
+    rad: &'p Pipe<'p>,
+    auth: A,
+) -> ControllerBuilder<'p, A, C, &'p Pipe<'p>> {
+    // SAFETY: the tests run one at a time on a single thread of execution, and each drops its
+    // controller before the next builds another over these buffers
+    builder_over(ID.into(), (cpu, sss, rad), unsafe { buffers() }, auth)
+}
+
+/// The buffers of this module, over which the controller under test is built
+///
+/// # Safety
+///
+/// No other controller may be built over the buffers while that built over them is in use.
+pub(super) unsafe fn buffers() -> (
+    &'static mut [u8; SCEWL_MAX_DATA_SZ],
+    &'static mut [u8; SCEWL_MAX_TX_SZ],
+    &'static Pool,
+) {
+    (
+        &mut *addr_of_mut!(DATA),
+        &mut *addr_of_mut!(TX),
+        &*addr_of!(SCRATCH),
+    )
+}
+
+/// Begins building a controller of the given id over the given pipes, using the given
+/// authentication handler and buffers, so that more than one controller may run at once
+pub(super) fn builder_over<'p, A: AuthHandler<C>, C: CryptoHandler>(
+    id: Id,
+    (cpu, sss, rad): (&'p Pipe<'p>, &'p Pipe<'p>, &'p Pipe<'p>),
+    (data, tx, scratch): (
+        &'p mut [u8; SCEWL_MAX_DATA_SZ],
+        &'p mut [u8; SCEWL_MAX_TX_SZ],
+        &'p Pool,
+    ),
+    auth: A,
+) -> ControllerBuilder<'p, A, C, &'p Pipe<'p>> {
+    Controller::builder(id, Links { cpu, sss, rad }, data, tx, scratch, auth, &Bare)
+}
+
+/// Registers the controller under test, answering for the SSS, then forgets what it wrote in
+/// doing so
+fn register(
+    controller: &mut Controller<'_, trivial::AuthHandler, trivial::Registered, &Pipe<'_>>,
+    (cpu, sss, rad): (&Pipe<'_>, &Pipe<'_>, &Pipe<'_>),
+) {
+    let msg = SSSMessage {
+        dev_id: ID.into(),
+        op: SSSOp::Register,
+    }
+    .to_bytes();
+    let mut buf = [0_u8; CAPACITY];
+    cpu.feed(frame(&mut buf, ID.into(), Id::SSS, &msg));
+    sss.answer(frame(&mut buf, Id::SSS, ID.into(), &msg));
+
+    controller.poll();
+    assert!(controller.registered());
+
+    // the answer of the SSS, and the hello to the peers
+    cpu.clear();
+    rad.clear();
+}
+
+/// A frame between the given devices
+pub(super) fn frame<'b>(buf: &'b mut [u8], src_id: Id, tgt_id: Id, body: &[u8]) -> &'b [u8] {
+    let hdr = MessageHeader {
+        tgt_id,
+        src_id,
+        len: u16::try_from(body.len()).unwrap(),
+    };
+    let (head, rest) = buf.split_at_mut(MessageHeader::size());
+    head.copy_from_slice(&hdr.to_bytes());
+    rest[..body.len()].copy_from_slice(body);
+    &buf[..MessageHeader::size() + body.len()]
+}
+
+/// The content of a message between SEDs of the given kind, as the trivial handlers send it
+fn content<'b>(buf: &'b mut [u8], kind: Kind, body: &[u8]) -> &'b [u8] {
+    buf[0] = kind.into();
+    buf[Kind::SIZE..][..body.len()].copy_from_slice(body);
+    let len = Kind::SIZE + body.len();
+    &buf[..len]
+}
+
+/// The controller announces itself over the CPU transport it was built with, and over no other
+pub fn announce() {
+    let (cpu, sss, rad) = (
+        Pipe::new(INTF::CPU),
+        Pipe::new(INTF::SSS),
+        Pipe::new(INTF::RAD),
+    );
+    let banner = Banner {
+        version: "1.0.0",
+        build: "selftest",
+        handlers: "trivial",
+        id: ID.into(),
+        watchdog: false,
+    };
+
+    let mut controller = builder(&cpu, &sss, &rad).mtu(512).build();
+    assert_eq!(controller.id(), Id::from(ID));
+    controller.announce(&banner).unwrap();
+
+    let mut body = [0_u8; CAPACITY];
+    let len = banner.to_bytes(&mut body);
+    let mut expected = [0_u8; CAPACITY];
+    assert!(cpu.wrote(frame(&mut expected, ID.into(), ID.into(), &body[..len])));
+    assert!(sss.wrote(&[]));
+    assert!(rad.wrote(&[]));
+}
+
+/// A frame which the CPU sends in full is read through the CPU transport, timeout or not
+pub fn read() {
+    let clock = MockClock::new();
+    let (cpu, sss, rad) = (
+        Pipe::new(INTF::CPU),
+        Pipe::new(INTF::SSS),
+        Pipe::new(INTF::RAD),
+    );
+    let mut buf = [0_u8; 32];
+    cpu.feed(frame(&mut buf, ID.into(), ID.into(), b"hello"));
+
+    let mut controller = builder(&cpu, &sss, &rad)
+        .frame_timeout(&clock, Duration::from_millis(100))
+        .build();
+    let msg = controller.read_msg(INTF::CPU, 32).unwrap();
+
+    assert_eq!(msg.src_id, Id::from(ID));
+    assert_eq!(&controller.data()[..msg.len], b"hello");
+}
+
+/// A frame which stalls part-way through fails once the frame timeout given to the builder passes
+pub fn frame_timeout() {
+    let clock = MockClock::new();
+    let (cpu, sss, rad) = (
+        Pipe::new(INTF::CPU).stalling(&clock),
+        Pipe::new(INTF::SSS),
+        Pipe::new(INTF::RAD),
+    );
+    let mut buf = [0_u8; 32];
+    let input = frame(&mut buf, ID.into(), ID.into(), b"hello");
+    cpu.feed(&input[..input.len() - 2]);
+
+    let mut controller = builder(&cpu, &sss, &rad)
+        .frame_timeout(&clock, Duration::from_millis(100))
+        .build();
+
+    assert!(matches!(
+        controller.read_msg(INTF::CPU, 32),
+        Err(Error::TimedOut)
+    ));
+    assert!(clock.now() >= Instant::from_millis(100));
+}
+
+/// An SSS which never answers fails the read once the SSS timeout given to the builder passes,
+/// independently of the frame timeout
+pub fn sss_timeout() {
+    let sss_clock = MockClock::new();
+    let frame_clock = MockClock::new();
+    let (cpu, sss, rad) = (
+        Pipe::new(INTF::CPU),
+        Pipe::new(INTF::SSS).stalling(&sss_clock),
+        Pipe::new(INTF::RAD),
+    );
+
+    let mut controller = builder(&cpu, &sss, &rad)
+        .frame_timeout(&frame_clock, Duration::from_millis(100))
+        .sss_timeout(&sss_clock, Duration::from_millis(250))
+        .build();
+
+    assert!(matches!(controller.read_sss(32), Err(Error::TimedOut)));
+    assert!(sss_clock.now() >= Instant::from_millis(250));
+    assert_eq!(frame_clock.now(), Instant::BOOT);
+}
+
+/// Data from the CPU which begins with the bytes that once marked a hello is sent and received as
+/// data, rather than taken for a hello; only content of the hello kind changes the peer's MTU
+pub fn hello_prefix() {
+    let (cpu, sss, rad) = (
+        Pipe::new(INTF::CPU),
+        Pipe::new(INTF::SSS),
+        Pipe::new(INTF::RAD),
+    );
+    let mut controller = builder(&cpu, &sss, &rad).build();
+    register(&mut controller, (&cpu, &sss, &rad));
+    let (mut buf, mut body) = ([0_u8; CAPACITY], [0_u8; CAPACITY]);
+    let data = b"\0MTU\x10\x00";
+
+    // sent to the peer as data
+    cpu.feed(frame(&mut buf, ID.into(), PEER.into(), data));
+    controller.poll();
+    let sent = content(&mut body, Kind::Data, data);
+    assert!(rad.wrote(frame(&mut buf, ID.into(), PEER.into(), sent)));
+
+    // received from the peer as data, and forwarded to the CPU as it is
+    rad.feed(frame(&mut buf, PEER.into(), ID.into(), sent));
+    controller.poll();
+    assert!(cpu.wrote(frame(&mut buf, PEER.into(), ID.into(), data)));
+
+    // the peer's MTU is unchanged, so a message larger than 16 bytes is still sent
+    rad.clear();
+    cpu.feed(frame(&mut buf, ID.into(), PEER.into(), &[0xA5; 32]));
+    controller.poll();
+    assert!(rad.written.get() > 32);
+    assert_eq!(controller.drops().get(Reason::PeerMtu), 0);
+
+    // whereas a hello is consumed, and the same message refused
+    cpu.clear();
+    rad.clear();
+    let hello = content(&mut body, Kind::Hello, b"\x10\x00");
+    rad.feed(frame(&mut buf, PEER.into(), ID.into(), hello));
+    controller.poll();
+    assert!(cpu.wrote(&[]));
+
+    cpu.feed(frame(&mut buf, ID.into(), PEER.into(), &[0xA5; 32]));
+    controller.poll();
+    assert!(rad.wrote(&[]));
+    assert_eq!(controller.drops().get(Reason::PeerMtu), 1);
+}
+
+/// Data from the CPU which begins with the bytes that once marked a handshake is sent and received
+/// as data, rather than taken for a handshake; only content of the handshake kind is handled as one
+pub fn handshake_prefix() {
+    let (cpu, sss, rad) = (
+        Pipe::new(INTF::CPU),
+        Pipe::new(INTF::SSS),
+        Pipe::new(INTF::RAD),
+    );
+    let mut controller = builder(&cpu, &sss, &rad).build();
+    register(&mut controller, (&cpu, &sss, &rad));
+    let (mut buf, mut body) = ([0_u8; CAPACITY], [0_u8; CAPACITY]);
+    let mut data = [0x5A_u8; 4 + 1 + 32];
+    data[..5].copy_from_slice(b"\0KEX\x00");
+
+    // sent to the peer as data
+    cpu.feed(frame(&mut buf, ID.into(), PEER.into(), &data));
+    controller.poll();
+    let sent = content(&mut body, Kind::Data, &data);
+    assert!(rad.wrote(frame(&mut buf, ID.into(), PEER.into(), sent)));
+
+    // received from the peer as data, and forwarded to the CPU as it is
+    rad.feed(frame(&mut buf, PEER.into(), ID.into(), sent));
+    controller.poll();
+    assert!(cpu.wrote(frame(&mut buf, PEER.into(), ID.into(), &data)));
+
+    // whereas a handshake is consumed, ignored as ephemeral keys are disabled
+    cpu.clear();
+    rad.clear();
+    let handshake = content(&mut body, Kind::Handshake, &data[4..]);
+    rad.feed(frame(&mut buf, PEER.into(), ID.into(), handshake));
+    controller.poll();
+    assert!(cpu.wrote(&[]));
+    assert!(rad.wrote(&[]));
+
+    // and dropped if broadcast
+    let handshake = content(&mut body, Kind::Handshake, &data[4..]);
+    rad.feed(frame(&mut buf, PEER.into(), Id::Broadcast, handshake));
+    controller.poll();
+    assert!(cpu.wrote(&[]));
+    assert_eq!(controller.drops().get(Reason::Malformed), 1);
+}
+
+/// Data from the CPU which begins with the bytes that once marked an update frame is sent and
+/// received as data; only an envelope from the CPU sends content of the update kind, and such
+/// content which the controller does not consume reaches the CPU in an envelope of its own
+pub fn update_prefix() {
+    let (cpu, sss, rad) = (
+        Pipe::new(INTF::CPU),
+        Pipe::new(INTF::SSS),
+        Pipe::new(INTF::RAD),
+    );
+    let mut controller = builder(&cpu, &sss, &rad).build();
+    register(&mut controller, (&cpu, &sss, &rad));
+    let (mut buf, mut body) = ([0_u8; CAPACITY], [0_u8; CAPACITY]);
+    let data = b"UPDT\x02";
+
+    // sent to the peer as data
+    cpu.feed(frame(&mut buf, ID.into(), PEER.into(), data));
+    controller.poll();
+    let sent = content(&mut body, Kind::Data, data);
+    assert!(rad.wrote(frame(&mut buf, ID.into(), PEER.into(), sent)));
+
+    // received from the peer as data, and forwarded to the CPU as it is
+    rad.feed(frame(&mut buf, PEER.into(), ID.into(), sent));
+    controller.poll();
+    assert!(cpu.wrote(frame(&mut buf, PEER.into(), ID.into(), data)));
+
+    // whereas an envelope is sent as content of its kind
+    cpu.clear();
+    rad.clear();
+    let mut envelope = [0_u8; CAPACITY];
+    let len = Envelope::write_header(&mut envelope, Kind::Update, PEER.into());
+    envelope[len] = 2;
+    cpu.feed(frame(&mut buf, ID.into(), ID.into(), &envelope[..=len]));
+    controller.poll();
+    let sent = content(&mut body, Kind::Update, &[2]);
+    assert!(rad.wrote(frame(&mut buf, ID.into(), PEER.into(), sent)));
+
+    // and, received with no update under way, handed to the CPU in an envelope
+    rad.feed(frame(&mut buf, PEER.into(), ID.into(), sent));
+    controller.poll();
+    assert!(cpu.wrote(frame(&mut buf, ID.into(), ID.into(), &envelope[..=len])));
+
+    // the CPU may not send the controllers' own kinds
+    cpu.clear();
+    rad.clear();
+    let len = Envelope::write_header(&mut envelope, Kind::Hello, PEER.into());
+    envelope[len..][..2].copy_from_slice(b"\x10\x00");
+    cpu.feed(frame(&mut buf, ID.into(), ID.into(), &envelope[..len + 2]));
+    controller.poll();
+    assert!(rad.wrote(&[]));
+}
+
+/// Data from the CPU which begins with the bytes that once marked a fragment is sent and received
+/// as data, rather than gathered; only content of the fragment kind is
+pub fn fragment_prefix() {
+    let (cpu, sss, rad) = (
+        Pipe::new(INTF::CPU),
+        Pipe::new(INTF::SSS),
+        Pipe::new(INTF::RAD),
+    );
+    let mut controller = builder(&cpu, &sss, &rad).build();
+    register(&mut controller, (&cpu, &sss, &rad));
+    let (mut buf, mut body) = ([0_u8; CAPACITY], [0_u8; CAPACITY]);
+    let data = b"\0FRG\x00\x00\x00\x00\x01\x00abc";
+
+    // sent to the peer as data
+    cpu.feed(frame(&mut buf, ID.into(), PEER.into(), data));
+    controller.poll();
+    let sent = content(&mut body, Kind::Data, data);
+    assert!(rad.wrote(frame(&mut buf, ID.into(), PEER.into(), sent)));
+
+    // received from the peer as data, and forwarded to the CPU as it is
+    rad.feed(frame(&mut buf, PEER.into(), ID.into(), sent));
+    controller.poll();
+    assert!(cpu.wrote(frame(&mut buf, PEER.into(), ID.into(), data)));
+
+    // whereas a fragment is dropped, as there is nowhere to reassemble it
+    cpu.clear();
+    let fragment = content(&mut body, Kind::Fragment, &data[4..]);
+    rad.feed(frame(&mut buf, PEER.into(), ID.into(), fragment));
+    controller.poll();
+    assert!(cpu.wrote(&[]));
+    assert_eq!(controller.drops().get(Reason::Oversize), 1);
+}
+
+/// Data from the CPU which begins with the bytes that once marked a message to a port is sent and
+/// received as data, rather than checked against the open ports; only content of the port kind is
+pub fn port_prefix() {
+    let (cpu, sss, rad) = (
+        Pipe::new(INTF::CPU),
+        Pipe::new(INTF::SSS),
+        Pipe::new(INTF::RAD),
+    );
+    let mut controller = builder(&cpu, &sss, &rad).build();
+    register(&mut controller, (&cpu, &sss, &rad));
+    let (mut buf, mut body) = ([0_u8; CAPACITY], [0_u8; CAPACITY]);
+    let data = b"\0PRT\x07hi";
+
+    // sent to the peer as data
+    cpu.feed(frame(&mut buf, ID.into(), PEER.into(), data));
+    controller.poll();
+    let sent = content(&mut body, Kind::Data, data);
+    assert!(rad.wrote(frame(&mut buf, ID.into(), PEER.into(), sent)));
+
+    // received from the peer as data, and forwarded to the CPU as it is, though port 7 is closed
+    rad.feed(frame(&mut buf, PEER.into(), ID.into(), sent));
+    controller.poll();
+    assert!(cpu.wrote(frame(&mut buf, PEER.into(), ID.into(), data)));
+
+    // whereas an envelope to port 7 of every SED is broadcast as content of the port kind
+    cpu.clear();
+    rad.clear();
+    let mut envelope = [0_u8; CAPACITY];
+    let len = Envelope::write_header(&mut envelope, Kind::Port, Id::Broadcast);
+    envelope[len..][..3].copy_from_slice(&data[4..]);
+    cpu.feed(frame(&mut buf, ID.into(), ID.into(), &envelope[..len + 3]));
+    controller.poll();
+    let sent = content(&mut body, Kind::Port, &data[4..]);
+    assert!(rad.wrote(frame(&mut buf, ID.into(), Id::Broadcast, sent)));
+
+    // and, received while port 7 is closed, dropped
+    rad.feed(frame(&mut buf, PEER.into(), Id::Broadcast, sent));
+    controller.poll();
+    assert!(cpu.wrote(&[]));
+    assert_eq!(controller.drops().get(Reason::ClosedPort), 1);
+}
+
+/// A (de)registration message between the controller under test and the SSS, of the given
+/// operation, sent to the SSS or from it
+fn sss_frame(buf: &mut [u8], op: SSSOp, to_sss: bool) -> &[u8] {
+    let msg = SSSMessage {
+        dev_id: ID.into(),
+        op,
+    }
+    .to_bytes();
+    if to_sss {
+        frame(buf, ID.into(), Id::SSS, &msg)
+    } else {
+        frame(buf, Id::SSS, ID.into(), &msg)
+    }
+}
+
+/// A registration while registered which the SSS refuses to deregister leaves the controller
+/// registered, and the CPU refused, without asking the SSS to register it afresh
+pub fn reregistration_refused() {
+    let (cpu, sss, rad) = (
+        Pipe::new(INTF::CPU),
+        Pipe::new(INTF::SSS),
+        Pipe::new(INTF::RAD),
+    );
+    let mut controller = builder(&cpu, &sss, &rad).build();
+    register(&mut controller, (&cpu, &sss, &rad));
+    sss.clear();
+    let (mut buf, mut expected) = ([0_u8; CAPACITY], [0_u8; CAPACITY]);
+
+    cpu.feed(sss_frame(&mut buf, SSSOp::Register, true));
+    sss.answer(sss_frame(&mut buf, SSSOp::Already, false));
+    controller.poll();
+
+    // the refusal of the SSS is withheld, and the CPU refused once in its stead
+    assert!(cpu.wrote(sss_frame(&mut expected, SSSOp::Already, false)));
+    assert!(sss.wrote(sss_frame(&mut expected, SSSOp::Deregister, true)));
+    assert!(controller.registered());
+}
+
+/// A registration while registered to which the SSS never answers the deregistration leaves the
+/// controller registered, and the CPU refused, without asking the SSS to register it afresh
+pub fn reregistration_timeout() {
+    let clock = MockClock::new();
+    let (cpu, sss, rad) = (
+        Pipe::new(INTF::CPU),
+        Pipe::new(INTF::SSS).stalling(&clock),
+        Pipe::new(INTF::RAD),
+    );
+    let mut controller = builder(&cpu, &sss, &rad)
+        .sss_timeout(&clock, Duration::from_millis(250))
+        .build();
+    register(&mut controller, (&cpu, &sss, &rad));
+    sss.clear();
+    let (mut buf, mut expected) = ([0_u8; CAPACITY], [0_u8; CAPACITY]);
+
+    cpu.feed(sss_frame(&mut buf, SSSOp::Register, true));
+    controller.poll();
+
+    assert!(clock.now() >= Instant::from_millis(250));
+    assert!(cpu.wrote(sss_frame(&mut expected, SSSOp::Already, false)));
+    assert!(sss.wrote(sss_frame(&mut expected, SSSOp::Deregister, true)));
+    assert!(controller.registered());
+}
+
+/// A registration while registered which the SSS deregisters registers the controller afresh,
+/// and the CPU is told only of the registration
+pub fn reregistration() {
+    let (cpu, sss, rad) = (
+        Pipe::new(INTF::CPU),
+        Pipe::new(INTF::SSS),
+        Pipe::new(INTF::RAD),
+    );
+    let mut controller = builder(&cpu, &sss, &rad).build();
+    register(&mut controller, (&cpu, &sss, &rad));
+    sss.clear();
+    let (mut buf, mut expected) = ([0_u8; CAPACITY], [0_u8; CAPACITY]);
+
+    cpu.feed(sss_frame(&mut buf, SSSOp::Register, true));
+    sss.answer(sss_frame(&mut buf, SSSOp::Deregister, false));
+    sss.feed(sss_frame(&mut buf, SSSOp::Register, false));
+    controller.poll();
+
+    assert!(cpu.wrote(sss_frame(&mut expected, SSSOp::Register, false)));
+    // the deregistration, then the registration afresh
+    let first = sss_frame(&mut expected, SSSOp::Deregister, true).len();
+    let second = sss_frame(&mut expected[first..], SSSOp::Register, true).len();
+    assert!(sss.wrote(&expected[..first + second]));
+    assert!(controller.registered());
+}
+
+/// A registration after deregistering which the SSS refuses leaves the controller deregistered,
+/// and the CPU is told of the refusal
+pub fn deregistration_then_refused() {
+    let (cpu, sss, rad) = (
+        Pipe::new(INTF::CPU),
+        Pipe::new(INTF::SSS),
+        Pipe::new(INTF::RAD),
+    );
+    let mut controller = builder(&cpu, &sss, &rad).build();
+    register(&mut controller, (&cpu, &sss, &rad));
+    let (mut buf, mut expected) = ([0_u8; CAPACITY], [0_u8; CAPACITY]);
+
+    cpu.feed(sss_frame(&mut buf, SSSOp::Deregister, true));
+    sss.answer(sss_frame(&mut buf, SSSOp::Deregister, false));
+    controller.poll();
+    assert!(cpu.wrote(sss_frame(&mut expected, SSSOp::Deregister, false)));
+    assert!(!controller.registered());
+
+    cpu.clear();
+    sss.clear();
+    cpu.feed(sss_frame(&mut buf, SSSOp::Register, true));
+    sss.answer(sss_frame(&mut buf, SSSOp::Already, false));
+    controller.poll();
+
+    // the controller had nothing to tear down, so the refusal of the SSS reaches the CPU as it is
+    assert!(cpu.wrote(sss_frame(&mut expected, SSSOp::Already, false)));
+    assert!(sss.wrote(sss_frame(&mut expected, SSSOp::Register, true)));
+    assert!(!controller.registered());
+}
+
+/// Asks the controller under test to register with the secure handlers, keeping the given record of
+/// epochs, and answers for the SSS with the deployment's keys of [`EPOCH`] and the given
+/// capabilities, wrapped under the given secret and followed by the given trailer (e.g. an
+/// allowlist); then checks that the CPU was answered with the given operation, and that the
+/// controller registered should it have been told so
+fn register_securely(
+    epochs: &'static Floor,
+    kek: &[u8; 64],
+    caps: u8,
+    trailer: &[u8],
+    answered: SSSOp,
+) {
+    let (cpu, sss, rad) = (
+        Pipe::new(INTF::CPU),
+        Pipe::new(INTF::SSS),
+        Pipe::new(INTF::RAD),
+    );
+    let mut controller = builder_with(&cpu, &sss, &rad, secure_auth(epochs)).build();
+    request_registration(ID.into(), (&cpu, &sss), kek, caps, trailer);
+
+    controller.poll();
+
+    let mut buf = [0_u8; CAPACITY];
+    let notify = SSSMessage {
+        dev_id: ID.into(),
+        op: answered,
+    }
+    .to_bytes();
+    assert!(cpu.wrote(frame(&mut buf, Id::SSS, ID.into(), &notify)));
+    assert_eq!(controller.registered(), answered == SSSOp::Register);
+}
+
+/// The secure authentication handler of a controller under test, keeping the given record of
+/// epochs
+pub(super) fn secure_auth(epochs: &'static Floor) -> secure::AuthHandler {
+    secure::AuthHandler::new(Masked::plain(&SECRET), SUITE, &ENTROPY).with_epochs(epochs)
+}
+
+/// Feeds the CPU of the controller of the given id a registration request, and answers for the SSS
+/// with the deployment's keys ([`AES_KEY`] and [`HMAC_KEY`]) of [`EPOCH`] and the given
+/// capabilities, wrapped under the given secret and followed by the given trailer
+pub(super) fn request_registration(
+    id: Id,
+    (cpu, sss): (&Pipe<'_>, &Pipe<'_>),
+    kek: &[u8; 64],
+    caps: u8,
+    trailer: &[u8],
+) {
+    let mut buf = [0_u8; CAPACITY];
+    let request = SSSMessage {
+        dev_id: id,
+        op: SSSOp::Register,
+    }
+    .to_bytes();
+    cpu.feed(frame(&mut buf, id, Id::SSS, &request));
+
+    let challenge = SecureSSSChallenge {
+        dev_id: id,
+        op: SSSOp::Challenge,
+        nonce: NONCE,
+    };
+    let secrets = SecureSSSSecrets {
+        aes_key: AES_KEY,
+        seed: [0x3C; 32],
+        hmac_key: HMAC_KEY,
+        epoch: EPOCH,
+        caps,
+        integrity: [0; 8],
+    };
+    let response = SecureSSSResponse {
+        dev_id: id,
+        op: SSSOp::Register,
+        secrets: Some(keywrap::wrap(kek, id, secrets)),
+    };
+    let mut body = [0_u8; CAPACITY];
+    let mut len = response.to_bytes(&mut body);
+    body[len..][..trailer.len()].copy_from_slice(trailer);
+    len += trailer.len();
+    let tag = challenge::tag(&SECRET, &NONCE, id, &body[..len]);
+    body[len..][..challenge::TAG].copy_from_slice(&tag);
+    len += challenge::TAG;
+    sss.answer(frame(&mut buf, Id::SSS, id, &challenge.to_bytes()));
+    sss.feed(frame(&mut buf, Id::SSS, id, &body[..len]));
+}
+
+/// The CPU is told that it registered once the response of the SSS passes every check
+pub fn secure_registration() {
+    register_securely(&CURRENT, &SECRET, 0, &[], SSSOp::Register);
+}
+
+/// Keys of an epoch older than any accepted before are refused, and so is the CPU, rather than
+/// being told that it registered
+pub fn secure_registration_rollback() {
+    register_securely(&SUPERSEDED, &SECRET, 0, &[], SSSOp::Already);
+}
+
+/// Keys which do not unwrap under the controller's secret are refused, and so is the CPU, rather
+/// than being told that it registered
+pub fn secure_registration_bad_wrap() {
+    register_securely(&CURRENT, &OTHER_SECRET, 0, &[], SSSOp::Already);
+}
+
+/// Capabilities which the firmware does not implement are refused, and so is the CPU, rather than
+/// being told that it registered
+pub fn secure_registration_unknown_caps() {
+    register_securely(&CURRENT, &SECRET, 0x80, &[], SSSOp::Already);
+}
+
+/// An allowlist is accepted should it fit, but one which is missing, or holds more SEDs than the
+/// controller keeps, is refused, and so is the CPU, rather than being told that it registered
+pub fn secure_registration_allowlist() {
+    let mut trailer = [0_u8; 1 + (ALLOWED + 1) * SecureSSSAllowlist::ID_SIZE];
+    let peers = |count| (0..count).map(|i| Id::Other(PEER + i));
+
+    let len = SecureSSSAllowlist::to_bytes(peers(1), &mut trailer);
+    register_securely(
+        &CURRENT,
+        &SECRET,
+        CAP_ALLOWLIST,
+        &trailer[..len],
+        SSSOp::Register,
+    );
+
+    register_securely(&CURRENT, &SECRET, CAP_ALLOWLIST, &[], SSSOp::Already);
+
+    let len =
+        SecureSSSAllowlist::to_bytes(peers(u16::try_from(ALLOWED + 1).unwrap()), &mut trailer);
+    register_securely(
+        &CURRENT,
+        &SECRET,
+        CAP_ALLOWLIST,
+        &trailer[..len],
+        SSSOp::Already,
+    );
+}
@@ -0,0 +1,993 @@
+//! On-target tests for the [trivial](scewl::trivial) and [secure](scewl::secure) crypto handlers
+
+#![allow(clippy::large_stack_arrays)] // each test owns a controller-sized buffer, as the controller does
+
+use scewl::codec::secure::{SecureSSSResponse, SecureSSSSecrets, CAP_PQ_KEM};
+use scewl::controller::{Id, Message, SSSOp, SCEWL_MAX_DATA_SZ};
+use scewl::crypto::Handler as CryptoHandler;
+use scewl::ct;
+use scewl::diag::Reason;
+use scewl::scratch::Pool;
+use scewl::secure;
+use scewl::secure::pq::{self, kem};
+use scewl::secure::{cmac, keywrap};
+use scewl::trivial;
+
+/// The id of the sending SED in these tests
+const SRC: Id = Id::Other(10);
+/// The id of the receiving SED in these tests
+const TGT: Id = Id::Other(11);
+/// The payload sent by these tests, which spans multiple AES blocks
+const PAYLOAD: &[u8] = b"the quick brown fox jumps over the lazy dog; 0123456789";
+
+/// An entropy source which always yields the same bytes, standing in for the firmware's
+struct Fixed(u8);
+
+impl secure::Entropy for Fixed {
+    fn fill(&self, buf: &mut [u8; 32]) {
+        buf.fill(self.0);
+    }
+}
+
+/// The entropy source of one boot in these tests
+static BOOT: Fixed = Fixed(7);
+/// The entropy source of the next boot in these tests
+static REBOOT: Fixed = Fixed(8);
+
+/// Instantiates a pair of secure crypto handlers which share keys, as after registration
+fn secure_pair() -> (secure::CryptoHandler, secure::CryptoHandler) {
+    (
+        secure::CryptoHandler::new([1; 32], [2; 16], [3; 64]),
+        secure::CryptoHandler::new([4; 32], [2; 16], [3; 64]),
+    )
+}
+
+/// Instantiates a pair of GCM crypto handlers which share a key, as after registration
+fn gcm_pair() -> (secure::GcmHandler, secure::GcmHandler) {
+    (
+        secure::GcmHandler::new([1; 32], [2; 16]),
+        secure::GcmHandler::new([4; 32], [2; 16]),
+    )
+}
+
+/// Instantiates a pair of GCM-SIV crypto handlers which share a key, as after registration
+fn siv_pair() -> (secure::SivHandler, secure::SivHandler) {
+    (
+        secure::SivHandler::new([1; 32], [2; 16]),
+        secure::SivHandler::new([4; 32], [2; 16]),
+    )
+}
+
+/// Instantiates a pair of signing handlers around secure crypto handlers, the receiver holding
+/// the public key of the sender should `known` be set
+fn signed_pair(
+    known: bool,
+) -> (
+    secure::SignedHandler<secure::CryptoHandler>,
+    secure::SignedHandler<secure::CryptoHandler>,
+) {
+    let (sender, receiver) = secure_pair();
+    let sender_keys = secure::SigningKeys::new([5; 32]);
+    let mut receiver_keys = secure::SigningKeys::new([6; 32]);
+    if known {
+        receiver_keys = receiver_keys.with_peer(SRC, sender_keys.public_key());
+    }
+
+    (
+        secure::SignedHandler::new(sender, Some(sender_keys)),
+        secure::SignedHandler::new(receiver, Some(receiver_keys)),
+    )
+}
+
+/// Encrypts the payload into the buffer with the sender, returning the message as it would be
+/// received from the radio
+fn send(
+    sender: &mut impl CryptoHandler,
+    data: &mut [u8; SCEWL_MAX_DATA_SZ],
+    scratch: &Pool,
+    tgt_id: Id,
+) -> Message {
+    data[sender.content_offset()..][..PAYLOAD.len()].copy_from_slice(PAYLOAD);
+    let mut msg = Message {
+        tgt_id,
+        src_id: SRC,
+        len: PAYLOAD.len(),
+    };
+    msg.len = sender.encrypt(data, msg, scratch);
+    msg
+}
+
+/// Verifies and decrypts the message with the receiver, as the controller would
+fn recv(
+    receiver: &mut impl CryptoHandler,
+    data: &mut [u8; SCEWL_MAX_DATA_SZ],
+    scratch: &Pool,
+    msg: Message,
+) -> Result<usize, Reason> {
+    receiver.verify(data, msg, scratch)?;
+    receiver.decrypt(data, msg, scratch)
+}
+
+/// The trivial handler passes messages through untouched
+pub fn trivial_round_trip() {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let mut handler = trivial::CryptoHandler;
+    assert_eq!(handler.verification_len(), 0);
+    assert_eq!(handler.content_offset(), 0);
+
+    let msg = send(&mut handler, &mut data, &scratch, TGT);
+    assert_eq!(msg.len, PAYLOAD.len());
+    assert_eq!(
+        recv(&mut handler, &mut data, &scratch, msg),
+        Ok(PAYLOAD.len())
+    );
+    assert_eq!(&data[..PAYLOAD.len()], PAYLOAD);
+}
+
+/// Direct messages decrypt to the original payload, and are not sent in cleartext
+pub fn direct_round_trip() {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let (mut sender, mut receiver) = secure_pair();
+
+    let msg = send(&mut sender, &mut data, &scratch, TGT);
+    assert_eq!((msg.len - sender.verification_len()) % 16, 0);
+    assert!(!data[..msg.len]
+        .windows(PAYLOAD.len())
+        .any(|window| window == PAYLOAD));
+
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, msg),
+        Ok(PAYLOAD.len())
+    );
+    assert_eq!(&data[receiver.content_offset()..][..PAYLOAD.len()], PAYLOAD);
+}
+
+/// Broadcasts decrypt to the original payload
+pub fn broadcast_round_trip() {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let (mut sender, mut receiver) = secure_pair();
+
+    let msg = send(&mut sender, &mut data, &scratch, Id::Broadcast);
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, msg),
+        Ok(PAYLOAD.len())
+    );
+    assert_eq!(&data[receiver.content_offset()..][..PAYLOAD.len()], PAYLOAD);
+}
+
+/// Broadcasts from the receiver to the sender of a pair made by `pair`, interleaved with the
+/// sender's, which neither moves the counters the other keeps of its broadcasts
+fn counted_apart<H: CryptoHandler>(pair: fn() -> (H, H)) {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let mut first = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let (mut sender, mut receiver) = pair();
+
+    let replayed = send(&mut sender, &mut first, &scratch, Id::Broadcast);
+    let pristine = first;
+    assert_eq!(
+        recv(&mut receiver, &mut first, &scratch, replayed),
+        Ok(PAYLOAD.len())
+    );
+
+    for _ in 0..3 {
+        data[receiver.content_offset()..][..PAYLOAD.len()].copy_from_slice(PAYLOAD);
+        let mut msg = Message {
+            tgt_id: Id::Broadcast,
+            src_id: TGT,
+            len: PAYLOAD.len(),
+        };
+        msg.len = receiver.encrypt(&mut data, msg, &scratch);
+        assert_eq!(
+            recv(&mut sender, &mut data, &scratch, msg),
+            Ok(PAYLOAD.len())
+        );
+    }
+
+    let msg = send(&mut sender, &mut data, &scratch, Id::Broadcast);
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, msg),
+        Ok(PAYLOAD.len())
+    );
+    assert_eq!(
+        receiver.verify(&pristine, replayed, &scratch),
+        Err(Reason::Replay)
+    );
+}
+
+/// The counters of the broadcasts sent are kept apart from those of the broadcasts received, for
+/// each handler
+pub fn broadcast_counters() {
+    counted_apart(secure_pair);
+    counted_apart(gcm_pair);
+    counted_apart(siv_pair);
+}
+
+/// Frames of a sender given buckets fill the smallest bucket which their ciphertext fits, yet
+/// decrypt to the original content with a receiver given none; content too long for every bucket
+/// is only padded to the AES block
+pub fn buckets() {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let (sender, mut receiver) = secure_pair();
+    let mut sender = sender.with_buckets(&[64, 512]);
+
+    // the content header takes 4 bytes, and the PKCS7 padding at least 1
+    for (len, padded) in [
+        (0, 64),
+        (10, 64),
+        (59, 64),
+        (60, 512),
+        (507, 512),
+        (600, 608),
+    ] {
+        data[sender.content_offset()..][..len].fill(0xa5);
+        let mut msg = Message {
+            tgt_id: TGT,
+            src_id: SRC,
+            len,
+        };
+        msg.len = sender.encrypt(&mut data, msg, &scratch);
+        assert_eq!(msg.len, sender.verification_len() + padded);
+
+        assert_eq!(recv(&mut receiver, &mut data, &scratch, msg), Ok(len));
+        assert!(data[receiver.content_offset()..][..len]
+            .iter()
+            .all(|&byte| byte == 0xa5));
+    }
+}
+
+/// Encrypts messages of several lengths to the target with the sender, each of which must be as
+/// long as the sender predicted before encrypting it
+fn seals_as_predicted(sender: &mut impl CryptoHandler, tgt_id: Id) {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    for len in [0, 1, 11, 12, 59, 60, 600] {
+        let msg = Message {
+            tgt_id,
+            src_id: SRC,
+            len,
+        };
+        let sealed = sender.sealed_len(msg, data.len());
+        assert_eq!(sender.encrypt(&mut data, msg, &scratch), sealed);
+    }
+}
+
+/// Every handler predicts the length of the frames it encrypts, as the controller checks them
+/// against the MTU of their target before encrypting
+pub fn sealed_len() {
+    seals_as_predicted(&mut trivial::CryptoHandler, TGT);
+    for tgt_id in [TGT, Id::Broadcast] {
+        seals_as_predicted(&mut secure_pair().0, tgt_id);
+        seals_as_predicted(&mut secure_pair().0.with_buckets(&[64, 512]), tgt_id);
+        seals_as_predicted(&mut gcm_pair().0, tgt_id);
+        seals_as_predicted(&mut siv_pair().0, tgt_id);
+        seals_as_predicted(&mut signed_pair(true).0, tgt_id);
+    }
+}
+
+/// Messages whose transport header was modified in transit fail HMAC verification
+pub fn tampered_header() {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let (mut sender, mut receiver) = secure_pair();
+
+    let msg = send(&mut sender, &mut data, &scratch, TGT);
+    let forged = Message {
+        src_id: Id::Other(12),
+        ..msg
+    };
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, forged),
+        Err(Reason::BadMac)
+    );
+}
+
+/// Messages whose encrypted content was modified in transit fail HMAC verification, as the HMAC
+/// covers the ciphertext, and are left undecrypted
+pub fn tampered_content() {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let (mut sender, mut receiver) = secure_pair();
+
+    let msg = send(&mut sender, &mut data, &scratch, TGT);
+    data[msg.len - 1] ^= 0x01;
+    let tampered = data;
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, msg),
+        Err(Reason::BadMac)
+    );
+    assert!(data[..msg.len] == tampered[..msg.len]);
+}
+
+/// Refuses, as a bad length, a frame which overruns the buffer lent to the handler, and one too
+/// short to hold its verification
+fn refuses_bad_lengths<H: CryptoHandler>((mut sender, mut receiver): (H, H)) {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+
+    let msg = send(&mut sender, &mut data, &scratch, TGT);
+    let truncated = msg.len - 1;
+    assert_eq!(
+        receiver.verify(&data[..truncated], msg, &scratch),
+        Err(Reason::BadLength)
+    );
+    assert_eq!(
+        receiver.decrypt(&mut data[..truncated], msg, &scratch),
+        Err(Reason::BadLength)
+    );
+
+    let short = Message { len: 4, ..msg };
+    assert_eq!(
+        receiver.verify(&data, short, &scratch),
+        Err(Reason::BadLength)
+    );
+}
+
+/// Frames whose length does not fit the buffer or the handler's layout are refused as such
+pub fn bad_length() {
+    refuses_bad_lengths(secure_pair());
+    refuses_bad_lengths(gcm_pair());
+    refuses_bad_lengths(siv_pair());
+}
+
+/// The constant-time padding check accepts exactly the padding PKCS#7 generates
+pub fn padding() {
+    let mut block = [0xa5_u8; 32];
+    assert!(!ct::pkcs7(&[]));
+
+    for pad in 1..=16_u8 {
+        block[32 - usize::from(pad)..].fill(pad);
+        assert!(ct::pkcs7(&block));
+        assert!(ct::pkcs7(&block[16..]));
+
+        // any byte of the padding which is off invalidates it
+        block[31] ^= 0x80;
+        assert!(!ct::pkcs7(&block));
+        block[31] ^= 0x80;
+        block[32 - usize::from(pad)] ^= 0x01;
+        assert!(!ct::pkcs7(&block));
+        block.fill(0xa5);
+    }
+
+    // the padding is of 1 to 16 bytes, and never longer than the data
+    block[31] = 0;
+    assert!(!ct::pkcs7(&block));
+    block[15..].fill(17);
+    assert!(!ct::pkcs7(&block));
+    assert!(!ct::pkcs7(&[2]));
+}
+
+/// Messages delivered out of order are accepted once each, while those bearing a counter already
+/// received, or one too far below the highest received, are rejected
+pub fn stale_counter() {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let mut oldest = [0_u8; SCEWL_MAX_DATA_SZ];
+    let mut late = [0_u8; SCEWL_MAX_DATA_SZ];
+    let (mut sender, mut receiver) = secure_pair();
+
+    let first = send(&mut sender, &mut oldest, &scratch, TGT);
+    let second = send(&mut sender, &mut late, &scratch, TGT);
+    let pristine = late;
+    // the last of these bears the counter WINDOW + 1, of which the first is just too far below
+    let newest = (2..=secure::WINDOW)
+        .map(|_| send(&mut sender, &mut data, &scratch, TGT))
+        .last()
+        .expect("nothing sent");
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, newest),
+        Ok(PAYLOAD.len())
+    );
+
+    assert_eq!(
+        recv(&mut receiver, &mut late, &scratch, second),
+        Ok(PAYLOAD.len())
+    );
+    assert_eq!(
+        receiver.verify(&pristine, second, &scratch),
+        Err(Reason::Replay)
+    );
+    assert_eq!(
+        receiver.verify(&oldest, first, &scratch),
+        Err(Reason::Replay)
+    );
+}
+
+/// The trivial handler does not jitter its failures, while the secure handler's delays are
+/// bounded and vary from one failure to the next
+pub fn jitter() {
+    assert_eq!(trivial::CryptoHandler.jitter(), 0);
+
+    let (mut handler, _) = secure_pair();
+    let first = handler.jitter();
+    let mut varied = false;
+    for _ in 0..16 {
+        let delay = handler.jitter();
+        assert!(delay < secure::JITTER);
+        varied |= delay != first;
+    }
+    assert!(varied);
+}
+
+/// Frames decrypted block by block as they arrive, as the controller streams them, decrypt to the
+/// original payload, whatever the size of the reads which deliver them
+pub fn streamed_round_trip() {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let (mut sender, mut receiver) = secure_pair();
+    assert_ne!(receiver.stream_block(), 0);
+
+    for tgt_id in [TGT, Id::Broadcast] {
+        let msg = send(&mut sender, &mut data, &scratch, tgt_id);
+        receiver.verify(&data, msg, &scratch).unwrap();
+
+        // the last read is short, as the controller's would be for a frame of any length
+        let mut arrived = receiver.verification_len();
+        while arrived < msg.len {
+            arrived = (arrived + receiver.stream_block() + 5).min(msg.len);
+            receiver.decrypt_received(&mut data, arrived);
+        }
+
+        assert_eq!(
+            receiver.decrypt(&mut data, msg, &scratch),
+            Ok(PAYLOAD.len())
+        );
+        assert_eq!(&data[receiver.content_offset()..][..PAYLOAD.len()], PAYLOAD);
+    }
+}
+
+/// Messages between more pairs of SEDs than have their session keys kept still decrypt, their
+/// keys being derived anew once evicted, and a message relabelled with another pair fails its HMAC
+pub fn session_keys() {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let (mut sender, mut receiver) = secure_pair();
+
+    // each from another source, as the receiver's counters are kept by source
+    for _ in 0..2 {
+        for id in 20..20 + 2 * secure::SESSIONS {
+            data[sender.content_offset()..][..PAYLOAD.len()].copy_from_slice(PAYLOAD);
+            #[allow(clippy::cast_possible_truncation)] // a handful of ids
+            let mut msg = Message {
+                tgt_id: TGT,
+                src_id: Id::Other(id as u16),
+                len: PAYLOAD.len(),
+            };
+            msg.len = sender.encrypt(&mut data, msg, &scratch);
+            assert_eq!(
+                recv(&mut receiver, &mut data, &scratch, msg),
+                Ok(PAYLOAD.len())
+            );
+        }
+    }
+
+    // a fresh receiver, as the counters of the first are not kept per pair
+    let (_, mut receiver) = secure_pair();
+    let msg = send(&mut sender, &mut data, &scratch, TGT);
+    let relabelled = Message {
+        tgt_id: Id::Other(20),
+        ..msg
+    };
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, relabelled),
+        Err(Reason::BadMac)
+    );
+}
+
+/// Direct messages and broadcasts of an AEAD handler decrypt to the original payload, are not sent
+/// in cleartext, and carry neither padding nor a content header
+fn aead_round_trip<H: CryptoHandler>(pair: fn() -> (H, H)) {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let (mut sender, mut receiver) = pair();
+    assert_eq!(receiver.content_offset(), receiver.verification_len());
+
+    for tgt_id in [TGT, Id::Broadcast] {
+        let msg = send(&mut sender, &mut data, &scratch, tgt_id);
+        assert_eq!(msg.len, sender.verification_len() + PAYLOAD.len());
+        assert!(!data[..msg.len]
+            .windows(PAYLOAD.len())
+            .any(|window| window == PAYLOAD));
+
+        assert_eq!(
+            recv(&mut receiver, &mut data, &scratch, msg),
+            Ok(PAYLOAD.len())
+        );
+        assert_eq!(&data[receiver.content_offset()..][..PAYLOAD.len()], PAYLOAD);
+    }
+}
+
+/// Frames of an AEAD handler whose header, counter, or content was modified in transit fail their
+/// tag, and a frame which failed its tag does not advance the sender's counter
+fn aead_tampered<H: CryptoHandler>(pair: fn() -> (H, H)) {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let (mut sender, mut receiver) = pair();
+
+    let msg = send(&mut sender, &mut data, &scratch, TGT);
+    let forged = Message {
+        src_id: Id::Other(12),
+        ..msg
+    };
+    let pristine = data;
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, forged),
+        Err(Reason::BadMac)
+    );
+
+    // the second byte of the counter, which follows the 12-byte nonce, raises it were it authentic
+    for offset in [13, msg.len - 1] {
+        let mut tampered = pristine;
+        tampered[offset] ^= 0x01;
+        assert_eq!(
+            recv(&mut receiver, &mut tampered, &scratch, msg),
+            Err(Reason::BadMac)
+        );
+    }
+
+    data = pristine;
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, msg),
+        Ok(PAYLOAD.len())
+    );
+}
+
+/// GCM direct messages and broadcasts decrypt to the original payload, are not sent in cleartext,
+/// and carry neither padding nor a content header
+pub fn gcm_round_trip() {
+    aead_round_trip(gcm_pair);
+}
+
+/// GCM frames which were modified in transit fail their tag, without advancing the counter
+pub fn gcm_tampered() {
+    aead_tampered(gcm_pair);
+}
+
+/// GCM-SIV frames round-trip as GCM frames do
+pub fn siv_round_trip() {
+    aead_round_trip(siv_pair);
+}
+
+/// GCM-SIV frames which were modified in transit fail their tag, without advancing the counter
+pub fn siv_tampered() {
+    aead_tampered(siv_pair);
+}
+
+/// Encrypts the payload, then its complement, with two handlers given the same seed, as though
+/// the seed had been reused, returning whether the XOR of the two contents is that of the payloads
+fn reveals_xor<H: CryptoHandler>(handler: fn() -> H) -> bool {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let mut first = [0_u8; 128];
+    let scratch = Pool::new();
+
+    let msg = send(&mut handler(), &mut data, &scratch, TGT);
+    first[..msg.len].copy_from_slice(&data[..msg.len]);
+
+    let mut sender = handler();
+    let offset = sender.content_offset();
+    for (byte, plain) in data[offset..][..PAYLOAD.len()].iter_mut().zip(PAYLOAD) {
+        *byte = !plain;
+    }
+    let len = sender.encrypt(
+        &mut data,
+        Message {
+            len: PAYLOAD.len(),
+            ..msg
+        },
+        &scratch,
+    );
+    assert_eq!(len, msg.len);
+    // the nonce leads the verification segment
+    assert_eq!(data[..12], first[..12]);
+
+    data[offset..len]
+        .iter()
+        .zip(&first[offset..len])
+        .all(|(second, first)| first ^ second == 0xFF)
+}
+
+/// A nonce repeated under GCM reveals the XOR of the contents which share it, but not under
+/// GCM-SIV, whose keystream also depends on the content
+pub fn siv_repeated_nonce() {
+    assert!(reveals_xor(|| gcm_pair().0));
+    assert!(!reveals_xor(|| siv_pair().0));
+}
+
+/// Signed broadcasts bear a signature after the frame of the inner handler and decrypt to the
+/// original payload, while direct messages are not signed
+pub fn signed_round_trip() {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let (mut sender, mut receiver) = signed_pair(true);
+    let (mut unsigned, _) = secure_pair();
+    let unsigned_len = send(&mut unsigned, &mut data, &scratch, TGT).len;
+
+    for (tgt_id, len) in [
+        (Id::Broadcast, unsigned_len + secure::SIGNATURE),
+        (TGT, unsigned_len),
+    ] {
+        let msg = send(&mut sender, &mut data, &scratch, tgt_id);
+        assert_eq!(msg.len, len);
+        assert_eq!(
+            recv(&mut receiver, &mut data, &scratch, msg),
+            Ok(PAYLOAD.len())
+        );
+        assert_eq!(&data[receiver.content_offset()..][..PAYLOAD.len()], PAYLOAD);
+    }
+}
+
+/// Broadcasts whose signature was modified in transit, or whose source has no public key, fail
+/// their signature
+pub fn signed_forged() {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let (mut sender, mut receiver) = signed_pair(true);
+
+    let msg = send(&mut sender, &mut data, &scratch, Id::Broadcast);
+    let pristine = data;
+    data[msg.len - 1] ^= 0x01;
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, msg),
+        Err(Reason::BadSignature)
+    );
+
+    let (_, mut stranger) = signed_pair(false);
+    data = pristine;
+    assert_eq!(
+        recv(&mut stranger, &mut data, &scratch, msg),
+        Err(Reason::BadSignature)
+    );
+
+    data = pristine;
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, msg),
+        Ok(PAYLOAD.len())
+    );
+}
+
+/// Completes a handshake between the handshakes of the sender and the receiver, returning the
+/// secret agreed by each
+fn agree() -> ([u8; 32], [u8; 32]) {
+    let mut initiator = secure::Handshakes::new(SRC, &[7; 32]);
+    let mut responder = secure::Handshakes::new(TGT, &[8; 32]);
+
+    let offer = initiator.offer(TGT).expect("no offer made");
+    assert!(initiator.offer(TGT).is_none());
+    let mut body = [0_u8; secure::Handshake::size()];
+    offer.to_bytes(&mut body);
+    assert_eq!(secure::Handshake::from_bytes(&body), Some(offer));
+
+    let accepted = responder.receive(SRC, offer);
+    let reply = accepted.reply.expect("offer not accepted");
+    assert_eq!(reply.kind, secure::HandshakeKind::Accept);
+    let completed = initiator.receive(TGT, reply);
+    assert!(completed.reply.is_none());
+
+    let (Some(ours), Some(theirs)) = (completed.secret, accepted.secret) else {
+        panic!("no secret agreed");
+    };
+    (*ours, *theirs)
+}
+
+/// Rekeys a pair of handlers made by `pair` with an agreed secret, after which a third holding
+/// only the deployment's keys cannot verify their direct messages, and the receiver accepts frames
+/// under the deployment's keys only until it has received one under the secret
+fn rekeyed<H: CryptoHandler>(pair: fn() -> (H, H)) {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let (mut sender, mut receiver) = pair();
+    let (mut stranger, mut eavesdropper) = pair();
+
+    let early = send(&mut sender, &mut data, &scratch, TGT);
+    let mut early_data = data;
+
+    let (ours, theirs) = agree();
+    assert_eq!(ours, theirs);
+    sender.rekey(TGT, Some(&ours));
+    receiver.rekey(SRC, Some(&theirs));
+
+    let msg = send(&mut sender, &mut data, &scratch, TGT);
+    let mut copy = data;
+    assert_eq!(
+        recv(&mut eavesdropper, &mut copy, &scratch, msg),
+        Err(Reason::BadMac)
+    );
+
+    assert_eq!(
+        recv(&mut receiver, &mut early_data, &scratch, early),
+        Ok(PAYLOAD.len())
+    );
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, msg),
+        Ok(PAYLOAD.len())
+    );
+    assert_eq!(&data[receiver.content_offset()..][..PAYLOAD.len()], PAYLOAD);
+
+    // past the receiver's counter, so that only the key fails
+    let stale = (0..3)
+        .map(|_| send(&mut stranger, &mut data, &scratch, TGT))
+        .last()
+        .expect("nothing sent");
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, stale),
+        Err(Reason::BadMac)
+    );
+}
+
+/// Direct messages between SEDs which agreed a secret in a handshake are keyed by it, for each
+/// handler
+pub fn handshake() {
+    rekeyed(secure_pair);
+    rekeyed(gcm_pair);
+    rekeyed(siv_pair);
+}
+
+/// Rotates a pair of handlers made by `pair` to the keys of later epochs, after which the receiver
+/// still accepts frames under the epoch before its own, but not under any older, and refuses keys
+/// of an epoch no newer than its own
+fn rotated<H: CryptoHandler>(pair: fn() -> (H, H)) {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let (mut sender, mut receiver) = pair();
+    let (mut stale, _) = pair();
+
+    assert!(!receiver.rotate(0, &[9; 16], &[9; 64]));
+    assert!(receiver.rotate(1, &[9; 16], &[9; 64]));
+
+    let msg = send(&mut sender, &mut data, &scratch, TGT);
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, msg),
+        Ok(PAYLOAD.len())
+    );
+
+    assert!(sender.rotate(1, &[9; 16], &[9; 64]));
+    let msg = send(&mut sender, &mut data, &scratch, TGT);
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, msg),
+        Ok(PAYLOAD.len())
+    );
+    assert_eq!(&data[receiver.content_offset()..][..PAYLOAD.len()], PAYLOAD);
+
+    assert!(sender.rotate(2, &[10; 16], &[10; 64]));
+    assert!(receiver.rotate(2, &[10; 16], &[10; 64]));
+    assert!(!receiver.rotate(1, &[9; 16], &[9; 64]));
+
+    // past the receiver's counter, so that only the epoch fails
+    let old = (0..3)
+        .map(|_| send(&mut stale, &mut data, &scratch, TGT))
+        .last()
+        .expect("nothing sent");
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, old),
+        Err(Reason::NoKey)
+    );
+
+    let msg = send(&mut sender, &mut data, &scratch, TGT);
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, msg),
+        Ok(PAYLOAD.len())
+    );
+}
+
+/// Handlers take up the keys of a new epoch pushed by the SSS, while still accepting frames under
+/// the keys they held before, for each handler
+pub fn rotation() {
+    rotated(secure_pair);
+    rotated(gcm_pair);
+    rotated(siv_pair);
+}
+
+/// Reseeds the sender of a pair of handlers made by `pair` with `with_entropy`, after which it
+/// draws other IVs or nonces than it did without entropy, or with other entropy, yet its frames
+/// are still accepted by the receiver, through several reseedings
+fn reseeded<H: CryptoHandler>(
+    pair: fn() -> (H, H),
+    with_entropy: fn(H, &'static dyn secure::Entropy) -> H,
+) {
+    let mut data = [0_u8; SCEWL_MAX_DATA_SZ];
+    let scratch = Pool::new();
+    let (mut unseeded, _) = pair();
+    let (sender, mut receiver) = pair();
+    let mut sender = with_entropy(sender, &BOOT);
+    let (rebooted, _) = pair();
+    let mut rebooted = with_entropy(rebooted, &REBOOT);
+
+    let msg = send(&mut unseeded, &mut data, &scratch, TGT);
+    let unseeded = data;
+    send(&mut rebooted, &mut data, &scratch, TGT);
+    let rebooted = data;
+    send(&mut sender, &mut data, &scratch, TGT);
+    assert_ne!(data[..msg.len], unseeded[..msg.len]);
+    assert_ne!(data[..msg.len], rebooted[..msg.len]);
+    assert_eq!(
+        recv(&mut receiver, &mut data, &scratch, msg),
+        Ok(PAYLOAD.len())
+    );
+
+    for _ in 0..2 * secure::RESEED_INTERVAL {
+        let msg = send(&mut sender, &mut data, &scratch, TGT);
+        assert_eq!(
+            recv(&mut receiver, &mut data, &scratch, msg),
+            Ok(PAYLOAD.len())
+        );
+    }
+    assert_eq!(&data[receiver.content_offset()..][..PAYLOAD.len()], PAYLOAD);
+}
+
+/// Handlers given an entropy source draw unpredictable IVs and nonces, yet interoperate with those
+/// given none, for each handler
+pub fn reseed() {
+    reseeded(secure_pair, secure::CryptoHandler::with_entropy);
+    reseeded(gcm_pair, secure::GcmHandler::with_entropy);
+    reseeded(siv_pair, secure::SivHandler::with_entropy);
+}
+
+/// An ML-KEM-512 keypair agrees a secret with an encapsulation to it, implicitly rejects a
+/// tampered ciphertext, and opens the response sealed to it, but no other
+pub fn pq_kem() {
+    let mut ek = [0_u8; kem::ENCAPSULATION_KEY];
+    let pair = pq::KeyPair::generate(&[5; 64], &[6; 32], &mut ek);
+    assert!(kem::well_formed(&ek));
+
+    let resp = SecureSSSResponse {
+        dev_id: SRC,
+        op: SSSOp::Register,
+        secrets: Some(SecureSSSSecrets {
+            aes_key: [2; 16],
+            seed: [1; 32],
+            hmac_key: [3; 64],
+            epoch: 1,
+            caps: CAP_PQ_KEM,
+            integrity: [4; 8],
+        }),
+    };
+    let mut buf = [0_u8; SecureSSSResponse::size() + pq::SEALING];
+    let len = resp.to_bytes(&mut buf);
+    let sealed = pq::seal(&ek, &[7; 32], SRC, &mut buf, len).unwrap();
+    assert_eq!(sealed, buf.len());
+    assert!(!buf.windows(16).any(|window| window == [2; 16]));
+
+    let mut tampered = buf;
+    tampered[SecureSSSResponse::size()] ^= 1;
+    assert_eq!(pair.open(SRC, &mut tampered, sealed), None);
+    assert_eq!(pair.open(TGT, &mut buf.clone(), sealed), None);
+
+    assert_eq!(pair.open(SRC, &mut buf, sealed), Some(len));
+    let secrets = SecureSSSResponse::from_bytes(&buf[..len])
+        .and_then(|resp| resp.secrets)
+        .unwrap();
+    assert_eq!(secrets.aes_key, [2; 16]);
+    assert_eq!(secrets.seed, [1; 32]);
+    assert_eq!(secrets.hmac_key, [3; 64]);
+    assert!(buf[len..].iter().all(|&byte| byte == 0));
+}
+
+/// The key of the AES-CMAC test vectors of RFC 4493
+const CMAC_KEY: u128 = 0x2B7E_1516_28AE_D2A6_ABF7_1588_09CF_4F3C;
+
+/// The message of the AES-CMAC test vectors of RFC 4493, of which each takes a prefix
+const CMAC_MESSAGE: [u8; 64] = [
+    0x6B, 0xC1, 0xBE, 0xE2, 0x2E, 0x40, 0x9F, 0x96, 0xE9, 0x3D, 0x7E, 0x11, 0x73, 0x93, 0x17, 0x2A,
+    0xAE, 0x2D, 0x8A, 0x57, 0x1E, 0x03, 0xAC, 0x9C, 0x9E, 0xB7, 0x6F, 0xAC, 0x45, 0xAF, 0x8E, 0x51,
+    0x30, 0xC8, 0x1C, 0x46, 0xA3, 0x5C, 0xE4, 0x11, 0xE5, 0xFB, 0xC1, 0x19, 0x1A, 0x0A, 0x52, 0xEF,
+    0xF6, 0x9F, 0x24, 0x45, 0xDF, 0x4F, 0x9B, 0x17, 0xAD, 0x2B, 0x41, 0x7B, 0xE6, 0x6C, 0x37, 0x10,
+];
+
+/// The tags of the AES-CMAC test vectors of RFC 4493, by the length of the prefix of the message
+const CMAC_TAGS: [(usize, u128); 4] = [
+    (0, 0xBB1D_6929_E959_3728_7FA3_7D12_9B75_6746),
+    (16, 0x070A_16B4_6B4D_4144_F79B_DD9D_D04A_287C),
+    (40, 0xDFA6_6747_DE9A_E630_30CA_3261_1497_C827),
+    (64, 0x51F0_BEBF_7E3B_9D92_FC49_7417_7936_3CFE),
+];
+
+/// AES-CMAC matches the test vectors of RFC 4493, however its input is split
+pub fn cmac_vectors() {
+    let key = CMAC_KEY.to_be_bytes();
+    for (len, tag) in CMAC_TAGS {
+        let tag = tag.to_be_bytes();
+        let mut cmac = cmac::Cmac::new(&key);
+        cmac.update(&CMAC_MESSAGE[..len]);
+        assert_eq!(cmac.finalize(), tag);
+
+        for split in [1, 15, 16, 17] {
+            let mut cmac = cmac::Cmac::new(&key);
+            for chunk in CMAC_MESSAGE[..len].chunks(split) {
+                cmac.update(chunk);
+            }
+            assert_eq!(cmac.finalize(), tag);
+        }
+    }
+}
+
+/// The key material derived from the key of RFC 4493 by the CMAC-based KDF, by its label, its
+/// context, and its length, as computed by OpenSSL's KBKDF and Python's `cryptography`
+const KDF_OUTPUTS: [(&[u8], &[u8], &[u8]); 2] = [
+    (
+        b"SCEWL cmac",
+        b"",
+        &[
+            0x65, 0x36, 0xC7, 0x4D, 0x3F, 0xF6, 0xA1, 0x9F, 0x66, 0x15, 0x91, 0x85, 0xE9, 0x73,
+            0x84, 0xFF,
+        ],
+    ),
+    (
+        b"label",
+        b"context",
+        &[
+            0xA3, 0x00, 0xFC, 0xB7, 0x65, 0xB3, 0x9D, 0x16, 0xA5, 0xB4, 0xB1, 0xE3, 0x2F, 0x81,
+            0x27, 0x65, 0xBB, 0xD0, 0xEB, 0xC9, 0xF4, 0x03, 0x62, 0x7D, 0xEC, 0x52, 0x87, 0x9D,
+            0x8B, 0xD3, 0xD7, 0x34, 0x6C, 0x88, 0xD2, 0x4A, 0xC7, 0xF0, 0xAB, 0x30,
+        ],
+    ),
+];
+
+/// The CMAC-based KDF of NIST SP 800-108 matches OpenSSL's, including for an output which ends
+/// part way through a block
+pub fn cmac_kdf_vectors() {
+    let key = CMAC_KEY.to_be_bytes();
+    for (label, context, expected) in KDF_OUTPUTS {
+        let mut out = [0_u8; 64];
+        let out = &mut out[..expected.len()];
+        cmac::derive(&key, label, context, out);
+        assert_eq!(out, expected);
+    }
+}
+
+/// The key encryption key of the AES-KW test vector of RFC 3394 (4.1)
+const KW_KEK: u128 = 0x0001_0203_0405_0607_0809_0A0B_0C0D_0E0F;
+
+/// The key data of the AES-KW test vector of RFC 3394 (4.1)
+const KW_DATA: u128 = 0x0011_2233_4455_6677_8899_AABB_CCDD_EEFF;
+
+/// The wrapped key data of the AES-KW test vector of RFC 3394 (4.1), after its integrity check
+/// value of `0x1FA6_8B0A_8112_B447`
+const KW_WRAPPED: u128 = 0xAEF3_4BD8_FB5A_7B82_9D3E_8623_71D2_CFE5;
+
+/// AES-KW matches the test vector of RFC 3394, and unwraps only what it wrapped
+pub fn key_wrap_vectors() {
+    let kek = KW_KEK.to_be_bytes();
+    let mut data = KW_DATA.to_be_bytes();
+    let integrity = keywrap::wrap_in_place(&kek, &mut data);
+    assert_eq!(integrity, 0x1FA6_8B0A_8112_B447_u64.to_be_bytes());
+    assert_eq!(data, KW_WRAPPED.to_be_bytes());
+
+    let mut tampered = data;
+    tampered[15] ^= 1;
+    assert!(!keywrap::unwrap_in_place(&kek, &integrity, &mut tampered));
+
+    assert!(keywrap::unwrap_in_place(&kek, &integrity, &mut data));
+    assert_eq!(data, KW_DATA.to_be_bytes());
+}
+
+/// The secrets of a registration response wrapped for an SED are unwrapped by it alone
+pub fn key_wrap_secrets() {
+    let plain = SecureSSSSecrets {
+        aes_key: [2; 16],
+        seed: [1; 32],
+        hmac_key: [3; 64],
+        epoch: 1,
+        caps: 0,
+        integrity: [0; 8],
+    };
+    let wrapped = keywrap::wrap(&[5; 64], SRC, plain);
+    assert_ne!(wrapped.aes_key, plain.aes_key);
+    assert_ne!(wrapped.seed, plain.seed);
+    assert_ne!(wrapped.hmac_key, plain.hmac_key);
+
+    assert!(keywrap::unwrap(&[6; 64], SRC, wrapped).is_none());
+    assert!(keywrap::unwrap(&[5; 64], TGT, wrapped).is_none());
+    let mut tampered = wrapped;
+    tampered.integrity[0] ^= 1;
+    assert!(keywrap::unwrap(&[5; 64], SRC, tampered).is_none());
+
+    let secrets = keywrap::unwrap(&[5; 64], SRC, wrapped).unwrap();
+    assert_eq!(secrets.aes_key, plain.aes_key);
+    assert_eq!(secrets.seed, plain.seed);
+    assert_eq!(secrets.hmac_key, plain.hmac_key);
+    assert_eq!(secrets.epoch, plain.epoch);
+}
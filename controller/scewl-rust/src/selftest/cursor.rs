@@ -0,0 +1,75 @@
+//! On-target tests for the [cursors](scewl::cursor)
+
+use core::mem::size_of;
+
+use scewl::codec::secure::ContentHeader;
+use scewl::cursor::{ReadCursor, WriteCursor};
+
+/// Every value written by the write cursor can be read back by the read cursor
+pub fn round_trip() {
+    let mut buf = [0_u8; 32];
+    WriteCursor::new(&mut buf)
+        .write_u16(0xBEEF)
+        .write_i16(-2)
+        .write_u32(0xDEAD_BEEF)
+        .write_u64(0x0123_4567_89AB_CDEF)
+        .write(b"SC");
+
+    let mut cur = ReadCursor::new(&buf);
+    assert_eq!(cur.read_u16(), 0xBEEF);
+    assert_eq!(cur.read_i16(), -2);
+    assert_eq!(cur.read_u32(), 0xDEAD_BEEF);
+    assert_eq!(cur.read_u64(), 0x0123_4567_89AB_CDEF);
+    assert_eq!(&cur.read_literal::<2>(), b"SC");
+}
+
+/// Multi-byte values are laid out little-endian, whatever the byte order of the host
+pub fn little_endian() {
+    let mut buf = [0_u8; 16];
+    WriteCursor::new(&mut buf)
+        .write_u16(0x0102)
+        .write_i16(-2)
+        .write_u32(0x0304_0506)
+        .write_u64(0x0708_090A_0B0C_0D0E);
+    assert_eq!(
+        buf,
+        [2, 1, 0xFE, 0xFF, 6, 5, 4, 3, 0xE, 0xD, 0xC, 0xB, 0xA, 9, 8, 7]
+    );
+
+    let mut cur = ReadCursor::new(&buf);
+    assert_eq!(cur.read_u16(), 0x0102);
+    cur.advance(2);
+    assert_eq!(cur.read_u32(), 0x0304_0506);
+}
+
+/// Multi-byte values at odd offsets are read and written without faulting
+pub fn unaligned() {
+    let mut buf = [0_u8; 17];
+    WriteCursor::new(&mut buf)
+        .advance(1)
+        .write_u64(u64::MAX - 1)
+        .write_u32(7);
+
+    let mut cur = ReadCursor::new(&buf);
+    cur.advance(1);
+    assert_eq!(cur.read_u64(), u64::MAX - 1);
+    assert_eq!(cur.read_u32(), 7);
+}
+
+/// Copies are truncated to the shorter of the two buffers
+pub fn copy_to() {
+    let src = [1_u8, 2, 3, 4];
+    let mut dst = [0_u8; 2];
+    assert_eq!(ReadCursor::new(&src).copy_to(&mut dst), 2);
+    assert_eq!(dst, [1, 2]);
+
+    let mut dst = [0_u8; 6];
+    assert_eq!(ReadCursor::new(&src).copy_to(&mut dst), 4);
+    assert_eq!(dst, [1, 2, 3, 4, 0, 0]);
+}
+
+/// The widths assumed by the wire format hold on the target
+pub fn target_widths() {
+    assert_eq!(size_of::<usize>(), 4);
+    assert_eq!(ContentHeader::size(), 4);
+}
@@ -0,0 +1,177 @@
+//! A minimal on-target test runner, which executes the unit tests for the cursors, the frame codec,
+//! the controller, the crypto handlers, the scratch pool, and the clock on the actual
+//! thumbv7m target under QEMU
+//!
+//! The host can only test the hardware-free portions of this crate, and only with the host's
+//! alignment rules and pointer width; running on the target catches issues which only manifest on
+//! the lm3s6965 (e.g. a `usize` that is 4 bytes wide). As `#[test]` requires `std`, this runner
+//! instead replaces the controller entrypoint when the `selftest` feature is enabled:
+//!
+//! ```text
+//! cargo run --release --features selftest
+//! ```
+//!
+//! which uses the QEMU runner configured in `.cargo/config`. Each test reports its result over
+//! semihosting; a failing assertion panics, which (via `panic-semihosting`'s `exit` feature) ends
+//! the QEMU session with a failure status. Should every test pass, QEMU exits successfully.
+//!
+//! With the `soak` feature, the [soak test](soak) additionally runs after the unit tests. Likewise,
+//! with the `bench` feature, the [throughput benchmark](bench) runs after the unit tests.
+
+use cortex_m::asm;
+use cortex_m_semihosting::debug::{self, EXIT_SUCCESS};
+
+use scewl::info;
+
+use crate::entry;
+
+#[cfg(feature = "bench")]
+mod bench;
+mod codec;
+mod controller;
+mod crypto;
+mod cursor;
+mod scratch;
+#[cfg(feature = "soak")]
+mod soak;
+mod time;
+
+/// A named test case
+type Test = (&'static str, fn());
+
+/// Every test run by the on-target runner, in order of execution
+const TESTS: &[Test] = &[
+    ("cursor::round_trip", cursor::round_trip),
+    ("cursor::unaligned", cursor::unaligned),
+    ("cursor::little_endian", cursor::little_endian),
+    ("cursor::copy_to", cursor::copy_to),
+    ("cursor::target_widths", cursor::target_widths),
+    ("codec::id", codec::id),
+    ("codec::header", codec::header),
+    ("codec::header_crc", codec::header_crc),
+    ("codec::legacy_mark", codec::legacy_mark),
+    ("codec::sss_message", codec::sss_message),
+    ("codec::secure_sss", codec::secure_sss),
+    ("codec::sss_push", codec::sss_push),
+    ("codec::sss_keepalive", codec::sss_keepalive),
+    ("codec::sss_erasure", codec::sss_erasure),
+    ("codec::verification_segment", codec::verification_segment),
+    ("codec::drops", codec::drops),
+    ("codec::integrity", codec::integrity),
+    ("codec::stats", codec::stats),
+    ("codec::notice", codec::notice),
+    ("codec::set_level", codec::set_level),
+    ("codec::banner", codec::banner),
+    ("codec::version", codec::version),
+    ("codec::provision", codec::provision),
+    ("codec::update", codec::update),
+    ("controller::announce", controller::announce),
+    ("controller::read", controller::read),
+    ("controller::frame_timeout", controller::frame_timeout),
+    ("controller::sss_timeout", controller::sss_timeout),
+    ("controller::hello_prefix", controller::hello_prefix),
+    ("controller::handshake_prefix", controller::handshake_prefix),
+    ("controller::update_prefix", controller::update_prefix),
+    ("controller::fragment_prefix", controller::fragment_prefix),
+    ("controller::port_prefix", controller::port_prefix),
+    (
+        "controller::reregistration_refused",
+        controller::reregistration_refused,
+    ),
+    (
+        "controller::reregistration_timeout",
+        controller::reregistration_timeout,
+    ),
+    ("controller::reregistration", controller::reregistration),
+    (
+        "controller::deregistration_then_refused",
+        controller::deregistration_then_refused,
+    ),
+    (
+        "controller::secure_registration",
+        controller::secure_registration,
+    ),
+    (
+        "controller::secure_registration_rollback",
+        controller::secure_registration_rollback,
+    ),
+    (
+        "controller::secure_registration_bad_wrap",
+        controller::secure_registration_bad_wrap,
+    ),
+    (
+        "controller::secure_registration_unknown_caps",
+        controller::secure_registration_unknown_caps,
+    ),
+    (
+        "controller::secure_registration_allowlist",
+        controller::secure_registration_allowlist,
+    ),
+    ("crypto::trivial_round_trip", crypto::trivial_round_trip),
+    ("crypto::direct_round_trip", crypto::direct_round_trip),
+    ("crypto::broadcast_round_trip", crypto::broadcast_round_trip),
+    ("crypto::broadcast_counters", crypto::broadcast_counters),
+    ("crypto::tampered_header", crypto::tampered_header),
+    ("crypto::tampered_content", crypto::tampered_content),
+    ("crypto::padding", crypto::padding),
+    ("crypto::buckets", crypto::buckets),
+    ("crypto::sealed_len", crypto::sealed_len),
+    ("crypto::bad_length", crypto::bad_length),
+    ("crypto::stale_counter", crypto::stale_counter),
+    ("crypto::streamed_round_trip", crypto::streamed_round_trip),
+    ("crypto::jitter", crypto::jitter),
+    ("crypto::session_keys", crypto::session_keys),
+    ("crypto::gcm_round_trip", crypto::gcm_round_trip),
+    ("crypto::gcm_tampered", crypto::gcm_tampered),
+    ("crypto::siv_round_trip", crypto::siv_round_trip),
+    ("crypto::siv_tampered", crypto::siv_tampered),
+    ("crypto::siv_repeated_nonce", crypto::siv_repeated_nonce),
+    ("crypto::signed_round_trip", crypto::signed_round_trip),
+    ("crypto::signed_forged", crypto::signed_forged),
+    ("crypto::handshake", crypto::handshake),
+    ("crypto::rotation", crypto::rotation),
+    ("crypto::reseed", crypto::reseed),
+    ("crypto::pq_kem", crypto::pq_kem),
+    ("crypto::cmac_vectors", crypto::cmac_vectors),
+    ("crypto::cmac_kdf_vectors", crypto::cmac_kdf_vectors),
+    ("crypto::key_wrap_vectors", crypto::key_wrap_vectors),
+    ("crypto::key_wrap_secrets", crypto::key_wrap_secrets),
+    ("scratch::exhaustion", scratch::exhaustion),
+    ("scratch::zeroed_on_release", scratch::zeroed_on_release),
+    ("time::instant_arithmetic", time::instant_arithmetic),
+    ("time::mock_clock", time::mock_clock),
+    ("time::heartbeat", time::heartbeat),
+];
+
+/// Entrypoint for the on-target test runner, used in place of the controller when the `selftest`
+/// feature is enabled
+#[entry]
+fn main() -> ! {
+    scewl::level::set_sink(crate::log);
+    run()
+}
+
+/// Runs every test in [`TESTS`](TESTS), then exits QEMU with the overall result
+pub fn run() -> ! {
+    info!("running {} tests", TESTS.len());
+
+    for (name, test) in TESTS {
+        info!("test {} ...", name);
+        test();
+        info!("test {} ... ok", name);
+    }
+
+    info!("test result: ok. {} passed", TESTS.len());
+
+    #[cfg(feature = "soak")]
+    soak::run();
+
+    #[cfg(feature = "bench")]
+    bench::run();
+    debug::exit(EXIT_SUCCESS);
+
+    // only reached when not running under QEMU
+    loop {
+        asm::wfi();
+    }
+}
@@ -0,0 +1,36 @@
+//! On-target tests for the [scratch pool](scewl::scratch)
+
+use scewl::scratch::{Pool, COUNT, SIZE};
+
+/// Every buffer may be taken at once, after which the pool is exhausted until one is returned
+pub fn exhaustion() {
+    let pool = Pool::new();
+
+    let mut taken: [_; COUNT] = core::array::from_fn(|_| pool.take().unwrap());
+    assert_eq!(pool.free(), 0);
+    assert!(pool.take().is_none());
+
+    // the buffers are distinct
+    for (buf, fill) in taken.iter_mut().zip(1_u8..) {
+        buf.fill(fill);
+    }
+    for (buf, fill) in taken.iter().zip(1_u8..) {
+        assert!(buf.iter().all(|&b| b == fill));
+    }
+
+    drop(taken);
+    assert_eq!(pool.free(), COUNT);
+}
+
+/// A buffer is zeroed when it is returned, so that the next borrower never sees its contents
+pub fn zeroed_on_release() {
+    let pool = Pool::new();
+
+    let mut buf = pool.take().unwrap();
+    buf.fill(0xA5);
+    drop(buf);
+
+    let buf = pool.take().unwrap();
+    assert_eq!(buf.len(), SIZE);
+    assert!(buf.iter().all(|&b| b == 0));
+}
@@ -0,0 +1,337 @@
+//! A long-running soak test, which pushes millions of messages between two controllers
+//!
+//! Two controllers, `alice` and `bob`, are built with the secure handlers over the in-memory
+//! [pipes](Pipe) of the controller tests, and registered by answering for the SSS as those tests
+//! do; whatever either puts on the radio is passed to the other:
+//!
+//!  - `alice`'s CPU sends direct messages to `bob`, broadcasts to him, and sends to a rotating set
+//!    of peers so that her outbound counter map is filled to capacity
+//!  - `bob`'s CPU replies to `alice`, and he receives from every simulated peer, so that his
+//!    inbound counter map is filled to capacity
+//!  - `carol`, a bare crypto handler holding the deployment's keys, impersonates every other peer,
+//!    sending to `bob`
+//!
+//! After every message, the counter carried by the frame is checked against the counter tracked
+//! independently by the test, and the message checked to reach the CPU of its receiver, so that any
+//! drift between peers is caught at the message where it first appears.
+//!
+//! The unused stack is painted before the run, and every checkpoint its high-water mark, i.e. the
+//! lowest word no longer painted, is checked to be exactly that of the first checkpoint, by when
+//! every kind of message has been sent and received; as nothing in the controller allocates, this
+//! shows that memory use is flat. The scratch buffers are likewise checked to have been returned.
+//!
+//! Run it with `cargo run --release --features soak`, without `bench`, whose buffers would leave
+//! too little RAM for the second controller's. Any counter map overflow panics (and thus ends the
+//! QEMU session with a failure status).
+
+use core::ptr::{addr_of, addr_of_mut};
+
+use cortex_m::register::msp;
+
+use scewl::codec::secure::VerificationSegment;
+use scewl::content::Kind;
+use scewl::controller::{
+    Controller, Id, Message, MessageHeader, SCEWL_MAX_DATA_SZ, SCEWL_MAX_TX_SZ,
+};
+use scewl::crypto::Handler as CryptoHandler;
+use scewl::scratch::{Pool, COUNT};
+use scewl::secure;
+use scewl::transport::INTF;
+use scewl::{deployment, info};
+
+use super::controller::{self as harness, frame, Pipe, AES_KEY, CURRENT, EPOCH, HMAC_KEY, SECRET};
+
+/// The total number of messages pushed between the controllers
+const MESSAGES: u32 = 2_000_000;
+/// The number of messages between each progress report and memory check
+const CHECKPOINT: u32 = 100_000;
+/// The number of simulated peers besides `alice` and `bob`; together with the counterpart, this
+/// fills each counter map to the capacity configured for the deployment
+const PEERS: u16 = deployment::PEER_CAPACITY - 1;
+/// The first id used by the simulated peers
+const FIRST_PEER: u16 = 0x100;
+/// The id of `alice`
+const ALICE: Id = Id::Other(10);
+/// The id of `bob`
+const BOB: Id = Id::Other(11);
+/// The payload of every message; kept small so millions of messages finish in reasonable time
+const PAYLOAD: &[u8] = b"soak test payload";
+/// The largest frame which the test composes
+const FRAME: usize = 256;
+/// The word with which the unused stack is painted
+const PAINT: u32 = 0x5CE5_50AC;
+/// The bytes below the stack pointer which are left unpainted, as [`paint`] itself uses them
+const GUARD: usize = 256;
+
+/// The data buffer of `bob`; `alice` is built over those of the controller tests
+static mut DATA: [u8; SCEWL_MAX_DATA_SZ] = [0_u8; SCEWL_MAX_DATA_SZ];
+
+/// The transmit buffer of `bob`
+static mut TX: [u8; SCEWL_MAX_TX_SZ] = [0_u8; SCEWL_MAX_TX_SZ];
+
+extern "C" {
+    /// The end of the statics, down to which the stack may grow (see `cortex-m-rt`'s `link.x`)
+    static mut __sheap: u32;
+    /// The top of the stack, from which it grows down
+    static _stack_start: u32;
+}
+
+/// A controller of the soak test
+type Sed<'p> = Controller<'p, secure::AuthHandler, secure::Registered, &'p Pipe<'p>>;
+
+/// The lines of a controller of the soak test
+struct Lines<'p> {
+    /// The line to its CPU
+    cpu: Pipe<'p>,
+    /// The line to the SSS
+    sss: Pipe<'p>,
+    /// The line to the radio
+    rad: Pipe<'p>,
+}
+
+impl Lines<'_> {
+    /// Lines on which nothing has been sent
+    fn new() -> Self {
+        Self {
+            cpu: Pipe::new(INTF::CPU),
+            sss: Pipe::new(INTF::SSS),
+            rad: Pipe::new(INTF::RAD),
+        }
+    }
+}
+
+/// The counters expected for every (sender, receiver) pair, tracked independently of the handlers
+struct Expected {
+    /// Direct messages from `alice` to `bob`
+    alice_bob: u64,
+    /// Direct messages from `bob` to `alice`
+    bob_alice: u64,
+    /// Broadcasts from `alice`
+    alice_brdcst: u64,
+    /// Direct messages from `carol` (impersonating any peer) to `bob`; the counter is kept by
+    /// `carol` per target, so it is shared across every peer she impersonates
+    carol_bob: u64,
+    /// Direct messages from `alice` to each simulated peer
+    alice_peer: [u64; PEERS as usize],
+}
+
+/// Paints the stack from the end of the statics up to [`GUARD`] bytes below the stack pointer, so
+/// that its [high-water mark](high_water) may later be found
+fn paint() {
+    let mut word = addr_of_mut!(__sheap);
+    let top = (msp::read() as usize - GUARD) as *mut u32;
+    while word < top {
+        // SAFETY: the words between the statics and the stack pointer are not in use
+        unsafe {
+            word.write_volatile(PAINT);
+            word = word.add(1);
+        }
+    }
+}
+
+/// The deepest the stack has reached since it was [painted](paint), in bytes below its top
+fn high_water() -> usize {
+    let (mut word, top) = (addr_of!(__sheap), addr_of!(_stack_start));
+    // SAFETY: the words between the statics and the top of the stack are all in RAM
+    while word < top && unsafe { word.read_volatile() } == PAINT {
+        // SAFETY: as above
+        word = unsafe { word.add(1) };
+    }
+    top as usize - word as usize
+}
+
+/// Polls the controller for as long as it has work waiting
+fn settle(sed: &mut Sed<'_>) {
+    sed.poll();
+    while sed.pending() {
+        sed.poll();
+    }
+}
+
+/// Registers the controller by answering for the SSS, then forgets what it wrote to its CPU
+fn register(sed: &mut Sed<'_>, lines: &Lines<'_>) {
+    harness::request_registration(sed.id(), (&lines.cpu, &lines.sss), &SECRET, 0, &[]);
+    settle(sed);
+    assert!(sed.registered(), "{:?} could not register", sed.id());
+    lines.cpu.clear();
+}
+
+/// Checks the counter of the frame which was last put on the radio, whose body is given
+fn check_counter(body: &[u8], src_id: Id, tgt_id: Id, expected: &mut u64) {
+    *expected += 1;
+    let ctr = VerificationSegment::from_bytes(body).ctr;
+    assert_eq!(ctr, *expected, "counter drift: {src_id:?} -> {tgt_id:?}");
+}
+
+/// Has the CPU of the sender send the payload to `tgt_id`, checks the counter on the resulting
+/// frame, then passes it to the receiver, if one is present, and checks that it reaches its CPU
+fn send(
+    (sender, from): (&mut Sed<'_>, &Lines<'_>),
+    receiver: Option<(&mut Sed<'_>, &Lines<'_>)>,
+    tgt_id: Id,
+    expected: &mut u64,
+) {
+    let src_id = sender.id();
+    let mut buf = [0_u8; FRAME];
+    from.cpu.feed(frame(&mut buf, src_id, tgt_id, PAYLOAD));
+    settle(sender);
+    check_counter(
+        &from.rad.written()[MessageHeader::size()..],
+        src_id,
+        tgt_id,
+        expected,
+    );
+
+    match receiver {
+        Some((receiver, to)) => {
+            from.rad.pass(&to.rad);
+            settle(receiver);
+            let delivered = to.cpu.wrote(frame(&mut buf, src_id, tgt_id, PAYLOAD));
+            assert!(delivered, "not delivered: {:?} -> {:?}", src_id, tgt_id);
+            to.cpu.clear();
+        }
+        None => from.rad.clear(),
+    }
+}
+
+/// Has `carol` send the payload to the receiver as the given peer, checks the counter on the
+/// resulting frame, then passes it to the receiver and checks that it reaches its CPU
+fn impersonate(
+    carol: &mut secure::CryptoHandler,
+    scratch: &Pool,
+    (receiver, to): (&mut Sed<'_>, &Lines<'_>),
+    src_id: Id,
+    expected: &mut u64,
+) {
+    let tgt_id = receiver.id();
+    let mut data = [0_u8; FRAME];
+    let offset = carol.content_offset();
+    data[offset] = Kind::Data.into();
+    data[offset + Kind::SIZE..][..PAYLOAD.len()].copy_from_slice(PAYLOAD);
+    let mut msg = Message {
+        tgt_id,
+        src_id,
+        len: Kind::SIZE + PAYLOAD.len(),
+    };
+    msg.len = carol.encrypt(&mut data, msg, scratch);
+    check_counter(&data, src_id, tgt_id, expected);
+
+    let mut buf = [0_u8; FRAME];
+    to.rad
+        .feed(frame(&mut buf, src_id, tgt_id, &data[..msg.len]));
+    settle(receiver);
+    let delivered = to.cpu.wrote(frame(&mut buf, src_id, tgt_id, PAYLOAD));
+    assert!(delivered, "not delivered: {:?} -> {:?}", src_id, tgt_id);
+    to.cpu.clear();
+}
+
+/// Runs the soak test to completion, panicking on the first failure
+pub fn run() {
+    info!(
+        "soak: {} messages, stack pointer {:#x}",
+        MESSAGES,
+        msp::read()
+    );
+    paint();
+
+    // SAFETY: the soak test runs to completion on a single thread of execution after every other
+    // test, and is the only user of these buffers
+    let (alice_buffers, bob_buffers) = unsafe {
+        let alice = harness::buffers();
+        let bob = (&mut *addr_of_mut!(DATA), &mut *addr_of_mut!(TX), alice.2);
+        (alice, bob)
+    };
+    let scratch = alice_buffers.2;
+
+    let (alice_lines, bob_lines) = (Lines::new(), Lines::new());
+    let mut alice = harness::builder_over(
+        ALICE,
+        (&alice_lines.cpu, &alice_lines.sss, &alice_lines.rad),
+        alice_buffers,
+        harness::secure_auth(&CURRENT),
+    )
+    .build();
+    let mut bob = harness::builder_over(
+        BOB,
+        (&bob_lines.cpu, &bob_lines.sss, &bob_lines.rad),
+        bob_buffers,
+        harness::secure_auth(&CURRENT),
+    )
+    .build();
+    let mut carol = secure::CryptoHandler::new([5; 32], AES_KEY, HMAC_KEY).with_epoch(EPOCH);
+
+    // each announces its MTU on registering, to which the other answers with its own
+    register(&mut alice, &alice_lines);
+    register(&mut bob, &bob_lines);
+    while !alice_lines.rad.wrote(&[]) || !bob_lines.rad.wrote(&[]) {
+        alice_lines.rad.pass(&bob_lines.rad);
+        bob_lines.rad.pass(&alice_lines.rad);
+        settle(&mut alice);
+        settle(&mut bob);
+    }
+
+    // the hellos took the first counter of each pair between alice and bob
+    let mut expected = Expected {
+        alice_bob: 1,
+        bob_alice: 1,
+        alice_brdcst: 1,
+        carol_bob: 0,
+        alice_peer: [0; PEERS as usize],
+    };
+    let mut mark = None;
+
+    for i in 0..MESSAGES {
+        #[allow(clippy::cast_possible_truncation)] // the modulus is within the range of a u16
+        let peer = (i / 5 % u32::from(PEERS)) as u16;
+
+        match i % 5 {
+            0 => send(
+                (&mut alice, &alice_lines),
+                Some((&mut bob, &bob_lines)),
+                BOB,
+                &mut expected.alice_bob,
+            ),
+            1 => send(
+                (&mut bob, &bob_lines),
+                Some((&mut alice, &alice_lines)),
+                ALICE,
+                &mut expected.bob_alice,
+            ),
+            2 => send(
+                (&mut alice, &alice_lines),
+                Some((&mut bob, &bob_lines)),
+                Id::Broadcast,
+                &mut expected.alice_brdcst,
+            ),
+            3 => impersonate(
+                &mut carol,
+                scratch,
+                (&mut bob, &bob_lines),
+                Id::Other(FIRST_PEER + peer),
+                &mut expected.carol_bob,
+            ),
+            _ => send(
+                (&mut alice, &alice_lines),
+                None,
+                Id::Other(FIRST_PEER + peer),
+                &mut expected.alice_peer[usize::from(peer)],
+            ),
+        }
+
+        if (i + 1) % CHECKPOINT == 0 {
+            let depth = high_water();
+            assert_eq!(
+                depth,
+                *mark.get_or_insert(depth),
+                "stack usage grew during the soak test"
+            );
+            assert_eq!(
+                scratch.free(),
+                COUNT,
+                "a scratch buffer leaked during the soak test"
+            );
+            assert_eq!(alice.drops().total() + bob.drops().total(), 0);
+            info!("soak: {} messages ok, {} bytes of stack", i + 1, depth);
+        }
+    }
+}
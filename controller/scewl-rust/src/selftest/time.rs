@@ -0,0 +1,76 @@
+//! On-target tests for the [clock](scewl::time)
+
+use core::time::Duration;
+
+use scewl::codec::Id;
+use scewl::heartbeat::{Beat, Heartbeat};
+use scewl::time::{Clock, Instant, MockClock};
+
+/// Instants advance by whole milliseconds, saturate rather than overflow, and never measure
+/// negative durations
+pub fn instant_arithmetic() {
+    let start = Instant::from_millis(1_000);
+    let later = start + Duration::from_millis(1_500);
+
+    assert_eq!(later.as_millis(), 2_500);
+    assert_eq!(
+        later.saturating_duration_since(start),
+        Duration::from_millis(1_500)
+    );
+    assert_eq!(
+        start.saturating_duration_since(later),
+        Duration::from_millis(0)
+    );
+    assert_eq!(
+        Instant::from_millis(u64::MAX) + Duration::from_secs(1),
+        Instant::from_millis(u64::MAX)
+    );
+
+    assert!(!start.has_elapsed(Duration::from_millis(1_501), later));
+    assert!(start.has_elapsed(Duration::from_millis(1_500), later));
+}
+
+/// The mock clock only moves when told to, including while borrowed by the code under test
+pub fn mock_clock() {
+    let clock = MockClock::new();
+    let borrowed: &dyn Clock = &&clock;
+    assert_eq!(borrowed.now(), Instant::BOOT);
+
+    clock.advance_millis(250);
+    clock.advance(Duration::from_secs(2));
+    assert_eq!(borrowed.now(), Instant::from_millis(2_250));
+
+    clock.set(Instant::from_millis(10_000));
+    assert_eq!(clock.now(), Instant::from_millis(10_000));
+}
+
+/// Heartbeats fall due once per period, coalescing any which were missed, and round-trip through
+/// their serialised form
+pub fn heartbeat() {
+    let clock = MockClock::new();
+    let mut heartbeat = Heartbeat::new(Id::FAA, Duration::from_secs(10), &clock);
+    assert_eq!(heartbeat.target(), Id::FAA);
+
+    clock.advance_millis(9_999);
+    assert_eq!(heartbeat.poll(), None);
+    clock.advance_millis(1);
+    assert_eq!(heartbeat.poll(), Some(Duration::from_secs(10)));
+    assert_eq!(heartbeat.poll(), None);
+
+    clock.advance(Duration::from_secs(35));
+    assert_eq!(heartbeat.poll(), Some(Duration::from_secs(45)));
+    assert_eq!(heartbeat.poll(), None);
+    clock.advance(Duration::from_secs(10));
+    assert_eq!(heartbeat.poll(), Some(Duration::from_secs(55)));
+
+    let beat = Beat {
+        uptime: 55,
+        registered: true,
+        dropped: 3,
+    };
+    let mut buf = [0_u8; 16];
+    let len = beat.to_bytes(&mut buf);
+    assert_eq!(len, Beat::size());
+    assert_eq!(Beat::from_bytes(&buf[..len]), Some(beat));
+    assert_eq!(Beat::from_bytes(&buf[..len - 1]), None);
+}
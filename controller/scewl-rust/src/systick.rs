@@ -0,0 +1,81 @@
+//! A [clock](Clock) driven by SysTick, which counts milliseconds since it was started
+//!
+//! SysTick is configured to fire its exception once per millisecond, each of which must call
+//! [`tick`]. As a library may not define exception handlers for the binary which links it, the
+//! firmware entrypoint does so:
+//!
+//! ```text
+//! #[exception]
+//! fn SysTick() {
+//!     systick::tick();
+//! }
+//! ```
+//!
+//! With the `rtic` feature, the count is a [resource](crate::tasks::Shared) of the SysTick task,
+//! locked by the crypto task which reads the clock without masking the UARTs.
+
+#[cfg(not(feature = "rtic"))]
+use core::cell::Cell;
+
+#[cfg(not(feature = "rtic"))]
+use cortex_m::interrupt::{self, Mutex};
+use cortex_m::peripheral::syst::SystClkSource;
+use cortex_m::peripheral::SYST;
+#[cfg(feature = "rtic")]
+use rtic_core::Mutex as _;
+
+use scewl::time::{Clock, Instant};
+
+#[cfg(feature = "rtic")]
+use crate::tasks::{self, Shared};
+
+/// The frequency of the core clock, which is the lm3s6965's 12 MHz oscillator out of reset
+pub const CORE_CLOCK_HZ: u32 = 12_000_000;
+
+/// The milliseconds elapsed since SysTick was started
+#[cfg(not(feature = "rtic"))]
+static MILLIS: Mutex<Cell<u64>> = Mutex::new(Cell::new(0));
+
+/// The milliseconds elapsed since SysTick was started
+#[cfg(feature = "rtic")]
+static MILLIS: Shared<u64> = Shared::new(tasks::RX, 0);
+
+/// Counts a millisecond; to be called from the SysTick exception handler only
+pub fn tick() {
+    #[cfg(not(feature = "rtic"))]
+    interrupt::free(|cs| {
+        let millis = MILLIS.borrow(cs);
+        millis.set(millis.get() + 1);
+    });
+    #[cfg(feature = "rtic")]
+    (&MILLIS).lock(|millis| *millis += 1);
+}
+
+/// A clock driven by SysTick, which owns the peripheral while it is running
+pub struct SysTickClock {
+    /// The SysTick peripheral, held so that nothing else may reconfigure it
+    _syst: SYST,
+}
+
+impl SysTickClock {
+    /// Starts SysTick at one exception per millisecond, and with it this clock
+    pub fn start(mut syst: SYST) -> Self {
+        syst.set_clock_source(SystClkSource::Core);
+        syst.set_reload(CORE_CLOCK_HZ / 1000 - 1);
+        syst.clear_current();
+        syst.enable_interrupt();
+        syst.enable_counter();
+
+        Self { _syst: syst }
+    }
+}
+
+impl Clock for SysTickClock {
+    fn now(&self) -> Instant {
+        #[cfg(not(feature = "rtic"))]
+        let millis = interrupt::free(|cs| MILLIS.borrow(cs).get());
+        #[cfg(feature = "rtic")]
+        let millis = (&MILLIS).lock(|millis| *millis);
+        Instant::from_millis(millis)
+    }
+}
@@ -0,0 +1,179 @@
+//! The task architecture of the firmware with the `rtic` feature, after that of the RTIC framework
+//!
+//! Rather than a single run loop which polls each UART in turn, the firmware is split into tasks of
+//! fixed priority, which the NVIC schedules:
+//!
+//!  - the hardware tasks: the receive interrupts of UART0, UART1, and UART2 (the CPU, the SSS, and
+//!    the radio), which drain their FIFOs into the [receive queues](crate::rx), and SysTick, which
+//!    counts the [clock](crate::systick); each then [spawns](spawn) the crypto task
+//!  - the crypto task, a software task dispatched on an interrupt the firmware has no other use
+//!    for, which [polls](Controller::poll) the controller for as long as it has work waiting:
+//!    registration, the verification and decryption of frames, and the encryption and sending of
+//!    messages
+//!  - idle, which sleeps until the next interrupt
+//!
+//! The hardware tasks preempt the crypto task, so that every UART is received while the controller
+//! registers with the SSS, decrypts a frame, or sends to the CPU, rather than only once it returns
+//! to its run loop.
+//!
+//! What the tasks share is held in [resources](Shared), each with a ceiling: the priority of the
+//! most urgent task which uses it. Locking a resource raises BASEPRI to its ceiling, so that no
+//! other task which uses it may run until it is unlocked, while those more urgent still may; as
+//! under RTIC's Stack Resource Policy, a lock is thus never contended, and cannot deadlock.
+//! Resources implement the [`Mutex`] of `rtic-core`, as RTIC's own do.
+//!
+//! The framework itself (`cortex-m-rtic`) is not used: it needs `cortex-m` 0.7, on which nothing
+//! else of the firmware is built, and its resources must be `'static`, while the controller borrows
+//! its buffers, clock, and board from the entrypoint. The crypto task instead borrows the
+//! controller from [`run`], which never returns.
+
+use core::cell::UnsafeCell;
+use core::mem;
+use core::ptr::{addr_of, addr_of_mut};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use cortex_m::asm;
+use cortex_m::peripheral::scb::SystemHandler;
+use cortex_m::peripheral::{NVIC, SCB};
+use cortex_m::register::{basepri, basepri_max};
+use lm3s6965::{Interrupt, NVIC_PRIO_BITS};
+use rtic_core::Mutex;
+
+use scewl::auth::Handler as AuthHandler;
+use scewl::board::Board;
+use scewl::controller::Controller;
+use scewl::crypto::Handler as CryptoHandler;
+use scewl::fatal::Fatal;
+use scewl::transport::Transport;
+
+/// The priority of the hardware tasks, the most urgent, which must not wait on the crypto task
+pub const RX: u8 = 2;
+
+/// The priority of the crypto task, which only idle is less urgent than
+pub const CRYPTO: u8 = 1;
+
+/// The interrupt on which the crypto task is dispatched, which has no peripheral of the firmware
+/// behind it
+const DISPATCHER: Interrupt = Interrupt::SSI0;
+
+/// The crypto task, which borrows the controller from [`run`]; set once, before its dispatcher is
+/// unmasked
+static mut TASK: Option<*mut (dyn FnMut() + 'static)> = None;
+
+/// Converts a priority, under which more urgent tasks are numbered higher, into that of the NVIC,
+/// under which they are numbered lower, in the upper bits of a byte
+const fn hardware(priority: u8) -> u8 {
+    ((1 << NVIC_PRIO_BITS) - priority) << (8 - NVIC_PRIO_BITS)
+}
+
+/// Assigns each task its priority; to be called at boot, before any of the tasks is unmasked
+pub fn init(mut nvic: NVIC, mut scb: SCB) {
+    // SAFETY: none of the tasks is unmasked yet, so no lock is held whose ceiling this would break
+    unsafe {
+        for uart in [Interrupt::UART0, Interrupt::UART1, Interrupt::UART2] {
+            nvic.set_priority(uart, hardware(RX));
+        }
+        scb.set_priority(SystemHandler::SysTick, hardware(RX));
+        nvic.set_priority(DISPATCHER, hardware(CRYPTO));
+    }
+}
+
+/// Runs the given closure with every task of at most the given priority masked
+pub fn lock<R>(ceiling: u8, f: impl FnOnce() -> R) -> R {
+    let previous = basepri::read();
+    basepri_max::write(hardware(ceiling));
+    let result = f();
+    // SAFETY: restores the mask to that before the lock, which any enclosing lock still holds
+    unsafe { basepri::write(previous) };
+    result
+}
+
+/// A resource shared between tasks, which is locked by raising BASEPRI to its ceiling
+pub struct Shared<T> {
+    /// The priority of the most urgent task which uses the resource
+    ceiling: u8,
+    /// Whether the resource is locked, which only a lock within a lock of the same resource finds
+    locked: AtomicBool,
+    /// The resource itself
+    value: UnsafeCell<T>,
+}
+
+// SAFETY: the value is only accessed within a lock, which masks every other task which uses it
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    /// A resource used by tasks of at most the given priority
+    pub const fn new(ceiling: u8, value: T) -> Self {
+        Shared {
+            ceiling,
+            locked: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+}
+
+impl<T> Mutex for &Shared<T> {
+    type T = T;
+
+    fn lock<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R {
+        lock(self.ceiling, || {
+            if self.locked.swap(true, Ordering::Relaxed) {
+                Fatal::Reentrant.panic();
+            }
+            // SAFETY: every other task which uses the resource is masked, and it is not already
+            // locked by this one, so this is the only reference to the value
+            let result = f(unsafe { &mut *self.value.get() });
+            self.locked.store(false, Ordering::Relaxed);
+            result
+        })
+    }
+}
+
+/// Spawns the crypto task, which runs once no more urgent task is; spawning it while it runs runs
+/// it again once it returns, so that no work is missed
+pub fn spawn() {
+    NVIC::pend(DISPATCHER);
+}
+
+/// Runs the crypto task; to be called from the dispatcher's interrupt handler only
+pub fn dispatch() {
+    // SAFETY: the task is only set before the dispatcher is unmasked, and the dispatcher does not
+    // preempt itself, so this is the only reference to the task
+    if let Some(task) = unsafe { *addr_of!(TASK) } {
+        // SAFETY: the task is borrowed from run, which never returns
+        unsafe { (*task)() };
+    }
+}
+
+/// Runs the controller in the crypto task, kicking the board's watchdog on each spawn, and sleeps
+/// in idle otherwise; never returns
+///
+/// The crypto task is spawned once at the outset, so that the controller serves anything received
+/// before its dispatcher was unmasked.
+pub fn run<A, C, T>(board: &dyn Board, controller: &mut Controller<'_, A, C, T>) -> !
+where
+    A: AuthHandler<C>,
+    C: CryptoHandler,
+    T: Transport,
+{
+    let mut task = || {
+        board.kick();
+        controller.poll();
+        while controller.pending() {
+            controller.poll();
+        }
+    };
+    let task: &mut dyn FnMut() = &mut task;
+
+    // SAFETY: the task outlives every run of the crypto task, as this never returns, and the
+    // dispatcher is not yet unmasked, so nothing else references the task
+    unsafe {
+        *addr_of_mut!(TASK) = Some(mem::transmute::<_, *mut (dyn FnMut() + 'static)>(task));
+        NVIC::unmask(DISPATCHER);
+    }
+    spawn();
+
+    loop {
+        asm::wfi();
+    }
+}
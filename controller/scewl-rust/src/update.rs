@@ -0,0 +1,286 @@
+//! The staging region of [firmware updates](scewl::update), and the application of the image
+//! staged in it
+//!
+//! build.rs reserves the region of `update.staging` bytes (by default, the larger half of the flash
+//! left by the other reservations) at the bottom of the flash reserved for runtime use. Its first page holds a header of the image's size and signature, and the image
+//! follows in the pages after. The [`Staging`] verifies a committed image against the deployment's
+//! public key, and marks the header committed; at the next boot, before anything else,
+//! [`swap_pending`] verifies the image again and copies it over the running firmware from RAM.
+
+use core::arch::global_asm;
+use core::hint::black_box;
+use core::ptr;
+
+use ed25519_compact::{PublicKey, Signature};
+
+use scewl::codec::Id;
+use scewl::update::{Ack, Frame, Status, Updater};
+use scewl::{glitch, info, warn};
+
+use crate::flash::{self, ERASED, PAGE};
+
+/// The words of a flash page
+const WORDS: usize = PAGE / 4;
+
+/// The first word of the header of a staged image
+const HEADER_MAGIC: u32 = u32::from_le_bytes(*b"SCUP");
+/// The word of the header which holds the size of the image
+const HEADER_SIZE: usize = 1;
+/// The first of the words of the header which hold the signature of the image
+const HEADER_SIGNATURE: usize = 2;
+/// The word of the header which holds the state of the image; erased while it is staged
+const HEADER_STATE: usize = HEADER_SIGNATURE + 64 / 4;
+
+/// The state of an image which was verified, and is to be swapped in at the next boot
+const COMMITTED: u32 = 0x5A5A_5A5A;
+
+/// The largest image which may be staged, i.e. the staging region less its header
+#[allow(clippy::cast_possible_truncation)] // a page is 1K
+const MAX_SIZE: u32 = crate::UPDATE_STAGING - PAGE as u32;
+
+extern "C" {
+    /// The start of the staging region, defined by the generated `memory.x`
+    static __sstaging: [u32; WORDS];
+
+    /// Copies the image at `src` of `len` bytes over the firmware at `dst`, programs the state
+    /// word at `state` to zero, and resets; runs from RAM, with interrupts disabled
+    fn scewl_update_swap(dst: usize, src: *const u32, len: usize, state: *const u32) -> !;
+}
+
+// the swap erases the very flash from which the firmware runs, so it is placed in `.data`,
+// which cortex-m-rt copies to RAM at boot, and drives the flash controller by itself
+global_asm!(
+    ".pushsection .data.scewl_update_swap,\"ax\",%progbits",
+    ".balign 4",
+    ".global scewl_update_swap",
+    ".type scewl_update_swap,%function",
+    ".thumb_func",
+    "scewl_update_swap:",
+    // r4: the flash controller, r5: the key and command, r6: the offset into the image
+    "    ldr r4, =0x400FD000",
+    "    movs r6, #0",
+    // erase every page of the firmware which the image covers
+    "2:  cmp r6, r2",
+    "    bhs 4f",
+    "    add r7, r0, r6",
+    "    str r7, [r4]",
+    "    ldr r5, =0xA4420002",
+    "    str r5, [r4, #8]",
+    "3:  ldr r5, [r4, #8]",
+    "    tst r5, #2",
+    "    bne 3b",
+    "    add r6, r6, #1024",
+    "    b 2b",
+    // program the image a word at a time
+    "4:  movs r6, #0",
+    "5:  cmp r6, r2",
+    "    bhs 7f",
+    "    add r7, r0, r6",
+    "    str r7, [r4]",
+    "    ldr r8, [r1, r6]",
+    "    str r8, [r4, #4]",
+    "    ldr r5, =0xA4420001",
+    "    str r5, [r4, #8]",
+    "6:  ldr r5, [r4, #8]",
+    "    tst r5, #1",
+    "    bne 6b",
+    "    adds r6, r6, #4",
+    "    b 5b",
+    // mark the image swapped, so that it is not applied again
+    "7:  str r3, [r4]",
+    "    movs r5, #0",
+    "    str r5, [r4, #4]",
+    "    ldr r5, =0xA4420001",
+    "    str r5, [r4, #8]",
+    "8:  ldr r5, [r4, #8]",
+    "    tst r5, #1",
+    "    bne 8b",
+    // reset through AIRCR.SYSRESETREQ
+    "    dsb",
+    "    ldr r4, =0xE000ED0C",
+    "    ldr r5, =0x05FA0004",
+    "    str r5, [r4]",
+    "    dsb",
+    "9:  b 9b",
+    ".ltorg",
+    ".size scewl_update_swap, . - scewl_update_swap",
+    ".popsection",
+);
+
+/// A pointer to the given word of the staging region
+fn word(index: usize) -> *const u32 {
+    ptr::addr_of!(__sstaging).cast::<u32>().wrapping_add(index)
+}
+
+/// Reads the given word of the staging region
+fn read(index: usize) -> u32 {
+    // SAFETY: the region is reserved for updates, and only ever read through volatile reads
+    // as the firmware programs it at runtime
+    unsafe { ptr::read_volatile(word(index)) }
+}
+
+/// Programs the given word of the staging region
+fn program(index: usize, value: u32) -> Result<(), Status> {
+    // SAFETY: as above, and updates are only ever handled from the main loop
+    unsafe { flash::program(word(index), value) }.map_err(|_| Status::Flash)
+}
+
+/// Determines whether a complete image is staged which bears the signature of the given key,
+/// returning its size if so
+fn verify(key: &PublicKey) -> Option<u32> {
+    let size = read(HEADER_SIZE);
+    if read(0) != HEADER_MAGIC || size == 0 || size > MAX_SIZE {
+        return None;
+    }
+
+    let mut signature = [0; 64];
+    for (index, chunk) in signature.chunks_exact_mut(4).enumerate() {
+        chunk.copy_from_slice(&read(HEADER_SIGNATURE + index).to_le_bytes());
+    }
+
+    // SAFETY: the image lies within the staging region, which is no longer programmed once
+    // the image is complete
+    let image = unsafe { core::slice::from_raw_parts(word(WORDS).cast::<u8>(), size as usize) };
+    glitch::check(|| key.verify(image, &Signature::new(signature)).is_ok()).then_some(size)
+}
+
+/// Swaps in the image staged by a committed update, should there be one which still bears the
+/// deployment's signature, then resets into it; to be called first thing at boot, before the
+/// [MPU](crate::mpu) makes the RAM (and so the swap) non-executable
+///
+/// An image which no longer verifies is marked swapped instead, so that it is never retried.
+pub fn swap_pending(key: &[u8; 32]) {
+    if read(0) != HEADER_MAGIC || read(HEADER_STATE) != COMMITTED {
+        return;
+    }
+
+    let Some(size) = verify(&PublicKey::new(*key)) else {
+        warn!("Discarding a committed update which no longer verifies");
+        let _ignored = program(HEADER_STATE, 0);
+        return;
+    };
+
+    info!("Applying a committed update of {} bytes", size);
+    cortex_m::interrupt::disable();
+    let swap: unsafe extern "C" fn(usize, *const u32, usize, *const u32) -> ! = scewl_update_swap;
+    // SAFETY: the image was verified, and lies outside of the firmware which it replaces; the
+    // swap runs from RAM with interrupts disabled, and never returns
+    unsafe { black_box(swap)(0, word(WORDS), size as usize, word(HEADER_STATE)) }
+}
+
+/// An update in progress
+#[derive(Copy, Clone)]
+struct Session {
+    /// The size of the image
+    size: u32,
+    /// The offset of the next chunk
+    next: u32,
+}
+
+/// The [updater](Updater) which stages each image in the staging region, as described in the
+/// [module documentation](self)
+pub struct Staging {
+    /// The SED from which updates are accepted
+    source: Id,
+    /// The key whose signature every image must bear
+    key: PublicKey,
+    /// The update in progress, if any
+    session: Option<Session>,
+}
+
+impl Updater for Staging {
+    fn source(&self) -> Id {
+        self.source
+    }
+
+    fn handle(&mut self, frame: Frame<'_>) -> Ack {
+        let res = match frame {
+            Frame::Begin { size, signature } => self.begin(size, signature),
+            Frame::Chunk { offset, data } => self.chunk(offset, data),
+            Frame::Commit => self.commit(),
+        };
+
+        Ack {
+            status: res.err().unwrap_or(Status::Ok),
+            next: self.session.map_or(0, |session| session.next),
+        }
+    }
+}
+
+impl Staging {
+    /// Accepts updates from the given source, signed by the given Ed25519 public key
+    pub fn new(source: Id, key: &[u8; 32]) -> Self {
+        Staging {
+            source,
+            key: PublicKey::new(*key),
+            session: None,
+        }
+    }
+
+    /// Erases the staging region for an image of the given size, and records its signature
+    fn begin(&mut self, size: u32, signature: &[u8; 64]) -> Result<(), Status> {
+        self.session = None;
+        if size == 0 || size > MAX_SIZE {
+            return Err(Status::Rejected);
+        }
+
+        info!("Beginning an update of {} bytes", size);
+        // the header page, and every page of the image
+        for page in 0..=(size as usize).div_ceil(PAGE) {
+            // SAFETY: as for program
+            unsafe { flash::erase(word(page * WORDS)) }.map_err(|_| Status::Flash)?;
+        }
+
+        program(0, HEADER_MAGIC)?;
+        program(HEADER_SIZE, size)?;
+        for (index, chunk) in signature.chunks_exact(4).enumerate() {
+            program(
+                HEADER_SIGNATURE + index,
+                u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+            )?;
+        }
+
+        self.session = Some(Session { size, next: 0 });
+        Ok(())
+    }
+
+    /// Programs the next chunk of the image; only the last may end part way through a word,
+    /// which is padded as erased flash
+    fn chunk(&mut self, offset: u32, data: &[u8]) -> Result<(), Status> {
+        let session = self.session.as_mut().ok_or(Status::Rejected)?;
+        #[allow(clippy::cast_possible_truncation)] // frames are far smaller than 4 GiB
+        let end = offset.saturating_add(data.len() as u32);
+        if offset != session.next || end > session.size {
+            return Err(Status::Rejected);
+        }
+        if !data.len().is_multiple_of(4) && end != session.size {
+            return Err(Status::Rejected);
+        }
+
+        let first = WORDS + offset as usize / 4;
+        for (index, chunk) in data.chunks(4).enumerate() {
+            let mut bytes = ERASED.to_le_bytes();
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            program(first + index, u32::from_le_bytes(bytes))?;
+        }
+
+        session.next = end;
+        Ok(())
+    }
+
+    /// Verifies the staged image, and commits it to be swapped in at the next boot
+    fn commit(&mut self) -> Result<(), Status> {
+        match self.session {
+            Some(session) if session.next == session.size => {}
+            _ => return Err(Status::Rejected),
+        }
+
+        if verify(&self.key).is_none() {
+            warn!("Refusing a staged update which does not bear the deployment's signature");
+            return Err(Status::Signature);
+        }
+
+        info!("Committing the staged update; it is applied at the next boot");
+        program(HEADER_STATE, COMMITTED)
+    }
+}
@@ -0,0 +1,123 @@
+//! A driver for the watchdog timer of the lm3s6965, which resets the controller should its run loop
+//! wedge, e.g. blocked on a UART which has gone dead
+//!
+//! The watchdog counts down from its load value at the core clock. When it first reaches zero, it
+//! raises its interrupt (which the firmware leaves masked) and counts down again; should it reach
+//! zero a second time before being [kicked](Watchdog::kick), it resets the core. The load value is
+//! thus half the timeout. The run loop kicks the watchdog on every pass, so the timeout need only
+//! outlast the longest pass, i.e. a (de)registration, whose reads from the SSS are each bounded by
+//! their own timeout.
+//!
+//! Once started, the watchdog cannot be stopped but by a reset, and its registers are locked
+//! between kicks, so that a stray write cannot disarm it.
+//!
+//! The system controller records the cause of each reset, which is [checked](reset_by_watchdog)
+//! at boot, so that the [banner](scewl::banner) tells the CPU when the watchdog has reset the
+//! controller.
+
+use core::time::Duration;
+
+use cortex_m::asm;
+use volatile_register::{RO, RW, WO};
+
+use crate::systick::CORE_CLOCK_HZ;
+
+/// The address of the watchdog timer
+const WATCHDOG: usize = 0x4000_0000;
+
+/// The address of `WDTLOCK`, which locks the watchdog's registers against writes
+const WDTLOCK: usize = WATCHDOG + 0xC00;
+
+/// `WDTLOCK`: the key which unlocks the watchdog's registers; any other value locks them
+const UNLOCK: u32 = 0x1ACC_E551;
+
+/// `WDTCTL`: starts the counter and its interrupt
+const WDTCTL_INTEN: u32 = 1;
+/// `WDTCTL`: resets the core should the counter time out with its interrupt still raised
+const WDTCTL_RESEN: u32 = 1 << 1;
+
+/// The address of `RESC`, the reset cause register of the system controller
+const RESC: usize = 0x400F_E05C;
+
+/// `RESC`: the watchdog reset the core
+const RESC_WDT: u32 = 1 << 3;
+
+/// The address of `RCGC0`, which gates the clocks of the system controller's peripherals
+const RCGC0: usize = 0x400F_E100;
+
+/// `RCGC0`: the clock of the watchdog
+const RCGC0_WDT: u32 = 1 << 3;
+
+/// The registers of the watchdog timer, save its lock
+#[repr(C)]
+struct Wdt {
+    /// Watchdog load register
+    load: RW<u32>,
+    /// Watchdog value register
+    _value: RO<u32>,
+    /// Watchdog control register
+    ctl: RW<u32>,
+    /// Watchdog interrupt clear register, a write to which reloads the counter
+    icr: WO<u32>,
+}
+
+/// Determines whether the previous boot was ended by the watchdog, clearing the record of it so
+/// that a later reset by other means is not mistaken for one; to be called once at boot
+pub fn reset_by_watchdog() -> bool {
+    // SAFETY: RESC is only ever accessed here, once at boot
+    let resc = unsafe { &*(RESC as *const RW<u32>) };
+    let fired = resc.read() & RESC_WDT != 0;
+    // SAFETY: clearing a cause only affects what the next boot reads
+    unsafe { resc.modify(|cause| cause & !RESC_WDT) };
+    fired
+}
+
+/// The running watchdog, which must be [kicked](Watchdog::kick) before each timeout passes
+pub struct Watchdog {
+    /// The watchdog timer's registers
+    wdt: &'static Wdt,
+}
+
+impl Watchdog {
+    /// Starts the watchdog with the given timeout, after which, unkicked, it resets the controller
+    pub fn start(timeout: Duration) -> Self {
+        // SAFETY: the system controller's clock gates are only modified at boot, and the watchdog's
+        // registers only through the single instance returned
+        let (rcgc0, wdt) = unsafe { (&*(RCGC0 as *const RW<u32>), &*(WATCHDOG as *const Wdt)) };
+
+        // SAFETY: enabling the watchdog's clock leaves the other peripherals' untouched
+        unsafe { rcgc0.modify(|gates| gates | RCGC0_WDT) };
+        // the watchdog's registers may only be accessed a few cycles after its clock is enabled
+        asm::delay(3);
+
+        #[allow(clippy::cast_possible_truncation)] // build.rs bounds the timeout to fit
+        let load = (timeout.as_millis() as u64 * u64::from(CORE_CLOCK_HZ / 1000) / 2) as u32;
+        let watchdog = Watchdog { wdt };
+        watchdog.unlocked(|wdt| {
+            // SAFETY: the load value is any count, and the control value starts the watchdog
+            unsafe {
+                wdt.load.write(load);
+                wdt.ctl.write(WDTCTL_INTEN | WDTCTL_RESEN);
+            }
+        });
+        watchdog
+    }
+
+    /// Reloads the counter, putting the reset off for another timeout; to be called on every pass
+    /// of the run loop
+    pub fn kick(&self) {
+        // SAFETY: any write to the interrupt clear register reloads the counter
+        self.unlocked(|wdt| unsafe { wdt.icr.write(1) });
+    }
+
+    /// Runs the given closure with the watchdog's registers unlocked, locking them afterwards
+    fn unlocked(&self, f: impl FnOnce(&Wdt)) {
+        // SAFETY: the lock is only written here, with the key and then to lock the registers again
+        let lock = unsafe { &*(WDTLOCK as *const WO<u32>) };
+        // SAFETY: as above
+        unsafe { lock.write(UNLOCK) };
+        f(self.wdt);
+        // SAFETY: as above
+        unsafe { lock.write(0) };
+    }
+}
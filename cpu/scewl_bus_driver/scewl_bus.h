@@ -49,8 +49,10 @@ typedef struct scewl_sss_msg_t {
 // SCEWL status codes
 enum scewl_status { SCEWL_ERR = -1, SCEWL_OK, SCEWL_ALREADY, SCEWL_NO_MSG };
 
-// registration/deregistration options
-enum scewl_sss_op_t { SCEWL_SSS_ALREADY = -1, SCEWL_SSS_REG, SCEWL_SSS_DEREG };
+// registration/deregistration options, and the rekey of a registered device
+enum scewl_sss_op_t {
+  SCEWL_SSS_ALREADY = -1, SCEWL_SSS_REG, SCEWL_SSS_DEREG, SCEWL_SSS_REKEY = 7
+};
 
 // reserved SCEWL IDs
 enum scewl_ids { SCEWL_BRDCST_ID, SCEWL_SSS_ID, SCEWL_FAA_ID };
@@ -86,6 +88,17 @@ int scewl_register();
 int scewl_deregister();
 
 
+/*
+ * scewl_rekey
+ *
+ * Obtains fresh keys from the SSS without deregistering
+ *
+ * Returns:
+ *   SCEWL_OK on success, SCEWL_ERR on failure or if not registered
+ */
+int scewl_rekey();
+
+
 /*
  * scewl_recv
  *
@@ -110,6 +110,34 @@ int scewl_deregister() {
 }
 
 
+int scewl_rekey() {
+  scewl_id_t dummy; // we don't care about src/tgt here
+  scewl_sss_msg_t msg;
+
+  msg.dev_id = SCEWL_ID;
+  msg.op = SCEWL_SSS_REKEY;
+
+  // send rekey
+  if (scewl_send(SCEWL_SSS_ID, sizeof(msg), (char *)&msg) == SCEWL_ERR) {
+    fprintf(logfp, "failed to rekey\n");
+    return SCEWL_ERR;
+  }
+
+  // receive response
+  if (scewl_recv((char *)&msg, &dummy, &dummy, sizeof(msg), 1) == SCEWL_ERR) {
+    fprintf(logfp, "failed to rekey\n");
+    return SCEWL_ERR;
+  }
+
+  // op should be REKEY on success
+  if (msg.op == SCEWL_SSS_REKEY) {
+    return SCEWL_OK;
+  }
+  fprintf(logfp, "not registered\n");
+  return SCEWL_ALREADY;
+}
+
+
 // read a full number of bytes, breaking if error
 // or if non-blocking and no bytes available
 int full_read(int sock, void *vbuf, int n) {
@@ -16,11 +16,20 @@
 # this script primarily focuses on verifying an SED as valid and distributing deployment wide keys.
 #
 # Registration:
-# 1) Given any SED with valid dev_id, establish path to SSS registration secret and scewl_secret
-# 2) Validate the scewl_secret that resides on the registering SED by comparing to the SSS's
-#    registration secret
-# 3) Distribute AES key (16B), HMAC key (64B) and Random seed (32B), given a match
+# 1) Given any SED requesting an operation, challenge it with a fresh nonce (32B), to which it
+#    answers with HMAC(scewl_secret, nonce || dev_id), so that its secret never crosses the wire
+#    (mirroring secure/challenge.rs)
+# 2) Validate the proof of the registering SED by computing it under the SSS's registration
+#    secret for its dev_id
+# 3) Distribute AES key (16B), HMAC key (64B), Random seed (32B), key epoch (4B) and the
+#    deployment's capabilities (1B), given a match, followed by the SED's signing seed and the
+#    public keys of every other SED should the deployment sign broadcasts. The keys and the seed are
+#    wrapped with AES-KW under a key derived from the SED's registration secret (mirroring
+#    secure/keywrap.rs), whose integrity check value (8B) follows the capabilities
 # 4) Send some error given a discrepancy
+# 5) Follow any response to an SED of the deployment with HMAC(key, nonce || response) (32B), under
+#    a key derived from its registration secret, so that it may tell this SSS from an impostor
+#    (mirroring secure/challenge.rs)
 #
 # Succesful execution of this procedure means a given SED is valid and may communicate with other
 # deployed SEDs while through use of the aformentioned keys. If an SED doesn't receive these keys
@@ -28,23 +37,496 @@
 #
 # Deregistration is handled by sending deregistration message and removing registration secret from
 # the SED (see dockerfiles/3_remove_sed.Dockerfile)
+#
+# Key rotation:
+# Once a second, the SSS checks the key epoch. Should it have been raised (after new keys were
+# written to /secrets/aes_key and /secrets/hmac_key), the SSS pushes the new keys and their epoch to
+# every registered SED, wrapped under a key derived from that SED's registration secret (mirroring
+# secure/rotation.rs), and each SED acknowledges with ROTATED, or ALREADY should it refuse them.
+#
+# Revocation:
+# Once an SED deregisters, the SSS adds it to a list of revoked SEDs, and removes it again should it
+# register once more. Whenever the list changes, the SSS raises its serial and pushes it to every
+# registered SED, tagged under a key derived from that SED's registration secret (mirroring
+# secure/revocation.rs), and pushes it as it stands to each SED after its registration. Each SED
+# drops frames from the revoked SEDs, and acknowledges with REVOKED, or ALREADY should it refuse it.
+#
+# Rekey:
+# A registered SED may request fresh keys without deregistering, exchanged exactly as a registration
+# is but for its op, REKEY, to which the SSS answers with the deployment's current keys and a fresh
+# seed, leaving the SED registered. Sending SIGUSR1 to the SSS prompts every registered SED to rekey
+# with a bare (dev_id, REKEY) message, which carries no keys and so is not authenticated.
+#
+# Post-quantum key establishment (experimental):
+# An SED built with the pq feature appends an ML-KEM-512 encapsulation key (800B) to its request.
+# Should the deployment enable CAP_PQ_KEM, the SSS encapsulates a shared secret to that key and
+# seals the response under it (mirroring secure/pq/mod.rs), so that the keys never cross the wire
+# as they are.
 
 
 import socket
 import select
 import struct
 import argparse
+import glob
+import hashlib
+import hmac
 import logging
 import os
 import secrets
+import signal
+import time
 from typing import NamedTuple
 
 
 SSS_IP = 'localhost'
 SSS_ID = 1
 
-# mirroring scewl enum at scewl.c:4
-ALREADY, REG, DEREG = -1, 0, 1
+# mirroring scewl enum at scewl.c:4, extended with the pushes of the keys of a new epoch and of the
+# revoked SEDs and their acknowledgements, the challenge of each request, the rekey of a registered
+# SED, its keepalive, and the attestation that a deregistering SED is erasing its keys, mirroring
+# codec/mod.rs
+(ALREADY, REG, DEREG, ROTATE, ROTATED, REVOKE, REVOKED, CHALLENGE, REKEY, KEEPALIVE,
+ ERASED) = range(-1, 10)
+
+# the cipher suite of the deployment, mirroring codec/secure.rs (1: AES-128-CBC with HMAC-SHA256,
+# 2: AES-128-CBC with AES-CMAC, for SEDs built with the cmac feature); a deployment without the
+# file is of suite 1
+SUITE_PATH = '/secrets/suite'
+SUITE = 1
+
+# the epoch of the deployment's keys, raised whenever they are replaced, which SEDs built with the
+# anti-rollback feature refuse to go back on; a deployment without the file is at epoch 0
+EPOCH_PATH = '/secrets/key_epoch'
+
+# how often, in seconds, the key epoch is checked for keys to push to the registered SEDs
+ROTATION_INTERVAL = 1
+
+# the info label from which the key wrapping the keys of a new epoch is expanded, mirroring
+# secure/rotation.rs
+ROTATE_LABEL = b'SCEWL rotate'
+
+# the info label from which the key tagging the list of revoked SEDs is expanded, mirroring
+# secure/revocation.rs
+REVOKE_LABEL = b'SCEWL revoke'
+
+# the capabilities enabled across the deployment, of which every SED must advertise all, mirroring
+# codec/secure.rs (bit 0: a CRC-16 after each header on the radio, bit 1: AES-128-GCM, bit 2:
+# signed broadcasts, bit 3: ephemeral keys, bit 4: AES-128-GCM-SIV, bit 5: ML-KEM-512 sealing of
+# the registration response, bit 6: allowlists); a deployment without the file enables none
+CAPS_PATH = '/secrets/caps'
+
+# the mission groups into which the SEDs are partitioned, one per line of comma-separated SCEWL ids;
+# should the deployment enable allowlists, each SED may only exchange frames with the other SEDs
+# of the groups which it is in
+GROUPS_PATH = '/secrets/groups'
+
+# the capability of signing broadcasts with a per-SED Ed25519 key, whose seed is generated for each
+# SED by dockerfiles/2b_create_sed_secrets.Dockerfile
+CAP_BROADCAST_SIGS = 1 << 2
+
+# the capabilities of protecting frames with AES-128-GCM or AES-128-GCM-SIV, whose crypto handlers
+# hold no HMAC key, so attest to erasing their keys under the AES key instead
+CAP_AES_GCM, CAP_AES_GCM_SIV = 1 << 1, 1 << 4
+
+# the capability of sealing the registration response under a secret encapsulated to the SED's
+# ML-KEM-512 key, which it appends to its request
+CAP_PQ_KEM = 1 << 5
+
+# the capability of restricting each SED to the other SEDs of its mission groups, whose ids follow
+# the secrets of the registration response
+CAP_ALLOWLIST = 1 << 6
+
+# the info label from which the key wrapping the keys and seed of the registration response is
+# expanded, mirroring secure/keywrap.rs
+WRAP_LABEL = b'SCEWL wrap'
+
+# the info label from which the key sealing the registration response is expanded, mirroring
+# secure/pq/mod.rs
+PQ_LABEL = b'SCEWL pq'
+
+# the length of a request, which is challenged, and of the proof answering the challenge, and of one
+# followed by an ML-KEM-512 encapsulation key
+REQUEST_LEN, PROOF_LEN, PQ_PROOF_LEN = 6, 36, 36 + 800
+
+# the info label from which the key tagging each response to a proof is expanded, mirroring
+# secure/challenge.rs
+RESPOND_LABEL = b'SCEWL respond'
+
+# the length of the keepalive which a registered SED sends periodically, bearing a nonce over which
+# the answer is tagged
+KEEPALIVE_LEN = 20
+
+# the info label from which the key tagging each answer to a keepalive is expanded, mirroring
+# secure/keepalive.rs
+KEEPALIVE_LABEL = b'SCEWL keepalive'
+
+# the length of the attestation with which a deregistering SED confirms that it is erasing its
+# keys, once its deregistration has been answered, and without which it is not deregistered
+ERASURE_LEN = 40
+
+# the info label from which the key of the attestation is expanded, mirroring secure/erasure.rs
+ERASURE_LABEL = b'SCEWL erased'
+
+# ML-KEM-512 (FIPS 203), of which the SSS only encapsulates, mirroring secure/pq/kem.rs
+KEM_Q, KEM_K, KEM_ETA1, KEM_ETA2, KEM_DU, KEM_DV = 3329, 2, 3, 2, 10, 4
+KEM_ZETAS = [pow(17, int(f'{i:07b}'[::-1], 2), KEM_Q) for i in range(128)]
+KEM_GAMMAS = [pow(17, 2 * int(f'{i:07b}'[::-1], 2) + 1, KEM_Q) for i in range(128)]
+
+# Ed25519 (RFC 8032), of which the SSS only derives the public keys of the SEDs from their seeds
+ED_P = 2 ** 255 - 19
+ED_D = -121665 * pow(121666, ED_P - 2, ED_P) % ED_P
+
+
+def ed_point_add(a, b):
+    '''Adds two points in extended coordinates'''
+    e = (a[1] - a[0]) * (b[1] - b[0]) % ED_P
+    h = (a[1] + a[0]) * (b[1] + b[0]) % ED_P
+    c = 2 * a[3] * b[3] * ED_D % ED_P
+    d = 2 * a[2] * b[2] % ED_P
+    e, f, g, h = h - e, d - c, d + c, h + e
+    return (e * f % ED_P, g * h % ED_P, f * g % ED_P, e * h % ED_P)
+
+
+def ed_point_mul(s, point):
+    '''Multiplies a point by a scalar'''
+    result = (0, 1, 1, 0)
+    while s > 0:
+        if s & 1:
+            result = ed_point_add(result, point)
+        point = ed_point_add(point, point)
+        s >>= 1
+    return result
+
+
+def ed_base():
+    '''The base point, whose x is recovered from its y of 4/5'''
+    y = 4 * pow(5, ED_P - 2, ED_P) % ED_P
+    x2 = (y * y - 1) * pow(ED_D * y * y + 1, ED_P - 2, ED_P)
+    x = pow(x2, (ED_P + 3) // 8, ED_P)
+    if (x * x - x2) % ED_P != 0:
+        x = x * pow(2, (ED_P - 1) // 4, ED_P) % ED_P
+    if x & 1:
+        x = ED_P - x
+    return (x, y, 1, x * y % ED_P)
+
+
+def ed_public_key(seed):
+    '''Derives the public key of the Ed25519 key of the given seed'''
+    a = int.from_bytes(hashlib.sha512(seed).digest()[:32], 'little')
+    a = (a & ((1 << 254) - 8)) | (1 << 254)
+    x, y, z, _ = ed_point_mul(a, ed_base())
+    z_inv = pow(z, ED_P - 2, ED_P)
+    x, y = x * z_inv % ED_P, y * z_inv % ED_P
+    return (y | ((x & 1) << 255)).to_bytes(32, 'little')
+
+
+def signing_keys(dev_id):
+    '''The seed of the SED's signing key followed by the id and public key of every other SED, or
+    None should the SED have no signing key'''
+    seed_path = f'/secrets/{dev_id}_sign_seed'
+    if not os.path.exists(seed_path):
+        return None
+    with open(seed_path, 'rb') as seed_file:
+        seed = seed_file.read(32)
+
+    peers = []
+    for path in glob.glob('/secrets/*_sign_seed'):
+        peer_id = int(os.path.basename(path)[:-len('_sign_seed')])
+        if peer_id != dev_id:
+            with open(path, 'rb') as peer_file:
+                peers.append((peer_id, ed_public_key(peer_file.read(32))))
+    peers.sort()
+
+    return seed + struct.pack('<B', len(peers)) + b''.join(
+        struct.pack('<H32s', peer_id, public) for peer_id, public in peers)
+
+
+def allowlist(dev_id):
+    '''The number of other SEDs which share a mission group with the SED, followed by their ids'''
+    allowed = set()
+    if os.path.exists(GROUPS_PATH):
+        with open(GROUPS_PATH) as groups_file:
+            for line in groups_file:
+                group = {int(peer_id) for peer_id in line.split(',') if peer_id.strip()}
+                if dev_id in group:
+                    allowed |= group
+    allowed = sorted(allowed - {dev_id})
+
+    return struct.pack('<B', len(allowed)) + b''.join(
+        struct.pack('<H', peer_id) for peer_id in allowed)
+
+
+def kem_ntt(f):
+    '''The NTT of a polynomial (algorithm 9 of FIPS 203)'''
+    f, i, length = list(f), 1, 128
+    while length >= 2:
+        for start in range(0, 256, 2 * length):
+            zeta, i = KEM_ZETAS[i], i + 1
+            for j in range(start, start + length):
+                t = zeta * f[j + length] % KEM_Q
+                f[j + length], f[j] = (f[j] - t) % KEM_Q, (f[j] + t) % KEM_Q
+        length //= 2
+    return f
+
+
+def kem_inv_ntt(f):
+    '''The inverse NTT of a polynomial (algorithm 10 of FIPS 203)'''
+    f, i, length = list(f), 127, 2
+    while length <= 128:
+        for start in range(0, 256, 2 * length):
+            zeta, i = KEM_ZETAS[i], i - 1
+            for j in range(start, start + length):
+                t, u = f[j], f[j + length]
+                f[j], f[j + length] = (t + u) % KEM_Q, zeta * (u - t) % KEM_Q
+        length *= 2
+    return [c * 3303 % KEM_Q for c in f]
+
+
+def kem_mul(f, g):
+    '''The product of two polynomials in the NTT domain (algorithm 11 of FIPS 203)'''
+    h = []
+    for i, gamma in enumerate(KEM_GAMMAS):
+        a0, a1, b0, b1 = f[2 * i], f[2 * i + 1], g[2 * i], g[2 * i + 1]
+        h += [(a0 * b0 + a1 * b1 * gamma) % KEM_Q, (a0 * b1 + a1 * b0) % KEM_Q]
+    return h
+
+
+def kem_add(f, g):
+    '''The sum of two polynomials'''
+    return [(a + b) % KEM_Q for a, b in zip(f, g)]
+
+
+def kem_sample_ntt(rho, row, col):
+    '''The entry of the matrix at the row and column (algorithm 7 of FIPS 203)'''
+    stream, f, pos = hashlib.shake_128(rho + bytes([col, row])).digest(168 * 8), [], 0
+    while len(f) < 256:
+        c = stream[pos:pos + 3]
+        pos += 3
+        for d in (c[0] | (c[1] & 0x0F) << 8, c[1] >> 4 | c[2] << 4):
+            if d < KEM_Q and len(f) < 256:
+                f.append(d)
+    return f
+
+
+def kem_sample_cbd(eta, seed, counter):
+    '''A polynomial of the centred binomial distribution (algorithms 8 and 2 of FIPS 203)'''
+    stream = hashlib.shake_256(seed + bytes([counter])).digest(64 * eta)
+    bits = [stream[i // 8] >> (i % 8) & 1 for i in range(512 * eta)]
+    x = [sum(bits[2 * i * eta:2 * i * eta + eta]) for i in range(256)]
+    y = [sum(bits[2 * i * eta + eta:2 * i * eta + 2 * eta]) for i in range(256)]
+    return [(x_i - y_i) % KEM_Q for x_i, y_i in zip(x, y)]
+
+
+def kem_encode(d, f):
+    '''The encoding of a polynomial of d-bit coefficients (algorithm 5 of FIPS 203)'''
+    return sum(c << (d * i) for i, c in enumerate(f)).to_bytes(32 * d, 'little')
+
+
+def kem_decode(d, data):
+    '''The polynomial of d-bit coefficients encoded (algorithm 6 of FIPS 203)'''
+    acc = int.from_bytes(data, 'little')
+    return [(acc >> (d * i) & ((1 << d) - 1)) % KEM_Q for i in range(256)]
+
+
+def kem_compress(d, f):
+    '''Each coefficient compressed to d bits'''
+    return [((c << d) + KEM_Q // 2) // KEM_Q % (1 << d) for c in f]
+
+
+def kem_decompress(d, f):
+    '''Each coefficient decompressed from d bits'''
+    return [(c * KEM_Q + (1 << (d - 1))) >> d for c in f]
+
+
+def kem_well_formed(ek):
+    '''Whether the encapsulation key is of the right length and its coefficients are reduced (the
+    checks of section 7.2 of FIPS 203)'''
+    if len(ek) != 384 * KEM_K + 32:
+        return False
+    t = [kem_decode(12, ek[384 * i:384 * (i + 1)]) for i in range(KEM_K)]
+    return b''.join(kem_encode(12, t_i) for t_i in t) == ek[:384 * KEM_K]
+
+
+def kem_encapsulate(ek):
+    '''A shared secret and its ciphertext encapsulated to the well-formed encapsulation key
+    (algorithms 17 and 14 of FIPS 203)'''
+    t = [kem_decode(12, ek[384 * i:384 * (i + 1)]) for i in range(KEM_K)]
+    rho = ek[384 * KEM_K:]
+
+    m = secrets.token_bytes(32)
+    g = hashlib.sha3_512(m + hashlib.sha3_256(ek).digest()).digest()
+    shared, r = g[:32], g[32:]
+
+    y = [kem_ntt(kem_sample_cbd(KEM_ETA1, r, i)) for i in range(KEM_K)]
+    ciphertext = b''
+    for i in range(KEM_K):
+        u = [0] * 256
+        for j in range(KEM_K):
+            u = kem_add(u, kem_mul(kem_sample_ntt(rho, j, i), y[j]))
+        u = kem_add(kem_inv_ntt(u), kem_sample_cbd(KEM_ETA2, r, KEM_K + i))
+        ciphertext += kem_encode(KEM_DU, kem_compress(KEM_DU, u))
+
+    v = [0] * 256
+    for j in range(KEM_K):
+        v = kem_add(v, kem_mul(t[j], y[j]))
+    v = kem_add(kem_inv_ntt(v), kem_sample_cbd(KEM_ETA2, r, 2 * KEM_K))
+    v = kem_add(v, kem_decompress(1, kem_decode(1, m)))
+    ciphertext += kem_encode(KEM_DV, kem_compress(KEM_DV, v))
+    return shared, ciphertext
+
+
+def seal_response(dev_id, ek, body):
+    '''The registration response sealed under a secret encapsulated to the SED's encapsulation key,
+    mirroring secure/pq/mod.rs'''
+    shared, ciphertext = kem_encapsulate(ek)
+
+    okm = hkdf_sha256(struct.pack('<H', dev_id), shared, PQ_LABEL, 144 + 32)
+    head, secrets_, tail, keys = body[:4], body[4:116], body[116:129], body[129:]
+    secrets_ = bytes(s ^ p for s, p in zip(secrets_, okm[:112]))
+    if keys:
+        keys = bytes(s ^ p for s, p in zip(keys[:32], okm[112:144])) + keys[32:]
+    response = head + secrets_ + tail
+    tag = hmac.new(okm[144:], response + ciphertext + keys, hashlib.sha256).digest()
+    return response + ciphertext + tag + keys
+
+
+def read_epoch():
+    '''The epoch of the deployment's keys, which is 0 should it not be recorded'''
+    if not os.path.exists(EPOCH_PATH):
+        return 0
+    with open(EPOCH_PATH, 'rb') as epoch_file:
+        epoch, = struct.unpack('<I', epoch_file.read(4))
+    return epoch
+
+
+def hkdf_sha256(salt, ikm, info, length):
+    '''HKDF-SHA256 (RFC 5869)'''
+    prk = hmac.new(salt, ikm, hashlib.sha256).digest()
+    okm, block = b'', b''
+    for counter in range(1, (length + 31) // 32 + 1):
+        block = hmac.new(prk, block + info + bytes([counter]), hashlib.sha256).digest()
+        okm += block
+    return okm[:length]
+
+
+def aes_sbox():
+    '''The AES S-box, computed from the multiplicative inverses of GF(2^8) rather than tabulated'''
+    sbox = [0x63] * 256
+    p = q = 1
+    while True:
+        # p runs over the multiplicative group by powers of 3, and q over their inverses
+        p ^= ((p << 1) ^ (0x1B if p & 0x80 else 0)) & 0xFF
+        q ^= q << 1
+        q ^= q << 2
+        q ^= q << 4
+        q &= 0xFF
+        if q & 0x80:
+            q ^= 0x09
+        affine = q
+        for shift in range(1, 5):
+            affine ^= ((q << shift) | (q >> (8 - shift))) & 0xFF
+        sbox[p] = affine ^ 0x63
+        if p == 1:
+            return sbox
+
+
+AES_SBOX = aes_sbox()
+
+
+def aes_xtime(byte):
+    '''The byte multiplied by x in GF(2^8)'''
+    return ((byte << 1) ^ (0x1B if byte & 0x80 else 0)) & 0xFF
+
+
+def aes128_encrypt(key, block):
+    '''Encrypts the 16-byte block under the 16-byte key with AES-128 (FIPS 197)'''
+    words = [list(key[i:i + 4]) for i in range(0, 16, 4)]
+    rcon = 1
+    for i in range(4, 44):
+        word = list(words[i - 1])
+        if i % 4 == 0:
+            word = [AES_SBOX[b] for b in word[1:] + word[:1]]
+            word[0] ^= rcon
+            rcon = aes_xtime(rcon)
+        words.append([a ^ b for a, b in zip(words[i - 4], word)])
+
+    state = [b ^ k for b, k in zip(block, sum(words[:4], []))]
+    for rnd in range(1, 11):
+        state = [AES_SBOX[b] for b in state]
+        # the state is column-major, so row r of column c is at 4c + r
+        state = [state[(4 * (c + r) + r) % 16] for c in range(4) for r in range(4)]
+        if rnd < 10:
+            mixed = []
+            for c in range(4):
+                col = state[4 * c:4 * c + 4]
+                total = col[0] ^ col[1] ^ col[2] ^ col[3]
+                mixed += [col[r] ^ total ^ aes_xtime(col[r] ^ col[(r + 1) % 4]) for r in range(4)]
+            state = mixed
+        state = [b ^ k for b, k in zip(state, sum(words[4 * rnd:4 * rnd + 4], []))]
+    return bytes(state)
+
+
+def aes_key_wrap(kek, data):
+    '''The integrity check value and the data wrapped under the key encryption key with AES-KW
+    (RFC 3394)'''
+    check = b'\xA6' * 8
+    blocks = [data[i:i + 8] for i in range(0, len(data), 8)]
+    for step in range(6 * len(blocks)):
+        block = aes128_encrypt(kek, check + blocks[step % len(blocks)])
+        check = (int.from_bytes(block[:8], 'big') ^ (step + 1)).to_bytes(8, 'big')
+        blocks[step % len(blocks)] = block[8:]
+    return check, b''.join(blocks)
+
+
+def wrap_secrets(dev_id, secret, aes_key, seed, hmac_key):
+    '''The keys and seed of the registration response wrapped for the SED of the given registration
+    secret, and their integrity check value, mirroring secure/keywrap.rs'''
+    kek = hkdf_sha256(struct.pack('<H', dev_id), secret, WRAP_LABEL, 16)
+    check, wrapped = aes_key_wrap(kek, aes_key + seed + hmac_key)
+    return wrapped[:16], wrapped[16:48], wrapped[48:], check
+
+
+def wrap_keys(dev_id, secret, epoch, aes_key, hmac_key):
+    '''The push of the keys of the epoch to the SED of the given registration secret, mirroring
+    secure/rotation.rs'''
+    okm = hkdf_sha256(struct.pack('<I', epoch), secret, ROTATE_LABEL, 80 + 32)
+    wrapped = bytes(key ^ pad for key, pad in zip(aes_key + hmac_key, okm[:80]))
+    tag = hmac.new(okm[80:], struct.pack('<HI', dev_id, epoch) + wrapped, hashlib.sha256).digest()
+    return struct.pack('<HhI80s32s', dev_id, ROTATE, epoch, wrapped, tag)
+
+
+def tag_revocation(dev_id, secret, serial, revoked):
+    '''The push of the list of revoked SEDs of the serial to the SED of the given registration
+    secret, mirroring secure/revocation.rs'''
+    key = hkdf_sha256(struct.pack('<H', dev_id), secret, REVOKE_LABEL, 32)
+    listed = struct.pack('<IB', serial, len(revoked)) + b''.join(
+        struct.pack('<H', revoked_id) for revoked_id in sorted(revoked))
+    tag = hmac.new(key, struct.pack('<H', dev_id) + listed, hashlib.sha256).digest()
+    return struct.pack('<Hh', dev_id, REVOKE) + listed + tag
+
+
+def tag_response(dev_id, secret, nonce, body):
+    '''The tag of the response to the SED of the given registration secret, as sent, over the nonce
+    of the challenge which it answers, mirroring secure/challenge.rs'''
+    key = hkdf_sha256(struct.pack('<H', dev_id), secret, RESPOND_LABEL, 32)
+    return hmac.new(key, nonce + body, hashlib.sha256).digest()
+
+
+def erasure_tag(dev_id, key, nonce):
+    '''The tag with which the SED attests to erasing the given deployment key, over the nonce of
+    the challenge of its deregistration, mirroring secure/erasure.rs'''
+    key = hkdf_sha256(struct.pack('<H', dev_id), key, ERASURE_LABEL, 32)
+    return hmac.new(key, nonce + struct.pack('<H', dev_id), hashlib.sha256).digest()
+
+
+def answer_keepalive(dev_id, secret, nonce, registered):
+    '''The answer to the keepalive of the nonce from the SED of the given registration secret, of
+    whether it is still held registered, mirroring secure/keepalive.rs'''
+    op = KEEPALIVE if registered else DEREG
+    key = hkdf_sha256(struct.pack('<H', dev_id), secret, KEEPALIVE_LABEL, 32)
+    tag = hmac.new(key, nonce + struct.pack('<Hh', dev_id, op), hashlib.sha256).digest()
+    return struct.pack('<Hh', dev_id, op) + tag
 
 logging.basicConfig(level=logging.INFO)
 
@@ -64,28 +546,165 @@ class SSS:
         self.sock.bind(sockf)
         self.sock.listen(10)
         self.devs = {}
+        self.epoch = read_epoch()
+        self.rotation_checked = time.monotonic()
+        # the SEDs deregistered and not registered since, and the serial of their list
+        self.revoked = set()
+        self.revocation_serial = 0
+        # the request challenged on each socket, and the nonce it was challenged with
+        self.challenges = {}
+        # whether every registered SED is to be prompted to rekey, as requested by SIGUSR1
+        self.rekey_requested = False
+        # when each SED last kept alive, by the monotonic clock
+        self.last_seen = {}
+        # the deregistration answered on each socket, pending the SED's attestation that it is
+        # erasing its keys: the SED, the nonce of its challenge, and its secret
+        self.erasures = {}
+        signal.signal(signal.SIGUSR1, self.request_rekey)
     
     @staticmethod
     def sock_ready(sock, op='r'):
         rready, wready, _ = select.select([sock], [sock], [], 0)
         return rready if op == 'r' else wready
 
-    def handle_transaction(self, csock: socket.SocketType):
-        logging.debug('handling transaction')
+    @staticmethod
+    def recv_exactly(csock, length):
         data = b''
-        while len(data) < 76:
-            recvd = csock.recv(76 - len(data))
+        while len(data) < length:
+            recvd = csock.recv(length - len(data))
             data += recvd
 
             # check for closed connection
             if not recvd:
                 raise ConnectionResetError
+        return data
+
+    def rotate(self):
+        '''Pushes the keys of a new epoch to every registered SED, should the epoch have been
+        raised'''
+        epoch = read_epoch()
+        if epoch <= self.epoch:
+            return
+        self.epoch = epoch
+
+        with open("/secrets/aes_key", "rb") as aes_file:
+            aes_key = aes_file.read(16)
+        with open("/secrets/hmac_key", "rb") as hmac_file:
+            hmac_key = hmac_file.read(64)
+        logging.info(f':Rotating to epoch {epoch}')
+
+        for dev in self.devs.values():
+            if dev.status != REG or not dev.csock:
+                continue
+            with open(f'/secrets/{dev.id}_secret', 'rb') as secret_file:
+                secret = secret_file.read(64)
+            body = wrap_keys(dev.id, secret, epoch, aes_key, hmac_key)
+            try:
+                dev.csock.send(struct.pack('<2sHHH', b'SC', dev.id, SSS_ID, len(body)) + body)
+            except OSError:
+                logging.info(f'{dev.id}:push failed')
+
+    def revoke(self, dev_ids):
+        '''Pushes the list of revoked SEDs to each of the given SEDs which is registered'''
+        for dev_id in dev_ids:
+            dev = self.devs.get(dev_id)
+            if not dev or dev.status != REG or not dev.csock:
+                continue
+            with open(f'/secrets/{dev.id}_secret', 'rb') as secret_file:
+                secret = secret_file.read(64)
+            body = tag_revocation(dev.id, secret, self.revocation_serial, self.revoked)
+            try:
+                dev.csock.send(struct.pack('<2sHHH', b'SC', dev.id, SSS_ID, len(body)) + body)
+            except OSError:
+                logging.info(f'{dev.id}:push failed')
+
+    def request_rekey(self, _signum, _frame):
+        '''Requests that every registered SED be prompted to rekey, once the SSS is between
+        transactions'''
+        self.rekey_requested = True
+
+    def prompt_rekey(self):
+        '''Prompts every registered SED to rekey'''
+        logging.info(':Prompting registered SEDs to rekey')
+        for dev in self.devs.values():
+            if dev.status != REG or not dev.csock:
+                continue
+            body = struct.pack('<Hh', dev.id, REKEY)
+            try:
+                dev.csock.send(struct.pack('<2sHHH', b'SC', dev.id, SSS_ID, len(body)) + body)
+            except OSError:
+                logging.info(f'{dev.id}:prompt failed')
+
+    def handle_transaction(self, csock: socket.SocketType):
+        logging.debug('handling transaction')
+        _, _, _, length = struct.unpack('<2sHHH', self.recv_exactly(csock, 8))
+        data = self.recv_exactly(csock, length)
         logging.debug(f'Received buffer: {repr(data)}')
 
-        # Unpack message received from a given SED
-        _, _, _, _, dev_id, op, scewl_secret = struct.unpack('<HHHHHH64s', data)
+        # An SED acknowledging the keys of a new epoch or the revoked SEDs pushed to it, in between
+        # transactions
+        if length == 4:
+            dev_id, op = struct.unpack('<Hh', data)
+            taken_up = {ROTATED: 'Rotated', REVOKED: 'Revoked'}.get(op, 'refused push')
+            logging.info(f'{dev_id}:{taken_up}')
+            return
+
+        # A registered SED keeping alive, which is answered with whether it is still held registered,
+        # as it may not be should the SSS have restarted or lost its connection
+        if length == KEEPALIVE_LEN:
+            dev_id, op, nonce = struct.unpack('<Hh16s', data)
+            secret_path = f'/secrets/{dev_id}_secret'
+            if op != KEEPALIVE or not os.path.exists(secret_path):
+                raise ConnectionResetError
+            with open(secret_path, "rb") as secret_file:
+                secret = secret_file.read(64)
+            self.last_seen[dev_id] = time.monotonic()
+            dev = self.devs.get(dev_id)
+            registered = dev is not None and dev.status == REG and dev.csock is csock
+            if not registered:
+                logging.info(f'{dev_id}:Keepalive while not registered')
+            body = answer_keepalive(dev_id, secret, nonce, registered)
+            csock.send(struct.pack('<2sHHH', b'SC', dev_id, SSS_ID, len(body)) + body)
+            return
+
+        # A deregistering SED attesting that it is erasing its keys, which is only then
+        # deregistered
+        if length == ERASURE_LEN:
+            self.handle_erasure(csock, data)
+            return
+
+        # An SED requesting an operation, which is challenged with a fresh nonce before it is
+        # answered
+        if length == REQUEST_LEN:
+            dev_id, op, suite, caps = struct.unpack('<HhBB', data)
+            # a deregistration not attested to before the next request is abandoned
+            self.erasures.pop(csock, None)
+            nonce = secrets.token_bytes(32)
+            self.challenges[csock] = (dev_id, op, suite, caps, nonce)
+            body = struct.pack('<Hh32s', dev_id, CHALLENGE, nonce)
+            csock.send(struct.pack('<2sHHH', b'SC', dev_id, SSS_ID, len(body)) + body)
+            return
+        if length not in (PROOF_LEN, PQ_PROOF_LEN) or csock not in self.challenges:
+            raise ConnectionResetError
+
+        # Unpack the proof answering the challenge of the request, and the encapsulation key which
+        # may follow it
+        proof_id, proof_op, proof = struct.unpack('<Hh32s', data[:PROOF_LEN])
+        dev_id, op, suite, caps, nonce = self.challenges.pop(csock)
+        ek = data[PROOF_LEN:]
+
+        deployment_caps = 0
+        if os.path.exists(CAPS_PATH):
+            with open(CAPS_PATH, "rb") as caps_file:
+                deployment_caps = caps_file.read(1)[0]
+
+        deployment_suite = SUITE
+        if os.path.exists(SUITE_PATH):
+            with open(SUITE_PATH, "rb") as suite_file:
+                deployment_suite = suite_file.read(1)[0]
 
         '''Message responses are constructed below'''
+        serial = self.revocation_serial
         
         # Read in corresponding scewl secret
         secret_path = f'/secrets/{dev_id}_secret'
@@ -94,13 +713,53 @@ class SSS:
                 # Read in the registration secret for verification
                 checked_secret = secret_file.read(64)
 
-                # Scewl_secret mismatch, registration key provided by SED is invalid. Log this event
-                # and back ALREADY resp_op into the response. Without deployment keys, this SED is
-                # considered invalid for registration.
-                if checked_secret != scewl_secret:
-                    logging.info(f'{dev_id}:expected: {checked_secret}, found: {scewl_secret}')
+                # Proof not of the registration secret over the nonce, or not of the request
+                # challenged, so the SED does not hold the secret. Log this event and back ALREADY
+                # resp_op into the response. Without deployment keys, this SED is considered invalid
+                # for registration.
+                expected = hmac.new(checked_secret, nonce + struct.pack('<H', dev_id),
+                                    hashlib.sha256).digest()
+                if (proof_id, proof_op) != (dev_id, op) or not hmac.compare_digest(proof, expected):
+                    resp_op = ALREADY
+                    logging.info(f'{dev_id}:bad proof')
+                    body = struct.pack('<Hh', dev_id, resp_op)
+
+                # SED built with a cipher suite other than the deployment's, which could not
+                # communicate with the rest of the deployment. Log this event.
+                elif suite != deployment_suite:
+                    resp_op = ALREADY
+                    logging.info(f'{dev_id}:suite mismatch: '
+                                 f'expected {deployment_suite}, found {suite}')
+                    body = struct.pack('<Hh', dev_id, resp_op)
+
+                # SED lacking a capability which the deployment enables, e.g. the header CRC,
+                # which could not communicate with the rest of the deployment. Log this event.
+                elif caps & deployment_caps != deployment_caps:
+                    resp_op = ALREADY
+                    logging.info(f'{dev_id}:capability mismatch: '
+                                 f'expected {deployment_caps}, found {caps}')
+                    body = struct.pack('<Hh', dev_id, resp_op)
+
+                # SED without a signing key in a deployment which signs broadcasts, whose
+                # broadcasts no other SED could verify. Log this event.
+                elif deployment_caps & CAP_BROADCAST_SIGS and signing_keys(dev_id) is None:
                     resp_op = ALREADY
-                    logging.info(f'{dev_id}:key mismatch')
+                    logging.info(f'{dev_id}:no signing key')
+                    body = struct.pack('<Hh', dev_id, resp_op)
+
+                # SED without a well-formed encapsulation key in a deployment which seals the
+                # registration response under one. Log this event.
+                elif (op in (REG, REKEY) and deployment_caps & CAP_PQ_KEM
+                      and not kem_well_formed(ek)):
+                    resp_op = ALREADY
+                    logging.info(f'{dev_id}:no encapsulation key')
+                    body = struct.pack('<Hh', dev_id, resp_op)
+
+                # Rekey of an SED which is not registered, and so has no keys to refresh. Log this
+                # event.
+                elif op == REKEY and (dev_id not in self.devs or self.devs[dev_id].status != REG):
+                    resp_op = ALREADY
+                    logging.info(f'{dev_id}:not registered')
                     body = struct.pack('<Hh', dev_id, resp_op)
 
                 # Requesting repeat transaction in the case that an SED state already reflects the
@@ -115,24 +774,50 @@ class SSS:
                 # AES key: 16 bytes
                 # HMAC key: 64 bytes
                 # Random seed: 32bytes
-                elif op == REG:
+                # Key epoch: 4 bytes
+                # Capabilities: 1 byte
+                # Integrity check value of the keys and seed, wrapped with AES-KW: 8 bytes
+                # Allowlist, should the deployment enable it: 1 byte + 2 per allowed SED
+                # Signing keys, should the deployment sign broadcasts: 33 bytes + 34 per other SED
+                # A rekey is answered alike, and leaves the SED registered.
+                elif op in (REG, REKEY):
                     self.devs[dev_id] = Device(dev_id, REG, csock)
-                    resp_op = REG
+                    resp_op = op
                     with open("/secrets/aes_key", "rb") as aes_file:
                         aes_key = aes_file.read(16)
                     with open("/secrets/hmac_key", "rb") as hmac_file:
                         hmac_key = hmac_file.read(64)
-                    logging.info(f'{dev_id}:Registered')
+                    logging.info(f'{dev_id}:{"Registered" if op == REG else "Rekeyed"}')
+                    if dev_id in self.revoked:
+                        self.revoked.discard(dev_id)
+                        self.revocation_serial += 1
+                    epoch = read_epoch()
                     seed = secrets.token_bytes(32)
-                    body = struct.pack('<Hh16s32s64s', dev_id, resp_op, aes_key, seed, hmac_key)
+                    aes_key, seed, hmac_key, check = wrap_secrets(dev_id, checked_secret, aes_key,
+                                                                  seed, hmac_key)
+                    body = struct.pack('<Hh16s32s64sIB8s', dev_id, resp_op, aes_key, seed,
+                                       hmac_key, epoch, deployment_caps, check)
+                    if deployment_caps & CAP_ALLOWLIST:
+                        body += allowlist(dev_id)
+                    if deployment_caps & CAP_BROADCAST_SIGS:
+                        body += signing_keys(dev_id)
+                    # Sealed under the SED's encapsulation key, should the deployment enable it:
+                    # 768 bytes of ciphertext and a 32-byte tag follow the capabilities
+                    if deployment_caps & CAP_PQ_KEM:
+                        body = seal_response(dev_id, ek, body)
 
-                # Record deregistration for an SED which was verified previously to register and
-                # hasn't already been deregistered.
+                # Answer the deregistration of an SED which was verified previously to register and
+                # hasn't already been deregistered, which is only recorded once the SED attests
+                # that it is erasing its keys.
                 else:
-                    self.devs[dev_id] = Device(dev_id, DEREG, csock)
+                    self.erasures[csock] = (dev_id, nonce, checked_secret)
                     resp_op = DEREG
-                    logging.info(f'{dev_id}:Deregistered')
+                    logging.info(f'{dev_id}:Deregistering')
                     body = struct.pack('<Hh', dev_id, resp_op)
+
+                # Tag the response over the nonce under a key derived from the registration
+                # secret, so that the SED knows it to be from this SSS: 32 bytes
+                body += tag_response(dev_id, checked_secret, nonce, body)
         # Record some error from reading in the SEDs {dev_id}_secrets folder. This may happen if
         # an SED is attempted to register, which should not be included on the deployment as specified
         # by the {dev_id}_secrets folders generated in dockerfiles/2b_create_sed_secrets.Dockerfile
@@ -146,6 +831,54 @@ class SSS:
         logging.debug(f'Sending response {repr(data)}')
         csock.send(resp)
 
+        # Push a changed list of revoked SEDs to every registered SED, and any list to an SED which
+        # has just registered, after its response
+        if self.revocation_serial != serial:
+            self.revoke(list(self.devs))
+        elif resp_op == REG:
+            self.revoke([dev_id])
+
+    def handle_erasure(self, csock, data):
+        '''Deregisters the SED whose deregistration was answered on the socket, should it attest to
+        erasing the deployment's current keys, and acknowledges the attestation with ERASED, or
+        refuses it with ALREADY and leaves the SED registered'''
+        dev_id, op, epoch, tag = struct.unpack('<HhI32s', data)
+        pending = self.erasures.pop(csock, None)
+        if op != ERASED or pending is None or pending[0] != dev_id:
+            raise ConnectionResetError
+        _, nonce, secret = pending
+
+        deployment_caps = 0
+        if os.path.exists(CAPS_PATH):
+            with open(CAPS_PATH, "rb") as caps_file:
+                deployment_caps = caps_file.read(1)[0]
+        if deployment_caps & (CAP_AES_GCM | CAP_AES_GCM_SIV):
+            with open("/secrets/aes_key", "rb") as aes_file:
+                key = aes_file.read(16)
+        else:
+            with open("/secrets/hmac_key", "rb") as hmac_file:
+                key = hmac_file.read(64)
+
+        serial = self.revocation_serial
+        if epoch == read_epoch() and hmac.compare_digest(tag, erasure_tag(dev_id, key, nonce)):
+            self.devs[dev_id] = Device(dev_id, DEREG, csock)
+            resp_op = ERASED
+            logging.info(f'{dev_id}:Deregistered')
+            if dev_id not in self.revoked:
+                self.revoked.add(dev_id)
+                self.revocation_serial += 1
+        else:
+            resp_op = ALREADY
+            logging.info(f'{dev_id}:bad erasure')
+
+        body = struct.pack('<Hh', dev_id, resp_op)
+        body += tag_response(dev_id, secret, nonce, body)
+        csock.send(struct.pack('<2sHHH', b'SC', dev_id, SSS_ID, len(body)) + body)
+
+        # Push the changed list of revoked SEDs to every registered SED
+        if self.revocation_serial != serial:
+            self.revoke(list(self.devs))
+
     # The following methods reflect the provided insecure implementation and keep the SSS active
     # to received registration and deregistration messages before responding
     def start(self):
@@ -153,6 +886,16 @@ class SSS:
 
         # serve forever
         while True:
+            # check for keys of a new epoch to push
+            if time.monotonic() - self.rotation_checked >= ROTATION_INTERVAL:
+                self.rotation_checked = time.monotonic()
+                self.rotate()
+
+            # prompt every registered SED to rekey, should it have been requested
+            if self.rekey_requested:
+                self.rekey_requested = False
+                self.prompt_rekey()
+
             # check for new client
             if self.sock_ready(self.sock):
                 csock, _ = self.sock.accept()
@@ -165,22 +908,29 @@ class SSS:
                 try:
                     if self.sock_ready(csock):
                         self.handle_transaction(csock)
-                        unattributed_socks.remove(csock)
+                        # a socket is attributed once it has registered an SED, rather than once it
+                        # has been challenged
+                        if any(dev.csock is csock for dev in self.devs.values()):
+                            unattributed_socks.remove(csock)
                         break
                 except (ConnectionResetError, BrokenPipeError):
                     logging.info(':Connection closed')
                     unattributed_socks.remove(csock)
+                    self.challenges.pop(csock, None)
+                    self.erasures.pop(csock, None)
                     csock.close()
                     break
             
             # check pool of attributed sockets first
             old_ids = []
-            for dev in self.devs.values():
+            for dev in list(self.devs.values()):
                 if dev.csock and self.sock_ready(dev.csock):
                     try:
                         self.handle_transaction(dev.csock)
                     except (ConnectionResetError, BrokenPipeError):
                         logging.info(f'{dev.id}:Connection closed')
+                        self.challenges.pop(dev.csock, None)
+                        self.erasures.pop(dev.csock, None)
                         dev.csock.close()
                         old_ids.append(dev.id)
             
@@ -0,0 +1,69 @@
+# 2021 Collegiate eCTF
+# SED personalisation
+#
+# Writes the provisioning record of a SED (see controller/scewl-rust/src/provision.rs) into the
+# flash image of a controller built with the `provisioned` feature, so that one generic image may
+# be personalised for each SED. The record occupies the last flash page, which is left erased (all
+# 0xFF) by the firmware; the image is padded with erased flash up to that page as necessary.
+#
+#   python3 provision.py kernel sed_10.bin --id 10 --secret /secrets/10_secret
+#
+# Without a secret, the record carries only the id, and the secret compiled into the image is used.
+
+import argparse
+import logging
+import struct
+import zlib
+
+MAGIC = b'PROV'
+VERSION = 1
+HAS_SECRET = 1
+PAGE = 1024
+FLASH = 256 * 1024
+
+logging.basicConfig(level=logging.INFO, format='%(levelname)s - %(message)s')
+
+
+def record(dev_id, secret=None):
+    flags = HAS_SECRET if secret is not None else 0
+    body = MAGIC + struct.pack('<BBH', VERSION, flags, dev_id) + (secret or bytes(64))
+    return body + struct.pack('<I', zlib.crc32(body))
+
+
+def parse_args():
+    parser = argparse.ArgumentParser()
+    parser.add_argument('image', help='Path to the generic flash image (i.e. the objcopied kernel)')
+    parser.add_argument('out', help='Path to write the personalised image to')
+    parser.add_argument('--id', type=int, required=True, help='The SCEWL_ID of the SED')
+    parser.add_argument('--secret', help='Path to the 64 byte registration secret of the SED')
+    parser.add_argument('--flash', type=lambda n: int(n, 0), default=FLASH,
+                        help='The size of the flash, as in the deployment configuration')
+    return parser.parse_args()
+
+
+def main():
+    args = parse_args()
+
+    secret = None
+    if args.secret:
+        with open(args.secret, 'rb') as f:
+            secret = f.read()
+        if len(secret) != 64:
+            raise ValueError(f'the secret {args.secret} is {len(secret)} bytes, not 64')
+
+    with open(args.image, 'rb') as f:
+        image = f.read()
+    offset = args.flash - PAGE
+    if len(image) > offset:
+        raise ValueError(f'the image overlaps the provisioning page at {offset:#x}; '
+                         'was it built with the `provisioned` feature?')
+
+    page = record(args.id, secret).ljust(PAGE, b'\xff')
+    with open(args.out, 'wb') as f:
+        f.write(image.ljust(offset, b'\xff') + page)
+    logging.info(f'provisioned {args.out} as SED {args.id} '
+                 f'({"with" if secret else "without"} a secret)')
+
+
+if __name__ == '__main__':
+    main()
@@ -0,0 +1,182 @@
+# 2021 Collegiate eCTF
+# Scripted on-target test driver
+#
+# Drives a controller built with the `scripted` feature through a regression scenario by standing
+# in for the SSS on its serial line. Test directives (see controller/scewl-rust/src/script.rs) are
+# sent as SSS frames and their responses checked; any other traffic from the controller (i.e.
+# (de)registration) is forwarded to the real SSS, if one is given.
+#
+# Scenarios are plain text, one step per line; blank lines and lines starting with '#' are
+# ignored:
+#
+#   inject cpu <tgt> <hex body>        handle a frame as though sent by the CPU
+#   inject rad <src> <tgt> <hex body>  handle a frame as though received on the radio
+#   fault drop-cpu|drop-rad|fail-decrypt
+#   query                              print the controller's state
+#   crash                              print the panic which ended the previous boot, if any
+#   expect ok|error                    check the status of the previous step
+#   expect registered|unregistered     check the state reported by the previous query
+#   wait <seconds>                     give the CPU time to (de)register or send
+
+import argparse
+import logging
+import os
+import select
+import socket
+import struct
+import sys
+import time
+
+SSS_ID = 1
+MAGIC = b'TEST'
+OPS = {'inject': 0, 'query': 1, 'fault': 2, 'crash': 3}
+INTFS = {'cpu': 0, 'rad': 2}
+FAULTS = {'drop-cpu': 0, 'drop-rad': 1, 'fail-decrypt': 2}
+
+logging.basicConfig(level=logging.INFO, format='%(levelname)s - %(message)s')
+
+
+def recv_exact(sock, n):
+    data = b''
+    while len(data) < n:
+        recvd = sock.recv(n - len(data))
+        if not recvd:
+            raise ConnectionResetError
+        data += recvd
+    return data
+
+
+def recv_frame(sock):
+    hdr = recv_exact(sock, 8)
+    magic, tgt, src, ln = struct.unpack('<2sHHH', hdr)
+    if magic != b'SC':
+        raise ValueError(f'bad header magic: {magic!r}')
+    return hdr, tgt, src, recv_exact(sock, ln)
+
+
+class ScriptedController:
+    def __init__(self, sockf, sss=None):
+        try:
+            os.unlink(sockf)
+        except OSError:
+            if os.path.exists(sockf):
+                raise
+
+        server = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+        server.bind(sockf)
+        server.listen(1)
+        logging.info(f'waiting for the controller on {sockf}')
+        self.sock, _ = server.accept()
+
+        self.sss = None
+        if sss:
+            self.sss = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+            self.sss.connect(sss)
+
+        self.status = None
+        self.state = None
+
+    def forward(self, hdr, body):
+        if self.sss is None:
+            logging.warning(f'dropping SSS traffic without an SSS: {body!r}')
+            return
+        self.sss.send(hdr + body)
+        hdr, _, _, body = recv_frame(self.sss)
+        self.sock.send(hdr + body)
+
+    def pump(self, timeout=0):
+        # forwards (de)registration traffic until the controller is quiet
+        while select.select([self.sock], [], [], timeout)[0]:
+            hdr, _, _, body = recv_frame(self.sock)
+            if body.startswith(MAGIC):
+                return body
+            self.forward(hdr, body)
+        return None
+
+    def directive(self, op, args=b''):
+        body = MAGIC + bytes([OPS[op]]) + args
+        self.sock.send(struct.pack('<2sHHH', b'SC', 0, SSS_ID, len(body)) + body)
+
+        resp = None
+        while resp is None:
+            resp = self.pump(timeout=None)
+        if resp[len(MAGIC)] != OPS[op]:
+            raise ValueError(f'response to the wrong directive: {resp!r}')
+
+        self.status = resp[len(MAGIC) + 1] == 0
+        return resp[len(MAGIC) + 2:]
+
+    def inject(self, intf, src, tgt, body):
+        self.directive('inject', struct.pack('<BHH', INTFS[intf], src, tgt) + body)
+
+    def fault(self, fault):
+        self.directive('fault', bytes([FAULTS[fault]]))
+
+    def query(self):
+        dev_id, registered, faults = struct.unpack('<HBB', self.directive('query'))
+        self.state = {'id': dev_id, 'registered': bool(registered), 'faults': faults}
+        return self.state
+
+    def crash(self):
+        resp = self.directive('crash')
+        if not self.status:
+            return None
+        line, column, file_len = struct.unpack('<IIB', resp[:9])
+        file, message = resp[9:9 + file_len], resp[9 + file_len:]
+        return f'{file.decode()}:{line}:{column}: {message.decode(errors="replace")}'
+
+    def step(self, line):
+        words = line.split()
+        cmd, args = words[0], words[1:]
+
+        if cmd == 'inject' and args[0] == 'cpu':
+            self.inject('cpu', 0, int(args[1]), bytes.fromhex(''.join(args[2:])))
+        elif cmd == 'inject' and args[0] == 'rad':
+            self.inject('rad', int(args[1]), int(args[2]), bytes.fromhex(''.join(args[3:])))
+        elif cmd == 'fault':
+            self.fault(args[0])
+        elif cmd == 'query':
+            logging.info(f'state: {self.query()}')
+        elif cmd == 'crash':
+            logging.info(f'previous boot ended in: {self.crash()}')
+        elif cmd == 'wait':
+            deadline = time.time() + float(args[0])
+            while time.time() < deadline:
+                self.pump(timeout=deadline - time.time())
+        elif cmd == 'expect' and args[0] in ('ok', 'error'):
+            return self.status == (args[0] == 'ok')
+        elif cmd == 'expect' and args[0] in ('registered', 'unregistered'):
+            return self.state['registered'] == (args[0] == 'registered')
+        else:
+            raise ValueError(f'unknown step: {line}')
+        return True
+
+
+def parse_args():
+    parser = argparse.ArgumentParser()
+    parser.add_argument('sock', help='Path to bind the controller\'s SSS socket to')
+    parser.add_argument('scenario', help='Path to the scenario to run')
+    parser.add_argument('--sss', help='Path to the socket of the real SSS, for (de)registration')
+    return parser.parse_args()
+
+
+def main():
+    args = parse_args()
+    controller = ScriptedController(args.sock, args.sss)
+
+    failures = 0
+    with open(args.scenario) as scenario:
+        for n, line in enumerate(scenario, 1):
+            line = line.strip()
+            if not line or line.startswith('#'):
+                continue
+            if not controller.step(line):
+                logging.error(f'{args.scenario}:{n}: failed: {line}')
+                failures += 1
+
+    logging.info(f'{failures} failures')
+    sys.exit(1 if failures else 0)
+
+
+if __name__ == '__main__':
+    main()